@@ -0,0 +1,153 @@
+//! Scanner and hot query benchmarks on a synthetic library.
+//!
+//! Run with `cargo bench --features fixtures`. `ROPDS_BENCH_BOOKS` sets the
+//! library size (default 2000); the same size always scans the same books.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use tokio::runtime::Runtime;
+
+use ropds::config::Config;
+use ropds::db::DbPool;
+use ropds::db::queries::books::{self, BookSort, RecentKind, SearchFilter};
+use ropds::db::queries::{MatchMode, authors};
+use ropds::fixtures::{FixtureOptions, generate};
+
+fn bench_books() -> usize {
+    std::env::var("ROPDS_BENCH_BOOKS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2000)
+}
+
+fn config(library: &Path, work: &Path, db_name: &str) -> Config {
+    let toml = format!(
+        r#"
+[server]
+base_url = "http://localhost:8081"
+session_secret = "bench-secret"
+
+[library]
+root_path = {library:?}
+inpx_enable = true
+
+[covers]
+covers_path = {covers:?}
+
+[database]
+url = "sqlite://{db}?mode=rwc"
+
+[opds]
+
+[scanner]
+"#,
+        covers = work.join("covers"),
+        db = work.join(db_name).display(),
+    );
+    toml::from_str(&toml).expect("bench config should parse")
+}
+
+async fn fresh_pool(config: &Config) -> DbPool {
+    let url = config.database.url.trim_start_matches("sqlite://");
+    let path = url.split('?').next().unwrap_or(url);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{path}{suffix}"));
+    }
+    ropds::db::create_pool(&config.database)
+        .await
+        .expect("bench database")
+}
+
+fn scan_benches(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let library = tempfile::tempdir().unwrap();
+    let work = tempfile::tempdir().unwrap();
+    let opts = FixtureOptions {
+        books: bench_books(),
+        ..Default::default()
+    };
+    generate(library.path(), &opts).expect("fixtures");
+
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+
+    let full = config(library.path(), work.path(), "full.db");
+    // Each iteration scans into a new database; only the scan is timed.
+    group.bench_function("full", |b| {
+        b.to_async(&rt).iter_custom(|iters| {
+            let full = &full;
+            async move {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    let pool = fresh_pool(full).await;
+                    let start = Instant::now();
+                    ropds::scanner::run_scan(&pool, full).await.unwrap();
+                    total += start.elapsed();
+                }
+                total
+            }
+        })
+    });
+
+    let mut unchanged = config(library.path(), work.path(), "rescan.db");
+    unchanged.scanner.skip_unchanged = true;
+    let pool = rt.block_on(fresh_pool(&unchanged));
+    rt.block_on(ropds::scanner::run_scan(&pool, &unchanged))
+        .unwrap();
+    group.bench_function("rescan_unchanged", |b| {
+        b.to_async(&rt)
+            .iter(|| ropds::scanner::run_scan(&pool, &unchanged))
+    });
+    group.finish();
+}
+
+fn query_benches(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let library = tempfile::tempdir().unwrap();
+    let work = tempfile::tempdir().unwrap();
+    let opts = FixtureOptions {
+        books: bench_books(),
+        ..Default::default()
+    };
+    generate(library.path(), &opts).expect("fixtures");
+    let config = config(library.path(), work.path(), "queries.db");
+    let pool = rt.block_on(fresh_pool(&config));
+    rt.block_on(ropds::scanner::run_scan(&pool, &config))
+        .unwrap();
+    let filter = SearchFilter::default();
+
+    let mut group = c.benchmark_group("queries");
+    group.bench_function("search_title_contains", |b| {
+        b.to_async(&rt).iter(|| {
+            books::search_by_title(
+                &pool,
+                "river",
+                MatchMode::Contains,
+                30,
+                0,
+                true,
+                BookSort::Title,
+                false,
+                &filter,
+            )
+        })
+    });
+    group.bench_function("search_author_begins", |b| {
+        b.to_async(&rt)
+            .iter(|| authors::search_by_name(&pool, "mil", MatchMode::Begins, 30, 0, false))
+    });
+    group.bench_function("author_prefix_groups", |b| {
+        b.to_async(&rt)
+            .iter(|| authors::get_name_prefix_groups(&pool, 0, ""))
+    });
+    group.bench_function("recent_added", |b| {
+        b.to_async(&rt)
+            .iter(|| books::get_recent(&pool, RecentKind::Added, 30, 0, true))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, scan_benches, query_benches);
+criterion_main!(benches);
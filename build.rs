@@ -286,6 +286,10 @@ fn write_embedded_static_metadata(static_root: &Path, metadata_file: &Path) -> i
     generated.push_str("        .binary_search_by_key(&path, |(candidate, _)| *candidate)\n");
     generated.push_str("        .ok()\n");
     generated.push_str("        .map(|index| EMBEDDED_STATIC_ETAGS[index].1)\n");
+    generated.push_str("}\n\n");
+    generated
+        .push_str("pub(crate) fn fingerprint_for_path(path: &str) -> Option<&'static str> {\n");
+    generated.push_str("    etag_for_path(path).map(|etag| &etag[1..=super::FINGERPRINT_LEN])\n");
     generated.push_str("}\n");
 
     fs::write(metadata_file, generated)
@@ -0,0 +1,3 @@
+-- Add per-user web theme preference ('light', 'dark', 'auto'; empty = not set)
+
+ALTER TABLE users ADD COLUMN theme VARCHAR(16) NOT NULL DEFAULT '';
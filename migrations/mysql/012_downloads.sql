@@ -0,0 +1,12 @@
+-- Download history: one row per book download (user_id is NULL for anonymous downloads)
+
+CREATE TABLE IF NOT EXISTS downloads (
+    id            BIGINT      PRIMARY KEY AUTO_INCREMENT,
+    book_id       BIGINT      NOT NULL,
+    user_id       BIGINT      NULL,
+    downloaded_at VARCHAR(64) NOT NULL DEFAULT (CURRENT_TIMESTAMP),
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+CREATE INDEX idx_downloads_time ON downloads(downloaded_at, book_id);
+CREATE INDEX idx_downloads_book ON downloads(book_id);
@@ -0,0 +1,11 @@
+-- Precomputed "readers also liked" scores between books, rebuilt by a background job
+
+CREATE TABLE IF NOT EXISTS recommendations (
+    book_id     BIGINT NOT NULL,
+    rec_book_id BIGINT NOT NULL,
+    score       BIGINT NOT NULL,
+    PRIMARY KEY (book_id, rec_book_id),
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE,
+    FOREIGN KEY (rec_book_id) REFERENCES books(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+CREATE INDEX idx_recommendations_score ON recommendations(book_id, score);
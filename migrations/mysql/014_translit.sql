@@ -0,0 +1,5 @@
+-- Transliteration search keys (see src/translit.rs).
+-- Filled at startup for existing rows when search.translit is enabled.
+
+ALTER TABLE books ADD COLUMN translit_title VARCHAR(1024) NOT NULL DEFAULT '';
+ALTER TABLE authors ADD COLUMN translit_full_name VARCHAR(1024) NOT NULL DEFAULT '';
@@ -0,0 +1,8 @@
+-- Audiobook details: running time and narrators of m4b files and mp3 folders
+
+CREATE TABLE IF NOT EXISTS book_audio (
+    book_id  BIGINT       PRIMARY KEY,
+    duration BIGINT       NOT NULL DEFAULT 0,
+    narrator VARCHAR(512) NOT NULL DEFAULT '',
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
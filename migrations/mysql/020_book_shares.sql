@@ -0,0 +1,16 @@
+-- Share links that let anyone holding the URL download one book. The link
+-- itself is signed; rows hold its nonce so links can be listed and revoked.
+-- expires_at is in Unix seconds.
+
+CREATE TABLE IF NOT EXISTS book_shares (
+    id         BIGINT      PRIMARY KEY AUTO_INCREMENT,
+    nonce      VARCHAR(64) NOT NULL UNIQUE,
+    book_id    BIGINT      NOT NULL,
+    user_id    BIGINT      NOT NULL,
+    expires_at BIGINT      NOT NULL,
+    revoked    INTEGER     NOT NULL DEFAULT 0,
+    created_at VARCHAR(64) NOT NULL DEFAULT (CURRENT_TIMESTAMP),
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+CREATE INDEX idx_book_shares_expires ON book_shares(expires_at);
@@ -0,0 +1,14 @@
+-- Guest links to a user's bookshelf: anyone holding the URL can browse the
+-- shelf and download its books. The link itself is signed; rows hold its
+-- nonce so links can be listed and revoked. expires_at is in Unix seconds.
+
+CREATE TABLE IF NOT EXISTS shelf_shares (
+    id         BIGINT      PRIMARY KEY AUTO_INCREMENT,
+    nonce      VARCHAR(64) NOT NULL UNIQUE,
+    user_id    BIGINT      NOT NULL,
+    expires_at BIGINT      NOT NULL,
+    revoked    INTEGER     NOT NULL DEFAULT 0,
+    created_at VARCHAR(64) NOT NULL DEFAULT (CURRENT_TIMESTAMP),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+CREATE INDEX idx_shelf_shares_expires ON shelf_shares(expires_at);
@@ -0,0 +1,15 @@
+-- Client User-Agents recorded when stats.user_agents is enabled: one row per
+-- user and OPDS client (the "devices seen" list in the admin panel), plus the
+-- client of each download. Agents are cut to 255 characters.
+
+CREATE TABLE IF NOT EXISTS user_devices (
+    user_id    BIGINT       NOT NULL,
+    user_agent VARCHAR(255) NOT NULL,
+    first_seen VARCHAR(64)  NOT NULL,
+    last_seen  VARCHAR(64)  NOT NULL,
+    requests   BIGINT       NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, user_agent),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+
+ALTER TABLE downloads ADD COLUMN user_agent VARCHAR(255) NULL;
@@ -0,0 +1,8 @@
+-- When the scanner (or an admin) last replaced a book's file with a changed
+-- one. Empty for books whose file never changed after registration.
+
+ALTER TABLE books ADD COLUMN last_modified VARCHAR(64) NOT NULL DEFAULT '';
+
+-- "Recently updated" feed: WHERE avail > 0 AND last_modified <> ''
+-- ORDER BY last_modified DESC, id DESC.
+CREATE INDEX idx_books_avail_last_modified ON books(avail, last_modified DESC, id DESC);
@@ -0,0 +1,6 @@
+-- First lines of the book text, extracted by the scanner and shown as a
+-- preview in the web UI and OPDS entries. Empty when none was found.
+-- VARCHAR (not TEXT, see 001_initial.sql); the scanner caps the preview at
+-- 3000 characters to stay within the InnoDB row size limit.
+
+ALTER TABLE books ADD COLUMN preview_text VARCHAR(3000) NOT NULL DEFAULT '';
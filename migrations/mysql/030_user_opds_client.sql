@@ -0,0 +1,4 @@
+-- Per-user OPDS client profile: empty = detect from the User-Agent,
+-- 'none' = no profile, anything else names a profile.
+
+ALTER TABLE users ADD COLUMN opds_client VARCHAR(64) NOT NULL DEFAULT '';
@@ -0,0 +1,15 @@
+-- Full-text index of book bodies (see src/fulltext.rs): one row per distinct
+-- term of a book. Filled after scans when search.fulltext is enabled;
+-- content_indexed marks books whose text was read (or could not be).
+-- Terms compare bytewise so the primary key serves prefix range scans.
+
+ALTER TABLE books ADD COLUMN content_indexed INT NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS book_terms (
+    term    VARCHAR(32) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL,
+    book_id BIGINT NOT NULL,
+    freq    INT NOT NULL,
+    PRIMARY KEY (term, book_id),
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+CREATE INDEX idx_book_terms_book ON book_terms(book_id);
@@ -0,0 +1,6 @@
+-- Length of a book read during scans: words of the body text (FB2, EPUB,
+-- TXT) and pages (PDF, DjVu); 0 when unknown. Used for reading time
+-- estimates and the OPDS 2.0 numberOfPages.
+
+ALTER TABLE books ADD COLUMN word_count INT NOT NULL DEFAULT 0;
+ALTER TABLE books ADD COLUMN page_count INT NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Dominant colours of a book's stored cover (see src/covers.rs), as up to
+-- three space-separated "#rrggbb" values, most common first; empty when not
+-- computed. Filled at scan time and by a startup backfill for older covers.
+
+ALTER TABLE books ADD COLUMN cover_palette VARCHAR(32) NOT NULL DEFAULT '';
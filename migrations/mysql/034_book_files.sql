@@ -0,0 +1,18 @@
+-- Identical files registered as one book (scanner.link_identical): the
+-- SHA-256 of a book's file, and further locations of the same content.
+-- A book's own path/filename stays its main copy.
+
+ALTER TABLE books ADD COLUMN file_hash VARCHAR(64) NOT NULL DEFAULT '';
+CREATE INDEX idx_books_size_hash ON books(size, file_hash);
+
+CREATE TABLE IF NOT EXISTS book_files (
+    id       BIGINT        PRIMARY KEY AUTO_INCREMENT,
+    book_id  BIGINT        NOT NULL,
+    path     VARCHAR(2048) NOT NULL,
+    filename VARCHAR(255)  NOT NULL,
+    format   VARCHAR(64)   NOT NULL,
+    size     BIGINT        NOT NULL,
+    FOREIGN KEY (book_id) REFERENCES books(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+CREATE INDEX idx_book_files_book ON book_files(book_id);
+CREATE INDEX idx_book_files_path_file ON book_files(path(255), filename(255));
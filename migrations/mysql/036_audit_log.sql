@@ -0,0 +1,12 @@
+-- Administrator actions shown in the admin panel (impersonation start and
+-- end). Names are copied so entries outlive deleted accounts.
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id         BIGINT       PRIMARY KEY AUTO_INCREMENT,
+    created_at VARCHAR(64)  NOT NULL,
+    admin      VARCHAR(255) NOT NULL,
+    action     VARCHAR(64)  NOT NULL,
+    subject    VARCHAR(255) NOT NULL DEFAULT '',
+    ip         VARCHAR(64)  NOT NULL DEFAULT ''
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+CREATE INDEX idx_audit_log_created ON audit_log(created_at);
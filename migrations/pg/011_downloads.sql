@@ -0,0 +1,10 @@
+-- Download history: one row per book download (user_id is NULL for anonymous downloads)
+
+CREATE TABLE IF NOT EXISTS downloads (
+    id            BIGSERIAL PRIMARY KEY,
+    book_id       BIGINT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    user_id       BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    downloaded_at TEXT   NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_downloads_time ON downloads(downloaded_at, book_id);
+CREATE INDEX idx_downloads_book ON downloads(book_id);
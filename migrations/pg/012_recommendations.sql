@@ -0,0 +1,9 @@
+-- Precomputed "readers also liked" scores between books, rebuilt by a background job
+
+CREATE TABLE IF NOT EXISTS recommendations (
+    book_id     BIGINT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    rec_book_id BIGINT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    score       BIGINT NOT NULL,
+    PRIMARY KEY (book_id, rec_book_id)
+);
+CREATE INDEX idx_recommendations_score ON recommendations(book_id, score);
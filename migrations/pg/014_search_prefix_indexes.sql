@@ -0,0 +1,7 @@
+-- Bytewise indexes for the prefix range scans of search suggestions
+-- (src/db/queries/suggest.rs). The default-collation indexes from 001 cannot
+-- serve `COLLATE "C"` comparisons.
+
+CREATE INDEX idx_books_search_c   ON books(search_title COLLATE "C");
+CREATE INDEX idx_authors_search_c ON authors(search_full_name COLLATE "C");
+CREATE INDEX idx_series_search_c  ON series(search_ser COLLATE "C");
@@ -0,0 +1,4 @@
+-- INPX collection release from version.info; unchanged archives are skipped
+-- by version instead of size when both sides have one.
+
+ALTER TABLE catalogs ADD COLUMN cat_version TEXT NOT NULL DEFAULT '';
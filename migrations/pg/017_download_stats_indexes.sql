@@ -0,0 +1,4 @@
+-- Covering index for the per-user download leaderboard on the admin stats page
+-- (per-book leaderboards and daily counts use idx_downloads_time).
+
+CREATE INDEX idx_downloads_time_user ON downloads(downloaded_at, user_id);
@@ -0,0 +1,10 @@
+-- Library scan lock shared by every instance using this database: a single
+-- row whose holder is set while a scan runs. expires_at (Unix seconds) is
+-- pushed forward by the scanning instance; an expired lock may be taken over.
+
+CREATE TABLE IF NOT EXISTS scan_lock (
+    id         BIGINT PRIMARY KEY,
+    holder     TEXT   NOT NULL DEFAULT '',
+    expires_at BIGINT NOT NULL DEFAULT 0
+);
+INSERT INTO scan_lock (id) VALUES (1);
@@ -0,0 +1,5 @@
+-- Set when an admin renamed a catalog or moved it under another parent;
+-- rescans then keep its cat_name and parent_id instead of restoring the
+-- values derived from the library.
+
+ALTER TABLE catalogs ADD COLUMN cat_custom INTEGER NOT NULL DEFAULT 0;
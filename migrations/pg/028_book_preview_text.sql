@@ -0,0 +1,4 @@
+-- First lines of the book text, extracted by the scanner and shown as a
+-- preview in the web UI and OPDS entries. Empty when none was found.
+
+ALTER TABLE books ADD COLUMN preview_text TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,14 @@
+-- Full-text index of book bodies (see src/fulltext.rs): one row per distinct
+-- term of a book. Filled after scans when search.fulltext is enabled;
+-- content_indexed marks books whose text was read (or could not be).
+-- Terms compare bytewise so the primary key serves prefix range scans.
+
+ALTER TABLE books ADD COLUMN content_indexed INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS book_terms (
+    term    TEXT COLLATE "C" NOT NULL,
+    book_id BIGINT NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    freq    INTEGER NOT NULL,
+    PRIMARY KEY (term, book_id)
+);
+CREATE INDEX idx_book_terms_book ON book_terms(book_id);
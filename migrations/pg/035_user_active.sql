@@ -0,0 +1,4 @@
+-- Admin-controlled account switch: disabled users (0) can neither log in
+-- nor keep using existing web sessions or OPDS Basic Auth.
+
+ALTER TABLE users ADD COLUMN is_active INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,6 @@
+-- Downloads of a book over all time, kept up to date as downloads are
+-- recorded, so the "most downloaded" sort reads a column instead of
+-- counting the download history of every listed book.
+
+ALTER TABLE books ADD COLUMN download_count INTEGER NOT NULL DEFAULT 0;
+UPDATE books SET download_count = (SELECT COUNT(*) FROM downloads d WHERE d.book_id = books.id);
@@ -0,0 +1,7 @@
+-- Audiobook details: running time and narrators of m4b files and mp3 folders
+
+CREATE TABLE IF NOT EXISTS book_audio (
+    book_id  INTEGER PRIMARY KEY REFERENCES books(id) ON DELETE CASCADE,
+    duration INTEGER NOT NULL DEFAULT 0,
+    narrator TEXT    NOT NULL DEFAULT ''
+);
@@ -0,0 +1,4 @@
+-- Library change stamp: bumped whenever scans, uploads or admin edits change
+-- the catalog; OPDS feed ETags are derived from it.
+
+INSERT INTO counters (name, value) VALUES ('library_version', 0);
@@ -0,0 +1,14 @@
+-- Share links that let anyone holding the URL download one book. The link
+-- itself is signed; rows hold its nonce so links can be listed and revoked.
+-- expires_at is in Unix seconds.
+
+CREATE TABLE IF NOT EXISTS book_shares (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    nonce      TEXT    NOT NULL UNIQUE,
+    book_id    INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    user_id    INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at INTEGER NOT NULL,
+    revoked    INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT    NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_book_shares_expires ON book_shares(expires_at);
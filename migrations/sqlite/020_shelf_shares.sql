@@ -0,0 +1,13 @@
+-- Guest links to a user's bookshelf: anyone holding the URL can browse the
+-- shelf and download its books. The link itself is signed; rows hold its
+-- nonce so links can be listed and revoked. expires_at is in Unix seconds.
+
+CREATE TABLE IF NOT EXISTS shelf_shares (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    nonce      TEXT    NOT NULL UNIQUE,
+    user_id    INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at INTEGER NOT NULL,
+    revoked    INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT    NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_shelf_shares_expires ON shelf_shares(expires_at);
@@ -0,0 +1,5 @@
+-- Set when an admin hid a catalog: its folder is skipped by the scanner and
+-- the catalog is left out of web and OPDS browsing. Hidden catalogs are kept
+-- when empty so the flag survives rescans.
+
+ALTER TABLE catalogs ADD COLUMN cat_hidden INTEGER NOT NULL DEFAULT 0;
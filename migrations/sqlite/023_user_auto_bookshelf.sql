@@ -0,0 +1,4 @@
+-- Per-user opt-out of opds.auto_bookshelf: 0 keeps downloaded books off the
+-- user's bookshelf.
+
+ALTER TABLE users ADD COLUMN auto_bookshelf INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,14 @@
+-- Client User-Agents recorded when stats.user_agents is enabled: one row per
+-- user and OPDS client (the "devices seen" list in the admin panel), plus the
+-- client of each download. Agents are cut to 255 characters.
+
+CREATE TABLE IF NOT EXISTS user_devices (
+    user_id    INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_agent TEXT    NOT NULL,
+    first_seen TEXT    NOT NULL,
+    last_seen  TEXT    NOT NULL,
+    requests   INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, user_agent)
+);
+
+ALTER TABLE downloads ADD COLUMN user_agent TEXT;
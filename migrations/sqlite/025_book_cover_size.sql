@@ -0,0 +1,5 @@
+-- Pixel size of the stored cover, for OPDS image size hints. 0 when unknown
+-- (no cover, or covers stored before this column existed).
+
+ALTER TABLE books ADD COLUMN cover_width INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE books ADD COLUMN cover_height INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+-- Per-user monthly upload quotas set by admins (0 = unlimited) and the
+-- uploads counted against them: one row per user and calendar month
+-- (`YYYY-MM`, UTC). Admins reset a user's counters by deleting their rows.
+
+ALTER TABLE users ADD COLUMN upload_quota_files INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN upload_quota_mb INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS upload_usage (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    period  TEXT    NOT NULL,
+    files   INTEGER NOT NULL DEFAULT 0,
+    bytes   INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, period)
+);
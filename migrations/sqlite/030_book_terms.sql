@@ -0,0 +1,13 @@
+-- Full-text index of book bodies (see src/fulltext.rs): one row per distinct
+-- term of a book. Filled after scans when search.fulltext is enabled;
+-- content_indexed marks books whose text was read (or could not be).
+
+ALTER TABLE books ADD COLUMN content_indexed INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS book_terms (
+    term    TEXT NOT NULL,
+    book_id INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    freq    INTEGER NOT NULL,
+    PRIMARY KEY (term, book_id)
+);
+CREATE INDEX idx_book_terms_book ON book_terms(book_id);
@@ -0,0 +1,17 @@
+-- Identical files registered as one book (scanner.link_identical): the
+-- SHA-256 of a book's file, and further locations of the same content.
+-- A book's own path/filename stays its main copy.
+
+ALTER TABLE books ADD COLUMN file_hash TEXT NOT NULL DEFAULT '';
+CREATE INDEX idx_books_size_hash ON books(size, file_hash);
+
+CREATE TABLE IF NOT EXISTS book_files (
+    id       INTEGER PRIMARY KEY AUTOINCREMENT,
+    book_id  INTEGER NOT NULL REFERENCES books(id) ON DELETE CASCADE,
+    path     TEXT    NOT NULL,
+    filename TEXT    NOT NULL,
+    format   TEXT    NOT NULL,
+    size     INTEGER NOT NULL,
+    UNIQUE (path, filename)
+);
+CREATE INDEX idx_book_files_book ON book_files(book_id);
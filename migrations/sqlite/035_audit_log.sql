@@ -0,0 +1,12 @@
+-- Administrator actions shown in the admin panel (impersonation start and
+-- end). Names are copied so entries outlive deleted accounts.
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TEXT    NOT NULL,
+    admin      TEXT    NOT NULL,
+    action     TEXT    NOT NULL,
+    subject    TEXT    NOT NULL DEFAULT '',
+    ip         TEXT    NOT NULL DEFAULT ''
+);
+CREATE INDEX idx_audit_log_created ON audit_log(created_at);
@@ -0,0 +1,258 @@
+//! Book endpoints and the combined search.
+
+use axum::extract::{Extension, Path, Query, Request, State};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use crate::db::models::{self, Book};
+use crate::db::queries::books::{BookSort, SearchFilter};
+use crate::db::queries::{MatchMode, authors, books, genres, series};
+use crate::state::AppState;
+
+use super::library::{AuthorRef, SeriesSummary};
+use super::{ApiError, ApiResult, ApiUser, Item, ListQuery, Page};
+
+/// Entries of each kind returned by `/search`.
+const SEARCH_HITS: i32 = 10;
+
+/// A book in lists.
+#[derive(Serialize)]
+pub struct BookSummary {
+    pub id: i64,
+    pub title: String,
+    pub format: String,
+    pub size: i64,
+    pub lang: String,
+    pub docdate: String,
+    pub added_at: String,
+    pub authors: Vec<AuthorRef>,
+    pub cover_url: Option<String>,
+    pub download_url: String,
+}
+
+/// A book with its annotation, series and genres.
+#[derive(Serialize)]
+pub struct BookDetail {
+    #[serde(flatten)]
+    pub summary: BookSummary,
+    pub annotation: String,
+    pub series: Vec<BookSeriesRef>,
+    pub genres: Vec<GenreRef>,
+}
+
+#[derive(Serialize)]
+pub struct BookSeriesRef {
+    pub id: i64,
+    pub name: String,
+    pub number: i32,
+}
+
+#[derive(Serialize)]
+pub struct GenreRef {
+    pub id: i64,
+    pub code: String,
+    pub section: String,
+    pub name: String,
+}
+
+impl From<models::Genre> for GenreRef {
+    fn from(g: models::Genre) -> Self {
+        Self {
+            id: g.id,
+            code: g.code,
+            section: g.section,
+            name: g.subsection,
+        }
+    }
+}
+
+async fn summary(state: &AppState, book: Book) -> Result<BookSummary, ApiError> {
+    let authors = authors::get_for_book(&state.db, book.id)
+        .await?
+        .into_iter()
+        .map(AuthorRef::from)
+        .collect();
+    Ok(BookSummary {
+        cover_url: (book.cover != 0)
+            .then(|| crate::opds::covers::CoverUrls::new(&state.config).cover(book.id)),
+        download_url: format!("/api/v1/books/{}/download", book.id),
+        id: book.id,
+        title: book.title,
+        format: book.format,
+        size: book.size,
+        lang: book.lang,
+        docdate: book.docdate,
+        added_at: book.reg_date,
+        authors,
+    })
+}
+
+/// Summaries of `books`, in order.
+pub(super) async fn summaries(
+    state: &AppState,
+    books: Vec<Book>,
+) -> Result<Vec<BookSummary>, ApiError> {
+    let mut out = Vec::with_capacity(books.len());
+    for book in books {
+        out.push(summary(state, book).await?);
+    }
+    Ok(out)
+}
+
+/// GET /api/v1/books — books, optionally filtered by title, language,
+/// format and genre.
+pub async fn list_books(
+    State(state): State<AppState>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Page<BookSummary>> {
+    let (limit, offset) = params.bounds();
+    let sort = params.sort(&BookSort::LISTING)?;
+    let filter = SearchFilter::parse(
+        params.genre.as_deref(),
+        params.lang.as_deref(),
+        params.format.as_deref(),
+    );
+    let config = &state.config;
+    let term = params.term();
+    let total = books::count_by_title_search(
+        &state.db,
+        &term,
+        MatchMode::Contains,
+        config.opds.hide_doubles,
+        config.search.translit,
+        &filter,
+    )
+    .await?;
+    let found = books::search_by_title(
+        &state.db,
+        &term,
+        MatchMode::Contains,
+        limit,
+        offset,
+        config.opds.hide_doubles,
+        sort,
+        config.search.translit,
+        &filter,
+    )
+    .await?;
+    Ok(params.page(summaries(&state, found).await?, total))
+}
+
+async fn available_book(state: &AppState, id: i64) -> Result<Book, ApiError> {
+    books::get_by_id(&state.db, id)
+        .await?
+        .filter(|b| b.avail > 0)
+        .ok_or(ApiError::NotFound("book"))
+}
+
+/// GET /api/v1/books/:id
+pub async fn get_book(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Item<BookDetail>> {
+    let book = available_book(&state, id).await?;
+    let annotation = book.annotation.clone();
+    let series = series::get_for_book(&state.db, id)
+        .await?
+        .into_iter()
+        .map(|(s, number)| BookSeriesRef {
+            id: s.id,
+            name: s.ser_name,
+            number,
+        })
+        .collect();
+    let genres = genres::get_for_book(&state.db, id, &state.config.web.language)
+        .await?
+        .into_iter()
+        .map(GenreRef::from)
+        .collect();
+    Ok(Item::json(BookDetail {
+        summary: summary(&state, book).await?,
+        annotation,
+        series,
+        genres,
+    }))
+}
+
+/// GET /api/v1/books/:id/download — the book file in its original format.
+pub async fn download_book(
+    State(state): State<AppState>,
+    Extension(ApiUser(user_id)): Extension<ApiUser>,
+    Path(id): Path<i64>,
+    request: Request,
+) -> Response {
+    match available_book(&state, id).await {
+        Ok(book) => {
+            crate::opds::download::book_file_response(&state, &book, user_id, request).await
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchResults {
+    pub books: Vec<BookSummary>,
+    pub books_total: i64,
+    pub authors: Vec<AuthorRef>,
+    pub authors_total: i64,
+    pub series: Vec<SeriesSummary>,
+    pub series_total: i64,
+}
+
+/// GET /api/v1/search?q= — the first matches among books, authors and series.
+pub async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Item<SearchResults>> {
+    let term = params.term();
+    if term.is_empty() {
+        return Err(ApiError::BadRequest("q is required".to_string()));
+    }
+    let config = &state.config;
+    let translit = config.search.translit;
+    let filter = SearchFilter::default();
+
+    let books_total = books::count_by_title_search(
+        &state.db,
+        &term,
+        MatchMode::Contains,
+        config.opds.hide_doubles,
+        translit,
+        &filter,
+    )
+    .await?;
+    let found = books::search_by_title(
+        &state.db,
+        &term,
+        MatchMode::Contains,
+        SEARCH_HITS,
+        0,
+        config.opds.hide_doubles,
+        BookSort::Title,
+        translit,
+        &filter,
+    )
+    .await?;
+    let authors_total =
+        authors::count_by_name_search(&state.db, &term, MatchMode::Contains, translit).await?;
+    let found_authors = authors::search_by_name(
+        &state.db,
+        &term,
+        MatchMode::Contains,
+        SEARCH_HITS,
+        0,
+        translit,
+    )
+    .await?;
+    let series_total = series::count_by_name_search(&state.db, &term).await?;
+    let found_series = series::search_by_name(&state.db, &term, SEARCH_HITS, 0).await?;
+
+    Ok(Item::json(SearchResults {
+        books: summaries(&state, found).await?,
+        books_total,
+        authors: found_authors.into_iter().map(AuthorRef::from).collect(),
+        authors_total,
+        series: found_series.into_iter().map(SeriesSummary::from).collect(),
+        series_total,
+    }))
+}
@@ -0,0 +1,266 @@
+//! Author, series, genre and catalog endpoints.
+
+use axum::extract::{Path, Query, State};
+use serde::Serialize;
+
+use crate::db::models::{Author, Catalog, Series};
+use crate::db::queries::books::BookSort;
+use crate::db::queries::{MatchMode, authors, books, catalogs, genres, series};
+use crate::state::AppState;
+
+use super::books::{BookSummary, GenreRef, summaries};
+use super::{ApiError, ApiResult, Item, ListQuery, Page};
+
+#[derive(Serialize)]
+pub struct AuthorRef {
+    pub id: i64,
+    pub name: String,
+}
+
+impl From<Author> for AuthorRef {
+    fn from(a: Author) -> Self {
+        Self {
+            id: a.id,
+            name: a.full_name,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SeriesSummary {
+    pub id: i64,
+    pub name: String,
+}
+
+impl From<Series> for SeriesSummary {
+    fn from(s: Series) -> Self {
+        Self {
+            id: s.id,
+            name: s.ser_name,
+        }
+    }
+}
+
+/// An author or series with the number of its available books.
+#[derive(Serialize)]
+pub struct Counted<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub book_count: i64,
+}
+
+/// GET /api/v1/authors — authors, optionally those whose name contains `q`.
+pub async fn list_authors(
+    State(state): State<AppState>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Page<AuthorRef>> {
+    let (limit, offset) = params.bounds();
+    let term = params.term();
+    let translit = state.config.search.translit;
+    let total =
+        authors::count_by_name_search(&state.db, &term, MatchMode::Contains, translit).await?;
+    let found = authors::search_by_name(
+        &state.db,
+        &term,
+        MatchMode::Contains,
+        limit,
+        offset,
+        translit,
+    )
+    .await?;
+    Ok(params.page(found.into_iter().map(AuthorRef::from).collect(), total))
+}
+
+/// GET /api/v1/authors/:id
+pub async fn get_author(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Item<Counted<AuthorRef>>> {
+    let author = authors::get_by_id(&state.db, id)
+        .await?
+        .ok_or(ApiError::NotFound("author"))?;
+    let book_count = books::count_by_author(&state.db, id, state.config.opds.hide_doubles).await?;
+    Ok(Item::json(Counted {
+        item: AuthorRef::from(author),
+        book_count,
+    }))
+}
+
+/// GET /api/v1/authors/:id/books
+pub async fn author_books(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Page<BookSummary>> {
+    authors::get_by_id(&state.db, id)
+        .await?
+        .ok_or(ApiError::NotFound("author"))?;
+    let (limit, offset) = params.bounds();
+    let sort = params.sort(&BookSort::LISTING)?;
+    let hide_doubles = state.config.opds.hide_doubles;
+    let total = books::count_by_author(&state.db, id, hide_doubles).await?;
+    let found = books::get_by_author(&state.db, id, limit, offset, hide_doubles, sort).await?;
+    Ok(params.page(summaries(&state, found).await?, total))
+}
+
+/// GET /api/v1/series — series, optionally those whose name contains `q`.
+pub async fn list_series(
+    State(state): State<AppState>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Page<SeriesSummary>> {
+    let (limit, offset) = params.bounds();
+    let term = params.term();
+    let total = series::count_by_name_search(&state.db, &term).await?;
+    let found = series::search_by_name(&state.db, &term, limit, offset).await?;
+    Ok(params.page(found.into_iter().map(SeriesSummary::from).collect(), total))
+}
+
+/// GET /api/v1/series/:id
+pub async fn get_series(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Item<Counted<SeriesSummary>>> {
+    let found = series::get_by_id(&state.db, id)
+        .await?
+        .ok_or(ApiError::NotFound("series"))?;
+    let book_count = books::count_by_series(&state.db, id, state.config.opds.hide_doubles).await?;
+    Ok(Item::json(Counted {
+        item: SeriesSummary::from(found),
+        book_count,
+    }))
+}
+
+/// GET /api/v1/series/:id/books — in volume order unless `sort` says otherwise.
+pub async fn series_books(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Page<BookSummary>> {
+    series::get_by_id(&state.db, id)
+        .await?
+        .ok_or(ApiError::NotFound("series"))?;
+    let (limit, offset) = params.bounds();
+    let sort = params.sort(&BookSort::SERIES_LISTING)?;
+    let hide_doubles = state.config.opds.hide_doubles;
+    let total = books::count_by_series(&state.db, id, hide_doubles).await?;
+    let found = books::get_by_series(&state.db, id, limit, offset, hide_doubles, sort).await?;
+    Ok(params.page(summaries(&state, found).await?, total))
+}
+
+#[derive(Serialize)]
+pub struct GenreSection {
+    pub code: String,
+    pub name: String,
+    pub genres: Vec<Counted<GenreRef>>,
+}
+
+/// GET /api/v1/genres — genre sections with their genres and book counts.
+/// Names follow `lang` (default: the web UI language).
+pub async fn list_genres(
+    State(state): State<AppState>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Item<Vec<GenreSection>>> {
+    let lang = params
+        .lang
+        .clone()
+        .unwrap_or_else(|| state.config.web.language.clone());
+    let mut sections = Vec::new();
+    for (code, name) in genres::get_sections(&state.db, &lang).await? {
+        let genres = genres::get_by_section_with_counts(&state.db, &code, &lang)
+            .await?
+            .into_iter()
+            .map(|(genre, book_count)| Counted {
+                item: GenreRef::from(genre),
+                book_count,
+            })
+            .collect();
+        sections.push(GenreSection { code, name, genres });
+    }
+    Ok(Item::json(sections))
+}
+
+/// GET /api/v1/genres/:id/books
+pub async fn genre_books(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Page<BookSummary>> {
+    genres::get_by_id(&state.db, id, &state.config.web.language)
+        .await?
+        .ok_or(ApiError::NotFound("genre"))?;
+    let (limit, offset) = params.bounds();
+    let sort = params.sort(&BookSort::LISTING)?;
+    let hide_doubles = state.config.opds.hide_doubles;
+    let total = books::count_by_genre(&state.db, id, hide_doubles).await?;
+    let found = books::get_by_genre(&state.db, id, limit, offset, hide_doubles, sort).await?;
+    Ok(params.page(summaries(&state, found).await?, total))
+}
+
+#[derive(Serialize)]
+pub struct CatalogRef {
+    pub id: i64,
+    pub parent_id: Option<i64>,
+    pub name: String,
+    pub path: String,
+}
+
+impl From<Catalog> for CatalogRef {
+    fn from(c: Catalog) -> Self {
+        Self {
+            id: c.id,
+            parent_id: c.parent_id,
+            name: c.cat_name,
+            path: c.path,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CatalogDetail {
+    #[serde(flatten)]
+    pub catalog: CatalogRef,
+    pub children: Vec<CatalogRef>,
+    pub book_count: i64,
+}
+
+/// GET /api/v1/catalogs — top-level catalogs of the library tree.
+pub async fn list_catalogs(State(state): State<AppState>) -> ApiResult<Item<Vec<CatalogRef>>> {
+    let roots = catalogs::get_root_catalogs(&state.db).await?;
+    Ok(Item::json(
+        roots.into_iter().map(CatalogRef::from).collect(),
+    ))
+}
+
+/// GET /api/v1/catalogs/:id — a catalog with its subcatalogs.
+pub async fn get_catalog(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> ApiResult<Item<CatalogDetail>> {
+    let catalog = catalogs::get_by_id(&state.db, id)
+        .await?
+        .ok_or(ApiError::NotFound("catalog"))?;
+    let children = catalogs::get_children(&state.db, id).await?;
+    let book_count = books::count_by_catalog(&state.db, id, state.config.opds.hide_doubles).await?;
+    Ok(Item::json(CatalogDetail {
+        catalog: CatalogRef::from(catalog),
+        children: children.into_iter().map(CatalogRef::from).collect(),
+        book_count,
+    }))
+}
+
+/// GET /api/v1/catalogs/:id/books — books directly in the catalog.
+pub async fn catalog_books(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<ListQuery>,
+) -> ApiResult<Page<BookSummary>> {
+    catalogs::get_by_id(&state.db, id)
+        .await?
+        .ok_or(ApiError::NotFound("catalog"))?;
+    let (limit, offset) = params.bounds();
+    let sort = params.sort(&BookSort::LISTING)?;
+    let hide_doubles = state.config.opds.hide_doubles;
+    let total = books::count_by_catalog(&state.db, id, hide_doubles).await?;
+    let found = books::get_by_catalog(&state.db, id, limit, offset, hide_doubles, sort).await?;
+    Ok(params.page(summaries(&state, found).await?, total))
+}
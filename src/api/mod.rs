@@ -0,0 +1,256 @@
+//! Versioned JSON REST API under `/api/v1/` for scripts and other programs.
+//!
+//! Lists answer `{"data": [...], "pagination": {...}}`, single resources
+//! `{"data": {...}}`, and failures `{"error": {"code", "message"}}` with a
+//! matching HTTP status. Authentication is the web session cookie or OPDS
+//! Basic credentials, as for the GraphQL endpoint.
+
+mod books;
+mod library;
+
+use axum::Router;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Json, Response};
+use axum::routing::get;
+use axum_extra::extract::cookie::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::db::queries::books::BookSort;
+use crate::state::AppState;
+use crate::web::auth::active_session_user;
+
+/// Items per page when `per_page` is not given.
+const DEFAULT_PER_PAGE: i32 = 30;
+/// Largest accepted `per_page`.
+const MAX_PER_PAGE: i32 = 100;
+
+/// Errors reported to API clients.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("authentication required")]
+    Unauthorized,
+    #[error("{0} not found")]
+    NotFound(&'static str),
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("database error")]
+    Database(#[from] sqlx::Error),
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Database(_) => "internal",
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if let ApiError::Database(e) = &self {
+            tracing::error!("API database error: {e}");
+        }
+        let body = serde_json::json!({
+            "error": { "code": self.code(), "message": self.to_string() }
+        });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+pub type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// `{"data": ...}` envelope of a single resource.
+#[derive(Serialize)]
+pub struct Item<T> {
+    pub data: T,
+}
+
+impl<T> Item<T> {
+    pub fn json(data: T) -> Json<Self> {
+        Json(Self { data })
+    }
+}
+
+/// `{"data": [...], "pagination": {...}}` envelope of a list.
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub pagination: PageInfo,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PageInfo {
+    /// 1-based.
+    pub page: i32,
+    pub per_page: i32,
+    pub total: i64,
+    pub total_pages: i64,
+}
+
+/// Query parameters of list endpoints. `page` is 1-based; `q` narrows the
+/// list by title or name; `sort`, `lang`, `format` and `genre` apply to
+/// book lists.
+#[derive(Debug, Default, Deserialize)]
+pub struct ListQuery {
+    pub page: Option<i32>,
+    pub per_page: Option<i32>,
+    #[serde(default)]
+    pub q: String,
+    pub sort: Option<String>,
+    pub lang: Option<String>,
+    pub format: Option<String>,
+    pub genre: Option<String>,
+}
+
+impl ListQuery {
+    /// `(limit, offset)` of the requested page, with `per_page` clamped.
+    pub fn bounds(&self) -> (i32, i32) {
+        let per_page = self
+            .per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE);
+        let page = self.page.unwrap_or(1).max(1);
+        (per_page, (page - 1).saturating_mul(per_page))
+    }
+
+    pub fn page<T>(&self, data: Vec<T>, total: i64) -> Json<Page<T>> {
+        let (per_page, offset) = self.bounds();
+        Json(Page {
+            data,
+            pagination: PageInfo {
+                page: offset / per_page + 1,
+                per_page,
+                total,
+                total_pages: (total + per_page as i64 - 1) / per_page as i64,
+            },
+        })
+    }
+
+    /// Requested book order if `options` offers it, else the first option.
+    /// An unknown value is an error rather than silently ignored.
+    pub fn sort(&self, options: &[BookSort]) -> Result<BookSort, ApiError> {
+        match self.sort.as_deref() {
+            None => Ok(options[0]),
+            Some(value) => BookSort::parse(value)
+                .filter(|sort| options.contains(sort))
+                .ok_or_else(|| ApiError::BadRequest(format!("unsupported sort: {value}"))),
+        }
+    }
+
+    /// Uppercased search term, matching the `search_*` columns.
+    pub fn term(&self) -> String {
+        self.q.trim().to_uppercase()
+    }
+}
+
+/// User of an API request, set by [`auth_layer`].
+#[derive(Clone, Copy)]
+pub struct ApiUser(pub Option<i64>);
+
+/// User of a request: the web session if present, else OPDS Basic auth.
+pub async fn request_user(state: &AppState, jar: &CookieJar, headers: &HeaderMap) -> Option<i64> {
+    if let Some(uid) = active_session_user(state, jar).await {
+        return Some(uid);
+    }
+    crate::opds::auth::get_user_id_from_headers(&state.db, headers).await
+}
+
+/// Middleware: resolve the caller and refuse anonymous requests when
+/// `opds.auth_required` is set.
+async fn auth_layer(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user_id = request_user(&state, &jar, request.headers()).await;
+    if user_id.is_none() && state.config.opds.auth_required {
+        return ApiError::Unauthorized.into_response();
+    }
+    request.extensions_mut().insert(ApiUser(user_id));
+    next.run(request).await
+}
+
+async fn not_found() -> ApiError {
+    ApiError::NotFound("endpoint")
+}
+
+/// Build the `/api/v1` router.
+pub fn router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/books", get(books::list_books))
+        .route("/books/{id}", get(books::get_book))
+        .route("/books/{id}/download", get(books::download_book))
+        .route("/search", get(books::search))
+        .route("/authors", get(library::list_authors))
+        .route("/authors/{id}", get(library::get_author))
+        .route("/authors/{id}/books", get(library::author_books))
+        .route("/series", get(library::list_series))
+        .route("/series/{id}", get(library::get_series))
+        .route("/series/{id}/books", get(library::series_books))
+        .route("/genres", get(library::list_genres))
+        .route("/genres/{id}/books", get(library::genre_books))
+        .route("/catalogs", get(library::list_catalogs))
+        .route("/catalogs/{id}", get(library::get_catalog))
+        .route("/catalogs/{id}/books", get(library::catalog_books))
+        .fallback(not_found)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::reject_writes_when_read_only,
+        ))
+        .layer(middleware::from_fn_with_state(state, auth_layer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_query_bounds_sort_and_info() {
+        let params = ListQuery::default();
+        assert_eq!(params.bounds(), (DEFAULT_PER_PAGE, 0));
+        assert_eq!(params.sort(&BookSort::LISTING).unwrap(), BookSort::Title);
+
+        let params = ListQuery {
+            page: Some(3),
+            per_page: Some(10),
+            sort: Some("recent".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(params.sort(&BookSort::LISTING).unwrap(), BookSort::Recent);
+        assert_eq!(params.bounds(), (10, 20));
+        let page = params.page(vec![1, 2], 25);
+        assert_eq!(
+            page.0.pagination,
+            PageInfo {
+                page: 3,
+                per_page: 10,
+                total: 25,
+                total_pages: 3,
+            }
+        );
+
+        let params = ListQuery {
+            page: Some(0),
+            per_page: Some(1000),
+            sort: Some("bogus".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(params.bounds(), (MAX_PER_PAGE, 0));
+        assert!(params.sort(&BookSort::LISTING).is_err());
+    }
+}
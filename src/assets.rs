@@ -1,23 +1,29 @@
+use std::collections::HashMap;
 #[cfg(debug_assertions)]
 use std::path::Path as FsPath;
 
-#[cfg(not(debug_assertions))]
-use std::collections::HashMap;
 #[cfg(not(debug_assertions))]
 use std::sync::LazyLock;
 
 use axum::body::Body;
 #[cfg(not(debug_assertions))]
 use axum::body::Bytes;
-use axum::extract::Path;
+use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 #[cfg(not(debug_assertions))]
 use include_dir::{Dir, include_dir};
 use sha2::{Digest, Sha256};
 
+use crate::config::OpdsConfig;
+use crate::state::AppState;
+
 const STATIC_CACHE_CONTROL: &str = "public, max-age=3600";
+/// Fingerprinted URLs change with the content, so they can be cached forever.
+const FINGERPRINTED_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
 const SERVICE_WORKER_CACHE_CONTROL: &str = "no-cache";
+/// Static asset served as `/favicon.ico` when `opds.icon_path` is unset.
+const BUNDLED_FAVICON: &str = "images/favicon.ico";
 
 #[cfg(not(debug_assertions))]
 static EMBEDDED_ASSETS: Dir<'_> = include_dir!("$OUT_DIR/embedded_assets");
@@ -43,6 +49,15 @@ struct EmbeddedStaticFile {
     etag: String,
 }
 
+/// Length of the content hash in fingerprinted asset URLs (hex characters).
+pub(crate) const FINGERPRINT_LEN: usize = 16;
+
+#[derive(serde::Deserialize)]
+pub struct StaticAssetQuery {
+    /// Content fingerprint added by [`asset_url`].
+    h: Option<String>,
+}
+
 #[cfg(debug_assertions)]
 pub fn load_templates() -> Result<tera::Tera, tera::Error> {
     tera::Tera::new("templates/**/*.html")
@@ -109,20 +124,121 @@ fn collect_templates(
     Ok(())
 }
 
-pub async fn static_asset(Path(path): Path<String>, headers: HeaderMap) -> Response {
+pub async fn static_asset(
+    Path(path): Path<String>,
+    Query(query): Query<StaticAssetQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let fingerprint = query.h.as_deref();
+
     #[cfg(debug_assertions)]
     {
-        debug_static_asset(path, headers).await
+        debug_static_asset(path, fingerprint, headers).await
     }
 
     #[cfg(not(debug_assertions))]
     {
-        embedded_static_asset(path, headers)
+        embedded_static_asset(path, fingerprint, headers)
+    }
+}
+
+/// GET /favicon.ico — the configured `opds.icon_path`, else the bundled icon.
+pub async fn favicon(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(icon_path) = state.config.opds.icon_path.as_deref() else {
+        return static_asset(
+            Path(BUNDLED_FAVICON.to_string()),
+            Query(StaticAssetQuery { h: None }),
+            headers,
+        )
+        .await;
+    };
+
+    let bytes = match tokio::fs::read(icon_path).await {
+        Ok(data) => data,
+        Err(error) => {
+            tracing::error!("Failed to read icon {}: {}", icon_path.display(), error);
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    };
+    let etag = build_etag(&bytes);
+    let if_none_match = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok());
+    if matches_if_none_match(if_none_match, &etag) {
+        return not_modified_response(&etag, STATIC_CACHE_CONTROL, false);
+    }
+
+    let content_length = bytes.len();
+    ok_response(
+        Body::from(bytes),
+        &favicon_content_type(&state.config.opds),
+        &etag,
+        content_length,
+        STATIC_CACHE_CONTROL,
+        false,
+    )
+}
+
+/// Content type of the icon served at `/favicon.ico`.
+pub fn favicon_content_type(opds: &OpdsConfig) -> String {
+    match &opds.icon_path {
+        Some(path) => static_content_type(&path.to_string_lossy()),
+        None => static_content_type(BUNDLED_FAVICON),
+    }
+}
+
+/// Build a cache-busting URL for a static asset: `/static/<path>?h=<content hash>`.
+///
+/// Unknown assets get a plain `/static/<path>` URL.
+pub fn asset_url(path: &str) -> String {
+    let Some(normalized) = normalize_static_path(path) else {
+        return format!("/static/{}", path.trim_start_matches('/'));
+    };
+    match static_fingerprint(&normalized) {
+        Some(fingerprint) => format!("/static/{normalized}?h={fingerprint}"),
+        None => format!("/static/{normalized}"),
+    }
+}
+
+#[cfg(debug_assertions)]
+fn static_fingerprint(path: &str) -> Option<String> {
+    // Assets are served from disk in debug builds, so hash the current file.
+    let bytes = std::fs::read(FsPath::new("static").join(path)).ok()?;
+    Some(fingerprint_from_etag(&build_etag(&bytes)).to_string())
+}
+
+#[cfg(not(debug_assertions))]
+fn static_fingerprint(path: &str) -> Option<String> {
+    embedded_static_metadata::fingerprint_for_path(path).map(str::to_string)
+}
+
+fn fingerprint_from_etag(etag: &str) -> &str {
+    &etag[1..=FINGERPRINT_LEN]
+}
+
+/// Tera function `asset_url(path="css/ropds.css")`.
+pub struct AssetUrl;
+
+impl tera::Function for AssetUrl {
+    fn call(&self, args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+        let path = args
+            .get("path")
+            .and_then(tera::Value::as_str)
+            .ok_or_else(|| tera::Error::msg("asset_url: missing string argument `path`"))?;
+        Ok(tera::Value::String(asset_url(path)))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
     }
 }
 
 #[cfg(debug_assertions)]
-async fn debug_static_asset(path: String, headers: HeaderMap) -> Response {
+async fn debug_static_asset(
+    path: String,
+    fingerprint: Option<&str>,
+    headers: HeaderMap,
+) -> Response {
     let if_none_match = headers
         .get(header::IF_NONE_MATCH)
         .and_then(|value| value.to_str().ok());
@@ -131,7 +247,6 @@ async fn debug_static_asset(path: String, headers: HeaderMap) -> Response {
         return StatusCode::NOT_FOUND.into_response();
     };
     let is_service_worker = is_service_worker_asset(&normalized);
-    let cache_control = cache_control_for_path(&normalized);
 
     let full_path = FsPath::new("static").join(&normalized);
     let bytes = match tokio::fs::read(&full_path).await {
@@ -150,6 +265,7 @@ async fn debug_static_asset(path: String, headers: HeaderMap) -> Response {
     };
 
     let etag = build_etag(&bytes);
+    let cache_control = cache_control_for_path(&normalized, fingerprint, &etag);
     if matches_if_none_match(if_none_match, &etag) {
         return not_modified_response(&etag, cache_control, is_service_worker);
     }
@@ -168,7 +284,7 @@ async fn debug_static_asset(path: String, headers: HeaderMap) -> Response {
 }
 
 #[cfg(not(debug_assertions))]
-fn embedded_static_asset(path: String, headers: HeaderMap) -> Response {
+fn embedded_static_asset(path: String, fingerprint: Option<&str>, headers: HeaderMap) -> Response {
     let if_none_match = headers
         .get(header::IF_NONE_MATCH)
         .and_then(|value| value.to_str().ok());
@@ -177,11 +293,11 @@ fn embedded_static_asset(path: String, headers: HeaderMap) -> Response {
         return StatusCode::NOT_FOUND.into_response();
     };
     let is_service_worker = is_service_worker_asset(&normalized);
-    let cache_control = cache_control_for_path(&normalized);
 
     let Some(asset) = EMBEDDED_STATIC_FILES.get(&normalized) else {
         return StatusCode::NOT_FOUND.into_response();
     };
+    let cache_control = cache_control_for_path(&normalized, fingerprint, &asset.etag);
 
     if matches_if_none_match(if_none_match, &asset.etag) {
         return not_modified_response(&asset.etag, cache_control, is_service_worker);
@@ -309,9 +425,13 @@ fn is_service_worker_asset(path: &str) -> bool {
     path == "sw.js"
 }
 
-fn cache_control_for_path(path: &str) -> &'static str {
+/// Far-future caching is only allowed when the requested fingerprint matches
+/// the served content; stale fingerprints from old pages get the default policy.
+fn cache_control_for_path(path: &str, fingerprint: Option<&str>, etag: &str) -> &'static str {
     if is_service_worker_asset(path) {
         SERVICE_WORKER_CACHE_CONTROL
+    } else if fingerprint.is_some_and(|value| value == fingerprint_from_etag(etag)) {
+        FINGERPRINTED_CACHE_CONTROL
     } else {
         STATIC_CACHE_CONTROL
     }
@@ -364,8 +484,8 @@ fn strip_weak_etag(value: &str) -> &str {
 #[cfg(test)]
 mod tests {
     use super::{
-        build_etag, cache_control_for_path, matches_if_none_match, normalize_static_path,
-        static_content_type,
+        asset_url, build_etag, cache_control_for_path, fingerprint_from_etag,
+        matches_if_none_match, normalize_static_path, static_content_type,
     };
 
     #[test]
@@ -409,10 +529,41 @@ mod tests {
 
     #[test]
     fn cache_control_varies_for_service_worker() {
-        assert_eq!(cache_control_for_path("sw.js"), "no-cache");
+        let etag = build_etag(b"asset-data");
+        assert_eq!(cache_control_for_path("sw.js", None, &etag), "no-cache");
         assert_eq!(
-            cache_control_for_path("js/ropds.js"),
+            cache_control_for_path("js/ropds.js", None, &etag),
             "public, max-age=3600"
         );
     }
+
+    #[test]
+    fn cache_control_is_immutable_only_for_matching_fingerprint() {
+        let etag = build_etag(b"asset-data");
+        let fingerprint = fingerprint_from_etag(&etag);
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(
+            cache_control_for_path("js/ropds.js", Some(fingerprint), &etag),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            cache_control_for_path("js/ropds.js", Some("0123456789abcdef"), &etag),
+            "public, max-age=3600"
+        );
+        assert_eq!(
+            cache_control_for_path("sw.js", Some(fingerprint), &etag),
+            "no-cache"
+        );
+    }
+
+    #[test]
+    fn asset_url_appends_content_fingerprint() {
+        let bytes = std::fs::read("static/css/ropds.css").unwrap();
+        let expected = fingerprint_from_etag(&build_etag(&bytes)).to_string();
+        assert_eq!(
+            asset_url("css/ropds.css"),
+            format!("/static/css/ropds.css?h={expected}")
+        );
+        assert_eq!(asset_url("/css/missing.css"), "/static/css/missing.css");
+    }
 }
@@ -0,0 +1,142 @@
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use crate::db::models::{Book, CatType};
+
+/// Name of the metadata file that marks a folder of MP3 tracks as one
+/// audiobook (the layout written by Audiobookshelf).
+pub const FOLDER_METADATA_FILE: &str = "metadata.json";
+
+/// Audiobook formats: single `.m4b` files and `mp3` track folders.
+pub fn is_audio_format(format: &str) -> bool {
+    matches!(format, "m4b" | "mp3")
+}
+
+/// MP3 tracks directly inside `dir`, in natural filename order
+/// ("2.mp3" before "10.mp3").
+pub fn list_tracks(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut tracks: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case("mp3"))
+        })
+        .collect();
+    tracks.sort_by(|a, b| {
+        natural_cmp(
+            &a.file_name().unwrap_or_default().to_string_lossy(),
+            &b.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+    Ok(tracks)
+}
+
+/// Whether `dir` is an MP3 audiobook folder: it holds the metadata file and
+/// at least one track.
+pub fn is_audio_folder(dir: &Path) -> bool {
+    dir.join(FOLDER_METADATA_FILE).is_file()
+        && list_tracks(dir).is_ok_and(|tracks| !tracks.is_empty())
+}
+
+/// Audio files of a book stored on disk: the tracks of an MP3 folder book,
+/// or the file itself for an M4B. Empty for books inside archives.
+pub fn book_tracks(root: &Path, book: &Book) -> Vec<PathBuf> {
+    if CatType::try_from(book.cat_type).ok() != Some(CatType::Normal) {
+        return Vec::new();
+    }
+    let path = crate::vfs::local_file(root, &book.path, &book.filename);
+    match book.format.as_str() {
+        "mp3" => list_tracks(&path).unwrap_or_default(),
+        "m4b" if path.is_file() => vec![path],
+        _ => Vec::new(),
+    }
+}
+
+/// Format a running time as `H:MM:SS` (or `M:SS` under an hour).
+pub fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+/// Compare filenames case-insensitively, with digit runs compared by value.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let na = take_number(&mut a);
+                let nb = take_number(&mut b);
+                let ord = na
+                    .trim_start_matches('0')
+                    .len()
+                    .cmp(&nb.trim_start_matches('0').len())
+                    .then_with(|| na.trim_start_matches('0').cmp(nb.trim_start_matches('0')));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_tracks_natural_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "10 - End.mp3",
+            "2 - Middle.MP3",
+            "1 - Start.mp3",
+            "cover.jpg",
+        ] {
+            std::fs::write(dir.path().join(name), b"x").unwrap();
+        }
+        let names: Vec<String> = list_tracks(dir.path())
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["1 - Start.mp3", "2 - Middle.MP3", "10 - End.mp3"]);
+        assert!(!is_audio_folder(dir.path()));
+
+        std::fs::write(dir.path().join(FOLDER_METADATA_FILE), b"{}").unwrap();
+        assert!(is_audio_folder(dir.path()));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(59), "0:59");
+        assert_eq!(format_duration(3 * 3600 + 5 * 60 + 7), "3:05:07");
+    }
+}
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
@@ -21,6 +22,26 @@ pub struct Config {
     pub oauth: OauthConfig,
     #[serde(default)]
     pub smtp: SmtpConfig,
+    #[serde(default)]
+    pub tools: ToolsConfig,
+    #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
+    pub sharing: SharingConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub password: PasswordConfig,
+    #[serde(default)]
+    pub download: DownloadConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    /// Overrides and additions to the built-in book formats, by extension.
+    #[serde(default)]
+    pub formats: BTreeMap<String, FormatConfig>,
+    /// Overrides and additions to the built-in OPDS client profiles, by name.
+    #[serde(default)]
+    pub clients: BTreeMap<String, ClientProfileConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -37,10 +58,65 @@ pub struct ServerConfig {
     /// Session TTL in hours (default 24).
     #[serde(default = "default_session_ttl_hours")]
     pub session_ttl_hours: u64,
+    /// Renew a session once more than half of its TTL has elapsed, so
+    /// sessions in use do not expire.
+    #[serde(default = "default_true")]
+    pub session_sliding: bool,
+    /// TTL in hours of sessions started with "remember me"; such cookies
+    /// outlive the browser session. 0 hides the checkbox.
+    #[serde(default = "default_remember_me_ttl_hours")]
+    pub remember_me_ttl_hours: u64,
     /// Public base URL used for absolute links and OAuth redirect URIs.
     pub base_url: String,
+    /// Take the scheme and host of absolute links from a reverse proxy's
+    /// `Forwarded` / `X-Forwarded-*` headers. Enable only behind a proxy
+    /// that sets them, since clients can send them too.
+    #[serde(default)]
+    pub trust_proxy_headers: bool,
+    /// Resource profile: `default` or `low-memory` for small devices.
+    #[serde(default)]
+    pub profile: ServerProfile,
+    /// Mirror mode: serve the library without scanning, uploads, admin edits
+    /// or migrations, next to a primary instance sharing the database.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Seconds a request may take before it is answered with 408; 0 disables.
+    /// File uploads are exempt.
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Largest request body accepted by OPDS routes, in KiB.
+    #[serde(default = "default_opds_body_limit_kb")]
+    pub opds_body_limit_kb: usize,
+    /// Largest request body accepted by web pages, the JSON API and GraphQL, in KiB.
+    #[serde(default = "default_web_body_limit_kb")]
+    pub web_body_limit_kb: usize,
+    /// Largest request body accepted by admin endpoints, in KiB.
+    #[serde(default = "default_admin_body_limit_kb")]
+    pub admin_body_limit_kb: usize,
+    /// Megabytes that must stay free on the library, covers and upload
+    /// volumes; uploads that would go below are refused. 0 disables the check.
+    #[serde(default = "default_min_free_space_mb")]
+    pub min_free_space_mb: u64,
+    /// Write the OPDS access log to this file (rotated daily, the date is
+    /// appended to the name) instead of the server log.
+    #[serde(default)]
+    pub access_log_path: Option<PathBuf>,
 }
 
+/// Resource profile of the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServerProfile {
+    #[default]
+    Default,
+    /// Small pools, one scan worker, ZIP entries parsed one at a time and
+    /// plain-file downloads streamed from disk.
+    LowMemory,
+}
+
+/// Database connections kept by the low-memory profile.
+pub const LOW_MEMORY_MAX_CONNECTIONS: u32 = 2;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct LibraryConfig {
     pub root_path: PathBuf,
@@ -59,6 +135,29 @@ pub struct LibraryConfig {
     pub zip_codepage: String,
     #[serde(default)]
     pub inpx_enable: bool,
+    /// Templates like `"{author} - {series} #{ser_no} - {title}"` matched
+    /// against the file name of books without embedded metadata.
+    #[serde(default)]
+    pub filename_patterns: Vec<String>,
+    /// Scan `.m4b` files and folders of MP3 tracks with a `metadata.json`
+    /// as audiobooks.
+    #[serde(default)]
+    pub audiobooks: bool,
+    /// Guess the language of books without a language tag from their text.
+    #[serde(default = "default_true")]
+    pub detect_lang: bool,
+    /// Confidence (0–1) a guess needs before it is stored.
+    #[serde(default = "default_detect_lang_confidence")]
+    pub detect_lang_confidence: f64,
+}
+
+impl LibraryConfig {
+    /// Minimum confidence for filling in a missing language, or `None` when
+    /// language detection is off.
+    pub fn lang_detect_threshold(&self) -> Option<f32> {
+        self.detect_lang
+            .then_some(self.detect_lang_confidence as f32)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -67,6 +166,17 @@ pub struct DatabaseConfig {
     pub url: String,
     #[serde(default = "default_db_max_connections")]
     pub max_connections: u32,
+    /// Extra attempts for writes failing on lock contention or deadlocks
+    /// (0 disables retrying).
+    #[serde(default = "default_db_retry")]
+    pub retry: u32,
+    /// Delay before the first retry, doubled on each further one.
+    #[serde(default = "default_db_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Apply pending migrations at startup; when off the server refuses to
+    /// start until `ropds migrate --up` has been run.
+    #[serde(default = "default_true")]
+    pub auto_migrate: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -88,6 +198,63 @@ pub struct OpdsConfig {
     pub alphabet_menu: bool,
     #[serde(default)]
     pub hide_doubles: bool,
+    /// Show the formats of a work (same title and authors) as one web card
+    /// and one OPDS 2 publication with a download link per format.
+    #[serde(default)]
+    pub group_formats: bool,
+    /// Leave genres, genre sections and script entries without available
+    /// books out of navigation (counts are refreshed after each scan).
+    #[serde(default)]
+    pub hide_empty: bool,
+    /// List books from the whole catalog subtree instead of only the
+    /// catalog itself; `?deep=0|1` overrides this per request.
+    #[serde(default)]
+    pub deep_catalogs: bool,
+    /// Attach catalog cover mosaics as thumbnails to catalog navigation
+    /// entries. Some readers render nav thumbnails poorly, so this is off
+    /// by default.
+    #[serde(default)]
+    pub catalog_thumbnails: bool,
+    /// Put every book an authenticated user downloads on their bookshelf.
+    /// Users can opt out on their profile page.
+    #[serde(default = "default_true")]
+    pub auto_bookshelf: bool,
+    /// Image file served as `/favicon.ico` and advertised as the feed icon;
+    /// the bundled favicon is used when unset.
+    #[serde(default)]
+    pub icon_path: Option<PathBuf>,
+    /// Absolute or server-relative URL of a wide logo for the feed `<logo>`.
+    #[serde(default)]
+    pub logo_url: Option<String>,
+    /// Make every feed link absolute for all clients, not only those whose
+    /// profile asks for it. The scheme and host come from the request's
+    /// `Forwarded` (or `X-Forwarded-Proto`/`X-Forwarded-Host`) headers when a
+    /// proxy sets them, else from `server.base_url`.
+    #[serde(default)]
+    pub absolute_urls: bool,
+    /// Books per page of the crawlable full dump (`/opds/all/`).
+    #[serde(default = "default_dump_page_size")]
+    pub dump_page_size: u32,
+}
+
+impl OpdsConfig {
+    /// Whether catalog listings include books from subdirectories. An explicit
+    /// `deep` request parameter (`1`/`0`, `true`/`false`) overrides
+    /// `deep_catalogs`; anything else falls back to it.
+    pub fn catalog_deep(&self, requested: Option<&str>) -> bool {
+        match requested.map(str::trim) {
+            Some("1" | "true" | "yes" | "on") => true,
+            Some("0" | "false" | "no" | "off") => false,
+            _ => self.deep_catalogs,
+        }
+    }
+
+    /// Whether web and OPDS 2 listings show one entry per work: with
+    /// `hide_doubles`, or with `group_formats` so the other formats are
+    /// offered on that entry.
+    pub fn collapse_doubles(&self) -> bool {
+        self.hide_doubles || self.group_formats
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -100,6 +267,13 @@ pub struct CoversConfig {
     pub cover_jpeg_quality: u8,
     #[serde(default = "default_true")]
     pub show_covers: bool,
+    /// With `opds.auth_required`, serve covers and thumbnails only to
+    /// authenticated requests or through signed links in feeds and pages.
+    #[serde(default)]
+    pub signed_urls: bool,
+    /// Minutes a signed cover link stays valid, at least (up to twice as long).
+    #[serde(default = "default_signed_url_ttl_mins")]
+    pub signed_url_ttl_mins: u64,
 }
 
 const DEFAULT_COVER_SCALE_TO: u32 = 600;
@@ -170,6 +344,22 @@ pub struct ScannerConfig {
     /// Parallel scan threads (default: 1 = sequential).
     #[serde(default = "default_workers_num")]
     pub workers_num: usize,
+    /// Glob patterns of library paths to skip, e.g. `"**/.stversions/**"`.
+    /// Matched against paths relative to `library.root_path`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Folder levels below `library.root_path` the scanner descends into;
+    /// deeper folders are skipped and listed in the scan report.
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// File the scanner appends one JSON line to per added, updated or
+    /// deleted book (see [`crate::scanner::ChangeLogObserver`]).
+    #[serde(default)]
+    pub changes_log: Option<PathBuf>,
+    /// Register new plain files with the same content as a known book as
+    /// further copies of it instead of new books (default: false).
+    #[serde(default)]
+    pub link_identical: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -178,6 +368,52 @@ pub struct WebConfig {
     pub language: String,
     #[serde(default = "default_theme")]
     pub theme: String,
+    /// Primary color of the web UI as `#rgb` or `#rrggbb`.
+    #[serde(default)]
+    pub accent_color: Option<String>,
+    /// Name shown in the navbar and page titles; defaults to `opds.title`.
+    #[serde(default)]
+    pub site_name: Option<String>,
+    /// Serve `/sitemap.xml` and embed schema.org metadata in book pages, for
+    /// libraries meant to be found by search engines. Only applies when
+    /// `opds.auth_required` is off.
+    #[serde(default)]
+    pub seo_enabled: bool,
+    /// Contents of `/robots.txt`; when unset, crawlers are turned away
+    /// unless `seo_enabled` applies.
+    #[serde(default)]
+    pub robots_txt: Option<String>,
+}
+
+impl WebConfig {
+    /// `web.accent_color` as an RGB triple, if set and well-formed.
+    pub fn accent_rgb(&self) -> Option<[u8; 3]> {
+        self.accent_color.as_deref().and_then(parse_hex_color)
+    }
+}
+
+/// Parse a CSS hex color in `#rgb` or `#rrggbb` form.
+fn parse_hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.trim().strip_prefix('#')?;
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgb = [0u8; 3];
+            for (slot, digit) in rgb.iter_mut().zip(hex.chars()) {
+                *slot = channel(&format!("{digit}{digit}"))?;
+            }
+            Some(rgb)
+        }
+        6 => Some([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ]),
+        _ => None,
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -191,6 +427,9 @@ pub struct UploadConfig {
     /// Maximum upload file size in megabytes (default 100).
     #[serde(default = "default_max_upload_size_mb")]
     pub max_upload_size_mb: u64,
+    /// How a published upload gets from `upload_path` into the library.
+    #[serde(default)]
+    pub publish_strategy: PublishStrategy,
 }
 
 impl Default for UploadConfig {
@@ -199,10 +438,26 @@ impl Default for UploadConfig {
             allow_upload: false,
             upload_path: PathBuf::new(),
             max_upload_size_mb: default_max_upload_size_mb(),
+            publish_strategy: PublishStrategy::default(),
         }
     }
 }
 
+/// How a staged upload is placed in the library on publish. Linking and
+/// moving need `upload_path` on the library's filesystem; on another one
+/// the file is copied. The library file appears in one rename either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PublishStrategy {
+    /// Rename the staged file into the library.
+    Move,
+    /// Hard-link the staged file into the library.
+    #[default]
+    Hardlink,
+    /// Copy the staged file (twice the disk space until staging is cleared).
+    Copy,
+}
+
 fn default_cached_books_max() -> i64 {
     5
 }
@@ -250,6 +505,10 @@ impl Default for WebConfig {
         Self {
             language: default_language(),
             theme: default_theme(),
+            accent_color: None,
+            site_name: None,
+            seo_enabled: false,
+            robots_txt: None,
         }
     }
 }
@@ -329,6 +588,236 @@ impl Default for SmtpConfig {
     }
 }
 
+/// External helper programs used for PDF/DJVU covers and metadata.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolsConfig {
+    #[serde(default = "default_pdftoppm_path")]
+    pub pdftoppm_path: PathBuf,
+    #[serde(default = "default_pdfinfo_path")]
+    pub pdfinfo_path: PathBuf,
+    #[serde(default = "default_ddjvu_path")]
+    pub ddjvu_path: PathBuf,
+    #[serde(default = "default_pdftotext_path")]
+    pub pdftotext_path: PathBuf,
+    /// Maximum run time of a single tool invocation; the process is killed afterwards.
+    #[serde(default = "default_tool_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Pages of a PDF whose text layer is read for the full-text index.
+    #[serde(default = "default_pdf_text_pages")]
+    pub pdf_text_pages: u32,
+    /// Maximum run time of `pdftotext` on one file, so a huge PDF cannot stall a scan.
+    #[serde(default = "default_pdf_text_timeout_secs")]
+    pub pdf_text_timeout_secs: u64,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            pdftoppm_path: default_pdftoppm_path(),
+            pdfinfo_path: default_pdfinfo_path(),
+            ddjvu_path: default_ddjvu_path(),
+            pdftotext_path: default_pdftotext_path(),
+            timeout_secs: default_tool_timeout_secs(),
+            pdf_text_pages: default_pdf_text_pages(),
+            pdf_text_timeout_secs: default_pdf_text_timeout_secs(),
+        }
+    }
+}
+
+/// Title and author search behaviour.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchConfig {
+    /// Match queries across scripts by transliteration ("Dostoevsky" finds "Достоевский").
+    #[serde(default)]
+    pub translit: bool,
+    /// Fall back to trigram similarity when a substring search finds nothing.
+    #[serde(default)]
+    pub fuzzy: bool,
+    /// Minimum trigram similarity (0..1] of a fuzzy match.
+    #[serde(default = "default_fuzzy_threshold")]
+    pub fuzzy_threshold: f64,
+    /// Index the text of FB2, EPUB and TXT books (and PDFs, with `pdftotext`)
+    /// after scans and offer a "search inside books" mode.
+    #[serde(default)]
+    pub fulltext: bool,
+    /// Text read per book for the full-text index, in KB; the rest of a
+    /// longer book is not searchable.
+    #[serde(default = "default_fulltext_max_kb")]
+    pub fulltext_max_kb: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            translit: false,
+            fuzzy: false,
+            fuzzy_threshold: default_fuzzy_threshold(),
+            fulltext: false,
+            fulltext_max_kb: default_fulltext_max_kb(),
+        }
+    }
+}
+
+/// Links that let people without an account download shared books.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SharingConfig {
+    /// Lifetime of a book share link in hours; 0 disables book sharing.
+    #[serde(default = "default_book_link_ttl_hours")]
+    pub book_link_ttl_hours: u64,
+    /// Lifetime of a guest bookshelf link in hours; 0 disables shelf sharing.
+    #[serde(default = "default_shelf_link_ttl_hours")]
+    pub shelf_link_ttl_hours: u64,
+}
+
+impl Default for SharingConfig {
+    fn default() -> Self {
+        Self {
+            book_link_ttl_hours: default_book_link_ttl_hours(),
+            shelf_link_ttl_hours: default_shelf_link_ttl_hours(),
+        }
+    }
+}
+
+/// Programmatic APIs for third-party clients.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiConfig {
+    /// Serve the GraphQL endpoint at `/graphql`.
+    #[serde(default = "default_true")]
+    pub graphql: bool,
+    /// Serve the JSON REST API under `/api/v1/`.
+    #[serde(default = "default_true")]
+    pub rest: bool,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            graphql: true,
+            rest: true,
+        }
+    }
+}
+
+/// Book downloads.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DownloadConfig {
+    /// File name of downloaded books, e.g. `"{author} - {title}.{ext}"`.
+    #[serde(default = "default_download_filename_template")]
+    pub filename_template: String,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            filename_template: default_download_filename_template(),
+        }
+    }
+}
+
+/// What download statistics and client logs record.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsConfig {
+    /// Count downloads by clients that are not signed in.
+    #[serde(default = "default_true")]
+    pub anonymous_downloads: bool,
+    /// Record client User-Agents on download rows, per user as the devices
+    /// listed in the admin panel, and in the OPDS access log.
+    #[serde(default)]
+    pub user_agents: bool,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            anonymous_downloads: true,
+            user_agents: false,
+        }
+    }
+}
+
+/// One `[formats.<ext>]` entry. Unset fields keep the built-in value, or the
+/// `application/octet-stream` defaults for a new format.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FormatConfig {
+    /// MIME type of downloads and acquisition links.
+    pub mime: Option<String>,
+    /// MIME type of zipped downloads (default: `<mime>+zip`).
+    pub zip_mime: Option<String>,
+    /// OPDS acquisition link relation.
+    pub rel: Option<String>,
+    /// Name shown in the web UI and OPDS facets.
+    pub name: Option<String>,
+    /// Offer a zipped download.
+    pub zip: Option<bool>,
+}
+
+/// One `[clients.<name>]` entry. Unset fields keep the built-in profile's
+/// value, or change nothing for a new profile.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClientProfileConfig {
+    /// User-Agent substrings (case-insensitive) that select the profile.
+    pub user_agent: Option<Vec<String>>,
+    /// Include facet links.
+    pub facets: Option<bool>,
+    /// Make feed links absolute with `server.base_url`.
+    pub absolute_urls: Option<bool>,
+    /// List the zipped download before the original file.
+    pub prefer_zip: Option<bool>,
+    /// Page size, capped by `opds.max_items` (0: no cap of its own).
+    pub max_items: Option<u32>,
+}
+
+/// Argon2id cost of new password hashes. Stored hashes made with other
+/// parameters (or a legacy algorithm) are replaced at the next login.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PasswordConfig {
+    /// Memory per hash in KiB.
+    #[serde(default = "default_argon2_memory_kib")]
+    pub memory_kib: u32,
+    /// Number of passes over the memory.
+    #[serde(default = "default_argon2_iterations")]
+    pub iterations: u32,
+    /// Degree of parallelism (lanes).
+    #[serde(default = "default_argon2_parallelism")]
+    pub parallelism: u32,
+    /// Shortest and longest accepted new password, in characters.
+    #[serde(default = "default_password_min_length")]
+    pub min_length: usize,
+    #[serde(default = "default_password_max_length")]
+    pub max_length: usize,
+    /// Character classes every new password must contain.
+    #[serde(default)]
+    pub required_classes: Vec<CharClass>,
+    /// Refuse new passwords from the built-in list of common passwords.
+    #[serde(default)]
+    pub reject_common: bool,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        Self {
+            memory_kib: default_argon2_memory_kib(),
+            iterations: default_argon2_iterations(),
+            parallelism: default_argon2_parallelism(),
+            min_length: default_password_min_length(),
+            max_length: default_password_max_length(),
+            required_classes: Vec::new(),
+            reject_common: false,
+        }
+    }
+}
+
+/// A kind of character a password policy can require.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    /// Anything that is not a letter or a digit.
+    Symbol,
+}
+
 impl Config {
     pub fn load(path: &Path) -> Result<Self, ConfigError> {
         let content = std::fs::read_to_string(path).map_err(|e| ConfigError::ReadFile {
@@ -340,11 +829,52 @@ impl Config {
             source: e,
         })?;
         config.apply_legacy_cover_fallbacks();
+        config.apply_profile();
+        config.apply_read_only();
         config.server.base_url = config.server.base_url.trim().to_string();
         config.validate()?;
         Ok(config)
     }
 
+    /// Whether search engine support (`web.seo_enabled`) is on; never for
+    /// libraries behind a login.
+    pub fn seo_enabled(&self) -> bool {
+        self.web.seo_enabled && !self.opds.auth_required
+    }
+
+    /// Instance name for web pages and feeds: `web.site_name`, else `opds.title`.
+    pub fn site_name(&self) -> &str {
+        self.web
+            .site_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&self.opds.title)
+    }
+
+    /// Whether the `low-memory` profile is active.
+    pub fn low_memory(&self) -> bool {
+        self.server.profile == ServerProfile::LowMemory
+    }
+
+    /// Turn off uploads on a read-only mirror.
+    pub fn apply_read_only(&mut self) {
+        if self.server.read_only {
+            self.upload.allow_upload = false;
+        }
+    }
+
+    /// Cap pool sizes and scanner concurrency for the selected profile.
+    pub fn apply_profile(&mut self) {
+        if self.low_memory() {
+            self.database.max_connections = self
+                .database
+                .max_connections
+                .min(LOW_MEMORY_MAX_CONNECTIONS);
+            self.scanner.workers_num = 1;
+        }
+    }
+
     fn apply_legacy_cover_fallbacks(&mut self) {
         if self.covers.covers_path == default_covers_path()
             && let Some(path) = self.library.covers_path.clone()
@@ -386,12 +916,152 @@ impl Config {
             ));
         }
 
+        for (key, limit) in [
+            ("server.opds_body_limit_kb", self.server.opds_body_limit_kb),
+            ("server.web_body_limit_kb", self.server.web_body_limit_kb),
+            (
+                "server.admin_body_limit_kb",
+                self.server.admin_body_limit_kb,
+            ),
+        ] {
+            if limit == 0 {
+                return Err(ConfigError::Validation(format!(
+                    "{key} must be greater than 0"
+                )));
+            }
+        }
+
+        crate::vfs::validate_root(&self.library.root_path)
+            .map_err(|e| ConfigError::Validation(format!("library.root_path: {e}")))?;
+        if self.upload.allow_upload && crate::vfs::is_remote(&self.library.root_path) {
+            return Err(ConfigError::Validation(
+                "upload.allow_upload requires a local library.root_path".to_string(),
+            ));
+        }
+
         if self.database.max_connections == 0 {
             return Err(ConfigError::Validation(
                 "database.max_connections must be greater than 0".to_string(),
             ));
         }
 
+        if !THEME_MODES.contains(&self.web.theme.as_str()) {
+            return Err(ConfigError::Validation(format!(
+                "web.theme must be one of {}, got {:?}",
+                THEME_MODES.join(", "),
+                self.web.theme
+            )));
+        }
+        if let Some(color) = &self.web.accent_color
+            && parse_hex_color(color).is_none()
+        {
+            return Err(ConfigError::Validation(format!(
+                "web.accent_color must be a hex color like #1a73e8, got {color:?}"
+            )));
+        }
+        if let Some(icon) = &self.opds.icon_path
+            && !icon.is_file()
+        {
+            return Err(ConfigError::Validation(format!(
+                "opds.icon_path {} is not a readable file",
+                icon.display()
+            )));
+        }
+
+        if self.covers.signed_urls && self.covers.signed_url_ttl_mins == 0 {
+            return Err(ConfigError::Validation(
+                "covers.signed_url_ttl_mins must be greater than 0".to_string(),
+            ));
+        }
+
+        if let Some(path) = &self.server.access_log_path
+            && path.file_name().is_none()
+        {
+            return Err(ConfigError::Validation(format!(
+                "server.access_log_path {} must name a file",
+                path.display()
+            )));
+        }
+
+        if self.opds.dump_page_size == 0 {
+            return Err(ConfigError::Validation(
+                "opds.dump_page_size must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.scanner.max_depth == 0 {
+            return Err(ConfigError::Validation(
+                "scanner.max_depth must be greater than 0".to_string(),
+            ));
+        }
+        if let Err(e) = crate::scanner::compile_exclude(&self.scanner.exclude) {
+            return Err(ConfigError::Validation(format!("scanner.exclude: {e}")));
+        }
+
+        if self.tools.timeout_secs == 0 {
+            return Err(ConfigError::Validation(
+                "tools.timeout_secs must be greater than 0".to_string(),
+            ));
+        }
+        if self.tools.pdf_text_pages == 0 || self.tools.pdf_text_timeout_secs == 0 {
+            return Err(ConfigError::Validation(
+                "tools.pdf_text_pages and tools.pdf_text_timeout_secs must be greater than 0"
+                    .to_string(),
+            ));
+        }
+
+        if let Err(e) = crate::password::params(&self.password) {
+            return Err(ConfigError::Validation(format!(
+                "invalid [password] Argon2 parameters: {e}"
+            )));
+        }
+        if self.password.min_length == 0 || self.password.min_length > self.password.max_length {
+            return Err(ConfigError::Validation(format!(
+                "password.min_length must be between 1 and password.max_length ({}), got {}",
+                self.password.max_length, self.password.min_length
+            )));
+        }
+
+        if let Err(e) =
+            crate::opds::filename::FilenameTemplate::parse(&self.download.filename_template)
+        {
+            return Err(ConfigError::Validation(format!(
+                "download.filename_template: {e}"
+            )));
+        }
+
+        for (ext, format) in &self.formats {
+            for (key, value) in [("mime", &format.mime), ("zip_mime", &format.zip_mime)] {
+                if let Some(value) = value
+                    && value.parse::<mime_guess::mime::Mime>().is_err()
+                {
+                    return Err(ConfigError::Validation(format!(
+                        "formats.{ext}.{key} is not a valid MIME type: {value:?}"
+                    )));
+                }
+            }
+        }
+
+        if !(0.0..=1.0).contains(&self.library.detect_lang_confidence) {
+            return Err(ConfigError::Validation(format!(
+                "library.detect_lang_confidence must be in [0, 1], got {}",
+                self.library.detect_lang_confidence
+            )));
+        }
+
+        if !(self.search.fuzzy_threshold > 0.0 && self.search.fuzzy_threshold <= 1.0) {
+            return Err(ConfigError::Validation(format!(
+                "search.fuzzy_threshold must be in (0, 1], got {}",
+                self.search.fuzzy_threshold
+            )));
+        }
+
+        if self.search.fulltext && self.search.fulltext_max_kb == 0 {
+            return Err(ConfigError::Validation(
+                "search.fulltext_max_kb must be at least 1".to_string(),
+            ));
+        }
+
         if self.oauth.notify_admin_email {
             if self.smtp.host.trim().is_empty() {
                 return Err(ConfigError::Validation(
@@ -485,6 +1155,14 @@ fn default_db_max_connections() -> u32 {
     5
 }
 
+fn default_db_retry() -> u32 {
+    3
+}
+
+fn default_db_retry_backoff_ms() -> u64 {
+    100
+}
+
 fn default_opds_title() -> String {
     "ROPDS".to_string()
 }
@@ -497,6 +1175,10 @@ fn default_split_items() -> u32 {
     300
 }
 
+fn default_dump_page_size() -> u32 {
+    500
+}
+
 fn default_covers_path() -> PathBuf {
     PathBuf::from("covers")
 }
@@ -516,10 +1198,16 @@ impl Default for CoversConfig {
             cover_max_dimension_px: default_cover_max_dimension_px(),
             cover_jpeg_quality: default_cover_jpeg_quality(),
             show_covers: default_true(),
+            signed_urls: false,
+            signed_url_ttl_mins: default_signed_url_ttl_mins(),
         }
     }
 }
 
+fn default_signed_url_ttl_mins() -> u64 {
+    60
+}
+
 fn default_schedule_minutes() -> Vec<u32> {
     vec![0]
 }
@@ -532,6 +1220,30 @@ fn default_session_ttl_hours() -> u64 {
     24
 }
 
+fn default_remember_me_ttl_hours() -> u64 {
+    24 * 30
+}
+
+fn default_request_timeout_secs() -> u64 {
+    60
+}
+
+fn default_opds_body_limit_kb() -> usize {
+    64
+}
+
+fn default_web_body_limit_kb() -> usize {
+    256
+}
+
+fn default_admin_body_limit_kb() -> usize {
+    2048
+}
+
+fn default_min_free_space_mb() -> u64 {
+    256
+}
+
 fn default_max_upload_size_mb() -> u64 {
     100
 }
@@ -540,6 +1252,10 @@ fn default_workers_num() -> usize {
     1
 }
 
+fn default_max_depth() -> usize {
+    64
+}
+
 fn default_read_history_max() -> i64 {
     100
 }
@@ -548,6 +1264,10 @@ fn default_language() -> String {
     "en".to_string()
 }
 
+/// Accepted values of `web.theme` and of the per-user theme preference.
+/// `auto` follows the browser's `prefers-color-scheme`.
+pub const THEME_MODES: [&str; 3] = ["light", "dark", "auto"];
+
 fn default_theme() -> String {
     "light".to_string()
 }
@@ -576,6 +1296,78 @@ fn default_keycloak_button_label() -> String {
     "Company SSO".to_string()
 }
 
+fn default_pdftoppm_path() -> PathBuf {
+    PathBuf::from("pdftoppm")
+}
+
+fn default_pdfinfo_path() -> PathBuf {
+    PathBuf::from("pdfinfo")
+}
+
+fn default_ddjvu_path() -> PathBuf {
+    PathBuf::from("ddjvu")
+}
+
+fn default_pdftotext_path() -> PathBuf {
+    PathBuf::from("pdftotext")
+}
+
+fn default_tool_timeout_secs() -> u64 {
+    60
+}
+
+fn default_pdf_text_pages() -> u32 {
+    100
+}
+
+fn default_pdf_text_timeout_secs() -> u64 {
+    20
+}
+
+fn default_fuzzy_threshold() -> f64 {
+    0.3
+}
+
+fn default_fulltext_max_kb() -> usize {
+    1024
+}
+
+fn default_detect_lang_confidence() -> f64 {
+    0.3
+}
+
+fn default_book_link_ttl_hours() -> u64 {
+    168
+}
+
+fn default_shelf_link_ttl_hours() -> u64 {
+    720
+}
+
+fn default_download_filename_template() -> String {
+    crate::opds::filename::DEFAULT_TEMPLATE.to_string()
+}
+
+fn default_argon2_memory_kib() -> u32 {
+    argon2::Params::DEFAULT_M_COST
+}
+
+fn default_argon2_iterations() -> u32 {
+    argon2::Params::DEFAULT_T_COST
+}
+
+fn default_argon2_parallelism() -> u32 {
+    argon2::Params::DEFAULT_P_COST
+}
+
+fn default_password_min_length() -> usize {
+    8
+}
+
+fn default_password_max_length() -> usize {
+    32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -602,6 +1394,7 @@ root_path = "/books"
         assert_eq!(config.database.url, "sqlite://ropds.db");
         assert_eq!(config.database.max_connections, 5);
         assert_eq!(config.opds.max_items, 30);
+        assert_eq!(config.opds.dump_page_size, 500);
         assert!(config.opds.auth_required);
         assert_eq!(config.web.language, "en");
         assert!(config.reader.enable);
@@ -653,6 +1446,8 @@ skip_unchanged = true
 test_zip = true
 test_files = true
 workers_num = 4
+exclude = ["**/.stversions/**"]
+max_depth = 16
 
 [web]
 language = "ru"
@@ -681,6 +1476,8 @@ read_history_max = 50
         assert!(config.scanner.skip_unchanged);
         assert!(config.scanner.test_zip);
         assert!(config.scanner.test_files);
+        assert_eq!(config.scanner.exclude, vec!["**/.stversions/**"]);
+        assert_eq!(config.scanner.max_depth, 16);
         assert_eq!(config.scanner.workers_num, 4);
         assert_eq!(config.web.language, "ru");
         assert_eq!(config.web.theme, "dark");
@@ -825,6 +1622,231 @@ max_connections = 0
         assert!(matches!(config.validate(), Err(ConfigError::Validation(_))));
     }
 
+    #[test]
+    fn test_server_request_limits() {
+        let toml_for = |extra: &str| {
+            format!(
+                "[server]\nbase_url = \"http://127.0.0.1:8081\"\n{extra}\n[library]\nroot_path = \"/books\"\n[database]\n[opds]\n[scanner]\n"
+            )
+        };
+        let config: Config = toml::from_str(&toml_for("")).unwrap();
+        assert_eq!(config.server.request_timeout_secs, 60);
+        assert_eq!(config.server.opds_body_limit_kb, 64);
+        assert_eq!(config.server.web_body_limit_kb, 256);
+        assert_eq!(config.server.admin_body_limit_kb, 2048);
+        assert_eq!(config.server.min_free_space_mb, 256);
+
+        let config: Config = toml::from_str(&toml_for("request_timeout_secs = 0")).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&toml_for("web_body_limit_kb = 0")).unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_formats_section() {
+        let toml_for = |mime: &str| {
+            format!(
+                "[server]\nbase_url = \"http://127.0.0.1:8081\"\n[library]\nroot_path = \"/books\"\n[database]\n[opds]\n[scanner]\n[formats.cbz]\nmime = \"{mime}\"\nzip = false\n"
+            )
+        };
+        let config: Config = toml::from_str(&toml_for("application/vnd.comicbook+zip")).unwrap();
+        assert!(config.validate().is_ok());
+        let cbz = &config.formats["cbz"];
+        assert_eq!(cbz.zip, Some(false));
+        assert!(cbz.name.is_none());
+
+        let config: Config = toml::from_str(&toml_for("not a mime")).unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_clients_section() {
+        let toml = "[server]\nbase_url = \"http://127.0.0.1:8081\"\n[library]\nroot_path = \"/books\"\n[database]\n[opds]\n[scanner]\n[clients.koreader]\nuser_agent = [\"KOReader\"]\nabsolute_urls = true\nmax_items = 10\n";
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.validate().is_ok());
+        let ko = &config.clients["koreader"];
+        assert_eq!(
+            ko.user_agent.as_deref(),
+            Some(&["KOReader".to_string()][..])
+        );
+        assert_eq!(ko.max_items, Some(10));
+        assert!(ko.facets.is_none());
+    }
+
+    #[test]
+    fn test_low_memory_profile_caps_pools() {
+        let toml_for = |profile: &str| {
+            format!(
+                "[server]\nbase_url = \"http://127.0.0.1:8081\"\nprofile = \"{profile}\"\n[library]\nroot_path = \"/books\"\n[database]\nmax_connections = 8\n[opds]\n[scanner]\nworkers_num = 4\n"
+            )
+        };
+        let mut config: Config = toml::from_str(&toml_for("default")).unwrap();
+        config.apply_profile();
+        assert!(!config.low_memory());
+        assert_eq!(config.database.max_connections, 8);
+        assert_eq!(config.scanner.workers_num, 4);
+
+        let mut config: Config = toml::from_str(&toml_for("low-memory")).unwrap();
+        config.apply_profile();
+        assert!(config.low_memory());
+        assert_eq!(config.database.max_connections, LOW_MEMORY_MAX_CONNECTIONS);
+        assert_eq!(config.scanner.workers_num, 1);
+
+        assert!(toml::from_str::<Config>(&toml_for("tiny")).is_err());
+    }
+
+    #[test]
+    fn test_validate_remote_library_root() {
+        let toml_for = |root: &str, upload: bool| {
+            format!(
+                "[server]\nbase_url = \"http://127.0.0.1:8081\"\n[library]\nroot_path = \"{root}\"\n[database]\n[opds]\n[scanner]\n[upload]\nallow_upload = {upload}\n"
+            )
+        };
+        let config: Config = toml::from_str(&toml_for("webdavs://nas/books", false)).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&toml_for("sftp://nas/books", false)).unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Validation(_))));
+
+        let config: Config = toml::from_str(&toml_for("webdavs://nas/books", true)).unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_read_only_disables_uploads() {
+        let toml_str = r#"
+[server]
+base_url = "http://127.0.0.1:8081"
+read_only = true
+[library]
+root_path = "/books"
+[database]
+[opds]
+[scanner]
+[upload]
+allow_upload = true
+upload_path = "/uploads"
+"#;
+        let mut config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.server.read_only);
+        config.apply_read_only();
+        assert!(!config.upload.allow_upload);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let base = r#"
+[server]
+base_url = "http://127.0.0.1:8081"
+[library]
+root_path = "/books"
+[database]
+[opds]
+[scanner]
+"#;
+        let config: Config = toml::from_str(base).unwrap();
+        assert_eq!(config.password.min_length, 8);
+        assert!(config.password.required_classes.is_empty());
+
+        let config: Config = toml::from_str(&format!(
+            "{base}[password]\nmin_length = 12\nrequired_classes = [\"upper\", \"symbol\"]\n"
+        ))
+        .unwrap();
+        assert_eq!(
+            config.password.required_classes,
+            vec![CharClass::Upper, CharClass::Symbol]
+        );
+        assert!(
+            toml::from_str::<Config>(&format!(
+                "{base}[password]\nrequired_classes = [\"emoji\"]\n"
+            ))
+            .is_err()
+        );
+
+        let mut config: Config = toml::from_str(base).unwrap();
+        assert!(config.validate().is_ok());
+        config.password.min_length = 40;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_upload_publish_strategy() {
+        let base = r#"
+[server]
+base_url = "http://127.0.0.1:8081"
+[library]
+root_path = "/books"
+[database]
+[opds]
+[scanner]
+"#;
+        let config: Config = toml::from_str(base).unwrap();
+        assert_eq!(config.upload.publish_strategy, PublishStrategy::Hardlink);
+
+        let config: Config =
+            toml::from_str(&format!("{base}[upload]\npublish_strategy = \"move\"\n")).unwrap();
+        assert_eq!(config.upload.publish_strategy, PublishStrategy::Move);
+        assert!(
+            toml::from_str::<Config>(&format!("{base}[upload]\npublish_strategy = \"symlink\"\n"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_web_theme() {
+        let toml_for = |theme: &str| {
+            format!(
+                "[server]\nbase_url = \"http://127.0.0.1:8081\"\n[library]\nroot_path = \"/books\"\n[database]\n[opds]\n[scanner]\n[web]\ntheme = \"{theme}\"\n"
+            )
+        };
+        let config: Config = toml::from_str(&toml_for("auto")).unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(&toml_for("sepia")).unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_validate_download_filename_template() {
+        let toml_for = |template: &str| {
+            format!(
+                "[server]\nbase_url = \"http://127.0.0.1:8081\"\n[library]\nroot_path = \"/books\"\n[database]\n[opds]\n[scanner]\n[download]\nfilename_template = \"{template}\"\n"
+            )
+        };
+        let config: Config = toml::from_str(&toml_for("{author} - {title}.{ext}")).unwrap();
+        assert!(config.validate().is_ok());
+
+        for template in ["{author}.{ext}", "{title} {publisher}", "{title"] {
+            let config: Config = toml::from_str(&toml_for(template)).unwrap();
+            assert!(
+                matches!(config.validate(), Err(ConfigError::Validation(_))),
+                "{template} should be refused"
+            );
+        }
+    }
+
+    #[test]
+    fn test_opds_catalog_deep_override() {
+        let toml_for = |deep: bool| {
+            format!(
+                "[server]\nbase_url = \"http://127.0.0.1:8081\"\n[library]\nroot_path = \"/books\"\n[database]\n[opds]\ndeep_catalogs = {deep}\n[scanner]\n"
+            )
+        };
+        let shallow: Config = toml::from_str(&toml_for(false)).unwrap();
+        assert!(!shallow.opds.catalog_deep(None));
+        assert!(shallow.opds.catalog_deep(Some("1")));
+        assert!(!shallow.opds.catalog_deep(Some("bogus")));
+        assert!(shallow.opds.auto_bookshelf);
+        assert!(!shallow.opds.absolute_urls);
+
+        let deep: Config = toml::from_str(&toml_for(true)).unwrap();
+        assert!(deep.opds.catalog_deep(None));
+        assert!(!deep.opds.catalog_deep(Some("0")));
+        assert!(deep.opds.catalog_deep(Some("")));
+    }
+
     #[test]
     fn test_reader_offline_default_when_section_missing() {
         let toml_src = r#"
@@ -856,4 +1878,191 @@ cached_books_max = 0
         let cfg: Config = toml::from_str(toml_src).unwrap();
         assert_eq!(cfg.reader.offline.cached_books_max, 0);
     }
+
+    #[test]
+    fn test_tools_config_defaults_and_override() {
+        let base = r#"
+[server]
+base_url = "http://localhost:8081"
+[library]
+root_path = "/tmp"
+[database]
+[opds]
+[scanner]
+"#;
+        let cfg: Config = toml::from_str(base).unwrap();
+        assert_eq!(cfg.tools.pdftoppm_path, PathBuf::from("pdftoppm"));
+        assert_eq!(cfg.tools.pdfinfo_path, PathBuf::from("pdfinfo"));
+        assert_eq!(cfg.tools.ddjvu_path, PathBuf::from("ddjvu"));
+        assert_eq!(cfg.tools.pdftotext_path, PathBuf::from("pdftotext"));
+        assert_eq!(cfg.tools.timeout_secs, 60);
+        assert_eq!(cfg.tools.pdf_text_pages, 100);
+        assert_eq!(cfg.tools.pdf_text_timeout_secs, 20);
+
+        let custom = format!(
+            "{base}[tools]\npdftoppm_path = \"/opt/poppler/bin/pdftoppm\"\ntimeout_secs = 5\n"
+        );
+        let cfg: Config = toml::from_str(&custom).unwrap();
+        assert_eq!(
+            cfg.tools.pdftoppm_path,
+            PathBuf::from("/opt/poppler/bin/pdftoppm")
+        );
+        assert_eq!(cfg.tools.pdfinfo_path, PathBuf::from("pdfinfo"));
+        assert_eq!(cfg.tools.timeout_secs, 5);
+        assert!(cfg.validate().is_ok());
+
+        let zero = format!("{base}[tools]\ntimeout_secs = 0\n");
+        let cfg: Config = toml::from_str(&zero).unwrap();
+        assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+        let zero = format!("{base}[tools]\npdf_text_pages = 0\n");
+        let cfg: Config = toml::from_str(&zero).unwrap();
+        assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_scanner_exclude_validation() {
+        let base = r#"
+[server]
+base_url = "http://localhost:8081"
+[library]
+root_path = "/tmp"
+[database]
+[opds]
+"#;
+        let cfg: Config = toml::from_str(&format!("{base}[scanner]\n")).unwrap();
+        assert!(cfg.scanner.exclude.is_empty());
+        assert_eq!(cfg.scanner.max_depth, 64);
+
+        let flat = format!("{base}[scanner]\nmax_depth = 0\n");
+        let cfg: Config = toml::from_str(&flat).unwrap();
+        assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+
+        let ok = format!("{base}[scanner]\nexclude = [\"**/samples/**\", \"*.tmp\"]\n");
+        let cfg: Config = toml::from_str(&ok).unwrap();
+        assert!(cfg.validate().is_ok());
+
+        let bad = format!("{base}[scanner]\nexclude = [\"shelf/[a\"]\n");
+        let cfg: Config = toml::from_str(&bad).unwrap();
+        assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_search_translit_default_and_override() {
+        let base = r#"
+[server]
+base_url = "http://localhost:8081"
+[library]
+root_path = "/tmp"
+[database]
+[opds]
+[scanner]
+"#;
+        let cfg: Config = toml::from_str(base).unwrap();
+        assert!(!cfg.search.translit);
+
+        let enabled = format!("{base}[search]\ntranslit = true\n");
+        let cfg: Config = toml::from_str(&enabled).unwrap();
+        assert!(cfg.search.translit);
+    }
+
+    #[test]
+    fn test_search_fuzzy_threshold_validation() {
+        let base = r#"
+[server]
+base_url = "http://localhost:8081"
+[library]
+root_path = "/tmp"
+[database]
+[opds]
+[scanner]
+"#;
+        let cfg: Config = toml::from_str(base).unwrap();
+        assert!(!cfg.search.fuzzy);
+        assert_eq!(cfg.search.fuzzy_threshold, 0.3);
+
+        let custom = format!("{base}[search]\nfuzzy = true\nfuzzy_threshold = 0.5\n");
+        let cfg: Config = toml::from_str(&custom).unwrap();
+        assert!(cfg.search.fuzzy);
+        assert_eq!(cfg.search.fuzzy_threshold, 0.5);
+        assert!(cfg.validate().is_ok());
+
+        for bad in ["0", "1.5", "-0.2"] {
+            let src = format!("{base}[search]\nfuzzy_threshold = {bad}\n");
+            let cfg: Config = toml::from_str(&src).unwrap();
+            assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+        }
+    }
+
+    #[test]
+    fn test_search_fulltext_options() {
+        let base = r#"
+[server]
+base_url = "http://localhost:8081"
+[library]
+root_path = "/tmp"
+[database]
+[opds]
+[scanner]
+"#;
+        let cfg: Config = toml::from_str(base).unwrap();
+        assert!(!cfg.search.fulltext);
+        assert_eq!(cfg.search.fulltext_max_kb, 1024);
+
+        let custom = format!("{base}[search]\nfulltext = true\nfulltext_max_kb = 64\n");
+        let cfg: Config = toml::from_str(&custom).unwrap();
+        assert!(cfg.search.fulltext);
+        assert_eq!(cfg.search.fulltext_max_kb, 64);
+        assert!(cfg.validate().is_ok());
+
+        let bad = format!("{base}[search]\nfulltext = true\nfulltext_max_kb = 0\n");
+        let cfg: Config = toml::from_str(&bad).unwrap();
+        assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_library_detect_lang_options() {
+        let base = r#"
+[server]
+base_url = "http://localhost:8081"
+[library]
+root_path = "/tmp"
+"#;
+        let tail = "[database]\n[opds]\n[scanner]\n";
+        let cfg: Config = toml::from_str(&format!("{base}{tail}")).unwrap();
+        assert_eq!(cfg.library.lang_detect_threshold(), Some(0.3));
+
+        let off = format!("{base}detect_lang = false\n{tail}");
+        let cfg: Config = toml::from_str(&off).unwrap();
+        assert_eq!(cfg.library.lang_detect_threshold(), None);
+
+        for bad in ["1.5", "-0.2"] {
+            let src = format!("{base}detect_lang_confidence = {bad}\n{tail}");
+            let cfg: Config = toml::from_str(&src).unwrap();
+            assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+        }
+    }
+
+    #[test]
+    fn test_web_branding_options() {
+        let base = "[server]\nbase_url = \"http://localhost:8081\"\n[library]\nroot_path = \"/tmp\"\n[database]\n[opds]\ntitle = \"Home Library\"\n[scanner]\n";
+        let cfg: Config = toml::from_str(base).unwrap();
+        assert_eq!(cfg.site_name(), "Home Library");
+        assert_eq!(cfg.web.accent_rgb(), None);
+
+        let branded = format!("{base}[web]\nsite_name = \"Kids\"\naccent_color = \"#1a73e8\"\n");
+        let cfg: Config = toml::from_str(&branded).unwrap();
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.site_name(), "Kids");
+        assert_eq!(cfg.web.accent_rgb(), Some([0x1a, 0x73, 0xe8]));
+
+        let short = format!("{base}[web]\naccent_color = \"#f80\"\n");
+        let cfg: Config = toml::from_str(&short).unwrap();
+        assert_eq!(cfg.web.accent_rgb(), Some([0xff, 0x88, 0x00]));
+
+        for bad in ["blue", "#12345", "#ggg"] {
+            let src = format!("{base}[web]\naccent_color = \"{bad}\"\n");
+            let cfg: Config = toml::from_str(&src).unwrap();
+            assert!(matches!(cfg.validate(), Err(ConfigError::Validation(_))));
+        }
+    }
 }
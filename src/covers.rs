@@ -0,0 +1,613 @@
+//! Cover image processing shared by the scanner and the cover endpoints:
+//! bounded decoding, EXIF orientation, colour conversion and JPEG re-encoding,
+//! the dominant colour palette used to tint cover placeholders, and the
+//! generated placeholder of books without a cover.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageReader, Limits};
+
+use crate::config::CoverImageConfig;
+use crate::db::DbPool;
+use crate::db::queries::books;
+
+/// Embedded covers larger than this are dropped without decoding.
+pub const MAX_COVER_INPUT_BYTES: usize = 32 * 1024 * 1024;
+/// Largest accepted width/height of a cover before decoding.
+const MAX_COVER_SIDE_PX: u32 = 16_384;
+/// Upper bound for decoder allocations (decoded pixels included).
+const MAX_DECODE_ALLOC_BYTES: u64 = 256 * 1024 * 1024;
+/// Colours kept in a cover palette.
+const PALETTE_COLORS: usize = 3;
+/// Side of the square a cover is shrunk to before its colours are counted.
+const PALETTE_SAMPLE_PX: u32 = 32;
+/// Smallest squared RGB distance between two colours of a palette.
+const PALETTE_MIN_DISTANCE: u32 = 48 * 48;
+/// Covers read per palette backfill batch.
+const PALETTE_BATCH: i32 = 200;
+/// Background colours of generated placeholders, picked by title and author.
+const PLACEHOLDER_COLORS: [&str; 8] = [
+    "#1d3557", "#2a6f97", "#40916c", "#6a4c93", "#9d4edd", "#b5651d", "#c44536", "#495057",
+];
+
+/// Decode a cover image within size limits and apply its EXIF orientation.
+pub fn decode(data: &[u8]) -> Result<DynamicImage, CoverError> {
+    if data.len() > MAX_COVER_INPUT_BYTES {
+        return Err(CoverError::TooLarge(data.len()));
+    }
+    let mut decoder = open_decoder(data)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(CoverError::from_image)?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+/// Prepare a cover for storage: downscale to `cover_max_dimension_px`, upright it
+/// and store as RGB JPEG. Small, upright RGB JPEGs are kept byte-for-byte.
+///
+/// Data in formats we cannot decode is kept as is; `None` means the cover should
+/// be dropped (oversized input or decode limits exceeded).
+pub fn normalize_for_storage(
+    data: &[u8],
+    mime: &str,
+    cover_cfg: CoverImageConfig,
+) -> Option<(Vec<u8>, String)> {
+    let max_dimension_px = cover_cfg.scale_to();
+    let original = || Some((data.to_vec(), normalize_mime(mime).to_string()));
+
+    if data.len() > MAX_COVER_INPUT_BYTES {
+        tracing::warn!(
+            "Dropping cover: {} bytes exceeds the {} byte limit",
+            data.len(),
+            MAX_COVER_INPUT_BYTES
+        );
+        return None;
+    }
+
+    let mut decoder = match open_decoder(data) {
+        Ok(decoder) => decoder,
+        // Keep original bytes if decoder can't parse this format.
+        Err(CoverError::Decode(_)) => return original(),
+        Err(e) => {
+            tracing::warn!("Dropping cover: {e}");
+            return None;
+        }
+    };
+
+    let (w, h) = decoder.dimensions();
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let is_jpeg = matches!(mime, "image/jpeg" | "image/jpg" | "image/pjpeg");
+    let needs_resize = w.max(h) > max_dimension_px;
+    let needs_rotation = orientation != Orientation::NoTransforms;
+
+    if is_jpeg && !needs_resize && !needs_rotation && !is_cmyk_jpeg(data) {
+        return original();
+    }
+
+    let mut img = match DynamicImage::from_decoder(decoder).map_err(CoverError::from_image) {
+        Ok(img) => img,
+        Err(CoverError::Decode(_)) => return original(),
+        Err(e) => {
+            tracing::warn!("Dropping cover: {e}");
+            return None;
+        }
+    };
+    img.apply_orientation(orientation);
+
+    let processed = if img.width().max(img.height()) > max_dimension_px {
+        img.resize(max_dimension_px, max_dimension_px, FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    // Store decodable covers as JPEG to ensure uniform quality/size handling.
+    match encode_jpeg(&processed, cover_cfg.jpeg_quality()) {
+        Ok(bytes) => Some((bytes, "image/jpeg".to_string())),
+        // Fallback if encoding fails for any reason.
+        Err(_) => original(),
+    }
+}
+
+/// Width and height of an image, read from its header.
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    open_decoder(data).ok().map(|decoder| decoder.dimensions())
+}
+
+/// Dominant colours of a cover as up to three `#rrggbb` values separated by
+/// spaces, most common first. Pixels of a downscaled copy are grouped by
+/// colour (3 bits per channel) and each group stands for its mean colour;
+/// groups too close to a colour already taken are skipped. `None` when the
+/// image cannot be decoded.
+pub fn palette(data: &[u8]) -> Option<String> {
+    let sample = decode(data)
+        .ok()?
+        .thumbnail_exact(PALETTE_SAMPLE_PX, PALETTE_SAMPLE_PX)
+        .to_rgb8();
+    let mut groups: HashMap<[u8; 3], (u32, [u32; 3])> = HashMap::new();
+    for px in sample.pixels() {
+        let (count, sum) = groups.entry(px.0.map(|c| c >> 5)).or_default();
+        *count += 1;
+        for (total, c) in sum.iter_mut().zip(px.0) {
+            *total += u32::from(c);
+        }
+    }
+    let mut groups: Vec<(u32, [u8; 3])> = groups
+        .into_values()
+        .map(|(count, sum)| (count, sum.map(|total| (total / count) as u8)))
+        .collect();
+    groups.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    for (_, color) in groups {
+        let distance = |other: &[u8; 3]| -> u32 {
+            color
+                .iter()
+                .zip(other)
+                .map(|(&a, &b)| u32::from(a.abs_diff(b)).pow(2))
+                .sum()
+        };
+        if colors.iter().all(|c| distance(c) >= PALETTE_MIN_DISTANCE) {
+            colors.push(color);
+            if colors.len() == PALETTE_COLORS {
+                break;
+            }
+        }
+    }
+    let hex: Vec<String> = colors
+        .iter()
+        .map(|[r, g, b]| format!("#{r:02x}{g:02x}{b:02x}"))
+        .collect();
+    Some(hex.join(" "))
+}
+
+/// The well-formed `#rrggbb` colours of a stored palette, most common first.
+pub fn palette_colors(palette: &str) -> Vec<&str> {
+    palette
+        .split_whitespace()
+        .filter(|c| {
+            c.len() == 7 && c.starts_with('#') && c[1..].bytes().all(|b| b.is_ascii_hexdigit())
+        })
+        .collect()
+}
+
+/// Compute the palettes of stored covers that have none (covers stored
+/// before palettes existed). Covers that cannot be read or decoded are
+/// passed over and tried again on the next run. Returns the number of
+/// palettes stored.
+pub async fn backfill_palettes(pool: &DbPool, covers_path: &Path) -> Result<u64, sqlx::Error> {
+    let mut after_id = 0;
+    let mut updated = 0;
+    loop {
+        let ids = books::ids_without_cover_palette(pool, after_id, PALETTE_BATCH).await?;
+        let Some(&last) = ids.last() else {
+            break;
+        };
+        after_id = last;
+        let covers_path: PathBuf = covers_path.to_path_buf();
+        let palettes = tokio::task::spawn_blocking(move || {
+            ids.into_iter()
+                .filter_map(|id| {
+                    let data = crate::scanner::read_cover(&covers_path, id)?;
+                    Some((id, palette(&data)?))
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .unwrap_or_default();
+        for (id, palette) in palettes {
+            books::set_cover_palette(pool, id, &palette).await?;
+            updated += 1;
+        }
+    }
+    Ok(updated)
+}
+
+/// Size of a `width`×`height` image resized by [`thumbnail`] to `size`.
+pub fn thumbnail_dimensions(width: u32, height: u32, size: u32) -> (u32, u32) {
+    let ratio = f64::min(
+        f64::from(size) / f64::from(width.max(1)),
+        f64::from(size) / f64::from(height.max(1)),
+    );
+    let scale = |side: u32| ((f64::from(side) * ratio).round() as u32).max(1);
+    (scale(width), scale(height))
+}
+
+/// Resize a cover to fit into `size`×`size`, preserving aspect ratio, as JPEG.
+pub fn thumbnail(data: &[u8], size: u32, quality: u8) -> Result<Vec<u8>, CoverError> {
+    let img = decode(data)?;
+    encode_jpeg(&img.resize(size, size, FilterType::Lanczos3), quality)
+}
+
+/// Compose up to four covers into a `size`×`size` JPEG mosaic: one cover fills
+/// the square, two sit side by side, three or four form a 2×2 grid (an empty
+/// fourth tile stays grey). Each cover is cropped to fill its tile; covers
+/// that fail to decode are skipped.
+pub fn mosaic(covers: &[Vec<u8>], size: u32, quality: u8) -> Result<Vec<u8>, CoverError> {
+    let images: Vec<DynamicImage> = covers
+        .iter()
+        .filter_map(|c| decode(c).ok())
+        .take(4)
+        .collect();
+    let half = size / 2;
+    let tiles: &[(u32, u32, u32, u32)] = match images.len() {
+        0 => return Err(CoverError::Empty),
+        1 => &[(0, 0, size, size)],
+        2 => &[(0, 0, half, size), (half, 0, size - half, size)],
+        _ => &[
+            (0, 0, half, half),
+            (half, 0, size - half, half),
+            (0, half, half, size - half),
+            (half, half, size - half, size - half),
+        ],
+    };
+    let mut canvas = image::RgbImage::from_pixel(size, size, image::Rgb([200, 200, 200]));
+    for (img, &(x, y, w, h)) in images.iter().zip(tiles) {
+        let tile = img.resize_to_fill(w, h, FilterType::Triangle).to_rgb8();
+        image::imageops::replace(&mut canvas, &tile, i64::from(x), i64::from(y));
+    }
+    encode_jpeg(&DynamicImage::ImageRgb8(canvas), quality)
+}
+
+fn open_decoder(data: &[u8]) -> Result<impl ImageDecoder + '_, CoverError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_COVER_SIDE_PX);
+    limits.max_image_height = Some(MAX_COVER_SIDE_PX);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC_BYTES);
+
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| CoverError::Decode(ImageError::IoError(e)))?;
+    reader.limits(limits);
+    reader.into_decoder().map_err(CoverError::from_image)
+}
+
+/// Encode as baseline RGB JPEG. Alpha is flattened onto white and
+/// grayscale/16-bit images are converted, since the encoder only takes 8-bit RGB/Luma.
+fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, CoverError> {
+    let rgb = if img.color().has_alpha() {
+        let mut rgba = img.to_rgba8();
+        for px in rgba.pixels_mut() {
+            let alpha = u16::from(px[3]);
+            for c in 0..3 {
+                px[c] = ((u16::from(px[c]) * alpha + 255 * (255 - alpha)) / 255) as u8;
+            }
+        }
+        DynamicImage::ImageRgba8(rgba).to_rgb8()
+    } else {
+        img.to_rgb8()
+    };
+
+    let mut out = Cursor::new(Vec::new());
+    let mut encoder = JpegEncoder::new_with_quality(&mut out, quality);
+    encoder.encode_image(&rgb).map_err(CoverError::Encode)?;
+    Ok(out.into_inner())
+}
+
+/// True for JPEGs with four colour components (CMYK/YCCK). They decode to RGB,
+/// but many readers show them with inverted or wrong colours, so they are re-encoded.
+fn is_cmyk_jpeg(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return false;
+        }
+        let marker = data[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let len = usize::from(u16::from_be_bytes([data[pos + 2], data[pos + 3]]));
+        // SOF0..SOF15 except DHT (C4), JPG (C8) and DAC (CC)
+        if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            // length(2) precision(1) height(2) width(2) components(1)
+            return data.get(pos + 9) == Some(&4);
+        }
+        if marker == 0xDA {
+            return false;
+        }
+        pos += 2 + len;
+    }
+    false
+}
+
+/// Placeholder cover of a book without one: the initials of the title and
+/// of the first author on a colour derived from both, as SVG.
+pub fn placeholder_svg(title: &str, author: &str) -> String {
+    let hash = format!("{title}\u{0}{author}")
+        .bytes()
+        .fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(u32::from(b)));
+    let color = PLACEHOLDER_COLORS[hash as usize % PLACEHOLDER_COLORS.len()];
+    format!(
+        concat!(
+            r##"<svg width="120" height="180" viewBox="0 0 120 180" xmlns="http://www.w3.org/2000/svg">"##,
+            r##"<rect width="120" height="180" rx="6" fill="{color}"/>"##,
+            r##"<rect x="10" y="10" width="100" height="160" rx="3" fill="none" stroke="#ffffff" stroke-opacity="0.35"/>"##,
+            r##"<g font-family="Segoe UI, Roboto, Helvetica, Arial, sans-serif" fill="#ffffff" text-anchor="middle">"##,
+            r##"<text x="60" y="100" font-size="44" font-weight="600">{title}</text>"##,
+            r##"<text x="60" y="145" font-size="16" fill-opacity="0.8">{author}</text>"##,
+            "</g></svg>"
+        ),
+        color = color,
+        title = initials(title),
+        author = initials(author),
+    )
+}
+
+/// Upper-cased first letters of the first two words, letters and digits only.
+fn initials(text: &str) -> String {
+    text.split_whitespace()
+        .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()))
+        .take(2)
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+fn normalize_mime(mime: &str) -> &str {
+    match mime {
+        "image/png" => "image/png",
+        "image/gif" => "image/gif",
+        _ => "image/jpeg",
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CoverError {
+    #[error("cover is too large ({0} bytes)")]
+    TooLarge(usize),
+    #[error("cover exceeds decode limits: {0}")]
+    Limits(ImageError),
+    #[error("failed to decode cover: {0}")]
+    Decode(ImageError),
+    #[error("failed to encode JPEG: {0}")]
+    Encode(ImageError),
+    #[error("no covers to compose")]
+    Empty,
+}
+
+impl CoverError {
+    fn from_image(e: ImageError) -> Self {
+        match e {
+            ImageError::Limits(_) => Self::Limits(e),
+            other => Self::Decode(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    fn test_cover_cfg() -> CoverImageConfig {
+        CoverImageConfig::new(0, 0)
+    }
+
+    fn encode(img: &DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        img.write_to(&mut out, format).unwrap();
+        out.into_inner()
+    }
+
+    /// Insert an APP1 Exif segment with the given orientation right after SOI.
+    fn with_exif_orientation(jpeg: &[u8], orientation: u16) -> Vec<u8> {
+        let mut tiff = b"MM\x00\x2A\x00\x00\x00\x08".to_vec();
+        tiff.extend_from_slice(&1u16.to_be_bytes()); // one IFD entry
+        tiff.extend_from_slice(&0x0112u16.to_be_bytes()); // Orientation
+        tiff.extend_from_slice(&3u16.to_be_bytes()); // SHORT
+        tiff.extend_from_slice(&1u32.to_be_bytes());
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0]);
+        tiff.extend_from_slice(&0u32.to_be_bytes()); // no next IFD
+
+        let mut app1 = b"Exif\x00\x00".to_vec();
+        app1.extend_from_slice(&tiff);
+
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(&app1);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    #[test]
+    fn test_normalize_converts_non_jpeg_and_resizes_when_needed() {
+        let small_bytes = encode(&DynamicImage::new_rgb8(320, 480), image::ImageFormat::Png);
+        let cfg = test_cover_cfg();
+        let (converted_data, converted_mime) =
+            normalize_for_storage(&small_bytes, "image/png", cfg).unwrap();
+        assert_eq!(converted_mime, "image/jpeg");
+        assert_ne!(converted_data, small_bytes);
+        assert!(matches!(
+            image::guess_format(&converted_data),
+            Ok(image::ImageFormat::Jpeg)
+        ));
+
+        let large_png = encode(&DynamicImage::new_rgb8(1800, 1200), image::ImageFormat::Png);
+        let (resized_data, resized_mime) =
+            normalize_for_storage(&large_png, "image/png", cfg).unwrap();
+        assert_eq!(resized_mime, "image/jpeg");
+        let resized = image::load_from_memory(&resized_data).unwrap();
+        let (w, h) = resized.dimensions();
+        assert_eq!(w.max(h), cfg.scale_to());
+    }
+
+    #[test]
+    fn test_normalize_converts_gif_to_jpeg() {
+        let gif_1x1 = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\
+\x00\x00\x00\xff\xff\xff!\xf9\x04\x01\x00\x00\x00\x00,\
+\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02D\x01\x00;";
+        let (converted_data, converted_mime) =
+            normalize_for_storage(gif_1x1, "image/gif", test_cover_cfg()).unwrap();
+        assert_eq!(converted_mime, "image/jpeg");
+        assert!(matches!(
+            image::guess_format(&converted_data),
+            Ok(image::ImageFormat::Jpeg)
+        ));
+    }
+
+    #[test]
+    fn test_normalize_keeps_small_upright_jpeg() {
+        let jpeg = encode(&DynamicImage::new_rgb8(200, 300), image::ImageFormat::Jpeg);
+        let (data, mime) = normalize_for_storage(&jpeg, "image/jpeg", test_cover_cfg()).unwrap();
+        assert_eq!(data, jpeg);
+        assert_eq!(mime, "image/jpeg");
+    }
+
+    #[test]
+    fn test_normalize_flattens_transparent_png() {
+        let mut rgba = image::RgbaImage::new(10, 10);
+        for px in rgba.pixels_mut() {
+            *px = image::Rgba([0, 0, 0, 0]);
+        }
+        let png = encode(&DynamicImage::ImageRgba8(rgba), image::ImageFormat::Png);
+        let (data, mime) = normalize_for_storage(&png, "image/png", test_cover_cfg()).unwrap();
+        assert_eq!(mime, "image/jpeg");
+        let decoded = image::load_from_memory(&data).unwrap().to_rgb8();
+        // Fully transparent pixels become white, not black.
+        assert!(decoded.get_pixel(5, 5).0.iter().all(|&c| c > 240));
+    }
+
+    #[test]
+    fn test_normalize_applies_exif_orientation() {
+        let jpeg = encode(&DynamicImage::new_rgb8(40, 20), image::ImageFormat::Jpeg);
+        let rotated = with_exif_orientation(&jpeg, 6);
+
+        let (data, mime) = normalize_for_storage(&rotated, "image/jpeg", test_cover_cfg()).unwrap();
+        assert_eq!(mime, "image/jpeg");
+        assert_eq!(
+            image::load_from_memory(&data).unwrap().dimensions(),
+            (20, 40)
+        );
+
+        assert_eq!(decode(&rotated).unwrap().dimensions(), (20, 40));
+    }
+
+    #[test]
+    fn test_oversized_input_is_dropped() {
+        let data = vec![0u8; MAX_COVER_INPUT_BYTES + 1];
+        assert!(normalize_for_storage(&data, "image/jpeg", test_cover_cfg()).is_none());
+        assert!(matches!(decode(&data), Err(CoverError::TooLarge(_))));
+    }
+
+    #[test]
+    fn test_huge_dimensions_hit_decode_limits() {
+        // A valid PNG header announcing a 20000x20000 image with no pixel data.
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&20_000u32.to_be_bytes());
+        png.extend_from_slice(&20_000u32.to_be_bytes());
+        png.extend_from_slice(&[8, 2, 0, 0, 0]);
+        png.extend_from_slice(&[0x6c, 0x12, 0xd1, 0x6e]); // IHDR CRC
+
+        assert!(matches!(decode(&png), Err(CoverError::Limits(_))));
+        assert!(normalize_for_storage(&png, "image/png", test_cover_cfg()).is_none());
+    }
+
+    #[test]
+    fn test_undecodable_data_is_kept() {
+        let (data, mime) = normalize_for_storage(b"cover", "image/png", test_cover_cfg()).unwrap();
+        assert_eq!(data, b"cover");
+        assert_eq!(mime, "image/png");
+    }
+
+    #[test]
+    fn test_is_cmyk_jpeg() {
+        // SOI, APP0 stub, SOF0 with four components
+        let cmyk = b"\xFF\xD8\xFF\xE0\x00\x04\x00\x00\xFF\xC0\x00\x14\x08\x00\x10\x00\x10\x04";
+        assert!(is_cmyk_jpeg(cmyk));
+
+        let rgb = encode(&DynamicImage::new_rgb8(8, 8), image::ImageFormat::Jpeg);
+        assert!(!is_cmyk_jpeg(&rgb));
+        assert!(!is_cmyk_jpeg(b"not a jpeg"));
+    }
+
+    #[test]
+    fn test_placeholder_svg() {
+        assert_eq!(initials("war and peace"), "WA");
+        assert_eq!(initials("«Ёлка» <b>"), "ЁB");
+        assert_eq!(initials(""), "");
+
+        let svg = placeholder_svg("War and Peace", "Leo Tolstoy");
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">WA</text>"));
+        assert!(svg.contains(">LT</text>"));
+        assert_eq!(svg, placeholder_svg("War and Peace", "Leo Tolstoy"));
+        assert!(
+            PLACEHOLDER_COLORS
+                .iter()
+                .any(|c| svg.contains(&format!("fill=\"{c}\"")))
+        );
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let png = encode(&DynamicImage::new_rgb8(300, 600), image::ImageFormat::Png);
+        let thumb = thumbnail(&png, 100, 85).unwrap();
+        let decoded = image::load_from_memory(&thumb).unwrap();
+        assert_eq!(decoded.dimensions(), (50, 100));
+        assert!(thumbnail(b"not-an-image", 100, 85).is_err());
+
+        assert_eq!(dimensions(&png), Some((300, 600)));
+        assert_eq!(dimensions(b"not-an-image"), None);
+        assert_eq!(thumbnail_dimensions(300, 600, 100), (50, 100));
+        assert_eq!(thumbnail_dimensions(40, 30, 200), (200, 150));
+    }
+
+    #[test]
+    fn test_palette_orders_colours_by_area() {
+        let mut img = image::RgbImage::from_pixel(60, 90, image::Rgb([200, 30, 30]));
+        for y in 60..90 {
+            for x in 0..60 {
+                img.put_pixel(x, y, image::Rgb([20, 40, 160]));
+            }
+        }
+        let png = encode(&DynamicImage::ImageRgb8(img), image::ImageFormat::Png);
+        let stored = palette(&png).unwrap();
+        let colors = palette_colors(&stored);
+        assert_eq!(colors.len(), 2, "{stored}");
+        assert_eq!(colors[0], "#c81e1e");
+        assert_eq!(colors[1], "#1428a0");
+        assert!(palette(b"not an image").is_none());
+
+        assert_eq!(
+            palette_colors("#aabbcc bad #12345g #001122"),
+            ["#aabbcc", "#001122"]
+        );
+        assert!(palette_colors("").is_empty());
+    }
+
+    #[test]
+    fn test_mosaic_tiles_covers() {
+        let red =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(60, 90, image::Rgb([255, 0, 0])));
+        let blue =
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(90, 60, image::Rgb([0, 0, 255])));
+        let covers = vec![
+            encode(&red, image::ImageFormat::Png),
+            b"not an image".to_vec(),
+            encode(&blue, image::ImageFormat::Png),
+            encode(&red, image::ImageFormat::Png),
+        ];
+
+        let img = decode(&mosaic(&covers, 100, 90).unwrap()).unwrap();
+        assert_eq!(img.dimensions(), (100, 100));
+        let px = |x, y| img.get_pixel(x, y).0;
+        assert!(px(25, 25)[0] > 200, "top left is red");
+        assert!(px(75, 25)[2] > 200, "top right is blue");
+        assert!(px(25, 75)[0] > 200, "bottom left is red");
+        assert!((190..=210).contains(&px(75, 75)[1]), "missing tile is grey");
+
+        assert!(matches!(
+            mosaic(&[b"junk".to_vec()], 100, 90),
+            Err(CoverError::Empty)
+        ));
+    }
+}
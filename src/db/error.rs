@@ -0,0 +1,171 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::config::DatabaseConfig;
+
+/// Coarse classification of a `sqlx::Error` across the three backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbErrorKind {
+    /// Lock contention: SQLite busy/locked, MySQL lock wait timeout,
+    /// PostgreSQL `lock_not_available`.
+    Busy,
+    /// Transaction rolled back by deadlock detection or a serialization
+    /// failure.
+    Deadlock,
+    /// No pooled connection became available in time.
+    PoolTimeout,
+    UniqueViolation,
+    ForeignKeyViolation,
+    Other,
+}
+
+impl DbErrorKind {
+    pub fn of(err: &sqlx::Error) -> Self {
+        let db_err = match err {
+            sqlx::Error::PoolTimedOut => return Self::PoolTimeout,
+            sqlx::Error::Database(db_err) => db_err,
+            _ => return Self::Other,
+        };
+        if db_err.is_unique_violation() {
+            return Self::UniqueViolation;
+        }
+        if db_err.is_foreign_key_violation() {
+            return Self::ForeignKeyViolation;
+        }
+        if let Some(mysql) = db_err.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() {
+            return match mysql.number() {
+                1205 => Self::Busy,
+                1213 => Self::Deadlock,
+                _ => Self::Other,
+            };
+        }
+        let Some(code) = db_err.code() else {
+            return Self::Other;
+        };
+        match code.as_ref() {
+            "40001" | "40P01" => Self::Deadlock,
+            "55P03" => Self::Busy,
+            // SQLite reports extended result codes; the low byte is the
+            // primary code (5 = SQLITE_BUSY, 6 = SQLITE_LOCKED).
+            code => match code.parse::<i32>() {
+                Ok(n) if matches!(n & 0xff, 5 | 6) => Self::Busy,
+                _ => Self::Other,
+            },
+        }
+    }
+
+    /// Whether re-running the whole operation may succeed.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Busy | Self::Deadlock | Self::PoolTimeout)
+    }
+}
+
+impl fmt::Display for DbErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Busy => "database busy",
+            Self::Deadlock => "deadlock",
+            Self::PoolTimeout => "pool timeout",
+            Self::UniqueViolation => "unique violation",
+            Self::ForeignKeyViolation => "foreign key violation",
+            Self::Other => "database error",
+        })
+    }
+}
+
+/// Bounded retry of write operations failing with a retryable error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Extra attempts after the first one; 0 disables retrying.
+    pub retries: u32,
+    /// Delay before the first retry, doubled on each further one.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+impl From<&DatabaseConfig> for RetryPolicy {
+    fn from(cfg: &DatabaseConfig) -> Self {
+        Self {
+            retries: cfg.retry,
+            backoff: Duration::from_millis(cfg.retry_backoff_ms),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (1-based).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    #[tokio::test]
+    async fn test_classifies_unique_violation() {
+        let pool = create_test_pool().await;
+        let sql = "INSERT INTO suppressed_books (path, filename) VALUES ('/lib', 'a.fb2')";
+        sqlx::query(sql).execute(pool.inner()).await.unwrap();
+        let err = sqlx::query(sql).execute(pool.inner()).await.unwrap_err();
+        assert_eq!(DbErrorKind::of(&err), DbErrorKind::UniqueViolation);
+        assert!(!DbErrorKind::of(&err).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_classifies_sqlite_busy() {
+        sqlx::any::install_default_drivers();
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}?mode=rwc", dir.path().join("busy.db").display());
+        let a = sqlx::AnyPool::connect(&url).await.unwrap();
+        let b = sqlx::AnyPool::connect(&url).await.unwrap();
+        sqlx::query("CREATE TABLE t (x INTEGER)")
+            .execute(&a)
+            .await
+            .unwrap();
+
+        let mut lock = a.acquire().await.unwrap();
+        sqlx::query("BEGIN EXCLUSIVE")
+            .execute(&mut *lock)
+            .await
+            .unwrap();
+        let mut conn = b.acquire().await.unwrap();
+        sqlx::query("PRAGMA busy_timeout=0")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let err = sqlx::query("INSERT INTO t (x) VALUES (1)")
+            .execute(&mut *conn)
+            .await
+            .unwrap_err();
+        assert_eq!(DbErrorKind::of(&err), DbErrorKind::Busy);
+        assert!(DbErrorKind::of(&err).is_retryable());
+    }
+
+    #[test]
+    fn test_pool_timeout_is_retryable() {
+        assert!(DbErrorKind::of(&sqlx::Error::PoolTimedOut).is_retryable());
+        assert!(!DbErrorKind::of(&sqlx::Error::RowNotFound).is_retryable());
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = RetryPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(50),
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(50));
+        assert_eq!(policy.delay(3), Duration::from_millis(200));
+    }
+}
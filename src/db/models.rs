@@ -1,6 +1,6 @@
 use sqlx::FromRow;
 
-#[derive(Debug, Clone, FromRow, serde::Serialize)]
+#[derive(Debug, Clone, FromRow, serde::Serialize, serde::Deserialize)]
 pub struct Catalog {
     pub id: i64,
     pub parent_id: Option<i64>,
@@ -9,6 +9,10 @@ pub struct Catalog {
     pub cat_type: i32,
     pub cat_size: i64,
     pub cat_mtime: String,
+    /// INPX collection version (`version.info`); empty for other catalogs.
+    pub cat_version: String,
+    /// 1 when an admin hid the catalog from browsing and scanning.
+    pub cat_hidden: i32,
 }
 
 #[derive(Debug, Clone, FromRow, serde::Serialize)]
@@ -31,6 +35,101 @@ pub struct Book {
     pub cover_type: String,
     pub author_key: String,
     pub reg_date: String,
+    /// Stored cover size in pixels (0 when unknown).
+    pub cover_width: i32,
+    pub cover_height: i32,
+    /// Dominant colours of the stored cover (`#rrggbb`, space-separated,
+    /// most common first); empty when not computed.
+    pub cover_palette: String,
+    /// When the book's file was last replaced by a changed one; empty if never.
+    pub last_modified: String,
+    /// First lines of the book text; empty if none was extracted.
+    pub preview_text: String,
+    /// Words of the body text (FB2, EPUB, TXT); 0 when not counted.
+    pub word_count: i32,
+    /// Pages of PDF and DjVu books; 0 when unknown.
+    pub page_count: i32,
+    /// SHA-256 of the file (hex) when `scanner.link_identical` is on; empty
+    /// otherwise.
+    pub file_hash: String,
+}
+
+/// Reading speed behind reading time estimates, in words per minute.
+pub const READING_WPM: u32 = 200;
+/// Words on a printed page, to estimate pages from a word count.
+pub const WORDS_PER_PAGE: u32 = 250;
+
+impl Book {
+    /// Pages: counted for PDF and DjVu, estimated from the word count for
+    /// text books; 0 when the length is unknown.
+    pub fn pages(&self) -> u32 {
+        if self.page_count > 0 {
+            self.page_count as u32
+        } else {
+            (self.word_count.max(0) as u32).div_ceil(WORDS_PER_PAGE)
+        }
+    }
+
+    /// Estimated reading time in minutes; 0 when the length is unknown.
+    pub fn reading_minutes(&self) -> u32 {
+        let words = if self.word_count > 0 {
+            self.word_count as u32
+        } else {
+            (self.page_count.max(0) as u32).saturating_mul(WORDS_PER_PAGE)
+        };
+        words.div_ceil(READING_WPM)
+    }
+}
+
+/// Another location of a book's file with identical content
+/// (`scanner.link_identical`).
+#[derive(Debug, Clone, PartialEq, Eq, FromRow, serde::Serialize)]
+pub struct BookFile {
+    pub id: i64,
+    pub book_id: i64,
+    pub path: String,
+    pub filename: String,
+    pub format: String,
+    pub size: i64,
+}
+
+/// Running time (seconds) and narrators of an audiobook.
+#[derive(Debug, Clone, FromRow, serde::Serialize)]
+pub struct BookAudio {
+    pub book_id: i64,
+    pub duration: i64,
+    pub narrator: String,
+}
+
+/// A download leaderboard row: a book, author or user and its download count.
+/// Anonymous downloads are grouped under user id 0 with an empty name.
+#[derive(Debug, Clone, PartialEq, FromRow, serde::Serialize)]
+pub struct DownloadCount {
+    pub id: i64,
+    pub name: String,
+    pub downloads: i64,
+}
+
+/// An OPDS client seen for a user (`stats.user_agents`).
+#[derive(Debug, Clone, FromRow, serde::Serialize)]
+pub struct UserDevice {
+    pub user_id: i64,
+    pub username: String,
+    pub user_agent: String,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub requests: i64,
+}
+
+/// An administrator action recorded in the `audit_log` table.
+#[derive(Debug, Clone, FromRow, serde::Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created_at: String,
+    pub admin: String,
+    pub action: String,
+    pub subject: String,
+    pub ip: String,
 }
 
 #[derive(Debug, Clone, FromRow, serde::Serialize)]
@@ -41,7 +140,7 @@ pub struct Author {
     pub lang_code: i32,
 }
 
-#[derive(Debug, Clone, FromRow, serde::Serialize)]
+#[derive(Debug, Clone, FromRow, serde::Serialize, serde::Deserialize)]
 pub struct Genre {
     pub id: i64,
     pub code: String,
@@ -91,6 +190,10 @@ pub struct User {
     pub password_change_required: i32,
     pub display_name: String,
     pub allow_upload: i32,
+    /// Web theme preference: `light`, `dark`, `auto`, or empty when not set.
+    pub theme: String,
+    /// `0` when the user opted out of `opds.auto_bookshelf`.
+    pub auto_bookshelf: i32,
 }
 
 #[derive(Debug, Clone, FromRow, serde::Serialize)]
@@ -106,6 +209,29 @@ pub struct OAuthIdentity {
     pub created_at: String,
 }
 
+/// A book share link with the shared book's title and its creator's name.
+#[derive(Debug, Clone, FromRow, serde::Serialize)]
+pub struct BookShare {
+    pub id: i64,
+    pub book_id: i64,
+    pub title: String,
+    pub username: String,
+    /// Unix seconds.
+    pub expires_at: i64,
+    pub created_at: String,
+}
+
+/// A guest link to a user's bookshelf with the owner's name.
+#[derive(Debug, Clone, FromRow, serde::Serialize)]
+pub struct ShelfShare {
+    pub id: i64,
+    pub user_id: i64,
+    pub username: String,
+    /// Unix seconds.
+    pub expires_at: i64,
+    pub created_at: String,
+}
+
 #[derive(Debug, Clone, FromRow, serde::Serialize)]
 pub struct Counter {
     pub name: String,
@@ -0,0 +1,68 @@
+//! Administrator actions kept in the `audit_log` table and listed in the
+//! admin panel. They are also logged under the `audit` tracing target.
+
+use crate::db::DbPool;
+use crate::db::models::AuditEntry;
+
+/// A superuser started browsing as another user.
+pub const IMPERSONATION_STARTED: &str = "impersonation_started";
+/// A superuser went back to their own session.
+pub const IMPERSONATION_ENDED: &str = "impersonation_ended";
+
+/// Record `action` of the administrator `admin` on `subject` (a user name).
+pub async fn record(
+    pool: &DbPool,
+    admin: &str,
+    action: &str,
+    subject: &str,
+    ip: &str,
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let sql = pool.sql(
+        "INSERT INTO audit_log (created_at, admin, action, subject, ip) VALUES (?, ?, ?, ?, ?)",
+    );
+    sqlx::query(&sql)
+        .bind(now)
+        .bind(admin)
+        .bind(action)
+        .bind(subject)
+        .bind(ip)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// The latest `limit` entries, newest first.
+pub async fn list_recent(pool: &DbPool, limit: i64) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT id, created_at, admin, action, subject, ip FROM audit_log \
+         ORDER BY id DESC LIMIT ?",
+    );
+    sqlx::query_as(&sql)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    #[tokio::test]
+    async fn test_record_and_list_recent() {
+        let pool = create_test_pool().await;
+        record(&pool, "admin", IMPERSONATION_STARTED, "alice", "127.0.0.1")
+            .await
+            .unwrap();
+        record(&pool, "admin", IMPERSONATION_ENDED, "alice", "127.0.0.1")
+            .await
+            .unwrap();
+
+        let entries = list_recent(&pool, 10).await.unwrap();
+        let actions: Vec<&str> = entries.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, [IMPERSONATION_ENDED, IMPERSONATION_STARTED]);
+        assert_eq!(entries[0].subject, "alice");
+        assert_eq!(list_recent(&pool, 1).await.unwrap().len(), 1);
+    }
+}
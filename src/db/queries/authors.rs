@@ -1,6 +1,14 @@
-use crate::db::{DbBackend, DbPool};
+use crate::db::DbPool;
 
 use crate::db::models::Author;
+use crate::db::queries::MatchMode;
+use crate::translit;
+
+/// Limits the alphabet drill-down (prefix groups, counts and listings) to
+/// authors with at least one available book, so the three stay in agreement.
+pub(crate) const WITH_BOOKS: &str = "EXISTS (SELECT 1 FROM book_authors ba \
+     JOIN books b ON b.id = ba.book_id \
+     WHERE ba.author_id = authors.id AND b.avail > 0)";
 
 pub async fn get_by_id(pool: &DbPool, id: i64) -> Result<Option<Author>, sqlx::Error> {
     let sql = pool.sql("SELECT * FROM authors WHERE id = ?");
@@ -10,19 +18,27 @@ pub async fn get_by_id(pool: &DbPool, id: i64) -> Result<Option<Author>, sqlx::E
         .await
 }
 
+/// Authors whose name matches `term` in `mode`; with `translit`, spellings in
+/// another script match as well. Names are stored surname first, so
+/// [`MatchMode::Begins`] is a last-name prefix search.
 pub async fn search_by_name(
     pool: &DbPool,
     term: &str,
+    mode: MatchMode,
     limit: i32,
     offset: i32,
+    translit: bool,
 ) -> Result<Vec<Author>, sqlx::Error> {
-    let pattern = format!("%{term}%");
+    let pattern = mode.like_pattern(term);
+    let translit_pattern = translit::pattern(term, translit, mode);
     let sql = pool.sql(
-        "SELECT * FROM authors WHERE search_full_name LIKE ? \
+        "SELECT * FROM authors \
+         WHERE (search_full_name LIKE ? OR translit_full_name LIKE ?) \
          ORDER BY search_full_name LIMIT ? OFFSET ?",
     );
     sqlx::query_as::<_, Author>(&sql)
         .bind(&pattern)
+        .bind(&translit_pattern)
         .bind(limit)
         .bind(offset)
         .fetch_all(pool.inner())
@@ -37,10 +53,11 @@ pub async fn get_by_lang_code_prefix(
     offset: i32,
 ) -> Result<Vec<Author>, sqlx::Error> {
     if prefix.is_empty() {
-        let sql = pool.sql(
-            "SELECT * FROM authors WHERE (? = 0 OR lang_code = ?) \
-             ORDER BY search_full_name LIMIT ? OFFSET ?",
+        let raw = format!(
+            "SELECT * FROM authors WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
+             ORDER BY search_full_name LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         return sqlx::query_as::<_, Author>(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -52,11 +69,12 @@ pub async fn get_by_lang_code_prefix(
     // Word-boundary prefix match: either at start of the name or after a space.
     let start_pat = format!("{prefix}%");
     let word_pat = format!("% {prefix}%");
-    let sql = pool.sql(
-        "SELECT * FROM authors WHERE (? = 0 OR lang_code = ?) \
+    let raw = format!(
+        "SELECT * FROM authors WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
          AND (search_full_name LIKE ? OR search_full_name LIKE ?) \
-         ORDER BY search_full_name LIMIT ? OFFSET ?",
+         ORDER BY search_full_name LIMIT ? OFFSET ?"
     );
+    let sql = pool.sql(&raw);
     sqlx::query_as::<_, Author>(&sql)
         .bind(lang_code)
         .bind(lang_code)
@@ -82,19 +100,19 @@ pub async fn insert(
     search_full_name: &str,
     lang_code: i32,
 ) -> Result<i64, sqlx::Error> {
-    let sql = match pool.backend() {
-        DbBackend::Mysql => {
-            "INSERT IGNORE INTO authors (full_name, search_full_name, lang_code) VALUES (?, ?, ?)"
-        }
-        _ => {
-            "INSERT INTO authors (full_name, search_full_name, lang_code) VALUES (?, ?, ?) \
-             ON CONFLICT (full_name) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_ignore(
+        "authors",
+        &[
+            "full_name",
+            "search_full_name",
+            "translit_full_name",
+            "lang_code",
+        ],
+    );
     let result = sqlx::query(&sql)
         .bind(full_name)
         .bind(search_full_name)
+        .bind(translit::fold(search_full_name))
         .bind(lang_code)
         .execute(pool.inner())
         .await?;
@@ -113,14 +131,7 @@ pub async fn insert(
 }
 
 pub async fn link_book(pool: &DbPool, book_id: i64, author_id: i64) -> Result<(), sqlx::Error> {
-    let sql = match pool.backend() {
-        DbBackend::Mysql => "INSERT IGNORE INTO book_authors (book_id, author_id) VALUES (?, ?)",
-        _ => {
-            "INSERT INTO book_authors (book_id, author_id) VALUES (?, ?) \
-             ON CONFLICT (book_id, author_id) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_ignore("book_authors", &["book_id", "author_id"]);
     sqlx::query(&sql)
         .bind(book_id)
         .bind(author_id)
@@ -148,14 +159,7 @@ pub async fn set_book_authors(
         .bind(book_id)
         .execute(pool.inner())
         .await?;
-    let sql = match pool.backend() {
-        DbBackend::Mysql => "INSERT IGNORE INTO book_authors (book_id, author_id) VALUES (?, ?)",
-        _ => {
-            "INSERT INTO book_authors (book_id, author_id) VALUES (?, ?) \
-             ON CONFLICT (book_id, author_id) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_ignore("book_authors", &["book_id", "author_id"]);
     for &author_id in author_ids {
         sqlx::query(&sql)
             .bind(book_id)
@@ -202,12 +206,22 @@ pub async fn get_for_book(pool: &DbPool, book_id: i64) -> Result<Vec<Author>, sq
         .await
 }
 
-/// Count authors matching a name search (contains).
-pub async fn count_by_name_search(pool: &DbPool, term: &str) -> Result<i64, sqlx::Error> {
-    let pattern = format!("%{term}%");
-    let sql = pool.sql("SELECT COUNT(*) FROM authors WHERE search_full_name LIKE ?");
+/// Count authors matching a name search, like [`search_by_name`].
+pub async fn count_by_name_search(
+    pool: &DbPool,
+    term: &str,
+    mode: MatchMode,
+    translit: bool,
+) -> Result<i64, sqlx::Error> {
+    let pattern = mode.like_pattern(term);
+    let translit_pattern = translit::pattern(term, translit, mode);
+    let sql = pool.sql(
+        "SELECT COUNT(*) FROM authors \
+         WHERE (search_full_name LIKE ? OR translit_full_name LIKE ?)",
+    );
     let row: (i64,) = sqlx::query_as(&sql)
         .bind(&pattern)
+        .bind(&translit_pattern)
         .fetch_one(pool.inner())
         .await?;
     Ok(row.0)
@@ -223,7 +237,9 @@ pub async fn count_by_lang_code_prefix(
     prefix: &str,
 ) -> Result<i64, sqlx::Error> {
     if prefix.is_empty() {
-        let sql = pool.sql("SELECT COUNT(*) FROM authors WHERE ? = 0 OR lang_code = ?");
+        let raw =
+            format!("SELECT COUNT(*) FROM authors WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS}");
+        let sql = pool.sql(&raw);
         let row: (i64,) = sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -233,10 +249,11 @@ pub async fn count_by_lang_code_prefix(
     }
     let start_pat = format!("{prefix}%");
     let word_pat = format!("% {prefix}%");
-    let sql = pool.sql(
-        "SELECT COUNT(*) FROM authors WHERE (? = 0 OR lang_code = ?) \
-         AND (search_full_name LIKE ? OR search_full_name LIKE ?)",
+    let raw = format!(
+        "SELECT COUNT(*) FROM authors WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
+         AND (search_full_name LIKE ? OR search_full_name LIKE ?)"
     );
+    let sql = pool.sql(&raw);
     let row: (i64,) = sqlx::query_as(&sql)
         .bind(lang_code)
         .bind(lang_code)
@@ -259,7 +276,10 @@ pub async fn get_name_prefix_groups(
     current_prefix: &str,
 ) -> Result<Vec<(String, i64)>, sqlx::Error> {
     let names: Vec<(String,)> = if current_prefix.is_empty() {
-        let sql = pool.sql("SELECT search_full_name FROM authors WHERE ? = 0 OR lang_code = ?");
+        let raw = format!(
+            "SELECT search_full_name FROM authors WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS}"
+        );
+        let sql = pool.sql(&raw);
         sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -268,11 +288,12 @@ pub async fn get_name_prefix_groups(
     } else {
         let start_pat = format!("{}%", current_prefix);
         let word_pat = format!("% {}%", current_prefix);
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT search_full_name FROM authors \
-             WHERE (? = 0 OR lang_code = ?) \
-             AND (search_full_name LIKE ? OR search_full_name LIKE ?)",
+             WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
+             AND (search_full_name LIKE ? OR search_full_name LIKE ?)"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -405,6 +426,18 @@ mod tests {
         row.0
     }
 
+    /// Give every author an available book; the alphabet drill-down
+    /// lists only those.
+    async fn link_books_to_all(pool: &DbPool) {
+        let catalog_id = ensure_catalog(pool).await;
+        let sql = pool.sql("SELECT id FROM authors");
+        let ids: Vec<(i64,)> = sqlx::query_as(&sql).fetch_all(pool.inner()).await.unwrap();
+        for (id,) in ids {
+            let book_id = insert_test_book(pool, catalog_id, &format!("Book {id}")).await;
+            link_book(pool, book_id, id).await.unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn test_insert_search_count_and_prefix_groups() {
         let pool = create_test_pool().await;
@@ -421,12 +454,72 @@ mod tests {
         let by_name = find_by_name(&pool, "Alice Smith").await.unwrap().unwrap();
         assert_eq!(by_name.id, alice);
 
-        let search = search_by_name(&pool, "ALI", 100, 0).await.unwrap();
+        let search = search_by_name(&pool, "ALI", MatchMode::Contains, 100, 0, false)
+            .await
+            .unwrap();
         assert_eq!(search.len(), 2);
 
-        let count = count_by_name_search(&pool, "ALI").await.unwrap();
+        let count = count_by_name_search(&pool, "ALI", MatchMode::Contains, false)
+            .await
+            .unwrap();
         assert_eq!(count, 2);
 
+        // Begins and exact modes only match from the start of the name.
+        let west = search_by_name(&pool, "WEST", MatchMode::Contains, 100, 0, false)
+            .await
+            .unwrap();
+        assert_eq!(west.len(), 1);
+        assert!(
+            search_by_name(&pool, "WEST", MatchMode::Begins, 100, 0, false)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            count_by_name_search(&pool, "ALINA", MatchMode::Begins, false)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            count_by_name_search(&pool, "ALINA", MatchMode::Exact, false)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            count_by_name_search(&pool, "ALINA WEST", MatchMode::Exact, false)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            count_by_name_search(&pool, "ALIS", MatchMode::Begins, true)
+                .await
+                .unwrap(),
+            1
+        );
+
+        // "ALISA" reaches "Алиса" only through transliteration.
+        assert!(
+            search_by_name(&pool, "ALISA", MatchMode::Contains, 100, 0, false)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let translit = search_by_name(&pool, "ALISA", MatchMode::Contains, 100, 0, true)
+            .await
+            .unwrap();
+        assert_eq!(translit.len(), 1);
+        assert_eq!(translit[0].full_name, "Алиса");
+        assert_eq!(
+            count_by_name_search(&pool, "ALISA", MatchMode::Contains, true)
+                .await
+                .unwrap(),
+            1
+        );
+
+        link_books_to_all(&pool).await;
         let prefix = get_by_lang_code_prefix(&pool, 2, "AL", 100, 0)
             .await
             .unwrap();
@@ -461,6 +554,7 @@ mod tests {
             .unwrap();
 
         // Listing for prefix "AB" — three authors with a word starting with AB.
+        link_books_to_all(&pool).await;
         let by_ab = get_by_lang_code_prefix(&pool, 2, "AB", 100, 0)
             .await
             .unwrap();
@@ -497,10 +591,45 @@ mod tests {
 
         // count_by_name_search is unrelated to drill-down (uses substring),
         // so a substring "AB" matches all five rows above.
-        let total = count_by_name_search(&pool, "AB").await.unwrap();
+        let total = count_by_name_search(&pool, "AB", MatchMode::Contains, false)
+            .await
+            .unwrap();
         assert_eq!(total, 5);
     }
 
+    #[tokio::test]
+    async fn test_drill_down_skips_authors_without_available_books() {
+        let pool = create_test_pool().await;
+        let live = insert(&pool, "Adams Live", "ADAMS LIVE", 2).await.unwrap();
+        let gone = insert(&pool, "Abbott Gone", "ABBOTT GONE", 2)
+            .await
+            .unwrap();
+        insert(&pool, "Avery Bookless", "AVERY BOOKLESS", 2)
+            .await
+            .unwrap();
+        let catalog_id = ensure_catalog(&pool).await;
+        let book_id = insert_test_book(&pool, catalog_id, "Kept").await;
+        link_book(&pool, book_id, live).await.unwrap();
+        let book_id = insert_test_book(&pool, catalog_id, "Removed").await;
+        link_book(&pool, book_id, gone).await.unwrap();
+        let sql = pool.sql("UPDATE books SET avail = 0 WHERE id = ?");
+        sqlx::query(&sql)
+            .bind(book_id)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+
+        let groups = get_name_prefix_groups(&pool, 2, "A").await.unwrap();
+        assert_eq!(groups, vec![("AD".to_string(), 1)]);
+        assert_eq!(count_by_lang_code_prefix(&pool, 2, "A").await.unwrap(), 1);
+        assert_eq!(count_by_lang_code_prefix(&pool, 0, "").await.unwrap(), 1);
+        let listed = get_by_lang_code_prefix(&pool, 2, "A", 100, 0)
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, live);
+    }
+
     #[tokio::test]
     async fn test_insert_duplicate_returns_same_id() {
         let pool = create_test_pool().await;
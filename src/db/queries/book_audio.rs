@@ -0,0 +1,61 @@
+use crate::db::DbPool;
+use crate::db::models::BookAudio;
+
+/// Audiobook details of a book, if it has any.
+pub async fn get_for_book(pool: &DbPool, book_id: i64) -> Result<Option<BookAudio>, sqlx::Error> {
+    let sql = pool.sql("SELECT book_id, duration, narrator FROM book_audio WHERE book_id = ?");
+    sqlx::query_as::<_, BookAudio>(&sql)
+        .bind(book_id)
+        .fetch_optional(pool.inner())
+        .await
+}
+
+/// Store the running time (seconds) and narrators of an audiobook.
+pub async fn upsert(
+    pool: &DbPool,
+    book_id: i64,
+    duration: i64,
+    narrator: &str,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.upsert_update(
+        "book_audio",
+        &["book_id", "duration", "narrator"],
+        &["book_id"],
+        &["duration", "narrator"],
+    );
+    sqlx::query(&sql)
+        .bind(book_id)
+        .bind(duration)
+        .bind(narrator)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    #[tokio::test]
+    async fn test_upsert_and_get() {
+        let pool = create_test_pool().await;
+        let sql = pool.sql("INSERT INTO catalogs (path, cat_name) VALUES ('audio', 'audio')");
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+        let sql = pool.sql(
+            "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+             lang, lang_code, size, avail, cat_type, cover, cover_type) \
+             VALUES (1, 'dune.m4b', 'audio', 'm4b', 'Dune', 'DUNE', 'en', 2, 100, 2, 0, 0, '')",
+        );
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+
+        assert!(get_for_book(&pool, 1).await.unwrap().is_none());
+        upsert(&pool, 1, 3600, "Scott Brick").await.unwrap();
+        upsert(&pool, 1, 7200, "Scott Brick, Orlagh Cassidy")
+            .await
+            .unwrap();
+        let audio = get_for_book(&pool, 1).await.unwrap().unwrap();
+        assert_eq!(audio.duration, 7200);
+        assert_eq!(audio.narrator, "Scott Brick, Orlagh Cassidy");
+    }
+}
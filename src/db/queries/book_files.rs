@@ -0,0 +1,110 @@
+use crate::db::DbPool;
+use crate::db::models::BookFile;
+
+const COLUMNS: &str = "id, book_id, path, filename, format, size";
+
+/// Further copies of a book's file, oldest first.
+pub async fn get_for_book(pool: &DbPool, book_id: i64) -> Result<Vec<BookFile>, sqlx::Error> {
+    let raw = format!("SELECT {COLUMNS} FROM book_files WHERE book_id = ? ORDER BY id");
+    let sql = pool.sql(&raw);
+    sqlx::query_as::<_, BookFile>(&sql)
+        .bind(book_id)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// All copies, for the scanner's in-memory index.
+pub async fn list_for_scan(pool: &DbPool) -> Result<Vec<BookFile>, sqlx::Error> {
+    let raw = format!("SELECT {COLUMNS} FROM book_files");
+    let sql = pool.sql(&raw);
+    sqlx::query_as::<_, BookFile>(&sql)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Register another location of a book's file.
+pub async fn insert(
+    pool: &DbPool,
+    book_id: i64,
+    path: &str,
+    filename: &str,
+    format: &str,
+    size: i64,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql(
+        "INSERT INTO book_files (book_id, path, filename, format, size) VALUES (?, ?, ?, ?, ?)",
+    );
+    sqlx::query(&sql)
+        .bind(book_id)
+        .bind(path)
+        .bind(filename)
+        .bind(format)
+        .bind(size)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Forget copies by id.
+pub async fn delete_ids(pool: &DbPool, ids: &[i64]) -> Result<u64, sqlx::Error> {
+    let mut deleted = 0;
+    for chunk in ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let raw = format!("DELETE FROM book_files WHERE id IN ({placeholders})");
+        let sql = pool.sql(&raw);
+        let mut query = sqlx::query(&sql);
+        for id in chunk {
+            query = query.bind(*id);
+        }
+        deleted += query.execute(pool.inner()).await?.rows_affected();
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    #[tokio::test]
+    async fn test_insert_list_and_delete() {
+        let pool = create_test_pool().await;
+        let sql = pool.sql("INSERT INTO catalogs (path, cat_name) VALUES ('main', 'main')");
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+        let sql = pool.sql(
+            "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+             lang, lang_code, size, avail, cat_type, cover, cover_type) \
+             VALUES (1, 'dune.fb2', 'main', 'fb2', 'Dune', 'DUNE', 'en', 2, 100, 2, 0, 0, '')",
+        );
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+
+        insert(&pool, 1, "mirror/b", "dune.fb2", "fb2", 100)
+            .await
+            .unwrap();
+        insert(&pool, 1, "mirror/a", "dune.fb2", "fb2", 100)
+            .await
+            .unwrap();
+        // The same location cannot be registered twice.
+        assert!(
+            insert(&pool, 1, "mirror/a", "dune.fb2", "fb2", 100)
+                .await
+                .is_err()
+        );
+
+        let copies = get_for_book(&pool, 1).await.unwrap();
+        assert_eq!(
+            copies.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(),
+            ["mirror/b", "mirror/a"]
+        );
+        assert_eq!(list_for_scan(&pool).await.unwrap(), copies);
+
+        assert_eq!(delete_ids(&pool, &[copies[0].id]).await.unwrap(), 1);
+        assert_eq!(get_for_book(&pool, 1).await.unwrap().len(), 1);
+
+        // Copies go with their book.
+        crate::db::queries::books::delete_book_and_relations(&pool, 1)
+            .await
+            .unwrap();
+        assert!(list_for_scan(&pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! Terms of the full-text index of book bodies (see [`crate::fulltext`]).
+
+use crate::db::models::Book;
+use crate::db::{DbPool, values_placeholders};
+
+/// Query terms at least this long match every indexed term they start
+/// ("рыб" finds "рыба" and "рыбы"); shorter ones only match whole terms.
+pub const PREFIX_MIN_CHARS: usize = 4;
+/// Term rows written per INSERT statement.
+const INSERT_CHUNK: usize = 500;
+
+/// Available books in one of `formats` whose text is not indexed yet,
+/// lowest id first.
+pub async fn pending_books(
+    pool: &DbPool,
+    formats: &[&str],
+    limit: i32,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let placeholders = vec!["?"; formats.len()].join(", ");
+    let raw = format!(
+        "SELECT * FROM books WHERE content_indexed = 0 AND avail > 0 \
+         AND format IN ({placeholders}) ORDER BY id LIMIT ?"
+    );
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, Book>(&sql);
+    for format in formats {
+        query = query.bind(*format);
+    }
+    query.bind(limit).fetch_all(pool.inner()).await
+}
+
+/// Replace the terms of a book (term, occurrences) and mark its text indexed.
+/// An empty list marks a book whose text could not be read.
+pub async fn replace_for_book(
+    pool: &DbPool,
+    book_id: i64,
+    terms: &[(String, i64)],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.inner().begin().await?;
+    let sql = pool.sql("DELETE FROM book_terms WHERE book_id = ?");
+    sqlx::query(&sql).bind(book_id).execute(&mut *tx).await?;
+    for chunk in terms.chunks(INSERT_CHUNK) {
+        let raw = format!(
+            "INSERT INTO book_terms (term, book_id, freq) VALUES {}",
+            values_placeholders(3, chunk.len())
+        );
+        let sql = pool.sql(&raw);
+        let mut query = sqlx::query(&sql);
+        for (term, freq) in chunk {
+            query = query.bind(term).bind(book_id).bind(*freq);
+        }
+        query.execute(&mut *tx).await?;
+    }
+    let sql = pool.sql("UPDATE books SET content_indexed = 1 WHERE id = ?");
+    sqlx::query(&sql).bind(book_id).execute(&mut *tx).await?;
+    tx.commit().await
+}
+
+/// Condition on `term` for one query term, with its binds.
+fn term_condition(term: &str) -> (&'static str, Vec<String>) {
+    if term.chars().count() >= PREFIX_MIN_CHARS {
+        (
+            "term >= ? AND term < ?",
+            vec![term.to_string(), format!("{term}{}", char::MAX)],
+        )
+    } else {
+        ("term = ?", vec![term.to_string()])
+    }
+}
+
+/// `books.id` must have every term; returns the condition and its binds.
+fn all_terms_condition(terms: &[String]) -> (String, Vec<String>) {
+    let mut conds = Vec::with_capacity(terms.len());
+    let mut binds = Vec::new();
+    for term in terms {
+        let (cond, term_binds) = term_condition(term);
+        conds.push(format!(
+            "books.id IN (SELECT book_id FROM book_terms WHERE {cond})"
+        ));
+        binds.extend(term_binds);
+    }
+    (conds.join(" AND "), binds)
+}
+
+/// Available books whose text contains every term, those where the terms
+/// occur most often first. Returns nothing for no terms.
+pub async fn search(
+    pool: &DbPool,
+    terms: &[String],
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<Book>, sqlx::Error> {
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (cond, mut binds) = all_terms_condition(terms);
+    let mut any = Vec::with_capacity(terms.len());
+    for term in terms {
+        let (term_cond, term_binds) = term_condition(term);
+        any.push(format!("({term_cond})"));
+        binds.extend(term_binds);
+    }
+    let raw = format!(
+        "SELECT * FROM books WHERE avail > 0 AND {cond} \
+         ORDER BY (SELECT SUM(freq) FROM book_terms \
+         WHERE book_terms.book_id = books.id AND ({})) DESC, books.id DESC \
+         LIMIT ? OFFSET ?",
+        any.join(" OR ")
+    );
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, Book>(&sql);
+    for bind in &binds {
+        query = query.bind(bind);
+    }
+    query.bind(limit).bind(offset).fetch_all(pool.inner()).await
+}
+
+/// Number of available books whose text contains every term.
+pub async fn count(pool: &DbPool, terms: &[String]) -> Result<i64, sqlx::Error> {
+    if terms.is_empty() {
+        return Ok(0);
+    }
+    let (cond, binds) = all_terms_condition(terms);
+    let raw = format!("SELECT COUNT(*) FROM books WHERE avail > 0 AND {cond}");
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, (i64,)>(&sql);
+    for bind in &binds {
+        query = query.bind(bind);
+    }
+    Ok(query.fetch_one(pool.inner()).await?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    async fn insert_book(pool: &DbPool, id: i64, format: &str) {
+        let sql = pool.sql(
+            "INSERT INTO books (id, catalog_id, filename, path, format, title, search_title, \
+             lang, lang_code, size, avail, cat_type, cover, cover_type) \
+             VALUES (?, 1, ?, 'lib', ?, ?, ?, 'en', 2, 100, 2, 0, 0, '')",
+        );
+        sqlx::query(&sql)
+            .bind(id)
+            .bind(format!("{id}.{format}"))
+            .bind(format)
+            .bind(format!("Book {id}"))
+            .bind(format!("BOOK {id}"))
+            .execute(pool.inner())
+            .await
+            .unwrap();
+    }
+
+    fn terms(list: &[(&str, i64)]) -> Vec<(String, i64)> {
+        list.iter().map(|(t, f)| (t.to_string(), *f)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_index_and_search() {
+        let pool = create_test_pool().await;
+        let sql = pool.sql("INSERT INTO catalogs (path, cat_name) VALUES ('lib', 'lib')");
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+        insert_book(&pool, 1, "fb2").await;
+        insert_book(&pool, 2, "epub").await;
+        insert_book(&pool, 3, "pdf").await;
+
+        let pending = pending_books(&pool, &["fb2", "epub"], 10).await.unwrap();
+        assert_eq!(pending.iter().map(|b| b.id).collect::<Vec<_>>(), [1, 2]);
+
+        replace_for_book(&pool, 1, &terms(&[("whale", 3), ("ship", 1)]))
+            .await
+            .unwrap();
+        replace_for_book(&pool, 2, &terms(&[("whales", 1), ("sea", 5)]))
+            .await
+            .unwrap();
+        let pending = pending_books(&pool, &["fb2", "epub"], 10).await.unwrap();
+        assert!(pending.is_empty());
+
+        // Prefix match, ranked by occurrences
+        let q = vec!["whale".to_string()];
+        let found = search(&pool, &q, 10, 0).await.unwrap();
+        assert_eq!(found.iter().map(|b| b.id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(count(&pool, &q).await.unwrap(), 2);
+
+        // Every term must match; short terms match whole terms only
+        let q = vec!["whale".to_string(), "sea".to_string()];
+        let found = search(&pool, &q, 10, 0).await.unwrap();
+        assert_eq!(found.iter().map(|b| b.id).collect::<Vec<_>>(), [2]);
+        let q = vec!["se".to_string()];
+        assert_eq!(count(&pool, &q).await.unwrap(), 0);
+
+        // Replacing drops the old terms
+        replace_for_book(&pool, 1, &[]).await.unwrap();
+        let q = vec!["ship".to_string()];
+        assert!(search(&pool, &q, 10, 0).await.unwrap().is_empty());
+    }
+}
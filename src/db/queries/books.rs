@@ -1,6 +1,8 @@
 use crate::db::{DbBackend, DbPool};
 
 use crate::db::models::{AvailStatus, Book, CatType};
+use crate::db::queries::MatchMode;
+use crate::translit;
 
 pub async fn get_by_id(pool: &DbPool, id: i64) -> Result<Option<Book>, sqlx::Error> {
     let sql = pool.sql("SELECT * FROM books WHERE id = ?");
@@ -10,19 +12,120 @@ pub async fn get_by_id(pool: &DbPool, id: i64) -> Result<Option<Book>, sqlx::Err
         .await
 }
 
+/// Sort order of book listings (`?sort=` in the web UI and OPDS feeds).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSort {
+    /// Alphabetical by search title.
+    Title,
+    /// Newest additions first.
+    Recent,
+    /// By series name and number; books without a series come last.
+    Series,
+    /// Largest files first.
+    Size,
+    /// Newest document date first.
+    Year,
+    /// Most downloaded first.
+    Rating,
+}
+
+impl BookSort {
+    /// Options for general listings, default first.
+    pub const LISTING: [BookSort; 6] = [
+        BookSort::Title,
+        BookSort::Recent,
+        BookSort::Series,
+        BookSort::Year,
+        BookSort::Size,
+        BookSort::Rating,
+    ];
+    /// Options for the books of one series, which default to series order.
+    pub const SERIES_LISTING: [BookSort; 6] = [
+        BookSort::Series,
+        BookSort::Title,
+        BookSort::Recent,
+        BookSort::Year,
+        BookSort::Size,
+        BookSort::Rating,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "title" => Some(Self::Title),
+            "recent" => Some(Self::Recent),
+            "series" => Some(Self::Series),
+            "size" => Some(Self::Size),
+            "year" => Some(Self::Year),
+            "rating" => Some(Self::Rating),
+            _ => None,
+        }
+    }
+
+    /// Effective sort of a listing: the requested order when the listing
+    /// offers it, otherwise the listing's default (its first option).
+    pub fn resolve(options: &[BookSort], requested: Option<&str>) -> Self {
+        requested
+            .and_then(Self::parse)
+            .filter(|sort| options.contains(sort))
+            .unwrap_or(options[0])
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Recent => "recent",
+            Self::Series => "series",
+            Self::Size => "size",
+            Self::Year => "year",
+            Self::Rating => "rating",
+        }
+    }
+
+    /// ORDER BY expression for the books table referenced as `books`
+    /// (a table name or alias). Every order ends on `id` so OFFSET paging
+    /// stays stable.
+    fn order_by(self, books: &str) -> String {
+        match self {
+            Self::Title => format!("{books}.search_title, {books}.id"),
+            Self::Recent => format!("{books}.reg_date DESC, {books}.id DESC"),
+            Self::Size => format!("{books}.size DESC, {books}.search_title, {books}.id"),
+            Self::Year => format!("{books}.docdate DESC, {books}.search_title, {books}.id"),
+            Self::Rating => {
+                format!("{books}.download_count DESC, {books}.search_title, {books}.id")
+            }
+            Self::Series => {
+                let ser_name = format!(
+                    "(SELECT MIN(s.search_ser) FROM book_series bso \
+                     JOIN series s ON s.id = bso.series_id WHERE bso.book_id = {books}.id)"
+                );
+                let ser_no = format!(
+                    "(SELECT MIN(bso.ser_no) FROM book_series bso WHERE bso.book_id = {books}.id)"
+                );
+                format!(
+                    "CASE WHEN {ser_name} IS NULL THEN 1 ELSE 0 END, {ser_name}, {ser_no}, \
+                     {books}.search_title, {books}.id"
+                )
+            }
+        }
+    }
+}
+
 pub async fn get_by_catalog(
     pool: &DbPool,
     catalog_id: i64,
     limit: i32,
     offset: i32,
     hide_doubles: bool,
+    sort: BookSort,
 ) -> Result<Vec<Book>, sqlx::Error> {
+    let order = sort.order_by("books");
     if hide_doubles {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT * FROM books WHERE catalog_id = ? AND avail > 0 \
              AND id IN (SELECT MIN(id) FROM books WHERE catalog_id = ? AND avail > 0 GROUP BY search_title, author_key) \
-             ORDER BY search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(catalog_id)
             .bind(catalog_id)
@@ -31,9 +134,79 @@ pub async fn get_by_catalog(
             .fetch_all(pool.inner())
             .await
     } else {
-        let sql = pool.sql(
-            "SELECT * FROM books WHERE catalog_id = ? AND avail > 0 ORDER BY search_title LIMIT ? OFFSET ?",
+        let raw = format!(
+            "SELECT * FROM books WHERE catalog_id = ? AND avail > 0 ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
+        sqlx::query_as::<_, Book>(&sql)
+            .bind(catalog_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool.inner())
+            .await
+    }
+}
+
+/// Key range `[path/, path0)` covering every folder below `path` on disk.
+/// `'0'` is the byte after `'/'`, so the range is a plain index scan on
+/// `books.path` and needs no LIKE escaping.
+///
+/// Only partial rescans use it: they work on library folders, and
+/// re-parenting a catalog changes `catalogs.parent_id`, not where its books
+/// are stored. Catalog listings walk the tree with [`subtree_catalogs_sql`].
+fn subtree_path_range(path: &str) -> (String, String) {
+    let path = path.trim_end_matches('/');
+    (format!("{path}/"), format!("{path}0"))
+}
+
+/// Subquery selecting a catalog id and its descendants; binds the catalog
+/// id. The tree is walked by `parent_id`, so catalogs an admin moved
+/// elsewhere count under their new parent, whatever their `path`.
+fn subtree_catalogs_sql() -> String {
+    format!(
+        "WITH RECURSIVE subtree (id, depth) AS ( \
+             SELECT id, 0 FROM catalogs WHERE id = ? \
+             UNION ALL \
+             SELECT c.id, subtree.depth + 1 FROM catalogs c \
+             JOIN subtree ON c.parent_id = subtree.id \
+             WHERE subtree.depth < {}) \
+         SELECT id FROM subtree",
+        super::catalogs::MAX_CATALOG_DEPTH
+    )
+}
+
+/// Books in a catalog and all of its descendants (catalog flattening).
+pub async fn get_by_catalog_tree(
+    pool: &DbPool,
+    catalog_id: i64,
+    limit: i32,
+    offset: i32,
+    hide_doubles: bool,
+    sort: BookSort,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let subtree = subtree_catalogs_sql();
+    let order = sort.order_by("books");
+    if hide_doubles {
+        let raw = format!(
+            "SELECT * FROM books WHERE avail > 0 AND catalog_id IN ({subtree}) \
+             AND id IN (SELECT MIN(id) FROM books WHERE avail > 0 \
+               AND catalog_id IN ({subtree}) GROUP BY search_title, author_key) \
+             ORDER BY {order} LIMIT ? OFFSET ?"
+        );
+        let sql = pool.sql(&raw);
+        sqlx::query_as::<_, Book>(&sql)
+            .bind(catalog_id)
+            .bind(catalog_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(pool.inner())
+            .await
+    } else {
+        let raw = format!(
+            "SELECT * FROM books WHERE avail > 0 AND catalog_id IN ({subtree}) \
+             ORDER BY {order} LIMIT ? OFFSET ?"
+        );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(catalog_id)
             .bind(limit)
@@ -49,17 +222,20 @@ pub async fn get_by_author(
     limit: i32,
     offset: i32,
     hide_doubles: bool,
+    sort: BookSort,
 ) -> Result<Vec<Book>, sqlx::Error> {
+    let order = sort.order_by("b");
     if hide_doubles {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT b.* FROM books b \
              JOIN book_authors ba ON ba.book_id = b.id \
              WHERE ba.author_id = ? AND b.avail > 0 \
              AND b.id IN (SELECT MIN(b2.id) FROM books b2 \
                JOIN book_authors ba2 ON ba2.book_id = b2.id \
                WHERE ba2.author_id = ? AND b2.avail > 0 GROUP BY b2.search_title, b2.author_key) \
-             ORDER BY b.search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(author_id)
             .bind(author_id)
@@ -68,12 +244,13 @@ pub async fn get_by_author(
             .fetch_all(pool.inner())
             .await
     } else {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT b.* FROM books b \
              JOIN book_authors ba ON ba.book_id = b.id \
              WHERE ba.author_id = ? AND b.avail > 0 \
-             ORDER BY b.search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(author_id)
             .bind(limit)
@@ -89,17 +266,20 @@ pub async fn get_by_genre(
     limit: i32,
     offset: i32,
     hide_doubles: bool,
+    sort: BookSort,
 ) -> Result<Vec<Book>, sqlx::Error> {
+    let order = sort.order_by("b");
     if hide_doubles {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT b.* FROM books b \
              JOIN book_genres bg ON bg.book_id = b.id \
              WHERE bg.genre_id = ? AND b.avail > 0 \
              AND b.id IN (SELECT MIN(b2.id) FROM books b2 \
                JOIN book_genres bg2 ON bg2.book_id = b2.id \
                WHERE bg2.genre_id = ? AND b2.avail > 0 GROUP BY b2.search_title, b2.author_key) \
-             ORDER BY b.search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(genre_id)
             .bind(genre_id)
@@ -108,12 +288,13 @@ pub async fn get_by_genre(
             .fetch_all(pool.inner())
             .await
     } else {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT b.* FROM books b \
              JOIN book_genres bg ON bg.book_id = b.id \
              WHERE bg.genre_id = ? AND b.avail > 0 \
-             ORDER BY b.search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(genre_id)
             .bind(limit)
@@ -129,17 +310,24 @@ pub async fn get_by_series(
     limit: i32,
     offset: i32,
     hide_doubles: bool,
+    sort: BookSort,
 ) -> Result<Vec<Book>, sqlx::Error> {
+    // Within one series, "series order" is simply the volume number.
+    let order = match sort {
+        BookSort::Series => "bs.ser_no, b.search_title, b.id".to_string(),
+        other => other.order_by("b"),
+    };
     if hide_doubles {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT b.* FROM books b \
              JOIN book_series bs ON bs.book_id = b.id \
              WHERE bs.series_id = ? AND b.avail > 0 \
              AND b.id IN (SELECT MIN(b2.id) FROM books b2 \
                JOIN book_series bs2 ON bs2.book_id = b2.id \
                WHERE bs2.series_id = ? AND b2.avail > 0 GROUP BY b2.search_title, b2.author_key) \
-             ORDER BY bs.ser_no, b.search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(series_id)
             .bind(series_id)
@@ -148,12 +336,13 @@ pub async fn get_by_series(
             .fetch_all(pool.inner())
             .await
     } else {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT b.* FROM books b \
              JOIN book_series bs ON bs.book_id = b.id \
              WHERE bs.series_id = ? AND b.avail > 0 \
-             ORDER BY bs.ser_no, b.search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(series_id)
             .bind(limit)
@@ -163,34 +352,347 @@ pub async fn get_by_series(
     }
 }
 
+/// Part of the library a title search is limited to, given as `c:<id>`,
+/// `a:<id>` or `s:<id>` in `?scope=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchScope {
+    /// A catalog and all of its descendants.
+    Catalog(i64),
+    Author(i64),
+    Series(i64),
+}
+
+impl SearchScope {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, id) = value.trim().split_once(':')?;
+        let id = id.parse().ok().filter(|id: &i64| *id > 0)?;
+        match kind {
+            "c" => Some(Self::Catalog(id)),
+            "a" => Some(Self::Author(id)),
+            "s" => Some(Self::Series(id)),
+            _ => None,
+        }
+    }
+
+    /// Value of the `scope` query parameter.
+    pub fn as_param(self) -> String {
+        match self {
+            Self::Catalog(id) => format!("c:{id}"),
+            Self::Author(id) => format!("a:{id}"),
+            Self::Series(id) => format!("s:{id}"),
+        }
+    }
+
+    /// WHERE condition for the books table referenced as `books`; binds the id.
+    fn condition(self, books: &str) -> (String, i64) {
+        match self {
+            Self::Catalog(id) => (
+                format!(" AND {books}.catalog_id IN ({})", subtree_catalogs_sql()),
+                id,
+            ),
+            Self::Author(id) => (
+                format!(
+                    " AND {books}.id IN (SELECT book_id FROM book_authors WHERE author_id = ?)"
+                ),
+                id,
+            ),
+            Self::Series(id) => (
+                format!(" AND {books}.id IN (SELECT book_id FROM book_series WHERE series_id = ?)"),
+                id,
+            ),
+        }
+    }
+}
+
+/// Optional narrowing of a title search by genre, book language and format
+/// (the facets of the OPDS search feeds), and by [`SearchScope`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilter {
+    pub genre_id: Option<i64>,
+    pub lang: Option<String>,
+    pub format: Option<String>,
+    pub scope: Option<SearchScope>,
+}
+
+impl SearchFilter {
+    /// Filter from raw query parameters; blank or malformed values are ignored.
+    pub fn parse(genre: Option<&str>, lang: Option<&str>, format: Option<&str>) -> Self {
+        let text = |value: Option<&str>| {
+            value
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            genre_id: genre.and_then(|v| v.trim().parse().ok()),
+            lang: text(lang),
+            format: text(format),
+            scope: None,
+        }
+    }
+
+    /// The same filter limited to the `?scope=` value, if it is valid.
+    pub fn scoped(self, scope: Option<&str>) -> Self {
+        Self {
+            scope: scope.and_then(SearchScope::parse),
+            ..self
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genre_id.is_none()
+            && self.lang.is_none()
+            && self.format.is_none()
+            && self.scope.is_none()
+    }
+
+    /// Query parameters (`scope`, `genre`, `language`, `format`) selecting this filter.
+    pub fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(scope) = self.scope {
+            pairs.push(("scope", scope.as_param()));
+        }
+        if let Some(id) = self.genre_id {
+            pairs.push(("genre", id.to_string()));
+        }
+        if let Some(lang) = &self.lang {
+            pairs.push(("language", lang.clone()));
+        }
+        if let Some(format) = &self.format {
+            pairs.push(("format", format.clone()));
+        }
+        pairs
+    }
+
+    /// WHERE conditions and bind values for the books table referenced as `books`.
+    fn conditions(&self, books: &str) -> (String, Vec<ListingBind>) {
+        let mut sql = String::new();
+        let mut binds = Vec::new();
+        if let Some(id) = self.genre_id {
+            sql.push_str(&format!(
+                " AND {books}.id IN (SELECT book_id FROM book_genres WHERE genre_id = ?)"
+            ));
+            binds.push(ListingBind::Int(id));
+        }
+        if let Some(lang) = &self.lang {
+            sql.push_str(&format!(" AND LOWER({books}.lang) = ?"));
+            binds.push(ListingBind::Text(lang.clone()));
+        }
+        if let Some(format) = &self.format {
+            sql.push_str(&format!(" AND {books}.format = ?"));
+            binds.push(ListingBind::Text(format.clone()));
+        }
+        if let Some(scope) = self.scope {
+            let (condition, id) = scope.condition(books);
+            sql.push_str(&condition);
+            binds.push(ListingBind::Int(id));
+        }
+        (sql, binds)
+    }
+}
+
+/// Title match condition and bind values of a title search for the books
+/// table referenced as `books`, narrowed by `filter`. [`MatchMode::Begins`]
+/// matches the start of any word, like the title drill-down groups.
+fn title_search_conditions(
+    books: &str,
+    term: &str,
+    mode: MatchMode,
+    translit: bool,
+    filter: &SearchFilter,
+) -> (String, Vec<ListingBind>) {
+    let translit_pattern = translit::pattern(term, translit, mode);
+    let (mut sql, mut binds) = match mode {
+        MatchMode::Begins => (
+            format!(
+                "({books}.search_title LIKE ? OR {books}.search_title LIKE ? \
+                 OR {books}.translit_title LIKE ? OR {books}.translit_title LIKE ?)"
+            ),
+            vec![
+                ListingBind::Text(format!("{term}%")),
+                ListingBind::Text(format!("% {term}%")),
+                ListingBind::OptText(translit_pattern.clone()),
+                ListingBind::OptText(translit_pattern.map(|p| format!("% {p}"))),
+            ],
+        ),
+        MatchMode::Contains | MatchMode::Exact => (
+            format!("({books}.search_title LIKE ? OR {books}.translit_title LIKE ?)"),
+            vec![
+                ListingBind::Text(mode.like_pattern(term)),
+                ListingBind::OptText(translit_pattern),
+            ],
+        ),
+    };
+    sql.push_str(&format!(" AND {books}.avail > 0"));
+    let (filter_sql, filter_binds) = filter.conditions(books);
+    sql.push_str(&filter_sql);
+    binds.extend(filter_binds);
+    (sql, binds)
+}
+
+/// Available books whose title matches `term` in `mode`; with `translit`,
+/// spellings in another script match as well.
+#[allow(clippy::too_many_arguments)]
 pub async fn search_by_title(
     pool: &DbPool,
     term: &str,
+    mode: MatchMode,
+    limit: i32,
+    offset: i32,
+    hide_doubles: bool,
+    sort: BookSort,
+    translit: bool,
+    filter: &SearchFilter,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let order = sort.order_by("books");
+    let (cond, mut binds) = title_search_conditions("books", term, mode, translit, filter);
+    let raw = if hide_doubles {
+        let (inner, inner_binds) = title_search_conditions("b2", term, mode, translit, filter);
+        binds.extend(inner_binds);
+        format!(
+            "SELECT * FROM books WHERE {cond} \
+             AND id IN (SELECT MIN(b2.id) FROM books b2 WHERE {inner} \
+             GROUP BY b2.search_title, b2.author_key) \
+             ORDER BY {order} LIMIT ? OFFSET ?"
+        )
+    } else {
+        format!("SELECT * FROM books WHERE {cond} ORDER BY {order} LIMIT ? OFFSET ?")
+    };
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, Book>(&sql);
+    for bind in binds {
+        query = bind.apply(query);
+    }
+    query.bind(limit).bind(offset).fetch_all(pool.inner()).await
+}
+
+/// Values listed per facet group of a title search.
+pub const SEARCH_FACET_VALUES: i32 = 20;
+
+/// Facet values of a title search with their book counts, most frequent
+/// first. Each group is counted with the other groups' filters applied.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFacets {
+    /// `(genre_id, translated genre name, count)`.
+    pub genres: Vec<(i64, String, i64)>,
+    pub langs: Vec<(String, i64)>,
+    pub formats: Vec<(String, i64)>,
+}
+
+/// Facet values for the OPDS title search, at most `limit` per group.
+/// Genre names are translated to `ui_lang` with English fallback.
+pub async fn title_search_facets(
+    pool: &DbPool,
+    term: &str,
+    mode: MatchMode,
+    translit: bool,
+    filter: &SearchFilter,
+    ui_lang: &str,
+    limit: i32,
+) -> Result<SearchFacets, sqlx::Error> {
+    let without_genre = SearchFilter {
+        genre_id: None,
+        ..filter.clone()
+    };
+    let (cond, binds) = title_search_conditions("b", term, mode, translit, &without_genre);
+    // Translation columns are grouped explicitly for strict GROUP BY modes;
+    // each (genre_id, lang) pair is unique, so the grouping is unchanged.
+    let raw = format!(
+        "SELECT g.id, COALESCE(gt.name, gt_en.name, g.subsection) AS name, \
+                COUNT(DISTINCT b.id) AS cnt \
+         FROM books b \
+         JOIN book_genres bg ON bg.book_id = b.id \
+         JOIN genres g ON g.id = bg.genre_id \
+         LEFT JOIN genre_translations gt ON gt.genre_id = g.id AND gt.lang = ? \
+         LEFT JOIN genre_translations gt_en ON gt_en.genre_id = g.id AND gt_en.lang = 'en' \
+         WHERE {cond} \
+         GROUP BY g.id, g.subsection, gt.name, gt_en.name \
+         ORDER BY cnt DESC, name LIMIT ?"
+    );
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, (i64, String, i64)>(&sql).bind(ui_lang);
+    for bind in binds {
+        query = bind.apply(query);
+    }
+    let genres = query.bind(limit).fetch_all(pool.inner()).await?;
+
+    let without_lang = SearchFilter {
+        lang: None,
+        ..filter.clone()
+    };
+    let langs =
+        title_search_column_facet(pool, "lang", term, mode, translit, &without_lang, limit).await?;
+    let without_format = SearchFilter {
+        format: None,
+        ..filter.clone()
+    };
+    let formats =
+        title_search_column_facet(pool, "format", term, mode, translit, &without_format, limit)
+            .await?;
+    Ok(SearchFacets {
+        genres,
+        langs,
+        formats,
+    })
+}
+
+/// Distinct non-empty values of a books column among title search matches.
+async fn title_search_column_facet(
+    pool: &DbPool,
+    column: &str,
+    term: &str,
+    mode: MatchMode,
+    translit: bool,
+    filter: &SearchFilter,
+    limit: i32,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    let (cond, binds) = title_search_conditions("b", term, mode, translit, filter);
+    let raw = format!(
+        "SELECT LOWER(b.{column}) AS val, COUNT(*) AS cnt FROM books b \
+         WHERE {cond} AND b.{column} <> '' \
+         GROUP BY LOWER(b.{column}) ORDER BY cnt DESC, val LIMIT ?"
+    );
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, (String, i64)>(&sql);
+    for bind in binds {
+        query = bind.apply(query);
+    }
+    query.bind(limit).fetch_all(pool.inner()).await
+}
+
+/// Available books of a script group (`lang_code`, `0` for all), used by the
+/// flat title listing when the alphabet menu is disabled.
+pub async fn get_by_lang_code(
+    pool: &DbPool,
+    lang_code: i32,
     limit: i32,
     offset: i32,
     hide_doubles: bool,
+    sort: BookSort,
 ) -> Result<Vec<Book>, sqlx::Error> {
-    let pattern = format!("%{term}%");
+    let order = sort.order_by("books");
     if hide_doubles {
-        let sql = pool.sql(
-            "SELECT * FROM books WHERE search_title LIKE ? AND avail > 0 \
-             AND id IN (SELECT MIN(id) FROM books WHERE search_title LIKE ? AND avail > 0 GROUP BY search_title, author_key) \
-             ORDER BY search_title LIMIT ? OFFSET ?",
+        let raw = format!(
+            "SELECT * FROM books WHERE avail > 0 AND (? = 0 OR lang_code = ?) \
+             AND id IN (SELECT MIN(id) FROM books WHERE avail > 0 GROUP BY search_title, author_key) \
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
-            .bind(&pattern)
-            .bind(&pattern)
+            .bind(lang_code)
+            .bind(lang_code)
             .bind(limit)
             .bind(offset)
             .fetch_all(pool.inner())
             .await
     } else {
-        let sql = pool.sql(
-            "SELECT * FROM books WHERE search_title LIKE ? AND avail > 0 \
-             ORDER BY search_title LIMIT ? OFFSET ?",
+        let raw = format!(
+            "SELECT * FROM books WHERE avail > 0 AND (? = 0 OR lang_code = ?) \
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
-            .bind(&pattern)
+            .bind(lang_code)
+            .bind(lang_code)
             .bind(limit)
             .bind(offset)
             .fetch_all(pool.inner())
@@ -204,24 +706,28 @@ pub async fn search_by_title_prefix(
     limit: i32,
     offset: i32,
     hide_doubles: bool,
+    sort: BookSort,
 ) -> Result<Vec<Book>, sqlx::Error> {
+    let order = sort.order_by("books");
     if prefix.is_empty() {
         return if hide_doubles {
-            let sql = pool.sql(
+            let raw = format!(
                 "SELECT * FROM books WHERE avail > 0 \
                  AND id IN (SELECT MIN(id) FROM books WHERE avail > 0 GROUP BY search_title, author_key) \
-                 ORDER BY search_title LIMIT ? OFFSET ?",
+                 ORDER BY {order} LIMIT ? OFFSET ?"
             );
+            let sql = pool.sql(&raw);
             sqlx::query_as::<_, Book>(&sql)
                 .bind(limit)
                 .bind(offset)
                 .fetch_all(pool.inner())
                 .await
         } else {
-            let sql = pool.sql(
+            let raw = format!(
                 "SELECT * FROM books WHERE avail > 0 \
-                 ORDER BY search_title LIMIT ? OFFSET ?",
+                 ORDER BY {order} LIMIT ? OFFSET ?"
             );
+            let sql = pool.sql(&raw);
             sqlx::query_as::<_, Book>(&sql)
                 .bind(limit)
                 .bind(offset)
@@ -233,11 +739,12 @@ pub async fn search_by_title_prefix(
     let start_pat = format!("{prefix}%");
     let word_pat = format!("% {prefix}%");
     if hide_doubles {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT * FROM books WHERE (search_title LIKE ? OR search_title LIKE ?) AND avail > 0 \
              AND id IN (SELECT MIN(id) FROM books WHERE (search_title LIKE ? OR search_title LIKE ?) AND avail > 0 GROUP BY search_title, author_key) \
-             ORDER BY search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(&start_pat)
             .bind(&word_pat)
@@ -248,10 +755,11 @@ pub async fn search_by_title_prefix(
             .fetch_all(pool.inner())
             .await
     } else {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT * FROM books WHERE (search_title LIKE ? OR search_title LIKE ?) AND avail > 0 \
-             ORDER BY search_title LIMIT ? OFFSET ?",
+             ORDER BY {order} LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as::<_, Book>(&sql)
             .bind(&start_pat)
             .bind(&word_pat)
@@ -262,6 +770,156 @@ pub async fn search_by_title_prefix(
     }
 }
 
+/// Book listings that support keyset pagination (ordered by `search_title, id`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookListing {
+    Author(i64),
+    Genre(i64),
+    /// Word-boundary title prefix; an empty prefix lists all books.
+    TitlePrefix(String),
+    /// Substring match on the search title.
+    TitleSearch(String),
+    /// Substring match on the search title or its transliteration key.
+    TranslitTitleSearch(String),
+}
+
+/// Keyset position: the last book of the previous page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleCursor {
+    pub search_title: String,
+    pub id: i64,
+}
+
+impl TitleCursor {
+    pub fn from_book(book: &Book) -> Self {
+        Self {
+            search_title: book.search_title.clone(),
+            id: book.id,
+        }
+    }
+
+    /// Opaque, URL-safe cursor token.
+    pub fn encode(&self) -> String {
+        use base64::Engine as _;
+        base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(format!("{}:{}", self.id, self.search_title))
+    }
+
+    pub fn decode(token: &str) -> Option<Self> {
+        use base64::Engine as _;
+        let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(token)
+            .ok()?;
+        let raw = String::from_utf8(raw).ok()?;
+        let (id, search_title) = raw.split_once(':')?;
+        Some(Self {
+            search_title: search_title.to_string(),
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+enum ListingBind {
+    Int(i64),
+    Text(String),
+    OptText(Option<String>),
+}
+
+impl ListingBind {
+    fn apply<'q, O>(
+        self,
+        query: sqlx::query::QueryAs<'q, sqlx::Any, O, sqlx::any::AnyArguments<'q>>,
+    ) -> sqlx::query::QueryAs<'q, sqlx::Any, O, sqlx::any::AnyArguments<'q>> {
+        match self {
+            ListingBind::Int(value) => query.bind(value),
+            ListingBind::Text(value) => query.bind(value),
+            ListingBind::OptText(value) => query.bind(value),
+        }
+    }
+}
+
+/// JOIN clause, WHERE conditions and bind values of a listing for the given
+/// `books` alias and link-table alias.
+fn listing_filter(
+    listing: &BookListing,
+    books: &str,
+    link: &str,
+) -> (String, String, Vec<ListingBind>) {
+    match listing {
+        BookListing::Author(id) => (
+            format!(" JOIN book_authors {link} ON {link}.book_id = {books}.id"),
+            format!(" AND {link}.author_id = ?"),
+            vec![ListingBind::Int(*id)],
+        ),
+        BookListing::Genre(id) => (
+            format!(" JOIN book_genres {link} ON {link}.book_id = {books}.id"),
+            format!(" AND {link}.genre_id = ?"),
+            vec![ListingBind::Int(*id)],
+        ),
+        BookListing::TitlePrefix(prefix) if prefix.is_empty() => {
+            (String::new(), String::new(), Vec::new())
+        }
+        BookListing::TitlePrefix(prefix) => (
+            String::new(),
+            format!(" AND ({books}.search_title LIKE ? OR {books}.search_title LIKE ?)"),
+            vec![
+                ListingBind::Text(format!("{prefix}%")),
+                ListingBind::Text(format!("% {prefix}%")),
+            ],
+        ),
+        BookListing::TitleSearch(term) => (
+            String::new(),
+            format!(" AND {books}.search_title LIKE ?"),
+            vec![ListingBind::Text(format!("%{term}%"))],
+        ),
+        BookListing::TranslitTitleSearch(term) => {
+            let mut binds = vec![ListingBind::Text(format!("%{term}%"))];
+            let mut filter = format!(" AND {books}.search_title LIKE ?");
+            if let Some(pattern) = translit::contains_pattern(term, true) {
+                filter =
+                    format!(" AND ({books}.search_title LIKE ? OR {books}.translit_title LIKE ?)");
+                binds.push(ListingBind::Text(pattern));
+            }
+            (String::new(), filter, binds)
+        }
+    }
+}
+
+/// Fetch up to `limit` books of a listing that sort after `after`.
+/// Unlike OFFSET paging, the cost does not grow with the page number.
+pub async fn list_after(
+    pool: &DbPool,
+    listing: &BookListing,
+    after: Option<&TitleCursor>,
+    limit: i32,
+    hide_doubles: bool,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let (join, filter, mut binds) = listing_filter(listing, "b", "l");
+    let mut raw = format!("SELECT b.* FROM books b{join} WHERE b.avail > 0{filter}");
+    if hide_doubles {
+        let (join2, filter2, binds2) = listing_filter(listing, "b2", "l2");
+        raw.push_str(&format!(
+            " AND b.id IN (SELECT MIN(b2.id) FROM books b2{join2} \
+             WHERE b2.avail > 0{filter2} GROUP BY b2.search_title, b2.author_key)"
+        ));
+        binds.extend(binds2);
+    }
+    if let Some(cursor) = after {
+        raw.push_str(" AND (b.search_title > ? OR (b.search_title = ? AND b.id > ?))");
+        binds.push(ListingBind::Text(cursor.search_title.clone()));
+        binds.push(ListingBind::Text(cursor.search_title.clone()));
+        binds.push(ListingBind::Int(cursor.id));
+    }
+    raw.push_str(" ORDER BY b.search_title, b.id LIMIT ?");
+
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, Book>(&sql);
+    for bind in binds {
+        query = bind.apply(query);
+    }
+    query.bind(limit).fetch_all(pool.inner()).await
+}
+
 pub async fn find_by_path_and_filename(
     pool: &DbPool,
     path: &str,
@@ -280,14 +938,34 @@ pub struct ExistingBookIndexRow {
     pub id: i64,
     pub path: String,
     pub filename: String,
+    pub size: i64,
+    /// When the file was recorded: the later of `reg_date` and `last_modified`.
+    pub recorded: String,
+    pub file_hash: String,
 }
 
+const SCAN_INDEX_COLUMNS: &str = "id, path, filename, size, \
+     CASE WHEN last_modified > reg_date THEN last_modified ELSE reg_date END AS recorded, \
+     file_hash";
+
 pub async fn list_existing_for_scan(
     pool: &DbPool,
 ) -> Result<Vec<ExistingBookIndexRow>, sqlx::Error> {
     // Full in-memory snapshot to eliminate per-book lookup queries during scan.
     // Trade-off: memory usage scales with total book rows.
-    let sql = pool.sql("SELECT id, path, filename FROM books");
+    let query_sql = format!("SELECT {SCAN_INDEX_COLUMNS} FROM books");
+    let sql = pool.sql(&query_sql);
+    sqlx::query_as::<_, ExistingBookIndexRow>(&sql)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Like [`list_existing_for_scan`], but skips logically deleted books.
+pub async fn list_available_for_scan(
+    pool: &DbPool,
+) -> Result<Vec<ExistingBookIndexRow>, sqlx::Error> {
+    let query_sql = format!("SELECT {SCAN_INDEX_COLUMNS} FROM books WHERE avail > 0");
+    let sql = pool.sql(&query_sql);
     sqlx::query_as::<_, ExistingBookIndexRow>(&sql)
         .fetch_all(pool.inner())
         .await
@@ -321,56 +999,291 @@ pub async fn set_avail_confirmed_for_ids(pool: &DbPool, ids: &[i64]) -> Result<u
     Ok(total_updated)
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn insert(
+#[allow(clippy::too_many_arguments)]
+pub async fn insert(
+    pool: &DbPool,
+    catalog_id: i64,
+    filename: &str,
+    path: &str,
+    format: &str,
+    title: &str,
+    search_title: &str,
+    annotation: &str,
+    docdate: &str,
+    lang: &str,
+    lang_code: i32,
+    size: i64,
+    cat_type: CatType,
+    cover: i32,
+    cover_type: &str,
+) -> Result<i64, sqlx::Error> {
+    let sql = pool.sql(
+        "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+         translit_title, annotation, docdate, lang, lang_code, size, avail, cat_type, cover, \
+         cover_type) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 2, ?, ?, ?)",
+    );
+    let result = sqlx::query(&sql)
+        .bind(catalog_id)
+        .bind(filename)
+        .bind(path)
+        .bind(format)
+        .bind(title)
+        .bind(search_title)
+        .bind(translit::fold(search_title))
+        .bind(annotation)
+        .bind(docdate)
+        .bind(lang)
+        .bind(lang_code)
+        .bind(size)
+        .bind(cat_type as i32)
+        .bind(cover)
+        .bind(cover_type)
+        .execute(pool.inner())
+        .await?;
+    if let Some(id) = result.last_insert_id() {
+        return Ok(id);
+    }
+    let sql = pool.sql("SELECT id FROM books WHERE path = ? AND filename = ?");
+    let row: (i64,) = sqlx::query_as(&sql)
+        .bind(path)
+        .bind(filename)
+        .fetch_one(pool.inner())
+        .await?;
+    Ok(row.0)
+}
+
+/// Point a book at a replacement file and store the file-level metadata
+/// read from it, stamping `last_modified`. Links, bookshelf entries and
+/// reading positions keep the id; the text is indexed again.
+#[allow(clippy::too_many_arguments)]
+pub async fn replace_file(
+    pool: &DbPool,
+    id: i64,
+    filename: &str,
+    format: &str,
+    size: i64,
+    annotation: &str,
+    docdate: &str,
+    lang: &str,
+    cover: i32,
+    cover_type: &str,
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let sql = pool.sql(
+        "UPDATE books SET filename = ?, format = ?, size = ?, annotation = ?, docdate = ?, \
+         lang = ?, cover = ?, cover_type = ?, last_modified = ?, content_indexed = 0 \
+         WHERE id = ?",
+    );
+    sqlx::query(&sql)
+        .bind(filename)
+        .bind(format)
+        .bind(size)
+        .bind(annotation)
+        .bind(docdate)
+        .bind(lang)
+        .bind(cover)
+        .bind(cover_type)
+        .bind(now)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Record whether a book has a stored cover and its MIME type.
+pub async fn set_cover(
+    pool: &DbPool,
+    id: i64,
+    cover: i32,
+    cover_type: &str,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET cover = ?, cover_type = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(cover)
+        .bind(cover_type)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// An available plain-file book with this file size and content hash.
+pub async fn find_by_file_hash(
+    pool: &DbPool,
+    size: i64,
+    hash: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT id FROM books WHERE size = ? AND file_hash = ? AND avail > 0 AND cat_type = ? \
+         ORDER BY id LIMIT 1",
+    );
+    let row: Option<(i64,)> = sqlx::query_as(&sql)
+        .bind(size)
+        .bind(hash)
+        .bind(CatType::Normal as i32)
+        .fetch_optional(pool.inner())
+        .await?;
+    Ok(row.map(|(id,)| id))
+}
+
+/// Available plain-file books of this file size whose hash was never
+/// computed, as `(id, path, filename)`.
+pub async fn unhashed_with_size(
+    pool: &DbPool,
+    size: i64,
+) -> Result<Vec<(i64, String, String)>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT id, path, filename FROM books \
+         WHERE size = ? AND file_hash = '' AND avail > 0 AND cat_type = ? ORDER BY id",
+    );
+    sqlx::query_as(&sql)
+        .bind(size)
+        .bind(CatType::Normal as i32)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Record the content hash of a book's file; empty when unknown.
+pub async fn set_file_hash(pool: &DbPool, id: i64, hash: &str) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET file_hash = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(hash)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Point a book at another copy of its file (its own one is gone) and mark
+/// it available.
+pub async fn move_to_file(
+    pool: &DbPool,
+    id: i64,
+    catalog_id: i64,
+    path: &str,
+    filename: &str,
+) -> Result<(), sqlx::Error> {
+    let sql =
+        pool.sql("UPDATE books SET catalog_id = ?, path = ?, filename = ?, avail = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(catalog_id)
+        .bind(path)
+        .bind(filename)
+        .bind(AvailStatus::Confirmed as i32)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Record the dominant colours of a book's stored cover.
+pub async fn set_cover_palette(pool: &DbPool, id: i64, palette: &str) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET cover_palette = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(palette)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// IDs above `after_id` of books with a cover but no palette, in order.
+pub async fn ids_without_cover_palette(
+    pool: &DbPool,
+    after_id: i64,
+    limit: i32,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT id FROM books WHERE cover <> 0 AND cover_palette = '' AND id > ? \
+         ORDER BY id LIMIT ?",
+    );
+    let rows: Vec<(i64,)> = sqlx::query_as(&sql)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Available books by id, for the crawlable full dump. Pages continue
+/// after `after_id` (keyset) when the client follows a `next` link and fall
+/// back to `offset` for pages reached directly.
+pub async fn get_all_by_id(
+    pool: &DbPool,
+    after_id: i64,
+    limit: i32,
+    offset: i64,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let sql =
+        pool.sql("SELECT * FROM books WHERE avail > 0 AND id > ? ORDER BY id LIMIT ? OFFSET ?");
+    sqlx::query_as::<_, Book>(&sql)
+        .bind(after_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Ids and registration dates of available books by id, a page at a time
+/// (sitemap).
+pub async fn available_ids(
+    pool: &DbPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    let sql =
+        pool.sql("SELECT id, reg_date FROM books WHERE avail > 0 ORDER BY id LIMIT ? OFFSET ?");
+    sqlx::query_as(&sql)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Record the pixel size of a book's stored cover.
+pub async fn set_cover_size(
+    pool: &DbPool,
+    id: i64,
+    width: u32,
+    height: u32,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET cover_width = ?, cover_height = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(width as i32)
+        .bind(height as i32)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Store the length read from the book file (0 when unknown).
+pub async fn set_length(
     pool: &DbPool,
-    catalog_id: i64,
-    filename: &str,
-    path: &str,
-    format: &str,
-    title: &str,
-    search_title: &str,
-    annotation: &str,
-    docdate: &str,
-    lang: &str,
-    lang_code: i32,
-    size: i64,
-    cat_type: CatType,
-    cover: i32,
-    cover_type: &str,
-) -> Result<i64, sqlx::Error> {
-    let sql = pool.sql(
-        "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
-         annotation, docdate, lang, lang_code, size, avail, cat_type, cover, cover_type) \
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 2, ?, ?, ?)",
-    );
-    let result = sqlx::query(&sql)
-        .bind(catalog_id)
-        .bind(filename)
-        .bind(path)
-        .bind(format)
-        .bind(title)
-        .bind(search_title)
-        .bind(annotation)
-        .bind(docdate)
-        .bind(lang)
-        .bind(lang_code)
-        .bind(size)
-        .bind(cat_type as i32)
-        .bind(cover)
-        .bind(cover_type)
+    id: i64,
+    word_count: i32,
+    page_count: i32,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET word_count = ?, page_count = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(word_count)
+        .bind(page_count)
+        .bind(id)
         .execute(pool.inner())
         .await?;
-    if let Some(id) = result.last_insert_id() {
-        return Ok(id);
-    }
-    let sql = pool.sql("SELECT id FROM books WHERE path = ? AND filename = ?");
-    let row: (i64,) = sqlx::query_as(&sql)
-        .bind(path)
-        .bind(filename)
-        .fetch_one(pool.inner())
+    Ok(())
+}
+
+/// Store the "first lines" preview extracted from the book file.
+pub async fn set_preview_text(pool: &DbPool, id: i64, text: &str) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET preview_text = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(text)
+        .bind(id)
+        .execute(pool.inner())
         .await?;
-    Ok(row.0)
+    Ok(())
 }
 
 pub async fn set_avail_all(pool: &DbPool, avail: AvailStatus) -> Result<u64, sqlx::Error> {
@@ -432,34 +1345,93 @@ pub async fn set_avail_for_inpx_dir(
     Ok(result.rows_affected())
 }
 
-/// Mark unverified books as logically deleted (avail=0, hidden from queries).
-pub async fn logical_delete_unavailable(pool: &DbPool) -> Result<u64, sqlx::Error> {
-    let sql = pool.sql("UPDATE books SET avail = ? WHERE avail <= ?");
+/// `books.path` condition matching the folder `path` and everything below
+/// it on disk; binds `path` and the two bounds from [`subtree_path_range`].
+fn subtree_books_sql(pool: &DbPool) -> &'static str {
+    match pool.backend() {
+        crate::db::DbBackend::Postgres => {
+            "(path = ? OR (path COLLATE \"C\" >= ? AND path COLLATE \"C\" < ?))"
+        }
+        _ => "(path = ? OR (path >= ? AND path < ?))",
+    }
+}
+
+/// Appends the [`subtree_books_sql`] condition when `under` is set.
+fn unavailable_sql(pool: &DbPool, head: &str, under: Option<&str>) -> String {
+    let raw = match under {
+        Some(_) => format!("{head} WHERE avail <= ? AND {}", subtree_books_sql(pool)),
+        None => format!("{head} WHERE avail <= ?"),
+    };
+    pool.sql(&raw).into_owned()
+}
+
+/// Set `avail` on available books stored at `path` or below it (partial scan).
+pub async fn set_avail_under_path(
+    pool: &DbPool,
+    path: &str,
+    avail: AvailStatus,
+) -> Result<u64, sqlx::Error> {
+    let (lower, upper) = subtree_path_range(path);
+    let raw = format!(
+        "UPDATE books SET avail = ? WHERE avail > 0 AND {}",
+        subtree_books_sql(pool)
+    );
+    let sql = pool.sql(&raw);
     let result = sqlx::query(&sql)
-        .bind(AvailStatus::Deleted as i32)
-        .bind(AvailStatus::Unverified as i32)
+        .bind(avail as i32)
+        .bind(path)
+        .bind(lower)
+        .bind(upper)
         .execute(pool.inner())
         .await?;
     Ok(result.rows_affected())
 }
 
+/// Mark unverified books as logically deleted (avail=0, hidden from queries).
+/// `under` limits the cleanup to a path subtree.
+pub async fn logical_delete_unavailable(
+    pool: &DbPool,
+    under: Option<&str>,
+) -> Result<u64, sqlx::Error> {
+    let sql = unavailable_sql(pool, "UPDATE books SET avail = ?", under);
+    let mut query = sqlx::query(&sql)
+        .bind(AvailStatus::Deleted as i32)
+        .bind(AvailStatus::Unverified as i32);
+    if let Some(path) = under {
+        let (lower, upper) = subtree_path_range(path);
+        query = query.bind(path).bind(lower).bind(upper);
+    }
+    let result = query.execute(pool.inner()).await?;
+    Ok(result.rows_affected())
+}
+
 /// Get IDs of unavailable books (for cover cleanup before physical deletion).
-pub async fn get_unavailable_ids(pool: &DbPool) -> Result<Vec<i64>, sqlx::Error> {
-    let sql = pool.sql("SELECT id FROM books WHERE avail <= ?");
-    let rows: Vec<(i64,)> = sqlx::query_as(&sql)
-        .bind(AvailStatus::Unverified as i32)
-        .fetch_all(pool.inner())
-        .await?;
+pub async fn get_unavailable_ids(
+    pool: &DbPool,
+    under: Option<&str>,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let sql = unavailable_sql(pool, "SELECT id FROM books", under);
+    let mut query = sqlx::query_as(&sql).bind(AvailStatus::Unverified as i32);
+    if let Some(path) = under {
+        let (lower, upper) = subtree_path_range(path);
+        query = query.bind(path).bind(lower).bind(upper);
+    }
+    let rows: Vec<(i64,)> = query.fetch_all(pool.inner()).await?;
     Ok(rows.into_iter().map(|(id,)| id).collect())
 }
 
 /// Physically delete unavailable books from the database.
-pub async fn physical_delete_unavailable(pool: &DbPool) -> Result<u64, sqlx::Error> {
-    let sql = pool.sql("DELETE FROM books WHERE avail <= ?");
-    let result = sqlx::query(&sql)
-        .bind(AvailStatus::Unverified as i32)
-        .execute(pool.inner())
-        .await?;
+pub async fn physical_delete_unavailable(
+    pool: &DbPool,
+    under: Option<&str>,
+) -> Result<u64, sqlx::Error> {
+    let sql = unavailable_sql(pool, "DELETE FROM books", under);
+    let mut query = sqlx::query(&sql).bind(AvailStatus::Unverified as i32);
+    if let Some(path) = under {
+        let (lower, upper) = subtree_path_range(path);
+        query = query.bind(path).bind(lower).bind(upper);
+    }
+    let result = query.execute(pool.inner()).await?;
     Ok(result.rows_affected())
 }
 
@@ -471,6 +1443,91 @@ pub async fn get_random(pool: &DbPool) -> Result<Option<Book>, sqlx::Error> {
         .await
 }
 
+/// Up to `limit` random available books (home page picks).
+pub async fn get_random_list(pool: &DbPool, limit: i32) -> Result<Vec<Book>, sqlx::Error> {
+    let random = match pool.backend() {
+        DbBackend::Mysql => "RAND()",
+        _ => "RANDOM()",
+    };
+    let raw = format!("SELECT * FROM books WHERE avail > 0 ORDER BY {random} LIMIT ?");
+    let sql = pool.sql(&raw);
+    sqlx::query_as::<_, Book>(&sql)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Recently added books in any of `genre_ids`, newest first, skipping books
+/// already on the user's bookshelf.
+pub async fn get_recent_in_genres(
+    pool: &DbPool,
+    genre_ids: &[i64],
+    user_id: i64,
+    limit: i32,
+) -> Result<Vec<Book>, sqlx::Error> {
+    if genre_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; genre_ids.len()].join(", ");
+    let raw = format!(
+        "SELECT * FROM books b WHERE b.avail > 0 \
+         AND EXISTS (SELECT 1 FROM book_genres bg \
+                     WHERE bg.book_id = b.id AND bg.genre_id IN ({placeholders})) \
+         AND NOT EXISTS (SELECT 1 FROM bookshelf bs \
+                         WHERE bs.book_id = b.id AND bs.user_id = ?) \
+         ORDER BY b.reg_date DESC, b.id DESC LIMIT ?"
+    );
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, Book>(&sql);
+    for id in genre_ids {
+        query = query.bind(*id);
+    }
+    query
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// The "recent" lists: books by registration date, books whose file was
+/// later replaced by a changed one (`last_modified`), and new arrivals in
+/// one genre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecentKind {
+    Added,
+    Updated,
+    Genre(i64),
+}
+
+/// [`get_recent_added`], [`get_recent_updated`] or the newest books of a genre.
+pub async fn get_recent(
+    pool: &DbPool,
+    kind: RecentKind,
+    limit: i32,
+    offset: i32,
+    hide_doubles: bool,
+) -> Result<Vec<Book>, sqlx::Error> {
+    match kind {
+        RecentKind::Added => get_recent_added(pool, limit, offset, hide_doubles).await,
+        RecentKind::Updated => get_recent_updated(pool, limit, offset, hide_doubles).await,
+        RecentKind::Genre(id) => {
+            get_by_genre(pool, id, limit, offset, hide_doubles, BookSort::Recent).await
+        }
+    }
+}
+
+pub async fn count_recent(
+    pool: &DbPool,
+    kind: RecentKind,
+    hide_doubles: bool,
+) -> Result<i64, sqlx::Error> {
+    match kind {
+        RecentKind::Added => count_recent_added(pool, hide_doubles).await,
+        RecentKind::Updated => count_recent_updated(pool, hide_doubles).await,
+        RecentKind::Genre(id) => count_by_genre(pool, id, hide_doubles).await,
+    }
+}
+
 /// Recently added books, newest first.
 pub async fn get_recent_added(
     pool: &DbPool,
@@ -501,6 +1558,26 @@ pub async fn get_recent_added(
     }
 }
 
+/// Most downloaded books of the last `days` days with their download counts,
+/// read from the popular cache in `counters` (see `counters::update_popular`).
+pub async fn get_popular(
+    pool: &DbPool,
+    days: u32,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<(Book, i64)>, sqlx::Error> {
+    let top = crate::db::queries::counters::get_popular(pool, days, limit, offset).await?;
+    let mut result = Vec::with_capacity(top.len());
+    for (book_id, downloads) in top {
+        if let Some(book) = get_by_id(pool, book_id).await?
+            && book.avail > 0
+        {
+            result.push((book, downloads));
+        }
+    }
+    Ok(result)
+}
+
 /// Count available books in the recently added view.
 pub async fn count_recent_added(pool: &DbPool, hide_doubles: bool) -> Result<i64, sqlx::Error> {
     let sql = if hide_doubles {
@@ -514,25 +1591,68 @@ pub async fn count_recent_added(pool: &DbPool, hide_doubles: bool) -> Result<i64
     Ok(row.0)
 }
 
-/// Count books matching a title search (contains).
+/// Books whose file was replaced by a changed one, most recent first.
+pub async fn get_recent_updated(
+    pool: &DbPool,
+    limit: i32,
+    offset: i32,
+    hide_doubles: bool,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let sql = if hide_doubles {
+        pool.sql(
+            "SELECT * FROM books WHERE avail > 0 AND last_modified <> '' \
+             AND id IN (SELECT MAX(id) FROM books WHERE avail > 0 GROUP BY search_title, author_key) \
+             ORDER BY last_modified DESC, id DESC LIMIT ? OFFSET ?",
+        )
+    } else {
+        pool.sql(
+            "SELECT * FROM books WHERE avail > 0 AND last_modified <> '' \
+             ORDER BY last_modified DESC, id DESC LIMIT ? OFFSET ?",
+        )
+    };
+    sqlx::query_as::<_, Book>(&sql)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool.inner())
+        .await
+}
+
+pub async fn count_recent_updated(pool: &DbPool, hide_doubles: bool) -> Result<i64, sqlx::Error> {
+    let sql = if hide_doubles {
+        "SELECT COUNT(*) FROM books WHERE avail > 0 AND last_modified <> '' \
+         AND id IN (SELECT MAX(id) FROM books WHERE avail > 0 GROUP BY search_title, author_key)"
+    } else {
+        "SELECT COUNT(*) FROM books WHERE avail > 0 AND last_modified <> ''"
+    };
+    let sql = pool.sql(sql);
+    let row: (i64,) = sqlx::query_as(&sql).fetch_one(pool.inner()).await?;
+    Ok(row.0)
+}
+
+/// Count books matching a title search, like [`search_by_title`].
 pub async fn count_by_title_search(
     pool: &DbPool,
     term: &str,
+    mode: MatchMode,
     hide_doubles: bool,
+    translit: bool,
+    filter: &SearchFilter,
 ) -> Result<i64, sqlx::Error> {
-    let pattern = format!("%{term}%");
-    let sql = if hide_doubles {
-        "SELECT COUNT(*) FROM (SELECT 1 FROM books \
-         WHERE search_title LIKE ? AND avail > 0 \
-         GROUP BY search_title, author_key) AS t"
+    let (cond, binds) = title_search_conditions("books", term, mode, translit, filter);
+    let raw = if hide_doubles {
+        format!(
+            "SELECT COUNT(*) FROM (SELECT 1 FROM books WHERE {cond} \
+             GROUP BY search_title, author_key) AS t"
+        )
     } else {
-        "SELECT COUNT(*) FROM books WHERE search_title LIKE ? AND avail > 0"
+        format!("SELECT COUNT(*) FROM books WHERE {cond}")
     };
-    let sql = pool.sql(sql);
-    let row: (i64,) = sqlx::query_as(&sql)
-        .bind(&pattern)
-        .fetch_one(pool.inner())
-        .await?;
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, (i64,)>(&sql);
+    for bind in binds {
+        query = bind.apply(query);
+    }
+    let row = query.fetch_one(pool.inner()).await?;
     Ok(row.0)
 }
 
@@ -665,6 +1785,51 @@ pub async fn count_by_catalog(
     Ok(row.0)
 }
 
+/// Count books in a catalog and all of its descendants.
+pub async fn count_by_catalog_tree(
+    pool: &DbPool,
+    catalog_id: i64,
+    hide_doubles: bool,
+) -> Result<i64, sqlx::Error> {
+    let subtree = subtree_catalogs_sql();
+    let sql = if hide_doubles {
+        format!(
+            "SELECT COUNT(*) FROM (SELECT 1 FROM books \
+             WHERE avail > 0 AND catalog_id IN ({subtree}) \
+             GROUP BY search_title, author_key) AS t"
+        )
+    } else {
+        format!("SELECT COUNT(*) FROM books WHERE avail > 0 AND catalog_id IN ({subtree})")
+    };
+    let sql = pool.sql(&sql);
+    let row: (i64,) = sqlx::query_as(&sql)
+        .bind(catalog_id)
+        .fetch_one(pool.inner())
+        .await?;
+    Ok(row.0)
+}
+
+/// Ids of the first `limit` available books with a cover in a catalog and
+/// its descendants (catalog mosaics).
+pub async fn cover_ids_in_catalog_tree(
+    pool: &DbPool,
+    catalog_id: i64,
+    limit: i32,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let subtree = subtree_catalogs_sql();
+    let raw = format!(
+        "SELECT id FROM books WHERE avail > 0 AND cover > 0 AND catalog_id IN ({subtree}) \
+         ORDER BY id LIMIT ?"
+    );
+    let sql = pool.sql(&raw);
+    let rows: Vec<(i64,)> = sqlx::query_as(&sql)
+        .bind(catalog_id)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
 /// Count how many available books share the same search_title and author_key as the given book.
 pub async fn count_doubles(pool: &DbPool, book_id: i64) -> Result<i64, sqlx::Error> {
     let sql = pool.sql(
@@ -681,10 +1846,29 @@ pub async fn count_doubles(pool: &DbPool, book_id: i64) -> Result<i64, sqlx::Err
     Ok(row.0)
 }
 
+/// Other formats of the same work (same title and authors) as an available
+/// book: the lowest book id of each format, ordered by format.
+pub async fn get_other_formats(
+    pool: &DbPool,
+    book_id: i64,
+) -> Result<Vec<(i64, String)>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT MIN(b.id), b.format FROM books b \
+         JOIN books w ON w.search_title = b.search_title AND w.author_key = b.author_key \
+         WHERE w.id = ? AND b.avail > 0 AND b.format <> w.format \
+         GROUP BY b.format ORDER BY b.format",
+    );
+    sqlx::query_as(&sql)
+        .bind(book_id)
+        .fetch_all(pool.inner())
+        .await
+}
+
 /// Alphabet drill-down: get prefix groups for book titles.
 /// Returns `(prefix_string, count)` pairs.
 /// `current_prefix` is the prefix already selected (empty for first level).
-/// `lang_code` = 0 means all languages.
+/// `lang_code` = 0 means all languages. With `hide_doubles`, duplicates of a
+/// book count once, as in [`search_by_title_prefix`].
 ///
 /// The prefix is matched at any word boundary inside `search_title` (start of
 /// the title or immediately after a space). See
@@ -693,12 +1877,19 @@ pub async fn get_title_prefix_groups(
     pool: &DbPool,
     lang_code: i32,
     current_prefix: &str,
+    hide_doubles: bool,
 ) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    let group_by = if hide_doubles {
+        " GROUP BY search_title, author_key"
+    } else {
+        ""
+    };
     let titles: Vec<(String,)> = if current_prefix.is_empty() {
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT search_title FROM books \
-             WHERE avail > 0 AND (? = 0 OR lang_code = ?)",
+             WHERE avail > 0 AND (? = 0 OR lang_code = ?){group_by}"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -707,11 +1898,12 @@ pub async fn get_title_prefix_groups(
     } else {
         let start_pat = format!("{}%", current_prefix);
         let word_pat = format!("% {}%", current_prefix);
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT search_title FROM books \
              WHERE avail > 0 AND (? = 0 OR lang_code = ?) \
-             AND (search_title LIKE ? OR search_title LIKE ?)",
+             AND (search_title LIKE ? OR search_title LIKE ?){group_by}"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -733,10 +1925,14 @@ pub async fn update_title(
     search_title: &str,
     lang_code: i32,
 ) -> Result<(), sqlx::Error> {
-    let sql = pool.sql("UPDATE books SET title = ?, search_title = ?, lang_code = ? WHERE id = ?");
+    let sql = pool.sql(
+        "UPDATE books SET title = ?, search_title = ?, translit_title = ?, lang_code = ? \
+         WHERE id = ?",
+    );
     sqlx::query(&sql)
         .bind(title)
         .bind(search_title)
+        .bind(translit::fold(search_title))
         .bind(lang_code)
         .bind(book_id)
         .execute(pool.inner())
@@ -744,6 +1940,48 @@ pub async fn update_title(
     Ok(())
 }
 
+pub async fn update_annotation(
+    pool: &DbPool,
+    book_id: i64,
+    annotation: &str,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET annotation = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(annotation)
+        .bind(book_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+pub async fn update_lang(pool: &DbPool, book_id: i64, lang: &str) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE books SET lang = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(lang)
+        .bind(book_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// `(id, title, annotation)` of available books with an empty `lang`, in id
+/// order starting after `after_id`.
+pub async fn get_without_lang(
+    pool: &DbPool,
+    after_id: i64,
+    limit: i32,
+) -> Result<Vec<(i64, String, String)>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT id, title, annotation FROM books \
+         WHERE avail > 0 AND lang = '' AND id > ? ORDER BY id LIMIT ?",
+    );
+    sqlx::query_as(&sql)
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
 // ── Duplicate detection queries ──────────────────────────────────────
 
 #[derive(Debug, Clone, sqlx::FromRow)]
@@ -851,14 +2089,7 @@ pub async fn set_book_authors_and_update_key(
     let sql = pool.sql("DELETE FROM book_authors WHERE book_id = ?");
     sqlx::query(&sql).bind(book_id).execute(&mut *tx).await?;
 
-    let link_sql = match pool.backend() {
-        DbBackend::Mysql => "INSERT IGNORE INTO book_authors (book_id, author_id) VALUES (?, ?)",
-        _ => {
-            "INSERT INTO book_authors (book_id, author_id) VALUES (?, ?) \
-             ON CONFLICT (book_id, author_id) DO NOTHING"
-        }
-    };
-    let link_sql = pool.sql(link_sql);
+    let link_sql = pool.upsert_ignore("book_authors", &["book_id", "author_id"]);
     for &author_id in author_ids {
         sqlx::query(&link_sql)
             .bind(book_id)
@@ -903,7 +2134,8 @@ pub async fn set_book_authors_and_update_key(
     Ok(())
 }
 
-/// Delete a book and all its related records (authors, genres, series links, bookshelf).
+/// Delete a book and all its related records (authors, genres, series links, bookshelf,
+/// download history, audiobook details, recommendations).
 pub async fn delete_book_and_relations(pool: &DbPool, book_id: i64) -> Result<(), sqlx::Error> {
     let mut tx = pool.inner().begin().await?;
 
@@ -913,12 +2145,23 @@ pub async fn delete_book_and_relations(pool: &DbPool, book_id: i64) -> Result<()
         "book_series",
         "bookshelf",
         "reading_positions",
+        "downloads",
+        "book_audio",
+        "book_terms",
+        "book_files",
     ] {
         let raw = format!("DELETE FROM {table} WHERE book_id = ?");
         let sql = pool.sql(&raw);
         sqlx::query(&sql).bind(book_id).execute(&mut *tx).await?;
     }
 
+    let sql = pool.sql("DELETE FROM recommendations WHERE book_id = ? OR rec_book_id = ?");
+    sqlx::query(&sql)
+        .bind(book_id)
+        .bind(book_id)
+        .execute(&mut *tx)
+        .await?;
+
     let sql = pool.sql("DELETE FROM books WHERE id = ?");
     sqlx::query(&sql).bind(book_id).execute(&mut *tx).await?;
 
@@ -1056,13 +2299,66 @@ mod tests {
             .fetch_one(pool.inner())
             .await
             .unwrap();
-        row.0
+        row.0
+    }
+
+    #[test]
+    fn test_title_cursor_roundtrip() {
+        let cursor = TitleCursor {
+            search_title: "WAR: AND PEACE".to_string(),
+            id: 42,
+        };
+        assert_eq!(TitleCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(TitleCursor::decode("not a cursor!"), None);
+    }
+
+    #[tokio::test]
+    async fn test_list_after_pages_by_cursor() {
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        for title in ["Delta", "Alpha", "Charlie", "Bravo", "Echo"] {
+            insert_test_book(&pool, cat, title, 2).await;
+        }
+
+        let listing = BookListing::TitlePrefix(String::new());
+        let mut titles = Vec::new();
+        let mut cursor: Option<TitleCursor> = None;
+        loop {
+            let page = list_after(&pool, &listing, cursor.as_ref(), 2, false)
+                .await
+                .unwrap();
+            let Some(last) = page.last() else { break };
+            cursor = Some(TitleCursor::from_book(last));
+            titles.extend(page.into_iter().map(|b| b.title));
+        }
+        assert_eq!(titles, ["Alpha", "Bravo", "Charlie", "Delta", "Echo"]);
+
+        let search = BookListing::TitleSearch("HA".to_string());
+        let found = list_after(&pool, &search, None, 10, true).await.unwrap();
+        let found: Vec<_> = found.into_iter().map(|b| b.title).collect();
+        assert_eq!(found, ["Alpha", "Charlie"]);
+
+        let chance = insert_test_book(&pool, cat, "Chance", 2).await;
+        update_title(&pool, chance, "Шанс", "ШАНС", 1)
+            .await
+            .unwrap();
+        let search = BookListing::TitleSearch("SHANS".to_string());
+        assert!(
+            list_after(&pool, &search, None, 10, true)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let search = BookListing::TranslitTitleSearch("SHANS".to_string());
+        let found = list_after(&pool, &search, None, 10, true).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, chance);
     }
 
     #[tokio::test]
     async fn test_title_prefix_groups_empty() {
         let pool = create_test_pool().await;
-        let groups = get_title_prefix_groups(&pool, 0, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
         assert!(groups.is_empty());
     }
 
@@ -1074,7 +2370,7 @@ mod tests {
         insert_test_book(&pool, cat, "Beta", 2).await;
         insert_test_book(&pool, cat, "Charlie", 2).await;
 
-        let groups = get_title_prefix_groups(&pool, 0, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
         assert_eq!(groups.len(), 3);
         assert_eq!(groups[0], ("A".to_string(), 1));
         assert_eq!(groups[1], ("B".to_string(), 1));
@@ -1090,16 +2386,16 @@ mod tests {
         insert_test_book(&pool, cat, "Alpha", 2).await; // Latin
 
         // lang_code=1 (Cyrillic) — only 2 groups
-        let groups = get_title_prefix_groups(&pool, 1, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 1, "", false).await.unwrap();
         assert_eq!(groups.len(), 2);
 
         // lang_code=2 (Latin) — only 1 group
-        let groups = get_title_prefix_groups(&pool, 2, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 2, "", false).await.unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].0, "A");
 
         // lang_code=0 (all) — all 3 groups
-        let groups = get_title_prefix_groups(&pool, 0, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
         assert_eq!(groups.len(), 3);
     }
 
@@ -1115,13 +2411,13 @@ mod tests {
         insert_test_book(&pool, cat, "Bab", 2).await;
 
         // Top level: "A" with count=3, "B" with count=1
-        let groups = get_title_prefix_groups(&pool, 0, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
         assert_eq!(groups.len(), 2);
         assert_eq!(groups[0], ("A".to_string(), 3));
         assert_eq!(groups[1], ("B".to_string(), 1));
 
         // Drill into "A": 3 sub-groups
-        let groups = get_title_prefix_groups(&pool, 0, "A").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "A", false).await.unwrap();
         assert_eq!(groups.len(), 3);
         assert_eq!(groups[0].0, "AA");
         assert_eq!(groups[1].0, "AB");
@@ -1137,17 +2433,19 @@ mod tests {
         insert_test_book(&pool, cat, "Abethree", 2).await;
 
         // Level 1: all under "A"
-        let groups = get_title_prefix_groups(&pool, 0, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0], ("A".to_string(), 3));
 
         // Level 2: all under "AB"
-        let groups = get_title_prefix_groups(&pool, 0, "A").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "A", false).await.unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0], ("AB".to_string(), 3));
 
         // Level 3: three distinct 3-char prefixes
-        let groups = get_title_prefix_groups(&pool, 0, "AB").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "AB", false)
+            .await
+            .unwrap();
         assert_eq!(groups.len(), 3);
         assert_eq!(groups[0].0, "ABC");
         assert_eq!(groups[1].0, "ABD");
@@ -1167,17 +2465,19 @@ mod tests {
         insert_test_book(&pool, cat, "Lamabad Cafir", 2).await;
 
         // Drill into "A" — every title above has at least one word starting with A.
-        let groups = get_title_prefix_groups(&pool, 0, "A").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "A", false).await.unwrap();
         let ab = groups.iter().find(|(p, _)| p == "AB").map(|(_, c)| *c);
         assert_eq!(ab, Some(3));
 
         // Drill into "AB" — only the three "real AB-word" titles.
-        let groups = get_title_prefix_groups(&pool, 0, "AB").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "AB", false)
+            .await
+            .unwrap();
         let total: i64 = groups.iter().map(|(_, c)| *c).sum();
         assert_eq!(total, 3);
 
         // Listing should return the same three titles, sorted by search_title.
-        let results = search_by_title_prefix(&pool, "AB", 100, 0, false)
+        let results = search_by_title_prefix(&pool, "AB", 100, 0, false, BookSort::Title)
             .await
             .unwrap();
         let titles: Vec<&str> = results.iter().map(|b| b.title.as_str()).collect();
@@ -1203,7 +2503,7 @@ mod tests {
         insert_test_book(&pool, cat, "Bravo", 2).await;
         insert_test_book(&pool, cat, "Charlie", 2).await;
 
-        let groups = get_title_prefix_groups(&pool, 0, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
         assert_eq!(groups.len(), 3);
         assert_eq!(groups[0], ("A".to_string(), 3));
         assert_eq!(groups[1], ("B".to_string(), 2));
@@ -1225,11 +2525,42 @@ mod tests {
             .await
             .unwrap();
 
-        let groups = get_title_prefix_groups(&pool, 0, "").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].0, "B");
     }
 
+    #[tokio::test]
+    async fn test_title_prefix_groups_hide_doubles_matches_listing() {
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        for filename in ["a1.fb2", "a2.fb2"] {
+            insert_test_book_custom(
+                &pool,
+                cat,
+                filename,
+                "/test",
+                "Alpha",
+                "ALPHA",
+                CatType::Normal,
+            )
+            .await;
+        }
+        insert_test_book(&pool, cat, "Atlas", 2).await;
+
+        let groups = get_title_prefix_groups(&pool, 0, "", false).await.unwrap();
+        assert_eq!(groups, vec![("A".to_string(), 3)]);
+        let groups = get_title_prefix_groups(&pool, 0, "", true).await.unwrap();
+        assert_eq!(groups, vec![("A".to_string(), 2)]);
+        let groups = get_title_prefix_groups(&pool, 0, "A", true).await.unwrap();
+        let total: i64 = groups.iter().map(|(_, count)| count).sum();
+        let listed = search_by_title_prefix(&pool, "A", 100, 0, true, BookSort::Title)
+            .await
+            .unwrap();
+        assert_eq!(total, listed.len() as i64);
+        assert_eq!(count_by_title_prefix(&pool, "A", true).await.unwrap(), 2);
+    }
+
     #[tokio::test]
     async fn test_search_by_title_prefix() {
         let pool = create_test_pool().await;
@@ -1239,27 +2570,27 @@ mod tests {
         insert_test_book(&pool, cat, "Beta", 2).await;
 
         // Prefix "A" matches "Alpha" and "Another"
-        let results = search_by_title_prefix(&pool, "A", 100, 0, false)
+        let results = search_by_title_prefix(&pool, "A", 100, 0, false, BookSort::Title)
             .await
             .unwrap();
         assert_eq!(results.len(), 2);
 
         // Prefix "AL" matches only "Alpha"
-        let results = search_by_title_prefix(&pool, "AL", 100, 0, false)
+        let results = search_by_title_prefix(&pool, "AL", 100, 0, false, BookSort::Title)
             .await
             .unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].title, "Alpha");
 
         // Prefix "B" matches only "Beta"
-        let results = search_by_title_prefix(&pool, "B", 100, 0, false)
+        let results = search_by_title_prefix(&pool, "B", 100, 0, false, BookSort::Title)
             .await
             .unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].title, "Beta");
 
         // Prefix "Z" matches nothing
-        let results = search_by_title_prefix(&pool, "Z", 100, 0, false)
+        let results = search_by_title_prefix(&pool, "Z", 100, 0, false, BookSort::Title)
             .await
             .unwrap();
         assert!(results.is_empty());
@@ -1275,13 +2606,13 @@ mod tests {
         insert_test_book(&pool, cat, "Ad", 2).await;
 
         // Page 1: limit 2, offset 0
-        let page1 = search_by_title_prefix(&pool, "A", 2, 0, false)
+        let page1 = search_by_title_prefix(&pool, "A", 2, 0, false, BookSort::Title)
             .await
             .unwrap();
         assert_eq!(page1.len(), 2);
 
         // Page 2: limit 2, offset 2
-        let page2 = search_by_title_prefix(&pool, "A", 2, 2, false)
+        let page2 = search_by_title_prefix(&pool, "A", 2, 2, false, BookSort::Title)
             .await
             .unwrap();
         assert_eq!(page2.len(), 2);
@@ -1301,15 +2632,211 @@ mod tests {
         insert_test_book(&pool, cat, "Alpha", 2).await;
 
         // Drill into Cyrillic "А" — should see 2 sub-prefixes
-        let groups = get_title_prefix_groups(&pool, 1, "А").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 1, "А", false).await.unwrap();
         assert_eq!(groups.len(), 2);
 
         // Drill into Latin "A" — should see 1 sub-prefix
-        let groups = get_title_prefix_groups(&pool, 2, "A").await.unwrap();
+        let groups = get_title_prefix_groups(&pool, 2, "A", false).await.unwrap();
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].0, "AL");
     }
 
+    #[tokio::test]
+    async fn test_get_by_catalog_tree_includes_descendants_only() {
+        use crate::db::queries::catalogs;
+
+        let pool = create_test_pool().await;
+        let top = catalogs::insert(&pool, None, "lib", "lib", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let child = catalogs::insert(&pool, Some(top), "lib/a", "a", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let grandchild =
+            catalogs::insert(&pool, Some(child), "lib/a/b", "b", CatType::Normal, 0, "")
+                .await
+                .unwrap();
+        // Siblings whose names share the prefix must stay out of the subtree.
+        let dash = catalogs::insert(&pool, None, "lib-x", "lib-x", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let longer = catalogs::insert(&pool, None, "libz", "libz", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+
+        insert_test_book(&pool, top, "Top", 2).await;
+        insert_test_book(&pool, child, "Child", 2).await;
+        insert_test_book(&pool, grandchild, "Grandchild", 2).await;
+        insert_test_book(&pool, dash, "Dash", 2).await;
+        insert_test_book(&pool, longer, "Longer", 2).await;
+
+        let rows = get_by_catalog_tree(&pool, top, 100, 0, false, BookSort::Title)
+            .await
+            .unwrap();
+        let titles: Vec<&str> = rows.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["Child", "Grandchild", "Top"]);
+        assert_eq!(count_by_catalog_tree(&pool, top, false).await.unwrap(), 3);
+        assert_eq!(count_by_catalog_tree(&pool, child, true).await.unwrap(), 2);
+
+        let page = get_by_catalog_tree(&pool, top, 1, 1, true, BookSort::Title)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].title, "Grandchild");
+
+        // The subtree follows the display structure, not the paths.
+        catalogs::move_to(&pool, child, Some(dash)).await.unwrap();
+        assert_eq!(count_by_catalog_tree(&pool, top, false).await.unwrap(), 1);
+        let rows = get_by_catalog_tree(&pool, dash, 100, 0, false, BookSort::Title)
+            .await
+            .unwrap();
+        let titles: Vec<&str> = rows.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["Child", "Dash", "Grandchild"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_by_lang_code() {
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        insert_test_book(&pool, cat, "Война", 1).await;
+        insert_test_book(&pool, cat, "Zebra", 2).await;
+        insert_test_book(&pool, cat, "Apple", 2).await;
+
+        let latin = get_by_lang_code(&pool, 2, 10, 0, false, BookSort::Title)
+            .await
+            .unwrap();
+        let titles: Vec<&str> = latin.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["Apple", "Zebra"]);
+
+        let all = get_by_lang_code(&pool, 0, 10, 0, true, BookSort::Title)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 3);
+        let page = get_by_lang_code(&pool, 0, 1, 1, false, BookSort::Title)
+            .await
+            .unwrap();
+        assert_eq!(page[0].title, "Zebra");
+    }
+
+    #[tokio::test]
+    async fn test_get_by_catalog_sort_orders() {
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        let mut ids = Vec::new();
+        for (title, docdate, size) in [
+            ("Beta", "2001", 100),
+            ("Alpha", "1999", 300),
+            ("Gamma", "", 200),
+        ] {
+            let id = insert(
+                &pool,
+                cat,
+                &format!("{title}.fb2"),
+                "/test",
+                "fb2",
+                title,
+                &title.to_uppercase(),
+                "",
+                docdate,
+                "ru",
+                2,
+                size,
+                CatType::Normal,
+                0,
+                "",
+            )
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+        let ser = insert_test_series(&pool, "Saga").await;
+        crate::db::queries::series::link_book(&pool, ids[2], ser, 1)
+            .await
+            .unwrap();
+        crate::db::queries::series::link_book(&pool, ids[0], ser, 2)
+            .await
+            .unwrap();
+
+        let titles = |rows: Vec<Book>| rows.into_iter().map(|b| b.title).collect::<Vec<_>>();
+        let listed = |sort| get_by_catalog(&pool, cat, 100, 0, false, sort);
+        assert_eq!(
+            titles(listed(BookSort::Title).await.unwrap()),
+            ["Alpha", "Beta", "Gamma"]
+        );
+        assert_eq!(
+            titles(listed(BookSort::Recent).await.unwrap()),
+            ["Gamma", "Alpha", "Beta"]
+        );
+        assert_eq!(
+            titles(listed(BookSort::Size).await.unwrap()),
+            ["Alpha", "Gamma", "Beta"]
+        );
+        assert_eq!(
+            titles(listed(BookSort::Year).await.unwrap()),
+            ["Beta", "Alpha", "Gamma"]
+        );
+        assert_eq!(
+            titles(listed(BookSort::Series).await.unwrap()),
+            ["Gamma", "Beta", "Alpha"]
+        );
+        for id in [ids[0], ids[0], ids[2]] {
+            crate::db::queries::downloads::record(&pool, id, None)
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            titles(listed(BookSort::Rating).await.unwrap()),
+            ["Beta", "Gamma", "Alpha"]
+        );
+
+        assert_eq!(
+            BookSort::resolve(&BookSort::LISTING, Some("size")),
+            BookSort::Size
+        );
+        assert_eq!(
+            BookSort::resolve(&BookSort::LISTING, Some("bogus")),
+            BookSort::Title
+        );
+        assert_eq!(
+            BookSort::resolve(&BookSort::SERIES_LISTING, None),
+            BookSort::Series
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_random_and_recent_in_genres() {
+        use crate::db::queries::{bookshelf, genres};
+
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        let fantasy = insert_test_genre(&pool, "fantasy_test").await;
+        let poetry = insert_test_genre(&pool, "poetry_test").await;
+        let read = insert_test_book(&pool, cat, "Read Fantasy", 2).await;
+        let fresh = insert_test_book(&pool, cat, "Fresh Fantasy", 2).await;
+        let other = insert_test_book(&pool, cat, "Poems", 2).await;
+        genres::link_book(&pool, read, fantasy).await.unwrap();
+        genres::link_book(&pool, fresh, fantasy).await.unwrap();
+        genres::link_book(&pool, other, poetry).await.unwrap();
+        bookshelf::upsert(&pool, 7, read).await.unwrap();
+
+        let favorites = genres::get_favorite_ids(&pool, 7, 3).await.unwrap();
+        assert_eq!(favorites, vec![fantasy]);
+        let picks = get_recent_in_genres(&pool, &favorites, 7, 10)
+            .await
+            .unwrap();
+        assert_eq!(picks.iter().map(|b| b.id).collect::<Vec<_>>(), vec![fresh]);
+        assert!(
+            get_recent_in_genres(&pool, &[], 7, 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let random = get_random_list(&pool, 2).await.unwrap();
+        assert_eq!(random.len(), 2);
+        assert_eq!(get_random_list(&pool, 10).await.unwrap().len(), 3);
+    }
+
     #[tokio::test]
     async fn test_get_by_catalog_and_find_by_path_with_doubles() {
         let pool = create_test_pool().await;
@@ -1348,10 +2875,14 @@ mod tests {
         // Availability filter should exclude this row from listing queries.
         set_avail(&pool, beta, AvailStatus::Deleted).await.unwrap();
 
-        let all_rows = get_by_catalog(&pool, cat, 100, 0, false).await.unwrap();
+        let all_rows = get_by_catalog(&pool, cat, 100, 0, false, BookSort::Title)
+            .await
+            .unwrap();
         assert_eq!(all_rows.len(), 2);
 
-        let deduped_rows = get_by_catalog(&pool, cat, 100, 0, true).await.unwrap();
+        let deduped_rows = get_by_catalog(&pool, cat, 100, 0, true, BookSort::Title)
+            .await
+            .unwrap();
         assert_eq!(deduped_rows.len(), 1);
         assert_eq!(deduped_rows[0].search_title, "ALPHA");
 
@@ -1428,42 +2959,42 @@ mod tests {
             .unwrap();
 
         assert_eq!(
-            get_by_author(&pool, author, 100, 0, false)
+            get_by_author(&pool, author, 100, 0, false, BookSort::Title)
                 .await
                 .unwrap()
                 .len(),
             2
         );
         assert_eq!(
-            get_by_author(&pool, author, 100, 0, true)
+            get_by_author(&pool, author, 100, 0, true, BookSort::Title)
                 .await
                 .unwrap()
                 .len(),
             1
         );
         assert_eq!(
-            get_by_genre(&pool, genre, 100, 0, false)
+            get_by_genre(&pool, genre, 100, 0, false, BookSort::Title)
                 .await
                 .unwrap()
                 .len(),
             2
         );
         assert_eq!(
-            get_by_genre(&pool, genre, 100, 0, true)
+            get_by_genre(&pool, genre, 100, 0, true, BookSort::Title)
                 .await
                 .unwrap()
                 .len(),
             1
         );
         assert_eq!(
-            get_by_series(&pool, series, 100, 0, false)
+            get_by_series(&pool, series, 100, 0, false, BookSort::Title)
                 .await
                 .unwrap()
                 .len(),
             2
         );
         assert_eq!(
-            get_by_series(&pool, series, 100, 0, true)
+            get_by_series(&pool, series, 100, 0, true, BookSort::Title)
                 .await
                 .unwrap()
                 .len(),
@@ -1507,21 +3038,65 @@ mod tests {
         .await;
 
         assert_eq!(
-            search_by_title(&pool, "FOO", 100, 0, false)
-                .await
-                .unwrap()
-                .len(),
+            search_by_title(
+                &pool,
+                "FOO",
+                MatchMode::Contains,
+                100,
+                0,
+                false,
+                BookSort::Title,
+                false,
+                &SearchFilter::default()
+            )
+            .await
+            .unwrap()
+            .len(),
             2
         );
         assert_eq!(
-            search_by_title(&pool, "FOO", 100, 0, true)
-                .await
-                .unwrap()
-                .len(),
+            search_by_title(
+                &pool,
+                "FOO",
+                MatchMode::Contains,
+                100,
+                0,
+                true,
+                BookSort::Title,
+                false,
+                &SearchFilter::default()
+            )
+            .await
+            .unwrap()
+            .len(),
+            1
+        );
+        assert_eq!(
+            count_by_title_search(
+                &pool,
+                "FOO",
+                MatchMode::Contains,
+                false,
+                false,
+                &SearchFilter::default()
+            )
+            .await
+            .unwrap(),
+            2
+        );
+        assert_eq!(
+            count_by_title_search(
+                &pool,
+                "FOO",
+                MatchMode::Contains,
+                true,
+                false,
+                &SearchFilter::default()
+            )
+            .await
+            .unwrap(),
             1
         );
-        assert_eq!(count_by_title_search(&pool, "FOO", false).await.unwrap(), 2);
-        assert_eq!(count_by_title_search(&pool, "FOO", true).await.unwrap(), 1);
         assert_eq!(count_by_title_prefix(&pool, "FO", false).await.unwrap(), 2);
         assert_eq!(count_by_title_prefix(&pool, "FO", true).await.unwrap(), 1);
 
@@ -1532,6 +3107,174 @@ mod tests {
         assert_eq!(row.title, "Updated");
         assert_eq!(row.search_title, "UPDATED");
         assert_eq!(row.lang_code, 3);
+
+        // Transliteration keys follow title updates and only match when enabled.
+        update_title(&pool, b1, "Идиот", "ИДИОТ", 1).await.unwrap();
+        let found = search_by_title(
+            &pool,
+            "IDIOT",
+            MatchMode::Contains,
+            100,
+            0,
+            false,
+            BookSort::Title,
+            true,
+            &SearchFilter::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, b1);
+        assert_eq!(
+            count_by_title_search(
+                &pool,
+                "IDIOT",
+                MatchMode::Contains,
+                true,
+                true,
+                &SearchFilter::default()
+            )
+            .await
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            count_by_title_search(
+                &pool,
+                "IDIOT",
+                MatchMode::Contains,
+                true,
+                false,
+                &SearchFilter::default()
+            )
+            .await
+            .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_title_match_modes() {
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        let war = insert_test_book(&pool, cat, "War and Peace", 2).await;
+        let cold = insert_test_book(&pool, cat, "The Cold War", 2).await;
+        insert_test_book(&pool, cat, "Postwar Stories", 2).await;
+
+        let ids = |mode: MatchMode, term: &'static str| {
+            let pool = pool.clone();
+            async move {
+                let filter = SearchFilter::default();
+                let found = search_by_title(
+                    &pool,
+                    term,
+                    mode,
+                    100,
+                    0,
+                    false,
+                    BookSort::Recent,
+                    false,
+                    &filter,
+                )
+                .await
+                .unwrap();
+                let count = count_by_title_search(&pool, term, mode, false, false, &filter)
+                    .await
+                    .unwrap();
+                assert_eq!(count, found.len() as i64, "{mode:?} {term}");
+                found.into_iter().map(|b| b.id).collect::<Vec<_>>()
+            }
+        };
+        // Contains also matches inside a word; begins needs a word start.
+        assert_eq!(ids(MatchMode::Contains, "WAR").await.len(), 3);
+        assert_eq!(ids(MatchMode::Begins, "WAR").await, [cold, war]);
+        assert_eq!(ids(MatchMode::Exact, "WAR").await, Vec::<i64>::new());
+        assert_eq!(ids(MatchMode::Exact, "WAR AND PEACE").await, [war]);
+    }
+
+    #[tokio::test]
+    async fn test_search_filter_and_facets() {
+        use crate::db::queries::genres;
+
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        let fantasy = insert_test_genre(&pool, "fantasy_test").await;
+        let ru = insert_test_book(&pool, cat, "Dragon Tale", 2).await;
+        let en = insert_test_book(&pool, cat, "Dragon Song", 2).await;
+        sqlx::query(&pool.sql("UPDATE books SET lang = 'EN', format = 'epub' WHERE id = ?"))
+            .bind(en)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        genres::link_book(&pool, ru, fantasy).await.unwrap();
+
+        let parsed = SearchFilter::parse(Some(" 7 "), Some("EN"), Some(""));
+        assert_eq!(parsed.genre_id, Some(7));
+        assert_eq!(parsed.lang.as_deref(), Some("en"));
+        assert_eq!(parsed.format, None);
+        assert!(SearchFilter::parse(Some("x"), None, None).is_empty());
+
+        let search = |filter: SearchFilter| {
+            let pool = pool.clone();
+            async move {
+                search_by_title(
+                    &pool,
+                    "DRAGON",
+                    MatchMode::Contains,
+                    100,
+                    0,
+                    true,
+                    BookSort::Title,
+                    false,
+                    &filter,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|b| b.id)
+                .collect::<Vec<_>>()
+            }
+        };
+        let by_lang = SearchFilter {
+            lang: Some("en".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search(by_lang.clone()).await, [en]);
+        let by_genre = SearchFilter {
+            genre_id: Some(fantasy),
+            ..Default::default()
+        };
+        assert_eq!(search(by_genre.clone()).await, [ru]);
+        assert_eq!(
+            count_by_title_search(
+                &pool,
+                "DRAGON",
+                MatchMode::Contains,
+                false,
+                false,
+                &by_genre
+            )
+            .await
+            .unwrap(),
+            1
+        );
+
+        // Each group ignores its own filter, so the language facet still
+        // lists both languages while English is selected.
+        let facets = title_search_facets(
+            &pool,
+            "DRAGON",
+            MatchMode::Contains,
+            false,
+            &by_lang,
+            "en",
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(facets.langs, [("en".to_string(), 1), ("ru".to_string(), 1)]);
+        assert_eq!(facets.formats, [("epub".to_string(), 1)]);
+        assert!(facets.genres.is_empty());
     }
 
     #[tokio::test]
@@ -1596,16 +3339,16 @@ mod tests {
         set_avail(&pool, normal, AvailStatus::Deleted)
             .await
             .unwrap();
-        let marked_deleted = logical_delete_unavailable(&pool).await.unwrap();
+        let marked_deleted = logical_delete_unavailable(&pool, None).await.unwrap();
         assert_eq!(marked_deleted, 3);
 
-        let mut unavailable_ids = get_unavailable_ids(&pool).await.unwrap();
+        let mut unavailable_ids = get_unavailable_ids(&pool, None).await.unwrap();
         unavailable_ids.sort_unstable();
         let mut expected = vec![normal, inpx_a, inpx_b];
         expected.sort_unstable();
         assert_eq!(unavailable_ids, expected);
 
-        let physically_deleted = physical_delete_unavailable(&pool).await.unwrap();
+        let physically_deleted = physical_delete_unavailable(&pool, None).await.unwrap();
         assert_eq!(physically_deleted, 3);
         assert!(get_by_id(&pool, normal).await.unwrap().is_none());
         assert!(get_by_id(&pool, inpx_a).await.unwrap().is_none());
@@ -1636,6 +3379,56 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_subtree_scoped_avail_and_cleanup() {
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        let inside = |name: &'static str, path: &'static str| {
+            let pool = pool.clone();
+            async move {
+                insert_test_book_custom(&pool, cat, name, path, name, name, CatType::Normal).await
+            }
+        };
+        let top = inside("top.fb2", "fiction/new").await;
+        let nested = inside("nested.fb2", "fiction/new/deep").await;
+        let sibling = inside("sibling.fb2", "fiction/newer").await;
+        let outside = inside("outside.fb2", "poetry").await;
+        set_avail(&pool, outside, AvailStatus::Unverified)
+            .await
+            .unwrap();
+
+        let marked = set_avail_under_path(&pool, "fiction/new", AvailStatus::Unverified)
+            .await
+            .unwrap();
+        assert_eq!(marked, 2);
+        assert_eq!(
+            get_by_id(&pool, sibling).await.unwrap().unwrap().avail,
+            AvailStatus::Confirmed as i32
+        );
+
+        let mut ids = get_unavailable_ids(&pool, Some("fiction/new"))
+            .await
+            .unwrap();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![top, nested]);
+        let deleted = logical_delete_unavailable(&pool, Some("fiction/new"))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        assert_eq!(
+            get_by_id(&pool, outside).await.unwrap().unwrap().avail,
+            AvailStatus::Unverified as i32,
+            "books outside the subtree are left alone"
+        );
+        assert_eq!(
+            physical_delete_unavailable(&pool, Some("poetry"))
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(get_by_id(&pool, outside).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_set_avail_confirmed_for_ids_only_marks_unverified_rows() {
         let pool = create_test_pool().await;
@@ -1840,11 +3633,15 @@ mod tests {
         update_author_key(&pool, b2).await.unwrap();
 
         // Without hide_doubles: both visible
-        let all = get_by_catalog(&pool, cat, 100, 0, false).await.unwrap();
+        let all = get_by_catalog(&pool, cat, 100, 0, false, BookSort::Title)
+            .await
+            .unwrap();
         assert_eq!(all.len(), 2);
 
         // With hide_doubles: still both visible (different author_key)
-        let deduped = get_by_catalog(&pool, cat, 100, 0, true).await.unwrap();
+        let deduped = get_by_catalog(&pool, cat, 100, 0, true, BookSort::Title)
+            .await
+            .unwrap();
         assert_eq!(deduped.len(), 2);
     }
 
@@ -1882,11 +3679,15 @@ mod tests {
         update_author_key(&pool, b2).await.unwrap();
 
         // Without hide_doubles: both visible
-        let all = get_by_catalog(&pool, cat, 100, 0, false).await.unwrap();
+        let all = get_by_catalog(&pool, cat, 100, 0, false, BookSort::Title)
+            .await
+            .unwrap();
         assert_eq!(all.len(), 2);
 
         // With hide_doubles: deduplicated to one (same search_title + author_key)
-        let deduped = get_by_catalog(&pool, cat, 100, 0, true).await.unwrap();
+        let deduped = get_by_catalog(&pool, cat, 100, 0, true, BookSort::Title)
+            .await
+            .unwrap();
         assert_eq!(deduped.len(), 1);
     }
 
@@ -1945,6 +3746,58 @@ mod tests {
         assert_eq!(count_doubles(&pool, b3).await.unwrap(), 1);
     }
 
+    #[tokio::test]
+    async fn test_get_other_formats_of_work() {
+        let pool = create_test_pool().await;
+        let cat = ensure_catalog(&pool).await;
+        let author_a = insert_test_author(&pool, "Writer A").await;
+        let author_b = insert_test_author(&pool, "Writer B").await;
+
+        let mut ids = Vec::new();
+        for (filename, format, author) in [
+            ("w.fb2", "fb2", author_a),
+            ("w2.pdf", "pdf", author_a),
+            ("w1.epub", "epub", author_a),
+            ("w2.epub", "epub", author_a),
+            ("other.epub", "epub", author_b),
+        ] {
+            let id = insert(
+                &pool,
+                cat,
+                filename,
+                "/test/work",
+                format,
+                "Work",
+                "WORK",
+                "",
+                "",
+                "en",
+                2,
+                1000,
+                CatType::Normal,
+                0,
+                "",
+            )
+            .await
+            .unwrap();
+            link_author(&pool, id, author).await;
+            update_author_key(&pool, id).await.unwrap();
+            ids.push(id);
+        }
+
+        let formats = get_other_formats(&pool, ids[0]).await.unwrap();
+        assert_eq!(
+            formats,
+            vec![(ids[2], "epub".to_string()), (ids[1], "pdf".to_string())]
+        );
+        let formats = get_other_formats(&pool, ids[3]).await.unwrap();
+        assert_eq!(
+            formats,
+            vec![(ids[0], "fb2".to_string()), (ids[1], "pdf".to_string())]
+        );
+        assert!(get_other_formats(&pool, ids[4]).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_duplicate_groups_detection() {
         let pool = create_test_pool().await;
@@ -2080,7 +3933,16 @@ mod tests {
         // Different author_key → hide_doubles should keep both
         assert_eq!(count_by_catalog(&pool, cat, true).await.unwrap(), 2);
         assert_eq!(
-            count_by_title_search(&pool, "COUNT", true).await.unwrap(),
+            count_by_title_search(
+                &pool,
+                "COUNT",
+                MatchMode::Contains,
+                true,
+                false,
+                &SearchFilter::default()
+            )
+            .await
+            .unwrap(),
             2
         );
         assert_eq!(count_by_title_prefix(&pool, "CO", true).await.unwrap(), 2);
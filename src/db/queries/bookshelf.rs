@@ -2,12 +2,102 @@ use crate::db::DbPool;
 use crate::db::models::Book;
 
 /// Bookshelf sort column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortColumn {
     Date,
     Title,
     Author,
 }
 
+impl SortColumn {
+    pub const ALL: [SortColumn; 3] = [Self::Date, Self::Title, Self::Author];
+
+    /// Parse a `sort` query parameter; unknown values sort by date.
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "title" => Self::Title,
+            "author" => Self::Author,
+            _ => Self::Date,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Title => "title",
+            Self::Author => "author",
+        }
+    }
+
+    /// Natural direction: newest first for dates, A to Z for names.
+    pub fn default_ascending(self) -> bool {
+        self != Self::Date
+    }
+}
+
+/// Progress from which a book on the shelf counts as finished.
+pub const FINISHED_PROGRESS: f64 = 0.98;
+
+/// Bookshelf filter by reading progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadState {
+    #[default]
+    All,
+    /// Never opened in the reader.
+    Unread,
+    /// Started but not finished.
+    Reading,
+    /// Read up to [`FINISHED_PROGRESS`].
+    Finished,
+}
+
+impl ReadState {
+    /// Parse a `state` query parameter; unknown values mean no filter.
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "unread" => Self::Unread,
+            "reading" => Self::Reading,
+            "finished" => Self::Finished,
+            _ => Self::All,
+        }
+    }
+
+    pub const ALL: [ReadState; 4] = [Self::All, Self::Unread, Self::Reading, Self::Finished];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Unread => "unread",
+            Self::Reading => "reading",
+            Self::Finished => "finished",
+        }
+    }
+
+    /// SQL condition on the `bs` bookshelf row, starting with `AND`.
+    fn condition(self) -> String {
+        let exists = |progress: &str| {
+            format!(
+                "EXISTS (SELECT 1 FROM reading_positions rp \
+                 WHERE rp.user_id = bs.user_id AND rp.book_id = bs.book_id AND {progress})"
+            )
+        };
+        match self {
+            Self::All => String::new(),
+            Self::Unread => format!(" AND NOT {}", exists("rp.progress > 0")),
+            Self::Reading => format!(
+                " AND {}",
+                exists(&format!(
+                    "rp.progress > 0 AND rp.progress < {FINISHED_PROGRESS}"
+                ))
+            ),
+            Self::Finished => format!(
+                " AND {}",
+                exists(&format!("rp.progress >= {FINISHED_PROGRESS}"))
+            ),
+        }
+    }
+}
+
 /// Add or update a book on the user's bookshelf.
 /// Uses ON CONFLICT to update read_time on re-download.
 pub async fn upsert(pool: &DbPool, user_id: i64, book_id: i64) -> Result<(), sqlx::Error> {
@@ -39,12 +129,35 @@ pub async fn get_by_user(
     limit: i32,
     offset: i32,
 ) -> Result<Vec<Book>, sqlx::Error> {
+    get_filtered(
+        pool,
+        user_id,
+        sort,
+        ascending,
+        ReadState::All,
+        limit,
+        offset,
+    )
+    .await
+}
+
+/// Get books on user's bookshelf in a reading state, with configurable sorting.
+pub async fn get_filtered(
+    pool: &DbPool,
+    user_id: i64,
+    sort: &SortColumn,
+    ascending: bool,
+    state: ReadState,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let filter = state.condition();
     let dir = if ascending { "ASC" } else { "DESC" };
     let raw = match sort {
         SortColumn::Date => format!(
             "SELECT b.* FROM books b \
              JOIN bookshelf bs ON bs.book_id = b.id \
-             WHERE bs.user_id = ? \
+             WHERE bs.user_id = ?{filter} \
              ORDER BY bs.read_time {dir} \
              LIMIT ? OFFSET ?"
         ),
@@ -56,7 +169,7 @@ pub async fn get_by_user(
             format!(
                 "SELECT b.* FROM books b \
                  JOIN bookshelf bs ON bs.book_id = b.id \
-                 WHERE bs.user_id = ? \
+                 WHERE bs.user_id = ?{filter} \
                  ORDER BY {order_expr} \
                  LIMIT ? OFFSET ?"
             )
@@ -73,7 +186,7 @@ pub async fn get_by_user(
                  JOIN bookshelf bs ON bs.book_id = b.id \
                  LEFT JOIN book_authors ba ON ba.book_id = b.id \
                  LEFT JOIN authors a ON a.id = ba.author_id \
-                 WHERE bs.user_id = ? \
+                 WHERE bs.user_id = ?{filter} \
                  GROUP BY b.id \
                  ORDER BY {order_expr} \
                  LIMIT ? OFFSET ?"
@@ -104,7 +217,20 @@ pub async fn get_read_times(
 
 /// Count books on user's bookshelf.
 pub async fn count_by_user(pool: &DbPool, user_id: i64) -> Result<i64, sqlx::Error> {
-    let sql = pool.sql("SELECT COUNT(*) FROM bookshelf WHERE user_id = ?");
+    count_filtered(pool, user_id, ReadState::All).await
+}
+
+/// Count books on user's bookshelf in a reading state.
+pub async fn count_filtered(
+    pool: &DbPool,
+    user_id: i64,
+    state: ReadState,
+) -> Result<i64, sqlx::Error> {
+    let raw = format!(
+        "SELECT COUNT(*) FROM bookshelf bs WHERE bs.user_id = ?{}",
+        state.condition()
+    );
+    let sql = pool.sql(&raw);
     let row: (i64,) = sqlx::query_as(&sql)
         .bind(user_id)
         .fetch_one(pool.inner())
@@ -311,4 +437,53 @@ mod tests {
         assert_eq!(by_date_desc[0].id, b_zulu);
         assert_eq!(by_date_desc[1].id, b_alpha);
     }
+
+    #[tokio::test]
+    async fn test_read_state_filters() {
+        use crate::db::queries::reading_positions::save_position;
+
+        let pool = create_test_pool().await;
+        let user_id = insert_user(&pool, "state_user").await;
+        let catalog_id = ensure_catalog(&pool).await;
+        let unread = insert_book(&pool, catalog_id, "Unread").await;
+        let opened = insert_book(&pool, catalog_id, "Opened").await;
+        let reading = insert_book(&pool, catalog_id, "Reading").await;
+        let finished = insert_book(&pool, catalog_id, "Finished").await;
+        for id in [unread, opened, reading, finished] {
+            upsert(&pool, user_id, id).await.unwrap();
+        }
+        save_position(&pool, user_id, opened, "start", 0.0, 100)
+            .await
+            .unwrap();
+        save_position(&pool, user_id, reading, "middle", 0.4, 100)
+            .await
+            .unwrap();
+        save_position(&pool, user_id, finished, "end", 0.99, 100)
+            .await
+            .unwrap();
+
+        let ids = |state| {
+            let pool = &pool;
+            async move {
+                get_filtered(pool, user_id, &SortColumn::Title, true, state, 10, 0)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|b| b.id)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(ids(ReadState::Unread).await, vec![opened, unread]);
+        assert_eq!(ids(ReadState::Reading).await, vec![reading]);
+        assert_eq!(ids(ReadState::Finished).await, vec![finished]);
+        assert_eq!(ids(ReadState::All).await.len(), 4);
+        assert_eq!(
+            count_filtered(&pool, user_id, ReadState::Unread)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(ReadState::parse("finished"), ReadState::Finished);
+        assert_eq!(ReadState::parse("bogus"), ReadState::All);
+    }
 }
@@ -0,0 +1,174 @@
+//! Expensive browse queries memoized in the [`AppState`] cache until the
+//! library changes, so e-readers paging through the same menus do not hit
+//! the database each time. Keys carry every query parameter.
+
+use crate::db::models::{Catalog, Genre};
+use crate::db::queries::counters::{self, ItemCounts};
+use crate::db::queries::{authors, books, catalogs, genres, series};
+use crate::state::AppState;
+
+pub async fn root_catalogs(state: &AppState) -> Result<Vec<Catalog>, sqlx::Error> {
+    state
+        .library_cached("catalogs:root", || catalogs::get_root_catalogs(&state.db))
+        .await
+}
+
+pub async fn author_prefix_groups(
+    state: &AppState,
+    lang_code: i32,
+    prefix: &str,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    state
+        .library_cached(&format!("authors:groups:{lang_code}:{prefix}"), || {
+            authors::get_name_prefix_groups(&state.db, lang_code, prefix)
+        })
+        .await
+}
+
+pub async fn series_prefix_groups(
+    state: &AppState,
+    lang_code: i32,
+    prefix: &str,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    state
+        .library_cached(&format!("series:groups:{lang_code}:{prefix}"), || {
+            series::get_name_prefix_groups(&state.db, lang_code, prefix)
+        })
+        .await
+}
+
+/// Title prefix groups; duplicates count once when `opds.hide_doubles` is set.
+pub async fn title_prefix_groups(
+    state: &AppState,
+    lang_code: i32,
+    prefix: &str,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    let hide_doubles = state.config.opds.hide_doubles;
+    state
+        .library_cached(
+            &format!("books:groups:{lang_code}:{prefix}:{hide_doubles}"),
+            || books::get_title_prefix_groups(&state.db, lang_code, prefix, hide_doubles),
+        )
+        .await
+}
+
+pub async fn author_count(
+    state: &AppState,
+    lang_code: i32,
+    prefix: &str,
+) -> Result<i64, sqlx::Error> {
+    state
+        .library_cached(&format!("authors:count:{lang_code}:{prefix}"), || {
+            authors::count_by_lang_code_prefix(&state.db, lang_code, prefix)
+        })
+        .await
+}
+
+pub async fn series_count(
+    state: &AppState,
+    lang_code: i32,
+    prefix: &str,
+) -> Result<i64, sqlx::Error> {
+    state
+        .library_cached(&format!("series:count:{lang_code}:{prefix}"), || {
+            series::count_by_lang_code_prefix(&state.db, lang_code, prefix)
+        })
+        .await
+}
+
+pub async fn genre_sections(
+    state: &AppState,
+    lang: &str,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    state
+        .library_cached(&format!("genres:sections:{lang}"), || {
+            genres::get_sections(&state.db, lang)
+        })
+        .await
+}
+
+pub async fn genres_in_section(
+    state: &AppState,
+    section_code: &str,
+    lang: &str,
+) -> Result<Vec<Genre>, sqlx::Error> {
+    state
+        .library_cached(&format!("genres:section:{lang}:{section_code}"), || {
+            genres::get_by_section(&state.db, section_code, lang)
+        })
+        .await
+}
+
+/// Available-book counts per genre, section and script from the counters
+/// table.
+pub async fn item_counts(state: &AppState) -> Result<ItemCounts, sqlx::Error> {
+    state
+        .library_cached("counters:items", || counters::get_item_counts(&state.db))
+        .await
+}
+
+/// Per-item counts to filter navigation by, when `opds.hide_empty` is set.
+/// `None` means every entry is shown.
+pub async fn hide_empty_counts(state: &AppState) -> Option<ItemCounts> {
+    if !state.config.opds.hide_empty {
+        return None;
+    }
+    item_counts(state).await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::db::create_test_pool;
+    use crate::web::i18n::Translations;
+
+    async fn test_state() -> AppState {
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            base_url = "http://127.0.0.1:8081"
+            [library]
+            root_path = "/tmp/books"
+            [database]
+            [opds]
+            [scanner]
+            "#,
+        )
+        .unwrap();
+        let pool = create_test_pool().await;
+        AppState::new(
+            config,
+            pool,
+            tera::Tera::default(),
+            Translations::new(),
+            crate::state::Capabilities::default(),
+        )
+    }
+
+    async fn add_root_catalog(state: &AppState, name: &str) {
+        let sql = state
+            .db
+            .sql("INSERT INTO catalogs (path, cat_name) VALUES (?, ?)");
+        sqlx::query(&sql)
+            .bind(format!("/{name}"))
+            .bind(name)
+            .execute(state.db.inner())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cached_until_library_changes() {
+        let state = test_state().await;
+        add_root_catalog(&state, "first").await;
+        assert_eq!(root_catalogs(&state).await.unwrap().len(), 1);
+
+        // Not seen while the library change stamp is unchanged.
+        add_root_catalog(&state, "second").await;
+        assert_eq!(root_catalogs(&state).await.unwrap().len(), 1);
+
+        state.library_changed().await;
+        assert_eq!(root_catalogs(&state).await.unwrap().len(), 2);
+    }
+}
@@ -10,21 +10,81 @@ pub async fn get_by_id(pool: &DbPool, id: i64) -> Result<Option<Catalog>, sqlx::
         .await
 }
 
+/// Visible child catalogs; hidden ones are left out of browsing.
 pub async fn get_children(pool: &DbPool, parent_id: i64) -> Result<Vec<Catalog>, sqlx::Error> {
-    let sql = pool.sql("SELECT * FROM catalogs WHERE parent_id = ? ORDER BY cat_name");
+    let sql =
+        pool.sql("SELECT * FROM catalogs WHERE parent_id = ? AND cat_hidden = 0 ORDER BY cat_name");
     sqlx::query_as::<_, Catalog>(&sql)
         .bind(parent_id)
         .fetch_all(pool.inner())
         .await
 }
 
+/// Upper bound on the ancestor walk, so a corrupted `parent_id` cycle
+/// cannot make the recursive query run away.
+pub(crate) const MAX_CATALOG_DEPTH: i32 = 256;
+
+/// Ancestor chain of a catalog, ordered from the root down to and including
+/// the catalog itself. Returns an empty list for an unknown id.
+pub async fn get_ancestors(pool: &DbPool, id: i64) -> Result<Vec<Catalog>, sqlx::Error> {
+    let sql = pool.sql(
+        "WITH RECURSIVE chain (id, parent_id, path, cat_name, cat_type, cat_size, cat_mtime, \
+                               cat_version, cat_hidden, depth) AS ( \
+             SELECT id, parent_id, path, cat_name, cat_type, cat_size, cat_mtime, cat_version, \
+                    cat_hidden, 0 \
+             FROM catalogs WHERE id = ? \
+             UNION ALL \
+             SELECT c.id, c.parent_id, c.path, c.cat_name, c.cat_type, c.cat_size, c.cat_mtime, \
+                    c.cat_version, c.cat_hidden, chain.depth + 1 \
+             FROM catalogs c JOIN chain ON c.id = chain.parent_id \
+             WHERE chain.depth < ?) \
+         SELECT id, parent_id, path, cat_name, cat_type, cat_size, cat_mtime, cat_version, \
+                cat_hidden \
+         FROM chain ORDER BY depth DESC",
+    );
+    sqlx::query_as::<_, Catalog>(&sql)
+        .bind(id)
+        .bind(MAX_CATALOG_DEPTH)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Visible top-level catalogs.
 pub async fn get_root_catalogs(pool: &DbPool) -> Result<Vec<Catalog>, sqlx::Error> {
-    let sql = pool.sql("SELECT * FROM catalogs WHERE parent_id IS NULL ORDER BY cat_name");
+    let sql = pool
+        .sql("SELECT * FROM catalogs WHERE parent_id IS NULL AND cat_hidden = 0 ORDER BY cat_name");
     sqlx::query_as::<_, Catalog>(&sql)
         .fetch_all(pool.inner())
         .await
 }
 
+pub async fn get_all(pool: &DbPool) -> Result<Vec<Catalog>, sqlx::Error> {
+    let sql = pool.sql("SELECT * FROM catalogs ORDER BY id");
+    sqlx::query_as::<_, Catalog>(&sql)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Catalogs whose display name contains `term`, for the admin parent picker.
+/// The term is matched as typed and upper-cased, since SQLite only folds
+/// ASCII case.
+pub async fn search_by_name(
+    pool: &DbPool,
+    term: &str,
+    limit: i32,
+) -> Result<Vec<Catalog>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT * FROM catalogs WHERE cat_name LIKE ? OR UPPER(cat_name) LIKE ? \
+         ORDER BY cat_name LIMIT ?",
+    );
+    sqlx::query_as::<_, Catalog>(&sql)
+        .bind(format!("%{term}%"))
+        .bind(format!("%{}%", term.to_uppercase()))
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
 pub async fn find_by_path(pool: &DbPool, path: &str) -> Result<Option<Catalog>, sqlx::Error> {
     let sql = pool.sql("SELECT * FROM catalogs WHERE path = ?");
     sqlx::query_as::<_, Catalog>(&sql)
@@ -42,17 +102,17 @@ pub async fn insert(
     cat_size: i64,
     cat_mtime: &str,
 ) -> Result<i64, sqlx::Error> {
-    let raw = match pool.backend() {
-        crate::db::DbBackend::Mysql => {
-            "INSERT IGNORE INTO catalogs (parent_id, path, cat_name, cat_type, cat_size, cat_mtime) \
-             VALUES (?, ?, ?, ?, ?, ?)"
-        }
-        _ => {
-            "INSERT INTO catalogs (parent_id, path, cat_name, cat_type, cat_size, cat_mtime) \
-             VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT (path) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(raw);
+    let sql = pool.upsert_ignore(
+        "catalogs",
+        &[
+            "parent_id",
+            "path",
+            "cat_name",
+            "cat_type",
+            "cat_size",
+            "cat_mtime",
+        ],
+    );
     let result = sqlx::query(&sql)
         .bind(parent_id)
         .bind(path)
@@ -95,8 +155,113 @@ pub async fn update_archive_meta(
     Ok(())
 }
 
+/// Attach a catalog to `parent_id` during a scan; catalogs an admin
+/// arranged (`cat_custom`) keep their place.
+pub async fn set_parent(pool: &DbPool, id: i64, parent_id: Option<i64>) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE catalogs SET parent_id = ? WHERE id = ? AND cat_custom = 0");
+    sqlx::query(&sql)
+        .bind(parent_id)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Store INPX `collection.info` name and `version.info` release on a catalog.
+/// A name set by an admin is kept.
+pub async fn update_collection_info(
+    pool: &DbPool,
+    id: i64,
+    cat_name: &str,
+    cat_version: &str,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql(
+        "UPDATE catalogs SET cat_name = CASE WHEN cat_custom = 0 THEN ? ELSE cat_name END, \
+         cat_version = ? WHERE id = ?",
+    );
+    sqlx::query(&sql)
+        .bind(cat_name)
+        .bind(cat_version)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Give a catalog a new display name (admin rename). Its `path` is kept.
+pub async fn rename(pool: &DbPool, id: i64, cat_name: &str) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE catalogs SET cat_name = ?, cat_custom = 1 WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(cat_name)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Move a catalog under `parent_id`, or to the top level for `None` (admin
+/// re-parenting). Callers must rule out cycles; see [`is_in_subtree`].
+pub async fn move_to(pool: &DbPool, id: i64, parent_id: Option<i64>) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE catalogs SET parent_id = ?, cat_custom = 1 WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(parent_id)
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Whether `id` is `root` or one of its descendants.
+pub async fn is_in_subtree(pool: &DbPool, root: i64, id: i64) -> Result<bool, sqlx::Error> {
+    Ok(get_ancestors(pool, id).await?.iter().any(|c| c.id == root))
+}
+
+/// Hide a catalog from browsing and scanning, or show it again.
+pub async fn set_hidden(pool: &DbPool, id: i64, hidden: bool) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE catalogs SET cat_hidden = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(i32::from(hidden))
+        .bind(id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Catalogs an admin hid, for listing them where they can be shown again.
+pub async fn get_hidden(pool: &DbPool) -> Result<Vec<Catalog>, sqlx::Error> {
+    let sql = pool.sql("SELECT * FROM catalogs WHERE cat_hidden = 1 ORDER BY path");
+    sqlx::query_as::<_, Catalog>(&sql)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Ids of visible catalogs by id, a page at a time (sitemap).
+pub async fn visible_ids(pool: &DbPool, limit: i64, offset: i64) -> Result<Vec<i64>, sqlx::Error> {
+    let sql = pool.sql("SELECT id FROM catalogs WHERE cat_hidden = 0 ORDER BY id LIMIT ? OFFSET ?");
+    let rows: Vec<(i64,)> = sqlx::query_as(&sql)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool.inner())
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+pub async fn count_visible(pool: &DbPool) -> Result<i64, sqlx::Error> {
+    let sql = pool.sql("SELECT COUNT(*) FROM catalogs WHERE cat_hidden = 0");
+    let row: (i64,) = sqlx::query_as(&sql).fetch_one(pool.inner()).await?;
+    Ok(row.0)
+}
+
+/// Library paths of the hidden catalogs, which the scanner skips.
+pub async fn hidden_paths(pool: &DbPool) -> Result<Vec<String>, sqlx::Error> {
+    let sql = pool.sql("SELECT path FROM catalogs WHERE cat_hidden = 1");
+    let rows: Vec<(String,)> = sqlx::query_as(&sql).fetch_all(pool.inner()).await?;
+    Ok(rows.into_iter().map(|(path,)| path).collect())
+}
+
 /// Delete catalogs that have no live books and no child catalogs.
-/// Repeats until no more empty catalogs are found (prunes leaf-up).
+/// Repeats until no more empty catalogs are found (prunes leaf-up). Hidden
+/// catalogs are kept so their flag outlives the books a scan drops.
 pub async fn delete_empty(pool: &DbPool) -> Result<u64, sqlx::Error> {
     // MySQL 8 rejects a DELETE whose IN subquery's FROM references the target
     // table ("You can't specify target table 'catalogs' for update in FROM
@@ -104,7 +269,7 @@ pub async fn delete_empty(pool: &DbPool) -> Result<u64, sqlx::Error> {
     // materialize the result into a derived table first, sidestepping the
     // restriction. MariaDB/PostgreSQL/SQLite accept either shape.
     let sql = pool.sql(
-        "DELETE FROM catalogs WHERE id NOT IN \
+        "DELETE FROM catalogs WHERE cat_hidden = 0 AND id NOT IN \
          (SELECT DISTINCT catalog_id FROM books WHERE avail > 0) \
          AND id NOT IN \
          (SELECT parent_id FROM \
@@ -191,6 +356,30 @@ mod tests {
         assert_eq!(children[0].id, child_id);
     }
 
+    #[tokio::test]
+    async fn test_get_ancestors_root_first() {
+        let pool = create_test_pool().await;
+
+        let a = insert(&pool, None, "a", "a", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let b = insert(&pool, Some(a), "a/b", "b", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let c = insert(&pool, Some(b), "a/b/c", "c", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+
+        let chain = get_ancestors(&pool, c).await.unwrap();
+        let ids: Vec<i64> = chain.iter().map(|cat| cat.id).collect();
+        assert_eq!(ids, vec![a, b, c]);
+        assert_eq!(chain[2].path, "a/b/c");
+
+        let root_only = get_ancestors(&pool, a).await.unwrap();
+        assert_eq!(root_only.len(), 1);
+        assert!(get_ancestors(&pool, 9999).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_insert_duplicate_returns_same_id() {
         let pool = create_test_pool().await;
@@ -230,6 +419,128 @@ mod tests {
         assert_eq!(cat.cat_mtime, "2026-02-19 10:30:00");
     }
 
+    #[tokio::test]
+    async fn test_update_collection_info() {
+        let pool = create_test_pool().await;
+
+        let id = insert(&pool, None, "lib.inpx", "lib.inpx", CatType::Inpx, 10, "")
+            .await
+            .unwrap();
+        assert_eq!(get_by_id(&pool, id).await.unwrap().unwrap().cat_version, "");
+        update_collection_info(&pool, id, "Librusec", "20240101")
+            .await
+            .unwrap();
+
+        let cat = get_by_id(&pool, id).await.unwrap().unwrap();
+        assert_eq!(cat.cat_name, "Librusec");
+        assert_eq!(cat.cat_version, "20240101");
+        assert_eq!(
+            get_ancestors(&pool, id).await.unwrap()[0].cat_version,
+            "20240101"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_rename_and_move_survive_rescan() {
+        let pool = create_test_pool().await;
+
+        let lib = insert(&pool, None, "lib.inpx", "lib.inpx", CatType::Inpx, 10, "")
+            .await
+            .unwrap();
+        let other = insert(&pool, None, "/other", "other", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let child = insert(
+            &pool,
+            Some(lib),
+            "lib.inpx/a.zip",
+            "a.zip",
+            CatType::Zip,
+            0,
+            "",
+        )
+        .await
+        .unwrap();
+
+        rename(&pool, lib, "My Library").await.unwrap();
+        move_to(&pool, child, Some(other)).await.unwrap();
+
+        // A re-import neither renames nor re-attaches admin-arranged catalogs.
+        update_collection_info(&pool, lib, "Librusec", "20240101")
+            .await
+            .unwrap();
+        set_parent(&pool, child, Some(lib)).await.unwrap();
+
+        let cat = get_by_id(&pool, lib).await.unwrap().unwrap();
+        assert_eq!(cat.cat_name, "My Library");
+        assert_eq!(cat.cat_version, "20240101");
+        assert_eq!(cat.path, "lib.inpx");
+        let moved = get_by_id(&pool, child).await.unwrap().unwrap();
+        assert_eq!(moved.parent_id, Some(other));
+        assert_eq!(moved.path, "lib.inpx/a.zip");
+
+        assert!(is_in_subtree(&pool, other, child).await.unwrap());
+        assert!(is_in_subtree(&pool, child, child).await.unwrap());
+        assert!(!is_in_subtree(&pool, lib, child).await.unwrap());
+
+        move_to(&pool, child, None).await.unwrap();
+        let roots = get_root_catalogs(&pool).await.unwrap();
+        assert!(roots.iter().any(|c| c.id == child));
+
+        let found = search_by_name(&pool, "librar", 10).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, lib);
+    }
+
+    #[tokio::test]
+    async fn test_hidden_catalogs_are_not_listed_and_kept_when_empty() {
+        let pool = create_test_pool().await;
+
+        let root = insert(&pool, None, "/root", "root", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let junk = insert(
+            &pool,
+            Some(root),
+            "/root/junk",
+            "junk",
+            CatType::Normal,
+            0,
+            "",
+        )
+        .await
+        .unwrap();
+        let top_junk = insert(
+            &pool,
+            None,
+            ".stversions",
+            ".stversions",
+            CatType::Normal,
+            0,
+            "",
+        )
+        .await
+        .unwrap();
+        insert_test_book(&pool, root, "Kept", 2).await;
+
+        set_hidden(&pool, junk, true).await.unwrap();
+        set_hidden(&pool, top_junk, true).await.unwrap();
+        assert!(get_children(&pool, root).await.unwrap().is_empty());
+        let roots = get_root_catalogs(&pool).await.unwrap();
+        assert_eq!(roots.iter().map(|c| c.id).collect::<Vec<_>>(), vec![root]);
+        let mut paths = hidden_paths(&pool).await.unwrap();
+        paths.sort();
+        assert_eq!(paths, vec![".stversions", "/root/junk"]);
+        assert_eq!(get_hidden(&pool).await.unwrap().len(), 2);
+
+        assert_eq!(delete_empty(&pool).await.unwrap(), 0);
+
+        set_hidden(&pool, junk, false).await.unwrap();
+        assert_eq!(get_children(&pool, root).await.unwrap().len(), 1);
+        assert_eq!(delete_empty(&pool).await.unwrap(), 1);
+        assert!(get_by_id(&pool, junk).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_delete_empty_prunes_tree_and_keeps_non_empty() {
         let pool = create_test_pool().await;
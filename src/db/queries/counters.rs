@@ -1,9 +1,74 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
 use crate::db::DbPool;
 
 use crate::db::models::Counter;
+use crate::db::queries::downloads::{self, POPULAR_PERIODS};
+use crate::db::queries::{authors, series};
+
+/// Books kept in the popular cache per period.
+const POPULAR_LIMIT: i32 = 200;
+
+/// Name prefixes of the per-item counts refreshed by [`update_all`]:
+/// available books per genre (`genre:<id>`) and per genre section
+/// (`genre_section:<code>`), and authors, series or available books per
+/// script (`lang:<authors|series|books>:<lang_code>`).
+const GENRE_PREFIX: &str = "genre:";
+const SECTION_PREFIX: &str = "genre_section:";
+const LANG_PREFIX: &str = "lang:";
+
+/// Per-item counts stored by [`update_all`], used to leave empty genres,
+/// sections and script entries out of navigation.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ItemCounts {
+    pub genres: HashMap<i64, i64>,
+    pub sections: HashMap<String, i64>,
+    /// Keyed by `<authors|series|books>:<lang_code>`.
+    pub langs: HashMap<String, i64>,
+}
+
+impl ItemCounts {
+    pub fn genre(&self, genre_id: i64) -> i64 {
+        self.genres.get(&genre_id).copied().unwrap_or(0)
+    }
 
+    pub fn section(&self, code: &str) -> i64 {
+        self.sections.get(code).copied().unwrap_or(0)
+    }
+
+    /// Entries of `kind` (`authors`, `series` or `books`) in a script;
+    /// lang code 0 stands for all of them.
+    pub fn lang(&self, kind: &str, lang_code: i32) -> i64 {
+        if lang_code == 0 {
+            let prefix = format!("{kind}:");
+            return self
+                .langs
+                .iter()
+                .filter(|(key, _)| key.starts_with(&prefix))
+                .map(|(_, count)| count)
+                .sum();
+        }
+        self.langs
+            .get(&format!("{kind}:{lang_code}"))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Name prefix of the cached per-book download counts of a period.
+fn popular_prefix(days: u32) -> String {
+    format!("popular:{days}:")
+}
+
+/// Aggregate counters (without the cached per-item rows such as popular
+/// books or genre counts, and the library change stamp).
 pub async fn get_all(pool: &DbPool) -> Result<Vec<Counter>, sqlx::Error> {
-    let sql = pool.sql("SELECT * FROM counters ORDER BY name");
+    let sql = pool.sql(
+        "SELECT * FROM counters WHERE name NOT LIKE '%:%' \
+         AND name <> 'library_version' ORDER BY name",
+    );
     sqlx::query_as::<_, Counter>(&sql)
         .fetch_all(pool.inner())
         .await
@@ -39,9 +104,181 @@ pub async fn update_all(pool: &DbPool) -> Result<(), sqlx::Error> {
     set(pool, "allgenres", genres.0).await?;
     set(pool, "allseries", series.0).await?;
 
+    update_item_counts(pool).await
+}
+
+/// Recompute the per-item counts (see [`ItemCounts`]).
+async fn update_item_counts(pool: &DbPool) -> Result<(), sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT bg.genre_id, COUNT(DISTINCT b.id) FROM book_genres bg \
+         JOIN books b ON b.id = bg.book_id AND b.avail > 0 \
+         GROUP BY bg.genre_id",
+    );
+    let genres: Vec<(i64, i64)> = sqlx::query_as(&sql).fetch_all(pool.inner()).await?;
+    let sql = pool.sql(
+        "SELECT gs.code, COUNT(DISTINCT b.id) FROM genre_sections gs \
+         JOIN genres g ON g.section_id = gs.id \
+         JOIN book_genres bg ON bg.genre_id = g.id \
+         JOIN books b ON b.id = bg.book_id AND b.avail > 0 \
+         GROUP BY gs.code",
+    );
+    let sections: Vec<(String, i64)> = sqlx::query_as(&sql).fetch_all(pool.inner()).await?;
+
+    let mut rows: Vec<(String, i64)> = genres
+        .into_iter()
+        .map(|(id, count)| (format!("{GENRE_PREFIX}{id}"), count))
+        .chain(
+            sections
+                .into_iter()
+                .map(|(code, count)| (format!("{SECTION_PREFIX}{code}"), count)),
+        )
+        .collect();
+    // Authors and series are counted like the alphabet drill-down lists them.
+    let books_sql =
+        "SELECT lang_code, COUNT(*) FROM books WHERE avail > 0 GROUP BY lang_code".to_string();
+    let authors_sql = format!(
+        "SELECT lang_code, COUNT(*) FROM authors WHERE {} GROUP BY lang_code",
+        authors::WITH_BOOKS
+    );
+    let series_sql = format!(
+        "SELECT lang_code, COUNT(*) FROM series WHERE {} GROUP BY lang_code",
+        series::WITH_BOOKS
+    );
+    for (kind, query) in [
+        ("books", books_sql),
+        ("authors", authors_sql),
+        ("series", series_sql),
+    ] {
+        let sql = pool.sql(&query);
+        let counts: Vec<(i32, i64)> = sqlx::query_as(&sql).fetch_all(pool.inner()).await?;
+        rows.extend(
+            counts
+                .into_iter()
+                .map(|(lang_code, count)| (format!("{LANG_PREFIX}{kind}:{lang_code}"), count)),
+        );
+    }
+
+    let mut tx = pool.inner().begin().await?;
+    let sql = pool.sql("DELETE FROM counters WHERE name LIKE ? OR name LIKE ? OR name LIKE ?");
+    sqlx::query(&sql)
+        .bind(format!("{GENRE_PREFIX}%"))
+        .bind(format!("{SECTION_PREFIX}%"))
+        .bind(format!("{LANG_PREFIX}%"))
+        .execute(&mut *tx)
+        .await?;
+    let sql =
+        pool.sql("INSERT INTO counters (name, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)");
+    for (name, count) in rows {
+        sqlx::query(&sql)
+            .bind(name)
+            .bind(count)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// Per-item counts as of the last [`update_all`].
+pub async fn get_item_counts(pool: &DbPool) -> Result<ItemCounts, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT name, value FROM counters \
+         WHERE name LIKE ? OR name LIKE ? OR name LIKE ?",
+    );
+    let rows: Vec<(String, i64)> = sqlx::query_as(&sql)
+        .bind(format!("{GENRE_PREFIX}%"))
+        .bind(format!("{SECTION_PREFIX}%"))
+        .bind(format!("{LANG_PREFIX}%"))
+        .fetch_all(pool.inner())
+        .await?;
+    let mut counts = ItemCounts::default();
+    for (name, value) in rows {
+        if let Some(code) = name.strip_prefix(SECTION_PREFIX) {
+            counts.sections.insert(code.to_string(), value);
+        } else if let Some(id) = name.strip_prefix(GENRE_PREFIX) {
+            if let Ok(id) = id.parse() {
+                counts.genres.insert(id, value);
+            }
+        } else if let Some(key) = name.strip_prefix(LANG_PREFIX) {
+            counts.langs.insert(key.to_string(), value);
+        }
+    }
+    Ok(counts)
+}
+
+/// Current library change stamp; grows whenever the catalog changes.
+pub async fn library_version(pool: &DbPool) -> Result<i64, sqlx::Error> {
+    let sql = pool.sql("SELECT value FROM counters WHERE name = 'library_version'");
+    let row: Option<(i64,)> = sqlx::query_as(&sql).fetch_optional(pool.inner()).await?;
+    Ok(row.map(|(value,)| value).unwrap_or(0))
+}
+
+/// Advance the library change stamp after a scan, upload or edit.
+pub async fn bump_library_version(pool: &DbPool) -> Result<(), sqlx::Error> {
+    let sql = pool.sql(
+        "UPDATE counters SET value = value + 1, updated_at = CURRENT_TIMESTAMP \
+         WHERE name = 'library_version'",
+    );
+    sqlx::query(&sql).execute(pool.inner()).await?;
+    Ok(())
+}
+
+/// Recompute the cached download counts of the most popular books for every
+/// period in [`POPULAR_PERIODS`]. Stored as `popular:<days>:<book_id>` rows.
+pub async fn update_popular(pool: &DbPool) -> Result<(), sqlx::Error> {
+    for days in POPULAR_PERIODS {
+        let since = (chrono::Utc::now() - chrono::Duration::days(days.into()))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let top = downloads::top_books(pool, &since, POPULAR_LIMIT).await?;
+        let prefix = popular_prefix(days);
+
+        let mut tx = pool.inner().begin().await?;
+        let sql = pool.sql("DELETE FROM counters WHERE name LIKE ?");
+        sqlx::query(&sql)
+            .bind(format!("{prefix}%"))
+            .execute(&mut *tx)
+            .await?;
+        let sql = pool
+            .sql("INSERT INTO counters (name, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)");
+        for (book_id, count) in top {
+            sqlx::query(&sql)
+                .bind(format!("{prefix}{book_id}"))
+                .bind(count)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+    }
     Ok(())
 }
 
+/// Cached popular books of a period as `(book_id, downloads)`, most downloaded first.
+pub async fn get_popular(
+    pool: &DbPool,
+    days: u32,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    let prefix = popular_prefix(days);
+    let sql = pool.sql(
+        "SELECT name, value FROM counters WHERE name LIKE ? \
+         ORDER BY value DESC, name LIMIT ? OFFSET ?",
+    );
+    let rows: Vec<(String, i64)> = sqlx::query_as(&sql)
+        .bind(format!("{prefix}%"))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool.inner())
+        .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let book_id = name.strip_prefix(&prefix)?.parse().ok()?;
+            Some((book_id, value))
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,6 +341,48 @@ mod tests {
         assert_eq!(get_counter_value(&pool, "allbooks").await, 123);
     }
 
+    #[tokio::test]
+    async fn test_bump_library_version() {
+        let pool = create_test_pool().await;
+
+        let before = library_version(&pool).await.unwrap();
+        bump_library_version(&pool).await.unwrap();
+        bump_library_version(&pool).await.unwrap();
+        assert_eq!(library_version(&pool).await.unwrap(), before + 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_popular_caches_counts() {
+        let pool = create_test_pool().await;
+        let catalog_id = ensure_catalog(&pool).await;
+        let first = insert_test_book(&pool, catalog_id, "First", 2).await;
+        let second = insert_test_book(&pool, catalog_id, "Second", 2).await;
+        for book_id in [first, second, second] {
+            downloads::record(&pool, book_id, None).await.unwrap();
+        }
+
+        update_popular(&pool).await.unwrap();
+        assert_eq!(
+            get_popular(&pool, 30, 10, 0).await.unwrap(),
+            vec![(second, 2), (first, 1)]
+        );
+        assert_eq!(
+            get_popular(&pool, 90, 1, 1).await.unwrap(),
+            vec![(first, 1)]
+        );
+        assert!(
+            get_all(&pool)
+                .await
+                .unwrap()
+                .iter()
+                .all(|c| c.name.starts_with("all"))
+        );
+
+        // A refresh replaces the cached rows instead of adding to them.
+        update_popular(&pool).await.unwrap();
+        assert_eq!(get_popular(&pool, 30, 10, 0).await.unwrap().len(), 2);
+    }
+
     #[tokio::test]
     async fn test_update_all_recalculates_values() {
         let pool = create_test_pool().await;
@@ -151,5 +430,20 @@ mod tests {
         assert_eq!(get_counter_value(&pool, "allauthors").await, 2);
         assert_eq!(get_counter_value(&pool, "allgenres").await, 2);
         assert_eq!(get_counter_value(&pool, "allseries").await, 1);
+
+        let items = get_item_counts(&pool).await.unwrap();
+        assert_eq!(items.genre(g1.0), 1);
+        assert_eq!(items.genre(g2.0), 0);
+        assert_eq!(items.lang("books", 2), 1);
+        assert_eq!(items.lang("books", 1), 0);
+        // Neither author has a book, so the script menus would skip them.
+        assert_eq!(items.lang("authors", 0), 0);
+        assert!(
+            get_all(&pool)
+                .await
+                .unwrap()
+                .iter()
+                .all(|c| c.name.starts_with("all"))
+        );
     }
 }
@@ -0,0 +1,94 @@
+//! OPDS clients seen per user in the `user_devices` table, recorded when
+//! `stats.user_agents` is enabled.
+
+use crate::db::DbPool;
+use crate::db::models::UserDevice;
+
+/// Longest User-Agent stored, in characters.
+pub const MAX_USER_AGENT_CHARS: usize = 255;
+
+/// Cut a User-Agent header to what the tables hold.
+pub fn truncate_user_agent(user_agent: &str) -> String {
+    user_agent
+        .trim()
+        .chars()
+        .take(MAX_USER_AGENT_CHARS)
+        .collect()
+}
+
+/// Count a request of `user_id` from the client `user_agent`.
+pub async fn touch(pool: &DbPool, user_id: i64, user_agent: &str) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let raw = match pool.backend() {
+        crate::db::DbBackend::Mysql => {
+            "INSERT INTO user_devices (user_id, user_agent, first_seen, last_seen, requests) \
+             VALUES (?, ?, ?, ?, 1) \
+             ON DUPLICATE KEY UPDATE last_seen = VALUES(last_seen), requests = requests + 1"
+        }
+        _ => {
+            "INSERT INTO user_devices (user_id, user_agent, first_seen, last_seen, requests) \
+             VALUES (?, ?, ?, ?, 1) \
+             ON CONFLICT(user_id, user_agent) DO UPDATE SET \
+             last_seen = excluded.last_seen, requests = user_devices.requests + 1"
+        }
+    };
+    let sql = pool.sql(raw);
+    sqlx::query(&sql)
+        .bind(user_id)
+        .bind(truncate_user_agent(user_agent))
+        .bind(&now)
+        .bind(&now)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Every recorded client, grouped by user, most recently seen first.
+pub async fn list_all(pool: &DbPool) -> Result<Vec<UserDevice>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT d.user_id, u.username, d.user_agent, d.first_seen, d.last_seen, d.requests \
+         FROM user_devices d JOIN users u ON u.id = d.user_id \
+         ORDER BY u.username, d.last_seen DESC",
+    );
+    sqlx::query_as(&sql).fetch_all(pool.inner()).await
+}
+
+/// Forget every recorded client.
+pub async fn clear_all(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let sql = pool.sql("DELETE FROM user_devices");
+    let result = sqlx::query(&sql).execute(pool.inner()).await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+    use crate::db::queries::users;
+
+    #[tokio::test]
+    async fn test_touch_counts_requests_per_client() {
+        let pool = create_test_pool().await;
+        let alice = users::create(&pool, "alice", "h", 0, "").await.unwrap();
+        let bob = users::create(&pool, "bob", "h", 0, "").await.unwrap();
+
+        touch(&pool, alice, "KOReader/2024.04").await.unwrap();
+        touch(&pool, alice, "KOReader/2024.04").await.unwrap();
+        touch(&pool, alice, "FBReader/3.0").await.unwrap();
+        touch(&pool, bob, &"x".repeat(400)).await.unwrap();
+
+        let devices = list_all(&pool).await.unwrap();
+        assert_eq!(devices.len(), 3);
+        assert!(devices[..2].iter().all(|d| d.username == "alice"));
+        let koreader = devices
+            .iter()
+            .find(|d| d.user_agent.starts_with("KOReader"))
+            .unwrap();
+        assert_eq!(koreader.requests, 2);
+        assert_eq!(devices[2].user_id, bob);
+        assert_eq!(devices[2].user_agent.len(), MAX_USER_AGENT_CHARS);
+
+        assert_eq!(clear_all(&pool).await.unwrap(), 3);
+        assert!(list_all(&pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+use crate::db::DbPool;
+use crate::db::models::DownloadCount;
+
+/// Periods (in days) the popular listings are computed for; the first one is the default.
+pub const POPULAR_PERIODS: [u32; 2] = [30, 90];
+
+/// Popular period from a `?period=` value, falling back to the default.
+pub fn popular_period(requested: Option<&str>) -> u32 {
+    requested
+        .and_then(|v| v.parse().ok())
+        .filter(|days| POPULAR_PERIODS.contains(days))
+        .unwrap_or(POPULAR_PERIODS[0])
+}
+
+/// Record a book download (`user_id` is `None` for anonymous downloads).
+pub async fn record(pool: &DbPool, book_id: i64, user_id: Option<i64>) -> Result<(), sqlx::Error> {
+    record_with_agent(pool, book_id, user_id, None).await
+}
+
+/// Record a book download along with the client's User-Agent, if known,
+/// and count it in the book's `download_count`.
+pub async fn record_with_agent(
+    pool: &DbPool,
+    book_id: i64,
+    user_id: Option<i64>,
+    user_agent: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut tx = pool.inner().begin().await?;
+    let sql = pool.sql(
+        "INSERT INTO downloads (book_id, user_id, downloaded_at, user_agent) VALUES (?, ?, ?, ?)",
+    );
+    sqlx::query(&sql)
+        .bind(book_id)
+        .bind(user_id)
+        .bind(now)
+        .bind(user_agent.map(super::devices::truncate_user_agent))
+        .execute(&mut *tx)
+        .await?;
+    let sql = pool.sql("UPDATE books SET download_count = download_count + 1 WHERE id = ?");
+    sqlx::query(&sql).bind(book_id).execute(&mut *tx).await?;
+    tx.commit().await
+}
+
+/// Most downloaded available books since `since` (`YYYY-MM-DD HH:MM:SS`, UTC)
+/// as `(book_id, downloads)`, most downloaded first.
+pub async fn top_books(
+    pool: &DbPool,
+    since: &str,
+    limit: i32,
+) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT d.book_id, COUNT(*) AS cnt FROM downloads d \
+         JOIN books b ON b.id = d.book_id \
+         WHERE d.downloaded_at >= ? AND b.avail > 0 \
+         GROUP BY d.book_id \
+         ORDER BY cnt DESC, d.book_id \
+         LIMIT ?",
+    );
+    sqlx::query_as(&sql)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Most downloaded books since `since` (`YYYY-MM-DD HH:MM:SS`, UTC; empty for
+/// all time), including books no longer available.
+pub async fn book_leaderboard(
+    pool: &DbPool,
+    since: &str,
+    limit: i32,
+) -> Result<Vec<DownloadCount>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT d.book_id AS id, b.title AS name, COUNT(*) AS downloads FROM downloads d \
+         JOIN books b ON b.id = d.book_id \
+         WHERE d.downloaded_at >= ? \
+         GROUP BY d.book_id, b.title \
+         ORDER BY downloads DESC, id \
+         LIMIT ?",
+    );
+    sqlx::query_as(&sql)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Authors whose books were downloaded most since `since`. A download counts
+/// once for each author of the book.
+pub async fn author_leaderboard(
+    pool: &DbPool,
+    since: &str,
+    limit: i32,
+) -> Result<Vec<DownloadCount>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT a.id AS id, a.full_name AS name, COUNT(*) AS downloads FROM downloads d \
+         JOIN book_authors ba ON ba.book_id = d.book_id \
+         JOIN authors a ON a.id = ba.author_id \
+         WHERE d.downloaded_at >= ? \
+         GROUP BY a.id, a.full_name \
+         ORDER BY downloads DESC, id \
+         LIMIT ?",
+    );
+    sqlx::query_as(&sql)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Users who downloaded most since `since`; anonymous downloads form one row
+/// with id 0.
+pub async fn user_leaderboard(
+    pool: &DbPool,
+    since: &str,
+    limit: i32,
+) -> Result<Vec<DownloadCount>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT COALESCE(d.user_id, 0) AS id, COALESCE(u.username, '') AS name, \
+         COUNT(*) AS downloads FROM downloads d \
+         LEFT JOIN users u ON u.id = d.user_id \
+         WHERE d.downloaded_at >= ? \
+         GROUP BY d.user_id, u.username \
+         ORDER BY downloads DESC, id \
+         LIMIT ?",
+    );
+    sqlx::query_as(&sql)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Downloads per day (`YYYY-MM-DD`) since `since`, oldest first. Days without
+/// downloads are omitted.
+pub async fn daily_counts(pool: &DbPool, since: &str) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT SUBSTR(downloaded_at, 1, 10) AS day, COUNT(*) AS cnt FROM downloads \
+         WHERE downloaded_at >= ? \
+         GROUP BY SUBSTR(downloaded_at, 1, 10) \
+         ORDER BY day",
+    );
+    sqlx::query_as(&sql)
+        .bind(since)
+        .fetch_all(pool.inner())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    async fn insert_book(pool: &DbPool, title: &str, avail: i32) -> i64 {
+        let sql = pool.sql("INSERT INTO catalogs (path, cat_name) VALUES (?, ?)");
+        sqlx::query(&sql)
+            .bind(format!("/{title}"))
+            .bind(title)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        let sql = pool.sql("SELECT id FROM catalogs WHERE path = ?");
+        let (catalog_id,): (i64,) = sqlx::query_as(&sql)
+            .bind(format!("/{title}"))
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        let sql = pool.sql(
+            "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+             lang, lang_code, size, avail, cat_type, cover, cover_type) \
+             VALUES (?, ?, '/dl', 'fb2', ?, ?, 'en', 2, 100, ?, 0, 0, '')",
+        );
+        sqlx::query(&sql)
+            .bind(catalog_id)
+            .bind(format!("{title}.fb2"))
+            .bind(title)
+            .bind(title.to_uppercase())
+            .bind(avail)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        let sql = pool.sql("SELECT id FROM books WHERE title = ?");
+        let (id,): (i64,) = sqlx::query_as(&sql)
+            .bind(title)
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        id
+    }
+
+    #[test]
+    fn test_popular_period() {
+        assert_eq!(popular_period(None), 30);
+        assert_eq!(popular_period(Some("90")), 90);
+        assert_eq!(popular_period(Some("7")), 30);
+        assert_eq!(popular_period(Some("x")), 30);
+    }
+
+    #[tokio::test]
+    async fn test_top_books_counts_recent_available_downloads() {
+        let pool = create_test_pool().await;
+        let first = insert_book(&pool, "First", 2).await;
+        let second = insert_book(&pool, "Second", 2).await;
+        let gone = insert_book(&pool, "Gone", 0).await;
+
+        record(&pool, first, None).await.unwrap();
+        for _ in 0..3 {
+            record(&pool, second, None).await.unwrap();
+        }
+        record(&pool, gone, None).await.unwrap();
+        // An old download falls out of the window.
+        let sql = pool.sql(
+            "INSERT INTO downloads (book_id, user_id, downloaded_at) \
+             VALUES (?, NULL, '2000-01-01 00:00:00')",
+        );
+        sqlx::query(&sql)
+            .bind(first)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+
+        let top = top_books(&pool, "2001-01-01 00:00:00", 10).await.unwrap();
+        assert_eq!(top, vec![(second, 3), (first, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_leaderboards_and_daily_counts() {
+        let pool = create_test_pool().await;
+        let first = insert_book(&pool, "First", 2).await;
+        let second = insert_book(&pool, "Second", 0).await;
+        let sql = pool.sql("INSERT INTO authors (full_name, search_full_name) VALUES (?, ?)");
+        sqlx::query(&sql)
+            .bind("Writer")
+            .bind("WRITER")
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        let (author_id,): (i64,) = sqlx::query_as("SELECT id FROM authors")
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        let sql = pool.sql("INSERT INTO book_authors (book_id, author_id) VALUES (?, ?)");
+        for book in [first, second] {
+            sqlx::query(&sql)
+                .bind(book)
+                .bind(author_id)
+                .execute(pool.inner())
+                .await
+                .unwrap();
+        }
+        let sql = pool.sql("INSERT INTO users (username) VALUES (?)");
+        sqlx::query(&sql)
+            .bind("reader")
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        let (user_id,): (i64,) = sqlx::query_as("SELECT id FROM users")
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+
+        let sql =
+            pool.sql("INSERT INTO downloads (book_id, user_id, downloaded_at) VALUES (?, ?, ?)");
+        for (book, user, at) in [
+            (first, Some(user_id), "2024-03-01 10:00:00"),
+            (second, Some(user_id), "2024-03-01 11:00:00"),
+            (second, None, "2024-03-03 09:00:00"),
+            (second, None, "2023-01-01 00:00:00"),
+        ] {
+            sqlx::query(&sql)
+                .bind(book)
+                .bind(user)
+                .bind(at)
+                .execute(pool.inner())
+                .await
+                .unwrap();
+        }
+
+        let since = "2024-01-01 00:00:00";
+        let count = |id: i64, name: &str, downloads: i64| DownloadCount {
+            id,
+            name: name.to_string(),
+            downloads,
+        };
+        assert_eq!(
+            book_leaderboard(&pool, since, 10).await.unwrap(),
+            vec![count(second, "Second", 2), count(first, "First", 1)]
+        );
+        assert_eq!(
+            author_leaderboard(&pool, since, 10).await.unwrap(),
+            vec![count(author_id, "Writer", 3)]
+        );
+        assert_eq!(
+            user_leaderboard(&pool, since, 10).await.unwrap(),
+            vec![count(user_id, "reader", 2), count(0, "", 1)]
+        );
+        assert_eq!(
+            daily_counts(&pool, since).await.unwrap(),
+            vec![("2024-03-01".to_string(), 2), ("2024-03-03".to_string(), 1)]
+        );
+        assert_eq!(
+            book_leaderboard(&pool, "", 1).await.unwrap()[0].downloads,
+            3
+        );
+    }
+}
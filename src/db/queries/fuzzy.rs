@@ -0,0 +1,382 @@
+//! Typo-tolerant title and author search based on trigram similarity.
+//!
+//! PostgreSQL uses `pg_trgm` when the extension is available. Otherwise rows
+//! are pre-selected by how many query trigrams they contain (`LIKE`), most
+//! first, and scored in process with [`word_similarity`], which follows the
+//! `pg_trgm` definition.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::db::models::{Author, Book};
+use crate::db::{DbBackend, DbPool};
+
+/// Rows scored per in-process fuzzy query.
+const CANDIDATE_LIMIT: i32 = 5000;
+/// Query trigrams used to pre-select candidate rows.
+const MAX_QUERY_TRIGRAMS: usize = 16;
+
+/// Try to enable the `pg_trgm` extension. Returns whether its similarity
+/// functions can be used (always `false` outside PostgreSQL).
+pub async fn enable_pg_trgm(pool: &DbPool) -> bool {
+    if pool.backend() != DbBackend::Postgres {
+        return false;
+    }
+    if let Err(e) = sqlx::query("CREATE EXTENSION IF NOT EXISTS pg_trgm")
+        .execute(pool.inner())
+        .await
+    {
+        tracing::debug!("CREATE EXTENSION pg_trgm failed: {e}");
+    }
+    sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM pg_extension WHERE extname = 'pg_trgm'")
+        .fetch_one(pool.inner())
+        .await
+        .is_ok_and(|(n,)| n > 0)
+}
+
+/// Available books whose title resembles `term`, most similar first.
+pub async fn search_books(
+    pool: &DbPool,
+    term: &str,
+    limit: i32,
+    hide_doubles: bool,
+    threshold: f64,
+    pg_trgm: bool,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let dedup = if hide_doubles {
+        " AND id IN (SELECT MIN(id) FROM books WHERE avail > 0 GROUP BY search_title, author_key)"
+    } else {
+        ""
+    };
+    if pg_trgm {
+        let raw = format!(
+            "SELECT * FROM books WHERE avail > 0{dedup} \
+             AND word_similarity(?, search_title) >= ? \
+             ORDER BY word_similarity(?, search_title) DESC, id DESC LIMIT ?"
+        );
+        let sql = pool.sql(&raw);
+        return sqlx::query_as::<_, Book>(&sql)
+            .bind(term)
+            .bind(threshold)
+            .bind(term)
+            .bind(limit)
+            .fetch_all(pool.inner())
+            .await;
+    }
+    let filter = format!("avail > 0{dedup}");
+    let ids = rank_candidates(
+        pool,
+        "books",
+        "search_title",
+        &filter,
+        term,
+        threshold,
+        limit,
+    )
+    .await?;
+    fetch_ordered(pool, "books", &ids, |b: &Book| b.id).await
+}
+
+/// Authors whose name resembles `term`, most similar first.
+pub async fn search_authors(
+    pool: &DbPool,
+    term: &str,
+    limit: i32,
+    threshold: f64,
+    pg_trgm: bool,
+) -> Result<Vec<Author>, sqlx::Error> {
+    if pg_trgm {
+        let sql = pool.sql(
+            "SELECT * FROM authors WHERE word_similarity(?, search_full_name) >= ? \
+             ORDER BY word_similarity(?, search_full_name) DESC, id DESC LIMIT ?",
+        );
+        return sqlx::query_as::<_, Author>(&sql)
+            .bind(term)
+            .bind(threshold)
+            .bind(term)
+            .bind(limit)
+            .fetch_all(pool.inner())
+            .await;
+    }
+    let filter = "search_full_name <> ''";
+    let ids = rank_candidates(
+        pool,
+        "authors",
+        "search_full_name",
+        filter,
+        term,
+        threshold,
+        limit,
+    )
+    .await?;
+    fetch_ordered(pool, "authors", &ids, |a: &Author| a.id).await
+}
+
+/// Ids of the `limit` rows of `table` matching `filter` whose `column` is
+/// most similar to `term`. Candidates are the rows containing the most query
+/// trigrams, and at least as many as a row reaching `threshold` must.
+async fn rank_candidates(
+    pool: &DbPool,
+    table: &str,
+    column: &str,
+    filter: &str,
+    term: &str,
+    threshold: f64,
+    limit: i32,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let grams = like_trigrams(term);
+    if grams.is_empty() {
+        return Ok(Vec::new());
+    }
+    let hits =
+        vec![format!("CASE WHEN {column} LIKE ? THEN 1 ELSE 0 END"); grams.len()].join(" + ");
+    let raw = format!(
+        "SELECT id, {column} FROM \
+         (SELECT id, {column}, {hits} AS hits FROM {table} WHERE {filter}) AS candidates \
+         WHERE hits >= ? ORDER BY hits DESC, id DESC LIMIT ?"
+    );
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, (i64, String)>(&sql);
+    for gram in &grams {
+        query = query.bind(format!("%{gram}%"));
+    }
+    let rows = query
+        .bind(min_trigram_hits(term, threshold))
+        .bind(CANDIDATE_LIMIT)
+        .fetch_all(pool.inner())
+        .await?;
+
+    let mut scored: Vec<(f64, i64)> = rows
+        .into_iter()
+        .filter_map(|(id, text)| {
+            let score = word_similarity(term, &text);
+            (score >= threshold).then_some((score, id))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.cmp(&a.1)));
+    Ok(scored
+        .into_iter()
+        .take(limit.max(0) as usize)
+        .map(|(_, id)| id)
+        .collect())
+}
+
+/// Rows of `table` with the given ids, in the order of `ids`.
+async fn fetch_ordered<T>(
+    pool: &DbPool,
+    table: &str,
+    ids: &[i64],
+    id_of: impl Fn(&T) -> i64,
+) -> Result<Vec<T>, sqlx::Error>
+where
+    T: for<'r> sqlx::FromRow<'r, sqlx::any::AnyRow> + Send + Unpin,
+{
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    let raw = format!("SELECT * FROM {table} WHERE id IN ({placeholders})");
+    let sql = pool.sql(&raw);
+    let mut query = sqlx::query_as::<_, T>(&sql);
+    for id in ids {
+        query = query.bind(*id);
+    }
+    let mut rows: HashMap<i64, T> = query
+        .fetch_all(pool.inner())
+        .await?
+        .into_iter()
+        .map(|row| (id_of(&row), row))
+        .collect();
+    Ok(ids.iter().filter_map(|id| rows.remove(id)).collect())
+}
+
+/// Greatest trigram similarity between `term` and any run of as many
+/// consecutive words of `text`, so a surname matches inside a full name.
+pub fn word_similarity(term: &str, text: &str) -> f64 {
+    let term_words = words(term);
+    let text_words = words(text);
+    if term_words.is_empty() || text_words.is_empty() {
+        return 0.0;
+    }
+    let term_grams = trigram_set(&term_words);
+    let span = term_words.len().min(text_words.len());
+    text_words
+        .windows(span)
+        .map(|window| similarity(&term_grams, &trigram_set(window)))
+        .fold(0.0, f64::max)
+}
+
+fn words(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_uppercase)
+        .collect()
+}
+
+/// Trigrams of each word padded with two leading and one trailing space.
+fn trigram_set(words: &[String]) -> HashSet<[char; 3]> {
+    let mut grams = HashSet::new();
+    for word in words {
+        let padded: Vec<char> = "  ".chars().chain(word.chars()).chain([' ']).collect();
+        grams.extend(padded.windows(3).map(|w| [w[0], w[1], w[2]]));
+    }
+    grams
+}
+
+fn similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let shared = a.intersection(b).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 {
+        0.0
+    } else {
+        shared as f64 / total as f64
+    }
+}
+
+/// Fewest [`like_trigrams`] of `term` a text must contain to reach
+/// `threshold` in [`word_similarity`]: the similarity is at most the share of
+/// padded query trigrams found in the text, and each word has three padded
+/// trigrams that `LIKE` does not look for.
+fn min_trigram_hits(term: &str, threshold: f64) -> i32 {
+    let words = words(term);
+    let padded = trigram_set(&words).len() as f64;
+    let unpadded: HashSet<String> = words
+        .iter()
+        .flat_map(|word| {
+            let chars: Vec<char> = word.chars().collect();
+            chars
+                .windows(3)
+                .map(|w| w.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let unchecked = unpadded.len().saturating_sub(MAX_QUERY_TRIGRAMS);
+    let hits = (threshold * padded).ceil() - (3 * words.len() + unchecked) as f64;
+    hits.max(1.0) as i32
+}
+
+/// Unpadded query trigrams for the `LIKE` pre-filter. Words shorter than
+/// three characters are used whole when the query has nothing longer.
+fn like_trigrams(term: &str) -> Vec<String> {
+    let words = words(term);
+    let mut grams: Vec<String> = Vec::new();
+    for word in &words {
+        let chars: Vec<char> = word.chars().collect();
+        for w in chars.windows(3) {
+            let gram: String = w.iter().collect();
+            if !grams.contains(&gram) {
+                grams.push(gram);
+            }
+        }
+    }
+    if grams.is_empty() {
+        grams = words;
+    }
+    grams.truncate(MAX_QUERY_TRIGRAMS);
+    grams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+    use crate::db::queries::{authors, books};
+
+    #[test]
+    fn test_word_similarity() {
+        assert_eq!(word_similarity("Tolkien", "TOLKIEN"), 1.0);
+        assert!(word_similarity("Tolkein", "TOLKIEN") >= 0.3);
+        assert!(word_similarity("TOLKEIN", "TOLKIEN JOHN RONALD REUEL") >= 0.3);
+        assert!(word_similarity("TOLKEIN", "TOLSTOY LEV") < 0.3);
+        assert_eq!(word_similarity("", "TOLKIEN"), 0.0);
+    }
+
+    #[test]
+    fn test_like_trigrams() {
+        assert_eq!(
+            like_trigrams("Tolkein"),
+            ["TOL", "OLK", "LKE", "KEI", "EIN"]
+        );
+        assert_eq!(like_trigrams("It"), ["IT"]);
+        assert!(like_trigrams(" - ").is_empty());
+    }
+
+    #[test]
+    fn test_min_trigram_hits() {
+        assert_eq!(min_trigram_hits("Tolkein", 0.3), 1);
+        assert_eq!(min_trigram_hits("It", 0.9), 1);
+        let term = "Hitchhiker Guide Galaxy";
+        let hits = min_trigram_hits(term, 0.6);
+        assert!(hits > 1);
+        // An exact match always passes.
+        let text = term.to_uppercase();
+        let found = like_trigrams(term)
+            .iter()
+            .filter(|gram| text.contains(gram.as_str()))
+            .count();
+        assert!(found as i32 >= hits);
+    }
+
+    #[tokio::test]
+    async fn test_search_authors_and_books_in_process() {
+        let pool = create_test_pool().await;
+        let tolkien = authors::insert(&pool, "Tolkien John", "TOLKIEN JOHN", 2)
+            .await
+            .unwrap();
+        authors::insert(&pool, "Tolstoy Lev", "TOLSTOY LEV", 2)
+            .await
+            .unwrap();
+
+        let found = search_authors(&pool, "TOLKEIN", 10, 0.3, false)
+            .await
+            .unwrap();
+        assert_eq!(found.iter().map(|a| a.id).collect::<Vec<_>>(), [tolkien]);
+        assert!(
+            search_authors(&pool, "TOLKEIN", 10, 0.9, false)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let sql = pool.sql("INSERT INTO catalogs (path, cat_name) VALUES ('/fuzzy', 'fuzzy')");
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+        let sql = pool.sql("SELECT id FROM catalogs WHERE path = '/fuzzy'");
+        let (cat,): (i64,) = sqlx::query_as(&sql).fetch_one(pool.inner()).await.unwrap();
+        let mut ids = Vec::new();
+        for (file, title) in [
+            ("a.fb2", "The Hobbit"),
+            ("b.fb2", "The Hobbit"),
+            ("c.fb2", "Hobbies"),
+        ] {
+            let id = books::insert(
+                &pool,
+                cat,
+                file,
+                "/fuzzy",
+                "fb2",
+                title,
+                &title.to_uppercase(),
+                "",
+                "",
+                "en",
+                2,
+                100,
+                crate::db::models::CatType::Normal,
+                0,
+                "",
+            )
+            .await
+            .unwrap();
+            ids.push(id);
+        }
+
+        let found = search_books(&pool, "HOBIT", 10, false, 0.3, false)
+            .await
+            .unwrap();
+        let found: Vec<i64> = found.iter().map(|b| b.id).collect();
+        assert_eq!(found, [ids[1], ids[0]]);
+        let found = search_books(&pool, "HOBIT", 10, true, 0.3, false)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+    }
+}
@@ -145,6 +145,29 @@ pub async fn get_for_book(
         .await
 }
 
+/// Genres a user downloads most, judged by the books on their bookshelf.
+/// Returns genre IDs, most frequent first.
+pub async fn get_favorite_ids(
+    pool: &DbPool,
+    user_id: i64,
+    limit: i32,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT bg.genre_id FROM bookshelf bs \
+         JOIN book_genres bg ON bg.book_id = bs.book_id \
+         WHERE bs.user_id = ? \
+         GROUP BY bg.genre_id \
+         ORDER BY COUNT(*) DESC, bg.genre_id \
+         LIMIT ?",
+    );
+    let rows: Vec<(i64,)> = sqlx::query_as(&sql)
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
 /// Section codes with translated names and book counts. Returns `(code, name, count)`.
 pub async fn get_sections_with_counts(
     pool: &DbPool,
@@ -192,7 +215,7 @@ pub async fn get_by_section_with_counts(
                COALESCE(gst.name, gst_en.name, g.section) AS section, \
                COALESCE(gt.name, gt_en.name, g.subsection) AS subsection, \
                g.section_id, \
-               COUNT(DISTINCT bg.book_id) AS cnt \
+               COUNT(DISTINCT b.id) AS cnt \
          FROM genres g \
          JOIN genre_sections gs ON gs.id = g.section_id \
          LEFT JOIN book_genres bg ON bg.genre_id = g.id \
@@ -234,14 +257,7 @@ pub async fn get_by_section_with_counts(
 // ---------------------------------------------------------------------------
 
 pub async fn link_book(pool: &DbPool, book_id: i64, genre_id: i64) -> Result<(), sqlx::Error> {
-    let sql = match pool.backend() {
-        DbBackend::Mysql => "INSERT IGNORE INTO book_genres (book_id, genre_id) VALUES (?, ?)",
-        _ => {
-            "INSERT INTO book_genres (book_id, genre_id) VALUES (?, ?) \
-             ON CONFLICT (book_id, genre_id) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_ignore("book_genres", &["book_id", "genre_id"]);
     sqlx::query(&sql)
         .bind(book_id)
         .bind(genre_id)
@@ -270,14 +286,7 @@ pub async fn set_book_genres(
         .bind(book_id)
         .execute(pool.inner())
         .await?;
-    let sql = match pool.backend() {
-        DbBackend::Mysql => "INSERT IGNORE INTO book_genres (book_id, genre_id) VALUES (?, ?)",
-        _ => {
-            "INSERT INTO book_genres (book_id, genre_id) VALUES (?, ?) \
-             ON CONFLICT (book_id, genre_id) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_ignore("book_genres", &["book_id", "genre_id"]);
     for &genre_id in genre_ids {
         sqlx::query(&sql)
             .bind(book_id)
@@ -344,17 +353,12 @@ pub async fn upsert_section_translation(
     lang: &str,
     name: &str,
 ) -> Result<(), sqlx::Error> {
-    let sql = match pool.backend() {
-        DbBackend::Mysql => {
-            "INSERT INTO genre_section_translations (section_id, lang, name) VALUES (?, ?, ?) \
-             ON DUPLICATE KEY UPDATE name = VALUES(name)"
-        }
-        _ => {
-            "INSERT INTO genre_section_translations (section_id, lang, name) VALUES (?, ?, ?) \
-             ON CONFLICT (section_id, lang) DO UPDATE SET name = excluded.name"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_update(
+        "genre_section_translations",
+        &["section_id", "lang", "name"],
+        &["section_id", "lang"],
+        &["name"],
+    );
     sqlx::query(&sql)
         .bind(section_id)
         .bind(lang)
@@ -431,17 +435,12 @@ pub async fn upsert_genre_translation(
     lang: &str,
     name: &str,
 ) -> Result<(), sqlx::Error> {
-    let sql = match pool.backend() {
-        DbBackend::Mysql => {
-            "INSERT INTO genre_translations (genre_id, lang, name) VALUES (?, ?, ?) \
-             ON DUPLICATE KEY UPDATE name = VALUES(name)"
-        }
-        _ => {
-            "INSERT INTO genre_translations (genre_id, lang, name) VALUES (?, ?, ?) \
-             ON CONFLICT (genre_id, lang) DO UPDATE SET name = excluded.name"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_update(
+        "genre_translations",
+        &["genre_id", "lang", "name"],
+        &["genre_id", "lang"],
+        &["name"],
+    );
     sqlx::query(&sql)
         .bind(genre_id)
         .bind(lang)
@@ -507,6 +506,65 @@ pub async fn get_all_admin(
     Ok(result)
 }
 
+// ---------------------------------------------------------------------------
+// Default taxonomy
+// ---------------------------------------------------------------------------
+
+/// The standard FB2 genre taxonomy (sections, genres and their en/ru names)
+/// seeded by the initial migrations. The SQLite files are plain multi-row
+/// `INSERT`s that every backend accepts.
+const DEFAULT_TAXONOMY_SQL: [&str; 3] = [
+    include_str!("../../../migrations/sqlite/002_genres_seed.sql"),
+    include_str!("../../../migrations/sqlite/003_genre_translations_en.sql"),
+    include_str!("../../../migrations/sqlite/004_genre_translations_ru.sql"),
+];
+
+/// Rows added by [`seed_defaults`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct SeedReport {
+    pub sections: u64,
+    pub genres: u64,
+    pub translations: u64,
+}
+
+/// Restore missing default sections, genres and translations. Rows that
+/// still exist, including edited names, are left alone.
+pub async fn seed_defaults(pool: &DbPool) -> Result<SeedReport, sqlx::Error> {
+    let mut report = SeedReport::default();
+    let mut tx = pool.inner().begin().await?;
+    for file in DEFAULT_TAXONOMY_SQL {
+        for statement in file.split(';') {
+            let statement = statement
+                .lines()
+                .filter(|line| !line.trim_start().starts_with("--"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let Some(rest) = statement.trim().strip_prefix("INSERT INTO ") else {
+                continue;
+            };
+            let sql = match pool.backend() {
+                DbBackend::Mysql => format!("INSERT IGNORE INTO {rest}"),
+                _ => format!("INSERT INTO {rest} ON CONFLICT DO NOTHING"),
+            };
+            let added = sqlx::query(&sql).execute(&mut *tx).await?.rows_affected();
+            match rest.split_whitespace().next() {
+                Some("genre_sections") => report.sections += added,
+                Some("genres") => report.genres += added,
+                _ => report.translations += added,
+            }
+        }
+    }
+    if pool.backend() == DbBackend::Postgres {
+        // Explicit ids do not advance the sequences.
+        for table in ["genre_sections", "genres"] {
+            let sql = format!("SELECT setval('{table}_id_seq', (SELECT MAX(id) FROM {table}))");
+            sqlx::query(&sql).execute(&mut *tx).await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(report)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -785,4 +843,44 @@ mod tests {
         assert_eq!(d2.2, "ut_section_d");
         assert!(d2.3.iter().any(|t| t.lang == "en" && t.name == "Genre D2"));
     }
+
+    #[tokio::test]
+    async fn test_seed_defaults_restores_missing_rows_only() {
+        let pool = create_test_pool().await;
+        let genre = get_by_code(&pool, "sf_fantasy_city")
+            .await
+            .unwrap()
+            .unwrap();
+        delete_genre_translation(&pool, genre.id, "en")
+            .await
+            .unwrap();
+        delete_genre_translation(&pool, genre.id, "ru")
+            .await
+            .unwrap();
+        delete_genre(&pool, genre.id).await.unwrap();
+        delete_section_translation(&pool, 1, "ru").await.unwrap();
+        upsert_section_translation(&pool, 2, "en", "Crime")
+            .await
+            .unwrap();
+
+        let report = seed_defaults(&pool).await.unwrap();
+        assert_eq!(
+            report,
+            SeedReport {
+                sections: 0,
+                genres: 1,
+                translations: 3,
+            }
+        );
+        let restored = get_by_code(&pool, "sf_fantasy_city")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.id, genre.id);
+        assert_eq!(restored.subsection, genre.subsection);
+        let names = get_section_translations(&pool, 2).await.unwrap();
+        assert!(names.iter().any(|t| t.lang == "en" && t.name == "Crime"));
+
+        assert_eq!(seed_defaults(&pool).await.unwrap(), SeedReport::default());
+    }
 }
@@ -1,11 +1,79 @@
+pub mod audit;
 pub mod authors;
+pub mod book_audio;
+pub mod book_files;
+pub mod book_terms;
 pub mod books;
 pub mod bookshelf;
+pub mod cached;
 pub mod catalogs;
 pub mod counters;
+pub mod devices;
+pub mod downloads;
+pub mod fuzzy;
 pub mod genres;
 pub mod oauth;
 pub mod reading_positions;
+pub mod recommendations;
+pub mod scan_lock;
 pub mod series;
+pub mod shares;
+pub mod suggest;
 pub mod suppressed;
+pub mod upload_quota;
 pub mod users;
+
+/// How a search term matches a name or title: the `b` (begins), `m`
+/// (contains) and `e` (exact) search types of the web and OPDS searches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    Begins,
+    #[default]
+    Contains,
+    Exact,
+}
+
+impl MatchMode {
+    /// Mode of a search type code; unknown codes search by substring.
+    pub fn from_search_type(search_type: &str) -> Self {
+        match search_type {
+            "b" => Self::Begins,
+            "e" => Self::Exact,
+            _ => Self::Contains,
+        }
+    }
+
+    pub fn as_search_type(self) -> &'static str {
+        match self {
+            Self::Begins => "b",
+            Self::Contains => "m",
+            Self::Exact => "e",
+        }
+    }
+
+    /// `LIKE` pattern matching values that relate to `term` in this mode.
+    pub fn like_pattern(self, term: &str) -> String {
+        match self {
+            Self::Begins => format!("{term}%"),
+            Self::Contains => format!("%{term}%"),
+            Self::Exact => term.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_mode_patterns() {
+        assert_eq!(MatchMode::from_search_type("b"), MatchMode::Begins);
+        assert_eq!(MatchMode::from_search_type("e"), MatchMode::Exact);
+        assert_eq!(MatchMode::from_search_type("m"), MatchMode::Contains);
+        assert_eq!(MatchMode::from_search_type("x"), MatchMode::Contains);
+        assert_eq!(MatchMode::Begins.like_pattern("DOE"), "DOE%");
+        assert_eq!(MatchMode::Contains.like_pattern("DOE"), "%DOE%");
+        assert_eq!(MatchMode::Exact.like_pattern("DOE"), "DOE");
+        assert_eq!(MatchMode::Exact.as_search_type(), "e");
+    }
+}
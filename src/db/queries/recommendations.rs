@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use crate::db::DbPool;
+use crate::db::models::Book;
+
+/// Score added for every user who downloaded both books.
+const CO_DOWNLOAD_WEIGHT: i64 = 3;
+/// Score added for every author the two books share.
+const AUTHOR_WEIGHT: i64 = 2;
+/// Score added for every genre the two books share.
+const GENRE_WEIGHT: i64 = 1;
+/// Candidates fetched per seed book and similarity kind.
+const CANDIDATE_LIMIT: i32 = 100;
+/// Recommendations kept per seed book.
+const MAX_PER_BOOK: usize = 20;
+
+/// Rebuild the `recommendations` table.
+///
+/// Seed books are the available books users have downloaded or put on their
+/// bookshelf. Each seed gets up to [`MAX_PER_BOOK`] related books scored by
+/// co-downloads ("users who downloaded X also downloaded Y"), shared authors
+/// and shared genres. Other editions of the seed itself (same title) are
+/// skipped. Returns the number of stored rows.
+pub async fn rebuild(pool: &DbPool) -> Result<usize, sqlx::Error> {
+    let mut scores: HashMap<i64, HashMap<i64, i64>> = HashMap::new();
+
+    for (book_id, rec_book_id, users) in co_downloads(pool).await? {
+        *scores
+            .entry(book_id)
+            .or_default()
+            .entry(rec_book_id)
+            .or_default() += users * CO_DOWNLOAD_WEIGHT;
+    }
+
+    for seed in seed_books(pool).await? {
+        let entry = scores.entry(seed).or_default();
+        for (rec_book_id, shared) in shared_links(pool, seed, "book_authors", "author_id").await? {
+            *entry.entry(rec_book_id).or_default() += shared * AUTHOR_WEIGHT;
+        }
+        for (rec_book_id, shared) in shared_links(pool, seed, "book_genres", "genre_id").await? {
+            *entry.entry(rec_book_id).or_default() += shared * GENRE_WEIGHT;
+        }
+    }
+
+    let mut tx = pool.inner().begin().await?;
+    let sql = pool.sql("DELETE FROM recommendations");
+    sqlx::query(&sql).execute(&mut *tx).await?;
+    let sql =
+        pool.sql("INSERT INTO recommendations (book_id, rec_book_id, score) VALUES (?, ?, ?)");
+    let mut stored = 0;
+    for (book_id, related) in scores {
+        let mut related: Vec<(i64, i64)> = related.into_iter().collect();
+        related.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+        related.truncate(MAX_PER_BOOK);
+        for (rec_book_id, score) in related {
+            sqlx::query(&sql)
+                .bind(book_id)
+                .bind(rec_book_id)
+                .bind(score)
+                .execute(&mut *tx)
+                .await?;
+            stored += 1;
+        }
+    }
+    tx.commit().await?;
+    Ok(stored)
+}
+
+/// Available books related to `book_id`, best match first.
+pub async fn get_for_book(
+    pool: &DbPool,
+    book_id: i64,
+    limit: i32,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT b.* FROM recommendations r \
+         JOIN books b ON b.id = r.rec_book_id \
+         WHERE r.book_id = ? AND b.avail > 0 \
+         ORDER BY r.score DESC, b.id DESC LIMIT ?",
+    );
+    sqlx::query_as::<_, Book>(&sql)
+        .bind(book_id)
+        .bind(limit)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Personal recommendations: books related to the user's bookshelf and
+/// downloads, excluding the books they already have, best match first.
+pub async fn get_for_user(
+    pool: &DbPool,
+    user_id: i64,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT b.* FROM books b \
+         JOIN (SELECT rec_book_id, SUM(score) AS total FROM recommendations \
+               WHERE book_id IN (SELECT book_id FROM bookshelf WHERE user_id = ? \
+                                 UNION SELECT book_id FROM downloads WHERE user_id = ?) \
+               GROUP BY rec_book_id) r ON r.rec_book_id = b.id \
+         WHERE b.avail > 0 \
+         AND NOT EXISTS (SELECT 1 FROM bookshelf bs WHERE bs.book_id = b.id AND bs.user_id = ?) \
+         AND NOT EXISTS (SELECT 1 FROM downloads d WHERE d.book_id = b.id AND d.user_id = ?) \
+         ORDER BY r.total DESC, b.id DESC LIMIT ? OFFSET ?",
+    );
+    sqlx::query_as::<_, Book>(&sql)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Available books downloaded by a signed-in user or kept on a bookshelf.
+async fn seed_books(pool: &DbPool) -> Result<Vec<i64>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT d.book_id FROM downloads d JOIN books b ON b.id = d.book_id \
+         WHERE d.user_id IS NOT NULL AND b.avail > 0 \
+         UNION \
+         SELECT bs.book_id FROM bookshelf bs JOIN books b ON b.id = bs.book_id \
+         WHERE b.avail > 0",
+    );
+    let rows: Vec<(i64,)> = sqlx::query_as(&sql).fetch_all(pool.inner()).await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Pairs of available books downloaded by the same users, as
+/// `(book_id, rec_book_id, users)`.
+async fn co_downloads(pool: &DbPool) -> Result<Vec<(i64, i64, i64)>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT a.book_id, c.id, COUNT(DISTINCT a.user_id) AS readers \
+         FROM downloads a \
+         JOIN downloads o ON o.user_id = a.user_id AND o.book_id <> a.book_id \
+         JOIN books s ON s.id = a.book_id \
+         JOIN books c ON c.id = o.book_id \
+         WHERE a.user_id IS NOT NULL AND s.avail > 0 AND c.avail > 0 \
+         AND c.search_title <> s.search_title \
+         GROUP BY a.book_id, c.id",
+    );
+    sqlx::query_as(&sql).fetch_all(pool.inner()).await
+}
+
+/// Available books sharing `column` values with `book_id` through the `table`
+/// junction, as `(rec_book_id, shared)`, most shared first.
+async fn shared_links(
+    pool: &DbPool,
+    book_id: i64,
+    table: &str,
+    column: &str,
+) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    let raw = format!(
+        "SELECT c.id, COUNT(*) AS shared FROM {table} l1 \
+         JOIN {table} l2 ON l2.{column} = l1.{column} AND l2.book_id <> l1.book_id \
+         JOIN books s ON s.id = l1.book_id \
+         JOIN books c ON c.id = l2.book_id \
+         WHERE l1.book_id = ? AND c.avail > 0 AND c.search_title <> s.search_title \
+         GROUP BY c.id \
+         ORDER BY shared DESC, c.id DESC LIMIT ?"
+    );
+    let sql = pool.sql(&raw);
+    sqlx::query_as(&sql)
+        .bind(book_id)
+        .bind(CANDIDATE_LIMIT)
+        .fetch_all(pool.inner())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+    use crate::db::queries::{authors, bookshelf, downloads, genres, users};
+
+    async fn insert_book(pool: &DbPool, title: &str) -> i64 {
+        let sql = pool.sql("INSERT INTO catalogs (path, cat_name) VALUES (?, ?)");
+        sqlx::query(&sql)
+            .bind(format!("/{title}"))
+            .bind(title)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        let sql = pool.sql("SELECT id FROM catalogs WHERE path = ?");
+        let (catalog_id,): (i64,) = sqlx::query_as(&sql)
+            .bind(format!("/{title}"))
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        let sql = pool.sql(
+            "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+             lang, lang_code, size, avail, cat_type, cover, cover_type) \
+             VALUES (?, ?, '/dl', 'fb2', ?, ?, 'en', 2, 100, 2, 0, 0, '')",
+        );
+        sqlx::query(&sql)
+            .bind(catalog_id)
+            .bind(format!("{title}.fb2"))
+            .bind(title)
+            .bind(title.to_uppercase())
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        let sql = pool.sql("SELECT id FROM books WHERE title = ?");
+        let (id,): (i64,) = sqlx::query_as(&sql)
+            .bind(title)
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        id
+    }
+
+    fn ids(books: &[Book]) -> Vec<i64> {
+        books.iter().map(|b| b.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_scores_co_downloads_authors_and_genres() {
+        let pool = create_test_pool().await;
+        let seed = insert_book(&pool, "Seed").await;
+        let co_read = insert_book(&pool, "Co Read").await;
+        let same_author = insert_book(&pool, "Same Author").await;
+        let same_genre = insert_book(&pool, "Same Genre").await;
+        let unrelated = insert_book(&pool, "Unrelated").await;
+
+        let alice = users::create(&pool, "alice", "", 0, "").await.unwrap();
+        let bob = users::create(&pool, "bob", "", 0, "").await.unwrap();
+        for (book_id, user_id) in [(seed, alice), (co_read, alice), (seed, bob)] {
+            downloads::record(&pool, book_id, Some(user_id))
+                .await
+                .unwrap();
+        }
+        // Anonymous downloads never link books together.
+        downloads::record(&pool, unrelated, None).await.unwrap();
+
+        let author = authors::insert(&pool, "Shared Author", "SHARED AUTHOR", 2)
+            .await
+            .unwrap();
+        authors::link_book(&pool, seed, author).await.unwrap();
+        authors::link_book(&pool, same_author, author)
+            .await
+            .unwrap();
+        let genre_id = genres::get_all(&pool, "en").await.unwrap()[0].id;
+        genres::link_book(&pool, seed, genre_id).await.unwrap();
+        genres::link_book(&pool, same_genre, genre_id)
+            .await
+            .unwrap();
+
+        assert!(rebuild(&pool).await.unwrap() > 0);
+        assert_eq!(
+            ids(&get_for_book(&pool, seed, 10).await.unwrap()),
+            vec![co_read, same_author, same_genre]
+        );
+        assert_eq!(
+            ids(&get_for_book(&pool, co_read, 10).await.unwrap()),
+            vec![seed]
+        );
+        assert!(get_for_book(&pool, unrelated, 10).await.unwrap().is_empty());
+
+        // Bob downloaded only the seed: suggest its neighbours, not the seed itself.
+        assert_eq!(
+            ids(&get_for_user(&pool, bob, 10, 0).await.unwrap()),
+            vec![co_read, same_author, same_genre]
+        );
+        assert_eq!(
+            ids(&get_for_user(&pool, bob, 1, 1).await.unwrap()),
+            vec![same_author]
+        );
+
+        // Bookshelf entries count as interest and are excluded from suggestions.
+        bookshelf::upsert(&pool, bob, same_author).await.unwrap();
+        assert_eq!(
+            ids(&get_for_user(&pool, bob, 10, 0).await.unwrap()),
+            vec![co_read, same_genre]
+        );
+
+        // A rebuild replaces the previous rows.
+        let stored = rebuild(&pool).await.unwrap();
+        let sql = pool.sql("SELECT COUNT(*) FROM recommendations");
+        let (count,): (i64,) = sqlx::query_as(&sql).fetch_one(pool.inner()).await.unwrap();
+        assert_eq!(count as usize, stored);
+    }
+}
@@ -0,0 +1,93 @@
+//! Library scan lock in the `scan_lock` table, shared by all instances that
+//! use the same database. Times are Unix seconds.
+
+use crate::db::DbPool;
+
+/// Take the lock for `holder` until `expires_at` if it is free or expired.
+/// Returns `true` when the lock was taken.
+pub async fn try_acquire(
+    pool: &DbPool,
+    holder: &str,
+    now: i64,
+    expires_at: i64,
+) -> Result<bool, sqlx::Error> {
+    let sql = pool.sql(
+        "UPDATE scan_lock SET holder = ?, expires_at = ? \
+         WHERE id = 1 AND (holder = '' OR expires_at < ?)",
+    );
+    let result = sqlx::query(&sql)
+        .bind(holder)
+        .bind(expires_at)
+        .bind(now)
+        .execute(pool.inner())
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Push the expiry of a lock held by `holder` forward. Returns `false` if the
+/// lock was lost (taken over after expiring).
+pub async fn refresh(pool: &DbPool, holder: &str, expires_at: i64) -> Result<bool, sqlx::Error> {
+    let sql = pool.sql("UPDATE scan_lock SET expires_at = ? WHERE id = 1 AND holder = ?");
+    let result = sqlx::query(&sql)
+        .bind(expires_at)
+        .bind(holder)
+        .execute(pool.inner())
+        .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Release the lock if `holder` still has it.
+pub async fn release(pool: &DbPool, holder: &str) -> Result<(), sqlx::Error> {
+    let sql =
+        pool.sql("UPDATE scan_lock SET holder = '', expires_at = 0 WHERE id = 1 AND holder = ?");
+    sqlx::query(&sql).bind(holder).execute(pool.inner()).await?;
+    Ok(())
+}
+
+/// Holder of an unexpired lock, if any.
+pub async fn current_holder(pool: &DbPool, now: i64) -> Result<Option<String>, sqlx::Error> {
+    let sql =
+        pool.sql("SELECT holder FROM scan_lock WHERE id = 1 AND holder <> '' AND expires_at >= ?");
+    let row: Option<(String,)> = sqlx::query_as(&sql)
+        .bind(now)
+        .fetch_optional(pool.inner())
+        .await?;
+    Ok(row.map(|(holder,)| holder))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    #[tokio::test]
+    async fn test_acquire_refresh_release_and_expiry() {
+        let pool = create_test_pool().await;
+        assert_eq!(current_holder(&pool, 100).await.unwrap(), None);
+
+        assert!(try_acquire(&pool, "a", 100, 400).await.unwrap());
+        assert!(!try_acquire(&pool, "b", 200, 500).await.unwrap());
+        assert_eq!(
+            current_holder(&pool, 200).await.unwrap().as_deref(),
+            Some("a")
+        );
+
+        assert!(refresh(&pool, "a", 700).await.unwrap());
+        assert!(!try_acquire(&pool, "b", 500, 800).await.unwrap());
+
+        // A stale lock is taken over; its old holder can neither refresh nor
+        // release it.
+        assert_eq!(current_holder(&pool, 701).await.unwrap(), None);
+        assert!(try_acquire(&pool, "b", 701, 1000).await.unwrap());
+        assert!(!refresh(&pool, "a", 1100).await.unwrap());
+        release(&pool, "a").await.unwrap();
+        assert_eq!(
+            current_holder(&pool, 800).await.unwrap().as_deref(),
+            Some("b")
+        );
+
+        release(&pool, "b").await.unwrap();
+        assert_eq!(current_holder(&pool, 800).await.unwrap(), None);
+        assert!(try_acquire(&pool, "a", 800, 900).await.unwrap());
+    }
+}
@@ -1,6 +1,13 @@
-use crate::db::{DbBackend, DbPool};
+use crate::db::DbPool;
 
-use crate::db::models::Series;
+use crate::db::models::{Book, Series};
+use crate::db::queries::bookshelf::FINISHED_PROGRESS;
+
+/// Limits the alphabet drill-down (prefix groups, counts and listings) to
+/// series with at least one available book, so the three stay in agreement.
+pub(crate) const WITH_BOOKS: &str = "EXISTS (SELECT 1 FROM book_series bs \
+     JOIN books b ON b.id = bs.book_id \
+     WHERE bs.series_id = series.id AND b.avail > 0)";
 
 pub async fn get_by_id(pool: &DbPool, id: i64) -> Result<Option<Series>, sqlx::Error> {
     let sql = pool.sql("SELECT * FROM series WHERE id = ?");
@@ -37,10 +44,11 @@ pub async fn get_by_lang_code_prefix(
     offset: i32,
 ) -> Result<Vec<Series>, sqlx::Error> {
     if prefix.is_empty() {
-        let sql = pool.sql(
-            "SELECT * FROM series WHERE (? = 0 OR lang_code = ?) \
-             ORDER BY search_ser LIMIT ? OFFSET ?",
+        let raw = format!(
+            "SELECT * FROM series WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
+             ORDER BY search_ser LIMIT ? OFFSET ?"
         );
+        let sql = pool.sql(&raw);
         return sqlx::query_as::<_, Series>(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -52,11 +60,12 @@ pub async fn get_by_lang_code_prefix(
     // Word-boundary prefix match: either at start of the name or after a space.
     let start_pat = format!("{prefix}%");
     let word_pat = format!("% {prefix}%");
-    let sql = pool.sql(
-        "SELECT * FROM series WHERE (? = 0 OR lang_code = ?) \
+    let raw = format!(
+        "SELECT * FROM series WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
          AND (search_ser LIKE ? OR search_ser LIKE ?) \
-         ORDER BY search_ser LIMIT ? OFFSET ?",
+         ORDER BY search_ser LIMIT ? OFFSET ?"
     );
+    let sql = pool.sql(&raw);
     sqlx::query_as::<_, Series>(&sql)
         .bind(lang_code)
         .bind(lang_code)
@@ -82,16 +91,7 @@ pub async fn insert(
     search_ser: &str,
     lang_code: i32,
 ) -> Result<i64, sqlx::Error> {
-    let sql = match pool.backend() {
-        DbBackend::Mysql => {
-            "INSERT IGNORE INTO series (ser_name, search_ser, lang_code) VALUES (?, ?, ?)"
-        }
-        _ => {
-            "INSERT INTO series (ser_name, search_ser, lang_code) VALUES (?, ?, ?) \
-             ON CONFLICT (ser_name) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_ignore("series", &["ser_name", "search_ser", "lang_code"]);
     let result = sqlx::query(&sql)
         .bind(ser_name)
         .bind(search_ser)
@@ -118,16 +118,7 @@ pub async fn link_book(
     series_id: i64,
     ser_no: i32,
 ) -> Result<(), sqlx::Error> {
-    let sql = match pool.backend() {
-        DbBackend::Mysql => {
-            "INSERT IGNORE INTO book_series (book_id, series_id, ser_no) VALUES (?, ?, ?)"
-        }
-        _ => {
-            "INSERT INTO book_series (book_id, series_id, ser_no) VALUES (?, ?, ?) \
-             ON CONFLICT (book_id, series_id) DO NOTHING"
-        }
-    };
-    let sql = pool.sql(sql);
+    let sql = pool.upsert_ignore("book_series", &["book_id", "series_id", "ser_no"]);
     sqlx::query(&sql)
         .bind(book_id)
         .bind(series_id)
@@ -164,6 +155,69 @@ pub async fn get_for_book(pool: &DbPool, book_id: i64) -> Result<Vec<(Series, i3
         .collect())
 }
 
+/// The available volume following a book in one of its numbered series.
+pub async fn next_in_series(pool: &DbPool, book_id: i64) -> Result<Option<Book>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT b.* FROM books b \
+         JOIN book_series bs ON bs.book_id = b.id \
+         JOIN book_series cur ON cur.series_id = bs.series_id \
+         WHERE cur.book_id = ? AND cur.ser_no > 0 AND bs.ser_no > cur.ser_no AND b.avail > 0 \
+         ORDER BY bs.ser_no, b.id LIMIT 1",
+    );
+    sqlx::query_as::<_, Book>(&sql)
+        .bind(book_id)
+        .fetch_optional(pool.inner())
+        .await
+}
+
+/// The next unread volume of each series a user has finished a book of,
+/// most recently read series first: the available book numbered lowest
+/// above the highest finished volume.
+pub async fn next_unread_for_user(
+    pool: &DbPool,
+    user_id: i64,
+    limit: i32,
+    offset: i32,
+) -> Result<Vec<Book>, sqlx::Error> {
+    let raw = format!(
+        "SELECT bs.series_id, b.id FROM books b \
+         JOIN book_series bs ON bs.book_id = b.id \
+         JOIN (SELECT f.series_id, MAX(f.ser_no) AS reached, MAX(rp.updated_at) AS last_read \
+               FROM book_series f \
+               JOIN reading_positions rp ON rp.book_id = f.book_id \
+               WHERE rp.user_id = ? AND rp.progress >= {FINISHED_PROGRESS} AND f.ser_no > 0 \
+               GROUP BY f.series_id) r ON r.series_id = bs.series_id \
+         WHERE b.avail > 0 AND bs.ser_no > r.reached \
+         AND NOT EXISTS (SELECT 1 FROM reading_positions done \
+                         WHERE done.user_id = ? AND done.book_id = b.id \
+                         AND done.progress >= {FINISHED_PROGRESS}) \
+         ORDER BY r.last_read DESC, bs.series_id, bs.ser_no, b.id"
+    );
+    let sql = pool.sql(&raw);
+    let rows: Vec<(i64, i64)> = sqlx::query_as(&sql)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(pool.inner())
+        .await?;
+
+    // Rows come grouped by series, lowest volume first.
+    let mut seen = std::collections::HashSet::new();
+    let book_ids: Vec<i64> = rows
+        .into_iter()
+        .filter(|(series_id, _)| seen.insert(*series_id))
+        .map(|(_, book_id)| book_id)
+        .skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .collect();
+    let mut books = Vec::with_capacity(book_ids.len());
+    for book_id in book_ids {
+        if let Some(book) = crate::db::queries::books::get_by_id(pool, book_id).await? {
+            books.push(book);
+        }
+    }
+    Ok(books)
+}
+
 /// Count series matching a name search (contains).
 pub async fn count_by_name_search(pool: &DbPool, term: &str) -> Result<i64, sqlx::Error> {
     let pattern = format!("%{term}%");
@@ -185,7 +239,9 @@ pub async fn count_by_lang_code_prefix(
     prefix: &str,
 ) -> Result<i64, sqlx::Error> {
     if prefix.is_empty() {
-        let sql = pool.sql("SELECT COUNT(*) FROM series WHERE ? = 0 OR lang_code = ?");
+        let raw =
+            format!("SELECT COUNT(*) FROM series WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS}");
+        let sql = pool.sql(&raw);
         let row: (i64,) = sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -195,10 +251,11 @@ pub async fn count_by_lang_code_prefix(
     }
     let start_pat = format!("{prefix}%");
     let word_pat = format!("% {prefix}%");
-    let sql = pool.sql(
-        "SELECT COUNT(*) FROM series WHERE (? = 0 OR lang_code = ?) \
-         AND (search_ser LIKE ? OR search_ser LIKE ?)",
+    let raw = format!(
+        "SELECT COUNT(*) FROM series WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
+         AND (search_ser LIKE ? OR search_ser LIKE ?)"
     );
+    let sql = pool.sql(&raw);
     let row: (i64,) = sqlx::query_as(&sql)
         .bind(lang_code)
         .bind(lang_code)
@@ -219,7 +276,10 @@ pub async fn get_name_prefix_groups(
     current_prefix: &str,
 ) -> Result<Vec<(String, i64)>, sqlx::Error> {
     let names: Vec<(String,)> = if current_prefix.is_empty() {
-        let sql = pool.sql("SELECT search_ser FROM series WHERE ? = 0 OR lang_code = ?");
+        let raw = format!(
+            "SELECT search_ser FROM series WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS}"
+        );
+        let sql = pool.sql(&raw);
         sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -228,11 +288,12 @@ pub async fn get_name_prefix_groups(
     } else {
         let start_pat = format!("{}%", current_prefix);
         let word_pat = format!("% {}%", current_prefix);
-        let sql = pool.sql(
+        let raw = format!(
             "SELECT search_ser FROM series \
-             WHERE (? = 0 OR lang_code = ?) \
-             AND (search_ser LIKE ? OR search_ser LIKE ?)",
+             WHERE (? = 0 OR lang_code = ?) AND {WITH_BOOKS} \
+             AND (search_ser LIKE ? OR search_ser LIKE ?)"
         );
+        let sql = pool.sql(&raw);
         sqlx::query_as(&sql)
             .bind(lang_code)
             .bind(lang_code)
@@ -343,6 +404,18 @@ mod tests {
         row.0
     }
 
+    /// Give every series an available book; the alphabet drill-down
+    /// lists only those.
+    async fn link_books_to_all(pool: &DbPool) {
+        let catalog_id = ensure_catalog(pool).await;
+        let sql = pool.sql("SELECT id FROM series");
+        let ids: Vec<(i64,)> = sqlx::query_as(&sql).fetch_all(pool.inner()).await.unwrap();
+        for (id,) in ids {
+            let book_id = insert_test_book(pool, catalog_id, &format!("Book {id}")).await;
+            link_book(pool, book_id, id, 1).await.unwrap();
+        }
+    }
+
     #[tokio::test]
     async fn test_insert_search_count_and_prefix_groups() {
         let pool = create_test_pool().await;
@@ -367,6 +440,7 @@ mod tests {
         let count = count_by_name_search(&pool, "ALP").await.unwrap();
         assert_eq!(count, 2);
 
+        link_books_to_all(&pool).await;
         let prefix = get_by_lang_code_prefix(&pool, 2, "AL", 100, 0)
             .await
             .unwrap();
@@ -392,6 +466,7 @@ mod tests {
         insert(&pool, "Gabriel", "GABRIEL", 2).await.unwrap();
         insert(&pool, "Lamabad", "LAMABAD", 2).await.unwrap();
 
+        link_books_to_all(&pool).await;
         let by_ab = get_by_lang_code_prefix(&pool, 2, "AB", 100, 0)
             .await
             .unwrap();
@@ -406,6 +481,31 @@ mod tests {
         assert_eq!(prefixes, vec!["ABD", "ABE", "ABR"]);
     }
 
+    #[tokio::test]
+    async fn test_drill_down_skips_series_without_available_books() {
+        let pool = create_test_pool().await;
+        let live = insert(&pool, "Atlas", "ATLAS", 2).await.unwrap();
+        let gone = insert(&pool, "Abyss", "ABYSS", 2).await.unwrap();
+        let catalog_id = ensure_catalog(&pool).await;
+        let book_id = insert_test_book(&pool, catalog_id, "Kept").await;
+        link_book(&pool, book_id, live, 1).await.unwrap();
+        let book_id = insert_test_book(&pool, catalog_id, "Removed").await;
+        link_book(&pool, book_id, gone, 1).await.unwrap();
+        let sql = pool.sql("UPDATE books SET avail = 0 WHERE id = ?");
+        sqlx::query(&sql)
+            .bind(book_id)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+
+        let groups = get_name_prefix_groups(&pool, 0, "").await.unwrap();
+        assert_eq!(groups, vec![("A".to_string(), 1)]);
+        assert_eq!(count_by_lang_code_prefix(&pool, 2, "A").await.unwrap(), 1);
+        let listed = get_by_lang_code_prefix(&pool, 0, "", 100, 0).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, live);
+    }
+
     #[tokio::test]
     async fn test_insert_duplicate_returns_same_id() {
         let pool = create_test_pool().await;
@@ -468,4 +568,56 @@ mod tests {
         // Dune should also be cleaned up
         assert!(find_by_name(&pool, "Dune").await.unwrap().is_none());
     }
+
+    #[tokio::test]
+    async fn test_next_in_series_and_next_unread_for_user() {
+        let pool = create_test_pool().await;
+        let catalog_id = ensure_catalog(&pool).await;
+        let sql = pool
+            .sql("INSERT INTO users (username, password_hash, is_superuser) VALUES ('r', 'h', 0)");
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+        let (user_id,): (i64,) = sqlx::query_as(&pool.sql("SELECT id FROM users"))
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+
+        let mut volumes = Vec::new();
+        for (title, ser_no) in [("Dune 1", 1), ("Dune 2", 2), ("Dune 3", 3)] {
+            let book_id = insert_test_book(&pool, catalog_id, title).await;
+            set_book_series(&pool, book_id, "Dune", ser_no)
+                .await
+                .unwrap();
+            volumes.push(book_id);
+        }
+        let next = next_in_series(&pool, volumes[0]).await.unwrap().unwrap();
+        assert_eq!(next.id, volumes[1]);
+        assert!(next_in_series(&pool, volumes[2]).await.unwrap().is_none());
+
+        let save = |book_id, progress| {
+            crate::db::queries::reading_positions::save_position(
+                &pool, user_id, book_id, "", progress, 100,
+            )
+        };
+        // Started but not finished: nothing to suggest yet.
+        save(volumes[0], 0.5).await.unwrap();
+        assert!(
+            next_unread_for_user(&pool, user_id, 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        save(volumes[0], 1.0).await.unwrap();
+        let next = next_unread_for_user(&pool, user_id, 10, 0).await.unwrap();
+        assert_eq!(next.iter().map(|b| b.id).collect::<Vec<_>>(), [volumes[1]]);
+        save(volumes[1], 0.99).await.unwrap();
+        let next = next_unread_for_user(&pool, user_id, 10, 0).await.unwrap();
+        assert_eq!(next.iter().map(|b| b.id).collect::<Vec<_>>(), [volumes[2]]);
+        save(volumes[2], 1.0).await.unwrap();
+        assert!(
+            next_unread_for_user(&pool, user_id, 10, 0)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
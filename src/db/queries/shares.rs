@@ -0,0 +1,219 @@
+//! Book share links in the `book_shares` table and guest bookshelf links in
+//! the `shelf_shares` table. Times are Unix seconds.
+
+use crate::db::DbPool;
+use crate::db::models::{BookShare, ShelfShare};
+
+/// Record a share link of `book_id` created by `user_id`.
+pub async fn create(
+    pool: &DbPool,
+    nonce: &str,
+    book_id: i64,
+    user_id: i64,
+    expires_at: i64,
+) -> Result<(), sqlx::Error> {
+    let sql = pool
+        .sql("INSERT INTO book_shares (nonce, book_id, user_id, expires_at) VALUES (?, ?, ?, ?)");
+    sqlx::query(&sql)
+        .bind(nonce)
+        .bind(book_id)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Whether the link with `nonce` for `book_id` exists, is not revoked and has
+/// not expired.
+pub async fn is_active(
+    pool: &DbPool,
+    nonce: &str,
+    book_id: i64,
+    now: i64,
+) -> Result<bool, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT COUNT(*) FROM book_shares \
+         WHERE nonce = ? AND book_id = ? AND revoked = 0 AND expires_at >= ?",
+    );
+    let row: (i64,) = sqlx::query_as(&sql)
+        .bind(nonce)
+        .bind(book_id)
+        .bind(now)
+        .fetch_one(pool.inner())
+        .await?;
+    Ok(row.0 > 0)
+}
+
+/// Unexpired, unrevoked links, newest first.
+pub async fn list_active(pool: &DbPool, now: i64) -> Result<Vec<BookShare>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT s.id, s.book_id, b.title, u.username, s.expires_at, s.created_at \
+         FROM book_shares s \
+         JOIN books b ON b.id = s.book_id \
+         JOIN users u ON u.id = s.user_id \
+         WHERE s.revoked = 0 AND s.expires_at >= ? \
+         ORDER BY s.id DESC",
+    );
+    sqlx::query_as::<_, BookShare>(&sql)
+        .bind(now)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Revoke a link; it stops working at once.
+pub async fn revoke(pool: &DbPool, id: i64) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE book_shares SET revoked = 1 WHERE id = ?");
+    sqlx::query(&sql).bind(id).execute(pool.inner()).await?;
+    Ok(())
+}
+
+/// Drop links that expired before `now`; they can never be used again.
+pub async fn delete_expired(pool: &DbPool, now: i64) -> Result<u64, sqlx::Error> {
+    let sql = pool.sql("DELETE FROM book_shares WHERE expires_at < ?");
+    let result = sqlx::query(&sql).bind(now).execute(pool.inner()).await?;
+    Ok(result.rows_affected())
+}
+
+/// Record a guest link to the bookshelf of `user_id`.
+pub async fn create_shelf(
+    pool: &DbPool,
+    nonce: &str,
+    user_id: i64,
+    expires_at: i64,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("INSERT INTO shelf_shares (nonce, user_id, expires_at) VALUES (?, ?, ?)");
+    sqlx::query(&sql)
+        .bind(nonce)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Whether the shelf link with `nonce` for `user_id` exists, is not revoked
+/// and has not expired.
+pub async fn is_shelf_active(
+    pool: &DbPool,
+    nonce: &str,
+    user_id: i64,
+    now: i64,
+) -> Result<bool, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT COUNT(*) FROM shelf_shares \
+         WHERE nonce = ? AND user_id = ? AND revoked = 0 AND expires_at >= ?",
+    );
+    let row: (i64,) = sqlx::query_as(&sql)
+        .bind(nonce)
+        .bind(user_id)
+        .bind(now)
+        .fetch_one(pool.inner())
+        .await?;
+    Ok(row.0 > 0)
+}
+
+/// Unexpired, unrevoked shelf links, newest first.
+pub async fn list_active_shelves(pool: &DbPool, now: i64) -> Result<Vec<ShelfShare>, sqlx::Error> {
+    let sql = pool.sql(
+        "SELECT s.id, s.user_id, u.username, s.expires_at, s.created_at \
+         FROM shelf_shares s \
+         JOIN users u ON u.id = s.user_id \
+         WHERE s.revoked = 0 AND s.expires_at >= ? \
+         ORDER BY s.id DESC",
+    );
+    sqlx::query_as::<_, ShelfShare>(&sql)
+        .bind(now)
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Revoke a shelf link; it stops working at once.
+pub async fn revoke_shelf(pool: &DbPool, id: i64) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE shelf_shares SET revoked = 1 WHERE id = ?");
+    sqlx::query(&sql).bind(id).execute(pool.inner()).await?;
+    Ok(())
+}
+
+/// Drop shelf links that expired before `now`.
+pub async fn delete_expired_shelves(pool: &DbPool, now: i64) -> Result<u64, sqlx::Error> {
+    let sql = pool.sql("DELETE FROM shelf_shares WHERE expires_at < ?");
+    let result = sqlx::query(&sql).bind(now).execute(pool.inner()).await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+
+    async fn insert_book(pool: &DbPool) -> i64 {
+        let sql = pool.sql("INSERT INTO catalogs (path, cat_name) VALUES ('/shares', 'shares')");
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+        let sql = pool.sql(
+            "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+             lang, lang_code, size, avail, cat_type, cover, cover_type) \
+             SELECT id, 'shared.fb2', '/shares', 'fb2', 'Shared', 'SHARED', 'en', 2, 100, 2, 0, 0, '' \
+             FROM catalogs WHERE path = '/shares'",
+        );
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+        let sql = pool.sql("SELECT id FROM books WHERE filename = 'shared.fb2'");
+        let row: (i64,) = sqlx::query_as(&sql).fetch_one(pool.inner()).await.unwrap();
+        row.0
+    }
+
+    async fn insert_user(pool: &DbPool) -> i64 {
+        crate::db::queries::users::create(pool, "sharer", "hash", 0, "")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_share_lifecycle() {
+        let pool = create_test_pool().await;
+        let book_id = insert_book(&pool).await;
+        let user_id = insert_user(&pool).await;
+
+        create(&pool, "n1", book_id, user_id, 200).await.unwrap();
+        create(&pool, "n2", book_id, user_id, 50).await.unwrap();
+        assert!(is_active(&pool, "n1", book_id, 100).await.unwrap());
+        assert!(!is_active(&pool, "n1", book_id + 1, 100).await.unwrap());
+        assert!(!is_active(&pool, "n2", book_id, 100).await.unwrap());
+        assert!(!is_active(&pool, "missing", book_id, 100).await.unwrap());
+
+        let active = list_active(&pool, 100).await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].title, "Shared");
+        assert_eq!(active[0].username, "sharer");
+
+        revoke(&pool, active[0].id).await.unwrap();
+        assert!(!is_active(&pool, "n1", book_id, 100).await.unwrap());
+        assert!(list_active(&pool, 100).await.unwrap().is_empty());
+
+        assert_eq!(delete_expired(&pool, 100).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shelf_share_lifecycle() {
+        let pool = create_test_pool().await;
+        let user_id = insert_user(&pool).await;
+
+        create_shelf(&pool, "s1", user_id, 200).await.unwrap();
+        create_shelf(&pool, "s2", user_id, 50).await.unwrap();
+        assert!(is_shelf_active(&pool, "s1", user_id, 100).await.unwrap());
+        assert!(
+            !is_shelf_active(&pool, "s1", user_id + 1, 100)
+                .await
+                .unwrap()
+        );
+        assert!(!is_shelf_active(&pool, "s2", user_id, 100).await.unwrap());
+
+        let active = list_active_shelves(&pool, 100).await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].username, "sharer");
+
+        revoke_shelf(&pool, active[0].id).await.unwrap();
+        assert!(!is_shelf_active(&pool, "s1", user_id, 100).await.unwrap());
+        assert_eq!(delete_expired_shelves(&pool, 100).await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,146 @@
+//! Type-ahead suggestions for the web search box.
+//!
+//! Each group is a key range `[PREFIX, PREFIX + U+10FFFF)` on an indexed
+//! search column, so lookups are index scans instead of `LIKE '%..%'` scans.
+
+use crate::db::{DbBackend, DbPool};
+
+/// Rows fetched per group before ranking.
+const CANDIDATE_FACTOR: i32 = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow, serde::Serialize)]
+pub struct Suggestion {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Distinct titles of available books starting with `prefix`.
+pub async fn titles(
+    pool: &DbPool,
+    prefix: &str,
+    limit: i32,
+) -> Result<Vec<Suggestion>, sqlx::Error> {
+    let range = range_condition(pool, "search_title");
+    let raw = format!(
+        "SELECT MIN(id) AS id, MIN(title) AS name FROM books \
+         WHERE avail > 0 AND {range} \
+         GROUP BY search_title ORDER BY search_title LIMIT ?"
+    );
+    fetch_ranked(pool, &raw, prefix, limit).await
+}
+
+/// Authors whose name starts with `prefix` (names are stored surname first).
+pub async fn authors(
+    pool: &DbPool,
+    prefix: &str,
+    limit: i32,
+) -> Result<Vec<Suggestion>, sqlx::Error> {
+    let range = range_condition(pool, "search_full_name");
+    let raw = format!(
+        "SELECT id, full_name AS name FROM authors WHERE {range} \
+         ORDER BY search_full_name LIMIT ?"
+    );
+    fetch_ranked(pool, &raw, prefix, limit).await
+}
+
+/// Series whose name starts with `prefix`.
+pub async fn series(
+    pool: &DbPool,
+    prefix: &str,
+    limit: i32,
+) -> Result<Vec<Suggestion>, sqlx::Error> {
+    let range = range_condition(pool, "search_ser");
+    let raw = format!(
+        "SELECT id, ser_name AS name FROM series WHERE {range} \
+         ORDER BY search_ser LIMIT ?"
+    );
+    fetch_ranked(pool, &raw, prefix, limit).await
+}
+
+/// `column` within the two bounds of [`prefix_range`]. PostgreSQL compares
+/// bytewise (`COLLATE "C"`, backed by the matching indexes) because locale
+/// collations would put the upper bound in the wrong place.
+fn range_condition(pool: &DbPool, column: &str) -> String {
+    match pool.backend() {
+        DbBackend::Postgres => {
+            format!("{column} COLLATE \"C\" >= ? AND {column} COLLATE \"C\" < ?")
+        }
+        _ => format!("{column} >= ? AND {column} < ?"),
+    }
+}
+
+/// Key range covering every uppercase search key that starts with `prefix`.
+fn prefix_range(prefix: &str) -> (String, String) {
+    let lower = prefix.to_uppercase();
+    let upper = format!("{lower}{}", char::MAX);
+    (lower, upper)
+}
+
+async fn fetch_ranked(
+    pool: &DbPool,
+    raw: &str,
+    prefix: &str,
+    limit: i32,
+) -> Result<Vec<Suggestion>, sqlx::Error> {
+    let (lower, upper) = prefix_range(prefix);
+    let sql = pool.sql(raw);
+    let candidates = sqlx::query_as::<_, Suggestion>(&sql)
+        .bind(&lower)
+        .bind(&upper)
+        .bind(limit * CANDIDATE_FACTOR)
+        .fetch_all(pool.inner())
+        .await?;
+    Ok(rank(&lower, candidates, limit))
+}
+
+/// Exact matches first, then shorter names; ties keep their alphabetical order.
+fn rank(prefix: &str, mut items: Vec<Suggestion>, limit: i32) -> Vec<Suggestion> {
+    items.sort_by_key(|s| {
+        let exact = s.name.to_uppercase() == prefix;
+        (!exact, s.name.chars().count())
+    });
+    items.truncate(limit.max(0) as usize);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+    use crate::db::queries::{authors as author_queries, series as series_queries};
+
+    fn names(items: &[Suggestion]) -> Vec<&str> {
+        items.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_prefix_range() {
+        let (lower, upper) = prefix_range("tol");
+        assert_eq!(lower, "TOL");
+        assert!(upper.as_str() > "TOLZZZ" && upper.as_str() > "TOLЯ");
+        assert!(upper.as_str() < "TOM");
+    }
+
+    #[tokio::test]
+    async fn test_suggestions_by_prefix() {
+        let pool = create_test_pool().await;
+        for (name, key) in [
+            ("Tolstoy Lev", "TOLSTOY LEV"),
+            ("Tol", "TOL"),
+            ("Tolkien John", "TOLKIEN JOHN"),
+            ("Atol Bob", "ATOL BOB"),
+        ] {
+            author_queries::insert(&pool, name, key, 2).await.unwrap();
+        }
+        let found = authors(&pool, "tol", 10).await.unwrap();
+        assert_eq!(names(&found), ["Tol", "Tolstoy Lev", "Tolkien John"]);
+        assert_eq!(authors(&pool, "tol", 2).await.unwrap().len(), 2);
+
+        series_queries::insert(&pool, "Tales", "TALES", 2)
+            .await
+            .unwrap();
+        assert_eq!(names(&series(&pool, "TA", 5).await.unwrap()), ["Tales"]);
+        assert!(series(&pool, "TB", 5).await.unwrap().is_empty());
+        assert!(titles(&pool, "TA", 5).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+//! Per-user monthly upload quotas: the limits admins set on the `users`
+//! table and the published uploads counted in `upload_usage`.
+
+use crate::db::DbPool;
+
+/// A user's upload limits and what they published this month. A limit of
+/// `0` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct UploadQuota {
+    pub max_files: i64,
+    pub max_mb: i64,
+    pub used_files: i64,
+    pub used_bytes: i64,
+}
+
+impl UploadQuota {
+    pub fn is_limited(&self) -> bool {
+        self.max_files > 0 || self.max_mb > 0
+    }
+
+    /// Whether `files` more books of `bytes` bytes in total fit this month.
+    pub fn allows(&self, files: i64, bytes: i64) -> bool {
+        let files_ok = self.max_files <= 0 || self.used_files + files <= self.max_files;
+        let bytes_ok = self.max_mb <= 0 || self.used_bytes + bytes <= self.max_mb * 1024 * 1024;
+        files_ok && bytes_ok
+    }
+}
+
+/// Usage period of `now`: the calendar month, `YYYY-MM` in UTC.
+pub fn current_period() -> String {
+    chrono::Utc::now().format("%Y-%m").to_string()
+}
+
+/// Limits and current-month usage of `user_id`; unknown users get no limits.
+pub async fn get(pool: &DbPool, user_id: i64) -> Result<UploadQuota, sqlx::Error> {
+    let sql = pool.sql("SELECT upload_quota_files, upload_quota_mb FROM users WHERE id = ?");
+    let limits: Option<(i32, i32)> = sqlx::query_as(&sql)
+        .bind(user_id)
+        .fetch_optional(pool.inner())
+        .await?;
+    let Some((max_files, max_mb)) = limits else {
+        return Ok(UploadQuota::default());
+    };
+
+    let sql = pool.sql("SELECT files, bytes FROM upload_usage WHERE user_id = ? AND period = ?");
+    let (used_files, used_bytes): (i64, i64) = sqlx::query_as(&sql)
+        .bind(user_id)
+        .bind(current_period())
+        .fetch_optional(pool.inner())
+        .await?
+        .unwrap_or_default();
+
+    Ok(UploadQuota {
+        max_files: max_files.into(),
+        max_mb: max_mb.into(),
+        used_files,
+        used_bytes,
+    })
+}
+
+/// Set the monthly limits of `user_id` (`0` = unlimited).
+pub async fn set_limits(
+    pool: &DbPool,
+    user_id: i64,
+    max_files: i32,
+    max_mb: i32,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE users SET upload_quota_files = ?, upload_quota_mb = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(max_files)
+        .bind(max_mb)
+        .bind(user_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Count `files` published books of `bytes` bytes against this month.
+pub async fn record(
+    pool: &DbPool,
+    user_id: i64,
+    files: i64,
+    bytes: i64,
+) -> Result<(), sqlx::Error> {
+    let raw = match pool.backend() {
+        crate::db::DbBackend::Mysql => {
+            "INSERT INTO upload_usage (user_id, period, files, bytes) VALUES (?, ?, ?, ?) \
+             ON DUPLICATE KEY UPDATE files = files + VALUES(files), bytes = bytes + VALUES(bytes)"
+        }
+        _ => {
+            "INSERT INTO upload_usage (user_id, period, files, bytes) VALUES (?, ?, ?, ?) \
+             ON CONFLICT(user_id, period) DO UPDATE SET \
+             files = upload_usage.files + excluded.files, \
+             bytes = upload_usage.bytes + excluded.bytes"
+        }
+    };
+    let sql = pool.sql(raw);
+    sqlx::query(&sql)
+        .bind(user_id)
+        .bind(current_period())
+        .bind(files)
+        .bind(bytes)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Reset the usage counters of `user_id`.
+pub async fn reset(pool: &DbPool, user_id: i64) -> Result<u64, sqlx::Error> {
+    let sql = pool.sql("DELETE FROM upload_usage WHERE user_id = ?");
+    let result = sqlx::query(&sql)
+        .bind(user_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+    use crate::db::queries::users;
+
+    #[test]
+    fn test_quota_allows() {
+        let unlimited = UploadQuota::default();
+        assert!(!unlimited.is_limited());
+        assert!(unlimited.allows(1000, i64::MAX / 2));
+
+        let quota = UploadQuota {
+            max_files: 3,
+            max_mb: 1,
+            used_files: 2,
+            used_bytes: 1024 * 1024 - 10,
+        };
+        assert!(quota.is_limited());
+        assert!(quota.allows(1, 10));
+        assert!(!quota.allows(1, 11));
+        assert!(!quota.allows(2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_record_and_reset_usage() {
+        let pool = create_test_pool().await;
+        let alice = users::create(&pool, "alice", "h", 0, "").await.unwrap();
+        let bob = users::create(&pool, "bob", "h", 0, "").await.unwrap();
+
+        assert_eq!(get(&pool, alice).await.unwrap(), UploadQuota::default());
+
+        set_limits(&pool, alice, 5, 10).await.unwrap();
+        record(&pool, alice, 1, 1000).await.unwrap();
+        record(&pool, alice, 2, 500).await.unwrap();
+        record(&pool, bob, 1, 42).await.unwrap();
+
+        let quota = get(&pool, alice).await.unwrap();
+        assert_eq!(quota.max_files, 5);
+        assert_eq!(quota.max_mb, 10);
+        assert_eq!(quota.used_files, 3);
+        assert_eq!(quota.used_bytes, 1500);
+
+        assert_eq!(reset(&pool, alice).await.unwrap(), 1);
+        let quota = get(&pool, alice).await.unwrap();
+        assert_eq!((quota.used_files, quota.used_bytes), (0, 0));
+        assert_eq!(quota.max_files, 5);
+        assert_eq!(get(&pool, bob).await.unwrap().used_files, 1);
+    }
+}
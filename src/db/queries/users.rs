@@ -14,26 +14,39 @@ pub struct UserView {
     pub password_change_required: i32,
     pub display_name: String,
     pub allow_upload: i32,
+    pub is_active: i32,
     pub is_oauth: i32,
+    /// Monthly upload limits (`0` = unlimited) and this month's usage.
+    pub upload_quota_files: i32,
+    pub upload_quota_mb: i32,
+    pub upload_used_files: i64,
+    pub upload_used_bytes: i64,
 }
 
 /// Get all users for admin panel listing (excludes password_hash).
 pub async fn get_all_views(pool: &DbPool) -> Result<Vec<UserView>, sqlx::Error> {
     let sql = pool.sql(
         "SELECT u.id, u.username, u.is_superuser, u.created_at, u.last_login, \
-         u.password_change_required, u.display_name, u.allow_upload, \
+         u.password_change_required, u.display_name, u.allow_upload, u.is_active, \
          CASE WHEN EXISTS (SELECT 1 FROM oauth_identities WHERE user_id = u.id AND status = 'active') \
-         THEN 1 ELSE 0 END AS is_oauth \
-         FROM users u ORDER BY u.id"
+         THEN 1 ELSE 0 END AS is_oauth, \
+         u.upload_quota_files, u.upload_quota_mb, \
+         COALESCE(q.files, 0) AS upload_used_files, COALESCE(q.bytes, 0) AS upload_used_bytes \
+         FROM users u \
+         LEFT JOIN upload_usage q ON q.user_id = u.id AND q.period = ? \
+         ORDER BY u.id"
     );
-    let users: Vec<UserView> = sqlx::query_as(&sql).fetch_all(pool.inner()).await?;
+    let users: Vec<UserView> = sqlx::query_as(&sql)
+        .bind(crate::db::queries::upload_quota::current_period())
+        .fetch_all(pool.inner())
+        .await?;
     Ok(users)
 }
 
 /// Get a single user by ID.
 pub async fn get_by_id(pool: &DbPool, user_id: i64) -> Result<Option<User>, sqlx::Error> {
     let sql = pool.sql(
-        "SELECT id, username, password_hash, is_superuser, created_at, last_login, password_change_required, display_name, allow_upload FROM users WHERE id = ?"
+        "SELECT id, username, password_hash, is_superuser, created_at, last_login, password_change_required, display_name, allow_upload, theme, auto_bookshelf FROM users WHERE id = ?"
     );
     let user: Option<User> = sqlx::query_as(&sql)
         .bind(user_id)
@@ -42,9 +55,9 @@ pub async fn get_by_id(pool: &DbPool, user_id: i64) -> Result<Option<User>, sqlx
     Ok(user)
 }
 
-/// Get `is_superuser` flag for a given user ID.
+/// Get `is_superuser` flag for a given user ID; `false` for disabled accounts.
 pub async fn is_superuser(pool: &DbPool, user_id: i64) -> Result<bool, sqlx::Error> {
-    let sql = pool.sql("SELECT is_superuser FROM users WHERE id = ?");
+    let sql = pool.sql("SELECT is_superuser FROM users WHERE id = ? AND is_active = 1");
     let row: Option<(i32,)> = sqlx::query_as(&sql)
         .bind(user_id)
         .fetch_optional(pool.inner())
@@ -52,6 +65,27 @@ pub async fn is_superuser(pool: &DbPool, user_id: i64) -> Result<bool, sqlx::Err
     Ok(row.map(|(v,)| v == 1).unwrap_or(false))
 }
 
+/// Whether the account exists and has not been disabled by an admin.
+pub async fn is_active(pool: &DbPool, user_id: i64) -> Result<bool, sqlx::Error> {
+    let sql = pool.sql("SELECT is_active FROM users WHERE id = ?");
+    let row: Option<(i32,)> = sqlx::query_as(&sql)
+        .bind(user_id)
+        .fetch_optional(pool.inner())
+        .await?;
+    Ok(row.is_some_and(|(v,)| v == 1))
+}
+
+/// Enable or disable an account.
+pub async fn set_active(pool: &DbPool, user_id: i64, active: bool) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE users SET is_active = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(active as i32)
+        .bind(user_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
 /// Create a new user. Returns the new user's ID.
 pub async fn create(
     pool: &DbPool,
@@ -133,6 +167,68 @@ pub async fn update_display_name(
     Ok(())
 }
 
+/// Update a user's web theme preference (`light`, `dark` or `auto`).
+pub async fn update_theme(pool: &DbPool, user_id: i64, theme: &str) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE users SET theme = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(theme)
+        .bind(user_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Set whether downloads put books on the user's bookshelf.
+pub async fn update_auto_bookshelf(
+    pool: &DbPool,
+    user_id: i64,
+    enabled: bool,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE users SET auto_bookshelf = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(enabled as i32)
+        .bind(user_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// Whether downloads put books on the user's bookshelf (the user's own
+/// setting; `opds.auto_bookshelf` is checked by the caller).
+pub async fn auto_bookshelf(pool: &DbPool, user_id: i64) -> Result<bool, sqlx::Error> {
+    let sql = pool.sql("SELECT auto_bookshelf FROM users WHERE id = ?");
+    let row: Option<(i32,)> = sqlx::query_as(&sql)
+        .bind(user_id)
+        .fetch_optional(pool.inner())
+        .await?;
+    Ok(row.is_some_and(|(v,)| v != 0))
+}
+
+/// Set the user's OPDS client profile (empty: detect from the User-Agent).
+pub async fn update_opds_client(
+    pool: &DbPool,
+    user_id: i64,
+    profile: &str,
+) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE users SET opds_client = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(profile)
+        .bind(user_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
+/// The user's OPDS client profile setting. Returns empty string if not found.
+pub async fn opds_client(pool: &DbPool, user_id: i64) -> Result<String, sqlx::Error> {
+    let sql = pool.sql("SELECT opds_client FROM users WHERE id = ?");
+    let row: Option<(String,)> = sqlx::query_as(&sql)
+        .bind(user_id)
+        .fetch_optional(pool.inner())
+        .await?;
+    Ok(row.map(|(v,)| v).unwrap_or_default())
+}
+
 /// Get display name for a user. Returns empty string if not found.
 pub async fn get_username(pool: &DbPool, user_id: i64) -> Result<String, sqlx::Error> {
     let sql = pool.sql("SELECT username FROM users WHERE id = ?");
@@ -242,6 +338,23 @@ pub async fn password_change_required(pool: &DbPool, user_id: i64) -> Result<boo
     Ok(row.map(|(v,)| v == 1).unwrap_or(false))
 }
 
+/// Id, username and password hash of every user, for auditing stored hashes.
+pub async fn password_hashes(pool: &DbPool) -> Result<Vec<(i64, String, String)>, sqlx::Error> {
+    sqlx::query_as("SELECT id, username, password_hash FROM users ORDER BY id")
+        .fetch_all(pool.inner())
+        .await
+}
+
+/// Make a user set a new password at the next login.
+pub async fn require_password_change(pool: &DbPool, user_id: i64) -> Result<(), sqlx::Error> {
+    let sql = pool.sql("UPDATE users SET password_change_required = 1 WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(user_id)
+        .execute(pool.inner())
+        .await?;
+    Ok(())
+}
+
 /// Clear the password_change_required flag after user changes password.
 pub async fn clear_password_change_required(
     pool: &DbPool,
@@ -309,6 +422,19 @@ mod tests {
         assert_eq!(user.password_hash, "new_hash");
     }
 
+    #[tokio::test]
+    async fn test_disabled_user_loses_superuser() {
+        let pool = create_test_pool().await;
+        let id = create(&pool, "dave", "hash", 1, "").await.unwrap();
+        assert!(is_active(&pool, id).await.unwrap());
+        assert!(is_superuser(&pool, id).await.unwrap());
+
+        set_active(&pool, id, false).await.unwrap();
+        assert!(!is_active(&pool, id).await.unwrap());
+        assert!(!is_superuser(&pool, id).await.unwrap());
+        assert!(!is_active(&pool, id + 100).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_update_password_hash_verify() {
         let pool = create_test_pool().await;
@@ -390,6 +516,45 @@ mod tests {
         assert_eq!(user.display_name, "J. Doe");
     }
 
+    #[tokio::test]
+    async fn test_update_theme() {
+        let pool = create_test_pool().await;
+        let id = create(&pool, "themed", "hash", 0, "").await.unwrap();
+
+        let user = get_by_id(&pool, id).await.unwrap().unwrap();
+        assert_eq!(user.theme, "");
+
+        update_theme(&pool, id, "dark").await.unwrap();
+        let user = get_by_id(&pool, id).await.unwrap().unwrap();
+        assert_eq!(user.theme, "dark");
+    }
+
+    #[tokio::test]
+    async fn test_update_auto_bookshelf() {
+        let pool = create_test_pool().await;
+        let id = create(&pool, "shelver", "hash", 0, "").await.unwrap();
+
+        assert!(auto_bookshelf(&pool, id).await.unwrap());
+        update_auto_bookshelf(&pool, id, false).await.unwrap();
+        assert!(!auto_bookshelf(&pool, id).await.unwrap());
+        assert_eq!(
+            get_by_id(&pool, id).await.unwrap().unwrap().auto_bookshelf,
+            0
+        );
+        assert!(!auto_bookshelf(&pool, 99999).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_update_opds_client() {
+        let pool = create_test_pool().await;
+        let id = create(&pool, "reader", "hash", 0, "").await.unwrap();
+
+        assert_eq!(opds_client(&pool, id).await.unwrap(), "");
+        update_opds_client(&pool, id, "pocketbook").await.unwrap();
+        assert_eq!(opds_client(&pool, id).await.unwrap(), "pocketbook");
+        assert_eq!(opds_client(&pool, 99999).await.unwrap(), "");
+    }
+
     #[tokio::test]
     async fn test_display_name_default_empty() {
         let pool = create_test_pool().await;
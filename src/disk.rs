@@ -0,0 +1,130 @@
+//! Free disk space of the library, covers and upload directories, and the
+//! `server.min_free_space_mb` guardrail that refuses writes before a volume
+//! runs full instead of failing half-way through them.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::Config;
+
+/// Free and total bytes of the volume holding a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskSpace {
+    /// Bytes available to unprivileged writers.
+    pub free_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Space of the volume holding `path`, or of its nearest existing parent
+/// when `path` is not created yet. `None` when it cannot be determined
+/// (remote roots, unsupported platforms).
+pub fn space(path: &Path) -> Option<DiskSpace> {
+    if crate::vfs::is_remote(path) {
+        return None;
+    }
+    let existing = path
+        .ancestors()
+        .find(|p| !p.as_os_str().is_empty() && p.exists())
+        .unwrap_or(Path::new("."));
+    statvfs(existing)
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Option<DiskSpace> {
+    let stat = rustix::fs::statvfs(path).ok()?;
+    Some(DiskSpace {
+        free_bytes: stat.f_bavail.saturating_mul(stat.f_frsize),
+        total_bytes: stat.f_blocks.saturating_mul(stat.f_frsize),
+    })
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &Path) -> Option<DiskSpace> {
+    None
+}
+
+/// Whether writing `needed_bytes` under `path` leaves at least
+/// `min_free_mb` megabytes free. Always true when the space is unknown or
+/// the guardrail is disabled (`0`).
+pub fn has_room(path: &Path, needed_bytes: u64, min_free_mb: u64) -> bool {
+    if min_free_mb == 0 {
+        return true;
+    }
+    match space(path) {
+        Some(space) => space.free_bytes.saturating_sub(needed_bytes) >= mb_to_bytes(min_free_mb),
+        None => true,
+    }
+}
+
+fn mb_to_bytes(mb: u64) -> u64 {
+    mb.saturating_mul(1024 * 1024)
+}
+
+/// Free space of one of the directories the server writes to.
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeReport {
+    /// `library`, `covers` or `upload`.
+    pub name: &'static str,
+    pub path: String,
+    pub free_bytes: Option<u64>,
+    pub total_bytes: Option<u64>,
+    /// Free space is below `server.min_free_space_mb`.
+    pub low: bool,
+}
+
+/// Free space of the library root, the covers directory and, when uploads
+/// are enabled, the upload directory.
+pub fn report(config: &Config) -> Vec<VolumeReport> {
+    let mut volumes = vec![
+        ("library", config.library.root_path.as_path()),
+        ("covers", config.covers.covers_path.as_path()),
+    ];
+    if config.upload.allow_upload && !config.upload.upload_path.as_os_str().is_empty() {
+        volumes.push(("upload", config.upload.upload_path.as_path()));
+    }
+
+    let min_free = mb_to_bytes(config.server.min_free_space_mb);
+    volumes
+        .into_iter()
+        .map(|(name, path)| {
+            let space = space(path);
+            VolumeReport {
+                name,
+                path: crate::vfs::display_root(path),
+                free_bytes: space.map(|s| s.free_bytes),
+                total_bytes: space.map(|s| s.total_bytes),
+                low: space.is_some_and(|s| s.free_bytes < min_free),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_of_missing_path_uses_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("not").join("yet");
+        if cfg!(unix) {
+            let parent = space(dir.path()).unwrap();
+            let nested = space(&missing).unwrap();
+            assert!(nested.total_bytes >= nested.free_bytes);
+            assert_eq!(nested.total_bytes, parent.total_bytes);
+        }
+        assert!(space(Path::new("webdav://host/books")).is_none());
+    }
+
+    #[test]
+    fn test_has_room_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(has_room(dir.path(), u64::MAX, 0));
+        assert!(has_room(dir.path(), 0, 1));
+        if cfg!(unix) {
+            assert!(!has_room(dir.path(), 0, u64::MAX / 2));
+            assert!(!has_room(dir.path(), u64::MAX, 1));
+        }
+    }
+}
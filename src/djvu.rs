@@ -1,17 +1,13 @@
-use std::io::Cursor;
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
-use std::process::{Command, Stdio};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::config::CoverImageConfig;
+use crate::tools::{self, ToolError};
 
 pub fn ddjvu_available() -> bool {
-    Command::new("ddjvu")
-        .arg("-h")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .is_ok()
+    tools::is_available(&tools::settings().ddjvu_path)
 }
 
 pub fn render_first_page_jpeg_from_path(
@@ -36,14 +32,14 @@ pub fn render_first_page_jpeg_from_bytes(
     let input_djvu = temp_dir.join("input.djvu");
     std::fs::write(&input_djvu, djvu_data).map_err(DjvuRenderError::WriteInput)?;
 
-    let output = Command::new("ddjvu")
-        .arg("-page=1")
-        .arg(format!("-size={scale_to}x{scale_to}"))
-        .arg("-format=ppm")
-        .arg(&input_djvu)
-        .arg("-")
-        .output()
-        .map_err(DjvuRenderError::Spawn)?;
+    let output = tools::run(
+        Command::new(&tools::settings().ddjvu_path)
+            .arg("-page=1")
+            .arg(format!("-size={scale_to}x{scale_to}"))
+            .arg("-format=ppm")
+            .arg(&input_djvu)
+            .arg("-"),
+    )?;
 
     if !output.status.success() {
         return Err(DjvuRenderError::ExitStatus(output.status.code()));
@@ -61,6 +57,41 @@ pub fn render_first_page_jpeg_from_bytes(
     Ok(jpeg.into_inner())
 }
 
+/// Number of pages of a DjVu document, read from its chunk headers: one for
+/// a single-page `DJVU` form, else the `DJVU` page forms bundled in a `DJVM`
+/// form. `None` for other data and for indirect documents (pages in
+/// separate files).
+pub fn page_count(mut reader: impl Read + Seek) -> Option<u32> {
+    let mut header = [0u8; 16];
+    reader.read_exact(&mut header).ok()?;
+    if &header[..8] != b"AT&TFORM" {
+        return None;
+    }
+    match &header[12..16] {
+        b"DJVU" => return Some(1),
+        b"DJVM" => {}
+        _ => return None,
+    }
+    // The DJVM form starts after its 4-byte type, 12 bytes into the file.
+    let end = 12 + u64::from(u32::from_be_bytes(header[8..12].try_into().ok()?));
+    let mut pos = 16;
+    let mut pages = 0;
+    while pos + 12 <= end {
+        let mut chunk = [0u8; 12];
+        reader.seek(SeekFrom::Start(pos)).ok()?;
+        if reader.read_exact(&mut chunk).is_err() {
+            break;
+        }
+        if &chunk[..4] == b"FORM" && &chunk[8..12] == b"DJVU" {
+            pages += 1;
+        }
+        let len = u64::from(u32::from_be_bytes(chunk[4..8].try_into().ok()?));
+        // Chunks are padded to an even length.
+        pos += 8 + len + (len & 1);
+    }
+    (pages > 0).then_some(pages)
+}
+
 fn temp_work_dir() -> std::path::PathBuf {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -89,6 +120,8 @@ pub enum DjvuRenderError {
     WriteInput(std::io::Error),
     #[error("failed to start ddjvu: {0}")]
     Spawn(std::io::Error),
+    #[error("ddjvu timed out after {}s", .0.as_secs())]
+    TimedOut(Duration),
     #[error("ddjvu exited with status {0:?}")]
     ExitStatus(Option<i32>),
     #[error("failed to decode ddjvu output: {0}")]
@@ -97,6 +130,15 @@ pub enum DjvuRenderError {
     EncodeJpeg(image::ImageError),
 }
 
+impl From<ToolError> for DjvuRenderError {
+    fn from(e: ToolError) -> Self {
+        match e {
+            ToolError::Spawn(e) | ToolError::Wait(e) => Self::Spawn(e),
+            ToolError::TimedOut(limit) => Self::TimedOut(limit),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,6 +166,34 @@ mod tests {
         assert!(matches!(err, DjvuRenderError::ReadInput(_)));
     }
 
+    fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    #[test]
+    fn test_page_count() {
+        let single = std::fs::read("tests/data/test_book.djvu").unwrap();
+        assert_eq!(page_count(Cursor::new(&single)), Some(1));
+
+        let mut forms = b"DJVM".to_vec();
+        forms.extend(chunk(b"DIRM", b"odd"));
+        forms.extend(chunk(b"FORM", b"DJVIshared"));
+        forms.extend(chunk(b"FORM", b"DJVUpage1"));
+        forms.extend(chunk(b"FORM", b"DJVUpage2"));
+        forms.extend(chunk(b"FORM", b"THUMthumbs"));
+        let mut bundled = b"AT&T".to_vec();
+        bundled.extend(chunk(b"FORM", &forms));
+        assert_eq!(page_count(Cursor::new(&bundled)), Some(2));
+
+        assert_eq!(page_count(Cursor::new(b"not a djvu")), None);
+    }
+
     #[test]
     fn test_render_first_page_from_invalid_bytes_errors() {
         let err = render_first_page_jpeg_from_bytes(b"not a djvu", test_cover_cfg()).unwrap_err();
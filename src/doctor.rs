@@ -0,0 +1,396 @@
+//! `ropds doctor`: a self-check of the configuration and runtime environment
+//! that prints a pass/warn/fail report instead of stopping at the first error.
+
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+
+/// `SELECT 1` round trips timed for the latency check.
+const LATENCY_SAMPLES: u32 = 3;
+/// Average query latency above which the database check warns.
+const SLOW_QUERY: Duration = Duration::from_millis(100);
+/// The session secret shipped in `config.toml.example`.
+const EXAMPLE_SESSION_SECRET: &str = "change-me-to-a-random-string";
+/// Languages the web UI must have translations for.
+const REQUIRED_LOCALES: [&str; 2] = ["en", "ru"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        })
+    }
+}
+
+/// Outcome of one check.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+/// All check outcomes, in the order they ran.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    fn push(&mut self, name: &'static str, status: CheckStatus, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    fn pass(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Pass, detail);
+    }
+
+    fn warn(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Warn, detail);
+    }
+
+    fn fail(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.push(name, CheckStatus::Fail, detail);
+    }
+
+    fn count(&self, status: CheckStatus) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    /// True when no check failed (warnings are allowed).
+    pub fn passed(&self) -> bool {
+        self.count(CheckStatus::Fail) == 0
+    }
+
+    /// Plain-text report, one line per check plus a summary line.
+    pub fn render(&self) -> String {
+        let width = self.checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        let mut out = format!("ropds doctor v{}\n", env!("CARGO_PKG_VERSION"));
+        for check in &self.checks {
+            out.push_str(&format!(
+                "[{}] {:width$}  {}\n",
+                check.status, check.name, check.detail
+            ));
+        }
+        out.push_str(&format!(
+            "{} checks: {} passed, {} warnings, {} failed\n",
+            self.checks.len(),
+            self.count(CheckStatus::Pass),
+            self.count(CheckStatus::Warn),
+            self.count(CheckStatus::Fail),
+        ));
+        out
+    }
+}
+
+/// Run every check against the config file at `config_path`. Checks that
+/// need a loaded config are skipped when the file does not load.
+pub async fn run(config_path: &Path) -> Report {
+    let mut report = Report::default();
+    let config = match Config::load(config_path) {
+        Ok(config) => {
+            report.pass("config", format!("{} loaded", config_path.display()));
+            config
+        }
+        Err(e) => {
+            report.fail("config", e.to_string());
+            return report;
+        }
+    };
+    for warning in config_warnings(&config) {
+        report.warn("config", warning);
+    }
+
+    match crate::scheduler::validate_config(&config.scanner) {
+        Ok(()) => report.pass("scan schedule", "valid"),
+        Err(e) => report.fail("scan schedule", e),
+    }
+
+    check_library(&mut report, &config).await;
+    if config.server.read_only {
+        report.pass("covers path", "read-only mirror; write checks skipped");
+    } else {
+        check_writable(&mut report, "covers path", &config.covers.covers_path, true);
+    }
+    if config.upload.allow_upload {
+        if config.upload.upload_path.as_os_str().is_empty() {
+            report.fail("upload path", "upload enabled but upload_path is not set");
+        } else {
+            check_writable(&mut report, "upload path", &config.upload.upload_path, true);
+        }
+        check_writable(
+            &mut report,
+            "library write",
+            &config.library.root_path,
+            false,
+        );
+    }
+
+    check_database(&mut report, &config).await;
+    check_tools(&mut report, &config);
+    check_templates(&mut report);
+    check_locales(&mut report);
+    report
+}
+
+/// Settings that load fine but are probably not what the admin wants.
+pub fn config_warnings(config: &Config) -> Vec<String> {
+    let mut warnings = Vec::new();
+    match config.server.session_secret.as_str() {
+        "" => warnings.push(
+            "server.session_secret is empty; a random one is generated at startup and \
+             sessions end on every restart"
+                .to_string(),
+        ),
+        EXAMPLE_SESSION_SECRET => warnings
+            .push("server.session_secret is the example value; set a random string".to_string()),
+        _ => {}
+    }
+    if config.library.book_extensions.is_empty() {
+        warnings.push("library.book_extensions is empty; scans find no books".to_string());
+    }
+    let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if config.scanner.workers_num > cpus {
+        warnings.push(format!(
+            "scanner.workers_num = {} exceeds the {cpus} available CPUs",
+            config.scanner.workers_num
+        ));
+    }
+    if !config.opds.auth_required {
+        warnings.push("opds.auth_required = false; the OPDS catalog is public".to_string());
+    }
+    warnings
+}
+
+async fn check_library(report: &mut Report, config: &Config) {
+    let root = &config.library.root_path;
+    if crate::vfs::is_remote(root) {
+        let url = crate::vfs::display_root(root);
+        let probe = match crate::vfs::remote(root) {
+            Ok(Some(fs)) => fs.probe().await.map_err(|e| e.to_string()),
+            Ok(None) => return,
+            Err(e) => Err(e.to_string()),
+        };
+        match probe {
+            Ok(count) => report.pass("library", format!("{url} reachable ({count} entries)")),
+            Err(e) => report.fail("library", format!("{url}: {e}")),
+        }
+        return;
+    }
+    match std::fs::read_dir(root) {
+        Ok(entries) => {
+            let count = entries.count();
+            report.pass(
+                "library",
+                format!("{} readable ({count} entries)", root.display()),
+            );
+        }
+        Err(e) => report.fail("library", format!("{}: {e}", root.display())),
+    }
+}
+
+/// Check that `dir` accepts new files, creating it first when `create` is set.
+fn check_writable(report: &mut Report, name: &'static str, dir: &Path, create: bool) {
+    if create && let Err(e) = std::fs::create_dir_all(dir) {
+        report.fail(name, format!("cannot create {}: {e}", dir.display()));
+        return;
+    }
+    let probe = dir.join(".ropds_write_test");
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            report.pass(name, format!("{} writable", dir.display()));
+        }
+        Err(e) => report.fail(name, format!("{} is not writable: {e}", dir.display())),
+    }
+}
+
+async fn check_database(report: &mut Report, config: &Config) {
+    let url = crate::db::redact_database_url(&config.database.url).into_owned();
+    let pool = match crate::db::connect(&config.database).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            report.fail("database", format!("{url}: {e}"));
+            return;
+        }
+    };
+
+    let started = Instant::now();
+    for _ in 0..LATENCY_SAMPLES {
+        if let Err(e) = sqlx::query("SELECT 1").execute(pool.inner()).await {
+            report.fail("database", format!("{url}: {e}"));
+            return;
+        }
+    }
+    let latency = started.elapsed() / LATENCY_SAMPLES;
+    let detail = format!(
+        "{url} connected, {:.1} ms per query",
+        latency.as_secs_f64() * 1000.0
+    );
+    if latency > SLOW_QUERY {
+        report.warn("database", detail);
+    } else {
+        report.pass("database", detail);
+    }
+
+    match crate::db::migration_status(&pool).await {
+        // Older code could corrupt a schema it does not know.
+        Ok(status) if !status.unknown.is_empty() => report.fail(
+            "migrations",
+            format!(
+                "database is newer than this binary (unknown: {}); upgrade ropds",
+                status.unknown.join(", ")
+            ),
+        ),
+        Ok(status) if status.pending.is_empty() => report.pass(
+            "migrations",
+            format!("{} applied, none pending", status.applied),
+        ),
+        // A mirror does not migrate and refuses to start on an older schema.
+        Ok(status) if config.server.read_only => report.fail(
+            "migrations",
+            format!(
+                "{} pending on a read-only mirror; upgrade the primary first: {}",
+                status.pending.len(),
+                status.pending.join(", ")
+            ),
+        ),
+        // The server applies pending migrations when it starts, unless
+        // `auto_migrate` is off.
+        Ok(status) => report.warn(
+            "migrations",
+            format!(
+                "{} applied, {} pending ({}): {}",
+                status.applied,
+                status.pending.len(),
+                if config.database.auto_migrate {
+                    "applied at next start"
+                } else {
+                    "run `ropds migrate --up`"
+                },
+                status.pending.join(", ")
+            ),
+        ),
+        Err(e) => report.fail("migrations", e.to_string()),
+    }
+}
+
+fn check_tools(report: &mut Report, config: &Config) {
+    let tools = [
+        (
+            "pdftoppm",
+            &config.tools.pdftoppm_path,
+            "PDF covers fall back to embedded JPEG images",
+        ),
+        (
+            "pdfinfo",
+            &config.tools.pdfinfo_path,
+            "the built-in PDF metadata reader is used",
+        ),
+        (
+            "pdftotext",
+            &config.tools.pdftotext_path,
+            "PDFs get no text preview or full-text index",
+        ),
+        (
+            "ddjvu",
+            &config.tools.ddjvu_path,
+            "DJVU covers are disabled",
+        ),
+    ];
+    for (name, path, fallback) in tools {
+        if crate::tools::is_available(path) {
+            report.pass(name, format!("{} found", path.display()));
+        } else {
+            report.warn(name, format!("{} not found; {fallback}", path.display()));
+        }
+    }
+}
+
+fn check_templates(report: &mut Report) {
+    match crate::assets::load_templates() {
+        Ok(tera) => report.pass(
+            "templates",
+            format!("{} templates loaded", tera.get_template_names().count()),
+        ),
+        Err(e) => report.fail("templates", e.to_string()),
+    }
+}
+
+fn check_locales(report: &mut Report) {
+    match crate::web::i18n::load_runtime_translations() {
+        Ok(translations) => {
+            let missing: Vec<&str> = REQUIRED_LOCALES
+                .into_iter()
+                .filter(|lang| !translations.contains_key(*lang))
+                .collect();
+            if missing.is_empty() {
+                report.pass("locales", REQUIRED_LOCALES.join(", "));
+            } else {
+                report.fail("locales", format!("missing {}", missing.join(", ")));
+            }
+        }
+        Err(e) => report.fail("locales", e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_from(extra_server: &str, extra_scanner: &str) -> Config {
+        let toml_str = format!(
+            "[server]\nbase_url = \"http://127.0.0.1:8081\"\n{extra_server}\n[library]\nroot_path = \"/books\"\n[database]\n[opds]\n[scanner]\n{extra_scanner}\n"
+        );
+        toml::from_str(&toml_str).unwrap()
+    }
+
+    #[test]
+    fn test_config_warnings() {
+        let config = config_from("session_secret = \"s3cret\"", "");
+        assert!(config_warnings(&config).is_empty());
+
+        let config = config_from("", "workers_num = 100000");
+        let warnings = config_warnings(&config);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("session_secret is empty"));
+        assert!(warnings[1].contains("workers_num"));
+
+        let config = config_from(
+            &format!("session_secret = \"{EXAMPLE_SESSION_SECRET}\""),
+            "",
+        );
+        assert!(config_warnings(&config)[0].contains("example value"));
+    }
+
+    #[test]
+    fn test_report_render_and_verdict() {
+        let mut report = Report::default();
+        report.pass("config", "ok");
+        report.warn("pdftoppm", "not found");
+        assert!(report.passed());
+        report.fail("database", "refused");
+        assert!(!report.passed());
+
+        let text = report.render();
+        assert!(text.contains("[PASS] config    ok\n"));
+        assert!(text.contains("[WARN] pdftoppm  not found\n"));
+        assert!(text.contains("[FAIL] database  refused\n"));
+        assert!(text.ends_with("3 checks: 1 passed, 1 warnings, 1 failed\n"));
+    }
+}
@@ -0,0 +1,590 @@
+//! Synthetic libraries for benchmarks (`ropds gen-fixtures`, feature
+//! `fixtures`).
+//!
+//! A fixture is a directory of generated FB2 and EPUB files plus an INPX
+//! collection whose books sit in ZIP archives. Output depends only on the
+//! options, so a given seed always produces the same library.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// What to generate.
+#[derive(Debug, Clone)]
+pub struct FixtureOptions {
+    /// Total number of books.
+    pub books: usize,
+    /// Seed of the pseudo-random generator.
+    pub seed: u64,
+    /// Share of books written as EPUB files, in percent.
+    pub epub_percent: u8,
+    /// Share of books listed in the INPX index, in percent.
+    pub inpx_percent: u8,
+    /// Files per folder under `books/`.
+    pub books_per_folder: usize,
+}
+
+impl Default for FixtureOptions {
+    fn default() -> Self {
+        Self {
+            books: 10_000,
+            seed: 1,
+            epub_percent: 15,
+            inpx_percent: 25,
+            books_per_folder: 500,
+        }
+    }
+}
+
+/// Counts of what [`generate`] wrote.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FixtureSummary {
+    pub fb2: usize,
+    pub epub: usize,
+    /// Books listed in the INPX index.
+    pub inpx: usize,
+    /// ZIP archives holding the INPX books.
+    pub inpx_archives: usize,
+    pub bytes: u64,
+}
+
+/// Books per INPX archive (and `.inp` file).
+const INPX_ARCHIVE_BOOKS: usize = 1000;
+
+const GENRES: &[&str] = &[
+    "sf",
+    "sf_fantasy",
+    "sf_history",
+    "detective",
+    "det_classic",
+    "thriller",
+    "prose_classic",
+    "prose_contemporary",
+    "love_contemporary",
+    "adventure",
+    "child_tale",
+    "poetry",
+    "humor",
+    "sci_history",
+    "sci_phys",
+    "comp_programming",
+];
+
+const LATIN_WORDS: &[&str] = &[
+    "amber", "border", "candle", "distant", "echo", "falcon", "garden", "harbor", "island",
+    "journey", "kingdom", "lantern", "meadow", "night", "ocean", "paper", "quiet", "river",
+    "silver", "tower", "under", "valley", "winter", "yellow", "zephyr", "shadow", "storm", "glass",
+    "iron", "north", "stone", "secret",
+];
+
+const CYRILLIC_WORDS: &[&str] = &[
+    "берег",
+    "ветер",
+    "город",
+    "дорога",
+    "ель",
+    "жизнь",
+    "звезда",
+    "изба",
+    "книга",
+    "лес",
+    "море",
+    "ночь",
+    "озеро",
+    "поле",
+    "река",
+    "сад",
+    "тень",
+    "утро",
+    "хлеб",
+    "церковь",
+    "час",
+    "шаг",
+    "щит",
+    "эхо",
+    "юг",
+    "ясень",
+    "огонь",
+    "зима",
+    "весна",
+    "осень",
+    "туман",
+    "след",
+];
+
+const LATIN_FIRST: &[&str] = &[
+    "Anna", "Boris", "Clara", "David", "Elena", "Frank", "Grace", "Henry", "Irene", "James",
+    "Karen", "Louis", "Maria", "Nikolas", "Olga", "Peter",
+];
+
+const LATIN_LAST: &[&str] = &[
+    "Adams", "Baker", "Carter", "Dawson", "Ellis", "Fisher", "Garner", "Hughes", "Irving",
+    "Jensen", "Keller", "Lawson", "Miller", "Norton", "Oliver", "Parker", "Quinn", "Reed", "Stone",
+    "Turner", "Walker", "Young",
+];
+
+const CYRILLIC_FIRST: &[&str] = &[
+    "Анна",
+    "Борис",
+    "Вера",
+    "Глеб",
+    "Дарья",
+    "Егор",
+    "Жанна",
+    "Иван",
+    "Ксения",
+    "Лев",
+    "Мария",
+    "Никита",
+    "Ольга",
+    "Павел",
+    "Роман",
+    "София",
+];
+
+const CYRILLIC_LAST: &[&str] = &[
+    "Андреев",
+    "Белов",
+    "Волков",
+    "Громов",
+    "Данилов",
+    "Ершов",
+    "Жуков",
+    "Зайцев",
+    "Ильин",
+    "Козлов",
+    "Лебедев",
+    "Морозов",
+    "Новиков",
+    "Орлов",
+    "Петров",
+    "Романов",
+    "Смирнов",
+    "Титов",
+    "Уваров",
+    "Фомин",
+    "Чернов",
+    "Яковлев",
+];
+
+/// SplitMix64: small, fast and stable across releases, unlike library RNGs
+/// whose streams may change.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+/// Metadata and text of one generated book.
+struct Book {
+    title: String,
+    /// (first name, last name)
+    authors: Vec<(String, String)>,
+    genre: &'static str,
+    lang: &'static str,
+    year: u32,
+    series: Option<(String, u32)>,
+    paragraphs: Vec<String>,
+}
+
+impl Book {
+    fn random(rng: &mut Rng, authors: usize, series: usize) -> Self {
+        let cyrillic = rng.below(100) < 40;
+        let (words, lang) = if cyrillic {
+            (CYRILLIC_WORDS, "ru")
+        } else {
+            (LATIN_WORDS, "en")
+        };
+
+        let title_words = 1 + rng.below(4);
+        let title = capitalize(&sentence(rng, words, title_words));
+
+        // Authors and series come from pools so that lists group books the
+        // way real libraries do
+        let author_count = if rng.below(100) < 10 { 2 } else { 1 };
+        let authors = (0..author_count)
+            .map(|_| author_name(rng.below(authors), cyrillic))
+            .collect();
+        let series = (rng.below(100) < 30).then(|| {
+            let id = rng.below(series);
+            let name = capitalize(&format!(
+                "{} {}",
+                words[id % words.len()],
+                words[(id / words.len() + 7) % words.len()]
+            ));
+            (name, 1 + rng.below(12) as u32)
+        });
+
+        let paragraphs = (0..3 + rng.below(6))
+            .map(|_| {
+                let n = 20 + rng.below(60);
+                capitalize(&sentence(rng, words, n)) + "."
+            })
+            .collect();
+
+        Self {
+            title,
+            authors,
+            genre: rng.pick(GENRES),
+            lang,
+            year: 1900 + rng.below(125) as u32,
+            series,
+            paragraphs,
+        }
+    }
+
+    fn fb2(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <FictionBook xmlns=\"http://www.gribuser.ru/xml/fictionbook/2.0\" \
+             xmlns:l=\"http://www.w3.org/1999/xlink\">\n<description>\n<title-info>\n",
+        );
+        xml.push_str(&format!("<genre>{}</genre>\n", self.genre));
+        for (first, last) in &self.authors {
+            xml.push_str(&format!(
+                "<author><first-name>{first}</first-name><last-name>{last}</last-name></author>\n"
+            ));
+        }
+        xml.push_str(&format!("<book-title>{}</book-title>\n", self.title));
+        xml.push_str(&format!(
+            "<annotation><p>{}</p></annotation>\n",
+            self.paragraphs[0]
+        ));
+        xml.push_str(&format!("<date>{}</date>\n", self.year));
+        xml.push_str(&format!("<lang>{}</lang>\n", self.lang));
+        if let Some((name, number)) = &self.series {
+            xml.push_str(&format!(
+                "<sequence name=\"{name}\" number=\"{number}\"/>\n"
+            ));
+        }
+        xml.push_str("</title-info>\n</description>\n<body>\n<section>\n");
+        xml.push_str(&format!("<title><p>{}</p></title>\n", self.title));
+        for p in &self.paragraphs {
+            xml.push_str(&format!("<p>{p}</p>\n"));
+        }
+        xml.push_str("</section>\n</body>\n</FictionBook>\n");
+        xml
+    }
+
+    fn write_epub(&self, path: &Path) -> io::Result<u64> {
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+        let stored =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default();
+
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+        zip.start_file("META-INF/container.xml", deflated)?;
+        zip.write_all(
+            b"<?xml version=\"1.0\"?>\n\
+              <container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+              <rootfiles><rootfile full-path=\"OEBPS/content.opf\" \
+              media-type=\"application/oebps-package+xml\"/></rootfiles>\n</container>\n",
+        )?;
+
+        let mut opf = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"id\">\n\
+             <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+        );
+        opf.push_str(&format!("<dc:title>{}</dc:title>\n", self.title));
+        for (first, last) in &self.authors {
+            opf.push_str(&format!("<dc:creator>{first} {last}</dc:creator>\n"));
+        }
+        opf.push_str(&format!("<dc:language>{}</dc:language>\n", self.lang));
+        opf.push_str(&format!("<dc:date>{}</dc:date>\n", self.year));
+        opf.push_str(&format!("<dc:subject>{}</dc:subject>\n", self.genre));
+        opf.push_str(&format!(
+            "<dc:description>{}</dc:description>\n",
+            self.paragraphs[0]
+        ));
+        if let Some((name, number)) = &self.series {
+            opf.push_str(&format!(
+                "<meta name=\"calibre:series\" content=\"{name}\"/>\n\
+                 <meta name=\"calibre:series_index\" content=\"{number}\"/>\n"
+            ));
+        }
+        opf.push_str(
+            "</metadata>\n<manifest><item id=\"text\" href=\"text.xhtml\" \
+             media-type=\"application/xhtml+xml\"/></manifest>\n\
+             <spine><itemref idref=\"text\"/></spine>\n</package>\n",
+        );
+        zip.start_file("OEBPS/content.opf", deflated)?;
+        zip.write_all(opf.as_bytes())?;
+
+        let mut html = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <html xmlns=\"http://www.w3.org/1999/xhtml\"><head><title>{0}</title></head><body>\n\
+             <h1>{0}</h1>\n",
+            self.title
+        );
+        for p in &self.paragraphs {
+            html.push_str(&format!("<p>{p}</p>\n"));
+        }
+        html.push_str("</body></html>\n");
+        zip.start_file("OEBPS/text.xhtml", deflated)?;
+        zip.write_all(html.as_bytes())?;
+
+        zip.finish()?.flush()?;
+        Ok(fs::metadata(path)?.len())
+    }
+
+    /// INPX record in the default field layout
+    /// (`AUTHOR;GENRE;TITLE;SERIES;SERNO;FILE;SIZE;LIBID;DEL;EXT;DATE;LANG`).
+    fn inp_line(&self, libid: usize, size: usize) -> String {
+        let authors: String = self
+            .authors
+            .iter()
+            .map(|(first, last)| format!("{last},{first},:"))
+            .collect();
+        let (series, ser_no) = match &self.series {
+            Some((name, number)) => (name.as_str(), number.to_string()),
+            None => ("", String::new()),
+        };
+        [
+            authors,
+            format!("{}:", self.genre),
+            self.title.clone(),
+            series.to_string(),
+            ser_no,
+            libid.to_string(),
+            size.to_string(),
+            libid.to_string(),
+            "0".to_string(),
+            "fb2".to_string(),
+            format!("{}-01-01", self.year),
+            self.lang.to_string(),
+        ]
+        .join("\u{4}")
+    }
+}
+
+fn sentence(rng: &mut Rng, words: &[&str], n: usize) -> String {
+    (0..n)
+        .map(|_| rng.pick(words))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Author `id` of the pool; the same id always gives the same name.
+fn author_name(id: usize, cyrillic: bool) -> (String, String) {
+    let (first, last) = if cyrillic {
+        (CYRILLIC_FIRST, CYRILLIC_LAST)
+    } else {
+        (LATIN_FIRST, LATIN_LAST)
+    };
+    let surname = last[id % last.len()];
+    // Past the plain surnames, numbered ones keep the names distinct
+    let round = id / last.len();
+    let surname = if round == 0 {
+        surname.to_string()
+    } else {
+        format!(
+            "{surname}-{}",
+            capitalize(CYRILLIC_WORDS[round % CYRILLIC_WORDS.len()])
+        )
+    };
+    (first[(id / 3) % first.len()].to_string(), surname)
+}
+
+/// INPX archive being filled.
+struct InpxArchive {
+    zip: ZipWriter<BufWriter<File>>,
+    inp: String,
+    books: usize,
+}
+
+/// Write a synthetic library of `opts.books` books into `root`: FB2 and EPUB
+/// files in `books/NNN/` folders, and `inpx/library.inpx` with its
+/// `fb2-NNNN.zip` archives. Existing files with the same names are
+/// overwritten.
+pub fn generate(root: &Path, opts: &FixtureOptions) -> io::Result<FixtureSummary> {
+    let mut rng = Rng(opts.seed);
+    let mut summary = FixtureSummary::default();
+    let authors = (opts.books / 5).max(1);
+    let series = (opts.books / 20).max(1);
+    let per_folder = opts.books_per_folder.max(1);
+
+    let inpx_dir = root.join("inpx");
+    let mut inp_files: Vec<(String, String)> = Vec::new();
+    let mut archive: Option<InpxArchive> = None;
+    let mut files = 0;
+
+    for _ in 0..opts.books {
+        let book = Book::random(&mut rng, authors, series);
+        let roll = rng.below(100);
+
+        if roll < opts.inpx_percent as usize {
+            if archive.is_none() {
+                fs::create_dir_all(&inpx_dir)?;
+                let name = format!("fb2-{:04}.zip", inp_files.len() + 1);
+                let zip = ZipWriter::new(BufWriter::new(File::create(inpx_dir.join(name))?));
+                archive = Some(InpxArchive {
+                    zip,
+                    inp: String::new(),
+                    books: 0,
+                });
+            }
+            let current = archive.as_mut().expect("archive was just opened");
+            let libid = 100_000 + summary.inpx;
+            let fb2 = book.fb2();
+            current
+                .zip
+                .start_file(format!("{libid}.fb2"), SimpleFileOptions::default())?;
+            current.zip.write_all(fb2.as_bytes())?;
+            current.inp.push_str(&book.inp_line(libid, fb2.len()));
+            current.inp.push_str("\r\n");
+            current.books += 1;
+            summary.inpx += 1;
+            summary.bytes += fb2.len() as u64;
+
+            if current.books == INPX_ARCHIVE_BOOKS {
+                let done = archive.take().expect("archive is open");
+                inp_files.push(finish_archive(done, inp_files.len() + 1)?);
+                summary.inpx_archives += 1;
+            }
+            continue;
+        }
+
+        let folder = root
+            .join("books")
+            .join(format!("{:03}", files / per_folder));
+        fs::create_dir_all(&folder)?;
+        let stem = format!("book-{:07}", files + 1);
+        files += 1;
+        if roll < opts.inpx_percent as usize + opts.epub_percent as usize {
+            summary.bytes += book.write_epub(&folder.join(format!("{stem}.epub")))?;
+            summary.epub += 1;
+        } else {
+            let fb2 = book.fb2();
+            fs::write(folder.join(format!("{stem}.fb2")), &fb2)?;
+            summary.bytes += fb2.len() as u64;
+            summary.fb2 += 1;
+        }
+    }
+
+    if let Some(done) = archive.take() {
+        inp_files.push(finish_archive(done, inp_files.len() + 1)?);
+        summary.inpx_archives += 1;
+    }
+    if !inp_files.is_empty() {
+        write_inpx(&inpx_dir.join("library.inpx"), &inp_files, opts)?;
+    }
+    Ok(summary)
+}
+
+/// Close an archive and return its `.inp` entry (name, records).
+fn finish_archive(archive: InpxArchive, number: usize) -> io::Result<(String, String)> {
+    archive.zip.finish()?.flush()?;
+    Ok((format!("fb2-{number:04}.inp"), archive.inp))
+}
+
+fn write_inpx(
+    path: &Path,
+    inp_files: &[(String, String)],
+    opts: &FixtureOptions,
+) -> io::Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let options = SimpleFileOptions::default();
+    zip.start_file("collection.info", options)?;
+    writeln!(zip, "Synthetic library (seed {})", opts.seed)?;
+    zip.start_file("version.info", options)?;
+    writeln!(zip, "{}", opts.books)?;
+    for (name, records) in inp_files {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(records.as_bytes())?;
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(opts: &FixtureOptions) -> (tempfile::TempDir, FixtureSummary) {
+        let dir = tempfile::tempdir().unwrap();
+        let summary = generate(dir.path(), opts).unwrap();
+        (dir, summary)
+    }
+
+    fn first_with_ext(root: &Path, ext: &str) -> std::path::PathBuf {
+        walkdir::WalkDir::new(root.join("books"))
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+            .find(|e| e.path().extension().is_some_and(|x| x == ext))
+            .unwrap()
+            .into_path()
+    }
+
+    #[test]
+    fn test_generate_counts_and_parses() {
+        let opts = FixtureOptions {
+            books: 120,
+            books_per_folder: 40,
+            ..Default::default()
+        };
+        let (dir, summary) = generated(&opts);
+        assert_eq!(summary.fb2 + summary.epub + summary.inpx, 120);
+        assert!(summary.fb2 > 0 && summary.epub > 0 && summary.inpx > 0);
+        assert_eq!(summary.inpx_archives, 1);
+
+        let fb2 = File::open(first_with_ext(dir.path(), "fb2")).unwrap();
+        let meta = crate::scanner::parsers::fb2::parse(io::BufReader::new(fb2)).unwrap();
+        assert!(!meta.title.is_empty() && !meta.authors.is_empty());
+        assert!(!meta.genres.is_empty() && !meta.lang.is_empty());
+
+        let epub = File::open(first_with_ext(dir.path(), "epub")).unwrap();
+        let meta = crate::scanner::parsers::epub::parse(epub).unwrap();
+        assert!(!meta.title.is_empty() && !meta.authors.is_empty());
+
+        let inpx = File::open(dir.path().join("inpx/library.inpx")).unwrap();
+        let records = crate::scanner::parsers::inpx::parse(inpx, encoding_rs::UTF_8).unwrap();
+        assert_eq!(records.len(), summary.inpx);
+        assert_eq!(records[0].folder, "fb2-0001.zip");
+        assert!(dir.path().join("inpx/fb2-0001.zip").exists());
+    }
+
+    #[test]
+    fn test_generate_is_deterministic() {
+        let opts = FixtureOptions {
+            books: 30,
+            ..Default::default()
+        };
+        let (a, first) = generated(&opts);
+        let (b, second) = generated(&opts);
+        assert_eq!(first, second);
+        let path = first_with_ext(a.path(), "fb2");
+        let same = b.path().join(path.strip_prefix(a.path()).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), fs::read(same).unwrap());
+
+        let (_, other) = generated(&FixtureOptions { seed: 2, ..opts });
+        assert_ne!(first, other);
+    }
+}
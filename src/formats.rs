@@ -0,0 +1,214 @@
+//! Book format registry: MIME type, OPDS acquisition relation, display name
+//! and zipping policy per file extension.
+//!
+//! Built-in formats can be overridden and new ones added in `[formats.<ext>]`;
+//! feeds, downloads and the web UI all look formats up here.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use crate::config::FormatConfig;
+
+/// OPDS relation of acquisition links unless a format sets its own.
+pub const DEFAULT_REL: &str = "http://opds-spec.org/acquisition/open-access";
+
+/// MIME type of unknown formats.
+const FALLBACK_MIME: &str = "application/octet-stream";
+
+/// Everything known about one book format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Format {
+    pub mime: String,
+    /// MIME type of the zipped download.
+    pub zip_mime: String,
+    /// OPDS acquisition link relation.
+    pub rel: String,
+    /// Name shown in the web UI and OPDS facets.
+    pub name: String,
+    /// Whether a zipped download is offered.
+    pub zip: bool,
+}
+
+impl Format {
+    fn new(mime: &str, name: &str, zip: bool) -> Self {
+        Self {
+            mime: mime.to_string(),
+            zip_mime: format!("{mime}+zip"),
+            rel: DEFAULT_REL.to_string(),
+            name: name.to_string(),
+            zip,
+        }
+    }
+
+    /// Entry for an extension nobody registered.
+    fn unknown(ext: &str) -> Self {
+        Self::new(FALLBACK_MIME, &ext.to_uppercase(), true)
+    }
+}
+
+/// Built-in formats: extension, MIME type, display name, zipped download offered.
+const BUILTIN: &[(&str, &str, &str, bool)] = &[
+    ("fb2", "application/fb2+xml", "FB2", true),
+    ("epub", "application/epub+zip", "EPUB", false),
+    ("mobi", "application/x-mobipocket-ebook", "MOBI", false),
+    ("pdf", "application/pdf", "PDF", true),
+    ("doc", "application/msword", "DOC", true),
+    ("docx", "application/msword", "DOCX", true),
+    ("djvu", "image/vnd.djvu", "DjVu", true),
+    ("txt", "text/plain", "TXT", true),
+    ("rtf", "application/rtf", "RTF", true),
+    ("m4b", "audio/mp4", "M4B", false),
+    ("mp3", "audio/mpeg", "MP3", false),
+    ("zip", "application/zip", "ZIP", true),
+];
+
+/// Formats by lowercase extension.
+#[derive(Debug, Clone)]
+pub struct Registry {
+    formats: HashMap<String, Format>,
+}
+
+impl Registry {
+    /// Built-in formats with `[formats]` overrides applied.
+    pub fn new(overrides: &BTreeMap<String, FormatConfig>) -> Self {
+        let mut formats: HashMap<String, Format> = BUILTIN
+            .iter()
+            .map(|(ext, mime, name, zip)| (ext.to_string(), Format::new(mime, name, *zip)))
+            .collect();
+        if let Some(fb2) = formats.get_mut("fb2") {
+            fb2.zip_mime = "application/fb2+zip".to_string();
+        }
+        for (ext, cfg) in overrides {
+            let ext = ext.to_lowercase();
+            let format = formats
+                .entry(ext.clone())
+                .or_insert_with(|| Format::unknown(&ext));
+            if let Some(mime) = &cfg.mime {
+                format.mime = mime.clone();
+                format.zip_mime = format!("{mime}+zip");
+            }
+            if let Some(zip_mime) = &cfg.zip_mime {
+                format.zip_mime = zip_mime.clone();
+            }
+            if let Some(rel) = &cfg.rel {
+                format.rel = rel.clone();
+            }
+            if let Some(name) = &cfg.name {
+                format.name = name.clone();
+            }
+            if let Some(zip) = cfg.zip {
+                format.zip = zip;
+            }
+        }
+        Self { formats }
+    }
+
+    /// The registered format for `ext`, if any.
+    pub fn get(&self, ext: &str) -> Option<&Format> {
+        self.formats.get(ext)
+    }
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+static UNKNOWN: OnceLock<Format> = OnceLock::new();
+
+/// Install the `[formats]` overrides for this process. Must be called before
+/// the first lookup; later calls are ignored.
+pub fn configure(overrides: &BTreeMap<String, FormatConfig>) {
+    if REGISTRY.set(Registry::new(overrides)).is_err() {
+        tracing::debug!("[formats] registry already initialized; ignoring reconfiguration");
+    }
+}
+
+/// Current registry (built-in formats when `configure` was never called).
+pub fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(|| Registry::new(&BTreeMap::new()))
+}
+
+/// Format entry of `ext`; unknown extensions get an `application/octet-stream`
+/// entry.
+fn lookup(ext: &str) -> &'static Format {
+    registry()
+        .get(ext)
+        .unwrap_or_else(|| UNKNOWN.get_or_init(|| Format::unknown("")))
+}
+
+/// MIME type of a book format.
+pub fn mime(ext: &str) -> &'static str {
+    &lookup(ext).mime
+}
+
+/// MIME type of a zipped download of a book format.
+pub fn zip_mime(ext: &str) -> &'static str {
+    &lookup(ext).zip_mime
+}
+
+/// OPDS acquisition relation of a book format.
+pub fn rel(ext: &str) -> &'static str {
+    &lookup(ext).rel
+}
+
+/// Whether books of this format are offered as zipped downloads.
+pub fn zippable(ext: &str) -> bool {
+    lookup(ext).zip
+}
+
+/// Display name of a book format (the uppercase extension when unknown).
+pub fn name(ext: &str) -> String {
+    match registry().get(ext) {
+        Some(format) => format.name.clone(),
+        None => ext.to_uppercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_formats() {
+        let registry = Registry::new(&BTreeMap::new());
+        let fb2 = registry.get("fb2").unwrap();
+        assert_eq!(fb2.mime, "application/fb2+xml");
+        assert_eq!(fb2.zip_mime, "application/fb2+zip");
+        assert_eq!(fb2.rel, DEFAULT_REL);
+        assert!(fb2.zip);
+        assert!(!registry.get("epub").unwrap().zip);
+        assert_eq!(registry.get("pdf").unwrap().zip_mime, "application/pdf+zip");
+        assert!(registry.get("cbz").is_none());
+        assert_eq!(mime("unknown-format"), FALLBACK_MIME);
+        assert_eq!(name("cbz"), "CBZ");
+    }
+
+    #[test]
+    fn test_overrides_and_new_formats() {
+        let overrides = BTreeMap::from([
+            (
+                "CBZ".to_string(),
+                FormatConfig {
+                    mime: Some("application/vnd.comicbook+zip".to_string()),
+                    name: Some("Comic book".to_string()),
+                    zip: Some(false),
+                    ..Default::default()
+                },
+            ),
+            (
+                "pdf".to_string(),
+                FormatConfig {
+                    rel: Some("http://opds-spec.org/acquisition".to_string()),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let registry = Registry::new(&overrides);
+        let cbz = registry.get("cbz").unwrap();
+        assert_eq!(cbz.mime, "application/vnd.comicbook+zip");
+        assert_eq!(cbz.name, "Comic book");
+        assert_eq!(cbz.rel, DEFAULT_REL);
+        assert!(!cbz.zip);
+        let pdf = registry.get("pdf").unwrap();
+        assert_eq!(pdf.rel, "http://opds-spec.org/acquisition");
+        assert_eq!(pdf.mime, "application/pdf");
+        assert_eq!(pdf.name, "PDF");
+    }
+}
@@ -0,0 +1,306 @@
+//! Full-text search inside book bodies.
+//!
+//! With `search.fulltext` on, the text of [`INDEXED_FORMATS`] books (and of
+//! PDFs, when `pdftotext` is installed) is read after each scan ([`index_pending`]), cut at `search.fulltext_max_kb`, split
+//! into terms by [`terms`] and stored in the `book_terms` table. Searches
+//! split the query the same way ([`query_terms`]) and match books containing
+//! every term (see [`book_terms::search`]).
+//!
+//! Terms are lowercased words; `ё` is folded into `е`, and the stop words of
+//! the book's language (English and Russian, both for books without a
+//! language) are left out.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use tracing::{debug, warn};
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::db::models::Book;
+use crate::db::queries::book_terms;
+use crate::scanner::parsers::{epub, fb2, txt};
+use crate::tools;
+
+/// Formats whose text is always indexed.
+pub const INDEXED_FORMATS: &[&str] = &["fb2", "epub", "txt"];
+/// Words shorter or longer than this (in characters) are not indexed.
+const MIN_TERM_CHARS: usize = 2;
+const MAX_TERM_CHARS: usize = 32;
+/// Query words beyond this many are ignored.
+const MAX_QUERY_TERMS: usize = 8;
+/// Books read per indexing batch.
+const INDEX_BATCH: i32 = 50;
+
+const STOP_WORDS_EN: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "had", "has", "have",
+    "he", "her", "his", "i", "if", "in", "into", "is", "it", "its", "me", "my", "no", "not", "of",
+    "on", "or", "she", "so", "that", "the", "their", "them", "then", "there", "they", "this", "to",
+    "was", "we", "were", "what", "which", "who", "will", "with", "you", "your",
+];
+
+const STOP_WORDS_RU: &[&str] = &[
+    "а",
+    "без",
+    "бы",
+    "был",
+    "была",
+    "были",
+    "было",
+    "в",
+    "вам",
+    "вас",
+    "во",
+    "вот",
+    "все",
+    "всё",
+    "вы",
+    "где",
+    "да",
+    "для",
+    "до",
+    "его",
+    "ее",
+    "её",
+    "если",
+    "есть",
+    "еще",
+    "ещё",
+    "же",
+    "за",
+    "и",
+    "из",
+    "или",
+    "им",
+    "их",
+    "к",
+    "как",
+    "ко",
+    "когда",
+    "кто",
+    "ли",
+    "мне",
+    "мы",
+    "на",
+    "над",
+    "не",
+    "него",
+    "нет",
+    "ни",
+    "но",
+    "ну",
+    "о",
+    "об",
+    "он",
+    "она",
+    "они",
+    "оно",
+    "от",
+    "по",
+    "под",
+    "при",
+    "с",
+    "со",
+    "так",
+    "там",
+    "то",
+    "тот",
+    "ты",
+    "у",
+    "уже",
+    "что",
+    "это",
+    "я",
+];
+
+/// Stop words of a book language (`ru`, `en-US`, `rus`, ...); both lists
+/// for other or unknown languages.
+fn stop_words(lang: &str) -> [&'static [&'static str]; 2] {
+    let lang = lang.trim().to_lowercase();
+    if lang.starts_with("ru") {
+        [STOP_WORDS_RU, &[]]
+    } else if lang.starts_with("en") {
+        [STOP_WORDS_EN, &[]]
+    } else {
+        [STOP_WORDS_EN, STOP_WORDS_RU]
+    }
+}
+
+/// Lowercase a word and fold `ё`; `None` when it is too short or too long.
+fn normalize(word: &str) -> Option<String> {
+    let term: String = word
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| if c == 'ё' { 'е' } else { c })
+        .collect();
+    let len = term.chars().count();
+    (MIN_TERM_CHARS..=MAX_TERM_CHARS)
+        .contains(&len)
+        .then_some(term)
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+}
+
+/// Distinct terms of a book text with their number of occurrences, sorted.
+pub fn terms(text: &str, lang: &str) -> Vec<(String, i64)> {
+    let stop = stop_words(lang);
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for term in words(text).filter_map(normalize) {
+        if !stop.iter().any(|list| list.contains(&term.as_str())) {
+            *counts.entry(term).or_default() += 1;
+        }
+    }
+    let mut terms: Vec<(String, i64)> = counts.into_iter().collect();
+    terms.sort_unstable();
+    terms
+}
+
+/// Terms of a search query, in query order without repeats. Stop words of
+/// any language are dropped.
+pub fn query_terms(query: &str) -> Vec<String> {
+    let stop = stop_words("");
+    let mut terms: Vec<String> = Vec::new();
+    for term in words(query).filter_map(normalize) {
+        if !stop.iter().any(|list| list.contains(&term.as_str())) && !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms.truncate(MAX_QUERY_TERMS);
+    terms
+}
+
+/// The body text of a book file, cut after `max_chars` characters. `None`
+/// for formats without text extraction or unreadable files.
+pub fn extract_text(data: &[u8], format: &str, max_chars: usize) -> Option<String> {
+    match format {
+        "fb2" => fb2::body_text(Cursor::new(data), max_chars).ok(),
+        "epub" => epub::body_text(Cursor::new(data), max_chars).ok(),
+        "txt" => Some(txt::decode(data).chars().take(max_chars).collect()),
+        "pdf" => crate::pdf::extract_text_from_bytes(data, tools::settings().pdf_text_pages)
+            .ok()
+            .map(|text| text.chars().take(max_chars).collect()),
+        _ => None,
+    }
+}
+
+/// Read and index the text of every book not indexed yet. Books that cannot
+/// be read are marked indexed without terms, so they are not read again
+/// until their file changes. Returns the number of books indexed.
+pub async fn index_pending(pool: &DbPool, config: &Config) -> Result<u64, sqlx::Error> {
+    let max_chars = config.search.fulltext_max_kb.saturating_mul(1024);
+    let zip_encoding = crate::scanner::codepage_encoding(&config.library.zip_codepage);
+    // Without pdftotext PDFs stay pending, to be indexed once it is installed.
+    let mut formats = INDEXED_FORMATS.to_vec();
+    if crate::pdf::available_tools().pdftotext {
+        formats.push("pdf");
+    }
+    let mut indexed = 0;
+    loop {
+        let books = book_terms::pending_books(pool, &formats, INDEX_BATCH).await?;
+        if books.is_empty() {
+            break;
+        }
+        for book in books {
+            let terms = match book_text(config, &book, zip_encoding, max_chars).await {
+                Some(text) => terms(&text, &book.lang),
+                None => Vec::new(),
+            };
+            book_terms::replace_for_book(pool, book.id, &terms).await?;
+            indexed += 1;
+        }
+    }
+    Ok(indexed)
+}
+
+async fn book_text(
+    config: &Config,
+    book: &Book,
+    zip_encoding: &'static encoding_rs::Encoding,
+    max_chars: usize,
+) -> Option<String> {
+    let data = match crate::opds::download::load_book_file(
+        &config.library.root_path,
+        &book.path,
+        &book.filename,
+        book.cat_type,
+        zip_encoding,
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Cannot read book {} for text indexing: {e}", book.id);
+            return None;
+        }
+    };
+    let format = book.format.clone();
+    let text = tokio::task::spawn_blocking(move || extract_text(&data, &format, max_chars))
+        .await
+        .ok()
+        .flatten();
+    if text.is_none() {
+        debug!("No text extracted from book {}", book.id);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_normalize_and_count() {
+        let terms = terms("The whale! The WHALE, and a ship. Ёжик", "en");
+        assert_eq!(
+            terms,
+            [
+                ("ship".to_string(), 1),
+                ("whale".to_string(), 2),
+                ("ежик".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_terms_stop_words_follow_language() {
+        let ru = terms("и кит и море", "ru");
+        assert_eq!(ru, [("кит".to_string(), 1), ("море".to_string(), 1)]);
+        // English stop words stay in Russian books ("it" may be a term)
+        assert!(terms("it", "ru").iter().any(|(t, _)| t == "it"));
+        // Unknown language: both lists apply
+        assert!(terms("the и", "").is_empty());
+    }
+
+    #[test]
+    fn test_query_terms() {
+        assert_eq!(
+            query_terms("the Whale and the whale, Ёлка"),
+            ["whale", "елка"]
+        );
+        assert!(query_terms("a и").is_empty());
+        let long = (0..20)
+            .map(|i| format!("w{i}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(query_terms(&long).len(), MAX_QUERY_TERMS);
+    }
+
+    #[test]
+    fn test_extract_text() {
+        let fb2 = br#"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook><description><title-info><book-title>T</book-title></title-info></description>
+<body><section><p>Call me Ishmael.</p><p>Some years ago.</p></section></body>
+<body name="notes"><p>Footnote</p></body></FictionBook>"#;
+        let text = extract_text(fb2, "fb2", 1000).unwrap();
+        assert!(text.contains("Call me Ishmael."));
+        assert!(!text.contains("Footnote"));
+        let short = extract_text(fb2, "fb2", 10).unwrap();
+        assert!(!short.contains("years"));
+
+        assert_eq!(extract_text(b"plain text", "txt", 5).unwrap(), "plain");
+        assert!(extract_text(b"%PDF", "pdf", 100).is_none());
+    }
+}
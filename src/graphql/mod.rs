@@ -0,0 +1,135 @@
+//! Read-only GraphQL API at `/graphql` for third-party frontends.
+//!
+//! Callers authenticate like the rest of the server: with the web session
+//! cookie or with OPDS Basic credentials. When `opds.auth_required` is off,
+//! anonymous callers may query the catalog; per-user fields (bookshelf,
+//! reading progress) always need a signed-in user.
+
+mod query;
+mod types;
+
+use std::sync::OnceLock;
+
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Schema};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::cookie::CookieJar;
+
+use crate::api::request_user;
+use crate::state::AppState;
+
+pub use query::QueryRoot;
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Items per page when a query does not pass `limit`.
+const DEFAULT_PAGE_SIZE: i32 = 30;
+/// Largest `limit` a query may ask for.
+const MAX_PAGE_SIZE: i32 = 100;
+/// Nesting and cost limits that keep one request from walking the whole
+/// library (e.g. author → books → authors → books ...).
+const MAX_DEPTH: usize = 16;
+const MAX_COMPLEXITY: usize = 2000;
+
+/// Per-request data available to resolvers.
+pub struct ApiContext {
+    pub state: AppState,
+    pub user_id: Option<i64>,
+    /// Language of genre names.
+    pub lang: String,
+}
+
+impl ApiContext {
+    fn get<'a>(ctx: &Context<'a>) -> async_graphql::Result<&'a ApiContext> {
+        ctx.data::<ApiContext>()
+    }
+
+    fn require_user(&self) -> async_graphql::Result<i64> {
+        self.user_id
+            .ok_or_else(|| async_graphql::Error::new("Authentication required"))
+    }
+}
+
+/// Clamp client paging arguments to `(limit, offset)`.
+fn page_bounds(limit: Option<i32>, offset: Option<i32>) -> (i32, i32) {
+    (
+        limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+        offset.unwrap_or(0).max(0),
+    )
+}
+
+/// The API schema; built once, it holds no request state.
+pub fn schema() -> &'static ApiSchema {
+    static SCHEMA: OnceLock<ApiSchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+            .limit_depth(MAX_DEPTH)
+            .limit_complexity(MAX_COMPLEXITY)
+            .finish()
+    })
+}
+
+/// POST /graphql
+pub async fn graphql_handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    axum::Json(request): axum::Json<async_graphql::Request>,
+) -> Response {
+    let user_id = request_user(&state, &jar, &headers).await;
+    if user_id.is_none() && state.config.opds.auth_required {
+        return (StatusCode::UNAUTHORIZED, "Authentication required").into_response();
+    }
+    let lang = jar
+        .get("lang")
+        .map(|c| c.value().to_string())
+        .unwrap_or_else(|| {
+            crate::opds::v1::helpers::detect_opds_lang(&headers, &state.config, None)
+        });
+
+    let request = request.data(ApiContext {
+        state,
+        user_id,
+        lang,
+    });
+    axum::Json(schema().execute(request).await).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_bounds() {
+        assert_eq!(page_bounds(None, None), (DEFAULT_PAGE_SIZE, 0));
+        assert_eq!(page_bounds(Some(0), Some(-5)), (1, 0));
+        assert_eq!(page_bounds(Some(10_000), Some(40)), (MAX_PAGE_SIZE, 40));
+    }
+
+    #[test]
+    fn test_schema_exposes_catalog_and_user_queries() {
+        let sdl = schema().sdl();
+        for field in [
+            "searchBooks",
+            "searchAuthors",
+            "searchSeries",
+            "genreSections",
+            "bookshelf",
+            "readingHistory",
+        ] {
+            assert!(sdl.contains(field), "missing {field}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_introspection_fits_limits() {
+        let response = schema()
+            .execute(
+                "{ __schema { types { name fields { name args { name } \
+                 type { name kind ofType { name kind ofType { name kind ofType { name } } } } } } } }",
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+}
@@ -0,0 +1,223 @@
+//! Root query type.
+
+use async_graphql::{Context, Object, Result};
+
+use crate::db::queries::books::SearchFilter;
+use crate::db::queries::{
+    MatchMode, authors, books, bookshelf, genres, reading_positions, series, users,
+};
+
+use super::types::{
+    Author, AuthorPage, Book, BookPage, Genre, GenreSection, ReadingEntry, Series, SeriesPage, User,
+};
+use super::{ApiContext, page_bounds};
+
+/// Reading history entries returned at most.
+const MAX_HISTORY: i32 = 100;
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The signed-in user; `null` for anonymous callers.
+    async fn me(&self, ctx: &Context<'_>) -> Result<Option<User>> {
+        let api = ApiContext::get(ctx)?;
+        let Some(uid) = api.user_id else {
+            return Ok(None);
+        };
+        Ok(users::get_by_id(&api.state.db, uid).await?.map(|u| User {
+            id: u.id,
+            username: u.username,
+            display_name: u.display_name,
+            is_superuser: u.is_superuser != 0,
+        }))
+    }
+
+    async fn book(&self, ctx: &Context<'_>, id: i64) -> Result<Option<Book>> {
+        let api = ApiContext::get(ctx)?;
+        Ok(books::get_by_id(&api.state.db, id)
+            .await?
+            .filter(|b| b.avail > 0)
+            .map(Book))
+    }
+
+    /// Books whose title contains `query`, optionally narrowed by language,
+    /// format and genre.
+    #[allow(clippy::too_many_arguments)]
+    async fn search_books(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        lang: Option<String>,
+        format: Option<String>,
+        genre_id: Option<i64>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BookPage> {
+        let api = ApiContext::get(ctx)?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let config = &api.state.config;
+        let term = query.trim().to_uppercase();
+        let filter = SearchFilter::parse(
+            genre_id.map(|id| id.to_string()).as_deref(),
+            lang.as_deref(),
+            format.as_deref(),
+        );
+        let total = books::count_by_title_search(
+            &api.state.db,
+            &term,
+            MatchMode::Contains,
+            config.opds.hide_doubles,
+            config.search.translit,
+            &filter,
+        )
+        .await?;
+        let items = books::search_by_title(
+            &api.state.db,
+            &term,
+            MatchMode::Contains,
+            limit,
+            offset,
+            config.opds.hide_doubles,
+            books::BookSort::Title,
+            config.search.translit,
+            &filter,
+        )
+        .await?;
+        Ok(BookPage::new(total, items))
+    }
+
+    /// Newest additions first.
+    async fn recent_books(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BookPage> {
+        let api = ApiContext::get(ctx)?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let hide_doubles = api.state.config.opds.hide_doubles;
+        let total = books::count_recent_added(&api.state.db, hide_doubles).await?;
+        let items = books::get_recent_added(&api.state.db, limit, offset, hide_doubles).await?;
+        Ok(BookPage::new(total, items))
+    }
+
+    async fn author(&self, ctx: &Context<'_>, id: i64) -> Result<Option<Author>> {
+        let api = ApiContext::get(ctx)?;
+        Ok(authors::get_by_id(&api.state.db, id).await?.map(Author))
+    }
+
+    /// Authors whose name contains `query`.
+    async fn search_authors(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<AuthorPage> {
+        let api = ApiContext::get(ctx)?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let translit = api.state.config.search.translit;
+        let term = query.trim().to_uppercase();
+        let total =
+            authors::count_by_name_search(&api.state.db, &term, MatchMode::Contains, translit)
+                .await?;
+        let items = authors::search_by_name(
+            &api.state.db,
+            &term,
+            MatchMode::Contains,
+            limit,
+            offset,
+            translit,
+        )
+        .await?;
+        Ok(AuthorPage {
+            total,
+            items: items.into_iter().map(Author).collect(),
+        })
+    }
+
+    async fn series(&self, ctx: &Context<'_>, id: i64) -> Result<Option<Series>> {
+        let api = ApiContext::get(ctx)?;
+        Ok(series::get_by_id(&api.state.db, id).await?.map(Series))
+    }
+
+    /// Series whose name contains `query`.
+    async fn search_series(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<SeriesPage> {
+        let api = ApiContext::get(ctx)?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let term = query.trim().to_uppercase();
+        let total = series::count_by_name_search(&api.state.db, &term).await?;
+        let items = series::search_by_name(&api.state.db, &term, limit, offset).await?;
+        Ok(SeriesPage {
+            total,
+            items: items.into_iter().map(Series).collect(),
+        })
+    }
+
+    async fn genre(&self, ctx: &Context<'_>, id: i64) -> Result<Option<Genre>> {
+        let api = ApiContext::get(ctx)?;
+        Ok(genres::get_by_id(&api.state.db, id, &api.lang)
+            .await?
+            .map(Genre))
+    }
+
+    /// Genre sections, each with its genres.
+    async fn genre_sections(&self, ctx: &Context<'_>) -> Result<Vec<GenreSection>> {
+        let api = ApiContext::get(ctx)?;
+        let rows = genres::get_sections(&api.state.db, &api.lang).await?;
+        Ok(rows
+            .into_iter()
+            .map(|(code, name)| GenreSection { code, name })
+            .collect())
+    }
+
+    /// The caller's bookshelf, most recently added first.
+    async fn bookshelf(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BookPage> {
+        let api = ApiContext::get(ctx)?;
+        let uid = api.require_user()?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let total = bookshelf::count_by_user(&api.state.db, uid).await?;
+        let items = bookshelf::get_by_user(
+            &api.state.db,
+            uid,
+            &bookshelf::SortColumn::Date,
+            false,
+            limit,
+            offset,
+        )
+        .await?;
+        Ok(BookPage::new(total, items))
+    }
+
+    /// Books the caller has opened in the reader, most recent first.
+    async fn reading_history(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+    ) -> Result<Vec<ReadingEntry>> {
+        let api = ApiContext::get(ctx)?;
+        let uid = api.require_user()?;
+        let limit = limit.unwrap_or(20).clamp(1, MAX_HISTORY);
+        let rows = reading_positions::get_recent(&api.state.db, uid, limit as i64).await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| ReadingEntry {
+                book_id: r.book_id,
+                progress: r.progress,
+                updated_at: r.updated_at,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,315 @@
+//! GraphQL object types. Each wraps a database model and resolves its
+//! relations lazily, so clients only pay for the fields they select.
+
+use async_graphql::{ComplexObject, Context, Object, Result, SimpleObject};
+
+use crate::db::models;
+use crate::db::queries::{authors, books, bookshelf, genres, reading_positions, series};
+
+use super::{ApiContext, page_bounds};
+
+/// A book in the library.
+pub struct Book(pub models::Book);
+
+#[Object]
+impl Book {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    /// File format as stored: `fb2`, `epub`, `pdf`, `mp3`, ...
+    async fn format(&self) -> &str {
+        &self.0.format
+    }
+
+    /// File size in bytes.
+    async fn size(&self) -> i64 {
+        self.0.size
+    }
+
+    async fn lang(&self) -> &str {
+        &self.0.lang
+    }
+
+    async fn annotation(&self) -> &str {
+        &self.0.annotation
+    }
+
+    /// Publication date as found in the book's metadata.
+    async fn docdate(&self) -> &str {
+        &self.0.docdate
+    }
+
+    /// When the book was added to the library.
+    async fn added_at(&self) -> &str {
+        &self.0.reg_date
+    }
+
+    async fn cover_url(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let api = ApiContext::get(ctx)?;
+        let urls = crate::opds::covers::CoverUrls::new(&api.state.config);
+        Ok((self.0.cover != 0).then(|| urls.cover(self.0.id)))
+    }
+
+    async fn thumbnail_url(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let api = ApiContext::get(ctx)?;
+        let urls = crate::opds::covers::CoverUrls::new(&api.state.config);
+        Ok((self.0.cover != 0).then(|| urls.thumb(self.0.id)))
+    }
+
+    /// Download in the original format, authorized by the web session.
+    async fn download_url(&self) -> String {
+        format!("/web/download/{}/0", self.0.id)
+    }
+
+    async fn authors(&self, ctx: &Context<'_>) -> Result<Vec<Author>> {
+        let api = ApiContext::get(ctx)?;
+        let rows = authors::get_for_book(&api.state.db, self.0.id).await?;
+        Ok(rows.into_iter().map(Author).collect())
+    }
+
+    async fn series(&self, ctx: &Context<'_>) -> Result<Vec<BookSeries>> {
+        let api = ApiContext::get(ctx)?;
+        let rows = series::get_for_book(&api.state.db, self.0.id).await?;
+        Ok(rows
+            .into_iter()
+            .map(|(s, number)| BookSeries {
+                series: Series(s),
+                number,
+            })
+            .collect())
+    }
+
+    async fn genres(&self, ctx: &Context<'_>) -> Result<Vec<Genre>> {
+        let api = ApiContext::get(ctx)?;
+        let rows = genres::get_for_book(&api.state.db, self.0.id, &api.lang).await?;
+        Ok(rows.into_iter().map(Genre).collect())
+    }
+
+    /// Whether the book is on the caller's bookshelf; `false` for anonymous
+    /// callers.
+    async fn on_bookshelf(&self, ctx: &Context<'_>) -> Result<bool> {
+        let api = ApiContext::get(ctx)?;
+        match api.user_id {
+            Some(uid) => Ok(bookshelf::is_on_shelf(&api.state.db, uid, self.0.id).await?),
+            None => Ok(false),
+        }
+    }
+
+    /// The caller's reading progress, 0..1, if they have opened the book in
+    /// the reader.
+    async fn reading_progress(&self, ctx: &Context<'_>) -> Result<Option<f64>> {
+        let api = ApiContext::get(ctx)?;
+        let Some(uid) = api.user_id else {
+            return Ok(None);
+        };
+        let position = reading_positions::get_position(&api.state.db, uid, self.0.id).await?;
+        Ok(position.map(|p| p.progress))
+    }
+}
+
+/// A book's membership in a series.
+#[derive(SimpleObject)]
+pub struct BookSeries {
+    pub series: Series,
+    /// Volume number; 0 when unknown.
+    pub number: i32,
+}
+
+/// A page of books with the total number of matches.
+#[derive(SimpleObject)]
+pub struct BookPage {
+    pub total: i64,
+    pub items: Vec<Book>,
+}
+
+impl BookPage {
+    pub fn new(total: i64, items: Vec<models::Book>) -> Self {
+        Self {
+            total,
+            items: items.into_iter().map(Book).collect(),
+        }
+    }
+}
+
+pub struct Author(pub models::Author);
+
+#[Object]
+impl Author {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.full_name
+    }
+
+    async fn books(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BookPage> {
+        let api = ApiContext::get(ctx)?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let hide_doubles = api.state.config.opds.hide_doubles;
+        let db = &api.state.db;
+        let total = books::count_by_author(db, self.0.id, hide_doubles).await?;
+        let items = books::get_by_author(
+            db,
+            self.0.id,
+            limit,
+            offset,
+            hide_doubles,
+            books::BookSort::Title,
+        )
+        .await?;
+        Ok(BookPage::new(total, items))
+    }
+}
+
+/// A page of authors with the total number of matches.
+#[derive(SimpleObject)]
+pub struct AuthorPage {
+    pub total: i64,
+    pub items: Vec<Author>,
+}
+
+pub struct Series(pub models::Series);
+
+#[Object]
+impl Series {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.ser_name
+    }
+
+    /// Books of the series in volume order.
+    async fn books(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BookPage> {
+        let api = ApiContext::get(ctx)?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let hide_doubles = api.state.config.opds.hide_doubles;
+        let db = &api.state.db;
+        let total = books::count_by_series(db, self.0.id, hide_doubles).await?;
+        let items = books::get_by_series(
+            db,
+            self.0.id,
+            limit,
+            offset,
+            hide_doubles,
+            books::BookSort::Series,
+        )
+        .await?;
+        Ok(BookPage::new(total, items))
+    }
+}
+
+/// A page of series with the total number of matches.
+#[derive(SimpleObject)]
+pub struct SeriesPage {
+    pub total: i64,
+    pub items: Vec<Series>,
+}
+
+/// A genre; names are translated to the request language.
+pub struct Genre(pub models::Genre);
+
+#[Object]
+impl Genre {
+    async fn id(&self) -> i64 {
+        self.0.id
+    }
+
+    async fn code(&self) -> &str {
+        &self.0.code
+    }
+
+    async fn section(&self) -> &str {
+        &self.0.section
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.subsection
+    }
+
+    async fn books(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BookPage> {
+        let api = ApiContext::get(ctx)?;
+        let (limit, offset) = page_bounds(limit, offset);
+        let hide_doubles = api.state.config.opds.hide_doubles;
+        let db = &api.state.db;
+        let total = books::count_by_genre(db, self.0.id, hide_doubles).await?;
+        let items = books::get_by_genre(
+            db,
+            self.0.id,
+            limit,
+            offset,
+            hide_doubles,
+            books::BookSort::Title,
+        )
+        .await?;
+        Ok(BookPage::new(total, items))
+    }
+}
+
+/// A top-level genre group.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct GenreSection {
+    pub code: String,
+    pub name: String,
+}
+
+#[ComplexObject]
+impl GenreSection {
+    async fn genres(&self, ctx: &Context<'_>) -> Result<Vec<Genre>> {
+        let api = ApiContext::get(ctx)?;
+        let rows = genres::get_by_section(&api.state.db, &self.code, &api.lang).await?;
+        Ok(rows.into_iter().map(Genre).collect())
+    }
+}
+
+/// A book the caller has opened in the reader.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct ReadingEntry {
+    pub book_id: i64,
+    /// 0..1.
+    pub progress: f64,
+    pub updated_at: String,
+}
+
+#[ComplexObject]
+impl ReadingEntry {
+    async fn book(&self, ctx: &Context<'_>) -> Result<Option<Book>> {
+        let api = ApiContext::get(ctx)?;
+        Ok(books::get_by_id(&api.state.db, self.book_id)
+            .await?
+            .map(Book))
+    }
+}
+
+/// The signed-in user.
+#[derive(SimpleObject)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub display_name: String,
+    pub is_superuser: bool,
+}
@@ -0,0 +1,415 @@
+//! Language guessing for books whose files carry no language tag.
+//!
+//! [`guess`] looks at the writing system first and settles most non-Latin
+//! text by script alone. Cyrillic and Latin text is told apart by letters
+//! particular to a language and by its most common short words. Text that is
+//! too short or too ambiguous yields `None` instead of a coin toss, with one
+//! exception: Cyrillic text without any distinguishing marks is taken as
+//! Russian, by far the most common case in these libraries.
+//!
+//! [`detect`] also reports how sure the guess is, so the scanner can fill in
+//! a missing language from a book's text only when the evidence is clear.
+
+use crate::db::DbPool;
+use crate::db::queries::books;
+
+/// Letters needed before any guess is made.
+const MIN_LETTERS: usize = 6;
+
+/// Score a Latin-script language needs to be picked.
+const MIN_LATIN_SCORE: usize = 2;
+
+/// Rows read per batch by [`guess_missing`].
+const BATCH: i32 = 500;
+
+/// Common words and particular letters of one language.
+struct Profile {
+    code: &'static str,
+    words: &'static [&'static str],
+    letters: &'static str,
+}
+
+const CYRILLIC: &[Profile] = &[
+    Profile {
+        code: "ru",
+        words: &[
+            "и", "в", "не", "на", "что", "он", "она", "с", "как", "это", "его", "по", "к", "но",
+            "из", "о", "за", "то", "все", "от", "был", "была",
+        ],
+        letters: "ыэё",
+    },
+    Profile {
+        code: "uk",
+        words: &[
+            "і", "в", "у", "не", "на", "що", "він", "вона", "з", "як", "це", "його", "до", "та",
+            "але", "від", "був", "була",
+        ],
+        letters: "іїєґ",
+    },
+    Profile {
+        code: "be",
+        words: &[
+            "і", "у", "не", "на", "што", "ён", "яна", "з", "як", "гэта", "яго", "да", "але", "ад",
+            "быў", "была",
+        ],
+        letters: "ўіыэё",
+    },
+    Profile {
+        code: "bg",
+        words: &[
+            "и", "в", "не", "на", "че", "той", "тя", "с", "като", "това", "е", "да", "за", "от",
+            "но", "се", "беше",
+        ],
+        letters: "ъ",
+    },
+    Profile {
+        code: "sr",
+        words: &[
+            "и", "у", "не", "на", "да", "је", "се", "са", "као", "то", "што", "од", "за", "био",
+        ],
+        letters: "ђћџљњј",
+    },
+];
+
+const LATIN: &[Profile] = &[
+    Profile {
+        code: "en",
+        words: &[
+            "the", "and", "of", "to", "a", "in", "is", "that", "it", "was", "he", "she", "for",
+            "with", "his", "her", "on", "as", "by",
+        ],
+        letters: "",
+    },
+    Profile {
+        code: "de",
+        words: &[
+            "der", "die", "das", "und", "ist", "nicht", "ein", "eine", "zu", "den", "mit", "sich",
+            "auf", "von", "dem", "er", "sie", "es", "im",
+        ],
+        letters: "äöüß",
+    },
+    Profile {
+        code: "fr",
+        words: &[
+            "le", "la", "les", "et", "de", "des", "un", "une", "est", "que", "il", "elle", "dans",
+            "qui", "ne", "pas", "pour", "du", "au", "sur",
+        ],
+        letters: "èêîœç",
+    },
+    Profile {
+        code: "es",
+        words: &[
+            "el", "la", "los", "las", "y", "de", "que", "en", "un", "una", "es", "por", "con",
+            "no", "se", "del", "su", "para", "al",
+        ],
+        letters: "ñ¿¡",
+    },
+    Profile {
+        code: "it",
+        words: &[
+            "il", "lo", "la", "gli", "le", "e", "di", "che", "un", "una", "è", "non", "per", "con",
+            "si", "del", "della", "in", "al",
+        ],
+        letters: "ìò",
+    },
+    Profile {
+        code: "pt",
+        words: &[
+            "o", "a", "os", "as", "e", "de", "que", "em", "um", "uma", "é", "não", "por", "com",
+            "se", "do", "da", "para", "no",
+        ],
+        letters: "ãõç",
+    },
+    Profile {
+        code: "pl",
+        words: &[
+            "i", "w", "z", "na", "nie", "się", "że", "to", "jest", "do", "jak", "co", "od", "po",
+            "ale", "był", "była",
+        ],
+        letters: "ąęłśźżńć",
+    },
+    Profile {
+        code: "cs",
+        words: &[
+            "a", "v", "se", "na", "je", "že", "to", "s", "z", "do", "jak", "ale", "po", "byl",
+            "byla", "jsem", "není",
+        ],
+        letters: "řůě",
+    },
+    Profile {
+        code: "nl",
+        words: &[
+            "de", "het", "een", "en", "van", "is", "dat", "niet", "ik", "zijn", "op", "te", "met",
+            "voor", "hij", "zij", "er",
+        ],
+        letters: "",
+    },
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Script {
+    Latin,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Devanagari,
+    Thai,
+    Georgian,
+    Armenian,
+    Hangul,
+    Kana,
+    Han,
+}
+
+fn script_of(c: char) -> Option<Script> {
+    Some(match c as u32 {
+        0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F => Script::Latin,
+        0x370..=0x3FF => Script::Greek,
+        0x400..=0x52F => Script::Cyrillic,
+        0x530..=0x58F => Script::Armenian,
+        0x590..=0x5FF => Script::Hebrew,
+        0x600..=0x6FF => Script::Arabic,
+        0x900..=0x97F => Script::Devanagari,
+        0xE00..=0xE7F => Script::Thai,
+        0x10A0..=0x10FF => Script::Georgian,
+        0x1100..=0x11FF | 0xAC00..=0xD7AF => Script::Hangul,
+        0x3040..=0x30FF => Script::Kana,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF => Script::Han,
+        _ => return None,
+    })
+}
+
+/// Guess the ISO 639-1 code of the language `text` is written in.
+pub fn guess(text: &str) -> Option<&'static str> {
+    detect(text).map(|(code, _)| code)
+}
+
+/// Like [`guess`], with a confidence between 0 and 1: the share of letters
+/// in the dominant script, times how far the winning language's score is
+/// ahead of the runner-up (1 when the script alone settles it). Unmarked
+/// Cyrillic text taken as Russian has confidence 0.
+pub fn detect(text: &str) -> Option<(&'static str, f32)> {
+    let text = text.to_lowercase();
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for script in text
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .filter_map(script_of)
+    {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+    let letters: usize = counts.iter().map(|(_, n)| n).sum();
+    if letters < MIN_LETTERS {
+        return None;
+    }
+    let count = |script| {
+        counts
+            .iter()
+            .find(|(s, _)| *s == script)
+            .map_or(0, |(_, n)| *n)
+    };
+    let (dominant, dominant_count) = counts.iter().copied().max_by_key(|(_, n)| *n)?;
+    // Japanese mixes kanji with kana, so both count towards its script share.
+    let script_count = match dominant {
+        Script::Han | Script::Kana => count(Script::Han) + count(Script::Kana),
+        _ => dominant_count,
+    };
+    let share = script_count as f32 / letters as f32;
+
+    let (code, margin) = match dominant {
+        // Japanese mixes kanji with kana; Chinese has no kana at all.
+        Script::Han | Script::Kana if count(Script::Kana) > 0 => ("ja", 1.0),
+        Script::Han => ("zh", 1.0),
+        Script::Kana => ("ja", 1.0),
+        Script::Hangul => ("ko", 1.0),
+        Script::Greek => ("el", 1.0),
+        Script::Hebrew => ("he", 1.0),
+        Script::Armenian => ("hy", 1.0),
+        Script::Georgian => ("ka", 1.0),
+        Script::Thai => ("th", 1.0),
+        Script::Devanagari => ("hi", 1.0),
+        Script::Arabic if text.contains(['پ', 'چ', 'ژ', 'گ', 'ی']) => ("fa", 1.0),
+        Script::Arabic => ("ar", 1.0),
+        Script::Cyrillic => best_profile(&text, CYRILLIC, 0).unwrap_or(("ru", 0.0)),
+        Script::Latin => best_profile(&text, LATIN, MIN_LATIN_SCORE)?,
+    };
+    Some((code, share * margin))
+}
+
+/// The profile with the strictly highest score of at least `min_score`, and
+/// its lead over the runner-up relative to its own score.
+/// Ties between Cyrillic profiles go to the first one listed.
+fn best_profile(text: &str, profiles: &[Profile], min_score: usize) -> Option<(&'static str, f32)> {
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let mut scores: Vec<(&'static str, usize)> = profiles
+        .iter()
+        .map(|p| {
+            let word_hits = words.iter().filter(|w| p.words.contains(w)).count();
+            let letter_hits = text.chars().filter(|c| p.letters.contains(*c)).count();
+            (p.code, word_hits + letter_hits)
+        })
+        .collect();
+    // Stable sort keeps list order among equal scores.
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    let (code, best) = scores[0];
+    if best == 0 || best < min_score {
+        return None;
+    }
+    let second = scores.get(1).map_or(0, |(_, s)| *s);
+    if second == best && min_score > 0 {
+        return None;
+    }
+    Some((code, (best - second) as f32 / best as f32))
+}
+
+/// A language guessed for a book stored without one.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Guess {
+    pub book_id: i64,
+    pub title: String,
+    pub lang: &'static str,
+}
+
+/// Guess languages for every available book with an empty `lang`, from its
+/// title and annotation. Books nothing can be guessed for are left out; the
+/// second value counts all books without a language.
+pub async fn guess_missing(pool: &DbPool) -> Result<(Vec<Guess>, usize), sqlx::Error> {
+    let mut guesses = Vec::new();
+    let mut total = 0;
+    let mut after_id = 0;
+    loop {
+        let batch = books::get_without_lang(pool, after_id, BATCH).await?;
+        let Some((last_id, _, _)) = batch.last() else {
+            break;
+        };
+        after_id = *last_id;
+        total += batch.len();
+        for (book_id, title, annotation) in batch {
+            if let Some(lang) = guess(&format!("{title}\n{annotation}")) {
+                guesses.push(Guess {
+                    book_id,
+                    title,
+                    lang,
+                });
+            }
+        }
+    }
+    Ok((guesses, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_by_script() {
+        assert_eq!(guess("Το πλοίο έφτασε στον σταθμό"), Some("el"));
+        assert_eq!(guess("船は駅に到着しました"), Some("ja"));
+        assert_eq!(guess("船到达了车站然后离开"), Some("zh"));
+        assert_eq!(guess("배가 역에 도착했습니다"), Some("ko"));
+        assert_eq!(guess("وصلت السفينة إلى المحطة"), Some("ar"));
+    }
+
+    #[test]
+    fn test_guess_cyrillic_languages() {
+        assert_eq!(guess("Война и мир"), Some("ru"));
+        assert_eq!(guess("Це історія про хлопця, який жив у селі"), Some("uk"));
+        assert_eq!(
+            guess("Гэта гісторыя пра хлопца, які жыў у вёсцы"),
+            Some("be")
+        );
+        assert_eq!(guess("Мастер"), Some("ru"));
+    }
+
+    #[test]
+    fn test_guess_latin_languages() {
+        assert_eq!(guess("The ship arrived at the station."), Some("en"));
+        assert_eq!(
+            guess("Die Geschichte von einem Mann, der nicht schlafen kann"),
+            Some("de")
+        );
+        assert_eq!(guess("L'histoire d'un homme qui ne dort pas"), Some("fr"));
+        assert_eq!(guess("La historia de un hombre que no duerme"), Some("es"));
+        assert_eq!(guess("Historia człowieka, który nie śpi"), Some("pl"));
+    }
+
+    #[test]
+    fn test_detect_confidence() {
+        let (code, confidence) = detect("Το πλοίο έφτασε στον σταθμό").unwrap();
+        assert_eq!((code, confidence), ("el", 1.0));
+
+        let (code, confidence) = detect(
+            "The ship arrived at the station and the captain walked to the harbour office \
+             with his mate, who was carrying the logbook in a bag.",
+        )
+        .unwrap();
+        assert_eq!(code, "en");
+        assert!(confidence > 0.5, "{confidence}");
+
+        let (code, confidence) = detect(
+            "Он сказал, что она не придёт. Это было всё, что он знал о ней, \
+             и как теперь быть, было не понятно никому из них.",
+        )
+        .unwrap();
+        assert_eq!(code, "ru");
+        assert!(confidence > 0.3, "{confidence}");
+
+        assert_eq!(detect("Мастер"), Some(("ru", 0.0)));
+        let (_, pure) = detect("Chapter one: the ship and the sea").unwrap();
+        let (_, mixed) = detect("Глава первая. Chapter one: the ship and the sea").unwrap();
+        assert!(mixed < pure, "{mixed} vs {pure}");
+    }
+
+    #[test]
+    fn test_guess_gives_up_on_thin_evidence() {
+        assert_eq!(guess("Foundation"), None);
+        assert_eq!(guess("1984"), None);
+        assert_eq!(guess("Ab"), None);
+        assert_eq!(guess(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_guess_missing_skips_tagged_and_unknown_books() {
+        let pool = crate::db::create_test_pool().await;
+        sqlx::query("INSERT INTO catalogs (path, cat_name) VALUES ('/t', 't')")
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        for (title, lang, annotation) in [
+            ("Война и мир", "", ""),
+            ("Foundation", "", ""),
+            ("Tagged", "en", "The ship arrived at the station."),
+            (
+                "Moby Dick",
+                "",
+                "The story of a whale and the man who hunts it.",
+            ),
+        ] {
+            sqlx::query(
+                "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+                 annotation, lang, lang_code, size, avail, cat_type, cover, cover_type) \
+                 VALUES ((SELECT id FROM catalogs WHERE path = '/t'), ?, '/t', 'fb2', ?, ?, ?, ?, 2, 100, 2, 0, 0, '')",
+            )
+            .bind(format!("{title}.fb2"))
+            .bind(title)
+            .bind(title.to_uppercase())
+            .bind(annotation)
+            .bind(lang)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+        }
+
+        let (guesses, total) = guess_missing(&pool).await.unwrap();
+        assert_eq!(total, 3);
+        let found: Vec<(&str, &str)> = guesses.iter().map(|g| (g.title.as_str(), g.lang)).collect();
+        assert_eq!(found, vec![("Война и мир", "ru"), ("Moby Dick", "en")]);
+    }
+}
@@ -1,8 +1,20 @@
+pub mod api;
 pub mod assets;
+pub mod audio;
 pub mod config;
+pub mod covers;
 pub mod db;
+pub mod disk;
 pub mod djvu;
+pub mod doctor;
 pub mod email;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod formats;
+pub mod fulltext;
+pub mod graphql;
+pub mod langdetect;
+pub mod markdown;
 pub mod oauth;
 pub mod opds;
 pub mod password;
@@ -10,15 +22,24 @@ pub mod pdf;
 pub mod scanner;
 pub mod scheduler;
 pub mod state;
+pub mod tools;
+pub mod translit;
 pub mod util;
+pub mod vfs;
 pub mod web;
 
+use std::time::Duration;
+
 use axum::Router;
-use axum::extract::State;
-use axum::response::Json;
-use axum::routing::get;
+use axum::extract::{DefaultBodyLimit, Request, State};
+use axum::http::{Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use axum::routing::{get, post};
 use tower_http::compression::CompressionLayer;
+use tower_http::timeout::TimeoutLayer;
 
+use crate::config::ServerConfig;
 use crate::state::AppState;
 
 async fn health_check(State(state): State<AppState>) -> Json<serde_json::Value> {
@@ -29,12 +50,84 @@ async fn health_check(State(state): State<AppState>) -> Json<serde_json::Value>
     Json(serde_json::json!({
         "status": if db_ok { "ok" } else { "degraded" },
         "version": env!("CARGO_PKG_VERSION"),
-        "library_root": state.config.library.root_path,
+        "library_root": crate::vfs::display_root(&state.config.library.root_path),
+        "read_only": state.config.server.read_only,
         "database": if db_ok { "connected" } else { "error" },
     }))
 }
 
+/// Readiness probe: `503` while the database is unreachable or a volume the
+/// server writes to has less than `server.min_free_space_mb` free.
+async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let db_ok = sqlx::query("SELECT 1")
+        .execute(state.db.inner())
+        .await
+        .is_ok();
+    let volumes = disk::report(&state.config);
+    let disk_ok = volumes.iter().all(|v| !v.low);
+    let disk: Vec<serde_json::Value> = volumes
+        .iter()
+        .map(|v| {
+            serde_json::json!({
+                "name": v.name,
+                "free_bytes": v.free_bytes,
+                "total_bytes": v.total_bytes,
+                "low": v.low,
+            })
+        })
+        .collect();
+
+    let ready = db_ok && disk_ok;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(serde_json::json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "database": if db_ok { "connected" } else { "error" },
+            "min_free_space_mb": state.config.server.min_free_space_mb,
+            "disk": disk,
+        })),
+    )
+}
+
+/// Cap the request body of every route already added to `router` and answer
+/// requests running longer than `server.request_timeout_secs` with 408.
+/// Routes added afterwards (uploads) keep their own limits.
+pub(crate) fn limit_requests(
+    router: Router<AppState>,
+    server: &ServerConfig,
+    body_limit_kb: usize,
+) -> Router<AppState> {
+    let router = router.layer(DefaultBodyLimit::max(body_limit_kb * 1024));
+    if server.request_timeout_secs == 0 {
+        return router;
+    }
+    router.layer(TimeoutLayer::with_status_code(
+        StatusCode::REQUEST_TIMEOUT,
+        Duration::from_secs(server.request_timeout_secs),
+    ))
+}
+
+/// Middleware: on a read-only mirror, refuse requests that could change
+/// anything (every method but GET and HEAD).
+pub(crate) async fn reject_writes_when_read_only(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let safe = matches!(*request.method(), Method::GET | Method::HEAD);
+    if state.config.server.read_only && !safe {
+        return (StatusCode::FORBIDDEN, "Read-only mirror").into_response();
+    }
+    next.run(request).await
+}
+
 pub fn build_router(state: AppState) -> Router {
+    let server = &state.config.server;
     let router = Router::new()
         .route("/", get(|| async { axum::response::Redirect::to("/web") }))
         .route(
@@ -42,9 +135,46 @@ pub fn build_router(state: AppState) -> Router {
             get(|| async { axum::response::Redirect::to("/web") }),
         )
         .route("/health", get(health_check))
-        .nest("/opds", opds::router(state.clone()))
+        .route("/health/ready", get(readiness_check))
+        .route("/share/{token}", get(web::share::shared_download))
+        .nest(
+            "/opds",
+            limit_requests(
+                opds::router(state.clone()),
+                server,
+                server.opds_body_limit_kb,
+            ),
+        )
         .nest("/web", web::router(state.clone()))
-        .route("/static/{*path}", get(assets::static_asset));
+        .route("/static/{*path}", get(assets::static_asset))
+        .route("/favicon.ico", get(assets::favicon))
+        .route("/robots.txt", get(web::seo::robots_txt))
+        .route("/sitemap.xml", get(web::seo::sitemap_index))
+        .route("/sitemap/{kind}/{file}", get(web::seo::sitemap_part));
+    let router = if state.config.api.graphql {
+        router.merge(limit_requests(
+            Router::new().route("/graphql", post(graphql::graphql_handler)),
+            server,
+            server.web_body_limit_kb,
+        ))
+    } else {
+        router
+    };
+    let router = if state.config.api.rest {
+        router.nest(
+            "/api/v1",
+            limit_requests(api::router(state.clone()), server, server.web_body_limit_kb),
+        )
+    } else {
+        router
+    };
+
+    // Libraries not open to search engines ask them to stay out.
+    let router = if state.config.seo_enabled() {
+        router
+    } else {
+        router.layer(axum::middleware::map_response(web::seo::noindex))
+    };
 
     router.layer(CompressionLayer::new()).with_state(state)
 }
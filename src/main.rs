@@ -1,17 +1,20 @@
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{ArgGroup, Parser, Subcommand};
 use tracing_subscriber::EnvFilter;
+use tracing_subscriber::filter::filter_fn;
 use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::prelude::*;
 
 use ropds::build_router;
 use ropds::config::Config;
-use ropds::state::AppState;
+use ropds::state::{AppState, Capabilities};
 use ropds::web::context;
 
 #[derive(Parser)]
 #[command(name = "ropds", version, about = "Rust OPDS Server")]
+#[command(group(ArgGroup::new("scan_mode").args(["scan", "scan_path"]).multiple(true)))]
 struct Cli {
     /// Path to config file
     #[arg(short, long, default_value = "config.toml")]
@@ -21,6 +24,16 @@ struct Cli {
     #[arg(long)]
     scan: bool,
 
+    /// Run a one-shot scan of a single folder (relative to the library
+    /// root) and exit; only books under that folder can be removed
+    #[arg(long, value_name = "PATH")]
+    scan_path: Option<String>,
+
+    /// With `--scan` or `--scan-path`: report new, missing and changed files
+    /// without modifying the database
+    #[arg(long, requires = "scan_mode")]
+    dry_run: bool,
+
     /// Create or update the admin user password and exit
     #[arg(long)]
     set_admin: Option<String>,
@@ -32,12 +45,101 @@ struct Cli {
     /// Refuses if user data exists without matching sqlx migration metadata.
     #[arg(long)]
     init_db: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check the config, database, paths, external tools, templates and
+    /// translations, print a pass/fail report and exit (status 1 on failure)
+    Doctor,
+    /// Manage user accounts
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+    /// Show or apply schema migrations and exit (with
+    /// `database.auto_migrate = false` the server only starts once they
+    /// are applied)
+    #[command(group(ArgGroup::new("migrate_action").args(["status", "up"]).required(true)))]
+    Migrate {
+        /// List applied, pending and unknown migrations (status 1 when the
+        /// database is newer than this binary)
+        #[arg(long)]
+        status: bool,
+        /// Apply pending migrations
+        #[arg(long)]
+        up: bool,
+    },
+    /// Write a synthetic library (FB2 and EPUB files plus an INPX
+    /// collection) for benchmarks and exit
+    #[cfg(feature = "fixtures")]
+    GenFixtures {
+        /// Number of books
+        #[arg(long, default_value_t = 10_000)]
+        books: usize,
+        /// Output directory (use it as `library.root_path`)
+        #[arg(long, default_value = "fixtures")]
+        out: PathBuf,
+        /// Seed; the same seed always writes the same library
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum UserCommand {
+    /// List accounts whose password hash is a legacy format or uses other
+    /// Argon2 parameters than `[password]`; each is rehashed at the user's
+    /// next login
+    Rehash {
+        /// Also make those users set a new password at their next login
+        #[arg(long)]
+        require_change: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
 
+    // Self-check mode: report every problem instead of exiting on the first
+    if let Some(Command::Doctor) = cli.command {
+        let report = ropds::doctor::run(&cli.config).await;
+        print!("{}", report.render());
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
+
+    #[cfg(feature = "fixtures")]
+    if let Some(Command::GenFixtures { books, out, seed }) = &cli.command {
+        let opts = ropds::fixtures::FixtureOptions {
+            books: *books,
+            seed: *seed,
+            ..Default::default()
+        };
+        match ropds::fixtures::generate(out, &opts) {
+            Ok(s) => {
+                println!(
+                    "{} books in {}: {} FB2, {} EPUB, {} in {} INPX archive(s), {} MB",
+                    books,
+                    out.display(),
+                    s.fb2,
+                    s.epub,
+                    s.inpx,
+                    s.inpx_archives,
+                    s.bytes / (1024 * 1024)
+                );
+                return;
+            }
+            Err(e) => {
+                eprintln!("Failed to write fixtures to {}: {e}", out.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Load configuration
     let mut config = Config::load(&cli.config).unwrap_or_else(|e| {
         eprintln!("Error loading config: {e}");
@@ -60,9 +162,48 @@ async fn main() {
     let writer = std::io::stdout
         .with_min_level(tracing::Level::INFO)
         .and(std::io::stderr.with_max_level(tracing::Level::WARN));
-    tracing_subscriber::fmt()
-        .with_writer(writer)
-        .with_env_filter(filter)
+    // With `server.access_log_path` the OPDS access log goes to its own
+    // daily-rotated file only.
+    let (access_writer, _access_guard) = match &config.server.access_log_path {
+        Some(path) => {
+            let dir = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(std::path::Path::new("."));
+            let appender = tracing_appender::rolling::Builder::new()
+                .rotation(tracing_appender::rolling::Rotation::DAILY)
+                .filename_prefix(path.file_name().unwrap_or_default().to_string_lossy())
+                .build(dir)
+                .unwrap_or_else(|e| {
+                    eprintln!("Cannot open access log {}: {e}", path.display());
+                    std::process::exit(1);
+                });
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(writer), Some(guard))
+        }
+        None => (None, None),
+    };
+    let separate_access_log = access_writer.is_some();
+    // `log_level` applies to the server log only: the access log file gets
+    // every access line whatever the level filter says.
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_filter(filter_fn(move |meta| {
+                    !separate_access_log || meta.target() != ropds::opds::ACCESS_LOG_TARGET
+                }))
+                .with_filter(filter),
+        )
+        .with(access_writer.map(|writer| {
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(filter_fn(|meta| {
+                    meta.target() == ropds::opds::ACCESS_LOG_TARGET
+                        && *meta.level() <= tracing::Level::INFO
+                }))
+        }))
         .init();
 
     // Validate scanner schedule config
@@ -71,22 +212,40 @@ async fn main() {
         std::process::exit(1);
     }
 
+    ropds::tools::configure(&config.tools);
+    ropds::password::configure(&config.password);
+    ropds::formats::configure(&config.formats);
     let pdf_preview_tool_available = ropds::pdf::pdftoppm_available();
     if !pdf_preview_tool_available {
         tracing::warn!(
-            "`pdftoppm` is not available in PATH; PDF cover/thumbnail generation is disabled"
+            "`{}` is not available; PDF covers fall back to embedded JPEG images",
+            config.tools.pdftoppm_path.display()
         );
     }
     let pdf_metadata_tool_available = ropds::pdf::pdfinfo_available();
     if !pdf_metadata_tool_available {
         tracing::warn!(
-            "`pdfinfo` is not available in PATH; PDF metadata extraction (title/author) is disabled"
+            "`{}` is not available; using the built-in PDF metadata reader",
+            config.tools.pdfinfo_path.display()
         );
     }
+    let pdf_text_tool_available = ropds::pdf::pdftotext_available();
+    if !pdf_text_tool_available {
+        tracing::warn!(
+            "`{}` is not available; PDFs get no text preview and are not searchable inside",
+            config.tools.pdftotext_path.display()
+        );
+    }
+    ropds::pdf::configure(ropds::pdf::PdfTools {
+        pdftoppm: pdf_preview_tool_available,
+        pdfinfo: pdf_metadata_tool_available,
+        pdftotext: pdf_text_tool_available,
+    });
     let djvu_preview_tool_available = ropds::djvu::ddjvu_available();
     if !djvu_preview_tool_available {
         tracing::warn!(
-            "`ddjvu` is not available in PATH; DJVU cover/thumbnail generation is disabled"
+            "`{}` is not available; DJVU cover/thumbnail generation is disabled",
+            config.tools.ddjvu_path.display()
         );
     }
 
@@ -107,38 +266,104 @@ async fn main() {
         }
     }
 
-    // Initialize database
-    let pool = ropds::db::create_pool(&config.database)
-        .await
-        .unwrap_or_else(|e| {
-            tracing::error!("Failed to initialize database: {e}");
+    if let Some(Command::Migrate { up, .. }) = cli.command {
+        if up && config.server.read_only {
+            tracing::error!("migrate --up is not available on a read-only mirror");
             std::process::exit(1);
-        });
+        }
+        let result = if up {
+            migrate_up(&config.database).await
+        } else {
+            migrate_status(&config.database).await
+        };
+        match result {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                tracing::error!("Migration failed: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Initialize database (a read-only mirror leaves migrations to the primary)
+    let pool = if config.server.read_only {
+        ropds::db::create_read_only_pool(&config.database).await
+    } else {
+        ropds::db::create_pool(&config.database).await
+    }
+    .unwrap_or_else(|e| {
+        tracing::error!("Failed to initialize database: {e}");
+        std::process::exit(1);
+    });
     tracing::info!(
         "Database initialized: {}",
         ropds::db::redact_database_url(&config.database.url)
     );
 
-    // Ensure covers directory exists
-    if let Err(e) = std::fs::create_dir_all(&config.covers.covers_path) {
-        tracing::error!(
-            "Failed to create covers directory {:?}: {e}",
-            config.covers.covers_path
-        );
-        std::process::exit(1);
+    if let Some(Command::User {
+        command: UserCommand::Rehash { require_change },
+    }) = cli.command
+    {
+        if require_change && config.server.read_only {
+            tracing::error!("--require-change is not available on a read-only mirror");
+            std::process::exit(1);
+        }
+        if let Err(e) = report_outdated_hashes(&pool, require_change).await {
+            tracing::error!("Failed to check password hashes: {e}");
+            std::process::exit(1);
+        }
+        return;
     }
-    let covers_test = config.covers.covers_path.join(".ropds_write_test");
-    match std::fs::File::create(&covers_test) {
-        Ok(_) => {
-            let _ = std::fs::remove_file(&covers_test);
+
+    // Index rows stored before transliteration keys existed (a dry run
+    // leaves the rows alone)
+    if config.search.translit && !cli.dry_run && !config.server.read_only {
+        match ropds::translit::backfill(&pool).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Transliteration search keys computed for {n} rows"),
+            Err(e) => tracing::warn!("Failed to compute transliteration search keys: {e}"),
         }
-        Err(e) => {
+    }
+
+    // Fuzzy search on PostgreSQL prefers the pg_trgm extension
+    let pg_trgm_available =
+        config.search.fuzzy && ropds::db::queries::fuzzy::enable_pg_trgm(&pool).await;
+    if config.search.fuzzy && pool.backend() == ropds::db::DbBackend::Postgres {
+        if pg_trgm_available {
+            tracing::info!("Fuzzy search uses pg_trgm");
+        } else {
+            tracing::warn!(
+                "pg_trgm extension is not available; fuzzy search scores matches in process"
+            );
+        }
+    }
+
+    // Ensure covers directory exists and is writable (a read-only mirror
+    // serves the covers written by the primary)
+    if config.server.read_only {
+        tracing::info!("Read-only mirror: scans, uploads and admin changes are disabled");
+    } else {
+        if let Err(e) = std::fs::create_dir_all(&config.covers.covers_path) {
             tracing::error!(
-                "Covers path '{}' is not writable: {e}",
-                config.covers.covers_path.display()
+                "Failed to create covers directory {:?}: {e}",
+                config.covers.covers_path
             );
             std::process::exit(1);
         }
+        let covers_test = config.covers.covers_path.join(".ropds_write_test");
+        match std::fs::File::create(&covers_test) {
+            Ok(_) => {
+                let _ = std::fs::remove_file(&covers_test);
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Covers path '{}' is not writable: {e}",
+                    config.covers.covers_path.display()
+                );
+                std::process::exit(1);
+            }
+        }
     }
 
     // Validate upload configuration
@@ -196,19 +421,64 @@ async fn main() {
         );
     }
 
+    let scan_path = cli.scan_path.as_deref().unwrap_or_default();
+
+    // One-shot dry-run scan: print the change report and exit
+    if cli.dry_run {
+        match ropds::scanner::preview_scan_path(&pool, &config, scan_path).await {
+            Ok(preview) => {
+                for path in &preview.new_archives {
+                    println!("new archive: {path}");
+                }
+                for path in &preview.changed_archives {
+                    println!("changed archive: {path}");
+                }
+                for path in &preview.new_books {
+                    println!("new: {path}");
+                }
+                for path in &preview.missing_books {
+                    println!("missing: {path}");
+                }
+                for path in &preview.skipped_paths {
+                    println!("skipped: {path}");
+                }
+                tracing::info!(
+                    "Dry run finished: new={}, missing={}, new_archives={}, changed_archives={}, errors={}",
+                    preview.new_books.len(),
+                    preview.missing_books.len(),
+                    preview.new_archives.len(),
+                    preview.changed_archives.len(),
+                    preview.errors,
+                );
+            }
+            Err(e) => {
+                tracing::error!("Dry run failed: {e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     // One-shot scan mode
-    if cli.scan {
+    if cli.scan || cli.scan_path.is_some() {
+        if config.server.read_only {
+            tracing::error!("Scanning is disabled on a read-only mirror");
+            std::process::exit(1);
+        }
         tracing::info!("Running one-shot scan...");
-        match ropds::scanner::run_scan(&pool, &config).await {
+        let observers = ropds::scanner::ScanObservers::from_config(&config);
+        match ropds::scanner::run_scan_path_observed(&pool, &config, scan_path, &observers).await {
             Ok(stats) => {
                 tracing::info!(
-                    "Scan finished: added={}, skipped={}, deleted={}, archives_scanned={}, archives_skipped={}, errors={}",
+                    "Scan finished: added={}, updated={}, skipped={}, deleted={}, archives_scanned={}, archives_skipped={}, errors={}, paths_skipped={}",
                     stats.books_added,
+                    stats.books_updated,
                     stats.books_skipped,
                     stats.books_deleted,
                     stats.archives_scanned,
                     stats.archives_skipped,
                     stats.errors,
+                    stats.paths_skipped,
                 );
             }
             Err(e) => {
@@ -221,8 +491,25 @@ async fn main() {
 
     // Set admin password mode
     if let Some(ref password) = cli.set_admin {
-        if password.len() < 8 || password.len() > 32 {
-            tracing::error!("Password must be 8 to 32 characters long");
+        if config.server.read_only {
+            tracing::error!("Admin changes are disabled on a read-only mirror");
+            std::process::exit(1);
+        }
+        if let Err(violation) = ropds::password::check_policy(&config.password, password) {
+            let cfg = &config.password;
+            match violation {
+                ropds::password::PolicyViolation::Length => tracing::error!(
+                    "Password must be {} to {} characters long",
+                    cfg.min_length,
+                    cfg.max_length
+                ),
+                ropds::password::PolicyViolation::MissingClass => {
+                    tracing::error!("Password must contain each of {:?}", cfg.required_classes)
+                }
+                ropds::password::PolicyViolation::Common => {
+                    tracing::error!("Password is too common")
+                }
+            }
             std::process::exit(1);
         }
         match set_admin_password(&pool, password).await {
@@ -272,20 +559,34 @@ async fn main() {
     );
 
     tracing::info!("ropds v{}", env!("CARGO_PKG_VERSION"));
-    tracing::info!("Library root: {}", config.library.root_path.display());
+    tracing::info!(
+        "Library root: {}",
+        ropds::vfs::display_root(&config.library.root_path)
+    );
     tracing::info!("Listening on {addr}");
 
-    // Start background scan scheduler
-    tokio::spawn(ropds::scheduler::run(pool.clone(), config.clone()));
-
     let state = AppState::new(
-        config,
-        pool,
+        config.clone(),
+        pool.clone(),
         tera,
         translations,
-        pdf_preview_tool_available,
-        djvu_preview_tool_available,
+        Capabilities {
+            pdf_preview_tool: pdf_preview_tool_available,
+            pdf_metadata_tool: pdf_metadata_tool_available,
+            pdf_text_tool: pdf_text_tool_available,
+            djvu_preview_tool: djvu_preview_tool_available,
+            pg_trgm: pg_trgm_available,
+        },
     );
+
+    // Start background scan scheduler (the primary scans for a mirror)
+    if !config.server.read_only {
+        tokio::spawn(ropds::scheduler::run(
+            pool,
+            config,
+            state.scan_observers.clone(),
+        ));
+    }
     let app = build_router(state);
 
     let listener = tokio::net::TcpListener::bind(addr)
@@ -306,7 +607,74 @@ async fn main() {
     });
 }
 
-/// Create the admin user or update its password.
+/// Print the users whose password hash is outdated and, with
+/// `require_change`, ask them for a new password at the next login.
+async fn report_outdated_hashes(
+    pool: &ropds::db::DbPool,
+    require_change: bool,
+) -> Result<(), sqlx::Error> {
+    let mut outdated = 0;
+    for (id, username, hash) in ropds::db::queries::users::password_hashes(pool).await? {
+        if hash.is_empty() || !ropds::password::needs_rehash(&hash) {
+            continue;
+        }
+        outdated += 1;
+        let scheme = hash
+            .trim_start_matches('$')
+            .split(['$', ','])
+            .next()
+            .unwrap_or_default();
+        println!("{username} (id {id}): {scheme}");
+        if require_change {
+            ropds::db::queries::users::require_password_change(pool, id).await?;
+        }
+    }
+    match (outdated, require_change) {
+        (0, _) => println!("All password hashes are current."),
+        (n, false) => {
+            println!("{n} outdated password hash(es); they are upgraded at the next login.")
+        }
+        (n, true) => println!("{n} user(s) must set a new password at the next login."),
+    }
+    Ok(())
+}
+
+/// Print the migration state; `Ok(false)` when the database is newer than
+/// this binary.
+async fn migrate_status(config: &ropds::config::DatabaseConfig) -> Result<bool, sqlx::Error> {
+    let pool = ropds::db::connect(config).await?;
+    let status = ropds::db::migration_status(&pool).await?;
+    println!("{} migration(s) applied.", status.applied);
+    for name in &status.pending {
+        println!("pending: {name}");
+    }
+    for name in &status.unknown {
+        println!("unknown: {name}");
+    }
+    if !status.unknown.is_empty() {
+        println!("The database was migrated by a newer ropds; upgrade this binary.");
+        return Ok(false);
+    }
+    if status.pending.is_empty() {
+        println!("The schema is up to date.");
+    }
+    Ok(true)
+}
+
+/// Apply pending migrations and list them.
+async fn migrate_up(config: &ropds::config::DatabaseConfig) -> Result<bool, sqlx::Error> {
+    let applied = ropds::db::migrate_up(config).await?;
+    for name in &applied {
+        println!("applied: {name}");
+    }
+    match applied.len() {
+        0 => println!("The schema is up to date."),
+        n => println!("{n} migration(s) applied."),
+    }
+    Ok(true)
+}
+
+/// Create the admin user or update its password, re-enabling a disabled account.
 /// Returns `Ok(true)` if a new user was created, `Ok(false)` if updated.
 async fn set_admin_password(pool: &ropds::db::DbPool, password: &str) -> Result<bool, sqlx::Error> {
     let existing: Option<(i64,)> = sqlx::query_as("SELECT id FROM users WHERE username = 'admin'")
@@ -316,7 +684,7 @@ async fn set_admin_password(pool: &ropds::db::DbPool, password: &str) -> Result<
     let hashed = ropds::password::hash(password);
 
     if let Some((id,)) = existing {
-        let sql = pool.sql("UPDATE users SET password_hash = ?, allow_upload = 1, display_name = CASE WHEN display_name = '' THEN 'Administrator' ELSE display_name END WHERE id = ?");
+        let sql = pool.sql("UPDATE users SET password_hash = ?, allow_upload = 1, is_active = 1, display_name = CASE WHEN display_name = '' THEN 'Administrator' ELSE display_name END WHERE id = ?");
         sqlx::query(&sql)
             .bind(&hashed)
             .bind(id)
@@ -0,0 +1,273 @@
+//! Markdown rendering for book annotations.
+//!
+//! [`to_html`] understands the small subset that descriptions need:
+//! paragraphs, line breaks, bullet and numbered lists, `**strong**`,
+//! `*emphasis*`, `` `code` `` and `[links](https://…)`. Everything else is
+//! text: the input is HTML-escaped and links are limited to `http`, `https`
+//! and `mailto`, so the output is safe to embed in web pages and OPDS
+//! content. Annotations read from book files are plain lines and come out
+//! as one paragraph with a line break per line.
+
+/// Render annotation markdown as sanitized HTML.
+pub fn to_html(text: &str) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut list: Option<(ListKind, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            flush_paragraph(&mut out, &mut paragraph);
+            flush_list(&mut out, &mut list);
+            continue;
+        }
+        if let Some((kind, item)) = list_item(line) {
+            flush_paragraph(&mut out, &mut paragraph);
+            match list {
+                Some((current, ref mut items)) if current == kind => items.push(item),
+                _ => {
+                    flush_list(&mut out, &mut list);
+                    list = Some((kind, vec![item]));
+                }
+            }
+        } else {
+            flush_list(&mut out, &mut list);
+            paragraph.push(line.trim_start());
+        }
+    }
+    flush_paragraph(&mut out, &mut paragraph);
+    flush_list(&mut out, &mut list);
+    out
+}
+
+/// Render plain text as escaped HTML, one paragraph per non-empty line and
+/// no markup. Used for text taken verbatim from book bodies, where dialogue
+/// dashes and asterisks must not turn into lists or emphasis.
+pub fn plain_to_html(text: &str) -> String {
+    let mut out = String::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        out.push_str("<p>");
+        push_escaped(&mut out, line);
+        out.push_str("</p>");
+    }
+    out
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ListKind {
+    Bullet,
+    Numbered,
+}
+
+/// The kind and text of a list item line (`- item`, `* item`, `1. item`).
+fn list_item(line: &str) -> Option<(ListKind, &str)> {
+    let line = line.trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return Some((ListKind::Bullet, rest.trim_start()));
+        }
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if (1..=9).contains(&digits) {
+        let rest = &line[digits..];
+        if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((ListKind::Numbered, rest.trim_start()));
+        }
+    }
+    None
+}
+
+fn flush_paragraph(out: &mut String, lines: &mut Vec<&str>) {
+    if lines.is_empty() {
+        return;
+    }
+    out.push_str("<p>");
+    for (i, line) in lines.drain(..).enumerate() {
+        if i > 0 {
+            out.push_str("<br>");
+        }
+        out.push_str(&render_inline(line));
+    }
+    out.push_str("</p>");
+}
+
+fn flush_list(out: &mut String, list: &mut Option<(ListKind, Vec<&str>)>) {
+    let Some((kind, items)) = list.take() else {
+        return;
+    };
+    let tag = match kind {
+        ListKind::Bullet => "ul",
+        ListKind::Numbered => "ol",
+    };
+    out.push_str(&format!("<{tag}>"));
+    for item in items {
+        out.push_str("<li>");
+        out.push_str(&render_inline(item));
+        out.push_str("</li>");
+    }
+    out.push_str(&format!("</{tag}>"));
+}
+
+/// Render emphasis, code spans and links within one line.
+fn render_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\'
+            && let Some(next) = rest[1..].chars().next()
+            && next.is_ascii_punctuation()
+        {
+            push_escaped(&mut out, &rest[1..1 + next.len_utf8()]);
+            rest = &rest[1 + next.len_utf8()..];
+            continue;
+        }
+        if c == '`'
+            && let Some(end) = rest[1..].find('`')
+        {
+            out.push_str("<code>");
+            push_escaped(&mut out, &rest[1..1 + end]);
+            out.push_str("</code>");
+            rest = &rest[end + 2..];
+            continue;
+        }
+        if let Some((html, len)) = emphasis(rest).or_else(|| link(rest)) {
+            out.push_str(&html);
+            rest = &rest[len..];
+            continue;
+        }
+        push_escaped(&mut out, &rest[..c.len_utf8()]);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// `**strong**`, `__strong__`, `*em*` or `_em_` at the start of `text`, as
+/// `(html, consumed bytes)`.
+fn emphasis(text: &str) -> Option<(String, usize)> {
+    for (delim, tag) in [("**", "strong"), ("__", "strong"), ("*", "em"), ("_", "em")] {
+        let Some(inner_start) = text.strip_prefix(delim) else {
+            continue;
+        };
+        // Opening delimiters must hug the text: `2 * 3 * 4` stays as is.
+        if inner_start.starts_with(char::is_whitespace) || inner_start.starts_with(delim) {
+            continue;
+        }
+        let end = inner_start.find(delim)?;
+        let inner = &inner_start[..end];
+        if inner.is_empty() || inner.ends_with(char::is_whitespace) {
+            continue;
+        }
+        // `snake_case_names` are not emphasis.
+        let after = inner_start[end + delim.len()..].chars().next();
+        if delim.starts_with('_') && after.is_some_and(char::is_alphanumeric) {
+            continue;
+        }
+        let html = format!("<{tag}>{}</{tag}>", render_inline(inner));
+        return Some((html, delim.len() + end + delim.len()));
+    }
+    None
+}
+
+/// `[text](url)` at the start of `text`, as `(html, consumed bytes)`.
+/// Links to other schemes are left as text.
+fn link(text: &str) -> Option<(String, usize)> {
+    let label_end = text.strip_prefix('[')?.find("](")? + 1;
+    let url_start = label_end + 2;
+    let url_len = text[url_start..].find(')')?;
+    let label = &text[1..label_end];
+    let url = text[url_start..url_start + url_len].trim();
+    let allowed = ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.len() > scheme.len() && url[..scheme.len()].eq_ignore_ascii_case(scheme));
+    if !allowed || label.is_empty() {
+        return None;
+    }
+    let mut html = String::from("<a href=\"");
+    push_escaped(&mut html, url);
+    html.push_str("\" rel=\"nofollow noopener\">");
+    html.push_str(&render_inline(label));
+    html.push_str("</a>");
+    Some((html, url_start + url_len + 1))
+}
+
+fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_lines_become_one_paragraph() {
+        assert_eq!(to_html("Line one\nLine two"), "<p>Line one<br>Line two</p>");
+        assert_eq!(to_html("First\n\n  Second  "), "<p>First</p><p>Second</p>");
+        assert_eq!(to_html(""), "");
+    }
+
+    #[test]
+    fn test_plain_to_html_escapes_without_markup() {
+        assert_eq!(
+            plain_to_html("- Hi, *you* <b>\n\n  Bye & go"),
+            "<p>- Hi, *you* &lt;b&gt;</p><p>Bye &amp; go</p>"
+        );
+        assert_eq!(plain_to_html(""), "");
+    }
+
+    #[test]
+    fn test_inline_markup() {
+        assert_eq!(
+            to_html("A **bold** and *light* `x<y` word"),
+            "<p>A <strong>bold</strong> and <em>light</em> <code>x&lt;y</code> word</p>"
+        );
+        assert_eq!(
+            to_html("**nested *em* here**"),
+            "<p><strong>nested <em>em</em> here</strong></p>"
+        );
+        assert_eq!(to_html("2 * 3 * 4"), "<p>2 * 3 * 4</p>");
+        assert_eq!(to_html("snake_case_name"), "<p>snake_case_name</p>");
+        assert_eq!(to_html(r"\*not em\*"), "<p>*not em*</p>");
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            to_html("Intro\n- one\n- two\n\n1. first\n2) second"),
+            "<p>Intro</p><ul><li>one</li><li>two</li></ul><ol><li>first</li><li>second</li></ol>"
+        );
+    }
+
+    #[test]
+    fn test_links_are_limited_to_safe_schemes() {
+        assert_eq!(
+            to_html("[site](https://example.com/?a=1&b=\"2\")"),
+            "<p><a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\" \
+             rel=\"nofollow noopener\">site</a></p>"
+        );
+        assert_eq!(
+            to_html("[x](javascript:alert(1))"),
+            "<p>[x](javascript:alert(1))</p>"
+        );
+    }
+
+    #[test]
+    fn test_html_is_escaped() {
+        assert_eq!(
+            to_html("<script>alert('x')</script> & <b>"),
+            "<p>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; &lt;b&gt;</p>"
+        );
+        assert_eq!(
+            to_html("**<img src=x onerror=y>**"),
+            "<p><strong>&lt;img src=x onerror=y&gt;</strong></p>"
+        );
+    }
+}
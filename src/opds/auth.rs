@@ -6,14 +6,22 @@ use base64::Engine;
 
 use crate::state::AppState;
 
+/// The user an OPDS request authenticated as, attached to the request's
+/// extensions for inner layers and to the response's for the request log.
+#[derive(Debug, Clone)]
+pub struct OpdsUser {
+    pub id: i64,
+    pub username: String,
+}
+
 /// Axum middleware layer for HTTP Basic Authentication.
 ///
 /// When `config.opds.auth_required` is true, all OPDS requests must
 /// carry a valid `Authorization: Basic ...` header. Credentials are
-/// checked against the `users` table.
+/// checked against the `users` table; disabled accounts are refused.
 pub async fn basic_auth_layer(
     state: axum::extract::State<AppState>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Response {
     if !state.config.opds.auth_required {
@@ -44,25 +52,43 @@ pub async fn basic_auth_layer(
 
             // Check credentials against DB
             match verify_credentials(&state.db, username, password).await {
-                true => next.run(request).await,
-                false => unauthorized_response(),
+                Some(id) => {
+                    let user = OpdsUser {
+                        id,
+                        username: username.to_string(),
+                    };
+                    request.extensions_mut().insert(user.clone());
+                    let mut response = next.run(request).await;
+                    response.extensions_mut().insert(user);
+                    response
+                }
+                None => unauthorized_response(),
             }
         }
         _ => unauthorized_response(),
     }
 }
 
-/// Verify username/password against the users table using argon2.
-async fn verify_credentials(pool: &crate::db::DbPool, username: &str, password: &str) -> bool {
-    let result: Result<Option<(String,)>, _> =
-        sqlx::query_as(&pool.sql("SELECT password_hash FROM users WHERE username = ?"))
-            .bind(username)
-            .fetch_optional(pool.inner())
-            .await;
+/// Verify username/password against the users table, upgrading an outdated
+/// password hash on success. Returns the user's ID.
+async fn verify_credentials(
+    pool: &crate::db::DbPool,
+    username: &str,
+    password: &str,
+) -> Option<i64> {
+    let result: Result<Option<(i64, String)>, _> = sqlx::query_as(
+        &pool.sql("SELECT id, password_hash FROM users WHERE username = ? AND is_active = 1"),
+    )
+    .bind(username)
+    .fetch_optional(pool.inner())
+    .await;
 
     match result {
-        Ok(Some((stored_hash,))) => crate::password::verify(password, &stored_hash),
-        _ => false,
+        Ok(Some((user_id, stored_hash))) if crate::password::verify(password, &stored_hash) => {
+            crate::password::rehash_if_needed(pool, user_id, password, &stored_hash).await;
+            Some(user_id)
+        }
+        _ => None,
     }
 }
 
@@ -86,11 +112,12 @@ pub async fn get_user_id_from_headers(
     let credentials = String::from_utf8(decoded).ok()?;
     let (username, password) = credentials.split_once(':')?;
 
-    let result: Result<Option<(i64, String)>, _> =
-        sqlx::query_as(&pool.sql("SELECT id, password_hash FROM users WHERE username = ?"))
-            .bind(username)
-            .fetch_optional(pool.inner())
-            .await;
+    let result: Result<Option<(i64, String)>, _> = sqlx::query_as(
+        &pool.sql("SELECT id, password_hash FROM users WHERE username = ? AND is_active = 1"),
+    )
+    .bind(username)
+    .fetch_optional(pool.inner())
+    .await;
     match result.ok().flatten() {
         Some((id, hash)) if crate::password::verify(password, &hash) => Some(id),
         _ => None,
@@ -133,9 +160,17 @@ mod tests {
         .await
         .unwrap();
 
-        assert!(verify_credentials(&pool, "alice", "secret123").await);
-        assert!(!verify_credentials(&pool, "alice", "wrong").await);
-        assert!(!verify_credentials(&pool, "missing", "secret123").await);
+        assert!(
+            verify_credentials(&pool, "alice", "secret123")
+                .await
+                .is_some()
+        );
+        assert!(verify_credentials(&pool, "alice", "wrong").await.is_none());
+        assert!(
+            verify_credentials(&pool, "missing", "secret123")
+                .await
+                .is_none()
+        );
 
         let mut headers = HeaderMap::new();
         headers.insert(
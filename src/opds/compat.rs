@@ -0,0 +1,465 @@
+//! Client compatibility profiles: output tweaks for OPDS readers that choke on
+//! parts of standard feeds.
+//!
+//! A profile is picked per request from the User-Agent (`[clients.<name>]`
+//! overrides and extends the built-in ones) unless the signed-in user chose
+//! one on their profile page. Feeds read the active profile through
+//! [`current`] while the request is handled. With `opds.absolute_urls` every
+//! client gets absolute links, as if its profile asked for them.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+
+use crate::config::{ClientProfileConfig, OpdsConfig};
+use crate::state::AppState;
+
+/// Per-user setting that turns profile detection off.
+pub const PROFILE_NONE: &str = "none";
+
+/// Output tweaks for one client. The default changes nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientProfile {
+    /// Profile name; empty when no profile applies.
+    pub name: String,
+    /// Leave out facet links.
+    pub hide_facets: bool,
+    /// Prefix every feed link with this base URL (no trailing slash).
+    pub link_base: Option<String>,
+    /// List the zipped download before the original file.
+    pub prefer_zip: bool,
+    /// Smaller page size than `opds.max_items`.
+    pub max_items: Option<u32>,
+}
+
+impl ClientProfile {
+    /// `href` as the client should see it: site-relative links get the
+    /// profile's base URL.
+    pub fn href<'a>(&self, href: &'a str) -> Cow<'a, str> {
+        match &self.link_base {
+            Some(base) if href.starts_with('/') => Cow::Owned(format!("{base}{href}")),
+            _ => Cow::Borrowed(href),
+        }
+    }
+
+    /// Page size for a configured `max_items`.
+    pub fn page_size(&self, max_items: u32) -> u32 {
+        self.max_items.map_or(max_items, |max| max.min(max_items))
+    }
+
+    /// Apply the profile to an OPDS 2.0 document: drop its facets and make
+    /// every `href` absolute.
+    pub fn apply_json(&self, body: &mut Value) {
+        if self.hide_facets
+            && let Value::Object(map) = body
+        {
+            map.remove("facets");
+        }
+        if self.link_base.is_some() {
+            self.absolutize_json(body);
+        }
+    }
+
+    fn absolutize_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, v) in map.iter_mut() {
+                    match v {
+                        Value::String(href) if key == "href" => {
+                            if let Cow::Owned(abs) = self.href(href) {
+                                *href = abs;
+                            }
+                        }
+                        _ => self.absolutize_json(v),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|v| self.absolutize_json(v)),
+            _ => {}
+        }
+    }
+}
+
+/// A profile definition: the User-Agent substrings it matches and its tweaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition {
+    user_agents: Vec<String>,
+    facets: bool,
+    absolute_urls: bool,
+    prefer_zip: bool,
+    max_items: Option<u32>,
+}
+
+/// Built-in profiles: name, User-Agent substrings, facets, absolute URLs,
+/// zipped download first, page size.
+#[allow(clippy::type_complexity)]
+const BUILTIN: &[(&str, &[&str], bool, bool, bool, Option<u32>)] = &[
+    ("alreader", &["AlReader"], false, false, true, Some(30)),
+    ("kybook", &["KyBook"], false, false, false, None),
+    (
+        "moonreader",
+        &["Moon+", "MoonReader"],
+        true,
+        true,
+        true,
+        None,
+    ),
+    ("pocketbook", &["PocketBook"], false, true, true, Some(25)),
+];
+
+/// Built-in profiles with `[clients]` overrides applied, by name.
+fn definitions(overrides: &BTreeMap<String, ClientProfileConfig>) -> BTreeMap<String, Definition> {
+    let mut defs: BTreeMap<String, Definition> = BUILTIN
+        .iter()
+        .map(
+            |(name, agents, facets, absolute_urls, prefer_zip, max_items)| {
+                let def = Definition {
+                    user_agents: agents.iter().map(|a| a.to_string()).collect(),
+                    facets: *facets,
+                    absolute_urls: *absolute_urls,
+                    prefer_zip: *prefer_zip,
+                    max_items: *max_items,
+                };
+                (name.to_string(), def)
+            },
+        )
+        .collect();
+    for (name, cfg) in overrides {
+        let def = defs.entry(name.to_lowercase()).or_insert(Definition {
+            user_agents: Vec::new(),
+            facets: true,
+            absolute_urls: false,
+            prefer_zip: false,
+            max_items: None,
+        });
+        if let Some(agents) = &cfg.user_agent {
+            def.user_agents = agents.clone();
+        }
+        if let Some(facets) = cfg.facets {
+            def.facets = facets;
+        }
+        if let Some(absolute_urls) = cfg.absolute_urls {
+            def.absolute_urls = absolute_urls;
+        }
+        if let Some(prefer_zip) = cfg.prefer_zip {
+            def.prefer_zip = prefer_zip;
+        }
+        if let Some(max_items) = cfg.max_items {
+            def.max_items = (max_items > 0).then_some(max_items);
+        }
+    }
+    defs
+}
+
+/// Names of the known profiles, for the profile page selector.
+pub fn profile_names(overrides: &BTreeMap<String, ClientProfileConfig>) -> Vec<String> {
+    definitions(overrides).into_keys().collect()
+}
+
+/// Profile for a request from `user_agent`. `user_choice` is the user's
+/// setting: empty detects the profile from the User-Agent, [`PROFILE_NONE`]
+/// applies none, anything else names a profile.
+pub fn resolve(
+    overrides: &BTreeMap<String, ClientProfileConfig>,
+    base_url: &str,
+    user_agent: &str,
+    user_choice: &str,
+) -> ClientProfile {
+    let defs = definitions(overrides);
+    let user_agent = user_agent.to_lowercase();
+    let found = match user_choice {
+        PROFILE_NONE => None,
+        "" => defs.iter().find(|(_, def)| {
+            def.user_agents
+                .iter()
+                .any(|a| !a.is_empty() && user_agent.contains(&a.to_lowercase()))
+        }),
+        name => defs.get_key_value(name),
+    };
+    let Some((name, def)) = found else {
+        return ClientProfile::default();
+    };
+    ClientProfile {
+        name: name.clone(),
+        hide_facets: !def.facets,
+        link_base: Some(base_url.trim_end_matches('/'))
+            .filter(|base| def.absolute_urls && !base.is_empty())
+            .map(str::to_string),
+        prefer_zip: def.prefer_zip,
+        max_items: def.max_items,
+    }
+}
+
+/// Base URL of the server as the client reached it, without a trailing
+/// slash: `base_url` with its scheme and host replaced by those of a
+/// reverse proxy's `Forwarded` header (or `X-Forwarded-Proto` and
+/// `X-Forwarded-Host`). The path of `base_url` is kept as the mount prefix.
+/// The headers are ignored unless `trust_proxy` is set.
+pub fn external_base(base_url: &str, trust_proxy: bool, headers: &HeaderMap) -> String {
+    let base = base_url.trim_end_matches('/');
+    if !trust_proxy {
+        return base.to_string();
+    }
+    let Ok(url) = reqwest::Url::parse(base) else {
+        return base.to_string();
+    };
+    let (mut proto, mut host) = (None, None);
+    if let Some(forwarded) = header_value(headers, "forwarded") {
+        // Only the element added by the proxy closest to the client counts
+        let first = forwarded.split(',').next().unwrap_or_default();
+        for pair in first.split(';') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"');
+            match key.trim().to_ascii_lowercase().as_str() {
+                "proto" => proto = Some(value.to_string()),
+                "host" => host = Some(value.to_string()),
+                _ => {}
+            }
+        }
+    } else {
+        let first = |name| {
+            header_value(headers, name)
+                .map(|v| v.split(',').next().unwrap_or_default().trim().to_string())
+        };
+        proto = first("x-forwarded-proto");
+        host = first("x-forwarded-host");
+    }
+
+    let valid_host = |h: &String| {
+        !h.is_empty()
+            && h.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'))
+    };
+    let Some(host) = host.filter(valid_host) else {
+        return base.to_string();
+    };
+    let proto = proto
+        .map(|p| p.to_ascii_lowercase())
+        .filter(|p| p == "http" || p == "https")
+        .unwrap_or_else(|| url.scheme().to_string());
+    format!("{proto}://{host}{}", url.path().trim_end_matches('/'))
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+tokio::task_local! {
+    static CURRENT: ClientProfile;
+}
+
+/// Profile of the request being handled (no tweaks outside OPDS requests).
+pub fn current() -> ClientProfile {
+    CURRENT.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// Feed page size for the current client.
+pub fn max_items(opds: &OpdsConfig) -> u32 {
+    current().page_size(opds.max_items)
+}
+
+/// Middleware: pick the client profile of an OPDS request and make it
+/// [`current`] while the request is handled. Runs inside the auth layer, so
+/// a signed-in user's own choice is known.
+pub async fn client_profile_layer(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let user_agent = request
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let user_choice = match request.extensions().get::<super::auth::OpdsUser>() {
+        Some(user) => crate::db::queries::users::opds_client(&state.db, user.id)
+            .await
+            .unwrap_or_default(),
+        None => String::new(),
+    };
+    let server = &state.config.server;
+    let base = external_base(
+        &server.base_url,
+        server.trust_proxy_headers,
+        request.headers(),
+    );
+    let mut profile = resolve(&state.config.clients, &base, &user_agent, &user_choice);
+    if state.config.opds.absolute_urls && !base.is_empty() {
+        profile.link_base = Some(base);
+    }
+    if !profile.name.is_empty() {
+        tracing::debug!("OPDS client profile {:?} for {user_agent:?}", profile.name);
+    }
+    CURRENT.scope(profile, next.run(request)).await
+}
+
+/// Run `f` with `profile` as the current one (tests).
+#[cfg(test)]
+pub fn with_profile<T>(profile: ClientProfile, f: impl FnOnce() -> T) -> T {
+    CURRENT.sync_scope(profile, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BASE: &str = "https://books.example.com/";
+
+    #[test]
+    fn test_resolve_by_user_agent() {
+        let none = BTreeMap::new();
+        let pb = resolve(&none, BASE, "Mozilla/5.0 PocketBook/740 (Linux)", "");
+        assert_eq!(pb.name, "pocketbook");
+        assert!(pb.hide_facets);
+        assert_eq!(pb.link_base.as_deref(), Some("https://books.example.com"));
+        assert_eq!(pb.max_items, Some(25));
+
+        assert_eq!(
+            resolve(&none, BASE, "moon+ reader pro", "").name,
+            "moonreader"
+        );
+        assert_eq!(
+            resolve(&none, BASE, "KOReader/2024.04", ""),
+            ClientProfile::default()
+        );
+        assert_eq!(
+            resolve(&none, BASE, "PocketBook", PROFILE_NONE),
+            ClientProfile::default()
+        );
+        assert_eq!(resolve(&none, BASE, "KOReader", "kybook").name, "kybook");
+        assert_eq!(
+            resolve(&none, BASE, "KOReader", "unknown"),
+            ClientProfile::default()
+        );
+    }
+
+    #[test]
+    fn test_config_overrides_and_adds_profiles() {
+        let overrides = BTreeMap::from([
+            (
+                "pocketbook".to_string(),
+                ClientProfileConfig {
+                    facets: Some(true),
+                    max_items: Some(0),
+                    ..Default::default()
+                },
+            ),
+            (
+                "koreader".to_string(),
+                ClientProfileConfig {
+                    user_agent: Some(vec!["KOReader".to_string()]),
+                    prefer_zip: Some(true),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let pb = resolve(&overrides, BASE, "PocketBook/626", "");
+        assert!(!pb.hide_facets);
+        assert_eq!(pb.max_items, None);
+        assert!(pb.link_base.is_some());
+
+        let ko = resolve(&overrides, BASE, "KOReader/2024.04", "");
+        assert_eq!(ko.name, "koreader");
+        assert!(ko.prefer_zip && !ko.hide_facets && ko.link_base.is_none());
+        assert!(profile_names(&overrides).contains(&"koreader".to_string()));
+    }
+
+    #[test]
+    fn test_links_and_facets() {
+        let plain = ClientProfile::default();
+        assert_eq!(plain.href("/opds/"), "/opds/");
+        let pb = resolve(&BTreeMap::new(), BASE, "PocketBook", "");
+        assert_eq!(pb.href("/opds/"), "https://books.example.com/opds/");
+        assert_eq!(
+            pb.href("https://cdn.example.com/logo.png"),
+            "https://cdn.example.com/logo.png"
+        );
+
+        let mut body = json!({
+            "links": [{ "href": "/opds/v2/", "rel": "self" }],
+            "facets": [{ "links": [{ "href": "/opds/v2/?sort=title" }] }],
+            "publications": [{ "images": [{ "href": "/opds/cover/1/" }] }]
+        });
+        pb.apply_json(&mut body);
+        assert!(body.get("facets").is_none());
+        assert_eq!(
+            body["links"][0]["href"],
+            "https://books.example.com/opds/v2/"
+        );
+        assert_eq!(body["links"][0]["rel"], "self");
+        assert_eq!(
+            body["publications"][0]["images"][0]["href"],
+            "https://books.example.com/opds/cover/1/"
+        );
+        assert_eq!(
+            resolve(&BTreeMap::new(), "", "PocketBook", "").link_base,
+            None
+        );
+    }
+
+    #[test]
+    fn test_external_base() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            external_base(BASE, true, &headers),
+            "https://books.example.com"
+        );
+
+        headers.insert("x-forwarded-proto", "http".parse().unwrap());
+        headers.insert(
+            "x-forwarded-host",
+            "lan.example:8080, inner".parse().unwrap(),
+        );
+        assert_eq!(
+            external_base("https://books.example.com/library/", true, &headers),
+            "http://lan.example:8080/library"
+        );
+
+        // `Forwarded` wins over the X-Forwarded-* headers
+        headers.insert(
+            "forwarded",
+            "for=192.0.2.1;proto=https;host=\"opds.example.org\", for=10.0.0.1"
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(
+            external_base(BASE, true, &headers),
+            "https://opds.example.org"
+        );
+        // Without a trusted proxy the headers are the client's word only
+        assert_eq!(
+            external_base(BASE, false, &headers),
+            "https://books.example.com"
+        );
+
+        headers.insert("forwarded", "host=\"evil.example/path\"".parse().unwrap());
+        assert_eq!(
+            external_base(BASE, true, &headers),
+            "https://books.example.com"
+        );
+    }
+
+    #[test]
+    fn test_max_items_is_capped_by_config() {
+        assert_eq!(ClientProfile::default().page_size(20), 20);
+        let small = ClientProfile {
+            max_items: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(small.page_size(20), 5);
+        let large = ClientProfile {
+            max_items: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(large.page_size(20), 20);
+    }
+}
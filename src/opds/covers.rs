@@ -1,30 +1,242 @@
 use std::io::{BufReader, Cursor};
 
-use axum::extract::{Path, State};
-use axum::http::{StatusCode, header};
+use std::collections::HashMap;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
-use image::imageops::FilterType;
+use axum_extra::extract::cookie::CookieJar;
+use hmac::{Hmac, KeyInit, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 
-use crate::config::CoverImageConfig;
+use crate::config::{Config, CoverImageConfig};
 use crate::db::models;
 use crate::db::queries::books;
 use crate::state::AppState;
 
-const THUMB_SIZE: u32 = 200;
+/// Bounding box of `/opds/thumb/` thumbnails, in pixels.
+pub const THUMB_SIZE: u32 = 200;
 const THUMB_JPEG_QUALITY: u8 = 85;
-const NOCOVER_SVG: &[u8] = include_bytes!("../../static/images/nocover.svg");
+/// Placeholders change only when a book gets a cover or is renamed, so they
+/// are revalidated against their ETag rather than cached for long.
+const PLACEHOLDER_CACHE_CONTROL: &str = "no-cache";
+
+/// Cover and thumbnail links of books, signed when `covers.signed_urls`
+/// keeps the covers of a private library from anonymous requests.
+#[derive(Debug, Clone, Default)]
+pub struct CoverUrls {
+    /// Signing key; `None` when links are not signed.
+    key: Option<Vec<u8>>,
+    ttl_secs: i64,
+}
+
+impl CoverUrls {
+    pub fn new(config: &Config) -> Self {
+        let signed = config.covers.signed_urls && config.opds.auth_required;
+        Self {
+            key: signed.then(|| config.server.session_secret.as_bytes().to_vec()),
+            ttl_secs: (config.covers.signed_url_ttl_mins * 60).max(1) as i64,
+        }
+    }
+
+    /// Whether cover requests must be authenticated or signed.
+    pub fn enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// Current signing period, or `None` when links are not signed. Links
+    /// rendered in the same period are identical.
+    pub fn period(&self) -> Option<i64> {
+        self.key
+            .as_ref()
+            .map(|_| chrono::Utc::now().timestamp() / self.ttl_secs)
+    }
+
+    /// `/opds/cover/` link of a book.
+    pub fn cover(&self, book_id: i64) -> String {
+        format!("/opds/cover/{book_id}/{}", self.query(book_id))
+    }
+
+    /// `/opds/thumb/` link of a book.
+    pub fn thumb(&self, book_id: i64) -> String {
+        format!("/opds/thumb/{book_id}/{}", self.query(book_id))
+    }
+
+    /// `/opds/catalog-thumb/` link of a catalog's cover mosaic.
+    pub fn catalog_thumb(&self, catalog_id: i64) -> String {
+        format!(
+            "/opds/catalog-thumb/{catalog_id}/{}",
+            self.signed_query(&format!("catalog:{catalog_id}"))
+        )
+    }
+
+    /// `?exp=…&sig=…` authorizing the cover links of a book, or empty when
+    /// links are not signed. The expiry is rounded up to whole TTL periods,
+    /// so links stay the same (and cacheable) for a while.
+    pub fn query(&self, book_id: i64) -> String {
+        self.signed_query(&format!("cover:{book_id}"))
+    }
+
+    /// Whether `exp` and `sig` are an unexpired signature of the book's links.
+    pub fn verify(&self, book_id: i64, exp: i64, sig: &str) -> bool {
+        self.verify_subject(&format!("cover:{book_id}"), exp, sig)
+    }
+
+    /// Whether `exp` and `sig` are an unexpired signature of the catalog's
+    /// mosaic link.
+    pub fn verify_catalog(&self, catalog_id: i64, exp: i64, sig: &str) -> bool {
+        self.verify_subject(&format!("catalog:{catalog_id}"), exp, sig)
+    }
+
+    fn signed_query(&self, subject: &str) -> String {
+        let Some(key) = &self.key else {
+            return String::new();
+        };
+        let period = chrono::Utc::now().timestamp() / self.ttl_secs;
+        let exp = (period + 2) * self.ttl_secs;
+        let sig = hex::encode(signature(key, subject, exp).finalize().into_bytes());
+        format!("?exp={exp}&sig={sig}")
+    }
+
+    fn verify_subject(&self, subject: &str, exp: i64, sig: &str) -> bool {
+        let Some(key) = &self.key else {
+            return false;
+        };
+        if exp < chrono::Utc::now().timestamp() {
+            return false;
+        }
+        hex::decode(sig).is_ok_and(|sig| signature(key, subject, exp).verify_slice(&sig).is_ok())
+    }
+}
+
+fn signature(key: &[u8], subject: &str, exp: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(format!("{subject}:{exp}").as_bytes());
+    mac
+}
+
+/// Tera function `cover_url(id=…, thumb=false)`: a book's (signed) cover link.
+/// `cover_url(catalog=…)` is the (signed) link of a catalog's cover mosaic.
+impl tera::Function for CoverUrls {
+    fn call(&self, args: &HashMap<String, tera::Value>) -> tera::Result<tera::Value> {
+        if let Some(catalog_id) = args.get("catalog").and_then(tera::Value::as_i64) {
+            return Ok(tera::Value::String(self.catalog_thumb(catalog_id)));
+        }
+        let id = args
+            .get("id")
+            .and_then(tera::Value::as_i64)
+            .ok_or_else(|| tera::Error::msg("cover_url: missing integer argument `id`"))?;
+        let thumb = args
+            .get("thumb")
+            .and_then(tera::Value::as_bool)
+            .unwrap_or(false);
+        let url = if thumb {
+            self.thumb(id)
+        } else {
+            self.cover(id)
+        };
+        Ok(tera::Value::String(url))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CoverQuery {
+    #[serde(default)]
+    exp: i64,
+    #[serde(default)]
+    sig: String,
+    /// `none` answers 404 for books without a cover instead of a placeholder.
+    #[serde(default)]
+    placeholder: String,
+}
 
 /// GET /opds/cover/:book_id/ — Full-size cover image.
-pub async fn cover(State(state): State<AppState>, Path((book_id,)): Path<(i64,)>) -> Response {
-    serve_cover(&state, book_id, false).await
+pub async fn cover(
+    State(state): State<AppState>,
+    Path((book_id,)): Path<(i64,)>,
+    Query(query): Query<CoverQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if !may_serve(&state, &headers, |urls| {
+        urls.verify(book_id, query.exp, &query.sig)
+    })
+    .await
+    {
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+    serve_cover(&state, book_id, false, &query, &headers).await
 }
 
 /// GET /opds/thumb/:book_id/ — Thumbnail cover image.
-pub async fn thumbnail(State(state): State<AppState>, Path((book_id,)): Path<(i64,)>) -> Response {
-    serve_cover(&state, book_id, true).await
+pub async fn thumbnail(
+    State(state): State<AppState>,
+    Path((book_id,)): Path<(i64,)>,
+    Query(query): Query<CoverQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if !may_serve(&state, &headers, |urls| {
+        urls.verify(book_id, query.exp, &query.sig)
+    })
+    .await
+    {
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+    serve_cover(&state, book_id, true, &query, &headers).await
+}
+
+/// Covers are public unless `covers.signed_urls` applies; then a request
+/// needs a valid link signature, a web session or OPDS Basic credentials.
+async fn may_serve(
+    state: &AppState,
+    headers: &HeaderMap,
+    signed: impl FnOnce(&CoverUrls) -> bool,
+) -> bool {
+    let urls = CoverUrls::new(&state.config);
+    if !urls.enabled() || signed(&urls) {
+        return true;
+    }
+    let jar = CookieJar::from_headers(headers);
+    if crate::web::auth::active_session_user(state, &jar)
+        .await
+        .is_some()
+    {
+        return true;
+    }
+    crate::opds::auth::get_user_id_from_headers(&state.db, headers)
+        .await
+        .is_some()
 }
 
-async fn serve_cover(state: &AppState, book_id: i64, as_thumbnail: bool) -> Response {
+/// GET /opds/catalog-thumb/:catalog_id/ — Cover mosaic of a catalog.
+pub async fn catalog_thumbnail(
+    State(state): State<AppState>,
+    Path((catalog_id,)): Path<(i64,)>,
+    Query(query): Query<CoverQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let signed = |urls: &CoverUrls| urls.verify_catalog(catalog_id, query.exp, &query.sig);
+    if !may_serve(&state, &headers, signed).await {
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    }
+    let path = crate::scanner::catalog_mosaic_path(&state.config.covers.covers_path, catalog_id);
+    match tokio::fs::read(&path).await {
+        Ok(data) => image_response(&data, "image/jpeg"),
+        Err(_) => (StatusCode::NOT_FOUND, "Catalog thumbnail not found").into_response(),
+    }
+}
+
+async fn serve_cover(
+    state: &AppState,
+    book_id: i64,
+    as_thumbnail: bool,
+    query: &CoverQuery,
+    headers: &HeaderMap,
+) -> Response {
     let book = match books::get_by_id(&state.db, book_id).await {
         Ok(Some(b)) => b,
         Ok(None) => return (StatusCode::NOT_FOUND, "Book not found").into_response(),
@@ -32,7 +244,7 @@ async fn serve_cover(state: &AppState, book_id: i64, as_thumbnail: bool) -> Resp
     };
 
     if book.cover == 0 && book.format != "pdf" && book.format != "djvu" {
-        return image_response(NOCOVER_SVG, "image/svg+xml");
+        return placeholder_response(state, &book, query, headers).await;
     }
 
     let covers_dir = state.config.covers.covers_path.clone();
@@ -42,6 +254,7 @@ async fn serve_cover(state: &AppState, book_id: i64, as_thumbnail: bool) -> Resp
     let format = book.format.clone();
     let cat_type = book.cat_type;
     let cover_cfg = CoverImageConfig::from(&state.config.covers);
+    let zip_encoding = crate::scanner::codepage_encoding(&state.config.library.zip_codepage);
 
     // Try disk cache first, then fallback to re-extraction from book file
     let cover_result = tokio::task::spawn_blocking(move || {
@@ -51,12 +264,17 @@ async fn serve_cover(state: &AppState, book_id: i64, as_thumbnail: bool) -> Resp
         }
 
         // 2. Fallback: re-extract from the book file
-        let extracted = extract_book_cover(&root, &path, &filename, &format, cat_type, cover_cfg)?;
-        let (cover_data, cover_mime) = crate::scanner::normalize_cover_for_storage_with_options(
-            &extracted.0,
-            &extracted.1,
+        let extracted = extract_book_cover(
+            &root,
+            &path,
+            &filename,
+            &format,
+            cat_type,
+            zip_encoding,
             cover_cfg,
-        );
+        )?;
+        let (cover_data, cover_mime) =
+            crate::covers::normalize_for_storage(&extracted.0, &extracted.1, cover_cfg)?;
 
         // Save extracted cover to disk for next time
         let ext = mime_to_ext(&cover_mime);
@@ -72,9 +290,18 @@ async fn serve_cover(state: &AppState, book_id: i64, as_thumbnail: bool) -> Resp
 
     let (cover_data, cover_mime) = match cover_result {
         Ok(Some((data, mime))) => (data, mime),
-        _ => return image_response(NOCOVER_SVG, "image/svg+xml"),
+        _ => return placeholder_response(state, &book, query, headers).await,
     };
 
+    // Covers stored before sizes were recorded get theirs on first use.
+    if book.cover != 0
+        && book.cover_width == 0
+        && let Some((width, height)) = crate::covers::dimensions(&cover_data)
+        && let Err(e) = books::set_cover_size(&state.db, book_id, width, height).await
+    {
+        tracing::debug!("Failed to record cover size of book {book_id}: {e}");
+    }
+
     if as_thumbnail {
         match make_thumbnail(&cover_data, THUMB_SIZE) {
             Ok(thumb) => image_response(&thumb, "image/jpeg"),
@@ -85,6 +312,44 @@ async fn serve_cover(state: &AppState, book_id: i64, as_thumbnail: bool) -> Resp
     }
 }
 
+/// Generated placeholder of a book without a cover, or 404 when the client
+/// asked for `?placeholder=none`.
+async fn placeholder_response(
+    state: &AppState,
+    book: &models::Book,
+    query: &CoverQuery,
+    headers: &HeaderMap,
+) -> Response {
+    if query.placeholder == "none" {
+        return (StatusCode::NOT_FOUND, "Cover not found").into_response();
+    }
+    let author = crate::db::queries::authors::get_for_book(&state.db, book.id)
+        .await
+        .ok()
+        .and_then(|authors| authors.into_iter().next())
+        .map(|a| a.full_name)
+        .unwrap_or_default();
+    let svg = crate::covers::placeholder_svg(&book.title, &author);
+
+    let etag = format!("\"{}\"", &hex::encode(Sha256::digest(svg.as_bytes()))[..16]);
+    let if_none_match = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok());
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, PLACEHOLDER_CACHE_CONTROL.to_string()),
+    ];
+    if crate::assets::matches_if_none_match(if_none_match, &etag) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (
+        cache_headers,
+        [(header::CONTENT_TYPE, "image/svg+xml")],
+        svg,
+    )
+        .into_response()
+}
+
 /// Try to find a cached cover file on disk for the given book id.
 /// Checks current (1-level), old (2-level), and legacy (flat) layouts, migrating on access.
 fn find_cover_file(covers_dir: &std::path::Path, book_id: i64) -> Option<(Vec<u8>, String)> {
@@ -132,6 +397,23 @@ fn migrate_legacy_cover(
     }
 }
 
+/// Type of a book's cover as served by `/opds/cover/`.
+pub fn cover_mime(book: &models::Book) -> String {
+    ext_to_mime(mime_to_ext(&book.cover_type))
+}
+
+/// Pixel sizes of a book's cover and of its `/opds/thumb/` thumbnail, when
+/// the cover size is known.
+pub fn image_sizes(book: &models::Book) -> Option<((u32, u32), (u32, u32))> {
+    let width = u32::try_from(book.cover_width).ok()?;
+    let height = u32::try_from(book.cover_height).ok()?;
+    if book.cover == 0 || width == 0 || height == 0 {
+        return None;
+    }
+    let thumb = crate::covers::thumbnail_dimensions(width, height, THUMB_SIZE);
+    Some(((width, height), thumb))
+}
+
 fn ext_to_mime(ext: &str) -> String {
     match ext {
         "png" => "image/png".to_string(),
@@ -149,15 +431,16 @@ fn mime_to_ext(mime: &str) -> &str {
 }
 
 /// Extract cover image from a book file.
-fn extract_book_cover(
+pub(crate) fn extract_book_cover(
     root: &std::path::Path,
     book_path: &str,
     filename: &str,
     format: &str,
     cat_type: i32,
+    zip_encoding: &'static encoding_rs::Encoding,
     cover_cfg: CoverImageConfig,
 ) -> Option<(Vec<u8>, String)> {
-    let data = read_book_file(root, book_path, filename, cat_type).ok()?;
+    let data = read_book_file(root, book_path, filename, cat_type, zip_encoding).ok()?;
 
     match format {
         "fb2" => {
@@ -209,21 +492,22 @@ fn read_book_file(
     book_path: &str,
     filename: &str,
     cat_type: i32,
+    zip_encoding: &'static encoding_rs::Encoding,
 ) -> Result<Vec<u8>, std::io::Error> {
     use std::io::Read;
     match models::CatType::try_from(cat_type) {
         Ok(models::CatType::Normal) => {
-            let full_path = root.join(book_path).join(filename);
+            let full_path = crate::vfs::local_file(root, book_path, filename);
             std::fs::read(&full_path)
         }
         Ok(models::CatType::Zip) | Ok(models::CatType::Inpx) | Ok(models::CatType::Inp) => {
-            let zip_path = root.join(book_path);
+            let zip_path = crate::vfs::local_path(root, book_path);
             let file = std::fs::File::open(&zip_path)?;
             let reader = BufReader::new(file);
             let mut archive = zip::ZipArchive::new(reader).map_err(std::io::Error::other)?;
-            let mut entry = archive
-                .by_name(filename)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+            let index = crate::scanner::find_zip_entry(&mut archive, filename, zip_encoding)
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+            let mut entry = archive.by_index(index).map_err(std::io::Error::other)?;
             let mut data = Vec::new();
             entry.read_to_end(&mut data)?;
             Ok(data)
@@ -295,119 +579,7 @@ fn extract_epub_cover<R: std::io::Read + std::io::Seek>(
     opf_path: &str,
     archive: &mut zip::ZipArchive<R>,
 ) -> Option<(Vec<u8>, String)> {
-    use quick_xml::XmlVersion;
-    use quick_xml::events::Event;
-    use quick_xml::reader::Reader;
-
-    let opf_dir = match opf_path.rfind('/') {
-        Some(i) => &opf_path[..=i],
-        None => "",
-    };
-
-    let mut cover_id: Option<String> = None;
-    let mut manifest: Vec<(String, String, String, String)> = Vec::new(); // (id, href, media_type, properties)
-
-    let mut xml = Reader::from_reader(opf_data);
-    xml.config_mut().trim_text(true);
-    let mut buf = Vec::new();
-    loop {
-        match xml.read_event_into(&mut buf) {
-            Ok(Event::Eof) | Err(_) => break,
-            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
-                let local = local_name(e.name().as_ref());
-                if local == "item" {
-                    let mut id = String::new();
-                    let mut href = String::new();
-                    let mut media_type = String::new();
-                    let mut properties = String::new();
-                    for attr in e.attributes().flatten() {
-                        let key = std::str::from_utf8(attr.key.as_ref()).unwrap_or("");
-                        let val = attr
-                            .decoded_and_normalized_value(XmlVersion::Implicit1_0, xml.decoder())
-                            .unwrap_or_default();
-                        match key {
-                            "id" => id = val.to_string(),
-                            "href" => href = val.to_string(),
-                            "media-type" => media_type = val.to_string(),
-                            "properties" => properties = val.to_string(),
-                            _ => {}
-                        }
-                    }
-                    manifest.push((id, href, media_type, properties));
-                }
-                if local == "meta" {
-                    let mut name_attr = String::new();
-                    let mut content_attr = String::new();
-                    for attr in e.attributes().flatten() {
-                        let key = std::str::from_utf8(attr.key.as_ref()).unwrap_or("");
-                        let val = attr
-                            .decoded_and_normalized_value(XmlVersion::Implicit1_0, xml.decoder())
-                            .unwrap_or_default();
-                        match key {
-                            "name" => name_attr = val.to_string(),
-                            "content" => content_attr = val.to_string(),
-                            _ => {}
-                        }
-                    }
-                    if name_attr == "cover" && !content_attr.is_empty() {
-                        cover_id = Some(content_attr);
-                    }
-                }
-            }
-            _ => {}
-        }
-        buf.clear();
-    }
-
-    // Strategy 1: properties="cover-image"
-    for (_, href, media_type, properties) in &manifest {
-        if properties.contains("cover-image") && media_type.starts_with("image/") {
-            let path = resolve_path(opf_dir, href);
-            if let Some(data) = read_zip_opt(archive, &path) {
-                return Some((data, media_type.clone()));
-            }
-        }
-    }
-
-    // Strategy 2: meta name="cover" → manifest id lookup
-    if let Some(ref cid) = cover_id {
-        for (id, href, media_type, _) in &manifest {
-            if id == cid && media_type.starts_with("image/") {
-                let path = resolve_path(opf_dir, href);
-                if let Some(data) = read_zip_opt(archive, &path) {
-                    return Some((data, media_type.clone()));
-                }
-            }
-        }
-    }
-
-    // Strategy 3: id="cover"
-    for (id, href, media_type, _) in &manifest {
-        if id.eq_ignore_ascii_case("cover") && media_type.starts_with("image/") {
-            let path = resolve_path(opf_dir, href);
-            if let Some(data) = read_zip_opt(archive, &path) {
-                return Some((data, media_type.clone()));
-            }
-        }
-    }
-
-    None
-}
-
-fn local_name(raw: &[u8]) -> String {
-    let s = std::str::from_utf8(raw).unwrap_or("");
-    match s.rfind(':') {
-        Some(i) => s[i + 1..].to_lowercase(),
-        None => s.to_lowercase(),
-    }
-}
-
-fn resolve_path(base_dir: &str, href: &str) -> String {
-    if href.starts_with('/') {
-        href.trim_start_matches('/').to_string()
-    } else {
-        format!("{base_dir}{href}")
-    }
+    crate::scanner::parsers::epub::extract_cover_from_opf(opf_data, opf_path, archive)
 }
 
 fn read_to_vec(mut entry: impl std::io::Read) -> Result<Vec<u8>, std::io::Error> {
@@ -426,21 +598,9 @@ fn read_zip_vec<R: std::io::Read + std::io::Seek>(
     read_to_vec(entry)
 }
 
-fn read_zip_opt<R: std::io::Read + std::io::Seek>(
-    archive: &mut zip::ZipArchive<R>,
-    name: &str,
-) -> Option<Vec<u8>> {
-    read_zip_vec(archive, name).ok()
-}
-
 /// Resize an image to a thumbnail, preserving aspect ratio.
-fn make_thumbnail(data: &[u8], size: u32) -> Result<Vec<u8>, image::ImageError> {
-    let img = image::load_from_memory(data)?;
-    let thumb = img.resize(size, size, FilterType::Lanczos3);
-    let mut buf = Cursor::new(Vec::new());
-    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, THUMB_JPEG_QUALITY);
-    thumb.write_with_encoder(encoder)?;
-    Ok(buf.into_inner())
+fn make_thumbnail(data: &[u8], size: u32) -> Result<Vec<u8>, crate::covers::CoverError> {
+    crate::covers::thumbnail(data, size, THUMB_JPEG_QUALITY)
 }
 
 /// Find the cover image reference id from raw FB2 bytes.
@@ -484,6 +644,38 @@ mod tests {
     use std::io::Write;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_signed_cover_urls() {
+        let unsigned = CoverUrls::default();
+        assert_eq!(unsigned.thumb(7), "/opds/thumb/7/");
+        assert!(!unsigned.verify(7, i64::MAX, ""));
+
+        let urls = CoverUrls {
+            key: Some(b"secret".to_vec()),
+            ttl_secs: 60,
+        };
+        let link = urls.cover(7);
+        let query = link.strip_prefix("/opds/cover/7/?").unwrap();
+        let (exp, sig) = query.split_once('&').unwrap();
+        let exp: i64 = exp.strip_prefix("exp=").unwrap().parse().unwrap();
+        let sig = sig.strip_prefix("sig=").unwrap();
+        assert!(exp > chrono::Utc::now().timestamp() + 60);
+        assert_eq!(exp % 60, 0);
+        assert!(urls.verify(7, exp, sig));
+        assert!(!urls.verify(8, exp, sig));
+        assert!(!urls.verify(7, exp + 60, sig));
+        // A book's signature does not open the catalog of the same id.
+        assert!(!urls.verify_catalog(7, exp, sig));
+
+        let expired = chrono::Utc::now().timestamp() - 1;
+        let sig = hex::encode(
+            signature(b"secret", "cover:7", expired)
+                .finalize()
+                .into_bytes(),
+        );
+        assert!(!urls.verify(7, expired, &sig));
+    }
+
     fn make_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
         let cursor = Cursor::new(Vec::new());
         let mut zip = zip::ZipWriter::new(cursor);
@@ -562,7 +754,7 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_container_rootfile_and_path_helpers() {
+    fn test_parse_container_rootfile() {
         let xml = br#"
             <container version="1.0">
               <rootfiles>
@@ -575,11 +767,6 @@ mod tests {
             Some("OPS/content.opf".to_string())
         );
         assert_eq!(parse_container_rootfile(b"<container/>"), None);
-
-        assert_eq!(local_name(b"opf:item"), "item");
-        assert_eq!(local_name(b"item"), "item");
-        assert_eq!(resolve_path("OPS/", "images/c.jpg"), "OPS/images/c.jpg");
-        assert_eq!(resolve_path("OPS/", "/images/c.jpg"), "images/c.jpg");
     }
 
     #[test]
@@ -1,15 +1,21 @@
 use std::io::{Cursor, Read, Write};
 
-use axum::extract::{Path, State};
+use axum::extract::{Path, Request, State};
 use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 
 use crate::db::models;
-use crate::db::queries::{books, bookshelf};
+use crate::db::queries::{
+    authors, book_files, books, bookshelf, devices, downloads, series, users,
+};
 use crate::state::AppState;
 
+use super::filename::FilenameFields;
 use super::v1::xml;
 
+/// Chunk size of downloads streamed from disk (low-memory profile).
+pub const DOWNLOAD_CHUNK_BYTES: usize = 64 * 1024;
+
 /// GET /opds/download/:book_id/:zip_flag/
 ///
 /// zip_flag: 0 = original file, 1 = wrapped in ZIP
@@ -17,17 +23,55 @@ pub async fn download(
     State(state): State<AppState>,
     headers: HeaderMap,
     Path((book_id, zip_flag)): Path<(i64, i32)>,
+    request: Request,
 ) -> Response {
     let book = match books::get_by_id(&state.db, book_id).await {
         Ok(Some(b)) => b,
         Ok(None) => return (StatusCode::NOT_FOUND, "Book not found").into_response(),
         Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
     };
+    let book = with_available_copy(&state, book).await;
 
     let root = &state.config.library.root_path;
+    let wants_zip = zip_flag == 1 && !xml::is_nozip_format(&book.format);
+    let format = xml::download_format(&book.format);
+    let download_name = download_filename(&state, &book, format).await;
+    let user_agent = client_user_agent(&state, &headers);
+
+    if state.config.low_memory()
+        && !wants_zip
+        && let Some(response) = stream_book_file(root, &book, &download_name, request).await
+    {
+        let user_id = super::auth::get_user_id_from_headers(&state.db, &headers).await;
+        record_download(&state, user_id, book_id, user_agent.as_deref()).await;
+        return response;
+    }
+
+    if wants_zip {
+        let user_id = super::auth::get_user_id_from_headers(&state.db, &headers).await;
+        return match zipped_book_response(&state, &book, &download_name).await {
+            Ok(response) => {
+                record_download(&state, user_id, book_id, user_agent.as_deref()).await;
+                response
+            }
+            Err(e) => {
+                tracing::warn!("Failed to read book {}: {e}", book_id);
+                (StatusCode::NOT_FOUND, "File not found").into_response()
+            }
+        };
+    }
 
     // Read the book file bytes
-    let data = match read_book_file(root, &book.path, &book.filename, book.cat_type) {
+    let zip_encoding = crate::scanner::codepage_encoding(&state.config.library.zip_codepage);
+    let data = match load_book_file(
+        root,
+        &book.path,
+        &book.filename,
+        book.cat_type,
+        zip_encoding,
+    )
+    .await
+    {
         Ok(d) => d,
         Err(e) => {
             tracing::warn!("Failed to read book {}: {e}", book_id);
@@ -35,26 +79,396 @@ pub async fn download(
         }
     };
 
-    // Fire-and-forget bookshelf tracking
-    if let Some(user_id) = super::auth::get_user_id_from_headers(&state.db, &headers).await {
-        let _ = bookshelf::upsert(&state.db, user_id, book_id).await;
+    // Fire-and-forget bookshelf and download history tracking
+    let user_id = super::auth::get_user_id_from_headers(&state.db, &headers).await;
+    record_download(&state, user_id, book_id, user_agent.as_deref()).await;
+
+    let mime = download_mime(format, &data);
+    file_response(&data, &download_name, &mime)
+}
+
+/// Serve a book in its original format (streamed from disk on the
+/// low-memory profile) and count the download for `user_id`.
+pub async fn book_file_response(
+    state: &AppState,
+    book: &models::Book,
+    user_id: Option<i64>,
+    request: Request,
+) -> Response {
+    let root = &state.config.library.root_path;
+    let book = &with_available_copy(state, book.clone()).await;
+    let format = xml::download_format(&book.format);
+    let download_name = download_filename(state, book, format).await;
+    let user_agent = client_user_agent(state, request.headers());
+    if state.config.low_memory()
+        && let Some(response) = stream_book_file(root, book, &download_name, request).await
+    {
+        record_download(state, user_id, book.id, user_agent.as_deref()).await;
+        return response;
     }
 
-    let download_name = title_to_filename(&book.title, &book.format, &book.filename);
-    let mime = xml::mime_for_format(&book.format);
+    let zip_encoding = crate::scanner::codepage_encoding(&state.config.library.zip_codepage);
+    let data = match load_book_file(
+        root,
+        &book.path,
+        &book.filename,
+        book.cat_type,
+        zip_encoding,
+    )
+    .await
+    {
+        Ok(d) => d,
+        Err(e) => {
+            tracing::warn!("Failed to read book {}: {e}", book.id);
+            return (StatusCode::NOT_FOUND, "File not found").into_response();
+        }
+    };
+    record_download(state, user_id, book.id, user_agent.as_deref()).await;
 
-    if zip_flag == 1 && !xml::is_nozip_format(&book.format) {
-        // Wrap in ZIP — use original filename inside the archive
-        match wrap_in_zip(&book.filename, &data) {
-            Ok(zipped) => {
-                let zip_name = format!("{download_name}.zip");
-                let zip_mime = xml::mime_for_zip(&book.format);
-                file_response(&zipped, &zip_name, &zip_mime)
+    let mime = download_mime(format, &data);
+    file_response(&data, &download_name, &mime)
+}
+
+/// The book itself, or, when its own file is gone from a local library, the
+/// book pointed at an identical copy registered by `scanner.link_identical`
+/// until the next scan moves it there.
+pub async fn with_available_copy(state: &AppState, mut book: models::Book) -> models::Book {
+    let root = &state.config.library.root_path;
+    if book.cat_type != models::CatType::Normal as i32
+        || crate::vfs::is_remote(root)
+        || crate::vfs::local_file(root, &book.path, &book.filename).exists()
+    {
+        return book;
+    }
+    let copies = book_files::get_for_book(&state.db, book.id)
+        .await
+        .unwrap_or_default();
+    if let Some(copy) = copies
+        .into_iter()
+        .find(|c| crate::vfs::local_file(root, &c.path, &c.filename).is_file())
+    {
+        book.path = copy.path;
+        book.filename = copy.filename;
+    }
+    book
+}
+
+/// File name of a downloaded book rendered from
+/// `download.filename_template`, falling back to the stored file name when
+/// the book has no usable title.
+pub async fn download_filename(state: &AppState, book: &models::Book, format: &str) -> String {
+    let template = &state.filename_template;
+    let author = if template.uses_author() {
+        authors::get_for_book(&state.db, book.id)
+            .await
+            .ok()
+            .and_then(|list| list.into_iter().next())
+            .map(|a| a.full_name)
+    } else {
+        None
+    };
+    let series = if template.uses_series() {
+        series::get_for_book(&state.db, book.id)
+            .await
+            .ok()
+            .and_then(|list| list.into_iter().next())
+    } else {
+        None
+    };
+    let fields = FilenameFields {
+        id: book.id,
+        title: &book.title,
+        author: author.as_deref().unwrap_or_default(),
+        series: series.as_ref().map_or("", |(s, _)| s.ser_name.as_str()),
+        ser_no: series.as_ref().map(|(_, n)| *n),
+        ext: format,
+    };
+    template
+        .render(&fields)
+        .unwrap_or_else(|| super::filename::sanitize(&book.filename))
+}
+
+/// Serve a book wrapped in a ZIP archive with a single member named
+/// `download_name` and the book's title, authors and series in the archive
+/// comment. Books of a local library are compressed on a blocking thread and
+/// streamed while the archive is written; remote books are zipped in memory.
+pub async fn zipped_book_response(
+    state: &AppState,
+    book: &models::Book,
+    download_name: &str,
+) -> Result<Response, std::io::Error> {
+    let root = &state.config.library.root_path;
+    let zip_encoding = crate::scanner::codepage_encoding(&state.config.library.zip_codepage);
+    let comment = zip_comment(state, book).await;
+    let zip_name = format!("{download_name}.zip");
+    let zip_mime = xml::mime_for_zip(&book.format);
+
+    if crate::vfs::is_remote(root) {
+        let data = load_book_file(
+            root,
+            &book.path,
+            &book.filename,
+            book.cat_type,
+            zip_encoding,
+        )
+        .await?;
+        let zipped = wrap_in_zip(download_name, &comment, &data).map_err(std::io::Error::other)?;
+        return Ok(file_response(&zipped, &zip_name, &zip_mime));
+    }
+
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    let root = root.clone();
+    let (book_path, filename, cat_type) = (book.path.clone(), book.filename.clone(), book.cat_type);
+    let member = download_name.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut ready_tx = Some(ready_tx);
+        let result = with_book_reader(
+            &root,
+            &book_path,
+            &filename,
+            cat_type,
+            zip_encoding,
+            |source| {
+                if let Some(ready) = ready_tx.take() {
+                    let _ = ready.send(Ok(()));
+                }
+                let zip = zip::ZipWriter::new_stream(BodyWriter::new(tx.clone()));
+                let mut body = write_zip(zip, &member, &comment, source)
+                    .map_err(std::io::Error::other)?
+                    .into_inner();
+                body.flush()
+            },
+        );
+        match (ready_tx, result) {
+            (Some(ready), Err(e)) => {
+                let _ = ready.send(Err(e));
             }
-            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ZIP error").into_response(),
+            (None, Err(e)) => {
+                // Headers are out already: end the body with the error
+                let _ = tx.blocking_send(Err(e));
+            }
+            _ => {}
+        }
+    });
+    ready_rx.await.map_err(std::io::Error::other)??;
+
+    let ascii_name = super::filename::ascii_fallback(&zip_name);
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                format!("{zip_mime}; name=\"{ascii_name}\""),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                super::filename::content_disposition("attachment", &zip_name),
+            ),
+        ],
+        axum::body::Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+/// Archive comment of a zipped download: title, authors and series, one per
+/// line.
+async fn zip_comment(state: &AppState, book: &models::Book) -> String {
+    let mut lines = vec![book.title.clone()];
+    let names: Vec<String> = authors::get_for_book(&state.db, book.id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|a| a.full_name)
+        .collect();
+    if !names.is_empty() {
+        lines.push(names.join(", "));
+    }
+    if let Some((ser, ser_no)) = series::get_for_book(&state.db, book.id)
+        .await
+        .ok()
+        .and_then(|list| list.into_iter().next())
+    {
+        lines.push(if ser_no > 0 {
+            format!("{} #{ser_no}", ser.ser_name)
+        } else {
+            ser.ser_name
+        });
+    }
+    lines.join("\n")
+}
+
+/// `Write` end of a streamed response body. Output is sent in
+/// [`DOWNLOAD_CHUNK_BYTES`] chunks from a blocking thread; writes fail with
+/// `BrokenPipe` once the client has gone away.
+struct BodyWriter {
+    tx: tokio::sync::mpsc::Sender<Result<Vec<u8>, std::io::Error>>,
+    buf: Vec<u8>,
+}
+
+impl BodyWriter {
+    fn new(tx: tokio::sync::mpsc::Sender<Result<Vec<u8>, std::io::Error>>) -> Self {
+        Self {
+            tx,
+            buf: Vec::with_capacity(DOWNLOAD_CHUNK_BYTES),
+        }
+    }
+}
+
+impl Write for BodyWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= DOWNLOAD_CHUNK_BYTES {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(DOWNLOAD_CHUNK_BYTES));
+        self.tx
+            .blocking_send(Ok(chunk))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+    }
+}
+
+/// Put a downloaded book on the user's bookshelf and count the download.
+/// `user_agent` comes from [`client_user_agent`]. A read-only mirror records
+/// nothing.
+pub async fn record_download(
+    state: &AppState,
+    user_id: Option<i64>,
+    book_id: i64,
+    user_agent: Option<&str>,
+) {
+    if state.config.server.read_only {
+        return;
+    }
+    if let Some(user_id) = user_id {
+        auto_shelve(state, user_id, book_id).await;
+    } else if !state.config.stats.anonymous_downloads {
+        return;
+    }
+    let _ = downloads::record_with_agent(&state.db, book_id, user_id, user_agent).await;
+}
+
+/// The client's User-Agent when `stats.user_agents` asks to record it.
+pub fn client_user_agent(state: &AppState, headers: &HeaderMap) -> Option<String> {
+    if !state.config.stats.user_agents {
+        return None;
+    }
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(devices::truncate_user_agent)
+        .filter(|ua| !ua.is_empty())
+}
+
+/// Put a book the user downloaded or opened in the reader on their bookshelf,
+/// unless `opds.auto_bookshelf` is off or the user opted out. A book already
+/// on the shelf only has its date refreshed.
+pub async fn auto_shelve(state: &AppState, user_id: i64, book_id: i64) {
+    if !state.config.opds.auto_bookshelf {
+        return;
+    }
+    if users::auto_bookshelf(&state.db, user_id)
+        .await
+        .unwrap_or(true)
+    {
+        let _ = bookshelf::upsert(&state.db, user_id, book_id).await;
+    }
+}
+
+/// Stream a plain book file from disk in [`DOWNLOAD_CHUNK_BYTES`] chunks
+/// instead of reading it into memory. Returns `None` for books that are read
+/// whole: entries of archives, audiobook folders and text files, whose
+/// charset is sniffed from the content.
+pub async fn stream_book_file(
+    root: &std::path::Path,
+    book: &models::Book,
+    download_name: &str,
+    request: Request,
+) -> Option<Response> {
+    if book.cat_type != models::CatType::Normal as i32 || book.format == "txt" {
+        return None;
+    }
+    let path = crate::vfs::local_file(root, &book.path, &book.filename);
+    if !path.is_file() {
+        return None;
+    }
+    let format = xml::download_format(&book.format);
+    let mime: mime_guess::mime::Mime = xml::mime_for_format(format).parse().ok()?;
+
+    let mut response = tower_http::services::ServeFile::new_with_mime(&path, &mime)
+        .with_buf_chunk_size(DOWNLOAD_CHUNK_BYTES)
+        .try_call(request)
+        .await
+        .ok()?
+        .into_response();
+    if !response.status().is_success() {
+        return None;
+    }
+    let headers = response.headers_mut();
+    let ascii_name = super::filename::ascii_fallback(download_name);
+    headers.insert(
+        header::CONTENT_TYPE,
+        format!("{mime}; name=\"{ascii_name}\"").parse().ok()?,
+    );
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        super::filename::content_disposition("attachment", download_name)
+            .parse()
+            .ok()?,
+    );
+    Some(response)
+}
+
+/// GET /opds/audio/:book_id/:track/
+///
+/// Stream one track of an audiobook (the whole file for M4B).
+pub async fn audio_track(
+    State(state): State<AppState>,
+    Path((book_id, track)): Path<(i64, usize)>,
+    request: Request,
+) -> Response {
+    serve_audio_track(&state, book_id, track, request).await
+}
+
+/// Serve an audiobook track from disk. Range requests are honored so players
+/// can seek without downloading the whole file.
+pub async fn serve_audio_track(
+    state: &AppState,
+    book_id: i64,
+    track: usize,
+    request: Request,
+) -> Response {
+    let book = match books::get_by_id(&state.db, book_id).await {
+        Ok(Some(b)) => b,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Book not found").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
+    };
+
+    let tracks = crate::audio::book_tracks(&state.config.library.root_path, &book);
+    let Some(path) = tracks.get(track) else {
+        return (StatusCode::NOT_FOUND, "Track not found").into_response();
+    };
+    let mime: mime_guess::mime::Mime = match xml::mime_for_format(&book.format).parse() {
+        Ok(m) => m,
+        Err(_) => mime_guess::mime::APPLICATION_OCTET_STREAM,
+    };
+
+    match tower_http::services::ServeFile::new_with_mime(path, &mime)
+        .try_call(request)
+        .await
+    {
+        Ok(response) => response.into_response(),
+        Err(e) => {
+            tracing::warn!("Failed to stream track {track} of book {book_id}: {e}");
+            (StatusCode::NOT_FOUND, "File not found").into_response()
         }
-    } else {
-        file_response(&data, &download_name, mime)
     }
 }
 
@@ -64,28 +478,58 @@ pub fn read_book_file(
     book_path: &str,
     filename: &str,
     cat_type: i32,
+    zip_encoding: &'static encoding_rs::Encoding,
 ) -> Result<Vec<u8>, std::io::Error> {
+    if models::CatType::try_from(cat_type) == Ok(models::CatType::Normal) {
+        let full_path = crate::vfs::local_file(root, book_path, filename);
+        if full_path.is_dir() {
+            // MP3 audiobook folder: bundle its tracks
+            return zip_tracks(&full_path);
+        }
+    }
+    with_book_reader(
+        root,
+        book_path,
+        filename,
+        cat_type,
+        zip_encoding,
+        |source| {
+            let mut data = Vec::new();
+            source.read_to_end(&mut data)?;
+            Ok(data)
+        },
+    )
+}
+
+/// Open a book of a local library, a plain file or an entry of a ZIP
+/// archive, and pass its content to `f`.
+fn with_book_reader<T>(
+    root: &std::path::Path,
+    book_path: &str,
+    filename: &str,
+    cat_type: i32,
+    zip_encoding: &'static encoding_rs::Encoding,
+    f: impl FnOnce(&mut dyn Read) -> Result<T, std::io::Error>,
+) -> Result<T, std::io::Error> {
     match models::CatType::try_from(cat_type) {
         Ok(models::CatType::Normal) => {
             // Plain file on disk
-            let full_path = root.join(book_path).join(filename);
-            std::fs::read(&full_path)
+            let full_path = crate::vfs::local_file(root, book_path, filename);
+            let mut file = std::fs::File::open(&full_path)?;
+            f(&mut file)
         }
         Ok(models::CatType::Zip) | Ok(models::CatType::Inpx) | Ok(models::CatType::Inp) => {
             // File inside a ZIP archive
             // book_path is the relative path to the ZIP file
-            let zip_path = root.join(book_path);
+            let zip_path = crate::vfs::local_path(root, book_path);
             let file = std::fs::File::open(&zip_path)?;
             let reader = std::io::BufReader::new(file);
             let mut archive = zip::ZipArchive::new(reader).map_err(std::io::Error::other)?;
 
-            let mut entry = archive
-                .by_name(filename)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
-
-            let mut data = Vec::new();
-            entry.read_to_end(&mut data)?;
-            Ok(data)
+            let index = crate::scanner::find_zip_entry(&mut archive, filename, zip_encoding)
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+            let mut entry = archive.by_index(index).map_err(std::io::Error::other)?;
+            f(&mut entry)
         }
         Err(_) => Err(std::io::Error::other(format!(
             "Unknown cat_type: {cat_type}"
@@ -93,71 +537,115 @@ pub fn read_book_file(
     }
 }
 
+/// Read a book file from the library, local or remote. Books of a remote
+/// library are fetched from the server; for archive entries the whole archive is
+/// downloaded to a temporary file first.
+pub async fn load_book_file(
+    root: &std::path::Path,
+    book_path: &str,
+    filename: &str,
+    cat_type: i32,
+    zip_encoding: &'static encoding_rs::Encoding,
+) -> Result<Vec<u8>, std::io::Error> {
+    use crate::vfs::LibraryFs;
+
+    let Some(fs) = crate::vfs::remote(root).map_err(std::io::Error::other)? else {
+        return read_book_file(root, book_path, filename, cat_type, zip_encoding);
+    };
+    if models::CatType::try_from(cat_type) == Ok(models::CatType::Normal) {
+        let rel_path = if book_path.is_empty() {
+            filename.to_string()
+        } else {
+            format!("{book_path}/{filename}")
+        };
+        return fs.read(&rel_path).await;
+    }
+    let staged = crate::vfs::StagedFile::fetch(fs.as_ref(), book_path).await?;
+    read_book_file(staged.root(), book_path, filename, cat_type, zip_encoding)
+}
+
 /// Wrap file bytes into a new ZIP archive in memory.
-pub fn wrap_in_zip(filename: &str, data: &[u8]) -> Result<Vec<u8>, zip::result::ZipError> {
-    let buf = Cursor::new(Vec::new());
-    let mut zip_writer = zip::ZipWriter::new(buf);
-    let options = zip::write::SimpleFileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated);
-    zip_writer.start_file(filename, options)?;
-    zip_writer.write_all(data)?;
-    let cursor = zip_writer.finish()?;
+pub fn wrap_in_zip(
+    member: &str,
+    comment: &str,
+    mut data: &[u8],
+) -> Result<Vec<u8>, zip::result::ZipError> {
+    let zip_writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let cursor = write_zip(zip_writer, member, comment, &mut data)?;
     Ok(cursor.into_inner())
 }
 
-/// Build a safe download filename from the book title and format extension.
-///
-/// - Collapses consecutive whitespace into a single `_`
-/// - Replaces non-alphanumeric, non-Unicode-letter characters with `_`
-/// - Collapses consecutive `_` into one
-/// - Trims leading/trailing `_`
-/// - Falls back to the original filename if the result is empty
-pub fn title_to_filename(title: &str, format: &str, original_filename: &str) -> String {
-    let safe: String = title
-        .chars()
-        .map(|c| {
-            if c.is_alphanumeric() || c == '.' || c == '-' || c == '\'' {
-                c
-            } else {
-                '_'
-            }
-        })
-        .collect();
+/// Write `source` as the only, deflated member of a ZIP archive.
+fn write_zip<W: Write + std::io::Seek>(
+    mut zip_writer: zip::ZipWriter<W>,
+    member: &str,
+    comment: &str,
+    source: &mut dyn Read,
+) -> Result<W, zip::result::ZipError> {
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    zip_writer.set_comment(comment)?;
+    zip_writer.start_file(member, options)?;
+    std::io::copy(source, &mut zip_writer)?;
+    zip_writer.finish()
+}
 
-    // Collapse consecutive underscores and trim
-    let mut result = String::new();
-    let mut prev_underscore = true; // trim leading
-    for c in safe.chars() {
-        if c == '_' {
-            if !prev_underscore {
-                result.push('_');
-            }
-            prev_underscore = true;
-        } else {
-            result.push(c);
-            prev_underscore = false;
-        }
+/// Bundle the tracks of an MP3 audiobook folder into an uncompressed ZIP.
+fn zip_tracks(dir: &std::path::Path) -> Result<Vec<u8>, std::io::Error> {
+    let tracks = crate::audio::list_tracks(dir)?;
+    if tracks.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no audio tracks",
+        ));
     }
-    // Trim trailing underscore
-    while result.ends_with('_') {
-        result.pop();
+    let mut zip_writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    // MP3 data does not compress further
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(true);
+    for track in &tracks {
+        let name = crate::vfs::decode_name(track.file_name().unwrap_or_default());
+        zip_writer
+            .start_file(name, options)
+            .map_err(std::io::Error::other)?;
+        zip_writer.write_all(&std::fs::read(track)?)?;
     }
+    let cursor = zip_writer.finish().map_err(std::io::Error::other)?;
+    Ok(cursor.into_inner())
+}
 
-    if result.is_empty() {
-        original_filename.to_string()
+/// MIME type of a downloaded book. Plain text carries the charset sniffed
+/// from its content, so browsers do not mis-decode legacy codepages.
+pub fn download_mime(format: &str, data: &[u8]) -> String {
+    let mime = xml::mime_for_format(format);
+    if format == "txt" {
+        let encoding = crate::scanner::parsers::txt::detect_encoding(data);
+        format!("{mime}; charset={}", encoding.name())
     } else {
-        format!("{result}.{format}")
+        mime.to_string()
     }
 }
 
 /// Build an HTTP response for a file download.
 pub fn file_response(data: &[u8], filename: &str, mime: &str) -> Response {
-    let content_disposition = format!("attachment; filename=\"{filename}\"");
+    file_response_with(data, filename, mime, "attachment")
+}
+
+/// Build an HTTP response for a file shown (`inline`) or saved (`attachment`).
+pub fn file_response_with(data: &[u8], filename: &str, mime: &str, disposition: &str) -> Response {
+    let ascii_name = super::filename::ascii_fallback(filename);
     (
         StatusCode::OK,
         [
-            (header::CONTENT_TYPE, format!("{mime}; name=\"{filename}\"")),
-            (header::CONTENT_DISPOSITION, content_disposition),
+            (
+                header::CONTENT_TYPE,
+                format!("{mime}; name=\"{ascii_name}\""),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                super::filename::content_disposition(disposition, filename),
+            ),
             (header::CONTENT_LENGTH, data.len().to_string()),
         ],
         data.to_vec(),
@@ -169,6 +657,7 @@ pub fn file_response(data: &[u8], filename: &str, mime: &str) -> Response {
 mod tests {
     use super::*;
     use crate::db::models::CatType;
+    use encoding_rs::UTF_8;
     use std::io::Write;
     use tempfile::tempdir;
 
@@ -185,10 +674,11 @@ mod tests {
     #[test]
     fn test_wrap_in_zip_roundtrip() {
         let bytes = b"hello-book";
-        let zipped = wrap_in_zip("book.fb2", bytes).unwrap();
+        let zipped = wrap_in_zip("book.fb2", "Title\nAuthor", bytes).unwrap();
         let reader = std::io::Cursor::new(zipped);
         let mut archive = zip::ZipArchive::new(reader).unwrap();
         assert_eq!(archive.len(), 1);
+        assert_eq!(archive.comment(), b"Title\nAuthor");
         let mut file = archive.by_name("book.fb2").unwrap();
         let mut out = Vec::new();
         use std::io::Read;
@@ -196,15 +686,6 @@ mod tests {
         assert_eq!(out, bytes);
     }
 
-    #[test]
-    fn test_title_to_filename_sanitization_and_fallback() {
-        assert_eq!(
-            title_to_filename("  A  Title / Name ", "fb2", "orig.fb2"),
-            "A_Title_Name.fb2"
-        );
-        assert_eq!(title_to_filename("***", "epub", "orig.epub"), "orig.epub");
-    }
-
     #[test]
     fn test_file_response_headers() {
         let resp = file_response(b"abc", "book.fb2", "application/fb2+xml");
@@ -218,6 +699,30 @@ mod tests {
             resp.headers().get(header::CONTENT_TYPE).unwrap(),
             "application/fb2+xml; name=\"book.fb2\""
         );
+
+        let resp = file_response(b"abc", "Мир.fb2", "application/fb2+xml");
+        assert_eq!(
+            resp.headers().get(header::CONTENT_DISPOSITION).unwrap(),
+            "attachment; filename=\"Mir.fb2\"; filename*=UTF-8''%D0%9C%D0%B8%D1%80.fb2"
+        );
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/fb2+xml; name=\"Mir.fb2\""
+        );
+    }
+
+    #[test]
+    fn test_download_mime_sniffs_text_charset() {
+        assert_eq!(
+            download_mime("txt", "текст".as_bytes()),
+            "text/plain; charset=UTF-8"
+        );
+        let (cp1251, _, _) = encoding_rs::WINDOWS_1251.encode("обычный русский текст");
+        assert_eq!(
+            download_mime("txt", &cp1251),
+            "text/plain; charset=windows-1251"
+        );
+        assert_eq!(download_mime("rtf", b"{\\rtf1}"), "application/rtf");
     }
 
     #[test]
@@ -228,8 +733,14 @@ mod tests {
         let full = book_dir.join("book.fb2");
         std::fs::write(&full, b"plain-data").unwrap();
 
-        let data =
-            read_book_file(dir.path(), "sub", "book.fb2", i32::from(CatType::Normal)).unwrap();
+        let data = read_book_file(
+            dir.path(),
+            "sub",
+            "book.fb2",
+            i32::from(CatType::Normal),
+            UTF_8,
+        )
+        .unwrap();
         assert_eq!(data, b"plain-data");
     }
 
@@ -244,15 +755,39 @@ mod tests {
             "books.zip",
             "inside.fb2",
             i32::from(CatType::Zip),
+            UTF_8,
         )
         .unwrap();
         assert_eq!(data, b"zip-data");
     }
 
+    #[test]
+    fn test_read_book_file_zips_audio_folder() {
+        let dir = tempdir().unwrap();
+        let book_dir = dir.path().join("audio").join("Book");
+        std::fs::create_dir_all(&book_dir).unwrap();
+        std::fs::write(book_dir.join("02.mp3"), b"two").unwrap();
+        std::fs::write(book_dir.join("01.mp3"), b"one").unwrap();
+        std::fs::write(book_dir.join("metadata.json"), b"{}").unwrap();
+
+        let data = read_book_file(
+            dir.path(),
+            "audio",
+            "Book",
+            i32::from(CatType::Normal),
+            UTF_8,
+        )
+        .unwrap();
+        let archive = zip::ZipArchive::new(Cursor::new(data)).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert_eq!(archive.len(), 2);
+        assert!(names.contains(&"01.mp3") && names.contains(&"02.mp3"));
+    }
+
     #[test]
     fn test_read_book_file_unknown_cat_type() {
         let dir = tempdir().unwrap();
-        let err = read_book_file(dir.path(), "", "book.fb2", 999).unwrap_err();
+        let err = read_book_file(dir.path(), "", "book.fb2", 999, UTF_8).unwrap_err();
         assert_eq!(err.kind(), std::io::ErrorKind::Other);
     }
 }
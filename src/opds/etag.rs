@@ -0,0 +1,173 @@
+//! Conditional GET for OPDS feeds.
+//!
+//! Feed ETags are derived from the library change stamp (`library_version`
+//! counter), which scans, uploads and admin edits bump. A client presenting
+//! a matching `If-None-Match` gets `304 Not Modified` without the feed being
+//! rebuilt. With signed cover links the signing period is part of the tag,
+//! so cached feeds never outlive their links. The client profile and the
+//! base URL of absolute links are part of it too, as they change the feed
+//! served at the same URL.
+
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use sha2::{Digest, Sha256};
+
+use super::compat::{self, ClientProfile};
+use crate::state::AppState;
+
+/// Request headers that select a different rendering of the same feed URL.
+const VARY_HEADERS: [header::HeaderName; 3] = [
+    header::AUTHORIZATION,
+    header::COOKIE,
+    header::ACCEPT_LANGUAGE,
+];
+
+/// Feeds whose content depends only on the library. Root feeds (bookshelf
+/// count) and the bookshelf, recommendation, next-in-series and popularity
+/// feeds change without a library change and are always rebuilt.
+fn is_library_feed(path: &str) -> bool {
+    let path = path.strip_prefix("/v2").unwrap_or(path);
+    let path = path.trim_start_matches('/');
+    if path.is_empty() || path.starts_with("lang/") {
+        return false;
+    }
+    !["bookshelf/", "recommended/", "next/", "popular/"]
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+fn feed_etag(
+    version: i64,
+    period: Option<i64>,
+    profile: &ClientProfile,
+    uri: &str,
+    headers: &HeaderMap,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(version.to_le_bytes());
+    if let Some(period) = period {
+        hasher.update(period.to_le_bytes());
+    }
+    hasher.update(profile.name.as_bytes());
+    hasher.update([0]);
+    hasher.update(profile.link_base.as_deref().unwrap_or_default().as_bytes());
+    hasher.update([0]);
+    hasher.update(uri.as_bytes());
+    for name in &VARY_HEADERS {
+        hasher.update([0]);
+        if let Some(value) = headers.get(name) {
+            hasher.update(value.as_bytes());
+        }
+    }
+    format!("W/\"{}\"", &hex::encode(hasher.finalize())[..16])
+}
+
+/// Middleware: tag library feeds with an ETag and answer matching
+/// `If-None-Match` requests with `304 Not Modified`.
+pub async fn conditional_get(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::GET || !is_library_feed(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let Some(version) = state.library_version().await else {
+        return next.run(request).await;
+    };
+
+    let period = crate::opds::covers::CoverUrls::new(&state.config).period();
+    let etag = feed_etag(
+        version,
+        period,
+        &compat::current(),
+        &request.uri().to_string(),
+        request.headers(),
+    );
+    let if_none_match = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok());
+    if crate::assets::matches_if_none_match(if_none_match, &etag) {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        if let Ok(value) = HeaderValue::from_str(&etag) {
+            response.headers_mut().insert(header::ETAG, value);
+        }
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::OK
+        && let Ok(value) = HeaderValue::from_str(&etag)
+    {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_library_feed() {
+        assert!(is_library_feed("/catalogs/"));
+        assert!(is_library_feed("/v2/authors/1/"));
+        assert!(is_library_feed("/search/books/m/tolkien/"));
+        assert!(!is_library_feed("/"));
+        assert!(!is_library_feed("/v2"));
+        assert!(!is_library_feed("/v2/"));
+        assert!(!is_library_feed("/lang/ru/"));
+        assert!(!is_library_feed("/bookshelf/2/"));
+        assert!(!is_library_feed("/v2/recommended/"));
+        assert!(!is_library_feed("/next/"));
+        assert!(!is_library_feed("/popular/"));
+    }
+
+    #[test]
+    fn test_feed_etag_varies_by_version_and_user() {
+        let none = ClientProfile::default();
+        let mut headers = HeaderMap::new();
+        let base = feed_etag(1, None, &none, "/catalogs/", &headers);
+        assert!(base.starts_with("W/\""));
+        assert_eq!(base, feed_etag(1, None, &none, "/catalogs/", &headers));
+        assert_ne!(base, feed_etag(2, None, &none, "/catalogs/", &headers));
+        assert_ne!(base, feed_etag(1, None, &none, "/authors/", &headers));
+        assert_ne!(base, feed_etag(1, Some(7), &none, "/catalogs/", &headers));
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Basic eDp5"),
+        );
+        assert_ne!(base, feed_etag(1, None, &none, "/catalogs/", &headers));
+    }
+
+    #[test]
+    fn test_feed_etag_varies_by_client_profile() {
+        let headers = HeaderMap::new();
+        let koreader = ClientProfile {
+            name: "koreader".to_string(),
+            ..Default::default()
+        };
+        let pocketbook = ClientProfile {
+            name: "pocketbook".to_string(),
+            ..Default::default()
+        };
+        let etag = |profile: &ClientProfile| feed_etag(1, None, profile, "/catalogs/", &headers);
+        assert_ne!(etag(&koreader), etag(&pocketbook));
+        assert_ne!(etag(&koreader), etag(&ClientProfile::default()));
+
+        let absolute = |base: &str| ClientProfile {
+            link_base: Some(base.to_string()),
+            ..koreader.clone()
+        };
+        assert_ne!(etag(&koreader), etag(&absolute("https://a.example")));
+        assert_ne!(
+            etag(&absolute("https://a.example")),
+            etag(&absolute("https://b.example"))
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Download file names.
+//!
+//! Names are rendered from `download.filename_template` and sent in
+//! `Content-Disposition` twice: as an RFC 5987 `filename*` with the UTF-8
+//! name, and as a transliterated ASCII `filename` for clients that do not
+//! understand the extended parameter.
+
+/// Template used when `download.filename_template` is not set.
+pub const DEFAULT_TEMPLATE: &str = "{title}.{ext}";
+
+/// Longest file name produced, in characters.
+const MAX_NAME_CHARS: usize = 180;
+
+/// A placeholder in a download filename template.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Author,
+    Title,
+    Series,
+    SerNo,
+    Ext,
+    Id,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Field(Field),
+}
+
+/// Values substituted into a [`FilenameTemplate`].
+#[derive(Debug, Default)]
+pub struct FilenameFields<'a> {
+    pub id: i64,
+    pub title: &'a str,
+    pub author: &'a str,
+    pub series: &'a str,
+    pub ser_no: Option<i32>,
+    pub ext: &'a str,
+}
+
+/// A compiled download filename template such as `"{author} - {title}.{ext}"`.
+///
+/// Supported placeholders are `{author}` (the first author), `{title}`,
+/// `{series}`, `{ser_no}`, `{ext}` and `{id}`. Text following a placeholder
+/// that renders empty is dropped up to the next placeholder, so
+/// `"{series} #{ser_no} - {title}"` yields just the title for books outside
+/// a series.
+#[derive(Debug, Clone)]
+pub struct FilenameTemplate {
+    tokens: Vec<Token>,
+}
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_TEMPLATE).expect("default template is valid")
+    }
+}
+
+impl FilenameTemplate {
+    /// Parse a template. Fails on unknown or unclosed placeholders and on
+    /// templates without `{title}` or `{id}`, which would give every book
+    /// the same name.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            literal.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                return Err(format!("unclosed placeholder in '{template}'"));
+            };
+            let field = match &rest[start + 1..start + len] {
+                "author" => Field::Author,
+                "title" => Field::Title,
+                "series" => Field::Series,
+                "ser_no" => Field::SerNo,
+                "ext" => Field::Ext,
+                "id" => Field::Id,
+                other => return Err(format!("unknown placeholder {{{other}}} in '{template}'")),
+            };
+            if !literal.is_empty() {
+                tokens.push(Token::Literal(std::mem::take(&mut literal)));
+            }
+            tokens.push(Token::Field(field));
+            rest = &rest[start + len + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+        if !tokens
+            .iter()
+            .any(|t| matches!(t, Token::Field(Field::Title | Field::Id)))
+        {
+            return Err(format!(
+                "'{template}' needs a {{title}} or {{id}} placeholder"
+            ));
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Whether `{author}` appears in the template.
+    pub fn uses_author(&self) -> bool {
+        self.tokens.contains(&Token::Field(Field::Author))
+    }
+
+    /// Whether `{series}` or `{ser_no}` appears in the template.
+    pub fn uses_series(&self) -> bool {
+        self.tokens
+            .iter()
+            .any(|t| matches!(t, Token::Field(Field::Series | Field::SerNo)))
+    }
+
+    /// Render a sanitized file name, or `None` when every placeholder other
+    /// than `{ext}` is empty.
+    pub fn render(&self, fields: &FilenameFields<'_>) -> Option<String> {
+        let mut out = String::new();
+        let mut has_content = false;
+        let mut skip_literal = false;
+        for (i, token) in self.tokens.iter().enumerate() {
+            match token {
+                Token::Literal(text) => {
+                    // Drop the separator after an empty placeholder unless
+                    // only the extension follows it
+                    let before_ext =
+                        matches!(self.tokens.get(i + 1), Some(Token::Field(Field::Ext)));
+                    if !skip_literal || before_ext {
+                        out.push_str(text);
+                    }
+                }
+                Token::Field(field) => {
+                    let value = match field {
+                        Field::Author => sanitize(fields.author),
+                        Field::Title => sanitize(fields.title),
+                        Field::Series => sanitize(fields.series),
+                        Field::SerNo => fields
+                            .ser_no
+                            .filter(|n| *n > 0)
+                            .map(|n| n.to_string())
+                            .unwrap_or_default(),
+                        Field::Ext => sanitize(fields.ext),
+                        Field::Id => fields.id.to_string(),
+                    };
+                    skip_literal = value.is_empty();
+                    has_content |= *field != Field::Ext && !value.is_empty();
+                    out.push_str(&value);
+                }
+            }
+        }
+        if !has_content {
+            return None;
+        }
+        let name = sanitize(&out);
+        let name = name.trim_start_matches([' ', '-', '_', ',', '#', '.']);
+        Some(truncate(name))
+    }
+}
+
+/// Make `name` safe as a file name on common systems: path separators and
+/// pipes become `-`, quotes become `'`, other characters Windows rejects are
+/// dropped, whitespace runs collapse into one space, and leading or trailing
+/// spaces and dots are trimmed.
+pub fn sanitize(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = match c {
+            '/' | '\\' | '|' => '-',
+            '"' => '\'',
+            ':' | '*' | '?' | '<' | '>' => continue,
+            c if c.is_whitespace() || c.is_control() => ' ',
+            c => c,
+        };
+        if c == ' ' && (out.is_empty() || out.ends_with(' ')) {
+            continue;
+        }
+        out.push(c);
+    }
+    out.trim_matches([' ', '.']).to_string()
+}
+
+/// Cut an overlong name, keeping its extension.
+fn truncate(name: &str) -> String {
+    if name.chars().count() <= MAX_NAME_CHARS {
+        return name.to_string();
+    }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if ext.chars().count() <= 8 => (stem, Some(ext)),
+        _ => (name, None),
+    };
+    let keep = MAX_NAME_CHARS - ext.map_or(0, |e| e.chars().count() + 1);
+    let stem: String = stem.chars().take(keep).collect();
+    let stem = stem.trim_end();
+    match ext {
+        Some(ext) => format!("{stem}.{ext}"),
+        None => stem.to_string(),
+    }
+}
+
+/// ASCII spelling of a file name for the plain `filename` parameter:
+/// Cyrillic is transliterated and other non-ASCII characters, as well as
+/// `%` and `\`, become `_`.
+pub fn ascii_fallback(name: &str) -> String {
+    crate::translit::latinize(name)
+        .chars()
+        .map(|c| match c {
+            '%' | '\\' | '"' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// `Content-Disposition` value for `disposition` (`attachment` or `inline`)
+/// carrying `filename` as an ASCII fallback and an RFC 5987 `filename*`.
+pub fn content_disposition(disposition: &str, filename: &str) -> String {
+    let ascii = ascii_fallback(filename);
+    if ascii == filename {
+        return format!("{disposition}; filename=\"{ascii}\"");
+    }
+    format!(
+        "{disposition}; filename=\"{ascii}\"; filename*=UTF-8''{}",
+        urlencoding::encode(filename)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields<'a>(title: &'a str, author: &'a str, series: &'a str) -> FilenameFields<'a> {
+        FilenameFields {
+            id: 42,
+            title,
+            author,
+            series,
+            ser_no: Some(2),
+            ext: "fb2",
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_templates() {
+        assert!(FilenameTemplate::parse("{author} - {title}.{ext}").is_ok());
+        assert!(FilenameTemplate::parse("{id}.{ext}").is_ok());
+        assert!(FilenameTemplate::parse("{author}.{ext}").is_err());
+        assert!(FilenameTemplate::parse("{title}.{format}").is_err());
+        assert!(FilenameTemplate::parse("{title").is_err());
+    }
+
+    #[test]
+    fn test_render_default_template() {
+        let template = FilenameTemplate::default();
+        assert_eq!(
+            template.render(&fields("  A  Title / Name: \"Part\" ", "", "")),
+            Some("A Title - Name 'Part'.fb2".to_string())
+        );
+        assert_eq!(template.render(&fields("***", "", "")), None);
+    }
+
+    #[test]
+    fn test_render_drops_separators_of_empty_fields() {
+        let template =
+            FilenameTemplate::parse("{author} - {series} #{ser_no} - {title}.{ext}").unwrap();
+        assert_eq!(
+            template.render(&fields("Мир", "Толстой Лев", "Сага")),
+            Some("Толстой Лев - Сага #2 - Мир.fb2".to_string())
+        );
+        let mut no_series = fields("Мир", "Толстой Лев", "");
+        no_series.ser_no = None;
+        assert_eq!(
+            template.render(&no_series),
+            Some("Толстой Лев - Мир.fb2".to_string())
+        );
+        no_series.author = "";
+        assert_eq!(template.render(&no_series), Some("Мир.fb2".to_string()));
+    }
+
+    #[test]
+    fn test_render_truncates_long_names() {
+        let title = "x".repeat(400);
+        let name = FilenameTemplate::default()
+            .render(&fields(&title, "", ""))
+            .unwrap();
+        assert_eq!(name.chars().count(), MAX_NAME_CHARS);
+        assert!(name.ends_with("x.fb2"));
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        assert_eq!(
+            content_disposition("attachment", "book.fb2"),
+            "attachment; filename=\"book.fb2\""
+        );
+        assert_eq!(
+            content_disposition("inline", "Война и мир.fb2"),
+            "inline; filename=\"Voina i mir.fb2\"; \
+             filename*=UTF-8''%D0%92%D0%BE%D0%B9%D0%BD%D0%B0%20%D0%B8%20%D0%BC%D0%B8%D1%80.fb2"
+        );
+        assert_eq!(ascii_fallback("Café 100%.epub"), "Caf_ 100_.epub");
+    }
+}
@@ -1,21 +1,49 @@
 pub mod auth;
+pub mod compat;
 pub mod covers;
 pub mod download;
+pub mod etag;
+pub mod filename;
+pub mod shared;
 pub mod v1;
 pub mod v2;
 
 use axum::Router;
+use axum::body::HttpBody;
 use axum::extract::ConnectInfo;
-use axum::extract::Request;
+use axum::extract::{OriginalUri, Request, State};
+use axum::http::{HeaderMap, header};
 use axum::middleware::{self, Next};
 use axum::response::Response;
 use axum::routing::get;
 use std::net::SocketAddr;
 
+use crate::db::queries::devices;
 use crate::state::AppState;
 
-/// Logging middleware for OPDS requests.
-async fn opds_logging(request: Request, next: Next) -> Response {
+/// `tracing` target of the OPDS access log, which `server.access_log_path`
+/// sends to its own file.
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+/// Body size in bytes for the access log: the exact body length, else the
+/// `Content-Length` header, else `-` (streamed bodies).
+fn body_size(body: &axum::body::Body, headers: &HeaderMap) -> String {
+    body.size_hint()
+        .exact()
+        .or_else(|| {
+            headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+        })
+        .map_or_else(|| "-".to_string(), |n| n.to_string())
+}
+
+/// Logging middleware for OPDS requests: client address, signed-in user,
+/// request, status, request and response body sizes and duration. With
+/// `stats.user_agents` the line ends with the client's User-Agent, and each
+/// client is also counted in the user's devices.
+async fn opds_logging(State(state): State<AppState>, request: Request, next: Next) -> Response {
     let start = std::time::Instant::now();
     let addr = request
         .extensions()
@@ -23,36 +51,92 @@ async fn opds_logging(request: Request, next: Next) -> Response {
         .map(|ci| ci.0.ip().to_string())
         .unwrap_or_else(|| "-".into());
     let method = request.method().clone();
-    let uri = request.uri().to_string();
+    let uri = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or_else(|| request.uri().to_string(), |uri| uri.0.to_string());
+    let request_size = body_size(request.body(), request.headers());
+    let device = download::client_user_agent(&state, request.headers());
+    let client = device.clone().unwrap_or_else(|| "-".to_string());
 
     let response = next.run(request).await;
 
     let elapsed = start.elapsed();
     let status = response.status().as_u16();
-    tracing::info!("{addr} {method} {uri} {status} {elapsed:.1?}",);
+    let response_size = body_size(response.body(), response.headers());
+    let user = response.extensions().get::<auth::OpdsUser>();
+    let username = user.map_or("-", |u| u.username.as_str());
+    tracing::info!(
+        target: ACCESS_LOG_TARGET,
+        "{addr} {username} {method} {uri} {status} {request_size} {response_size} {elapsed:.1?} \"{client}\""
+    );
+    // A read-only mirror keeps no client records
+    if !state.config.server.read_only
+        && let (Some(user), Some(device)) = (user, device)
+        && let Err(e) = devices::touch(&state.db, user.id, &device).await
+    {
+        tracing::warn!("Failed to record OPDS client of user {}: {e}", user.id);
+    }
 
     response
 }
 
 /// Build the OPDS router with all feed, download, and cover routes.
 pub fn router(state: AppState) -> Router<AppState> {
+    // Feeds answer conditional GETs from the library change stamp
+    let feeds = Router::new().merge(v1::router()).merge(v2::router()).layer(
+        middleware::from_fn_with_state(state.clone(), etag::conditional_get),
+    );
+
     // Auth-protected routes (feeds/search/download)
     let protected = Router::new()
-        .merge(v1::router())
-        .merge(v2::router())
+        .merge(feeds)
         // Download
         .route("/download/{book_id}/{zip_flag}/", get(download::download))
+        .route("/audio/{book_id}/{track}/", get(download::audio_track))
+        // Client profile, inside auth so the user's choice is known
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            compat::client_profile_layer,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::reject_writes_when_read_only,
+        ))
         // Auth middleware
         .layer(middleware::from_fn_with_state(
-            state,
+            state.clone(),
             auth::basic_auth_layer,
         ))
-        .layer(middleware::from_fn(opds_logging));
+        .layer(middleware::from_fn_with_state(state.clone(), opds_logging));
+
+    // Guest shelf links carry their own credentials in the token
+    let shared = Router::new()
+        .route("/shared/{token}/", get(shared::shelf_root))
+        .route("/shared/{token}/{page}/", get(shared::shelf_feed))
+        .route(
+            "/shared/{token}/download/{book_id}/",
+            get(shared::shelf_download),
+        )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            compat::client_profile_layer,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::reject_writes_when_read_only,
+        ))
+        .layer(middleware::from_fn_with_state(state, opds_logging));
 
     // Public routes (covers don't need auth, used by web UI img tags)
     Router::new()
         .route("/cover/{book_id}/", get(covers::cover))
         .route("/thumb/{book_id}/", get(covers::thumbnail))
+        .route(
+            "/catalog-thumb/{catalog_id}/",
+            get(covers::catalog_thumbnail),
+        )
+        .merge(shared)
         .merge(protected)
 }
 
@@ -61,7 +145,7 @@ mod tests {
     use super::*;
     use crate::config::{
         Config, CoversConfig, DatabaseConfig, LibraryConfig, OpdsConfig, ReaderConfig,
-        ScannerConfig, ServerConfig, UploadConfig, WebConfig,
+        ScannerConfig, ServerConfig, ServerProfile, UploadConfig, WebConfig,
     };
     use crate::db::create_test_pool;
     use crate::web::i18n::Translations;
@@ -76,7 +160,18 @@ mod tests {
                 log_level: "info".to_string(),
                 session_secret: "test-secret".to_string(),
                 session_ttl_hours: 24,
+                session_sliding: true,
+                remember_me_ttl_hours: 720,
                 base_url: String::new(),
+                trust_proxy_headers: false,
+                profile: ServerProfile::Default,
+                read_only: false,
+                request_timeout_secs: 60,
+                opds_body_limit_kb: 64,
+                web_body_limit_kb: 256,
+                admin_body_limit_kb: 2048,
+                min_free_space_mb: 0,
+                access_log_path: None,
             },
             library: LibraryConfig {
                 root_path: PathBuf::from("/tmp/books"),
@@ -87,16 +182,25 @@ mod tests {
                 scan_zip: true,
                 zip_codepage: "cp866".to_string(),
                 inpx_enable: false,
+                filename_patterns: Vec::new(),
+                audiobooks: false,
+                detect_lang: true,
+                detect_lang_confidence: 0.3,
             },
             covers: CoversConfig {
                 covers_path: PathBuf::from("/tmp/covers"),
                 cover_max_dimension_px: 600,
                 cover_jpeg_quality: 85,
                 show_covers: true,
+                signed_urls: false,
+                signed_url_ttl_mins: 60,
             },
             database: DatabaseConfig {
                 url: "sqlite::memory:".to_string(),
                 max_connections: 5,
+                retry: 3,
+                retry_backoff_ms: 100,
+                auto_migrate: true,
             },
             opds: OpdsConfig {
                 title: "ROPDS".to_string(),
@@ -107,6 +211,15 @@ mod tests {
                 show_covers: None,
                 alphabet_menu: true,
                 hide_doubles: false,
+                group_formats: false,
+                hide_empty: false,
+                deep_catalogs: false,
+                catalog_thumbnails: false,
+                auto_bookshelf: true,
+                icon_path: None,
+                logo_url: None,
+                absolute_urls: false,
+                dump_page_size: 500,
             },
             scanner: ScannerConfig {
                 schedule_minutes: vec![0],
@@ -117,26 +230,61 @@ mod tests {
                 test_zip: false,
                 test_files: false,
                 workers_num: 1,
+                exclude: Vec::new(),
+                max_depth: 64,
+                changes_log: None,
+                link_identical: false,
             },
             web: WebConfig {
                 language: "en".to_string(),
                 theme: "light".to_string(),
+                accent_color: None,
+                site_name: None,
+                seo_enabled: false,
+                robots_txt: None,
             },
             upload: UploadConfig {
                 allow_upload: true,
                 upload_path: PathBuf::from("/tmp/uploads"),
                 max_upload_size_mb: 10,
+                ..Default::default()
             },
             reader: ReaderConfig::default(),
             oauth: Default::default(),
             smtp: Default::default(),
+            tools: Default::default(),
+            search: Default::default(),
+            sharing: Default::default(),
+            api: Default::default(),
+            password: Default::default(),
+            download: Default::default(),
+            formats: Default::default(),
+            clients: Default::default(),
+            stats: Default::default(),
         };
 
         let db = create_test_pool().await;
         let tera = tera::Tera::default();
         let mut translations = Translations::new();
         translations.insert("en".to_string(), serde_json::json!({}));
-        let state = AppState::new(config, db, tera, translations, false, false);
+        let state = AppState::new(
+            config,
+            db,
+            tera,
+            translations,
+            crate::state::Capabilities::default(),
+        );
         let _router = router(state);
     }
+
+    #[test]
+    fn test_body_size() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(body_size(&axum::body::Body::from("feed"), &headers), "4");
+        let stream =
+            axum::body::Body::from_stream(tokio_stream::empty::<Result<Vec<u8>, std::io::Error>>());
+        assert_eq!(body_size(&stream, &headers), "-");
+        headers.insert(header::CONTENT_LENGTH, "2048".parse().unwrap());
+        assert_eq!(body_size(&stream, &headers), "2048");
+    }
 }
@@ -0,0 +1,163 @@
+//! Guest OPDS feed of a shared bookshelf: `/opds/shared/{token}/` lists the
+//! books on the shelf of the user who created the link, and its acquisition
+//! links download them without credentials. See [`crate::web::share`].
+
+use axum::extract::{Path, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+
+use crate::db::queries::{authors, books, bookshelf, users};
+use crate::state::AppState;
+use crate::web::share::resolve_shelf_token;
+
+use super::v1::helpers::{DEFAULT_UPDATED, atom_response, detect_opds_lang, error_response, tr};
+use super::v1::xml::{self, FeedBuilder};
+
+/// GET /opds/shared/:token/
+pub async fn shelf_root(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(token): Path<String>,
+) -> Response {
+    build_shelf_feed(&state, &headers, &token, 1).await
+}
+
+/// GET /opds/shared/:token/:page/
+pub async fn shelf_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((token, page)): Path<(String, i32)>,
+) -> Response {
+    build_shelf_feed(&state, &headers, &token, page.max(1)).await
+}
+
+async fn build_shelf_feed(
+    state: &AppState,
+    headers: &HeaderMap,
+    token: &str,
+    page: i32,
+) -> Response {
+    let user_id = match resolve_shelf_token(state, token).await {
+        Ok(Some(uid)) => uid,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Link expired or revoked"),
+        Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "DB error"),
+    };
+    let lang = detect_opds_lang(headers, &state.config, None);
+    let owner = users::get_username(&state.db, user_id)
+        .await
+        .unwrap_or_default();
+
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let offset = (page - 1) * max_items;
+    let base = format!("/opds/shared/{token}");
+
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let title = format!(
+        "{} — {owner}",
+        tr(state, &lang, "opds", "root_bookshelf", "Book shelf")
+    );
+    let _ = fb.begin_feed(
+        &format!("tag:shared:{user_id}:{page}"),
+        &title,
+        "",
+        DEFAULT_UPDATED,
+        &xml::Breadcrumbs::new(format!("{base}/{page}/"), format!("{base}/")).acquisition(),
+    );
+
+    let book_list = bookshelf::get_by_user(
+        &state.db,
+        user_id,
+        &bookshelf::SortColumn::Date,
+        false,
+        max_items,
+        offset,
+    )
+    .await
+    .unwrap_or_default();
+
+    let has_next = book_list.len() as i32 >= max_items;
+    let prev_href = (page > 1).then(|| format!("{base}/{}/", page - 1));
+    let next_href = has_next.then(|| format!("{base}/{}/", page + 1));
+    let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
+
+    let covers = crate::opds::covers::CoverUrls::new(&state.config);
+    for book in &book_list {
+        let _ = fb.begin_entry(&format!("b:{}", book.id), &book.title, &book.reg_date);
+        let mime = xml::mime_for_format(xml::download_format(&book.format));
+        let _ = fb.write_link(
+            &format!("{base}/download/{}/", book.id),
+            crate::formats::rel(&book.format),
+            mime,
+            None,
+        );
+        if book.cover != 0 {
+            let thumb_href = covers.thumb(book.id);
+            let _ = fb.write_link(
+                &covers.cover(book.id),
+                xml::REL_IMAGE,
+                &crate::opds::covers::cover_mime(book),
+                None,
+            );
+            let _ = fb.write_link(&thumb_href, xml::REL_THUMBNAIL, "image/jpeg", None);
+            let _ = fb.write_link(&thumb_href, xml::REL_THUMBNAIL_LEGACY, "image/jpeg", None);
+        }
+
+        let mut html = format!("<b>Format: </b>{}<br/>", book.format);
+        html.push_str(&format!("<b>Size: </b>{} KB<br/>", book.size / 1024));
+        if !book.annotation.is_empty() {
+            html.push_str(&format!("<p class='book'>{}</p>", book.annotation));
+        }
+        let _ = fb.write_content_html(&html);
+
+        if let Ok(book_authors) = authors::get_for_book(&state.db, book.id).await {
+            for author in &book_authors {
+                let _ = fb.write_author_obj(&xml::Author {
+                    name: author.full_name.clone(),
+                });
+            }
+        }
+        let _ = fb.end_entry();
+    }
+
+    match fb.finish() {
+        Ok(body) => atom_response(body),
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "XML error"),
+    }
+}
+
+/// GET /opds/shared/:token/download/:book_id/ — download a book from a shared
+/// shelf. Only books that are still on the shelf can be fetched.
+pub async fn shelf_download(
+    State(state): State<AppState>,
+    Path((token, book_id)): Path<(String, i64)>,
+    request: Request,
+) -> Response {
+    shared_book_response(&state, &token, book_id, request).await
+}
+
+/// Serve `book_id` to a holder of the shelf link `token`.
+pub async fn shared_book_response(
+    state: &AppState,
+    token: &str,
+    book_id: i64,
+    request: Request,
+) -> Response {
+    let gone = || error_response(StatusCode::NOT_FOUND, "Link expired or revoked");
+    let db_error = || error_response(StatusCode::INTERNAL_SERVER_ERROR, "DB error");
+    let user_id = match resolve_shelf_token(state, token).await {
+        Ok(Some(uid)) => uid,
+        Ok(None) => return gone(),
+        Err(_) => return db_error(),
+    };
+    match bookshelf::is_on_shelf(&state.db, user_id, book_id).await {
+        Ok(true) => {}
+        Ok(false) => return error_response(StatusCode::NOT_FOUND, "Book not found"),
+        Err(_) => return db_error(),
+    }
+    let book = match books::get_by_id(&state.db, book_id).await {
+        Ok(Some(b)) => b,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Book not found"),
+        Err(_) => return db_error(),
+    };
+    super::download::book_file_response(state, &book, None, request).await
+}
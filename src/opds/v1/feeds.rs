@@ -2,12 +2,19 @@ use axum::extract::{Path, Query, State};
 use axum::http::{StatusCode, header};
 use axum::response::{IntoResponse, Response};
 
-use crate::db::queries::{authors, books, catalogs, genres, series};
+use crate::db::queries::books::{BookSort, RecentKind, SearchFilter, SearchScope};
+use crate::db::queries::downloads::{self, POPULAR_PERIODS};
+use crate::db::queries::{
+    MatchMode, authors, book_terms, books, bookshelf, cached, catalogs, fuzzy, genres, series,
+};
 use crate::state::AppState;
 
 use super::helpers::*;
 use super::xml::{self, FeedBuilder};
-use super::{AuthorsListParams, AuthorsParams, CatalogsParams, LangQuery, SearchBooksParams};
+use super::{
+    AuthorsListParams, AuthorsParams, BooksQuery, BookshelfQuery, CatalogSearchParams,
+    CatalogsParams, CatalogsQuery, DumpQuery, LangQuery, PopularQuery, SearchBooksParams,
+};
 
 /// GET /opds/ — Root navigation feed.
 pub async fn root_feed(
@@ -39,6 +46,8 @@ async fn build_root_feed(
     let by_series = tr(state, &lang, "opds", "root_by_series", "By Series");
     let by_title = tr(state, &lang, "opds", "root_by_title", "By Title");
     let by_recent = tr(state, &lang, "opds", "root_by_recent", "Recently Added");
+    let by_updated = tr(state, &lang, "opds", "root_by_updated", "Recently Updated");
+    let by_popular = tr(state, &lang, "opds", "root_by_popular", "Popular");
     let language_facets = tr(
         state,
         &lang,
@@ -88,6 +97,20 @@ async fn build_root_feed(
         "root_content_recent",
         "Browse newly scanned books",
     );
+    let by_updated_content = tr(
+        state,
+        &lang,
+        "opds",
+        "root_content_updated",
+        "Books replaced with changed files",
+    );
+    let by_popular_content = tr(
+        state,
+        &lang,
+        "opds",
+        "root_content_popular",
+        "Most downloaded books",
+    );
     let language_facets_content = tr(
         state,
         &lang,
@@ -98,21 +121,21 @@ async fn build_root_feed(
     let title = &state.config.opds.title;
     let subtitle = &state.config.opds.subtitle;
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     if fb
         .begin_feed(
             "tag:root",
             title,
             subtitle,
             DEFAULT_UPDATED,
-            "/opds/",
-            "/opds/",
+            &xml::Breadcrumbs::new("/opds/", "/opds/"),
         )
         .is_err()
     {
         return error_response(StatusCode::INTERNAL_SERVER_ERROR, "XML error");
     }
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
+    let _ = fb.write_link("/opds/all/1/", xml::REL_CRAWLABLE, xml::ACQ_TYPE, None);
     write_language_facets_as_root_lang_paths(&mut fb, state, &lang);
 
     let entries: Vec<(&str, String, String, String)> = vec![
@@ -152,6 +175,18 @@ async fn build_root_feed(
             add_lang_query("/opds/recent/", &lang),
             by_recent_content,
         ),
+        (
+            "m:11",
+            by_updated,
+            add_lang_query("/opds/updated/", &lang),
+            by_updated_content,
+        ),
+        (
+            "m:9",
+            by_popular,
+            add_lang_query("/opds/popular/", &lang),
+            by_popular_content,
+        ),
         (
             "m:7",
             language_facets,
@@ -178,6 +213,32 @@ async fn build_root_feed(
             &content,
             DEFAULT_UPDATED,
         );
+        let _ = fb.write_nav_entry(
+            "m:10",
+            &tr(state, &lang, "opds", "root_recommended", "Recommended"),
+            &add_lang_query("/opds/recommended/", &lang),
+            &tr(
+                state,
+                &lang,
+                "opds",
+                "root_content_recommended",
+                "Books similar to the ones you read",
+            ),
+            DEFAULT_UPDATED,
+        );
+        let _ = fb.write_nav_entry(
+            "m:12",
+            &tr(state, &lang, "opds", "root_next", "Next in series"),
+            &add_lang_query("/opds/next/", &lang),
+            &tr(
+                state,
+                &lang,
+                "opds",
+                "root_content_next",
+                "The next volume of each series you have finished a book of",
+            ),
+            DEFAULT_UPDATED,
+        );
     }
 
     match fb.finish() {
@@ -190,9 +251,18 @@ async fn build_root_feed(
 pub async fn catalogs_root(
     State(state): State<AppState>,
     headers: axum::http::HeaderMap,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<CatalogsQuery>,
 ) -> Response {
-    build_catalogs_feed(&state, &headers, q.lang.as_deref(), 0, 1).await
+    build_catalogs_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        q.deep.as_deref(),
+        q.sort.as_deref(),
+        0,
+        1,
+    )
+    .await
 }
 
 /// GET /opds/catalogs/:cat_id/
@@ -201,12 +271,14 @@ pub async fn catalogs_feed(
     State(state): State<AppState>,
     headers: axum::http::HeaderMap,
     Path(p): Path<CatalogsParams>,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<CatalogsQuery>,
 ) -> Response {
     build_catalogs_feed(
         &state,
         &headers,
         q.lang.as_deref(),
+        q.deep.as_deref(),
+        q.sort.as_deref(),
         p.cat_id,
         p.page.unwrap_or(1).max(1),
     )
@@ -217,70 +289,127 @@ async fn build_catalogs_feed(
     state: &AppState,
     headers: &axum::http::HeaderMap,
     query_lang: Option<&str>,
+    query_deep: Option<&str>,
+    query_sort: Option<&str>,
     cat_id: i64,
     page: i32,
 ) -> Response {
     let lang = detect_opds_lang(headers, &state.config, query_lang);
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let offset = (page - 1) * max_items;
+    let deep = state.config.opds.catalog_deep(query_deep);
+    // Keep an explicit ?deep= choice on every catalog link of the feed.
+    let deep_qs = query_deep
+        .map(|_| format!("?deep={}", u8::from(deep)))
+        .unwrap_or_default();
+    let sort = BookSort::resolve(&BookSort::LISTING, query_sort);
+    // Book pages also keep an explicit ?sort= choice.
+    let books_qs = match query_sort {
+        Some(_) => add_query_param(&deep_qs, "sort", sort.as_str()),
+        None => deep_qs.clone(),
+    };
+    let chain = if cat_id > 0 {
+        catalogs::get_ancestors(&state.db, cat_id)
+            .await
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    // Books in this catalog, or its whole subtree when flattened (paginated)
+    let hide_doubles = state.config.opds.hide_doubles;
+    let book_list = match chain.last() {
+        _ if cat_id == 0 => Ok(Vec::new()),
+        Some(_) if deep => {
+            books::get_by_catalog_tree(&state.db, cat_id, max_items, offset, hide_doubles, sort)
+                .await
+        }
+        _ => books::get_by_catalog(&state.db, cat_id, max_items, offset, hide_doubles, sort).await,
+    }
+    .unwrap_or_default();
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     let self_href = if cat_id == 0 {
-        add_lang_query("/opds/catalogs/", &lang)
+        add_lang_query(&format!("/opds/catalogs/{deep_qs}"), &lang)
     } else {
-        add_lang_query(&format!("/opds/catalogs/{cat_id}/{page}/"), &lang)
+        add_lang_query(&format!("/opds/catalogs/{cat_id}/{page}/{books_qs}"), &lang)
     };
+    let mut crumbs = xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang));
+    // Up to the parent catalog (or the catalogs root for top-level ones)
+    if cat_id > 0 {
+        let (up_href, up_title) = match chain.len().checked_sub(2).map(|i| &chain[i]) {
+            Some(parent) => (
+                format!("/opds/catalogs/{}/{deep_qs}", parent.id),
+                parent.cat_name.as_str(),
+            ),
+            None => (format!("/opds/catalogs/{deep_qs}"), "Catalogs"),
+        };
+        crumbs = crumbs.up(add_lang_query(&up_href, &lang), up_title);
+    }
+    if !book_list.is_empty() {
+        crumbs = crumbs.acquisition();
+    }
     let _ = fb.begin_feed(
         &format!("tag:catalogs:{cat_id}:{page}"),
         "Catalogs",
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        &add_lang_query("/opds/", &lang),
+        &crumbs,
     );
+    // Searching from a catalog stays within its subtree.
+    let scope = (cat_id > 0).then_some(SearchScope::Catalog(cat_id));
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
-        &add_lang_query("/opds/search/{searchTerms}/", &lang),
+        &add_lang_query(&search_template_href(scope), &lang),
     );
     write_language_facets_for_href(&mut fb, state, &lang, "/opds/catalogs/");
 
     // Child catalogs (only on page 1 — subcatalogs are not paginated)
     if page == 1 {
         let cats = if cat_id == 0 {
-            catalogs::get_root_catalogs(&state.db)
-                .await
-                .unwrap_or_default()
+            cached::root_catalogs(state).await.unwrap_or_default()
         } else {
             catalogs::get_children(&state.db, cat_id)
                 .await
                 .unwrap_or_default()
         };
 
+        let covers = &state.config.covers;
+        let thumbnails = state.config.opds.catalog_thumbnails && covers.show_covers;
+        let urls = crate::opds::covers::CoverUrls::new(&state.config);
         for cat in &cats {
-            let href = add_lang_query(&format!("/opds/catalogs/{}/", cat.id), &lang);
-            let _ = fb.write_nav_entry(
+            let href = add_lang_query(&format!("/opds/catalogs/{}/{deep_qs}", cat.id), &lang);
+            let thumb = (thumbnails
+                && crate::scanner::catalog_mosaic_path(&covers.covers_path, cat.id).is_file())
+            .then(|| urls.catalog_thumb(cat.id));
+            let _ = fb.write_nav_entry_with_thumbnail(
                 &format!("c:{}", cat.id),
                 &cat.cat_name,
                 &href,
                 "",
                 DEFAULT_UPDATED,
+                thumb.as_deref(),
             );
         }
     }
 
-    // Books in this catalog (paginated)
     if cat_id > 0 {
-        let hide_doubles = state.config.opds.hide_doubles;
-        let book_list = books::get_by_catalog(&state.db, cat_id, max_items, offset, hide_doubles)
-            .await
-            .unwrap_or_default();
+        if !book_list.is_empty() || page > 1 {
+            write_sort_facets(
+                &mut fb,
+                state,
+                &lang,
+                &format!("/opds/catalogs/{cat_id}/{deep_qs}"),
+                &BookSort::LISTING,
+                sort,
+            );
+        }
 
         // Pagination links
         let has_next = book_list.len() as i32 >= max_items;
         let has_prev = page > 1;
         let prev_href = if has_prev {
             Some(add_lang_query(
-                &format!("/opds/catalogs/{cat_id}/{}/", page - 1),
+                &format!("/opds/catalogs/{cat_id}/{}/{books_qs}", page - 1),
                 &lang,
             ))
         } else {
@@ -288,7 +417,7 @@ async fn build_catalogs_feed(
         };
         let next_href = if has_next {
             Some(add_lang_query(
-                &format!("/opds/catalogs/{cat_id}/{}/", page + 1),
+                &format!("/opds/catalogs/{cat_id}/{}/{books_qs}", page + 1),
                 &lang,
             ))
         } else {
@@ -326,15 +455,24 @@ pub async fn authors_root(
 
 /// GET /opds/authors/:lang_code/ — Alphabet drill-down for authors.
 /// GET /opds/authors/:lang_code/:prefix/ — Drill down by prefix.
+/// With `alphabet_menu` off both serve the paginated list instead.
 pub async fn authors_feed(
     State(state): State<AppState>,
     Path(params): Path<AuthorsParams>,
 ) -> Response {
     let lang_code = params.lang_code;
     let prefix = params.prefix.unwrap_or_default();
+    if !state.config.opds.alphabet_menu {
+        let params = AuthorsListParams {
+            lang_code,
+            prefix,
+            page: None,
+        };
+        return authors_list(State(state), Path(params)).await;
+    }
     let split_items = state.config.opds.split_items as i64;
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     let self_href = if prefix.is_empty() {
         format!("/opds/authors/{lang_code}/")
     } else {
@@ -348,12 +486,14 @@ pub async fn authors_feed(
         "Authors",
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/").up(
+            drill_up_href("/opds/authors/", lang_code, &prefix),
+            "Authors",
+        ),
     );
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
 
-    let groups = authors::get_name_prefix_groups(&state.db, lang_code, &prefix.to_uppercase())
+    let groups = cached::author_prefix_groups(&state, lang_code, &prefix.to_uppercase())
         .await
         .unwrap_or_default();
 
@@ -393,28 +533,33 @@ pub async fn authors_feed(
 
 /// GET /opds/authors/:lang_code/:prefix/list/ — Paginated author listing for a prefix.
 /// GET /opds/authors/:lang_code/:prefix/list/:page/
+/// GET /opds/authors/:lang_code/list/:page/ — Flat listing without a prefix.
 pub async fn authors_list(
     State(state): State<AppState>,
     Path(params): Path<AuthorsListParams>,
 ) -> Response {
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let lang_code = params.lang_code;
     let prefix = params.prefix;
     let page = params.page.unwrap_or(1).max(1);
     let offset = (page - 1) * max_items;
 
-    let mut fb = FeedBuilder::new();
-    let self_href = format!(
-        "/opds/authors/{lang_code}/{}/list/{page}/",
-        urlencoding::encode(&prefix)
-    );
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = list_href("/opds/authors/", lang_code, &prefix, page);
+    let title = if prefix.is_empty() {
+        "Authors".to_string()
+    } else {
+        format!("Authors: {prefix}")
+    };
     let _ = fb.begin_feed(
         &format!("tag:authors:{lang_code}:{prefix}:list:{page}"),
-        &format!("Authors: {prefix}"),
+        &title,
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/").up(
+            drill_up_href("/opds/authors/", lang_code, &prefix),
+            "Authors",
+        ),
     );
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
 
@@ -430,20 +575,13 @@ pub async fn authors_list(
 
     let has_next = author_list.len() as i32 >= max_items;
     let has_prev = page > 1;
-    let encoded_prefix = urlencoding::encode(&prefix);
     let prev_href = if has_prev {
-        Some(format!(
-            "/opds/authors/{lang_code}/{encoded_prefix}/list/{}/",
-            page - 1
-        ))
+        Some(list_href("/opds/authors/", lang_code, &prefix, page - 1))
     } else {
         None
     };
     let next_href = if has_next {
-        Some(format!(
-            "/opds/authors/{lang_code}/{encoded_prefix}/list/{}/",
-            page + 1
-        ))
+        Some(list_href("/opds/authors/", lang_code, &prefix, page + 1))
     } else {
         None
     };
@@ -485,15 +623,24 @@ pub async fn series_root(
 
 /// GET /opds/series/:lang_code/ — Alphabet drill-down for series.
 /// GET /opds/series/:lang_code/:prefix/ — Drill down by prefix.
+/// With `alphabet_menu` off both serve the paginated list instead.
 pub async fn series_feed(
     State(state): State<AppState>,
     Path(params): Path<AuthorsParams>,
 ) -> Response {
     let lang_code = params.lang_code;
     let prefix = params.prefix.unwrap_or_default();
+    if !state.config.opds.alphabet_menu {
+        let params = AuthorsListParams {
+            lang_code,
+            prefix,
+            page: None,
+        };
+        return series_list(State(state), Path(params)).await;
+    }
     let split_items = state.config.opds.split_items as i64;
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     let self_href = if prefix.is_empty() {
         format!("/opds/series/{lang_code}/")
     } else {
@@ -504,12 +651,12 @@ pub async fn series_feed(
         "Series",
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/")
+            .up(drill_up_href("/opds/series/", lang_code, &prefix), "Series"),
     );
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
 
-    let groups = series::get_name_prefix_groups(&state.db, lang_code, &prefix.to_uppercase())
+    let groups = cached::series_prefix_groups(&state, lang_code, &prefix.to_uppercase())
         .await
         .unwrap_or_default();
 
@@ -549,28 +696,31 @@ pub async fn series_feed(
 
 /// GET /opds/series/:lang_code/:prefix/list/ — Paginated series listing for a prefix.
 /// GET /opds/series/:lang_code/:prefix/list/:page/
+/// GET /opds/series/:lang_code/list/:page/ — Flat listing without a prefix.
 pub async fn series_list(
     State(state): State<AppState>,
     Path(params): Path<AuthorsListParams>,
 ) -> Response {
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let lang_code = params.lang_code;
     let prefix = params.prefix;
     let page = params.page.unwrap_or(1).max(1);
     let offset = (page - 1) * max_items;
 
-    let mut fb = FeedBuilder::new();
-    let self_href = format!(
-        "/opds/series/{lang_code}/{}/list/{page}/",
-        urlencoding::encode(&prefix)
-    );
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = list_href("/opds/series/", lang_code, &prefix, page);
+    let title = if prefix.is_empty() {
+        "Series".to_string()
+    } else {
+        format!("Series: {prefix}")
+    };
     let _ = fb.begin_feed(
         &format!("tag:series:{lang_code}:{prefix}:list:{page}"),
-        &format!("Series: {prefix}"),
+        &title,
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/")
+            .up(drill_up_href("/opds/series/", lang_code, &prefix), "Series"),
     );
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
 
@@ -586,20 +736,13 @@ pub async fn series_list(
 
     let has_next = series_list.len() as i32 >= max_items;
     let has_prev = page > 1;
-    let encoded_prefix = urlencoding::encode(&prefix);
     let prev_href = if has_prev {
-        Some(format!(
-            "/opds/series/{lang_code}/{encoded_prefix}/list/{}/",
-            page - 1
-        ))
+        Some(list_href("/opds/series/", lang_code, &prefix, page - 1))
     } else {
         None
     };
     let next_href = if has_next {
-        Some(format!(
-            "/opds/series/{lang_code}/{encoded_prefix}/list/{}/",
-            page + 1
-        ))
+        Some(list_href("/opds/series/", lang_code, &prefix, page + 1))
     } else {
         None
     };
@@ -629,15 +772,17 @@ pub async fn genres_root(
     Query(q): Query<LangQuery>,
 ) -> Response {
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
 
     let _ = fb.begin_feed(
         "tag:genres",
         "Genres",
         "",
         DEFAULT_UPDATED,
-        &add_lang_query("/opds/genres/", &lang),
-        &add_lang_query("/opds/", &lang),
+        &xml::Breadcrumbs::new(
+            add_lang_query("/opds/genres/", &lang),
+            add_lang_query("/opds/", &lang),
+        ),
     );
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
@@ -645,15 +790,21 @@ pub async fn genres_root(
     );
     write_language_facets_for_href(&mut fb, &state, &lang, "/opds/genres/");
 
-    let sections = genres::get_sections(&state.db, &lang)
+    let mut sections = cached::genre_sections(&state, &lang)
         .await
         .unwrap_or_default();
+    let counts = cached::item_counts(&state).await.unwrap_or_default();
+    if state.config.opds.hide_empty {
+        sections.retain(|(code, _)| counts.section(code) > 0);
+    }
+    let books_prefix = tr(&state, &lang, "opds", "books_count_prefix", "Books");
     for (i, (code, name)) in sections.iter().enumerate() {
         let href = add_lang_query(
             &format!("/opds/genres/{}/", urlencoding::encode(code)),
             &lang,
         );
-        let _ = fb.write_nav_entry(&format!("gs:{i}"), name, &href, "", DEFAULT_UPDATED);
+        let content = format!("{books_prefix}: {}", counts.section(code));
+        let _ = fb.write_nav_entry(&format!("gs:{i}"), name, &href, &content, DEFAULT_UPDATED);
     }
 
     match fb.finish() {
@@ -670,14 +821,14 @@ pub async fn genres_by_section(
     Query(q): Query<LangQuery>,
 ) -> Response {
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
 
     let self_href = add_lang_query(
         &format!("/opds/genres/{}/", urlencoding::encode(&section_code)),
         &lang,
     );
 
-    let genre_list = genres::get_by_section(&state.db, &section_code, &lang)
+    let mut genre_list = cached::genres_in_section(&state, &section_code, &lang)
         .await
         .unwrap_or_default();
 
@@ -685,14 +836,18 @@ pub async fn genres_by_section(
         .first()
         .map(|g| g.section.clone())
         .unwrap_or_else(|| section_code.clone());
+    let counts = cached::item_counts(&state).await.unwrap_or_default();
+    if state.config.opds.hide_empty {
+        genre_list.retain(|g| counts.genre(g.id) > 0);
+    }
 
     let _ = fb.begin_feed(
         &format!("tag:genres:{section_code}"),
         &section_title,
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        &add_lang_query("/opds/", &lang),
+        &xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang))
+            .up(add_lang_query("/opds/genres/", &lang), "Genres"),
     );
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
@@ -705,13 +860,15 @@ pub async fn genres_by_section(
         &format!("/opds/genres/{}/", urlencoding::encode(&section_code)),
     );
 
+    let books_prefix = tr(&state, &lang, "opds", "books_count_prefix", "Books");
     for genre in &genre_list {
         let href = add_lang_query(&format!("/opds/search/books/g/{}/", genre.id), &lang);
+        let content = format!("{books_prefix}: {}", counts.genre(genre.id));
         let _ = fb.write_nav_entry(
             &format!("g:{}", genre.id),
             &genre.subsection,
             &href,
-            &genre.code,
+            &content,
             DEFAULT_UPDATED,
         );
     }
@@ -739,14 +896,13 @@ pub async fn language_facets_feed(
         "Browse OPDS catalog in",
     );
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     let _ = fb.begin_feed(
         "tag:facets:languages",
         &facets_title,
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        &add_lang_query("/opds/", &lang),
+        &xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang)),
     );
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
@@ -795,15 +951,26 @@ pub async fn books_root(
 
 /// GET /opds/books/:lang_code/
 /// GET /opds/books/:lang_code/:prefix/
+/// With `alphabet_menu` off both serve the flat paginated list instead.
 pub async fn books_feed(
     State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
     Path(params): Path<AuthorsParams>,
+    query: Query<LangQuery>,
 ) -> Response {
     let lang_code = params.lang_code;
+    if !state.config.opds.alphabet_menu {
+        let params = AuthorsListParams {
+            lang_code,
+            prefix: String::new(),
+            page: None,
+        };
+        return books_list(State(state), headers, Path(params), query).await;
+    }
     let prefix = params.prefix.unwrap_or_default();
     let split_items = state.config.opds.split_items as i64;
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     let self_href = if prefix.is_empty() {
         format!("/opds/books/{lang_code}/")
     } else {
@@ -814,12 +981,12 @@ pub async fn books_feed(
         "Books",
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/")
+            .up(drill_up_href("/opds/books/", lang_code, &prefix), "Books"),
     );
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
 
-    let groups = books::get_title_prefix_groups(&state.db, lang_code, &prefix.to_uppercase())
+    let groups = cached::title_prefix_groups(&state, lang_code, &prefix.to_uppercase())
         .await
         .unwrap_or_default();
 
@@ -854,13 +1021,73 @@ pub async fn books_feed(
     }
 }
 
+/// GET /opds/books/:lang_code/list/:page/ — Flat paginated title listing.
+pub async fn books_list(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path(params): Path<AuthorsListParams>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let lang_code = params.lang_code;
+    let page = params.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * max_items;
+
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let _ = fb.begin_feed(
+        &format!("tag:books:{lang_code}:list:{page}"),
+        "Books",
+        "",
+        DEFAULT_UPDATED,
+        &xml::Breadcrumbs::new(list_href("/opds/books/", lang_code, "", page), "/opds/")
+            .up(add_lang_query("/opds/books/", &lang), "Books")
+            .acquisition(),
+    );
+    let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
+
+    let book_list = books::get_by_lang_code(
+        &state.db,
+        lang_code,
+        max_items,
+        offset,
+        state.config.opds.hide_doubles,
+        BookSort::Title,
+    )
+    .await
+    .unwrap_or_default();
+
+    let has_next = book_list.len() as i32 >= max_items;
+    let has_prev = page > 1;
+    let prev_href = if has_prev {
+        Some(list_href("/opds/books/", lang_code, "", page - 1))
+    } else {
+        None
+    };
+    let next_href = if has_next {
+        Some(list_href("/opds/books/", lang_code, "", page + 1))
+    } else {
+        None
+    };
+    let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
+
+    for book in &book_list {
+        write_book_entry(&mut fb, &state, book, &lang).await;
+    }
+
+    match fb.finish() {
+        Ok(body) => atom_response(body),
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "XML error"),
+    }
+}
+
 /// GET /opds/recent/
 pub async fn recent_root(
     State(state): State<AppState>,
     headers: axum::http::HeaderMap,
     Query(q): Query<LangQuery>,
 ) -> Response {
-    build_recent_feed(&state, &headers, q.lang.as_deref(), 1).await
+    build_recent_feed(&state, &headers, q.lang.as_deref(), RecentKind::Added, 1).await
 }
 
 /// GET /opds/recent/:page/
@@ -870,55 +1097,152 @@ pub async fn recent_feed(
     Path((page,)): Path<(i32,)>,
     Query(q): Query<LangQuery>,
 ) -> Response {
-    build_recent_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Added,
+        page.max(1),
+    )
+    .await
+}
+
+/// GET /opds/updated/
+pub async fn updated_root(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(&state, &headers, q.lang.as_deref(), RecentKind::Updated, 1).await
+}
+
+/// GET /opds/updated/:page/
+///
+/// Books whose file the scanner found changed (or an admin replaced), by
+/// `last_modified`.
+pub async fn updated_feed(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Updated,
+        page.max(1),
+    )
+    .await
+}
+
+/// GET /opds/recent/genre/:genre_id/
+pub async fn recent_genre_root(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path((genre_id,)): Path<(i64,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Genre(genre_id),
+        1,
+    )
+    .await
+}
+
+/// GET /opds/recent/genre/:genre_id/:page/
+///
+/// Newest books of one genre, for subscribing to a genre's new arrivals.
+pub async fn recent_genre_feed(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path((genre_id, page)): Path<(i64, i32)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Genre(genre_id),
+        page.max(1),
+    )
+    .await
 }
 
 async fn build_recent_feed(
     state: &AppState,
     headers: &axum::http::HeaderMap,
     query_lang: Option<&str>,
+    kind: RecentKind,
     page: i32,
 ) -> Response {
     let lang = detect_opds_lang(headers, &state.config, query_lang);
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let offset = (page - 1) * max_items;
     let hide_doubles = state.config.opds.hide_doubles;
+    let by_recent = tr(state, &lang, "opds", "root_by_recent", "Recently Added");
+    let (base, tag, title) = match kind {
+        RecentKind::Added => (
+            "/opds/recent/".to_string(),
+            "recent".to_string(),
+            by_recent.clone(),
+        ),
+        RecentKind::Updated => (
+            "/opds/updated/".to_string(),
+            "updated".to_string(),
+            tr(state, &lang, "opds", "root_by_updated", "Recently Updated"),
+        ),
+        RecentKind::Genre(id) => {
+            let Ok(Some(genre)) = genres::get_by_id(&state.db, id, &lang).await else {
+                return error_response(StatusCode::NOT_FOUND, "Genre not found");
+            };
+            (
+                format!("/opds/recent/genre/{id}/"),
+                format!("recent:genre:{id}"),
+                format!("{by_recent}: {}", genre.subsection),
+            )
+        }
+    };
 
-    let mut fb = FeedBuilder::new();
-    let self_href = add_lang_query(&format!("/opds/recent/{page}/"), &lang);
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = add_lang_query(&format!("{base}{page}/"), &lang);
+    let mut crumbs =
+        xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang)).acquisition();
+    if let RecentKind::Genre(id) = kind {
+        crumbs = crumbs.up(
+            add_lang_query(&format!("/opds/search/books/g/{id}/"), &lang),
+            "",
+        );
+    }
     let _ = fb.begin_feed(
-        &format!("tag:recent:{page}"),
-        &tr(state, &lang, "opds", "root_by_recent", "Recently Added"),
+        &format!("tag:{tag}:{page}"),
+        &title,
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        &add_lang_query("/opds/", &lang),
+        &crumbs,
     );
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
         &add_lang_query("/opds/search/{searchTerms}/", &lang),
     );
-    write_language_facets_for_href(&mut fb, state, &lang, "/opds/recent/");
+    write_language_facets_for_href(&mut fb, state, &lang, &base);
 
-    let book_list = books::get_recent_added(&state.db, max_items, offset, hide_doubles)
+    let book_list = books::get_recent(&state.db, kind, max_items, offset, hide_doubles)
         .await
         .unwrap_or_default();
 
     let has_next = book_list.len() as i32 >= max_items;
     let has_prev = page > 1;
     let prev_href = if has_prev {
-        Some(add_lang_query(
-            &format!("/opds/recent/{}/", page - 1),
-            &lang,
-        ))
+        Some(add_lang_query(&format!("{base}{}/", page - 1), &lang))
     } else {
         None
     };
     let next_href = if has_next {
-        Some(add_lang_query(
-            &format!("/opds/recent/{}/", page + 1),
-            &lang,
-        ))
+        Some(add_lang_query(&format!("{base}{}/", page + 1), &lang))
     } else {
         None
     };
@@ -934,23 +1258,161 @@ async fn build_recent_feed(
     }
 }
 
+/// GET /opds/all/:page/
+///
+/// Every available book by id, for crawlers (`opds.dump_page_size` per
+/// page). `next` links carry the last id of the page (`?after=`), so
+/// following them neither skips nor repeats books while the library changes
+/// and never scans past skipped rows.
+pub async fn all_books_feed(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<DumpQuery>,
+) -> Response {
+    let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
+    let page = i64::from(page.max(1));
+    let page_size = state.config.opds.dump_page_size.clamp(1, i32::MAX as u32) as i32;
+    let (after_id, offset) = match q.after {
+        Some(after) => (after, 0),
+        None => (0, (page - 1) * i64::from(page_size)),
+    };
+    let book_list = match books::get_all_by_id(&state.db, after_id, page_size, offset).await {
+        Ok(list) => list,
+        Err(_) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, "DB error"),
+    };
+    let total = books::count_recent_added(&state.db, false)
+        .await
+        .unwrap_or(0);
+    let last_page = ((total + i64::from(page_size) - 1) / i64::from(page_size)).max(1);
+    let page_href = |page: i64| add_lang_query(&format!("/opds/all/{page}/"), &lang);
+
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = match q.after {
+        Some(after) => add_query_param(&page_href(page), "after", &after.to_string()),
+        None => page_href(page),
+    };
+    let crumbs = xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang)).acquisition();
+    let _ = fb.begin_feed(
+        &format!("tag:all:{page}"),
+        &tr(&state, &lang, "opds", "all_books", "All books"),
+        "",
+        DEFAULT_UPDATED,
+        &crumbs,
+    );
+
+    let prev_href = (page > 1).then(|| page_href(page - 1));
+    let next_href = match book_list.last() {
+        Some(last) if book_list.len() as i32 >= page_size && page < last_page => Some(
+            add_query_param(&page_href(page + 1), "after", &last.id.to_string()),
+        ),
+        _ => None,
+    };
+    let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
+    let _ = fb.write_link(&page_href(1), "first", xml::ACQ_TYPE, None);
+    let _ = fb.write_link(&page_href(last_page), "last", xml::ACQ_TYPE, None);
+
+    for book in &book_list {
+        write_book_entry(&mut fb, &state, book, &lang).await;
+    }
+
+    match fb.finish() {
+        Ok(body) => atom_response(body),
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "XML error"),
+    }
+}
+
+/// GET /opds/popular/
+pub async fn popular_root(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<PopularQuery>,
+) -> Response {
+    build_popular_feed(&state, &headers, &q, 1).await
+}
+
+/// GET /opds/popular/:page/
+pub async fn popular_feed(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<PopularQuery>,
+) -> Response {
+    build_popular_feed(&state, &headers, &q, page.max(1)).await
+}
+
+/// Most downloaded books of the selected period, from the cached counts.
+async fn build_popular_feed(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    q: &PopularQuery,
+    page: i32,
+) -> Response {
+    let lang = detect_opds_lang(headers, &state.config, q.lang.as_deref());
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let offset = (page - 1) * max_items;
+    let period = downloads::popular_period(q.period.as_deref());
+    let page_href =
+        |page: i32| add_lang_query(&format!("/opds/popular/{page}/?period={period}"), &lang);
+
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let _ = fb.begin_feed(
+        &format!("tag:popular:{period}:{page}"),
+        &tr(state, &lang, "opds", "root_by_popular", "Popular"),
+        "",
+        DEFAULT_UPDATED,
+        &xml::Breadcrumbs::new(page_href(page), add_lang_query("/opds/", &lang)).acquisition(),
+    );
+    let _ = fb.write_search_links(
+        &add_lang_query("/opds/search/", &lang),
+        &add_lang_query("/opds/search/{searchTerms}/", &lang),
+    );
+    let period_group = tr(state, &lang, "opds", "facet_period", "Period");
+    let days_label = tr(state, &lang, "home", "days", "days");
+    for days in POPULAR_PERIODS {
+        let _ = fb.write_facet_link(
+            &add_lang_query(&format!("/opds/popular/?period={days}"), &lang),
+            xml::ACQ_TYPE,
+            &format!("{days} {days_label}"),
+            &period_group,
+            days == period,
+        );
+    }
+
+    let book_list = books::get_popular(&state.db, period, max_items, offset)
+        .await
+        .unwrap_or_default();
+
+    let prev_href = (page > 1).then(|| page_href(page - 1));
+    let next_href = (book_list.len() as i32 >= max_items).then(|| page_href(page + 1));
+    let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
+
+    for (book, _) in &book_list {
+        write_book_entry(&mut fb, state, book, &lang).await;
+    }
+
+    match fb.finish() {
+        Ok(body) => atom_response(body),
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "XML error"),
+    }
+}
+
 /// GET /opds/search/:terms/ — Search type selection.
 pub async fn search_types_feed(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path((terms,)): Path<(String,)>,
 ) -> Response {
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     let self_href = format!("/opds/search/{}/", urlencoding::encode(&terms));
     let _ = fb.begin_feed(
         &format!("tag:search:{terms}"),
         &format!("Search: {terms}"),
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/"),
     );
 
-    let entries = [
+    let mut entries = vec![
         (
             "st:1",
             "Search by title",
@@ -966,7 +1428,19 @@ pub async fn search_types_feed(
             "Search by series",
             format!("/opds/search/series/m/{}/", urlencoding::encode(&terms)),
         ),
+        (
+            "st:4",
+            "Search by author last name",
+            format!("/opds/search/authors/b/{}/", urlencoding::encode(&terms)),
+        ),
     ];
+    if state.config.search.fulltext {
+        entries.push((
+            "st:5",
+            "Search inside books",
+            format!("/opds/search/books/t/{}/", urlencoding::encode(&terms)),
+        ));
+    }
     for (id, title, href) in &entries {
         let _ = fb.write_nav_entry(id, title, href, "", DEFAULT_UPDATED);
     }
@@ -980,100 +1454,190 @@ pub async fn search_types_feed(
 /// GET /opds/search/books/:search_type/:terms/
 /// GET /opds/search/books/:search_type/:terms/:page/
 ///
-/// Search types: b=begins, m=contains, e=exact, a=by author id, s=by series id, g=by genre id
+/// Search types: b=begins, m=contains, e=exact, a=by author id, s=by series id, g=by genre id,
+/// t=inside book texts (with `search.fulltext`).
+/// Title searches take `?genre=`, `?language=` and `?format=` facet filters,
+/// and `?scope=` (`c:ID`, `a:ID`, `s:ID`) to stay within a catalog, author or series.
 pub async fn search_books_feed(
     State(state): State<AppState>,
     headers: axum::http::HeaderMap,
     Path(params): Path<SearchBooksParams>,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<BooksQuery>,
 ) -> Response {
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let page = params.page.unwrap_or(1).max(1);
     let offset = (page - 1) * max_items;
     let search_type = &params.search_type;
     let terms = &params.terms;
-
-    let mut fb = FeedBuilder::new();
-    let self_href = add_lang_query(
-        &format!(
-            "/opds/search/books/{}/{}/{}/",
-            search_type,
-            urlencoding::encode(terms),
-            page
-        ),
-        &lang,
+    let sort_choices: &[BookSort] = if search_type == "s" {
+        &BookSort::SERIES_LISTING
+    } else {
+        &BookSort::LISTING
+    };
+    let sort = BookSort::resolve(sort_choices, q.sort.as_deref());
+    let text_search = search_type == "t";
+    let title_search = !matches!(search_type.as_str(), "a" | "s" | "g" | "t");
+    let mode = MatchMode::from_search_type(search_type);
+    let filter = if title_search {
+        q.search_filter()
+    } else {
+        SearchFilter::default()
+    };
+    let listing_href = format!(
+        "/opds/search/books/{}/{}/",
+        search_type,
+        urlencoding::encode(terms)
     );
+    // Keep an explicit ?sort= choice on self, pagination and facet links.
+    let keep_sort = |href: String| match q.sort {
+        Some(_) => add_query_param(&href, "sort", sort.as_str()),
+        None => href,
+    };
+    let page_href = |page: i32| {
+        add_lang_query(
+            &add_search_filter(&keep_sort(format!("{listing_href}{page}/")), &filter),
+            &lang,
+        )
+    };
+
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let mut crumbs =
+        xml::Breadcrumbs::new(page_href(page), add_lang_query("/opds/", &lang)).acquisition();
+    // Scoped title searches go back to their scope, others to the search
+    // type selection.
+    if title_search || text_search {
+        let (up_href, up_title) = match filter.scope {
+            Some(SearchScope::Catalog(id)) => (format!("/opds/catalogs/{id}/"), ""),
+            Some(SearchScope::Author(id)) => (format!("/opds/search/books/a/{id}/"), ""),
+            Some(SearchScope::Series(id)) => (format!("/opds/search/books/s/{id}/"), ""),
+            None => (
+                format!("/opds/search/{}/", urlencoding::encode(terms)),
+                "Search",
+            ),
+        };
+        crumbs = crumbs.up(add_lang_query(&up_href, &lang), up_title);
+    }
     let _ = fb.begin_feed(
         &format!("tag:search:books:{search_type}:{terms}:{page}"),
         &format!("Search: {terms}"),
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        &add_lang_query("/opds/", &lang),
+        &crumbs,
     );
+    // Searching from an author's or series' books stays within them.
+    let scope = match search_type.as_str() {
+        "a" => terms.parse().ok().map(SearchScope::Author),
+        "s" => terms.parse().ok().map(SearchScope::Series),
+        _ => filter.scope,
+    };
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
-        &add_lang_query("/opds/search/{searchTerms}/", &lang),
+        &add_lang_query(&search_template_href(scope), &lang),
     );
 
     let hide_doubles = state.config.opds.hide_doubles;
+    let mut fuzzy_results = false;
     let book_list = match search_type.as_str() {
         "a" => {
             // By author ID
             let author_id: i64 = terms.parse().unwrap_or(0);
-            books::get_by_author(&state.db, author_id, max_items, offset, hide_doubles)
+            books::get_by_author(&state.db, author_id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default()
         }
         "s" => {
             // By series ID
             let series_id: i64 = terms.parse().unwrap_or(0);
-            books::get_by_series(&state.db, series_id, max_items, offset, hide_doubles)
+            books::get_by_series(&state.db, series_id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default()
         }
         "g" => {
             // By genre ID
             let genre_id: i64 = terms.parse().unwrap_or(0);
-            books::get_by_genre(&state.db, genre_id, max_items, offset, hide_doubles)
+            let _ = fb.write_link(
+                &add_lang_query(&format!("/opds/recent/genre/{genre_id}/"), &lang),
+                xml::REL_SORT_NEW,
+                xml::ACQ_TYPE,
+                Some(&tr(
+                    &state,
+                    &lang,
+                    "opds",
+                    "root_by_recent",
+                    "Recently Added",
+                )),
+            );
+            books::get_by_genre(&state.db, genre_id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default()
         }
+        "t" if state.config.search.fulltext => {
+            let terms = crate::fulltext::query_terms(terms);
+            book_terms::search(&state.db, &terms, max_items, offset)
+                .await
+                .unwrap_or_default()
+        }
+        "t" => Vec::new(),
         _ => {
-            // Title search: m=contains, b=begins, e=exact
+            // Title search: m=contains, b=a word begins, e=whole title
             let search_term = terms.to_uppercase();
-            books::search_by_title(&state.db, &search_term, max_items, offset, hide_doubles)
+            let found = books::search_by_title(
+                &state.db,
+                &search_term,
+                mode,
+                max_items,
+                offset,
+                hide_doubles,
+                sort,
+                state.config.search.translit,
+                &filter,
+            )
+            .await
+            .unwrap_or_default();
+            if found.is_empty() && page == 1 && filter.is_empty() && state.config.search.fuzzy {
+                fuzzy_results = true;
+                fuzzy::search_books(
+                    &state.db,
+                    &search_term,
+                    max_items,
+                    hide_doubles,
+                    state.config.search.fuzzy_threshold,
+                    state.pg_trgm_available,
+                )
                 .await
                 .unwrap_or_default()
+            } else {
+                found
+            }
         }
     };
 
-    // Pagination
-    let has_next = book_list.len() as i32 >= max_items;
-    let has_prev = page > 1;
-    let prev_href = if has_prev {
-        Some(format!(
-            "/opds/search/books/{}/{}/{}/",
-            search_type,
-            urlencoding::encode(terms),
-            page - 1
-        ))
-        .map(|href| add_lang_query(&href, &lang))
-    } else {
-        None
-    };
-    let next_href = if has_next {
-        Some(format!(
-            "/opds/search/books/{}/{}/{}/",
-            search_type,
-            urlencoding::encode(terms),
-            page + 1
-        ))
-        .map(|href| add_lang_query(&href, &lang))
-    } else {
-        None
-    };
+    // Text matches come ranked by relevance.
+    if !text_search && ((!book_list.is_empty() && !fuzzy_results) || page > 1) {
+        let sort_target = add_search_filter(&listing_href, &filter);
+        write_sort_facets(&mut fb, &state, &lang, &sort_target, sort_choices, sort);
+    }
+    if title_search && ((!book_list.is_empty() && !fuzzy_results) || !filter.is_empty()) {
+        let facets = books::title_search_facets(
+            &state.db,
+            &terms.to_uppercase(),
+            mode,
+            state.config.search.translit,
+            &filter,
+            &lang,
+            books::SEARCH_FACET_VALUES,
+        )
+        .await
+        .unwrap_or_default();
+        let groups = search_facet_groups(&state, &lang, &facets, &filter);
+        write_search_facets(&mut fb, &lang, &keep_sort(listing_href.clone()), &groups);
+    }
+
+    // Pagination (similar matches are a single ranked page)
+    let has_next = !fuzzy_results && book_list.len() as i32 >= max_items;
+    let prev_href = (page > 1).then(|| page_href(page - 1));
+    let next_href = has_next.then(|| page_href(page + 1));
     let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
 
     for book in &book_list {
@@ -1086,58 +1650,89 @@ pub async fn search_books_feed(
     }
 }
 
+/// GET /opds/catalogs/:cat_id/search/:terms/
+/// GET /opds/catalogs/:cat_id/search/:terms/:page/
+///
+/// Title search limited to a catalog and its descendants.
+pub async fn catalog_search_feed(
+    state: State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path(params): Path<CatalogSearchParams>,
+    Query(mut q): Query<BooksQuery>,
+) -> Response {
+    q.scope = Some(SearchScope::Catalog(params.cat_id).as_param());
+    let params = SearchBooksParams {
+        search_type: "m".to_string(),
+        terms: params.terms,
+        page: params.page,
+    };
+    search_books_feed(state, headers, Path(params), Query(q)).await
+}
+
 /// GET /opds/search/authors/:search_type/:terms/
 /// GET /opds/search/authors/:search_type/:terms/:page/
+///
+/// Search types: b=surname begins (names are stored surname first),
+/// m=contains, e=exact.
 pub async fn search_authors_feed(
     State(state): State<AppState>,
     Path(params): Path<SearchBooksParams>,
 ) -> Response {
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let page = params.page.unwrap_or(1).max(1);
     let offset = (page - 1) * max_items;
     let terms = &params.terms;
+    let mode = MatchMode::from_search_type(&params.search_type);
+    let page_href = |page: i32| {
+        format!(
+            "/opds/search/authors/{}/{}/{page}/",
+            mode.as_search_type(),
+            urlencoding::encode(terms)
+        )
+    };
 
-    let mut fb = FeedBuilder::new();
-    let self_href = format!(
-        "/opds/search/authors/m/{}/{}/",
-        urlencoding::encode(terms),
-        page
-    );
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = page_href(page);
+    let search_href = format!("/opds/search/{}/", urlencoding::encode(terms));
     let _ = fb.begin_feed(
-        &format!("tag:search:authors:{terms}:{page}"),
+        &format!(
+            "tag:search:authors:{}:{terms}:{page}",
+            mode.as_search_type()
+        ),
         &format!("Authors: {terms}"),
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/").up(search_href, "Search"),
     );
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
 
     let search_term = terms.to_uppercase();
-    let author_list = authors::search_by_name(&state.db, &search_term, max_items, offset)
+    let mut author_list = authors::search_by_name(
+        &state.db,
+        &search_term,
+        mode,
+        max_items,
+        offset,
+        state.config.search.translit,
+    )
+    .await
+    .unwrap_or_default();
+    let fuzzy_results = author_list.is_empty() && page == 1 && state.config.search.fuzzy;
+    if fuzzy_results {
+        author_list = fuzzy::search_authors(
+            &state.db,
+            &search_term,
+            max_items,
+            state.config.search.fuzzy_threshold,
+            state.pg_trgm_available,
+        )
         .await
         .unwrap_or_default();
+    }
 
-    let has_next = author_list.len() as i32 >= max_items;
-    let has_prev = page > 1;
-    let prev_href = if has_prev {
-        Some(format!(
-            "/opds/search/authors/m/{}/{}/",
-            urlencoding::encode(terms),
-            page - 1
-        ))
-    } else {
-        None
-    };
-    let next_href = if has_next {
-        Some(format!(
-            "/opds/search/authors/m/{}/{}/",
-            urlencoding::encode(terms),
-            page + 1
-        ))
-    } else {
-        None
-    };
+    let has_next = !fuzzy_results && author_list.len() as i32 >= max_items;
+    let prev_href = (page > 1).then(|| page_href(page - 1));
+    let next_href = has_next.then(|| page_href(page + 1));
     let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
 
     for author in &author_list {
@@ -1163,12 +1758,13 @@ pub async fn search_series_feed(
     State(state): State<AppState>,
     Path(params): Path<SearchBooksParams>,
 ) -> Response {
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let page = params.page.unwrap_or(1).max(1);
     let offset = (page - 1) * max_items;
     let terms = &params.terms;
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let search_href = format!("/opds/search/{}/", urlencoding::encode(terms));
     let self_href = format!(
         "/opds/search/series/m/{}/{}/",
         urlencoding::encode(terms),
@@ -1179,8 +1775,7 @@ pub async fn search_series_feed(
         &format!("Series: {terms}"),
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        "/opds/",
+        &xml::Breadcrumbs::new(self_href, "/opds/").up(search_href, "Search"),
     );
     let _ = fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/");
 
@@ -1232,9 +1827,9 @@ pub async fn search_series_feed(
 pub async fn bookshelf_root(
     State(state): State<AppState>,
     headers: axum::http::HeaderMap,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<BookshelfQuery>,
 ) -> Response {
-    build_bookshelf_feed(&state, &headers, q.lang.as_deref(), 1).await
+    build_bookshelf_feed(&state, &headers, &q, 1).await
 }
 
 /// GET /opds/bookshelf/:page/
@@ -1242,35 +1837,47 @@ pub async fn bookshelf_feed(
     State(state): State<AppState>,
     headers: axum::http::HeaderMap,
     Path((page,)): Path<(i32,)>,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<BookshelfQuery>,
 ) -> Response {
-    build_bookshelf_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+    build_bookshelf_feed(&state, &headers, &q, page.max(1)).await
 }
 
 async fn build_bookshelf_feed(
     state: &AppState,
     headers: &axum::http::HeaderMap,
-    query_lang: Option<&str>,
+    q: &BookshelfQuery,
     page: i32,
 ) -> Response {
-    let lang = detect_opds_lang(headers, &state.config, query_lang);
+    let lang = detect_opds_lang(headers, &state.config, q.lang.as_deref());
     let user_id = match crate::opds::auth::get_user_id_from_headers(&state.db, headers).await {
         Some(uid) => uid,
         None => return error_response(StatusCode::UNAUTHORIZED, "Authentication required"),
     };
+    let (sort, ascending) = q.order();
+    let read_state = q.read_state();
+    let page_href = |page: i32| {
+        add_lang_query(
+            &add_bookshelf_query(
+                &format!("/opds/bookshelf/{page}/"),
+                sort,
+                ascending,
+                read_state,
+            ),
+            &lang,
+        )
+    };
 
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let offset = (page - 1) * max_items;
 
-    let mut fb = FeedBuilder::new();
-    let self_href = add_lang_query(&format!("/opds/bookshelf/{page}/"), &lang);
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = page_href(page);
     let _ = fb.begin_feed(
         &format!("tag:bookshelf:{page}"),
         "Book shelf",
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        &add_lang_query("/opds/", &lang),
+        &xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang)).acquisition(),
     );
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
@@ -1278,23 +1885,121 @@ async fn build_bookshelf_feed(
     );
     write_language_facets_for_href(&mut fb, state, &lang, "/opds/bookshelf/");
 
-    let book_list = crate::db::queries::bookshelf::get_by_user(
-        &state.db,
-        user_id,
-        &crate::db::queries::bookshelf::SortColumn::Date,
-        false,
-        max_items,
-        offset,
+    let sort_group = tr(state, &lang, "opds", "facet_sort", "Sort by");
+    for option in bookshelf::SortColumn::ALL {
+        let value = option.as_str();
+        let href = add_lang_query(
+            &add_bookshelf_query(
+                "/opds/bookshelf/",
+                option,
+                option.default_ascending(),
+                read_state,
+            ),
+            &lang,
+        );
+        let label = tr(state, &lang, "bookshelf", &format!("sort_{value}"), value);
+        let _ = fb.write_facet_link(&href, xml::ACQ_TYPE, &label, &sort_group, option == sort);
+    }
+    let state_group = tr(state, &lang, "opds", "facet_read_state", "Reading state");
+    for option in bookshelf::ReadState::ALL {
+        let value = option.as_str();
+        let href = add_lang_query(
+            &add_bookshelf_query("/opds/bookshelf/", sort, ascending, option),
+            &lang,
+        );
+        let label = tr(state, &lang, "bookshelf", &format!("state_{value}"), value);
+        let _ = fb.write_facet_link(
+            &href,
+            xml::ACQ_TYPE,
+            &label,
+            &state_group,
+            option == read_state,
+        );
+    }
+
+    let book_list = bookshelf::get_filtered(
+        &state.db, user_id, &sort, ascending, read_state, max_items, offset,
     )
     .await
     .unwrap_or_default();
 
+    // Pagination
+    let has_next = book_list.len() as i32 >= max_items;
+    let has_prev = page > 1;
+    let prev_href = has_prev.then(|| page_href(page - 1));
+    let next_href = has_next.then(|| page_href(page + 1));
+    let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
+
+    for book in &book_list {
+        write_book_entry(&mut fb, state, book, &lang).await;
+    }
+
+    match fb.finish() {
+        Ok(body) => atom_response(body),
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "XML error"),
+    }
+}
+
+/// GET /opds/recommended/
+pub async fn recommended_root(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recommended_feed(&state, &headers, q.lang.as_deref(), 1).await
+}
+
+/// GET /opds/recommended/:page/
+pub async fn recommended_feed(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recommended_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+}
+
+async fn build_recommended_feed(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    query_lang: Option<&str>,
+    page: i32,
+) -> Response {
+    let lang = detect_opds_lang(headers, &state.config, query_lang);
+    let user_id = match crate::opds::auth::get_user_id_from_headers(&state.db, headers).await {
+        Some(uid) => uid,
+        None => return error_response(StatusCode::UNAUTHORIZED, "Authentication required"),
+    };
+
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let offset = (page - 1) * max_items;
+
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = add_lang_query(&format!("/opds/recommended/{page}/"), &lang);
+    let _ = fb.begin_feed(
+        &format!("tag:recommended:{page}"),
+        &tr(state, &lang, "opds", "root_recommended", "Recommended"),
+        "",
+        DEFAULT_UPDATED,
+        &xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang)).acquisition(),
+    );
+    let _ = fb.write_search_links(
+        &add_lang_query("/opds/search/", &lang),
+        &add_lang_query("/opds/search/{searchTerms}/", &lang),
+    );
+    write_language_facets_for_href(&mut fb, state, &lang, "/opds/recommended/");
+
+    let book_list =
+        crate::db::queries::recommendations::get_for_user(&state.db, user_id, max_items, offset)
+            .await
+            .unwrap_or_default();
+
     // Pagination
     let has_next = book_list.len() as i32 >= max_items;
     let has_prev = page > 1;
     let prev_href = if has_prev {
         Some(add_lang_query(
-            &format!("/opds/bookshelf/{}/", page - 1),
+            &format!("/opds/recommended/{}/", page - 1),
             &lang,
         ))
     } else {
@@ -1302,7 +2007,7 @@ async fn build_bookshelf_feed(
     };
     let next_href = if has_next {
         Some(add_lang_query(
-            &format!("/opds/bookshelf/{}/", page + 1),
+            &format!("/opds/recommended/{}/", page + 1),
             &lang,
         ))
     } else {
@@ -1320,20 +2025,92 @@ async fn build_bookshelf_feed(
     }
 }
 
+/// GET /opds/next/
+pub async fn next_in_series_root(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_next_in_series_feed(&state, &headers, q.lang.as_deref(), 1).await
+}
+
+/// GET /opds/next/:page/
+pub async fn next_in_series_feed(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_next_in_series_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+}
+
+/// The next unread volume of each series the user has finished a book of.
+async fn build_next_in_series_feed(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    query_lang: Option<&str>,
+    page: i32,
+) -> Response {
+    let lang = detect_opds_lang(headers, &state.config, query_lang);
+    let user_id = match crate::opds::auth::get_user_id_from_headers(&state.db, headers).await {
+        Some(uid) => uid,
+        None => return error_response(StatusCode::UNAUTHORIZED, "Authentication required"),
+    };
+
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let offset = (page - 1) * max_items;
+
+    let mut fb = FeedBuilder::for_instance(&state.config);
+    let self_href = add_lang_query(&format!("/opds/next/{page}/"), &lang);
+    let _ = fb.begin_feed(
+        &format!("tag:next:{page}"),
+        &tr(state, &lang, "opds", "root_next", "Next in series"),
+        "",
+        DEFAULT_UPDATED,
+        &xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang)).acquisition(),
+    );
+    let _ = fb.write_search_links(
+        &add_lang_query("/opds/search/", &lang),
+        &add_lang_query("/opds/search/{searchTerms}/", &lang),
+    );
+
+    let book_list = series::next_unread_for_user(&state.db, user_id, max_items, offset)
+        .await
+        .unwrap_or_default();
+
+    // Pagination
+    let has_next = book_list.len() as i32 >= max_items;
+    let prev_href = (page > 1).then(|| add_lang_query(&format!("/opds/next/{}/", page - 1), &lang));
+    let next_href = has_next.then(|| add_lang_query(&format!("/opds/next/{}/", page + 1), &lang));
+    let _ = fb.write_pagination(prev_href.as_deref(), next_href.as_deref());
+
+    for book in &book_list {
+        write_book_entry(&mut fb, state, book, &lang).await;
+    }
+
+    match fb.finish() {
+        Ok(body) => atom_response(body),
+        Err(_) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "XML error"),
+    }
+}
+
 /// GET /opds/search/ — OpenSearch description.
 pub async fn opensearch(_state: State<AppState>) -> Response {
-    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+    let template = crate::opds::compat::current().href("/opds/search/{searchTerms}/");
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
 <OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">
     <ShortName>ropds</ShortName>
     <LongName>Rust OPDS Server</LongName>
     <Description>Search the OPDS catalog</Description>
-    <Url type="application/atom+xml" template="/opds/search/{searchTerms}/" />
+    <Url type="application/atom+xml" template="{template}" />
     <SyndicationRight>open</SyndicationRight>
     <AdultContent>false</AdultContent>
     <Language>*</Language>
     <OutputEncoding>UTF-8</OutputEncoding>
     <InputEncoding>UTF-8</InputEncoding>
-</OpenSearchDescription>"#;
+</OpenSearchDescription>"#
+    );
 
     (
         StatusCode::OK,
@@ -1397,7 +2174,13 @@ language = "{default_lang}"
                 "lang": { "en": "English", "ru": "Русский" }
             }),
         );
-        let state = AppState::new(cfg, db, tera, translations, false, false);
+        let state = AppState::new(
+            cfg,
+            db,
+            tera,
+            translations,
+            crate::state::Capabilities::default(),
+        );
         let headers = HeaderMap::new();
 
         let response = build_root_feed(&state, &headers, Some("en")).await;
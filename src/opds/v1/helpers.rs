@@ -1,7 +1,8 @@
 use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 
-use crate::db::queries::{authors, genres};
+use crate::db::queries::books::{BookSort, SearchFacets, SearchFilter, SearchScope};
+use crate::db::queries::{authors, book_audio, bookshelf, cached, genres, series};
 use crate::state::AppState;
 
 use super::xml::{self, FeedBuilder};
@@ -105,6 +106,211 @@ pub fn add_lang_query(href: &str, lang: &str) -> String {
     }
 }
 
+/// Href of a paginated listing page under `base` (e.g. `/opds/authors/`).
+/// An empty prefix addresses the flat listing used when `alphabet_menu` is off.
+pub fn list_href(base: &str, lang_code: i32, prefix: &str, page: i32) -> String {
+    if prefix.is_empty() {
+        format!("{base}{lang_code}/list/{page}/")
+    } else {
+        format!(
+            "{base}{lang_code}/{}/list/{page}/",
+            urlencoding::encode(prefix)
+        )
+    }
+}
+
+/// Parent of an alphabet drill-down or listing page: the drill-down one
+/// letter up, or the language selection feed at `base` for the top level.
+pub fn drill_up_href(base: &str, lang_code: i32, prefix: &str) -> String {
+    if prefix.is_empty() {
+        return base.to_string();
+    }
+    let mut parent = prefix.to_string();
+    parent.pop();
+    if parent.is_empty() {
+        format!("{base}{lang_code}/")
+    } else {
+        format!("{base}{lang_code}/{}/", urlencoding::encode(&parent))
+    }
+}
+
+/// Append a query parameter, like [`add_lang_query`].
+pub fn add_query_param(href: &str, key: &str, value: &str) -> String {
+    let encoded = urlencoding::encode(value);
+    if href.contains('?') {
+        format!("{href}&{key}={encoded}")
+    } else {
+        format!("{href}?{key}={encoded}")
+    }
+}
+
+/// Write the "Sort by" facet group for a book listing. `target_href` is the
+/// first page of the listing without `sort` and `lang`.
+pub fn write_sort_facets(
+    fb: &mut FeedBuilder,
+    state: &AppState,
+    lang: &str,
+    target_href: &str,
+    options: &[BookSort],
+    active: BookSort,
+) {
+    let group = tr(state, lang, "opds", "facet_sort", "Sort by");
+    for sort in options {
+        let value = sort.as_str();
+        let href = add_lang_query(&add_query_param(target_href, "sort", value), lang);
+        let label = tr(state, lang, "book", &format!("sort_{value}"), value);
+        let _ = fb.write_facet_link(&href, xml::ACQ_TYPE, &label, &group, *sort == active);
+    }
+}
+
+/// Append the bookshelf order and read-state filter to `href`, leaving out
+/// defaults.
+pub fn add_bookshelf_query(
+    href: &str,
+    sort: bookshelf::SortColumn,
+    ascending: bool,
+    read_state: bookshelf::ReadState,
+) -> String {
+    let mut href = href.to_string();
+    if sort != bookshelf::SortColumn::Date {
+        href = add_query_param(&href, "sort", sort.as_str());
+    }
+    if ascending != sort.default_ascending() {
+        href = add_query_param(&href, "dir", if ascending { "asc" } else { "desc" });
+    }
+    if read_state != bookshelf::ReadState::All {
+        href = add_query_param(&href, "state", read_state.as_str());
+    }
+    href
+}
+
+/// Append the query parameters selecting `filter`.
+pub fn add_search_filter(href: &str, filter: &SearchFilter) -> String {
+    filter
+        .query_pairs()
+        .iter()
+        .fold(href.to_string(), |href, (key, value)| {
+            add_query_param(&href, key, value)
+        })
+}
+
+/// Atom search template (`{searchTerms}`) for a title search limited to
+/// `scope`, or the global search type selection.
+pub fn search_template_href(scope: Option<SearchScope>) -> String {
+    match scope {
+        Some(SearchScope::Catalog(id)) => format!("/opds/catalogs/{id}/search/{{searchTerms}}/"),
+        Some(scope) => format!(
+            "/opds/search/books/m/{{searchTerms}}/?scope={}",
+            scope.as_param()
+        ),
+        None => "/opds/search/{searchTerms}/".to_string(),
+    }
+}
+
+/// One facet group of a title search: its title and `(label, filter, active)`
+/// links, the first of which ("All") drops the group's own filter.
+pub struct SearchFacetGroup {
+    pub title: String,
+    pub links: Vec<(String, SearchFilter, bool)>,
+}
+
+/// Genre, book language and format facet groups of a title search. A group
+/// is offered when it has a choice to make or one of its values is active.
+pub fn search_facet_groups(
+    state: &AppState,
+    lang: &str,
+    facets: &SearchFacets,
+    filter: &SearchFilter,
+) -> Vec<SearchFacetGroup> {
+    let all = tr(state, lang, "opds", "facet_all", "All");
+    let mut groups = Vec::new();
+    let mut push_group =
+        |title: String, cleared: SearchFilter, values: Vec<(String, SearchFilter)>| {
+            let any_active = cleared != *filter;
+            if values.len() < 2 && !any_active {
+                return;
+            }
+            let mut links = vec![(all.clone(), cleared, !any_active)];
+            links.extend(values.into_iter().map(|(label, value)| {
+                let active = value == *filter;
+                (label, value, active)
+            }));
+            groups.push(SearchFacetGroup { title, links });
+        };
+
+    push_group(
+        tr(state, lang, "opds", "facet_genre", "Genre"),
+        SearchFilter {
+            genre_id: None,
+            ..filter.clone()
+        },
+        facets
+            .genres
+            .iter()
+            .map(|(id, name, count)| {
+                let value = SearchFilter {
+                    genre_id: Some(*id),
+                    ..filter.clone()
+                };
+                (format!("{name} ({count})"), value)
+            })
+            .collect(),
+    );
+    push_group(
+        tr(state, lang, "opds", "facet_book_language", "Book language"),
+        SearchFilter {
+            lang: None,
+            ..filter.clone()
+        },
+        facets
+            .langs
+            .iter()
+            .map(|(code, count)| {
+                let value = SearchFilter {
+                    lang: Some(code.clone()),
+                    ..filter.clone()
+                };
+                (format!("{} ({count})", locale_label(state, code)), value)
+            })
+            .collect(),
+    );
+    push_group(
+        tr(state, lang, "opds", "facet_format", "Format"),
+        SearchFilter {
+            format: None,
+            ..filter.clone()
+        },
+        facets
+            .formats
+            .iter()
+            .map(|(format, count)| {
+                let value = SearchFilter {
+                    format: Some(format.clone()),
+                    ..filter.clone()
+                };
+                (format!("{} ({count})", crate::formats::name(format)), value)
+            })
+            .collect(),
+    );
+    groups
+}
+
+/// Write the facet groups of a title search. `target_href` is the first page
+/// of the search without filters and `lang`.
+pub fn write_search_facets(
+    fb: &mut FeedBuilder,
+    lang: &str,
+    target_href: &str,
+    groups: &[SearchFacetGroup],
+) {
+    for group in groups {
+        for (label, filter, active) in &group.links {
+            let href = add_lang_query(&add_search_filter(target_href, filter), lang);
+            let _ = fb.write_facet_link(&href, xml::ACQ_TYPE, label, &group.title, *active);
+        }
+    }
+}
+
 pub fn write_language_facets_for_href(
     fb: &mut FeedBuilder,
     state: &AppState,
@@ -156,13 +362,41 @@ pub async fn write_book_entry(
     let alternate_link = xml::Link {
         href: dl_href,
         rel: "alternate".to_string(),
-        link_type: xml::mime_for_format(&book.format).to_string(),
+        link_type: xml::mime_for_format(xml::download_format(&book.format)).to_string(),
         title: None,
     };
     let _ = fb.write_link_obj(&alternate_link);
 
     // Acquisition links
-    let _ = fb.write_acquisition_links(book.id, &book.format, book.cover != 0);
+    let cover_mime = (book.cover != 0).then(|| crate::opds::covers::cover_mime(book));
+    let _ = fb.write_acquisition_links(book.id, &book.format, cover_mime.as_deref());
+
+    let audio = if crate::audio::is_audio_format(&book.format) {
+        let _ = fb.write_audiobook_type();
+        if book.format == "mp3" {
+            let tracks: Vec<String> =
+                crate::audio::book_tracks(&state.config.library.root_path, book)
+                    .iter()
+                    .map(|p| crate::vfs::decode_name(p.file_name().unwrap_or_default()))
+                    .collect();
+            let _ = fb.write_track_links(book.id, &tracks);
+        }
+        book_audio::get_for_book(&state.db, book.id)
+            .await
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+
+    // Dublin Core metadata for clients that show more than the content text
+    if !book.lang.is_empty() {
+        let _ = fb.write_language(&book.lang);
+    }
+    if let Some(issued) = issued_date(&book.docdate) {
+        let _ = fb.write_issued(&issued);
+    }
+    let _ = fb.write_format(xml::mime_for_format(&book.format));
 
     // Content: book description HTML
     let mut html = format!("<b>Title: </b>{}<br/>", book.title);
@@ -176,8 +410,28 @@ pub async fn write_book_entry(
     if !book.docdate.is_empty() {
         html.push_str(&format!("<b>Date: </b>{}<br/>", book.docdate));
     }
+    if let Some(audio) = &audio {
+        if !audio.narrator.is_empty() {
+            html.push_str(&format!("<b>Narrator: </b>{}<br/>", audio.narrator));
+        }
+        if audio.duration > 0 {
+            html.push_str(&format!(
+                "<b>Duration: </b>{}<br/>",
+                crate::audio::format_duration(audio.duration)
+            ));
+        }
+    }
     if !book.annotation.is_empty() {
-        html.push_str(&format!("<p class='book'>{}</p>", book.annotation));
+        html.push_str(&format!(
+            "<div class='book'>{}</div>",
+            crate::markdown::to_html(&book.annotation)
+        ));
+    }
+    if !book.preview_text.is_empty() {
+        html.push_str(&format!(
+            "<div class='book-preview'>{}</div>",
+            crate::markdown::plain_to_html(&book.preview_text)
+        ));
     }
     let _ = fb.write_content_html(&html);
 
@@ -206,14 +460,58 @@ pub async fn write_book_entry(
             let category = xml::Category {
                 term: genre.code.clone(),
                 label: genre.subsection.clone(),
+                scheme: None,
             };
             let _ = fb.write_category_obj(&category);
         }
     }
 
+    // Series
+    if let Ok(book_series) = series::get_for_book(&state.db, book.id).await {
+        for (ser, ser_no) in &book_series {
+            let label = if *ser_no > 0 {
+                format!("{} #{ser_no}", ser.ser_name)
+            } else {
+                ser.ser_name.clone()
+            };
+            let _ = fb.write_category_obj(&xml::Category {
+                term: ser.ser_name.clone(),
+                label,
+                scheme: Some(xml::SERIES_SCHEME.to_string()),
+            });
+            let _ = fb.write_link_obj(&xml::Link {
+                href: format!("/opds/search/books/s/{}/", ser.id),
+                rel: "related".to_string(),
+                link_type: xml::ACQ_TYPE.to_string(),
+                title: Some(format!("All books in {}", ser.ser_name)),
+            });
+        }
+    }
+
     let _ = fb.end_entry();
 }
 
+/// `dcterms:issued` value for a free-form document date: a leading
+/// `YYYY-MM-DD` or `YYYY-MM` is kept, otherwise the first four-digit year.
+pub fn issued_date(docdate: &str) -> Option<String> {
+    let docdate = docdate.trim();
+    let is_date = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok();
+    if let Some(day) = docdate.get(..10)
+        && is_date(day)
+    {
+        return Some(day.to_string());
+    }
+    if let Some(month) = docdate.get(..7)
+        && is_date(&format!("{month}-01"))
+    {
+        return Some(month.to_string());
+    }
+    docdate
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|run| run.len() == 4 && matches!(run.as_bytes()[0], b'1' | b'2'))
+        .map(str::to_string)
+}
+
 /// Generate the language/script selection feed.
 pub async fn lang_selection_feed(
     state: &AppState,
@@ -231,15 +529,14 @@ pub async fn lang_selection_feed(
     let digits_label = tr(state, &lang, "browse", "digits", "Digits");
     let other_label = tr(state, &lang, "browse", "other", "Other");
 
-    let mut fb = FeedBuilder::new();
+    let mut fb = FeedBuilder::for_instance(&state.config);
     let self_href = add_lang_query(base_href, &lang);
     let _ = fb.begin_feed(
         &format!("tag:lang:{title}"),
         &title,
         "",
         DEFAULT_UPDATED,
-        &self_href,
-        &add_lang_query("/opds/", &lang),
+        &xml::Breadcrumbs::new(self_href, add_lang_query("/opds/", &lang)),
     );
     let _ = fb.write_search_links(
         &add_lang_query("/opds/search/", &lang),
@@ -247,35 +544,21 @@ pub async fn lang_selection_feed(
     );
     write_language_facets_for_href(&mut fb, state, &lang, base_href);
 
+    // "All" stays even when empty so the feed is never blank.
+    let counts = cached::hide_empty_counts(state).await;
     let entries = [
-        (
-            "l:0",
-            all_label,
-            add_lang_query(&format!("{base_href}0/"), &lang),
-        ),
-        (
-            "l:1",
-            cyrillic_label,
-            add_lang_query(&format!("{base_href}1/"), &lang),
-        ),
-        (
-            "l:2",
-            latin_label,
-            add_lang_query(&format!("{base_href}2/"), &lang),
-        ),
-        (
-            "l:3",
-            digits_label,
-            add_lang_query(&format!("{base_href}3/"), &lang),
-        ),
-        (
-            "l:9",
-            other_label,
-            add_lang_query(&format!("{base_href}9/"), &lang),
-        ),
+        (0, all_label),
+        (1, cyrillic_label),
+        (2, latin_label),
+        (3, digits_label),
+        (9, other_label),
     ];
-    for (id, label, href) in &entries {
-        let _ = fb.write_nav_entry(id, label, href, "", DEFAULT_UPDATED);
+    for (code, label) in &entries {
+        if *code != 0 && counts.as_ref().is_some_and(|c| c.lang(nav_key, *code) == 0) {
+            continue;
+        }
+        let href = add_lang_query(&format!("{base_href}{code}/"), &lang);
+        let _ = fb.write_nav_entry(&format!("l:{code}"), label, &href, "", DEFAULT_UPDATED);
     }
 
     match fb.finish() {
@@ -290,6 +573,21 @@ mod tests {
     use axum::body::to_bytes;
     use axum::http::HeaderMap;
 
+    #[test]
+    fn test_issued_date() {
+        assert_eq!(issued_date("1951").as_deref(), Some("1951"));
+        assert_eq!(
+            issued_date(" 2024-03-05 12:00 ").as_deref(),
+            Some("2024-03-05")
+        );
+        assert_eq!(issued_date("2024-03").as_deref(), Some("2024-03"));
+        assert_eq!(issued_date("12.03.2007").as_deref(), Some("2007"));
+        assert_eq!(issued_date("c. 1890s").as_deref(), Some("1890"));
+        assert_eq!(issued_date("2024-13-40").as_deref(), Some("2024"));
+        assert_eq!(issued_date("12345"), None);
+        assert_eq!(issued_date(""), None);
+    }
+
     fn test_config(default_lang: &str) -> crate::config::Config {
         let cfg = format!(
             r#"
@@ -388,7 +686,13 @@ language = "{default_lang}"
                 "lang": { "en": "English", "ru": "Русский" }
             }),
         );
-        let state = AppState::new(cfg, db, tera, translations, false, false);
+        let state = AppState::new(
+            cfg,
+            db,
+            tera,
+            translations,
+            crate::state::Capabilities::default(),
+        );
         let mut headers = HeaderMap::new();
         headers.insert("accept-language", "ru".parse().unwrap());
 
@@ -426,4 +730,42 @@ language = "{default_lang}"
             "/opds/genres/?page=1&lang=en"
         );
     }
+
+    #[test]
+    fn test_list_href_helper() {
+        assert_eq!(
+            list_href("/opds/authors/", 2, "DO", 3),
+            "/opds/authors/2/DO/list/3/"
+        );
+        assert_eq!(
+            list_href("/opds/series/", 1, "ВОЙ", 1),
+            "/opds/series/1/%D0%92%D0%9E%D0%99/list/1/"
+        );
+        assert_eq!(list_href("/opds/books/", 0, "", 2), "/opds/books/0/list/2/");
+    }
+    #[test]
+    fn test_drill_up_href_helper() {
+        assert_eq!(drill_up_href("/opds/authors/", 2, ""), "/opds/authors/");
+        assert_eq!(drill_up_href("/opds/authors/", 2, "D"), "/opds/authors/2/");
+        assert_eq!(
+            drill_up_href("/opds/authors/", 2, "DOS"),
+            "/opds/authors/2/DO/"
+        );
+        assert_eq!(
+            drill_up_href("/opds/series/", 1, "ВОЙ"),
+            "/opds/series/1/%D0%92%D0%9E/"
+        );
+    }
+    #[test]
+    fn test_search_template_href() {
+        assert_eq!(search_template_href(None), "/opds/search/{searchTerms}/");
+        assert_eq!(
+            search_template_href(Some(SearchScope::Catalog(7))),
+            "/opds/catalogs/7/search/{searchTerms}/"
+        );
+        assert_eq!(
+            search_template_href(Some(SearchScope::Author(3))),
+            "/opds/search/books/m/{searchTerms}/?scope=a:3"
+        );
+    }
 }
@@ -5,6 +5,8 @@ pub mod xml;
 use axum::Router;
 use axum::routing::get;
 
+use crate::db::queries::books::SearchFilter;
+use crate::db::queries::bookshelf;
 use crate::state::AppState;
 
 #[derive(serde::Deserialize, Default)]
@@ -12,6 +14,81 @@ pub struct LangQuery {
     pub lang: Option<String>,
 }
 
+#[derive(serde::Deserialize, Default)]
+pub struct CatalogsQuery {
+    pub lang: Option<String>,
+    /// `1` lists books from the whole subtree, `0` only from this catalog.
+    pub deep: Option<String>,
+    pub sort: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct BooksQuery {
+    pub lang: Option<String>,
+    /// Book order (`title`, `recent`, `series`, `size`, `year`, `rating`).
+    pub sort: Option<String>,
+    /// Title search facets: genre ID, book language and format.
+    pub genre: Option<String>,
+    pub language: Option<String>,
+    pub format: Option<String>,
+    /// Limit a title search to a catalog subtree, author or series
+    /// (`c:<id>`, `a:<id>`, `s:<id>`).
+    pub scope: Option<String>,
+}
+
+impl BooksQuery {
+    pub fn search_filter(&self) -> SearchFilter {
+        SearchFilter::parse(
+            self.genre.as_deref(),
+            self.language.as_deref(),
+            self.format.as_deref(),
+        )
+        .scoped(self.scope.as_deref())
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct BookshelfQuery {
+    pub lang: Option<String>,
+    /// Shelf order (`date`, `title`, `author`).
+    pub sort: Option<String>,
+    /// `asc` or `desc`; defaults to newest first for dates and A to Z otherwise.
+    pub dir: Option<String>,
+    /// Reading-progress filter (`unread`, `reading`, `finished`).
+    pub state: Option<String>,
+}
+
+impl BookshelfQuery {
+    /// Sort column and direction.
+    pub fn order(&self) -> (bookshelf::SortColumn, bool) {
+        let sort = bookshelf::SortColumn::parse(self.sort.as_deref().unwrap_or_default());
+        let ascending = match self.dir.as_deref() {
+            Some("asc") => true,
+            Some("desc") => false,
+            _ => sort.default_ascending(),
+        };
+        (sort, ascending)
+    }
+
+    pub fn read_state(&self) -> bookshelf::ReadState {
+        bookshelf::ReadState::parse(self.state.as_deref().unwrap_or_default())
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct PopularQuery {
+    pub lang: Option<String>,
+    /// Download window in days (`30` or `90`).
+    pub period: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct DumpQuery {
+    pub lang: Option<String>,
+    /// Id of the last book on the previous page (keyset pagination).
+    pub after: Option<i64>,
+}
+
 #[derive(serde::Deserialize)]
 pub struct CatalogsParams {
     pub cat_id: i64,
@@ -27,6 +104,8 @@ pub struct AuthorsParams {
 #[derive(serde::Deserialize)]
 pub struct AuthorsListParams {
     pub lang_code: i32,
+    /// Empty for the flat listings served when `alphabet_menu` is off.
+    #[serde(default)]
     pub prefix: String,
     pub page: Option<i32>,
 }
@@ -38,6 +117,13 @@ pub struct SearchBooksParams {
     pub page: Option<i32>,
 }
 
+#[derive(serde::Deserialize)]
+pub struct CatalogSearchParams {
+    pub cat_id: i64,
+    pub terms: String,
+    pub page: Option<i32>,
+}
+
 /// Build OPDS 1.2 (Atom XML) routes.
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -48,6 +134,14 @@ pub fn router() -> Router<AppState> {
         .route("/catalogs/", get(feeds::catalogs_root))
         .route("/catalogs/{cat_id}/", get(feeds::catalogs_feed))
         .route("/catalogs/{cat_id}/{page}/", get(feeds::catalogs_feed))
+        .route(
+            "/catalogs/{cat_id}/search/{terms}/",
+            get(feeds::catalog_search_feed),
+        )
+        .route(
+            "/catalogs/{cat_id}/search/{terms}/{page}/",
+            get(feeds::catalog_search_feed),
+        )
         // Authors
         .route("/authors/", get(feeds::authors_root))
         .route("/authors/{lang_code}/", get(feeds::authors_feed))
@@ -60,6 +154,10 @@ pub fn router() -> Router<AppState> {
             "/authors/{lang_code}/{prefix}/list/{page}/",
             get(feeds::authors_list),
         )
+        .route(
+            "/authors/{lang_code}/list/{page}/",
+            get(feeds::authors_list),
+        )
         // Series
         .route("/series/", get(feeds::series_root))
         .route("/series/{lang_code}/", get(feeds::series_feed))
@@ -72,6 +170,7 @@ pub fn router() -> Router<AppState> {
             "/series/{lang_code}/{prefix}/list/{page}/",
             get(feeds::series_list),
         )
+        .route("/series/{lang_code}/list/{page}/", get(feeds::series_list))
         // Genres
         .route("/genres/", get(feeds::genres_root))
         .route("/genres/{section}/", get(feeds::genres_by_section))
@@ -82,9 +181,22 @@ pub fn router() -> Router<AppState> {
         .route("/books/", get(feeds::books_root))
         .route("/books/{lang_code}/", get(feeds::books_feed))
         .route("/books/{lang_code}/{prefix}/", get(feeds::books_feed))
+        .route("/books/{lang_code}/list/{page}/", get(feeds::books_list))
         // Recently added
         .route("/recent/", get(feeds::recent_root))
         .route("/recent/{page}/", get(feeds::recent_feed))
+        .route("/recent/genre/{genre_id}/", get(feeds::recent_genre_root))
+        .route(
+            "/recent/genre/{genre_id}/{page}/",
+            get(feeds::recent_genre_feed),
+        )
+        .route("/updated/", get(feeds::updated_root))
+        .route("/updated/{page}/", get(feeds::updated_feed))
+        // Crawlable full dump
+        .route("/all/{page}/", get(feeds::all_books_feed))
+        // Most downloaded
+        .route("/popular/", get(feeds::popular_root))
+        .route("/popular/{page}/", get(feeds::popular_feed))
         // OpenSearch
         .route("/search/", get(feeds::opensearch))
         // Search type selection
@@ -119,4 +231,10 @@ pub fn router() -> Router<AppState> {
         // Bookshelf
         .route("/bookshelf/", get(feeds::bookshelf_root))
         .route("/bookshelf/{page}/", get(feeds::bookshelf_feed))
+        // Personal recommendations
+        .route("/recommended/", get(feeds::recommended_root))
+        .route("/recommended/{page}/", get(feeds::recommended_feed))
+        // Next unread volumes of started series
+        .route("/next/", get(feeds::next_in_series_root))
+        .route("/next/{page}/", get(feeds::next_in_series_feed))
 }
@@ -3,50 +3,129 @@ use std::io::Cursor;
 use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
 use quick_xml::writer::Writer;
 
+use crate::opds::compat::{self, ClientProfile};
+
 /// OPDS Atom content types.
 pub const ATOM_XML: &str = "application/atom+xml; charset=utf-8";
 pub const NAV_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
-pub const ACQ_TYPE: &str = "application/atom+xml;profile=opds-catalog";
+pub const ACQ_TYPE: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
 pub const OPENSEARCH_TYPE: &str = "application/opensearchdescription+xml";
 
 /// OPDS link relations.
-pub const REL_ACQUISITION: &str = "http://opds-spec.org/acquisition/open-access";
+pub const REL_ACQUISITION: &str = crate::formats::DEFAULT_REL;
 pub const REL_IMAGE: &str = "http://opds-spec.org/image";
 pub const REL_THUMBNAIL: &str = "http://opds-spec.org/image/thumbnail";
 pub const REL_THUMBNAIL_LEGACY: &str = "http://opds-spec.org/thumbnail";
 pub const REL_FACET: &str = "http://opds-spec.org/facet";
-
-/// Book format MIME types.
+pub const REL_SORT_NEW: &str = "http://opds-spec.org/sort/new";
+pub const REL_CRAWLABLE: &str = "http://opds-spec.org/crawlable";
+pub const AUDIOBOOK_TYPE: &str = "http://bib.schema.org/Audiobook";
+/// Category scheme marking a book's series (term: series name).
+pub const SERIES_SCHEME: &str = "http://schema.org/CreativeWorkSeries";
+/// Feed icon; serves `opds.icon_path` or the bundled favicon.
+pub const FEED_ICON_HREF: &str = "/favicon.ico";
+
+/// Book format MIME types (see [`crate::formats`]).
 pub fn mime_for_format(format: &str) -> &'static str {
-    match format {
-        "fb2" => "application/fb2+xml",
-        "epub" => "application/epub+zip",
-        "mobi" => "application/x-mobipocket-ebook",
-        "pdf" => "application/pdf",
-        "doc" | "docx" => "application/msword",
-        "djvu" => "image/vnd.djvu",
-        "txt" => "text/plain",
-        "rtf" => "text/rtf",
-        _ => "application/octet-stream",
-    }
+    crate::formats::mime(format)
 }
 
 /// Formats that should NOT be offered as zipped downloads.
 pub fn is_nozip_format(format: &str) -> bool {
-    matches!(format, "epub" | "mobi")
+    !crate::formats::zippable(format)
+}
+
+/// Format a book is downloaded as: MP3 audiobook folders are sent as a ZIP
+/// of their tracks.
+pub fn download_format(format: &str) -> &str {
+    if format == "mp3" { "zip" } else { format }
 }
 
 /// MIME type for zipped book download.
 pub fn mime_for_zip(format: &str) -> String {
-    match format {
-        "fb2" => "application/fb2+zip".to_string(),
-        _ => format!("{}+zip", mime_for_format(format)),
-    }
+    crate::formats::zip_mime(format).to_string()
 }
 
 /// An OPDS Atom feed builder.
 pub struct FeedBuilder {
     writer: Writer<Cursor<Vec<u8>>>,
+    kind: FeedKind,
+    logo: Option<String>,
+    /// Client profile of the request the feed is built for.
+    client: ClientProfile,
+    covers: crate::opds::covers::CoverUrls,
+}
+
+/// OPDS catalog feed kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedKind {
+    /// Entries link to other feeds.
+    Navigation,
+    /// Entries are books.
+    Acquisition,
+}
+
+impl FeedKind {
+    pub fn link_type(self) -> &'static str {
+        match self {
+            Self::Navigation => NAV_TYPE,
+            Self::Acquisition => ACQ_TYPE,
+        }
+    }
+}
+
+/// Position of a feed in the catalog tree, written as its `self`, `start`
+/// and `up` links by [`FeedBuilder::begin_feed`].
+///
+/// Every feed below the root has a parent: it defaults to the start feed
+/// and is narrowed with [`Breadcrumbs::up`]. A feed whose `self` is the
+/// start feed is the root and gets no `up` link.
+#[derive(Debug, Clone)]
+pub struct Breadcrumbs {
+    pub self_href: String,
+    pub start_href: String,
+    pub up: Option<Crumb>,
+    pub kind: FeedKind,
+}
+
+/// A parent feed link.
+#[derive(Debug, Clone)]
+pub struct Crumb {
+    pub href: String,
+    pub title: Option<String>,
+}
+
+impl Breadcrumbs {
+    /// A navigation feed whose parent is the start feed.
+    pub fn new(self_href: impl Into<String>, start_href: impl Into<String>) -> Self {
+        let self_href = self_href.into();
+        let start_href = start_href.into();
+        let up = (self_href != start_href).then(|| Crumb {
+            href: start_href.clone(),
+            title: None,
+        });
+        Self {
+            self_href,
+            start_href,
+            up,
+            kind: FeedKind::Navigation,
+        }
+    }
+
+    /// Set the parent feed.
+    pub fn up(mut self, href: impl Into<String>, title: &str) -> Self {
+        self.up = Some(Crumb {
+            href: href.into(),
+            title: (!title.is_empty()).then(|| title.to_string()),
+        });
+        self
+    }
+
+    /// Mark the feed as an acquisition feed (its entries are books).
+    pub fn acquisition(mut self) -> Self {
+        self.kind = FeedKind::Acquisition;
+        self
+    }
 }
 
 impl Default for FeedBuilder {
@@ -72,31 +151,56 @@ pub struct Author {
 pub struct Category {
     pub term: String,
     pub label: String,
+    /// Vocabulary of `term`; `None` for genre codes.
+    pub scheme: Option<String>,
 }
 
 impl FeedBuilder {
     pub fn new() -> Self {
         let buf = Cursor::new(Vec::new());
         let writer = Writer::new_with_indent(buf, b' ', 2);
-        Self { writer }
+        Self {
+            writer,
+            kind: FeedKind::Navigation,
+            logo: None,
+            client: compat::current(),
+            covers: Default::default(),
+        }
+    }
+
+    /// A builder whose feeds carry the instance `<logo>` from `[opds]` and
+    /// cover links signed as `[covers]` asks.
+    pub fn for_instance(config: &crate::config::Config) -> Self {
+        let mut fb = Self::new();
+        fb.covers = crate::opds::covers::CoverUrls::new(config);
+        fb.logo = config
+            .opds
+            .logo_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string);
+        fb
     }
 
-    /// Write the XML declaration and open the <feed> element with namespaces.
+    /// Write the XML declaration and open the <feed> element with namespaces,
+    /// the instance icon/logo, and the `self`, `start` and `up` links of `crumbs`.
     pub fn begin_feed(
         &mut self,
         id: &str,
         title: &str,
         subtitle: &str,
         updated: &str,
-        self_href: &str,
-        start_href: &str,
+        crumbs: &Breadcrumbs,
     ) -> Result<(), quick_xml::Error> {
+        self.kind = crumbs.kind;
         self.writer
             .write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
 
         let mut feed = BytesStart::new("feed");
         feed.push_attribute(("xmlns", "http://www.w3.org/2005/Atom"));
-        feed.push_attribute(("xmlns:dcterms", "http://purl.org/dc/terms"));
+        feed.push_attribute(("xmlns:dc", "http://purl.org/dc/elements/1.1/"));
+        feed.push_attribute(("xmlns:dcterms", "http://purl.org/dc/terms/"));
         feed.push_attribute(("xmlns:opds", "http://opds-spec.org/2010/catalog"));
         self.writer.write_event(Event::Start(feed))?;
 
@@ -106,11 +210,18 @@ impl FeedBuilder {
             self.write_text_element("subtitle", subtitle)?;
         }
         self.write_text_element("updated", updated)?;
+        let icon = self.client.href(FEED_ICON_HREF).into_owned();
+        self.write_text_element("icon", &icon)?;
+        if let Some(logo) = self.logo.take() {
+            let logo = self.client.href(&logo).into_owned();
+            self.write_text_element("logo", &logo)?;
+        }
 
-        // Self link
-        self.write_link(self_href, "self", NAV_TYPE, None)?;
-        // Start link
-        self.write_link(start_href, "start", NAV_TYPE, None)?;
+        self.write_link(&crumbs.self_href, "self", crumbs.kind.link_type(), None)?;
+        self.write_link(&crumbs.start_href, "start", NAV_TYPE, None)?;
+        if let Some(up) = &crumbs.up {
+            self.write_link(&up.href, "up", NAV_TYPE, up.title.as_deref())?;
+        }
 
         Ok(())
     }
@@ -126,17 +237,18 @@ impl FeedBuilder {
         Ok(())
     }
 
-    /// Write pagination links.
+    /// Write pagination links, typed like the feed's `self` link.
     pub fn write_pagination(
         &mut self,
         prev_href: Option<&str>,
         next_href: Option<&str>,
     ) -> Result<(), quick_xml::Error> {
+        let link_type = self.kind.link_type();
         if let Some(prev) = prev_href {
-            self.write_link(prev, "prev", ACQ_TYPE, Some("Previous Page"))?;
+            self.write_link(prev, "prev", link_type, Some("Previous Page"))?;
         }
         if let Some(next) = next_href {
-            self.write_link(next, "next", ACQ_TYPE, Some("Next Page"))?;
+            self.write_link(next, "next", link_type, Some("Next Page"))?;
         }
         Ok(())
     }
@@ -149,12 +261,29 @@ impl FeedBuilder {
         href: &str,
         content: &str,
         updated: &str,
+    ) -> Result<(), quick_xml::Error> {
+        self.write_nav_entry_with_thumbnail(id, title, href, content, updated, None)
+    }
+
+    /// Navigation entry with an optional JPEG thumbnail (catalog cover mosaic).
+    pub fn write_nav_entry_with_thumbnail(
+        &mut self,
+        id: &str,
+        title: &str,
+        href: &str,
+        content: &str,
+        updated: &str,
+        thumbnail_href: Option<&str>,
     ) -> Result<(), quick_xml::Error> {
         self.writer
             .write_event(Event::Start(BytesStart::new("entry")))?;
         self.write_text_element("id", id)?;
         self.write_text_element("title", title)?;
         self.write_link(href, "subsection", NAV_TYPE, None)?;
+        if let Some(thumb) = thumbnail_href {
+            self.write_link(thumb, REL_IMAGE, "image/jpeg", None)?;
+            self.write_link(thumb, REL_THUMBNAIL, "image/jpeg", None)?;
+        }
         self.write_text_element("updated", updated)?;
         if !content.is_empty() {
             self.write_content_text(content)?;
@@ -180,30 +309,41 @@ impl FeedBuilder {
     }
 
     /// Write book acquisition links (download original, zipped, cover, thumbnail).
+    /// `cover_mime` is the type of the stored cover, if the book has one.
+    /// Clients that prefer zipped downloads get the zipped link first.
     pub fn write_acquisition_links(
         &mut self,
         book_id: i64,
         format: &str,
-        has_cover: bool,
+        cover_mime: Option<&str>,
     ) -> Result<(), quick_xml::Error> {
         let dl_href = format!("/opds/download/{book_id}/0/");
-        let mime = mime_for_format(format);
+        let mime = mime_for_format(download_format(format));
+
+        let rel = crate::formats::rel(format);
+
+        let zipped = !is_nozip_format(format);
+        let zip_href = format!("/opds/download/{book_id}/1/");
+        let zip_mime = mime_for_zip(format);
+        let zip_first = zipped && self.client.prefer_zip;
+
+        if zip_first {
+            self.write_link(&zip_href, rel, &zip_mime, None)?;
+        }
 
         // Original format download
-        self.write_link(&dl_href, REL_ACQUISITION, mime, None)?;
+        self.write_link(&dl_href, rel, mime, None)?;
 
         // Zipped download (if applicable)
-        if !is_nozip_format(format) {
-            let zip_href = format!("/opds/download/{book_id}/1/");
-            let zip_mime = mime_for_zip(format);
-            self.write_link(&zip_href, REL_ACQUISITION, &zip_mime, None)?;
+        if zipped && !zip_first {
+            self.write_link(&zip_href, rel, &zip_mime, None)?;
         }
 
         // Cover and thumbnail
-        if has_cover {
-            let cover_href = format!("/opds/cover/{book_id}/");
-            let thumb_href = format!("/opds/thumb/{book_id}/");
-            self.write_link(&cover_href, REL_IMAGE, "image/jpeg", None)?;
+        if let Some(cover_mime) = cover_mime {
+            let cover_href = self.covers.cover(book_id);
+            let thumb_href = self.covers.thumb(book_id);
+            self.write_link(&cover_href, REL_IMAGE, cover_mime, None)?;
             self.write_link(&thumb_href, REL_THUMBNAIL, "image/jpeg", None)?;
             // Keep legacy relation for broader client compatibility.
             self.write_link(&thumb_href, REL_THUMBNAIL_LEGACY, "image/jpeg", None)?;
@@ -212,6 +352,39 @@ impl FeedBuilder {
         Ok(())
     }
 
+    /// Write streaming links for the tracks of an MP3 audiobook, in play order.
+    pub fn write_track_links(
+        &mut self,
+        book_id: i64,
+        tracks: &[String],
+    ) -> Result<(), quick_xml::Error> {
+        for (i, name) in tracks.iter().enumerate() {
+            let href = format!("/opds/audio/{book_id}/{i}/");
+            self.write_link(&href, REL_ACQUISITION, "audio/mpeg", Some(name))?;
+        }
+        Ok(())
+    }
+
+    /// Write `<dc:language>` (ISO 639 code) for the current entry.
+    pub fn write_language(&mut self, lang: &str) -> Result<(), quick_xml::Error> {
+        self.write_text_element("dc:language", lang)
+    }
+
+    /// Write `<dcterms:issued>` (W3CDTF date or year) for the current entry.
+    pub fn write_issued(&mut self, date: &str) -> Result<(), quick_xml::Error> {
+        self.write_text_element("dcterms:issued", date)
+    }
+
+    /// Write `<dc:format>` (MIME type of the book file) for the current entry.
+    pub fn write_format(&mut self, mime: &str) -> Result<(), quick_xml::Error> {
+        self.write_text_element("dc:format", mime)
+    }
+
+    /// Type the current entry as an audiobook.
+    pub fn write_audiobook_type(&mut self) -> Result<(), quick_xml::Error> {
+        self.write_text_element("dcterms:type", AUDIOBOOK_TYPE)
+    }
+
     /// Write HTML content (book description).
     pub fn write_content_html(&mut self, html: &str) -> Result<(), quick_xml::Error> {
         let mut el = BytesStart::new("content");
@@ -248,6 +421,9 @@ impl FeedBuilder {
     /// Write a <category> element from a typed model.
     pub fn write_category_obj(&mut self, category: &Category) -> Result<(), quick_xml::Error> {
         let mut el = BytesStart::new("category");
+        if let Some(scheme) = &category.scheme {
+            el.push_attribute(("scheme", scheme.as_str()));
+        }
         el.push_attribute(("term", category.term.as_str()));
         el.push_attribute(("label", category.label.as_str()));
         self.writer.write_event(Event::Empty(el))?;
@@ -287,7 +463,7 @@ impl FeedBuilder {
     /// Write a <link> element from a typed model.
     pub fn write_link_obj(&mut self, link: &Link) -> Result<(), quick_xml::Error> {
         let mut el = BytesStart::new("link");
-        el.push_attribute(("href", link.href.as_str()));
+        el.push_attribute(("href", self.client.href(&link.href).as_ref()));
         el.push_attribute(("rel", link.rel.as_str()));
         el.push_attribute(("type", link.link_type.as_str()));
         if let Some(t) = &link.title {
@@ -297,7 +473,7 @@ impl FeedBuilder {
         Ok(())
     }
 
-    /// Write an OPDS facet link.
+    /// Write an OPDS facet link (nothing for clients without facets).
     pub fn write_facet_link(
         &mut self,
         href: &str,
@@ -306,8 +482,11 @@ impl FeedBuilder {
         facet_group: &str,
         active: bool,
     ) -> Result<(), quick_xml::Error> {
+        if self.client.hide_facets {
+            return Ok(());
+        }
         let mut el = BytesStart::new("link");
-        el.push_attribute(("href", href));
+        el.push_attribute(("href", self.client.href(href).as_ref()));
         el.push_attribute(("rel", REL_FACET));
         el.push_attribute(("type", link_type));
         el.push_attribute(("title", title));
@@ -338,6 +517,10 @@ mod tests {
         assert!(!is_nozip_format("fb2"));
         assert_eq!(mime_for_zip("fb2"), "application/fb2+zip");
         assert_eq!(mime_for_zip("pdf"), "application/pdf+zip");
+        assert!(is_nozip_format("m4b"));
+        assert_eq!(download_format("mp3"), "zip");
+        assert_eq!(download_format("m4b"), "m4b");
+        assert_eq!(mime_for_format("m4b"), "audio/mp4");
     }
 
     #[test]
@@ -348,8 +531,7 @@ mod tests {
             "Test Feed",
             "Subtitle",
             "2024-01-01T00:00:00Z",
-            "/opds/test/",
-            "/opds/",
+            &Breadcrumbs::new("/opds/test/", "/opds/"),
         )
         .unwrap();
         fb.write_search_links("/opds/search/", "/opds/search/{searchTerms}/")
@@ -378,13 +560,13 @@ mod tests {
             "Books",
             "",
             "2024-01-01T00:00:00Z",
-            "/opds/",
-            "/opds/",
+            &Breadcrumbs::new("/opds/", "/opds/"),
         )
         .unwrap();
         fb.begin_entry("b:1", "Book One", "2024-01-01T00:00:00Z")
             .unwrap();
-        fb.write_acquisition_links(1, "fb2", true).unwrap();
+        fb.write_acquisition_links(1, "fb2", Some("image/png"))
+            .unwrap();
         fb.write_author_obj(&Author {
             name: "Author A".to_string(),
         })
@@ -392,8 +574,18 @@ mod tests {
         fb.write_category_obj(&Category {
             term: "sf".to_string(),
             label: "Sci-Fi".to_string(),
+            scheme: None,
         })
         .unwrap();
+        fb.write_category_obj(&Category {
+            term: "Saga".to_string(),
+            label: "Saga #2".to_string(),
+            scheme: Some(SERIES_SCHEME.to_string()),
+        })
+        .unwrap();
+        fb.write_language("en").unwrap();
+        fb.write_issued("1951").unwrap();
+        fb.write_format("application/fb2+xml").unwrap();
         fb.write_content_html("<p>anno</p>").unwrap();
         fb.end_entry().unwrap();
         let xml = String::from_utf8(fb.finish().unwrap()).unwrap();
@@ -403,8 +595,17 @@ mod tests {
         assert!(xml.contains(REL_IMAGE));
         assert!(xml.contains(REL_THUMBNAIL));
         assert!(xml.contains(REL_THUMBNAIL_LEGACY));
+        assert!(xml.contains(&format!(
+            "<link href=\"/opds/cover/1/\" rel=\"{REL_IMAGE}\" type=\"image/png\"/>"
+        )));
         assert!(xml.contains("Author A"));
-        assert!(xml.contains("term=\"sf\""));
+        assert!(xml.contains("<category term=\"sf\" label=\"Sci-Fi\"/>"));
+        assert!(xml.contains(&format!(
+            "<category scheme=\"{SERIES_SCHEME}\" term=\"Saga\" label=\"Saga #2\"/>"
+        )));
+        assert!(xml.contains("<dc:language>en</dc:language>"));
+        assert!(xml.contains("<dcterms:issued>1951</dcterms:issued>"));
+        assert!(xml.contains("<dc:format>application/fb2+xml</dc:format>"));
         assert!(xml.contains("type=\"text/html\""));
         assert!(xml.contains("anno"));
     }
@@ -417,13 +618,12 @@ mod tests {
             "Books",
             "",
             "2024-01-01T00:00:00Z",
-            "/opds/",
-            "/opds/",
+            &Breadcrumbs::new("/opds/", "/opds/"),
         )
         .unwrap();
         fb.begin_entry("b:2", "EPUB", "2024-01-01T00:00:00Z")
             .unwrap();
-        fb.write_acquisition_links(2, "epub", false).unwrap();
+        fb.write_acquisition_links(2, "epub", None).unwrap();
         fb.end_entry().unwrap();
         let xml = String::from_utf8(fb.finish().unwrap()).unwrap();
         assert!(xml.contains("/opds/download/2/0/"));
@@ -438,8 +638,7 @@ mod tests {
             "Facets",
             "",
             "2024-01-01T00:00:00Z",
-            "/opds/facets/",
-            "/opds/",
+            &Breadcrumbs::new("/opds/facets/", "/opds/"),
         )
         .unwrap();
         fb.write_facet_link(
@@ -456,4 +655,90 @@ mod tests {
         assert!(xml.contains("opds:activeFacet=\"true\""));
         assert!(xml.contains("title=\"Russian\""));
     }
+    #[test]
+    fn test_breadcrumbs_links() {
+        let mut fb = FeedBuilder::new();
+        fb.begin_feed(
+            "tag:root",
+            "Root",
+            "",
+            "2024-01-01T00:00:00Z",
+            &Breadcrumbs::new("/opds/", "/opds/"),
+        )
+        .unwrap();
+        let xml = String::from_utf8(fb.finish().unwrap()).unwrap();
+        assert!(!xml.contains("rel=\"up\""));
+
+        let mut fb = FeedBuilder::new();
+        fb.begin_feed(
+            "tag:list",
+            "List",
+            "",
+            "2024-01-01T00:00:00Z",
+            &Breadcrumbs::new("/opds/authors/0/A/", "/opds/"),
+        )
+        .unwrap();
+        let xml = String::from_utf8(fb.finish().unwrap()).unwrap();
+        assert!(xml.contains(&format!(
+            "<link href=\"/opds/\" rel=\"up\" type=\"{NAV_TYPE}\"/>"
+        )));
+
+        let mut fb = FeedBuilder::new();
+        fb.begin_feed(
+            "tag:books",
+            "Books",
+            "",
+            "2024-01-01T00:00:00Z",
+            &Breadcrumbs::new("/opds/books/0/list/2/", "/opds/")
+                .up("/opds/books/", "Books")
+                .acquisition(),
+        )
+        .unwrap();
+        fb.write_pagination(Some("/opds/books/0/list/1/"), None)
+            .unwrap();
+        let xml = String::from_utf8(fb.finish().unwrap()).unwrap();
+        assert!(xml.contains(&format!(
+            "<link href=\"/opds/books/0/list/2/\" rel=\"self\" type=\"{ACQ_TYPE}\"/>"
+        )));
+        assert!(xml.contains(&format!(
+            "<link href=\"/opds/books/\" rel=\"up\" type=\"{NAV_TYPE}\" title=\"Books\"/>"
+        )));
+        assert!(xml.contains(&format!("rel=\"prev\" type=\"{ACQ_TYPE}\"")));
+    }
+
+    #[test]
+    fn test_feed_builder_client_profile() {
+        let profile = ClientProfile {
+            name: "pocketbook".to_string(),
+            hide_facets: true,
+            link_base: Some("https://books.example.com".to_string()),
+            prefer_zip: true,
+            max_items: None,
+        };
+        let xml = compat::with_profile(profile, || {
+            let mut fb = FeedBuilder::new();
+            fb.begin_feed(
+                "tag:books",
+                "Books",
+                "",
+                "2024-01-01T00:00:00Z",
+                &Breadcrumbs::new("/opds/books/", "/opds/").acquisition(),
+            )
+            .unwrap();
+            fb.write_facet_link("/opds/books/?sort=title", ACQ_TYPE, "Title", "Sort", false)
+                .unwrap();
+            fb.begin_entry("b:1", "Book One", "2024-01-01T00:00:00Z")
+                .unwrap();
+            fb.write_acquisition_links(1, "fb2", None).unwrap();
+            fb.end_entry().unwrap();
+            String::from_utf8(fb.finish().unwrap()).unwrap()
+        });
+
+        assert!(!xml.contains(REL_FACET));
+        assert!(xml.contains("<icon>https://books.example.com/favicon.ico</icon>"));
+        assert!(xml.contains("<link href=\"https://books.example.com/opds/books/\" rel=\"self\""));
+        let zip = xml.find("/opds/download/1/1/").unwrap();
+        let original = xml.find("/opds/download/1/0/").unwrap();
+        assert!(zip < original);
+    }
 }
@@ -3,11 +3,19 @@ use axum::http::{HeaderMap, StatusCode};
 use axum::response::Response;
 use serde_json::{Value, json};
 
-use crate::db::queries::{authors, books, bookshelf, catalogs, genres, series};
+use crate::db::queries::books::{BookSort, RecentKind, SearchFilter};
+use crate::db::queries::downloads::{self, POPULAR_PERIODS};
+use crate::db::queries::{
+    MatchMode, authors, books, bookshelf, cached, catalogs, fuzzy, genres, recommendations, series,
+};
+use crate::opds::v1::helpers::{add_bookshelf_query, search_facet_groups};
 use crate::state::AppState;
 
 use super::helpers::*;
-use super::{AuthorsListParams, AuthorsParams, CatalogsParams, LangQuery, SearchBooksParams};
+use super::{
+    AuthorsListParams, AuthorsParams, BooksQuery, BookshelfQuery, CatalogsParams, CatalogsQuery,
+    LangQuery, PopularQuery, SearchBooksParams,
+};
 
 pub async fn root_feed(
     State(state): State<AppState>,
@@ -36,6 +44,8 @@ async fn build_root_feed(
     let by_genres = tr(state, &lang, "opds", "root_by_genres", "By Genres");
     let by_series = tr(state, &lang, "opds", "root_by_series", "By Series");
     let by_recent = tr(state, &lang, "opds", "root_by_recent", "Recently Added");
+    let by_updated = tr(state, &lang, "opds", "root_by_updated", "Recently Updated");
+    let by_popular = tr(state, &lang, "opds", "root_by_popular", "Popular");
     let language_facets = tr(
         state,
         &lang,
@@ -50,6 +60,8 @@ async fn build_root_feed(
         nav_link(by_genres, add_lang_query("/opds/v2/genres/", &lang)),
         nav_link(by_series, add_lang_query("/opds/v2/series/", &lang)),
         nav_link(by_recent, add_lang_query("/opds/v2/recent/", &lang)),
+        nav_link(by_updated, add_lang_query("/opds/v2/updated/", &lang)),
+        nav_link(by_popular, add_lang_query("/opds/v2/popular/", &lang)),
         nav_link(
             language_facets,
             add_lang_query("/opds/v2/facets/languages/", &lang),
@@ -67,19 +79,30 @@ async fn build_root_feed(
             format!("{bookshelf_title}: {count}"),
             add_lang_query("/opds/v2/bookshelf/", &lang),
         ));
+        navigation.push(nav_link(
+            tr(state, &lang, "opds", "root_recommended", "Recommended"),
+            add_lang_query("/opds/v2/recommended/", &lang),
+        ));
+        navigation.push(nav_link(
+            tr(state, &lang, "opds", "root_next", "Next in series"),
+            add_lang_query("/opds/v2/next/", &lang),
+        ));
     }
 
+    let mut links = feed_links(
+        add_lang_query("/opds/v2/", &lang),
+        add_lang_query("/opds/v2/", &lang),
+        &lang,
+    );
+    links.extend(branding_links(&state.config.opds));
+
     opds2_response(json!({
         "metadata": {
             "title": state.config.opds.title,
             "modified": DEFAULT_MODIFIED,
             "numberOfItems": navigation.len()
         },
-        "links": feed_links(
-            add_lang_query("/opds/v2/", &lang),
-            add_lang_query("/opds/v2/", &lang),
-            &lang
-        ),
+        "links": links,
         "navigation": navigation
     }))
 }
@@ -87,21 +110,32 @@ async fn build_root_feed(
 pub async fn catalogs_root(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<CatalogsQuery>,
 ) -> Response {
-    build_catalogs_feed(&state, &headers, q.lang.as_deref(), 0, 1).await
+    build_catalogs_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        q.deep.as_deref(),
+        q.sort.as_deref(),
+        0,
+        1,
+    )
+    .await
 }
 
 pub async fn catalogs_feed(
     State(state): State<AppState>,
     headers: HeaderMap,
     Path(p): Path<CatalogsParams>,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<CatalogsQuery>,
 ) -> Response {
     build_catalogs_feed(
         &state,
         &headers,
         q.lang.as_deref(),
+        q.deep.as_deref(),
+        q.sort.as_deref(),
         p.cat_id,
         p.page.unwrap_or(1).max(1),
     )
@@ -112,27 +146,63 @@ async fn build_catalogs_feed(
     state: &AppState,
     headers: &HeaderMap,
     query_lang: Option<&str>,
+    query_deep: Option<&str>,
+    query_sort: Option<&str>,
     cat_id: i64,
     page: i32,
 ) -> Response {
     let lang = detect_opds_lang(headers, &state.config, query_lang);
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let offset = (page - 1) * max_items;
+    let deep = state.config.opds.catalog_deep(query_deep);
+    let deep_qs = query_deep
+        .map(|_| format!("?deep={}", u8::from(deep)))
+        .unwrap_or_default();
+    let sort = BookSort::resolve(&BookSort::LISTING, query_sort);
+    let books_qs = match query_sort {
+        Some(_) => add_query_param(&deep_qs, "sort", sort.as_str()),
+        None => deep_qs.clone(),
+    };
+    let chain = if cat_id > 0 {
+        catalogs::get_ancestors(&state.db, cat_id)
+            .await
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
 
     let self_href = if cat_id == 0 {
-        add_lang_query("/opds/v2/catalogs/", &lang)
+        add_lang_query(&format!("/opds/v2/catalogs/{deep_qs}"), &lang)
     } else {
-        add_lang_query(&format!("/opds/v2/catalogs/{cat_id}/{page}/"), &lang)
+        add_lang_query(
+            &format!("/opds/v2/catalogs/{cat_id}/{page}/{books_qs}"),
+            &lang,
+        )
     };
     let mut links = feed_links(self_href, add_lang_query("/opds/v2/", &lang), &lang);
     let mut navigation = Vec::new();
     let mut publications = Vec::new();
+    let mut facets = Vec::new();
+
+    if cat_id > 0 {
+        let (up_href, up_title) = match chain.len().checked_sub(2).map(|i| &chain[i]) {
+            Some(parent) => (
+                format!("/opds/v2/catalogs/{}/{deep_qs}", parent.id),
+                parent.cat_name.as_str(),
+            ),
+            None => (format!("/opds/v2/catalogs/{deep_qs}"), "Catalogs"),
+        };
+        links.push(json!({
+            "rel": "up",
+            "href": add_lang_query(&up_href, &lang),
+            "type": OPDS2_TYPE,
+            "title": up_title
+        }));
+    }
 
     if page == 1 {
         let cats = if cat_id == 0 {
-            catalogs::get_root_catalogs(&state.db)
-                .await
-                .unwrap_or_default()
+            cached::root_catalogs(state).await.unwrap_or_default()
         } else {
             catalogs::get_children(&state.db, cat_id)
                 .await
@@ -141,30 +211,48 @@ async fn build_catalogs_feed(
         for cat in cats {
             navigation.push(nav_link(
                 cat.cat_name,
-                add_lang_query(&format!("/opds/v2/catalogs/{}/", cat.id), &lang),
+                add_lang_query(&format!("/opds/v2/catalogs/{}/{deep_qs}", cat.id), &lang),
             ));
         }
     }
 
     if cat_id > 0 {
-        let hide_doubles = state.config.opds.hide_doubles;
-        let book_list = books::get_by_catalog(&state.db, cat_id, max_items, offset, hide_doubles)
-            .await
-            .unwrap_or_default();
+        let hide_doubles = state.config.opds.collapse_doubles();
+        let book_list = match chain.last() {
+            Some(_) if deep => {
+                books::get_by_catalog_tree(&state.db, cat_id, max_items, offset, hide_doubles, sort)
+                    .await
+            }
+            _ => {
+                books::get_by_catalog(&state.db, cat_id, max_items, offset, hide_doubles, sort)
+                    .await
+            }
+        }
+        .unwrap_or_default();
+
+        if !book_list.is_empty() || page > 1 {
+            facets.push(sort_facets(
+                state,
+                &lang,
+                &format!("/opds/v2/catalogs/{cat_id}/{deep_qs}"),
+                &BookSort::LISTING,
+                sort,
+            ));
+        }
 
         let has_next = book_list.len() as i32 >= max_items;
         let has_prev = page > 1;
         if has_prev {
             links.push(json!({
                 "rel": "prev",
-                "href": add_lang_query(&format!("/opds/v2/catalogs/{cat_id}/{}/", page - 1), &lang),
+                "href": add_lang_query(&format!("/opds/v2/catalogs/{cat_id}/{}/{books_qs}", page - 1), &lang),
                 "type": OPDS2_TYPE
             }));
         }
         if has_next {
             links.push(json!({
                 "rel": "next",
-                "href": add_lang_query(&format!("/opds/v2/catalogs/{cat_id}/{}/", page + 1), &lang),
+                "href": add_lang_query(&format!("/opds/v2/catalogs/{cat_id}/{}/{books_qs}", page + 1), &lang),
                 "type": OPDS2_TYPE
             }));
         }
@@ -184,6 +272,9 @@ async fn build_catalogs_feed(
         }),
     );
     body.insert("links".to_string(), Value::Array(links));
+    if !facets.is_empty() {
+        body.insert("facets".to_string(), Value::Array(facets));
+    }
     if !navigation.is_empty() {
         body.insert("navigation".to_string(), Value::Array(navigation));
     }
@@ -209,25 +300,18 @@ async fn lang_selection_feed(
     let digits_label = tr(state, &lang, "browse", "digits", "Digits");
     let other_label = tr(state, &lang, "browse", "other", "Other");
 
-    let navigation = vec![
-        nav_link(all_label, add_lang_query(&format!("{base_href}0/"), &lang)),
-        nav_link(
-            cyrillic_label,
-            add_lang_query(&format!("{base_href}1/"), &lang),
-        ),
-        nav_link(
-            latin_label,
-            add_lang_query(&format!("{base_href}2/"), &lang),
-        ),
-        nav_link(
-            digits_label,
-            add_lang_query(&format!("{base_href}3/"), &lang),
-        ),
-        nav_link(
-            other_label,
-            add_lang_query(&format!("{base_href}9/"), &lang),
-        ),
-    ];
+    let counts = cached::hide_empty_counts(state).await;
+    let navigation: Vec<Value> = [
+        (0, all_label),
+        (1, cyrillic_label),
+        (2, latin_label),
+        (3, digits_label),
+        (9, other_label),
+    ]
+    .into_iter()
+    .filter(|(code, _)| *code == 0 || counts.as_ref().is_none_or(|c| c.lang(nav_key, *code) > 0))
+    .map(|(code, label)| nav_link(label, add_lang_query(&format!("{base_href}{code}/"), &lang)))
+    .collect();
 
     opds2_response(json!({
         "metadata": {
@@ -266,14 +350,21 @@ pub async fn authors_feed(
     Path(params): Path<AuthorsParams>,
     Query(q): Query<LangQuery>,
 ) -> Response {
+    if !state.config.opds.alphabet_menu {
+        let params = AuthorsListParams {
+            lang_code: params.lang_code,
+            prefix: params.prefix.unwrap_or_default(),
+            page: None,
+        };
+        return authors_list(State(state), headers, Path(params), Query(q)).await;
+    }
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
     let split_items = state.config.opds.split_items as i64;
     let prefix = params.prefix.unwrap_or_default();
 
-    let groups =
-        authors::get_name_prefix_groups(&state.db, params.lang_code, &prefix.to_uppercase())
-            .await
-            .unwrap_or_default();
+    let groups = cached::author_prefix_groups(&state, params.lang_code, &prefix.to_uppercase())
+        .await
+        .unwrap_or_default();
 
     let mut navigation = Vec::with_capacity(groups.len());
     for (prefix_str, count) in &groups {
@@ -324,7 +415,7 @@ pub async fn authors_list(
     Query(q): Query<LangQuery>,
 ) -> Response {
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let page = params.page.unwrap_or(1).max(1);
     let offset = (page - 1) * max_items;
 
@@ -340,12 +431,7 @@ pub async fn authors_list(
 
     let mut links = feed_links(
         add_lang_query(
-            &format!(
-                "/opds/v2/authors/{}/{}/list/{}/",
-                params.lang_code,
-                urlencoding::encode(&params.prefix),
-                page
-            ),
+            &list_href("/opds/v2/authors/", params.lang_code, &params.prefix, page),
             &lang,
         ),
         add_lang_query("/opds/v2/", &lang),
@@ -355,12 +441,7 @@ pub async fn authors_list(
         links.push(json!({
             "rel": "prev",
             "href": add_lang_query(
-                &format!(
-                    "/opds/v2/authors/{}/{}/list/{}/",
-                    params.lang_code,
-                    urlencoding::encode(&params.prefix),
-                    page - 1
-                ),
+                &list_href("/opds/v2/authors/", params.lang_code, &params.prefix, page - 1),
                 &lang
             ),
             "type": OPDS2_TYPE
@@ -370,12 +451,7 @@ pub async fn authors_list(
         links.push(json!({
             "rel": "next",
             "href": add_lang_query(
-                &format!(
-                    "/opds/v2/authors/{}/{}/list/{}/",
-                    params.lang_code,
-                    urlencoding::encode(&params.prefix),
-                    page + 1
-                ),
+                &list_href("/opds/v2/authors/", params.lang_code, &params.prefix, page + 1),
                 &lang
             ),
             "type": OPDS2_TYPE
@@ -391,10 +467,16 @@ pub async fn authors_list(
             )
         })
         .collect();
+    let title = tr(&state, &lang, "nav", "authors", "Authors");
+    let title = if params.prefix.is_empty() {
+        title
+    } else {
+        format!("{title}: {}", params.prefix)
+    };
 
     opds2_response(json!({
         "metadata": {
-            "title": format!("{}: {}", tr(&state, &lang, "nav", "authors", "Authors"), params.prefix),
+            "title": title,
             "modified": DEFAULT_MODIFIED,
             "numberOfItems": navigation.len()
         },
@@ -425,14 +507,21 @@ pub async fn series_feed(
     Path(params): Path<AuthorsParams>,
     Query(q): Query<LangQuery>,
 ) -> Response {
+    if !state.config.opds.alphabet_menu {
+        let params = AuthorsListParams {
+            lang_code: params.lang_code,
+            prefix: params.prefix.unwrap_or_default(),
+            page: None,
+        };
+        return series_list(State(state), headers, Path(params), Query(q)).await;
+    }
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
     let split_items = state.config.opds.split_items as i64;
     let prefix = params.prefix.unwrap_or_default();
 
-    let groups =
-        series::get_name_prefix_groups(&state.db, params.lang_code, &prefix.to_uppercase())
-            .await
-            .unwrap_or_default();
+    let groups = cached::series_prefix_groups(&state, params.lang_code, &prefix.to_uppercase())
+        .await
+        .unwrap_or_default();
 
     let mut navigation = Vec::with_capacity(groups.len());
     for (prefix_str, count) in &groups {
@@ -483,7 +572,7 @@ pub async fn series_list(
     Query(q): Query<LangQuery>,
 ) -> Response {
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let page = params.page.unwrap_or(1).max(1);
     let offset = (page - 1) * max_items;
 
@@ -499,12 +588,7 @@ pub async fn series_list(
 
     let mut links = feed_links(
         add_lang_query(
-            &format!(
-                "/opds/v2/series/{}/{}/list/{}/",
-                params.lang_code,
-                urlencoding::encode(&params.prefix),
-                page
-            ),
+            &list_href("/opds/v2/series/", params.lang_code, &params.prefix, page),
             &lang,
         ),
         add_lang_query("/opds/v2/", &lang),
@@ -514,12 +598,7 @@ pub async fn series_list(
         links.push(json!({
             "rel": "prev",
             "href": add_lang_query(
-                &format!(
-                    "/opds/v2/series/{}/{}/list/{}/",
-                    params.lang_code,
-                    urlencoding::encode(&params.prefix),
-                    page - 1
-                ),
+                &list_href("/opds/v2/series/", params.lang_code, &params.prefix, page - 1),
                 &lang
             ),
             "type": OPDS2_TYPE
@@ -529,12 +608,7 @@ pub async fn series_list(
         links.push(json!({
             "rel": "next",
             "href": add_lang_query(
-                &format!(
-                    "/opds/v2/series/{}/{}/list/{}/",
-                    params.lang_code,
-                    urlencoding::encode(&params.prefix),
-                    page + 1
-                ),
+                &list_href("/opds/v2/series/", params.lang_code, &params.prefix, page + 1),
                 &lang
             ),
             "type": OPDS2_TYPE
@@ -550,10 +624,16 @@ pub async fn series_list(
             )
         })
         .collect();
+    let title = tr(&state, &lang, "nav", "series", "Series");
+    let title = if params.prefix.is_empty() {
+        title
+    } else {
+        format!("{title}: {}", params.prefix)
+    };
 
     opds2_response(json!({
         "metadata": {
-            "title": format!("{}: {}", tr(&state, &lang, "nav", "series", "Series"), params.prefix),
+            "title": title,
             "modified": DEFAULT_MODIFIED,
             "numberOfItems": navigation.len()
         },
@@ -568,18 +648,23 @@ pub async fn genres_root(
     Query(q): Query<LangQuery>,
 ) -> Response {
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
-    let sections = genres::get_sections(&state.db, &lang)
+    let mut sections = cached::genre_sections(&state, &lang)
         .await
         .unwrap_or_default();
+    let counts = cached::item_counts(&state).await.unwrap_or_default();
+    if state.config.opds.hide_empty {
+        sections.retain(|(code, _)| counts.section(code) > 0);
+    }
     let navigation: Vec<Value> = sections
         .iter()
         .map(|(code, name)| {
-            nav_link(
+            nav_link_with_count(
                 name.clone(),
                 add_lang_query(
                     &format!("/opds/v2/genres/{}/", urlencoding::encode(code)),
                     &lang,
                 ),
+                counts.section(code),
             )
         })
         .collect();
@@ -606,20 +691,25 @@ pub async fn genres_by_section(
     Query(q): Query<LangQuery>,
 ) -> Response {
     let lang = detect_opds_lang(&headers, &state.config, q.lang.as_deref());
-    let genre_list = genres::get_by_section(&state.db, &section_code, &lang)
+    let mut genre_list = cached::genres_in_section(&state, &section_code, &lang)
         .await
         .unwrap_or_default();
     let title = genre_list
         .first()
         .map(|g| g.section.clone())
         .unwrap_or_else(|| section_code.clone());
+    let counts = cached::item_counts(&state).await.unwrap_or_default();
+    if state.config.opds.hide_empty {
+        genre_list.retain(|g| counts.genre(g.id) > 0);
+    }
 
     let navigation: Vec<Value> = genre_list
         .iter()
         .map(|g| {
-            nav_link(
+            nav_link_with_count(
                 g.subsection.clone(),
                 add_lang_query(&format!("/opds/v2/search/books/g/{}/", g.id), &lang),
+                counts.genre(g.id),
             )
         })
         .collect();
@@ -678,7 +768,7 @@ pub async fn recent_root(
     headers: HeaderMap,
     Query(q): Query<LangQuery>,
 ) -> Response {
-    build_recent_feed(&state, &headers, q.lang.as_deref(), 1).await
+    build_recent_feed(&state, &headers, q.lang.as_deref(), RecentKind::Added, 1).await
 }
 
 pub async fn recent_feed(
@@ -687,40 +777,121 @@ pub async fn recent_feed(
     Path((page,)): Path<(i32,)>,
     Query(q): Query<LangQuery>,
 ) -> Response {
-    build_recent_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Added,
+        page.max(1),
+    )
+    .await
+}
+
+pub async fn recent_genre_root(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((genre_id,)): Path<(i64,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Genre(genre_id),
+        1,
+    )
+    .await
+}
+
+pub async fn recent_genre_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((genre_id, page)): Path<(i64, i32)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Genre(genre_id),
+        page.max(1),
+    )
+    .await
+}
+
+pub async fn updated_root(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(&state, &headers, q.lang.as_deref(), RecentKind::Updated, 1).await
+}
+
+pub async fn updated_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recent_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        RecentKind::Updated,
+        page.max(1),
+    )
+    .await
 }
 
 async fn build_recent_feed(
     state: &AppState,
     headers: &HeaderMap,
     query_lang: Option<&str>,
+    kind: RecentKind,
     page: i32,
 ) -> Response {
     let lang = detect_opds_lang(headers, &state.config, query_lang);
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let offset = (page - 1) * max_items;
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
+    let by_recent = tr(state, &lang, "opds", "root_by_recent", "Recently Added");
+    let (base, title) = match kind {
+        RecentKind::Added => ("/opds/v2/recent/".to_string(), by_recent),
+        RecentKind::Updated => (
+            "/opds/v2/updated/".to_string(),
+            tr(state, &lang, "opds", "root_by_updated", "Recently Updated"),
+        ),
+        RecentKind::Genre(id) => {
+            let Ok(Some(genre)) = genres::get_by_id(&state.db, id, &lang).await else {
+                return error_response(StatusCode::NOT_FOUND, "Genre not found");
+            };
+            (
+                format!("/opds/v2/recent/genre/{id}/"),
+                format!("{by_recent}: {}", genre.subsection),
+            )
+        }
+    };
 
-    let book_list = books::get_recent_added(&state.db, max_items, offset, hide_doubles)
+    let book_list = books::get_recent(&state.db, kind, max_items, offset, hide_doubles)
         .await
         .unwrap_or_default();
 
     let mut links = feed_links(
-        add_lang_query(&format!("/opds/v2/recent/{page}/"), &lang),
+        add_lang_query(&format!("{base}{page}/"), &lang),
         add_lang_query("/opds/v2/", &lang),
         &lang,
     );
     if page > 1 {
         links.push(json!({
             "rel": "prev",
-            "href": add_lang_query(&format!("/opds/v2/recent/{}/", page - 1), &lang),
+            "href": add_lang_query(&format!("{base}{}/", page - 1), &lang),
             "type": OPDS2_TYPE
         }));
     }
     if book_list.len() as i32 >= max_items {
         links.push(json!({
             "rel": "next",
-            "href": add_lang_query(&format!("/opds/v2/recent/{}/", page + 1), &lang),
+            "href": add_lang_query(&format!("{base}{}/", page + 1), &lang),
             "type": OPDS2_TYPE
         }));
     }
@@ -732,11 +903,97 @@ async fn build_recent_feed(
 
     opds2_response(json!({
         "metadata": {
-            "title": tr(state, &lang, "opds", "root_by_recent", "Recently Added"),
+            "title": title,
+            "modified": DEFAULT_MODIFIED,
+            "numberOfItems": publications.len()
+        },
+        "links": links,
+        "publications": publications
+    }))
+}
+
+pub async fn popular_root(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<PopularQuery>,
+) -> Response {
+    build_popular_feed(&state, &headers, &q, 1).await
+}
+
+pub async fn popular_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<PopularQuery>,
+) -> Response {
+    build_popular_feed(&state, &headers, &q, page.max(1)).await
+}
+
+async fn build_popular_feed(
+    state: &AppState,
+    headers: &HeaderMap,
+    q: &PopularQuery,
+    page: i32,
+) -> Response {
+    let lang = detect_opds_lang(headers, &state.config, q.lang.as_deref());
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let offset = (page - 1) * max_items;
+    let period = downloads::popular_period(q.period.as_deref());
+    let page_href =
+        |page: i32| add_lang_query(&format!("/opds/v2/popular/{page}/?period={period}"), &lang);
+
+    let book_list = books::get_popular(&state.db, period, max_items, offset)
+        .await
+        .unwrap_or_default();
+
+    let mut links = feed_links(page_href(page), add_lang_query("/opds/v2/", &lang), &lang);
+    if page > 1 {
+        links.push(json!({
+            "rel": "prev",
+            "href": page_href(page - 1),
+            "type": OPDS2_TYPE
+        }));
+    }
+    if book_list.len() as i32 >= max_items {
+        links.push(json!({
+            "rel": "next",
+            "href": page_href(page + 1),
+            "type": OPDS2_TYPE
+        }));
+    }
+
+    let days_label = tr(state, &lang, "home", "days", "days");
+    let period_links: Vec<Value> = POPULAR_PERIODS
+        .iter()
+        .map(|&days| {
+            let mut link = json!({
+                "title": format!("{days} {days_label}"),
+                "href": add_lang_query(&format!("/opds/v2/popular/?period={days}"), &lang),
+                "type": OPDS2_TYPE
+            });
+            if days == period {
+                link["rel"] = json!("self");
+            }
+            link
+        })
+        .collect();
+
+    let mut publications = Vec::with_capacity(book_list.len());
+    for (book, _) in &book_list {
+        publications.push(book_publication(state, book, &lang).await);
+    }
+
+    opds2_response(json!({
+        "metadata": {
+            "title": tr(state, &lang, "opds", "root_by_popular", "Popular"),
             "modified": DEFAULT_MODIFIED,
             "numberOfItems": publications.len()
         },
         "links": links,
+        "facets": [{
+            "metadata": { "title": tr(state, &lang, "opds", "facet_period", "Period") },
+            "links": period_links
+        }],
         "publications": publications
     }))
 }
@@ -744,65 +1001,103 @@ async fn build_recent_feed(
 pub async fn bookshelf_root(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<BookshelfQuery>,
 ) -> Response {
-    build_bookshelf_feed(&state, &headers, q.lang.as_deref(), 1).await
+    build_bookshelf_feed(&state, &headers, &q, 1).await
 }
 
 pub async fn bookshelf_feed(
     State(state): State<AppState>,
     headers: HeaderMap,
     Path((page,)): Path<(i32,)>,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<BookshelfQuery>,
 ) -> Response {
-    build_bookshelf_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+    build_bookshelf_feed(&state, &headers, &q, page.max(1)).await
 }
 
 async fn build_bookshelf_feed(
     state: &AppState,
     headers: &HeaderMap,
-    query_lang: Option<&str>,
+    q: &BookshelfQuery,
     page: i32,
 ) -> Response {
-    let lang = detect_opds_lang(headers, &state.config, query_lang);
+    let lang = detect_opds_lang(headers, &state.config, q.lang.as_deref());
     let user_id = match crate::opds::auth::get_user_id_from_headers(&state.db, headers).await {
         Some(uid) => uid,
         None => return error_response(StatusCode::UNAUTHORIZED, "Authentication required"),
     };
+    let (sort, ascending) = q.order();
+    let read_state = q.read_state();
+    let shelf_href = |page: i32, sort, ascending, read_state| {
+        add_lang_query(
+            &add_bookshelf_query(
+                &format!("/opds/v2/bookshelf/{page}/"),
+                sort,
+                ascending,
+                read_state,
+            ),
+            &lang,
+        )
+    };
 
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let offset = (page - 1) * max_items;
-    let book_list = bookshelf::get_by_user(
-        &state.db,
-        user_id,
-        &bookshelf::SortColumn::Date,
-        false,
-        max_items,
-        offset,
+    let book_list = bookshelf::get_filtered(
+        &state.db, user_id, &sort, ascending, read_state, max_items, offset,
     )
     .await
     .unwrap_or_default();
 
     let mut links = feed_links(
-        add_lang_query(&format!("/opds/v2/bookshelf/{page}/"), &lang),
+        shelf_href(page, sort, ascending, read_state),
         add_lang_query("/opds/v2/", &lang),
         &lang,
     );
     if page > 1 {
         links.push(json!({
             "rel": "prev",
-            "href": add_lang_query(&format!("/opds/v2/bookshelf/{}/", page - 1), &lang),
+            "href": shelf_href(page - 1, sort, ascending, read_state),
             "type": OPDS2_TYPE
         }));
     }
     if book_list.len() as i32 >= max_items {
         links.push(json!({
             "rel": "next",
-            "href": add_lang_query(&format!("/opds/v2/bookshelf/{}/", page + 1), &lang),
+            "href": shelf_href(page + 1, sort, ascending, read_state),
             "type": OPDS2_TYPE
         }));
     }
 
+    let facet_link = |title: String, href: String, active: bool| {
+        let mut link = json!({ "title": title, "href": href, "type": OPDS2_TYPE });
+        if active {
+            link["rel"] = json!("self");
+        }
+        link
+    };
+    let sort_links: Vec<Value> = bookshelf::SortColumn::ALL
+        .into_iter()
+        .map(|option| {
+            let value = option.as_str();
+            facet_link(
+                tr(state, &lang, "bookshelf", &format!("sort_{value}"), value),
+                shelf_href(1, option, option.default_ascending(), read_state),
+                option == sort,
+            )
+        })
+        .collect();
+    let state_links: Vec<Value> = bookshelf::ReadState::ALL
+        .into_iter()
+        .map(|option| {
+            let value = option.as_str();
+            facet_link(
+                tr(state, &lang, "bookshelf", &format!("state_{value}"), value),
+                shelf_href(1, sort, ascending, option),
+                option == read_state,
+            )
+        })
+        .collect();
+
     let mut publications = Vec::with_capacity(book_list.len());
     for book in &book_list {
         publications.push(book_publication(state, book, &lang).await);
@@ -815,6 +1110,158 @@ async fn build_bookshelf_feed(
             "numberOfItems": publications.len()
         },
         "links": links,
+        "facets": [
+            {
+                "metadata": { "title": tr(state, &lang, "opds", "facet_sort", "Sort by") },
+                "links": sort_links
+            },
+            {
+                "metadata": { "title": tr(state, &lang, "opds", "facet_read_state", "Reading state") },
+                "links": state_links
+            }
+        ],
+        "publications": publications
+    }))
+}
+
+pub async fn recommended_root(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recommended_feed(&state, &headers, q.lang.as_deref(), 1).await
+}
+
+pub async fn recommended_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_recommended_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+}
+
+async fn build_recommended_feed(
+    state: &AppState,
+    headers: &HeaderMap,
+    query_lang: Option<&str>,
+    page: i32,
+) -> Response {
+    let lang = detect_opds_lang(headers, &state.config, query_lang);
+    let user_id = match crate::opds::auth::get_user_id_from_headers(&state.db, headers).await {
+        Some(uid) => uid,
+        None => return error_response(StatusCode::UNAUTHORIZED, "Authentication required"),
+    };
+
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let offset = (page - 1) * max_items;
+    let book_list = recommendations::get_for_user(&state.db, user_id, max_items, offset)
+        .await
+        .unwrap_or_default();
+
+    let mut links = feed_links(
+        add_lang_query(&format!("/opds/v2/recommended/{page}/"), &lang),
+        add_lang_query("/opds/v2/", &lang),
+        &lang,
+    );
+    if page > 1 {
+        links.push(json!({
+            "rel": "prev",
+            "href": add_lang_query(&format!("/opds/v2/recommended/{}/", page - 1), &lang),
+            "type": OPDS2_TYPE
+        }));
+    }
+    if book_list.len() as i32 >= max_items {
+        links.push(json!({
+            "rel": "next",
+            "href": add_lang_query(&format!("/opds/v2/recommended/{}/", page + 1), &lang),
+            "type": OPDS2_TYPE
+        }));
+    }
+
+    let mut publications = Vec::with_capacity(book_list.len());
+    for book in &book_list {
+        publications.push(book_publication(state, book, &lang).await);
+    }
+
+    opds2_response(json!({
+        "metadata": {
+            "title": tr(state, &lang, "opds", "root_recommended", "Recommended"),
+            "modified": DEFAULT_MODIFIED,
+            "numberOfItems": publications.len()
+        },
+        "links": links,
+        "publications": publications
+    }))
+}
+
+pub async fn next_in_series_root(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_next_in_series_feed(&state, &headers, q.lang.as_deref(), 1).await
+}
+
+pub async fn next_in_series_feed(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path((page,)): Path<(i32,)>,
+    Query(q): Query<LangQuery>,
+) -> Response {
+    build_next_in_series_feed(&state, &headers, q.lang.as_deref(), page.max(1)).await
+}
+
+async fn build_next_in_series_feed(
+    state: &AppState,
+    headers: &HeaderMap,
+    query_lang: Option<&str>,
+    page: i32,
+) -> Response {
+    let lang = detect_opds_lang(headers, &state.config, query_lang);
+    let user_id = match crate::opds::auth::get_user_id_from_headers(&state.db, headers).await {
+        Some(uid) => uid,
+        None => return error_response(StatusCode::UNAUTHORIZED, "Authentication required"),
+    };
+
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
+    let offset = (page - 1) * max_items;
+    let book_list = series::next_unread_for_user(&state.db, user_id, max_items, offset)
+        .await
+        .unwrap_or_default();
+
+    let mut links = feed_links(
+        add_lang_query(&format!("/opds/v2/next/{page}/"), &lang),
+        add_lang_query("/opds/v2/", &lang),
+        &lang,
+    );
+    if page > 1 {
+        links.push(json!({
+            "rel": "prev",
+            "href": add_lang_query(&format!("/opds/v2/next/{}/", page - 1), &lang),
+            "type": OPDS2_TYPE
+        }));
+    }
+    if book_list.len() as i32 >= max_items {
+        links.push(json!({
+            "rel": "next",
+            "href": add_lang_query(&format!("/opds/v2/next/{}/", page + 1), &lang),
+            "type": OPDS2_TYPE
+        }));
+    }
+
+    let mut publications = Vec::with_capacity(book_list.len());
+    for book in &book_list {
+        publications.push(book_publication(state, book, &lang).await);
+    }
+
+    opds2_response(json!({
+        "metadata": {
+            "title": tr(state, &lang, "opds", "root_next", "Next in series"),
+            "modified": DEFAULT_MODIFIED,
+            "numberOfItems": publications.len()
+        },
+        "links": links,
         "publications": publications
     }))
 }
@@ -825,19 +1272,31 @@ pub async fn search_books_default(
     Path((terms,)): Path<(String,)>,
     Query(q): Query<LangQuery>,
 ) -> Response {
-    build_search_books_feed(&state, &headers, q.lang.as_deref(), "m", &terms, 1).await
+    build_search_books_feed(
+        &state,
+        &headers,
+        q.lang.as_deref(),
+        None,
+        &SearchFilter::default(),
+        "m",
+        &terms,
+        1,
+    )
+    .await
 }
 
 pub async fn search_books_feed(
     State(state): State<AppState>,
     headers: HeaderMap,
     Path(params): Path<SearchBooksParams>,
-    Query(q): Query<LangQuery>,
+    Query(q): Query<BooksQuery>,
 ) -> Response {
     build_search_books_feed(
         &state,
         &headers,
         q.lang.as_deref(),
+        q.sort.as_deref(),
+        &q.search_filter(),
         &params.search_type,
         &params.terms,
         params.page.unwrap_or(1).max(1),
@@ -845,86 +1304,121 @@ pub async fn search_books_feed(
     .await
 }
 
+/// Title searches (`b`, `m`, `e`) take `?genre=`, `?language=` and `?format=`
+/// facet filters and a `?scope=`; listings by author, series or genre ID ignore them.
+#[allow(clippy::too_many_arguments)]
 async fn build_search_books_feed(
     state: &AppState,
     headers: &HeaderMap,
     query_lang: Option<&str>,
+    query_sort: Option<&str>,
+    filter: &SearchFilter,
     search_type: &str,
     terms: &str,
     page: i32,
 ) -> Response {
     let lang = detect_opds_lang(headers, &state.config, query_lang);
-    let max_items = state.config.opds.max_items as i32;
+    let max_items = crate::opds::compat::max_items(&state.config.opds) as i32;
     let offset = (page - 1) * max_items;
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
+    let sort_choices: &[BookSort] = if search_type == "s" {
+        &BookSort::SERIES_LISTING
+    } else {
+        &BookSort::LISTING
+    };
+    let sort = BookSort::resolve(sort_choices, query_sort);
+    let title_search = !matches!(search_type, "a" | "s" | "g");
+    let mode = MatchMode::from_search_type(search_type);
+    let no_filter = SearchFilter::default();
+    let filter = if title_search { filter } else { &no_filter };
+    let listing_href = format!(
+        "/opds/v2/search/books/{}/{}/",
+        search_type,
+        urlencoding::encode(terms)
+    );
+    // Keep an explicit ?sort= choice on self, pagination and facet links.
+    let keep_sort = |href: String| match query_sort {
+        Some(_) => add_query_param(&href, "sort", sort.as_str()),
+        None => href,
+    };
+    let page_href = |page: i32| {
+        let href = keep_sort(format!("{listing_href}{page}/"));
+        add_lang_query(&add_search_filter(&href, filter), &lang)
+    };
 
+    let mut fuzzy_results = false;
     let book_list = match search_type {
         "a" => {
             let author_id: i64 = terms.parse().unwrap_or(0);
-            books::get_by_author(&state.db, author_id, max_items, offset, hide_doubles)
+            books::get_by_author(&state.db, author_id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default()
         }
         "s" => {
             let series_id: i64 = terms.parse().unwrap_or(0);
-            books::get_by_series(&state.db, series_id, max_items, offset, hide_doubles)
+            books::get_by_series(&state.db, series_id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default()
         }
         "g" => {
             let genre_id: i64 = terms.parse().unwrap_or(0);
-            books::get_by_genre(&state.db, genre_id, max_items, offset, hide_doubles)
+            books::get_by_genre(&state.db, genre_id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default()
         }
         _ => {
             let search_term = terms.to_uppercase();
-            books::search_by_title(&state.db, &search_term, max_items, offset, hide_doubles)
+            let found = books::search_by_title(
+                &state.db,
+                &search_term,
+                mode,
+                max_items,
+                offset,
+                hide_doubles,
+                sort,
+                state.config.search.translit,
+                filter,
+            )
+            .await
+            .unwrap_or_default();
+            if found.is_empty() && page == 1 && filter.is_empty() && state.config.search.fuzzy {
+                fuzzy_results = true;
+                fuzzy::search_books(
+                    &state.db,
+                    &search_term,
+                    max_items,
+                    hide_doubles,
+                    state.config.search.fuzzy_threshold,
+                    state.pg_trgm_available,
+                )
                 .await
                 .unwrap_or_default()
+            } else {
+                found
+            }
         }
     };
 
-    let mut links = feed_links(
-        add_lang_query(
-            &format!(
-                "/opds/v2/search/books/{}/{}/{}/",
-                search_type,
-                urlencoding::encode(terms),
-                page
-            ),
-            &lang,
-        ),
-        add_lang_query("/opds/v2/", &lang),
-        &lang,
-    );
+    let mut links = feed_links(page_href(page), add_lang_query("/opds/v2/", &lang), &lang);
+    if search_type == "g" {
+        links.push(json!({
+            "rel": "http://opds-spec.org/sort/new",
+            "href": add_lang_query(&format!("/opds/v2/recent/genre/{terms}/"), &lang),
+            "type": OPDS2_TYPE,
+            "title": tr(state, &lang, "opds", "root_by_recent", "Recently Added")
+        }));
+    }
     if page > 1 {
         links.push(json!({
             "rel": "prev",
-            "href": add_lang_query(
-                &format!(
-                    "/opds/v2/search/books/{}/{}/{}/",
-                    search_type,
-                    urlencoding::encode(terms),
-                    page - 1
-                ),
-                &lang
-            ),
+            "href": page_href(page - 1),
             "type": OPDS2_TYPE
         }));
     }
-    if book_list.len() as i32 >= max_items {
+    if !fuzzy_results && book_list.len() as i32 >= max_items {
         links.push(json!({
             "rel": "next",
-            "href": add_lang_query(
-                &format!(
-                    "/opds/v2/search/books/{}/{}/{}/",
-                    search_type,
-                    urlencoding::encode(terms),
-                    page + 1
-                ),
-                &lang
-            ),
+            "href": page_href(page + 1),
             "type": OPDS2_TYPE
         }));
     }
@@ -934,13 +1428,43 @@ async fn build_search_books_feed(
         publications.push(book_publication(state, book, &lang).await);
     }
 
-    opds2_response(json!({
-        "metadata": {
+    let mut body = serde_json::Map::new();
+    body.insert(
+        "metadata".to_string(),
+        json!({
             "title": format!("Search: {terms}"),
             "modified": DEFAULT_MODIFIED,
             "numberOfItems": publications.len()
-        },
-        "links": links,
-        "publications": publications
-    }))
+        }),
+    );
+    body.insert("links".to_string(), Value::Array(links));
+    let mut facets = Vec::new();
+    if (!publications.is_empty() && !fuzzy_results) || page > 1 {
+        let sort_target = add_search_filter(&listing_href, filter);
+        facets.push(sort_facets(state, &lang, &sort_target, sort_choices, sort));
+    }
+    if title_search && ((!publications.is_empty() && !fuzzy_results) || !filter.is_empty()) {
+        let values = books::title_search_facets(
+            &state.db,
+            &terms.to_uppercase(),
+            mode,
+            state.config.search.translit,
+            filter,
+            &lang,
+            books::SEARCH_FACET_VALUES,
+        )
+        .await
+        .unwrap_or_default();
+        let groups = search_facet_groups(state, &lang, &values, filter);
+        facets.extend(search_facets(
+            &lang,
+            &keep_sort(listing_href.clone()),
+            &groups,
+        ));
+    }
+    if !facets.is_empty() {
+        body.insert("facets".to_string(), Value::Array(facets));
+    }
+    body.insert("publications".to_string(), Value::Array(publications));
+    opds2_response(Value::Object(body))
 }
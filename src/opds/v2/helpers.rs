@@ -2,16 +2,22 @@ use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use serde_json::{Value, json};
 
+use crate::config::OpdsConfig;
 use crate::db::models::Book;
-use crate::db::queries::{authors, genres};
+use crate::db::queries::books::{BookSort, SearchFilter};
+use crate::db::queries::{authors, book_audio, books, genres, series};
 use crate::state::AppState;
 
+use super::super::v1::xml::{AUDIOBOOK_TYPE, FEED_ICON_HREF};
+
 pub const OPDS2_JSON: &str = "application/opds+json; charset=utf-8";
 pub const OPDS2_TYPE: &str = "application/opds+json";
 pub const DEFAULT_MODIFIED: &str = "2024-01-01T00:00:00Z";
-pub const REL_ACQUISITION: &str = "http://opds-spec.org/acquisition/open-access";
+pub const REL_ACQUISITION: &str = crate::formats::DEFAULT_REL;
 
-pub fn opds2_response(body: Value) -> Response {
+/// Serialize an OPDS 2.0 document, adjusted for the client's profile.
+pub fn opds2_response(mut body: Value) -> Response {
+    crate::opds::compat::current().apply_json(&mut body);
     match serde_json::to_vec(&body) {
         Ok(bytes) => (StatusCode::OK, [(header::CONTENT_TYPE, OPDS2_JSON)], bytes).into_response(),
         Err(_) => error_response(
@@ -109,6 +115,102 @@ pub fn add_lang_query(href: &str, lang: &str) -> String {
     }
 }
 
+/// Href of a paginated listing page under `base` (e.g. `/opds/authors/`).
+/// An empty prefix addresses the flat listing used when `alphabet_menu` is off.
+pub fn list_href(base: &str, lang_code: i32, prefix: &str, page: i32) -> String {
+    if prefix.is_empty() {
+        format!("{base}{lang_code}/list/{page}/")
+    } else {
+        format!(
+            "{base}{lang_code}/{}/list/{page}/",
+            urlencoding::encode(prefix)
+        )
+    }
+}
+
+/// Append a query parameter, like [`add_lang_query`].
+pub fn add_query_param(href: &str, key: &str, value: &str) -> String {
+    let encoded = urlencoding::encode(value);
+    if href.contains('?') {
+        format!("{href}&{key}={encoded}")
+    } else {
+        format!("{href}?{key}={encoded}")
+    }
+}
+
+/// "Sort by" facet group for a book listing. `target_href` is the first page
+/// of the listing without `sort` and `lang`; the active order gets `rel: self`.
+pub fn sort_facets(
+    state: &AppState,
+    lang: &str,
+    target_href: &str,
+    options: &[BookSort],
+    active: BookSort,
+) -> Value {
+    let links: Vec<Value> = options
+        .iter()
+        .map(|sort| {
+            let value = sort.as_str();
+            let mut link = json!({
+                "title": tr(state, lang, "book", &format!("sort_{value}"), value),
+                "href": add_lang_query(&add_query_param(target_href, "sort", value), lang),
+                "type": OPDS2_TYPE
+            });
+            if *sort == active {
+                link["rel"] = json!("self");
+            }
+            link
+        })
+        .collect();
+    json!({
+        "metadata": { "title": tr(state, lang, "opds", "facet_sort", "Sort by") },
+        "links": links
+    })
+}
+
+/// Append the query parameters selecting `filter`.
+pub fn add_search_filter(href: &str, filter: &SearchFilter) -> String {
+    filter
+        .query_pairs()
+        .iter()
+        .fold(href.to_string(), |href, (key, value)| {
+            add_query_param(&href, key, value)
+        })
+}
+
+/// Facet groups of a title search as OPDS 2.0 facets. `target_href` is the
+/// first page of the search without filters and `lang`.
+pub fn search_facets(
+    lang: &str,
+    target_href: &str,
+    groups: &[crate::opds::v1::helpers::SearchFacetGroup],
+) -> Vec<Value> {
+    groups
+        .iter()
+        .map(|group| {
+            let links: Vec<Value> = group
+                .links
+                .iter()
+                .map(|(label, filter, active)| {
+                    let mut link = json!({
+                        "title": label,
+                        "href": add_lang_query(&add_search_filter(target_href, filter), lang),
+                        "type": OPDS2_TYPE
+                    });
+                    if *active {
+                        link["rel"] = json!("self");
+                    }
+                    link
+                })
+                .collect();
+            json!({
+                "metadata": { "title": group.title },
+                "links": links
+            })
+        })
+        .collect()
+}
+
 pub fn nav_link(title: String, href: String) -> Value {
     json!({
         "title": title,
@@ -117,6 +219,16 @@ pub fn nav_link(title: String, href: String) -> Value {
     })
 }
 
+/// Navigation link that tells how many publications it leads to.
+pub fn nav_link_with_count(title: String, href: String, count: i64) -> Value {
+    json!({
+        "title": title,
+        "href": href,
+        "type": OPDS2_TYPE,
+        "properties": { "numberOfItems": count }
+    })
+}
+
 pub fn feed_links(self_href: String, start_href: String, lang: &str) -> Vec<Value> {
     vec![
         json!({
@@ -138,6 +250,21 @@ pub fn feed_links(self_href: String, start_href: String, lang: &str) -> Vec<Valu
     ]
 }
 
+/// Instance icon and optional logo links for the root feed.
+pub fn branding_links(opds: &OpdsConfig) -> Vec<Value> {
+    let mut links = vec![json!({
+        "rel": "icon",
+        "href": FEED_ICON_HREF,
+        "type": crate::assets::favicon_content_type(opds)
+    })];
+    if let Some(logo) = opds.logo_url.as_deref().map(str::trim)
+        && !logo.is_empty()
+    {
+        links.push(json!({ "rel": "logo", "href": logo }));
+    }
+    links
+}
+
 pub async fn book_publication(state: &AppState, book: &Book, lang: &str) -> Value {
     let mut metadata = serde_json::Map::new();
     metadata.insert("identifier".to_string(), json!(format!("b:{}", book.id)));
@@ -149,8 +276,15 @@ pub async fn book_publication(state: &AppState, book: &Book, lang: &str) -> Valu
     if !book.docdate.is_empty() {
         metadata.insert("published".to_string(), json!(book.docdate));
     }
+    // Books without an annotation are described by their first lines.
     if !book.annotation.is_empty() {
         metadata.insert("description".to_string(), json!(book.annotation));
+    } else if !book.preview_text.is_empty() {
+        metadata.insert("description".to_string(), json!(book.preview_text));
+    }
+    let pages = book.pages();
+    if pages > 0 {
+        metadata.insert("numberOfPages".to_string(), json!(pages));
     }
 
     if let Ok(book_authors) = authors::get_for_book(&state.db, book.id).await
@@ -178,37 +312,137 @@ pub async fn book_publication(state: &AppState, book: &Book, lang: &str) -> Valu
         metadata.insert("subject".to_string(), Value::Array(subjects));
     }
 
+    // Series with the book's position, linked to all books of the series so
+    // readers can go on to the next volume.
+    let mut series_links = Vec::new();
+    if let Ok(book_series) = series::get_for_book(&state.db, book.id).await
+        && !book_series.is_empty()
+    {
+        let mut entries = Vec::new();
+        for (ser, ser_no) in &book_series {
+            let href = add_lang_query(&format!("/opds/v2/search/books/s/{}/", ser.id), lang);
+            let mut entry = json!({
+                "name": ser.ser_name,
+                "links": [{ "href": href, "type": OPDS2_TYPE }]
+            });
+            if *ser_no > 0 {
+                entry["position"] = json!(ser_no);
+            }
+            entries.push(entry);
+            series_links.push(json!({
+                "rel": "related",
+                "href": href,
+                "type": OPDS2_TYPE,
+                "title": format!("All books in {}", ser.ser_name)
+            }));
+        }
+        metadata.insert("belongsTo".to_string(), json!({ "series": entries }));
+    }
+
+    // Audiobooks: schema.org typing, running time, narrators and the tracks
+    // to stream in play order.
+    let mut reading_order = Vec::new();
+    if crate::audio::is_audio_format(&book.format) {
+        metadata.insert("@type".to_string(), json!(AUDIOBOOK_TYPE));
+        if let Ok(Some(audio)) = book_audio::get_for_book(&state.db, book.id).await {
+            if audio.duration > 0 {
+                metadata.insert("duration".to_string(), json!(audio.duration));
+            }
+            if !audio.narrator.is_empty() {
+                let narrators: Vec<Value> = audio
+                    .narrator
+                    .split(", ")
+                    .map(|n| json!({ "name": n }))
+                    .collect();
+                metadata.insert("narrator".to_string(), Value::Array(narrators));
+            }
+        }
+        let mime = super::super::v1::xml::mime_for_format(&book.format);
+        for (i, track) in crate::audio::book_tracks(&state.config.library.root_path, book)
+            .iter()
+            .enumerate()
+        {
+            reading_order.push(json!({
+                "href": format!("/opds/audio/{}/{i}/", book.id),
+                "type": mime,
+                "title": crate::vfs::decode_name(track.file_name().unwrap_or_default())
+            }));
+        }
+    }
+
+    let download_format = super::super::v1::xml::download_format(&book.format);
+    let rel = crate::formats::rel(&book.format);
     let mut links = vec![json!({
-        "rel": REL_ACQUISITION,
+        "rel": rel,
         "href": format!("/opds/download/{}/0/", book.id),
-        "type": super::super::v1::xml::mime_for_format(&book.format)
+        "type": super::super::v1::xml::mime_for_format(download_format)
     })];
 
     if !super::super::v1::xml::is_nozip_format(&book.format) {
-        links.push(json!({
-            "rel": REL_ACQUISITION,
+        let zipped = json!({
+            "rel": rel,
             "href": format!("/opds/download/{}/1/", book.id),
             "type": super::super::v1::xml::mime_for_zip(&book.format)
-        }));
+        });
+        if crate::opds::compat::current().prefer_zip {
+            links.insert(0, zipped);
+        } else {
+            links.push(zipped);
+        }
+    }
+
+    // The other formats of the work, each acquired from its own book.
+    if state.config.opds.group_formats {
+        for (id, format) in books::get_other_formats(&state.db, book.id)
+            .await
+            .unwrap_or_default()
+        {
+            links.push(json!({
+                "rel": crate::formats::rel(&format),
+                "href": format!("/opds/download/{id}/0/"),
+                "type": super::super::v1::xml::mime_for_format(
+                    super::super::v1::xml::download_format(&format)
+                )
+            }));
+        }
     }
 
+    links.extend(series_links);
+
     let mut images = Vec::new();
     if book.cover != 0 {
-        images.push(json!({
-            "href": format!("/opds/cover/{}/", book.id),
+        let urls = crate::opds::covers::CoverUrls::new(&state.config);
+        let mut cover = json!({
+            "href": urls.cover(book.id),
+            "type": crate::opds::covers::cover_mime(book)
+        });
+        let mut thumb = json!({
+            "href": urls.thumb(book.id),
             "type": "image/jpeg"
-        }));
-        images.push(json!({
-            "href": format!("/opds/thumb/{}/", book.id),
-            "type": "image/jpeg",
-            "width": 200,
-            "height": 200
-        }));
+        });
+        if let Some(((width, height), (thumb_width, thumb_height))) =
+            crate::opds::covers::image_sizes(book)
+        {
+            cover["width"] = json!(width);
+            cover["height"] = json!(height);
+            thumb["width"] = json!(thumb_width);
+            thumb["height"] = json!(thumb_height);
+        }
+        // Lets clients paint a matching placeholder before the image loads.
+        if let Some(tint) = crate::covers::palette_colors(&book.cover_palette).first() {
+            cover["properties"] = json!({ "tint": tint });
+            thumb["properties"] = json!({ "tint": tint });
+        }
+        images.push(cover);
+        images.push(thumb);
     }
 
     let mut pub_obj = serde_json::Map::new();
     pub_obj.insert("metadata".to_string(), Value::Object(metadata));
     pub_obj.insert("links".to_string(), Value::Array(links));
+    if !reading_order.is_empty() {
+        pub_obj.insert("readingOrder".to_string(), Value::Array(reading_order));
+    }
     if !images.is_empty() {
         pub_obj.insert("images".to_string(), Value::Array(images));
     }
@@ -4,6 +4,8 @@ pub mod helpers;
 use axum::Router;
 use axum::routing::get;
 
+use crate::db::queries::books::SearchFilter;
+use crate::db::queries::bookshelf;
 use crate::state::AppState;
 
 /// Build OPDS 2.0 (JSON) routes.
@@ -29,6 +31,10 @@ pub fn router() -> Router<AppState> {
             "/v2/authors/{lang_code}/{prefix}/list/{page}/",
             get(feeds::authors_list),
         )
+        .route(
+            "/v2/authors/{lang_code}/list/{page}/",
+            get(feeds::authors_list),
+        )
         .route("/v2/series/", get(feeds::series_root))
         .route("/v2/series/{lang_code}/", get(feeds::series_feed))
         .route("/v2/series/{lang_code}/{prefix}/", get(feeds::series_feed))
@@ -40,14 +46,34 @@ pub fn router() -> Router<AppState> {
             "/v2/series/{lang_code}/{prefix}/list/{page}/",
             get(feeds::series_list),
         )
+        .route(
+            "/v2/series/{lang_code}/list/{page}/",
+            get(feeds::series_list),
+        )
         .route("/v2/genres/", get(feeds::genres_root))
         .route("/v2/genres/{section}/", get(feeds::genres_by_section))
         .route("/v2/facets/languages", get(feeds::language_facets_feed))
         .route("/v2/facets/languages/", get(feeds::language_facets_feed))
         .route("/v2/recent/", get(feeds::recent_root))
         .route("/v2/recent/{page}/", get(feeds::recent_feed))
+        .route(
+            "/v2/recent/genre/{genre_id}/",
+            get(feeds::recent_genre_root),
+        )
+        .route(
+            "/v2/recent/genre/{genre_id}/{page}/",
+            get(feeds::recent_genre_feed),
+        )
+        .route("/v2/updated/", get(feeds::updated_root))
+        .route("/v2/updated/{page}/", get(feeds::updated_feed))
+        .route("/v2/popular/", get(feeds::popular_root))
+        .route("/v2/popular/{page}/", get(feeds::popular_feed))
         .route("/v2/bookshelf/", get(feeds::bookshelf_root))
         .route("/v2/bookshelf/{page}/", get(feeds::bookshelf_feed))
+        .route("/v2/recommended/", get(feeds::recommended_root))
+        .route("/v2/recommended/{page}/", get(feeds::recommended_feed))
+        .route("/v2/next/", get(feeds::next_in_series_root))
+        .route("/v2/next/{page}/", get(feeds::next_in_series_feed))
         .route("/v2/search/{terms}/", get(feeds::search_books_default))
         .route(
             "/v2/search/books/{search_type}/{terms}/",
@@ -64,6 +90,74 @@ pub struct LangQuery {
     pub lang: Option<String>,
 }
 
+#[derive(serde::Deserialize, Default)]
+pub struct CatalogsQuery {
+    pub lang: Option<String>,
+    /// `1` lists books from the whole subtree, `0` only from this catalog.
+    pub deep: Option<String>,
+    pub sort: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct BooksQuery {
+    pub lang: Option<String>,
+    /// Book order (`title`, `recent`, `series`, `size`, `year`, `rating`).
+    pub sort: Option<String>,
+    /// Title search facets: genre ID, book language and format.
+    pub genre: Option<String>,
+    pub language: Option<String>,
+    pub format: Option<String>,
+    /// Limit a title search to a catalog subtree, author or series
+    /// (`c:<id>`, `a:<id>`, `s:<id>`).
+    pub scope: Option<String>,
+}
+
+impl BooksQuery {
+    pub fn search_filter(&self) -> SearchFilter {
+        SearchFilter::parse(
+            self.genre.as_deref(),
+            self.language.as_deref(),
+            self.format.as_deref(),
+        )
+        .scoped(self.scope.as_deref())
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct BookshelfQuery {
+    pub lang: Option<String>,
+    /// Shelf order (`date`, `title`, `author`).
+    pub sort: Option<String>,
+    /// `asc` or `desc`; defaults to newest first for dates and A to Z otherwise.
+    pub dir: Option<String>,
+    /// Reading-progress filter (`unread`, `reading`, `finished`).
+    pub state: Option<String>,
+}
+
+impl BookshelfQuery {
+    /// Sort column and direction.
+    pub fn order(&self) -> (bookshelf::SortColumn, bool) {
+        let sort = bookshelf::SortColumn::parse(self.sort.as_deref().unwrap_or_default());
+        let ascending = match self.dir.as_deref() {
+            Some("asc") => true,
+            Some("desc") => false,
+            _ => sort.default_ascending(),
+        };
+        (sort, ascending)
+    }
+
+    pub fn read_state(&self) -> bookshelf::ReadState {
+        bookshelf::ReadState::parse(self.state.as_deref().unwrap_or_default())
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+pub struct PopularQuery {
+    pub lang: Option<String>,
+    /// Download window in days (`30` or `90`).
+    pub period: Option<String>,
+}
+
 #[derive(serde::Deserialize)]
 pub struct CatalogsParams {
     pub cat_id: i64,
@@ -79,6 +173,8 @@ pub struct AuthorsParams {
 #[derive(serde::Deserialize)]
 pub struct AuthorsListParams {
     pub lang_code: i32,
+    /// Empty for the flat listings served when `alphabet_menu` is off.
+    #[serde(default)]
     pub prefix: String,
     pub page: Option<i32>,
 }
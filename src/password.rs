@@ -1,29 +1,201 @@
-use argon2::password_hash::SaltString;
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use std::sync::OnceLock;
+
+use argon2::password_hash::{Output, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use base64::Engine;
+use hmac::{Hmac, KeyInit, Mac};
 use rand::RngExt;
+use sha2::Sha256;
+
+use crate::config::{CharClass, PasswordConfig};
+use crate::db::DbPool;
+
+/// Prefix of PBKDF2-SHA256 hashes in the Django format, as stored by
+/// SimpleOPDS (`pbkdf2_sha256$<iterations>$<salt>$<base64 hash>`).
+const PBKDF2_SHA256_PREFIX: &str = "pbkdf2_sha256$";
+
+static PARAMS: OnceLock<Params> = OnceLock::new();
+
+/// Argon2 cost parameters of a `[password]` section.
+pub fn params(cfg: &PasswordConfig) -> Result<Params, argon2::Error> {
+    Params::new(cfg.memory_kib, cfg.iterations, cfg.parallelism, None)
+}
+
+/// Install the `[password]` settings for this process. Must be called before
+/// the first hash; later calls are ignored.
+pub fn configure(cfg: &PasswordConfig) {
+    match params(cfg) {
+        Ok(params) => {
+            if PARAMS.set(params).is_err() {
+                tracing::debug!(
+                    "[password] settings already initialized; ignoring reconfiguration"
+                );
+            }
+        }
+        Err(e) => tracing::warn!("Invalid [password] settings, using Argon2 defaults: {e}"),
+    }
+}
+
+/// Parameters new hashes are made with (Argon2 defaults when `configure`
+/// was never called).
+fn current_params() -> &'static Params {
+    PARAMS.get_or_init(Params::default)
+}
 
 /// Hash a plaintext password using Argon2id with a random salt.
 pub fn hash(password: &str) -> String {
     let salt = SaltString::generate(&mut argon2::password_hash::rand_core::OsRng);
-    let argon2 = Argon2::default();
+    let argon2 = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        current_params().clone(),
+    );
     argon2
         .hash_password(password.as_bytes(), &salt)
         .expect("Argon2 hashing should not fail")
         .to_string()
 }
 
-/// Generate a 16-character cryptographically random alphanumeric password
-/// for use as an OAuth user's OPDS Basic Auth credential.
-pub fn generate_opds_password() -> String {
-    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+/// Generate a cryptographically random password for an OAuth user's OPDS
+/// Basic Auth credential: 16 characters or `min_length`, whichever is more,
+/// with a character of every class the policy requires.
+pub fn generate_opds_password(cfg: &PasswordConfig) -> String {
+    const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+    const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const DIGITS: &[u8] = b"0123456789";
+    // Symbols that are safe in URLs and `user:password` Basic Auth pairs.
+    const SYMBOLS: &[u8] = b"!#%*+-.=?@_~";
+
+    let class_chars = |class: &CharClass| match class {
+        CharClass::Lower => LOWER,
+        CharClass::Upper => UPPER,
+        CharClass::Digit => DIGITS,
+        CharClass::Symbol => SYMBOLS,
+    };
+    let mut charset = [LOWER, UPPER, DIGITS].concat();
+    if cfg.required_classes.contains(&CharClass::Symbol) {
+        charset.extend_from_slice(SYMBOLS);
+    }
+    let len = cfg
+        .min_length
+        .max(16)
+        .min(cfg.max_length)
+        .max(cfg.required_classes.len());
+
     let mut rng = rand::rng();
-    (0..16)
-        .map(|_| CHARSET[rng.random_range(0..CHARSET.len())] as char)
-        .collect()
+    let mut password: Vec<u8> = cfg
+        .required_classes
+        .iter()
+        .map(|class| {
+            let set = class_chars(class);
+            set[rng.random_range(0..set.len())]
+        })
+        .collect();
+    while password.len() < len {
+        password.push(charset[rng.random_range(0..charset.len())]);
+    }
+    // Move the required characters away from the front.
+    for i in (1..password.len()).rev() {
+        password.swap(i, rng.random_range(0..=i));
+    }
+    password.into_iter().map(char::from).collect()
 }
 
-/// Verify a plaintext password against a stored hash.
+/// Frequently used passwords refused when `reject_common` is set, lowercase.
+const COMMON_PASSWORDS: &[&str] = &[
+    "123456789",
+    "12345678",
+    "1234567890",
+    "password",
+    "password1",
+    "password123",
+    "qwerty123",
+    "qwertyuiop",
+    "11111111",
+    "00000000",
+    "87654321",
+    "123123123",
+    "iloveyou",
+    "sunshine",
+    "princess",
+    "football",
+    "baseball",
+    "superman",
+    "trustno1",
+    "welcome1",
+    "letmein1",
+    "abc12345",
+    "passw0rd",
+    "p@ssw0rd",
+    "qwerty12",
+    "1q2w3e4r",
+    "1qaz2wsx",
+    "zaq12wsx",
+    "asdfghjkl",
+    "changeme",
+    "admin123",
+    "administrator",
+    "starwars",
+    "whatever",
+    "computer",
+    "michelle",
+    "jennifer",
+    "1q2w3e4r5t",
+    "q1w2e3r4",
+    "qazwsxedc",
+    "123qweasd",
+    "master123",
+];
+
+/// Why a new password was refused by the `[password]` policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyViolation {
+    Length,
+    MissingClass,
+    Common,
+}
+
+impl PolicyViolation {
+    /// Error code of the violation in web redirects.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Length => "password_short",
+            Self::MissingClass => "password_weak",
+            Self::Common => "password_common",
+        }
+    }
+}
+
+/// Check a new password against the length, character class and common
+/// password rules of a `[password]` section.
+pub fn check_policy(cfg: &PasswordConfig, password: &str) -> Result<(), PolicyViolation> {
+    let len = password.chars().count();
+    if !(cfg.min_length..=cfg.max_length).contains(&len) {
+        return Err(PolicyViolation::Length);
+    }
+    let has = |class: &CharClass| {
+        password.chars().any(|c| match class {
+            CharClass::Lower => c.is_lowercase(),
+            CharClass::Upper => c.is_uppercase(),
+            CharClass::Digit => c.is_numeric(),
+            CharClass::Symbol => !c.is_alphanumeric(),
+        })
+    };
+    if !cfg.required_classes.iter().all(has) {
+        return Err(PolicyViolation::MissingClass);
+    }
+    if cfg.reject_common && COMMON_PASSWORDS.contains(&password.to_lowercase().as_str()) {
+        return Err(PolicyViolation::Common);
+    }
+    Ok(())
+}
+
+/// Verify a plaintext password against a stored hash: any Argon2 hash (its
+/// own parameters apply) or a legacy PBKDF2-SHA256 hash.
 pub fn verify(password: &str, stored_hash: &str) -> bool {
+    if let Some(legacy) = stored_hash.strip_prefix(PBKDF2_SHA256_PREFIX) {
+        return verify_pbkdf2_sha256(password, legacy);
+    }
     let Ok(parsed) = PasswordHash::new(stored_hash) else {
         return false;
     };
@@ -32,6 +204,83 @@ pub fn verify(password: &str, stored_hash: &str) -> bool {
         .is_ok()
 }
 
+/// Whether a stored hash should be replaced: it is not Argon2id, or was made
+/// with other parameters than the configured ones.
+pub fn needs_rehash(stored_hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(stored_hash) else {
+        return true;
+    };
+    if parsed.algorithm != Algorithm::Argon2id.ident()
+        || parsed.version != Some(Version::V0x13 as u32)
+    {
+        return true;
+    }
+    let Ok(params) = Params::try_from(&parsed) else {
+        return true;
+    };
+    let current = current_params();
+    params.m_cost() != current.m_cost()
+        || params.t_cost() != current.t_cost()
+        || params.p_cost() != current.p_cost()
+}
+
+/// After a successful login with `password`, replace an outdated hash of
+/// `user_id` with a current Argon2id one. Failures are logged; the login
+/// itself is not affected.
+pub async fn rehash_if_needed(pool: &DbPool, user_id: i64, password: &str, stored_hash: &str) {
+    if !needs_rehash(stored_hash) {
+        return;
+    }
+    match crate::db::queries::users::update_password(pool, user_id, &hash(password)).await {
+        Ok(()) => tracing::info!("Upgraded password hash of user {user_id}"),
+        Err(e) => tracing::warn!("Failed to upgrade password hash of user {user_id}: {e}"),
+    }
+}
+
+/// Check a Django-style `<iterations>$<salt>$<base64 hash>` PBKDF2-SHA256
+/// hash. The derived key is compared in constant time.
+fn verify_pbkdf2_sha256(password: &str, legacy: &str) -> bool {
+    let mut parts = legacy.splitn(3, '$');
+    let (Some(iterations), Some(salt), Some(expected)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let Ok(iterations) = iterations.parse::<u32>() else {
+        return false;
+    };
+    let Ok(expected) = base64::engine::general_purpose::STANDARD.decode(expected) else {
+        return false;
+    };
+    let (Ok(expected), Ok(derived)) = (
+        Output::new(&expected),
+        Output::new(&pbkdf2_sha256(
+            password.as_bytes(),
+            salt.as_bytes(),
+            iterations,
+        )),
+    ) else {
+        return false;
+    };
+    iterations > 0 && derived == expected
+}
+
+/// PBKDF2-HMAC-SHA256 with a 32-byte derived key (a single block).
+fn pbkdf2_sha256(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let prf = Hmac::<Sha256>::new_from_slice(password).expect("HMAC can take key of any size");
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut block: [u8; 32] = mac.finalize().into_bytes().into();
+    let mut key = block;
+    for _ in 1..iterations {
+        let mut mac = prf.clone();
+        mac.update(&block);
+        block = mac.finalize().into_bytes().into();
+        key.iter_mut().zip(block).for_each(|(k, b)| *k ^= b);
+    }
+    key
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,6 +293,36 @@ mod tests {
         assert!(verify(pw, &hashed));
     }
 
+    #[test]
+    fn test_check_policy() {
+        let mut cfg = PasswordConfig::default();
+        assert_eq!(check_policy(&cfg, "1234567"), Err(PolicyViolation::Length));
+        assert_eq!(check_policy(&cfg, "password123"), Ok(()));
+        assert_eq!(
+            check_policy(&cfg, &"x".repeat(33)),
+            Err(PolicyViolation::Length)
+        );
+
+        cfg.min_length = 10;
+        cfg.required_classes = vec![CharClass::Upper, CharClass::Digit, CharClass::Symbol];
+        cfg.reject_common = true;
+        assert_eq!(
+            check_policy(&cfg, "Passw0rd!"),
+            Err(PolicyViolation::Length)
+        );
+        assert_eq!(
+            check_policy(&cfg, "Password123"),
+            Err(PolicyViolation::MissingClass)
+        );
+        assert_eq!(check_policy(&cfg, "Пароль-1234"), Ok(()));
+
+        cfg.required_classes.clear();
+        assert_eq!(
+            check_policy(&cfg, "PassWord123"),
+            Err(PolicyViolation::Common)
+        );
+    }
+
     #[test]
     fn test_wrong_password() {
         let hashed = hash("real-password");
@@ -65,17 +344,87 @@ mod tests {
         assert!(verify("same", &h2));
     }
 
+    #[test]
+    fn test_legacy_pbkdf2_sha256_hash() {
+        let legacy = "pbkdf2_sha256$1000$Qx7bN2kLpZ4a$ADEvf+Iq23VhDwUZwc3P8GzPxlye0lQhdWrA9wlH7q8=";
+        assert!(verify("sopds-secret", legacy));
+        assert!(!verify("sopds-secreT", legacy));
+        assert!(!verify("sopds-secret", "pbkdf2_sha256$0$Qx7bN2kLpZ4a$AAAA"));
+        assert!(!verify("sopds-secret", "pbkdf2_sha256$1000$Qx7bN2kLpZ4a"));
+        assert!(needs_rehash(legacy));
+    }
+
+    #[test]
+    fn test_needs_rehash_for_other_argon2_params() {
+        assert!(!needs_rehash(&hash("pw")));
+        assert!(needs_rehash("not-a-valid-hash"));
+
+        let salt = SaltString::generate(&mut argon2::password_hash::rand_core::OsRng);
+        let weak = Params::new(Params::MIN_M_COST, 1, 1, None).unwrap();
+        let old = Argon2::new(Algorithm::Argon2id, Version::V0x13, weak)
+            .hash_password(b"pw", &salt)
+            .unwrap()
+            .to_string();
+        assert!(verify("pw", &old));
+        assert!(needs_rehash(&old));
+
+        let argon2i = Argon2::new(Algorithm::Argon2i, Version::V0x13, Params::default())
+            .hash_password(b"pw", &salt)
+            .unwrap()
+            .to_string();
+        assert!(verify("pw", &argon2i));
+        assert!(needs_rehash(&argon2i));
+    }
+
+    #[tokio::test]
+    async fn test_rehash_if_needed_upgrades_legacy_hash() {
+        let pool = crate::db::create_test_pool().await;
+        let legacy = "pbkdf2_sha256$1000$Qx7bN2kLpZ4a$ADEvf+Iq23VhDwUZwc3P8GzPxlye0lQhdWrA9wlH7q8=";
+        let id = crate::db::queries::users::create(&pool, "olduser", legacy, 0, "")
+            .await
+            .unwrap();
+
+        rehash_if_needed(&pool, id, "sopds-secret", legacy).await;
+        let user = crate::db::queries::users::get_by_id(&pool, id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(user.password_hash.starts_with("$argon2id$"));
+        assert!(verify("sopds-secret", &user.password_hash));
+        assert!(!needs_rehash(&user.password_hash));
+    }
+
     #[test]
     fn test_generate_opds_password_length_and_charset() {
-        let pw = generate_opds_password();
+        let pw = generate_opds_password(&PasswordConfig::default());
         assert_eq!(pw.len(), 16);
         assert!(pw.chars().all(|c| c.is_alphanumeric()));
     }
 
+    #[test]
+    fn test_generate_opds_password_meets_policy() {
+        let cfg = PasswordConfig {
+            min_length: 24,
+            required_classes: vec![
+                CharClass::Lower,
+                CharClass::Upper,
+                CharClass::Digit,
+                CharClass::Symbol,
+            ],
+            reject_common: true,
+            ..PasswordConfig::default()
+        };
+        for _ in 0..50 {
+            let pw = generate_opds_password(&cfg);
+            assert_eq!(pw.len(), 24);
+            assert_eq!(check_policy(&cfg, &pw), Ok(()));
+        }
+    }
+
     #[test]
     fn test_generate_opds_password_unique() {
-        let a = generate_opds_password();
-        let b = generate_opds_password();
+        let a = generate_opds_password(&PasswordConfig::default());
+        let b = generate_opds_password(&PasswordConfig::default());
         assert_ne!(a, b, "passwords should be random");
     }
 }
@@ -1,45 +1,85 @@
 use std::path::Path;
-use std::process::{Command, Stdio};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::config::CoverImageConfig;
+use crate::tools::{self, ToolError};
+
+/// Embedded images smaller than this (short side, px) are skipped by the
+/// built-in cover extractor: they are logos or decorations, not page scans.
+const NATIVE_MIN_COVER_SIDE: u32 = 100;
+/// Page tree levels searched for inherited resources; guards against
+/// `/Parent` cycles in broken files.
+const MAX_PAGE_TREE_DEPTH: usize = 32;
 
 pub fn pdftoppm_available() -> bool {
-    Command::new("pdftoppm")
-        .arg("-h")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .is_ok()
+    tools::is_available(&tools::settings().pdftoppm_path)
 }
 
 pub fn pdfinfo_available() -> bool {
-    Command::new("pdfinfo")
-        .arg("-h")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .is_ok()
+    tools::is_available(&tools::settings().pdfinfo_path)
+}
+
+pub fn pdftotext_available() -> bool {
+    tools::is_available(&tools::settings().pdftotext_path)
 }
 
-#[derive(Debug, Clone, Default)]
+/// Poppler tools found by the startup probe. The built-in reader stands in
+/// for `pdftoppm` and `pdfinfo`; PDFs have no text without `pdftotext`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PdfTools {
+    pub pdftoppm: bool,
+    pub pdfinfo: bool,
+    pub pdftotext: bool,
+}
+
+static TOOLS: OnceLock<PdfTools> = OnceLock::new();
+
+/// Install the startup probe results for this process. Must be called before
+/// the first PDF is read; later calls are ignored.
+pub fn configure(available: PdfTools) {
+    if TOOLS.set(available).is_err() {
+        tracing::debug!("PDF tools already initialized; ignoring reconfiguration");
+    }
+}
+
+/// Tools installed by [`configure`] (none when it was never called).
+pub fn available_tools() -> PdfTools {
+    TOOLS.get().copied().unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct PdfMetadata {
     pub title: Option<String>,
     pub author: Option<String>,
+    pub pages: Option<u32>,
 }
 
 pub fn render_first_page_jpeg_from_path(
     path: &Path,
     cover_cfg: CoverImageConfig,
 ) -> Result<Vec<u8>, PdfRenderError> {
+    if !available_tools().pdftoppm {
+        let doc = lopdf::Document::load(path).map_err(PdfRenderError::from_lopdf)?;
+        return find_embedded_jpeg(&doc).ok_or(PdfRenderError::NoEmbeddedImage);
+    }
     let pdf_data = std::fs::read(path).map_err(PdfRenderError::ReadInput)?;
     render_first_page_jpeg_from_bytes(&pdf_data, cover_cfg)
 }
 
+/// Render the first page as JPEG with `pdftoppm`. Without `pdftoppm` the
+/// largest JPEG image of the first page (typical for scanned books) is
+/// returned instead.
 pub fn render_first_page_jpeg_from_bytes(
     pdf_data: &[u8],
     cover_cfg: CoverImageConfig,
 ) -> Result<Vec<u8>, PdfRenderError> {
+    if !available_tools().pdftoppm {
+        let doc = lopdf::Document::load_mem(pdf_data).map_err(PdfRenderError::from_lopdf)?;
+        return find_embedded_jpeg(&doc).ok_or(PdfRenderError::NoEmbeddedImage);
+    }
+
     let temp_dir = temp_work_dir();
     std::fs::create_dir_all(&temp_dir).map_err(PdfRenderError::CreateTempDir)?;
     let _cleanup = TempDirCleanup(temp_dir.clone());
@@ -51,32 +91,36 @@ pub fn render_first_page_jpeg_from_bytes(
     std::fs::write(&input_pdf, pdf_data).map_err(PdfRenderError::WriteInput)?;
 
     let jpegopt = format!("quality={}", cover_cfg.jpeg_quality());
-    let status = Command::new("pdftoppm")
-        .arg("-f")
-        .arg("1")
-        .arg("-singlefile")
-        .arg("-jpeg")
-        .arg("-jpegopt")
-        .arg(jpegopt)
-        .arg("-scale-to")
-        .arg(cover_cfg.scale_to().to_string())
-        .arg(&input_pdf)
-        .arg(&output_base)
-        .status()
-        .map_err(PdfRenderError::Spawn)?;
-
-    if !status.success() {
-        return Err(PdfRenderError::ExitStatus(status.code()));
+    let output = tools::run(
+        Command::new(&tools::settings().pdftoppm_path)
+            .arg("-f")
+            .arg("1")
+            .arg("-singlefile")
+            .arg("-jpeg")
+            .arg("-jpegopt")
+            .arg(jpegopt)
+            .arg("-scale-to")
+            .arg(cover_cfg.scale_to().to_string())
+            .arg(&input_pdf)
+            .arg(&output_base),
+    )?;
+
+    if !output.status.success() {
+        return Err(PdfRenderError::ExitStatus(output.status.code()));
     }
 
     std::fs::read(&output_jpg).map_err(PdfRenderError::ReadOutput)
 }
 
+/// Read title/author with `pdfinfo`, or with the built-in parser when `pdfinfo` is missing.
 pub fn extract_metadata_from_path(path: &Path) -> Result<PdfMetadata, PdfInfoError> {
-    let output = Command::new("pdfinfo")
-        .arg(path)
-        .output()
-        .map_err(PdfInfoError::Spawn)?;
+    if !available_tools().pdfinfo {
+        return lopdf::Document::load_metadata(path)
+            .map(native_metadata)
+            .map_err(PdfInfoError::from_lopdf);
+    }
+
+    let output = tools::run(Command::new(&tools::settings().pdfinfo_path).arg(path))?;
 
     if !output.status.success() {
         return Err(PdfInfoError::ExitStatus(output.status.code()));
@@ -87,6 +131,12 @@ pub fn extract_metadata_from_path(path: &Path) -> Result<PdfMetadata, PdfInfoErr
 }
 
 pub fn extract_metadata_from_bytes(pdf_data: &[u8]) -> Result<PdfMetadata, PdfInfoError> {
+    if !available_tools().pdfinfo {
+        return lopdf::Document::load_metadata_mem(pdf_data)
+            .map(native_metadata)
+            .map_err(PdfInfoError::from_lopdf);
+    }
+
     let temp_dir = temp_work_dir();
     std::fs::create_dir_all(&temp_dir).map_err(PdfInfoError::CreateTempDir)?;
     let _cleanup = TempDirCleanup(temp_dir.clone());
@@ -97,6 +147,71 @@ pub fn extract_metadata_from_bytes(pdf_data: &[u8]) -> Result<PdfMetadata, PdfIn
     extract_metadata_from_path(&input_pdf)
 }
 
+/// Read the text layer of the first `max_pages` pages with `pdftotext`,
+/// killed after `tools.pdf_text_timeout_secs`. Scanned PDFs without a text
+/// layer give an empty string.
+pub fn extract_text_from_path(path: &Path, max_pages: u32) -> Result<String, PdfTextError> {
+    if !available_tools().pdftotext {
+        return Err(PdfTextError::Unavailable);
+    }
+
+    let settings = tools::settings();
+    let output = tools::run_with_timeout(
+        Command::new(&settings.pdftotext_path)
+            .arg("-q")
+            .arg("-enc")
+            .arg("UTF-8")
+            .arg("-f")
+            .arg("1")
+            .arg("-l")
+            .arg(max_pages.to_string())
+            .arg(path)
+            .arg("-"),
+        Duration::from_secs(settings.pdf_text_timeout_secs),
+    )?;
+
+    if !output.status.success() {
+        return Err(PdfTextError::ExitStatus(output.status.code()));
+    }
+
+    Ok(clean_pdftotext_stdout(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+pub fn extract_text_from_bytes(pdf_data: &[u8], max_pages: u32) -> Result<String, PdfTextError> {
+    if !available_tools().pdftotext {
+        return Err(PdfTextError::Unavailable);
+    }
+
+    let temp_dir = temp_work_dir();
+    std::fs::create_dir_all(&temp_dir).map_err(PdfTextError::CreateTempDir)?;
+    let _cleanup = TempDirCleanup(temp_dir.clone());
+
+    let input_pdf = temp_dir.join("input.pdf");
+    std::fs::write(&input_pdf, pdf_data).map_err(PdfTextError::WriteInput)?;
+
+    extract_text_from_path(&input_pdf, max_pages)
+}
+
+/// Turn page breaks (form feeds) into paragraph breaks and join words
+/// hyphenated across lines.
+fn clean_pdftotext_stdout(stdout: &str) -> String {
+    let text = stdout.replace('\u{c}', "\n\n").replace("\r\n", "\n");
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(at) = rest.find("-\n") {
+        let (before, after) = (&rest[..at], &rest[at + 2..]);
+        out.push_str(before);
+        if !(before.ends_with(char::is_alphabetic) && after.starts_with(char::is_lowercase)) {
+            out.push_str("-\n");
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
 fn parse_pdfinfo_stdout(stdout: &str) -> PdfMetadata {
     let mut meta = PdfMetadata::default();
 
@@ -107,6 +222,7 @@ fn parse_pdfinfo_stdout(stdout: &str) -> PdfMetadata {
             match key.as_str() {
                 "title" => meta.title = value,
                 "author" => meta.author = value,
+                "pages" => meta.pages = value.and_then(|v| v.parse().ok()),
                 _ => {}
             }
         }
@@ -124,6 +240,57 @@ fn normalize_pdfinfo_value(value: &str) -> Option<String> {
     }
 }
 
+/// Title, author and page count read by `lopdf` from the Info dictionary
+/// and the page tree, without loading page contents.
+fn native_metadata(info: lopdf::PdfMetadata) -> PdfMetadata {
+    PdfMetadata {
+        title: info.title.as_deref().and_then(normalize_pdfinfo_value),
+        author: info.author.as_deref().and_then(normalize_pdfinfo_value),
+        pages: Some(info.page_count).filter(|&n| n > 0),
+    }
+}
+
+/// The largest JPEG (DCT) image among the resources of the first page that
+/// is large enough to be a cover.
+fn find_embedded_jpeg(doc: &lopdf::Document) -> Option<Vec<u8>> {
+    let first_page = *doc.get_pages().values().next()?;
+    // Resources of the page itself and those inherited from the page tree.
+    let mut resources = Vec::new();
+    let mut node = doc.get_dictionary(first_page).ok();
+    for _ in 0..MAX_PAGE_TREE_DEPTH {
+        let Some(dict) = node else { break };
+        resources.extend(doc.get_dict_in_dict(dict, b"Resources").ok());
+        node = doc.get_dict_in_dict(dict, b"Parent").ok();
+    }
+    resources
+        .into_iter()
+        .filter_map(|res| doc.get_dict_in_dict(res, b"XObject").ok())
+        .flat_map(|xobjects| xobjects.iter())
+        .filter_map(|(_, obj)| doc.dereference(obj).ok()?.1.as_stream().ok())
+        .filter(|stream| {
+            stream
+                .dict
+                .get(b"Subtype")
+                .and_then(lopdf::Object::as_name)
+                .is_ok_and(|name| name == b"Image")
+                // JPEG data as stored, without another filter on top.
+                && stream
+                    .filters()
+                    .is_ok_and(|filters| filters == [b"DCTDecode".as_slice()])
+        })
+        .filter_map(|stream| {
+            let (w, h) = image::ImageReader::with_format(
+                std::io::Cursor::new(&stream.content),
+                image::ImageFormat::Jpeg,
+            )
+            .into_dimensions()
+            .ok()?;
+            (w.min(h) >= NATIVE_MIN_COVER_SIDE).then_some((u64::from(w) * u64::from(h), stream))
+        })
+        .max_by_key(|(area, _)| *area)
+        .map(|(_, stream)| stream.content.clone())
+}
+
 fn temp_work_dir() -> std::path::PathBuf {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -152,27 +319,120 @@ pub enum PdfRenderError {
     WriteInput(std::io::Error),
     #[error("failed to start pdftoppm: {0}")]
     Spawn(std::io::Error),
+    #[error("pdftoppm timed out after {}s", .0.as_secs())]
+    TimedOut(Duration),
     #[error("pdftoppm exited with status {0:?}")]
     ExitStatus(Option<i32>),
     #[error("failed to read rendered JPEG: {0}")]
     ReadOutput(std::io::Error),
+    #[error("pdftoppm is unavailable and no embedded JPEG cover was found")]
+    NoEmbeddedImage,
+    #[error("failed to parse PDF: {0}")]
+    Parse(lopdf::Error),
+}
+
+impl PdfRenderError {
+    fn from_lopdf(e: lopdf::Error) -> Self {
+        match e {
+            lopdf::Error::IO(e) => Self::ReadInput(e),
+            e => Self::Parse(e),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum PdfInfoError {
+    #[error("failed to read input PDF: {0}")]
+    ReadInput(std::io::Error),
+    #[error("failed to parse PDF: {0}")]
+    Parse(lopdf::Error),
     #[error("failed to create temp dir: {0}")]
     CreateTempDir(std::io::Error),
     #[error("failed to write temp input PDF: {0}")]
     WriteInput(std::io::Error),
     #[error("failed to start pdfinfo: {0}")]
     Spawn(std::io::Error),
+    #[error("pdfinfo timed out after {}s", .0.as_secs())]
+    TimedOut(Duration),
     #[error("pdfinfo exited with status {0:?}")]
     ExitStatus(Option<i32>),
 }
 
+impl PdfInfoError {
+    fn from_lopdf(e: lopdf::Error) -> Self {
+        match e {
+            lopdf::Error::IO(e) => Self::ReadInput(e),
+            e => Self::Parse(e),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PdfTextError {
+    #[error("pdftotext is unavailable")]
+    Unavailable,
+    #[error("failed to create temp dir: {0}")]
+    CreateTempDir(std::io::Error),
+    #[error("failed to write temp input PDF: {0}")]
+    WriteInput(std::io::Error),
+    #[error("failed to start pdftotext: {0}")]
+    Spawn(std::io::Error),
+    #[error("pdftotext timed out after {}s", .0.as_secs())]
+    TimedOut(Duration),
+    #[error("pdftotext exited with status {0:?}")]
+    ExitStatus(Option<i32>),
+}
+
+impl From<ToolError> for PdfRenderError {
+    fn from(e: ToolError) -> Self {
+        match e {
+            ToolError::Spawn(e) | ToolError::Wait(e) => Self::Spawn(e),
+            ToolError::TimedOut(limit) => Self::TimedOut(limit),
+        }
+    }
+}
+
+impl From<ToolError> for PdfInfoError {
+    fn from(e: ToolError) -> Self {
+        match e {
+            ToolError::Spawn(e) | ToolError::Wait(e) => Self::Spawn(e),
+            ToolError::TimedOut(limit) => Self::TimedOut(limit),
+        }
+    }
+}
+
+impl From<ToolError> for PdfTextError {
+    fn from(e: ToolError) -> Self {
+        match e {
+            ToolError::Spawn(e) | ToolError::Wait(e) => Self::Spawn(e),
+            ToolError::TimedOut(limit) => Self::TimedOut(limit),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lopdf::dictionary;
+
+    #[test]
+    fn test_clean_pdftotext_stdout() {
+        let out =
+            "Chapter 1\n\nA sen-\ntence on two lines.\nNon-\nLatin stays.\u{c}Page two\n\u{c}";
+        assert_eq!(
+            clean_pdftotext_stdout(out),
+            "Chapter 1\n\nA sentence on two lines.\nNon-\nLatin stays.\n\nPage two\n\n\n"
+        );
+    }
+
+    #[test]
+    fn test_extract_text_from_invalid_bytes_errors() {
+        let err = extract_text_from_bytes(b"not a pdf", 10).unwrap_err();
+        assert!(matches!(
+            err,
+            PdfTextError::Unavailable | PdfTextError::Spawn(_) | PdfTextError::ExitStatus(_)
+        ));
+    }
 
     #[test]
     fn test_parse_pdfinfo_stdout_and_normalize_values() {
@@ -180,10 +440,12 @@ mod tests {
 Title:   The Book
 Author:  Jane Doe
 Producer: ignored
+Pages:          312
 "#;
         let meta = parse_pdfinfo_stdout(out);
         assert_eq!(meta.title, Some("The Book".to_string()));
         assert_eq!(meta.author, Some("Jane Doe".to_string()));
+        assert_eq!(meta.pages, Some(312));
 
         let out_null = "Title: (null)\nAuthor:   \n";
         let meta = parse_pdfinfo_stdout(out_null);
@@ -222,6 +484,8 @@ Producer: ignored
             PdfRenderError::Spawn(_)
                 | PdfRenderError::ExitStatus(_)
                 | PdfRenderError::ReadOutput(_)
+                | PdfRenderError::NoEmbeddedImage
+                | PdfRenderError::Parse(_)
         ));
     }
 
@@ -231,7 +495,7 @@ Producer: ignored
             extract_metadata_from_path(Path::new("/definitely/missing/file.pdf")).unwrap_err();
         assert!(matches!(
             err,
-            PdfInfoError::Spawn(_) | PdfInfoError::ExitStatus(_)
+            PdfInfoError::Spawn(_) | PdfInfoError::ExitStatus(_) | PdfInfoError::ReadInput(_)
         ));
     }
 
@@ -240,7 +504,106 @@ Producer: ignored
         let err = extract_metadata_from_bytes(b"not a pdf").unwrap_err();
         assert!(matches!(
             err,
-            PdfInfoError::Spawn(_) | PdfInfoError::ExitStatus(_)
+            PdfInfoError::Spawn(_) | PdfInfoError::ExitStatus(_) | PdfInfoError::Parse(_)
         ));
     }
+
+    fn make_jpeg(width: u32, height: u32) -> Vec<u8> {
+        let image = image::DynamicImage::new_rgb8(width, height);
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut jpeg, image::ImageFormat::Jpeg).unwrap();
+        jpeg.into_inner()
+    }
+
+    /// A PDF of `pages` pages whose first page uses `images` (JPEG data)
+    /// through resources inherited from the page tree, saved with object
+    /// and cross-reference streams.
+    fn make_pdf(info: Option<lopdf::Dictionary>, images: &[Vec<u8>], pages: u32) -> Vec<u8> {
+        use lopdf::{Object, Stream};
+
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let mut xobjects = lopdf::Dictionary::new();
+        for (i, jpeg) in images.iter().enumerate() {
+            let (w, h) = image::load_from_memory(jpeg)
+                .unwrap()
+                .to_rgb8()
+                .dimensions();
+            let id = doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => w,
+                    "Height" => h,
+                    "ColorSpace" => "DeviceRGB",
+                    "BitsPerComponent" => 8,
+                    "Filter" => "DCTDecode",
+                },
+                jpeg.clone(),
+            ));
+            xobjects.set(format!("Im{i}"), id);
+        }
+        let kids: Vec<Object> = (0..pages)
+            .map(|_| {
+                doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id })
+                    .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => pages,
+                "Resources" => dictionary! { "XObject" => xobjects },
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        if let Some(info) = info {
+            let info_id = doc.add_object(info);
+            doc.trailer.set("Info", info_id);
+        }
+        let options = lopdf::SaveOptions::builder()
+            .use_object_streams(true)
+            .use_xref_streams(true)
+            .build();
+        let mut out = Vec::new();
+        doc.save_with_options(&mut out, options).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_native_metadata_from_compressed_info_dict() {
+        let info = dictionary! {
+            "Title" => lopdf::Object::string_literal("A (Tale) of Two"),
+            "Author" => lopdf::Object::String(
+                vec![0xFE, 0xFF, 0x04, 0x16, 0x04, 0x3E],
+                lopdf::StringFormat::Hexadecimal,
+            ),
+        };
+        let pdf = make_pdf(Some(info), &[], 12);
+        let meta = extract_metadata_from_bytes(&pdf).unwrap();
+        assert_eq!(meta.title.as_deref(), Some("A (Tale) of Two"));
+        assert_eq!(meta.author.as_deref(), Some("\u{416}\u{43E}"));
+        assert_eq!(meta.pages, Some(12));
+
+        let meta = extract_metadata_from_bytes(&make_pdf(None, &[], 1)).unwrap();
+        assert_eq!(meta.title, None);
+        assert_eq!(meta.pages, Some(1));
+    }
+
+    #[test]
+    fn test_find_embedded_jpeg_takes_the_largest_first_page_image() {
+        let logo = make_jpeg(32, 32);
+        let small = make_jpeg(150, 200);
+        let page = make_jpeg(300, 420);
+        let pdf = make_pdf(None, &[logo.clone(), page.clone(), small], 2);
+        let doc = lopdf::Document::load_mem(&pdf).unwrap();
+        assert_eq!(find_embedded_jpeg(&doc), Some(page));
+
+        let doc = lopdf::Document::load_mem(&make_pdf(None, &[logo], 1)).unwrap();
+        assert_eq!(find_embedded_jpeg(&doc), None);
+    }
 }
@@ -1,5 +1,13 @@
 use super::*;
-use std::io::{BufReader, Cursor};
+use parsers::filename::{FilenamePattern, apply_patterns};
+use std::io::{BufReader, Cursor, Read};
+
+/// Only the head of a TXT/RTF book is read for its title and annotation.
+pub(super) const TEXT_READ_LIMIT: usize = 1 << 20;
+
+/// Pages of a PDF text layer read for its preview; front matter can fill
+/// the first few.
+const PDF_PREVIEW_PAGES: u32 = 5;
 
 /// Process a single book file on disk.
 pub(super) async fn process_file(
@@ -10,8 +18,16 @@ pub(super) async fn process_file(
     extension: &str,
     size: i64,
 ) -> Result<(), ScanError> {
-    if let Some(existing_id) = ctx.existing_book_id(rel_path, filename) {
-        ctx.mark_existing_book_confirmed(existing_id);
+    if let Some(known) = ctx.existing_book(rel_path, filename) {
+        ctx.mark_existing_book_confirmed(known.id);
+        if known.size == size && !rewritten_in_place(path, known).await? {
+            ctx.stats.books_skipped.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        return update_changed_file(ctx, path, known.id, filename, extension, size).await;
+    }
+
+    if identical::confirm_copy(ctx, rel_path, filename, size).await? {
         ctx.stats.books_skipped.fetch_add(1, Ordering::Relaxed);
         return Ok(());
     }
@@ -38,6 +54,17 @@ pub(super) async fn process_file(
         return Ok(());
     }
 
+    let mut file_hash = String::new();
+    if ctx.link_identical && path.is_file() {
+        match identical::link_new_file(ctx, path, rel_path, filename, extension, size).await? {
+            Some(hash) => file_hash = hash,
+            None => {
+                ctx.stats.books_skipped.fetch_add(1, Ordering::Relaxed);
+                return Ok(());
+            }
+        }
+    }
+
     // Parse metadata
     let meta = {
         let _permit = acquire_scan_permit(ctx).await?;
@@ -45,13 +72,14 @@ pub(super) async fn process_file(
             let path = path.to_path_buf();
             let ext = extension.to_string();
             let cover_cfg = ctx.cover_image_cfg;
-            move || parse_book_file(&path, &ext, cover_cfg)
+            let patterns = ctx.filename_patterns.clone();
+            move || parse_book_file(&path, &ext, cover_cfg, &patterns)
         })
         .await
         .map_err(|e| ScanError::Internal(e.to_string()))??
     };
 
-    let pending = build_pending_book_insert(
+    let mut pending = build_pending_book_insert(
         ctx,
         filename,
         rel_path,
@@ -61,19 +89,131 @@ pub(super) async fn process_file(
         &meta,
     )
     .await?;
+    pending.file_hash = file_hash;
     enqueue_pending_book(ctx, pending).await?;
     Ok(())
 }
 
+/// Read a known book again after its file changed size, updating the row in
+/// place (see [`replace_book_with_meta`]) so it shows up as recently updated.
+/// Whether a file of the recorded size was rewritten since it was recorded:
+/// it was modified later, and its content differs from the recorded hash
+/// when there is one.
+async fn rewritten_in_place(path: &Path, known: &KnownBook) -> Result<bool, ScanError> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).map(|t| {
+        chrono::DateTime::<Utc>::from(t)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    });
+    let Ok(modified) = modified else {
+        return Ok(false);
+    };
+    let recorded = known.recorded.get(..19).unwrap_or(&known.recorded);
+    if recorded.is_empty() || modified.as_str() <= recorded {
+        return Ok(false);
+    }
+    if known.file_hash.is_empty() {
+        return Ok(true);
+    }
+    let hash = identical::hash_file_blocking(path.to_path_buf()).await?;
+    Ok(hash != known.file_hash)
+}
+
+async fn update_changed_file(
+    ctx: &ScanContext,
+    path: &Path,
+    book_id: i64,
+    filename: &str,
+    extension: &str,
+    size: i64,
+) -> Result<(), ScanError> {
+    let Some(book) = books::get_by_id(&ctx.pool, book_id).await? else {
+        return Ok(());
+    };
+    let mut meta = {
+        let _permit = acquire_scan_permit(ctx).await?;
+        tokio::task::spawn_blocking({
+            let path = path.to_path_buf();
+            let ext = extension.to_string();
+            let cover_cfg = ctx.cover_image_cfg;
+            let patterns = ctx.filename_patterns.clone();
+            move || parse_book_file(&path, &ext, cover_cfg, &patterns)
+        })
+        .await
+        .map_err(|e| ScanError::Internal(e.to_string()))??
+    };
+    meta.lang = resolve_lang(&meta, ctx.lang_detect);
+    replace_book_with_meta(
+        &ctx.pool,
+        &book,
+        filename,
+        extension,
+        size,
+        &meta,
+        &ctx.covers_path,
+        ctx.cover_image_cfg,
+    )
+    .await?;
+    if ctx.link_identical && path.is_file() {
+        let hash = identical::hash_file_blocking(path.to_path_buf()).await?;
+        books::set_file_hash(&ctx.pool, book_id, &hash).await?;
+    } else if !book.file_hash.is_empty() {
+        books::set_file_hash(&ctx.pool, book_id, "").await?;
+    }
+    ctx.stats.books_updated.fetch_add(1, Ordering::Relaxed);
+    ctx.observers.book_updated(&ScannedBook {
+        id: book_id,
+        path: &book.path,
+        filename,
+        format: extension,
+        title: if book.title.is_empty() {
+            &meta.title
+        } else {
+            &book.title
+        },
+    });
+    Ok(())
+}
+
+/// The book's own language, or one guessed from its preview text when it has
+/// none (`library.detect_lang`). Guesses below `min_confidence` are dropped;
+/// `None` disables guessing.
+pub fn resolve_lang(meta: &BookMeta, min_confidence: Option<f32>) -> String {
+    if !meta.lang.trim().is_empty() {
+        return meta.lang.clone();
+    }
+    min_confidence
+        .and_then(|min| {
+            crate::langdetect::detect(&meta.preview_text)
+                .filter(|(_, confidence)| *confidence >= min)
+        })
+        .map(|(code, _)| code.to_string())
+        .unwrap_or_default()
+}
+
 /// Parse a book file from disk by extension.
+/// Formats without embedded metadata fall back to the filename, matched
+/// against `filename_patterns` when any are configured.
 pub fn parse_book_file(
     path: &Path,
     ext: &str,
     cover_cfg: CoverImageConfig,
+    filename_patterns: &[FilenamePattern],
 ) -> Result<BookMeta, ScanError> {
+    // MP3 audiobooks are folders of tracks rather than a single file.
+    if ext == "mp3" && path.is_dir() {
+        let mut meta =
+            parsers::audio::parse_mp3_folder(path).map_err(|e| ScanError::Parse(e.to_string()))?;
+        if meta.title.is_empty() {
+            meta.title = crate::vfs::decode_name(path.file_name().unwrap_or_default());
+        }
+        prepare_cover(&mut meta, cover_cfg);
+        return Ok(meta);
+    }
+
     let file = fs::File::open(path)?;
     let reader = BufReader::new(file);
-    match ext {
+    let mut meta = match ext {
         "fb2" => parsers::fb2::parse(reader).map_err(|e| ScanError::Parse(e.to_string())),
         "epub" => {
             // EPUB needs Read + Seek, reopen as file
@@ -82,11 +222,7 @@ pub fn parse_book_file(
         }
         "mobi" => parsers::mobi::parse(reader).map_err(|e| ScanError::Parse(e.to_string())),
         "pdf" => {
-            let fallback_title = path
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+            let fallback_title = crate::vfs::decode_name(path.file_stem().unwrap_or_default());
             let mut meta = BookMeta {
                 title: fallback_title.clone(),
                 ..Default::default()
@@ -100,6 +236,7 @@ pub fn parse_book_file(
                     if let Some(author) = pdf_meta.author {
                         meta.authors = vec![author];
                     }
+                    meta.page_count = pdf_meta.pages.map_or(0, |n| n as i32);
                 }
                 Err(e) => {
                     warn!(
@@ -114,6 +251,12 @@ pub fn parse_book_file(
                 meta.title = fallback_title;
             }
 
+            match crate::pdf::extract_text_from_path(path, PDF_PREVIEW_PAGES) {
+                Ok(text) => meta.preview_text = parsers::preview_from_text(&text),
+                Err(crate::pdf::PdfTextError::Unavailable) => {}
+                Err(e) => warn!("Failed to extract PDF text for {}: {}", path.display(), e),
+            }
+
             match crate::pdf::render_first_page_jpeg_from_path(path, cover_cfg) {
                 Ok(cover) => {
                     meta.cover_data = Some(cover);
@@ -127,15 +270,16 @@ pub fn parse_book_file(
             Ok(meta)
         }
         "djvu" => {
-            let fallback_title = path
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+            let fallback_title = crate::vfs::decode_name(path.file_stem().unwrap_or_default());
             let mut meta = BookMeta {
-                title: fallback_title,
+                title: fallback_title.clone(),
                 ..Default::default()
             };
+            apply_patterns(filename_patterns, &fallback_title, &mut meta);
+            meta.page_count = fs::File::open(path)
+                .ok()
+                .and_then(crate::djvu::page_count)
+                .map_or(0, |n| n as i32);
 
             match crate::djvu::render_first_page_jpeg_from_path(path, cover_cfg) {
                 Ok(cover) => {
@@ -149,18 +293,87 @@ pub fn parse_book_file(
 
             Ok(meta)
         }
+        "m4b" => parsers::audio::parse_m4b(reader).map_err(|e| ScanError::Parse(e.to_string())),
+        "txt" | "rtf" => {
+            let stem = crate::vfs::decode_name(path.file_stem().unwrap_or_default());
+            let file = fs::File::open(path)?;
+            let size = file.metadata()?.len();
+            let mut data = Vec::new();
+            file.take(TEXT_READ_LIMIT as u64).read_to_end(&mut data)?;
+            Ok(text_book_meta(&data, size, ext, &stem, filename_patterns))
+        }
         _ => {
             // For unsupported formats, return minimal metadata from filename
-            Ok(BookMeta {
-                title: path
-                    .file_stem()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string(),
-                ..Default::default()
-            })
+            let stem = crate::vfs::decode_name(path.file_stem().unwrap_or_default());
+            Ok(filename_meta(&stem, filename_patterns))
         }
+    }?;
+    prepare_cover(&mut meta, cover_cfg);
+    Ok(meta)
+}
+
+/// Downscale and convert the parsed cover right away, so oversized embedded
+/// images are not carried through the insert queue.
+fn prepare_cover(meta: &mut BookMeta, cover_cfg: CoverImageConfig) {
+    let Some(data) = meta.cover_data.take() else {
+        return;
+    };
+    if let Some((data, mime)) =
+        crate::covers::normalize_for_storage(&data, &meta.cover_type, cover_cfg)
+    {
+        meta.cover_data = Some(data);
+        meta.cover_type = mime;
+    } else {
+        meta.cover_type.clear();
+    }
+}
+
+/// Parse an FB2 book from a reader without buffering the whole file.
+pub(super) fn parse_fb2_stream(
+    reader: impl std::io::BufRead,
+    cover_cfg: CoverImageConfig,
+) -> Result<BookMeta, ScanError> {
+    let mut meta = parsers::fb2::parse(reader).map_err(|e| ScanError::Parse(e.to_string()))?;
+    prepare_cover(&mut meta, cover_cfg);
+    Ok(meta)
+}
+
+/// Minimal metadata for formats without any: the file stem as title, or
+/// the fields of the first matching filename pattern.
+pub(super) fn filename_meta(stem: &str, filename_patterns: &[FilenamePattern]) -> BookMeta {
+    let mut meta = BookMeta {
+        title: stem.to_string(),
+        ..Default::default()
+    };
+    apply_patterns(filename_patterns, stem, &mut meta);
+    meta
+}
+
+/// Plain text and RTF books: title from the first line (or the filename),
+/// annotation from the text after it. A matching filename pattern takes
+/// precedence over the first line. `data` is the head of a `size`-byte file;
+/// the word count of a longer file is extrapolated from it.
+fn text_book_meta(
+    data: &[u8],
+    size: u64,
+    ext: &str,
+    stem: &str,
+    filename_patterns: &[FilenamePattern],
+) -> BookMeta {
+    let mut meta = if ext == "rtf" {
+        parsers::rtf::parse(data)
+    } else {
+        parsers::txt::parse(data)
+    };
+    if !data.is_empty() && size > data.len() as u64 {
+        let words = meta.word_count as u64 * size / data.len() as u64;
+        meta.word_count = i32::try_from(words).unwrap_or(i32::MAX);
+    }
+    if meta.title.is_empty() {
+        meta.title = stem.to_string();
     }
+    apply_patterns(filename_patterns, stem, &mut meta);
+    meta
 }
 
 /// Parse book metadata from in-memory bytes.
@@ -169,8 +382,9 @@ pub fn parse_book_bytes(
     ext: &str,
     filename: &str,
     cover_cfg: CoverImageConfig,
+    filename_patterns: &[FilenamePattern],
 ) -> Result<BookMeta, ScanError> {
-    match ext {
+    let mut meta = match ext {
         "fb2" => {
             let reader = BufReader::new(Cursor::new(data));
             parsers::fb2::parse(reader).map_err(|e| ScanError::Parse(e.to_string()))
@@ -200,6 +414,7 @@ pub fn parse_book_bytes(
                     if let Some(author) = pdf_meta.author {
                         meta.authors = vec![author];
                     }
+                    meta.page_count = pdf_meta.pages.map_or(0, |n| n as i32);
                 }
                 Err(e) => {
                     warn!("Failed to extract PDF metadata from archive bytes: {}", e);
@@ -210,6 +425,12 @@ pub fn parse_book_bytes(
                 meta.title = fallback_title;
             }
 
+            match crate::pdf::extract_text_from_bytes(data, PDF_PREVIEW_PAGES) {
+                Ok(text) => meta.preview_text = parsers::preview_from_text(&text),
+                Err(crate::pdf::PdfTextError::Unavailable) => {}
+                Err(e) => warn!("Failed to extract PDF text from archive bytes: {}", e),
+            }
+
             match crate::pdf::render_first_page_jpeg_from_bytes(data, cover_cfg) {
                 Ok(cover) => {
                     meta.cover_data = Some(cover);
@@ -229,9 +450,11 @@ pub fn parse_book_bytes(
                 .to_string();
 
             let mut meta = BookMeta {
-                title: fallback_title,
+                title: fallback_title.clone(),
                 ..Default::default()
             };
+            apply_patterns(filename_patterns, &fallback_title, &mut meta);
+            meta.page_count = crate::djvu::page_count(Cursor::new(data)).map_or(0, |n| n as i32);
 
             match crate::djvu::render_first_page_jpeg_from_bytes(data, cover_cfg) {
                 Ok(cover) => {
@@ -245,15 +468,27 @@ pub fn parse_book_bytes(
 
             Ok(meta)
         }
-        _ => Ok(BookMeta {
-            title: Path::new(filename)
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string(),
-            ..Default::default()
-        }),
-    }
+        "m4b" => parsers::audio::parse_m4b(Cursor::new(data))
+            .map_err(|e| ScanError::Parse(e.to_string())),
+        "txt" | "rtf" => {
+            let stem = Path::new(filename).file_stem().unwrap_or_default();
+            let size = data.len() as u64;
+            let data = &data[..data.len().min(TEXT_READ_LIMIT)];
+            Ok(text_book_meta(
+                data,
+                size,
+                ext,
+                &stem.to_string_lossy(),
+                filename_patterns,
+            ))
+        }
+        _ => {
+            let stem = Path::new(filename).file_stem().unwrap_or_default();
+            Ok(filename_meta(&stem.to_string_lossy(), filename_patterns))
+        }
+    }?;
+    prepare_cover(&mut meta, cover_cfg);
+    Ok(meta)
 }
 
 /// Insert a book record and link authors, genres, series.
@@ -311,18 +546,29 @@ pub async fn insert_book_with_meta(
         &meta.cover_type,
     )
     .await?;
+    if !meta.preview_text.is_empty() {
+        books::set_preview_text(pool, book_id, &meta.preview_text).await?;
+    }
+    if meta.word_count > 0 || meta.page_count > 0 {
+        books::set_length(pool, book_id, meta.word_count, meta.page_count).await?;
+    }
 
     // Save cover to disk
-    if let Some(ref cover_data) = meta.cover_data
-        && let Err(e) = save_cover(
+    if let Some(ref cover_data) = meta.cover_data {
+        match save_cover(
             covers_path,
             book_id,
             cover_data,
             &meta.cover_type,
             cover_cfg,
-        )
-    {
-        warn!("Failed to save cover for book {book_id}: {e}");
+        ) {
+            Ok(Some(stored)) if stored.width > 0 => {
+                books::set_cover_size(pool, book_id, stored.width, stored.height).await?;
+                books::set_cover_palette(pool, book_id, &stored.palette).await?;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to save cover for book {book_id}: {e}"),
+        }
     }
 
     // Link authors
@@ -354,5 +600,130 @@ pub async fn insert_book_with_meta(
         series::link_book(pool, book_id, series_id, meta.series_index).await?;
     }
 
+    if let Some((duration, narrator)) = audio_details(meta) {
+        book_audio::upsert(pool, book_id, duration, &narrator).await?;
+    }
+
     Ok(book_id)
 }
+
+/// Point an existing book at a replacement file, keeping its id and every
+/// per-user record (bookshelf, reading positions, downloads).
+/// Fields an admin can edit (title, authors, genres, series) keep their
+/// stored values and are only filled from `meta` when empty; annotation,
+/// date, language and cover come from the new file where it has them, the
+/// text preview always does.
+#[allow(clippy::too_many_arguments)]
+pub async fn replace_book_with_meta(
+    pool: &DbPool,
+    book: &crate::db::models::Book,
+    filename: &str,
+    format: &str,
+    size: i64,
+    meta: &BookMeta,
+    covers_path: &Path,
+    cover_cfg: CoverImageConfig,
+) -> Result<(), ScanError> {
+    let annotation: String = if meta.annotation.is_empty() {
+        book.annotation.clone()
+    } else {
+        meta.annotation
+            .chars()
+            .filter(|c| (*c as u32) < 0x10000)
+            .collect()
+    };
+    let pick = |new: &str, old: &str| {
+        if new.is_empty() {
+            old.to_string()
+        } else {
+            new.to_string()
+        }
+    };
+    let docdate = pick(&meta.docdate, &book.docdate);
+    let lang = pick(&meta.lang, &book.lang);
+
+    let stored = meta.cover_data.as_ref().and_then(|cover_data| {
+        replace_cover(
+            covers_path,
+            book.id,
+            cover_data,
+            &meta.cover_type,
+            cover_cfg,
+        )
+        .unwrap_or_else(|e| {
+            warn!("Failed to save cover for book {}: {e}", book.id);
+            None
+        })
+    });
+    let (cover, cover_type) = match &stored {
+        Some(stored) => (1, stored.mime.clone()),
+        None => (book.cover, book.cover_type.clone()),
+    };
+
+    books::replace_file(
+        pool,
+        book.id,
+        filename,
+        format,
+        size,
+        &annotation,
+        &docdate,
+        &lang,
+        cover,
+        &cover_type,
+    )
+    .await?;
+    if let Some(stored) = stored {
+        books::set_cover_size(pool, book.id, stored.width, stored.height).await?;
+        books::set_cover_palette(pool, book.id, &stored.palette).await?;
+    }
+    // The preview always follows the file: a stale one would describe the old text.
+    if meta.preview_text != book.preview_text {
+        books::set_preview_text(pool, book.id, &meta.preview_text).await?;
+    }
+    if (meta.word_count, meta.page_count) != (book.word_count, book.page_count) {
+        books::set_length(pool, book.id, meta.word_count, meta.page_count).await?;
+    }
+
+    // Authors: a book known only as "Unknown" takes the new file's authors.
+    let current = authors::get_for_book(pool, book.id).await?;
+    if current.iter().all(|a| a.full_name == "Unknown") && !meta.authors.is_empty() {
+        let mut author_ids = Vec::new();
+        for author_name in &meta.authors {
+            let name = normalise_author_name(author_name);
+            if !name.is_empty() {
+                author_ids.push(ensure_author(pool, &name).await?);
+            }
+        }
+        if !author_ids.is_empty() {
+            books::set_book_authors_and_update_key(pool, book.id, &author_ids).await?;
+        }
+    }
+
+    if genres::get_for_book(pool, book.id, &lang).await?.is_empty() {
+        for genre_code in &meta.genres {
+            genres::link_book_by_code(pool, book.id, genre_code).await?;
+        }
+    }
+
+    if let Some(ref ser_title) = meta.series_title
+        && !ser_title.is_empty()
+        && series::get_for_book(pool, book.id).await?.is_empty()
+    {
+        let series_id = ensure_series(pool, ser_title).await?;
+        series::link_book(pool, book.id, series_id, meta.series_index).await?;
+    }
+
+    if let Some((duration, narrator)) = audio_details(meta) {
+        book_audio::upsert(pool, book.id, duration, &narrator).await?;
+    }
+
+    Ok(())
+}
+
+/// Running time and narrator list stored for audiobooks; `None` for books
+/// without either.
+pub(super) fn audio_details(meta: &BookMeta) -> Option<(i64, String)> {
+    (meta.duration_secs > 0 || !meta.narrators.is_empty())
+        .then(|| (meta.duration_secs, meta.narrators.join(", ")))
+}
@@ -1,66 +1,76 @@
 use super::*;
-use image::DynamicImage;
-use image::GenericImageView;
-use image::codecs::jpeg::JpegEncoder;
-use std::io::Cursor;
 
-pub(crate) fn normalize_cover_for_storage_with_options(
-    data: &[u8],
-    mime: &str,
-    cover_cfg: CoverImageConfig,
-) -> (Vec<u8>, String) {
-    let max_dimension_px = cover_cfg.scale_to();
-    let jpeg_quality = cover_cfg.jpeg_quality();
-
-    let Ok(img) = image::load_from_memory(data) else {
-        // Keep original bytes if decoder can't parse this format.
-        return (data.to_vec(), normalize_mime(mime).to_string());
-    };
-
-    let (w, h) = img.dimensions();
-    let is_jpeg = matches!(mime, "image/jpeg" | "image/jpg" | "image/pjpeg");
-    let needs_resize = w.max(h) > max_dimension_px;
-    let needs_format_conversion = !is_jpeg;
-
-    if !needs_resize && !needs_format_conversion {
-        return (data.to_vec(), normalize_mime(mime).to_string());
-    }
-
-    let processed = if needs_resize {
-        img.resize(
-            max_dimension_px,
-            max_dimension_px,
-            image::imageops::FilterType::Lanczos3,
-        )
-    } else {
-        img
-    };
+/// Subdirectory of the covers directory holding catalog mosaics.
+pub(super) const CATALOG_MOSAIC_DIR: &str = "catalogs";
+
+/// A cover written to the covers directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredCover {
+    pub mime: String,
+    /// Pixel size, 0×0 for data kept as is because it could not be decoded.
+    pub width: u32,
+    pub height: u32,
+    /// See [`crate::covers::palette`]; empty for undecodable data.
+    pub palette: String,
+}
 
-    // Store decodable covers as JPEG to ensure uniform quality/size handling.
-    if let Some(bytes) = encode_jpeg(&processed, jpeg_quality) {
-        return (bytes, "image/jpeg".to_string());
+impl StoredCover {
+    fn new(data: &[u8], mime: String) -> Self {
+        let (width, height) = crate::covers::dimensions(data).unwrap_or_default();
+        Self {
+            mime,
+            width,
+            height,
+            palette: crate::covers::palette(data).unwrap_or_default(),
+        }
     }
-
-    // Fallback if encoding fails for any reason.
-    (data.to_vec(), normalize_mime(mime).to_string())
 }
 
 /// Save cover image bytes to disk using hierarchical cover storage.
+/// Returns `None` when `data` is dropped by normalization.
 pub fn save_cover(
     covers_path: &Path,
     book_id: i64,
     data: &[u8],
     mime: &str,
     cover_cfg: CoverImageConfig,
-) -> Result<(), std::io::Error> {
-    let (normalized_data, normalized_mime) =
-        normalize_cover_for_storage_with_options(data, mime, cover_cfg);
+) -> Result<Option<StoredCover>, std::io::Error> {
+    let Some((normalized_data, normalized_mime)) =
+        crate::covers::normalize_for_storage(data, mime, cover_cfg)
+    else {
+        return Ok(None);
+    };
     let ext = mime_to_ext(&normalized_mime);
     let path = cover_storage_path(covers_path, book_id, ext);
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::write(&path, normalized_data)
+    fs::write(&path, &normalized_data)?;
+    Ok(Some(StoredCover::new(&normalized_data, normalized_mime)))
+}
+
+/// Replace the stored cover of a book, dropping copies in other formats or
+/// older layouts. Returns `None` (keeping the old cover) when `data` is
+/// dropped by normalization.
+pub fn replace_cover(
+    covers_path: &Path,
+    book_id: i64,
+    data: &[u8],
+    mime: &str,
+    cover_cfg: CoverImageConfig,
+) -> Result<Option<StoredCover>, std::io::Error> {
+    let Some((normalized_data, normalized_mime)) =
+        crate::covers::normalize_for_storage(data, mime, cover_cfg)
+    else {
+        return Ok(None);
+    };
+    delete_cover(covers_path, book_id);
+    let path = cover_storage_path(covers_path, book_id, mime_to_ext(&normalized_mime));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, &normalized_data)?;
+    Ok(Some(StoredCover::new(&normalized_data, normalized_mime)))
 }
 
 /// Return hierarchical storage path for a cover file.
@@ -90,6 +100,21 @@ pub fn legacy_cover_storage_path(covers_path: &Path, book_id: i64, ext: &str) ->
     covers_path.join(format!("{book_id}.{ext}"))
 }
 
+/// Return the storage path of a catalog cover mosaic.
+/// Layout: `{covers_dir}/catalogs/{catalog_id}.jpg`.
+pub fn catalog_mosaic_path(covers_path: &Path, catalog_id: i64) -> PathBuf {
+    covers_path
+        .join(CATALOG_MOSAIC_DIR)
+        .join(format!("{catalog_id}.jpg"))
+}
+
+/// Read a stored cover of a book (current layout).
+pub(crate) fn read_cover(covers_path: &Path, book_id: i64) -> Option<Vec<u8>> {
+    ["jpg", "png", "gif"]
+        .iter()
+        .find_map(|ext| fs::read(cover_storage_path(covers_path, book_id, ext)).ok())
+}
+
 pub(super) fn mime_to_ext(mime: &str) -> &str {
     match mime {
         "image/png" => "png", // legacy/decode-fallback covers
@@ -98,23 +123,8 @@ pub(super) fn mime_to_ext(mime: &str) -> &str {
     }
 }
 
-fn normalize_mime(mime: &str) -> &str {
-    match mime {
-        "image/png" => "image/png",
-        "image/gif" => "image/gif",
-        _ => "image/jpeg",
-    }
-}
-
-fn encode_jpeg(img: &DynamicImage, quality: u8) -> Option<Vec<u8>> {
-    let mut out = Cursor::new(Vec::new());
-    let mut encoder = JpegEncoder::new_with_quality(&mut out, quality);
-    encoder.encode_image(img).ok()?;
-    Some(out.into_inner())
-}
-
 /// Remove cover file for a book (tries all known extensions and layouts).
-pub(super) fn delete_cover(covers_path: &Path, book_id: i64) {
+pub(crate) fn delete_cover(covers_path: &Path, book_id: i64) {
     for ext in &["jpg", "png", "gif"] {
         for path in [
             cover_storage_path(covers_path, book_id, ext),
@@ -1,5 +1,5 @@
 use super::*;
-use crate::db::DbBackend;
+use crate::db::{MAX_BIND_PARAMS, values_placeholders};
 
 /// Ensure a catalog row exists for the given path, creating it if needed.
 pub async fn ensure_catalog(
@@ -28,7 +28,9 @@ pub async fn ensure_catalog(
         .to_string_lossy()
         .to_string();
 
-    let id = catalogs::insert(pool, parent_id, path, &cat_name, cat_type, 0, "").await?;
+    let id = pool
+        .retry(|| catalogs::insert(pool, parent_id, path, &cat_name, cat_type, 0, ""))
+        .await?;
     Ok(id)
 }
 
@@ -64,10 +66,13 @@ pub(super) async fn ensure_archive_catalog(
         .to_string_lossy()
         .to_string();
 
-    let id = catalogs::insert(
-        pool, parent_id, path, &cat_name, cat_type, cat_size, cat_mtime,
-    )
-    .await?;
+    let id = pool
+        .retry(|| {
+            catalogs::insert(
+                pool, parent_id, path, &cat_name, cat_type, cat_size, cat_mtime,
+            )
+        })
+        .await?;
     Ok(id)
 }
 
@@ -78,7 +83,9 @@ pub async fn ensure_author(pool: &DbPool, full_name: &str) -> Result<i64, ScanEr
     }
     let search = full_name.to_uppercase();
     let lang_code = detect_lang_code(full_name);
-    let id = authors::insert(pool, full_name, &search, lang_code).await?;
+    let id = pool
+        .retry(|| authors::insert(pool, full_name, &search, lang_code))
+        .await?;
     Ok(id)
 }
 
@@ -89,7 +96,9 @@ pub async fn ensure_series(pool: &DbPool, ser_name: &str) -> Result<i64, ScanErr
     }
     let search = ser_name.to_uppercase();
     let lang_code = detect_lang_code(ser_name);
-    let id = series::insert(pool, ser_name, &search, lang_code).await?;
+    let id = pool
+        .retry(|| series::insert(pool, ser_name, &search, lang_code))
+        .await?;
     Ok(id)
 }
 
@@ -220,8 +229,12 @@ pub(super) async fn build_pending_book_insert(
         title,
         search_title,
         annotation,
+        preview_text: meta.preview_text.clone(),
+        word_count: meta.word_count,
+        page_count: meta.page_count,
+        file_hash: String::new(),
         docdate: meta.docdate.clone(),
-        lang: meta.lang.clone(),
+        lang: resolve_lang(meta, ctx.lang_detect),
         lang_code,
         cover_type: meta.cover_type.clone(),
         cover_data: meta.cover_data.clone(),
@@ -229,6 +242,7 @@ pub(super) async fn build_pending_book_insert(
         genre_ids,
         series_link,
         author_key,
+        audio: super::book::audio_details(meta),
     })
 }
 
@@ -247,13 +261,20 @@ pub(super) async fn run_pending_book_writer(
     mut pending_rx: mpsc::Receiver<PendingBookMsg>,
 ) -> Result<(), ScanError> {
     const BOOK_INSERT_BATCH_SIZE: usize = 512;
+    const LOW_MEMORY_INSERT_BATCH_SIZE: usize = 32;
 
-    let mut batch = Vec::with_capacity(BOOK_INSERT_BATCH_SIZE);
+    let batch_size = if ctx.low_memory {
+        LOW_MEMORY_INSERT_BATCH_SIZE
+    } else {
+        BOOK_INSERT_BATCH_SIZE
+    };
+    let mut batch = Vec::with_capacity(batch_size);
     while let Some(msg) = pending_rx.recv().await {
         match msg {
             PendingBookMsg::Insert(book) => {
                 batch.push(*book);
-                if batch.len() >= BOOK_INSERT_BATCH_SIZE {
+                if batch.len() >= batch_size {
+                    ctx.ensure_lock_held()?;
                     commit_pending_book_batch(&ctx, std::mem::take(&mut batch)).await?;
                 }
             }
@@ -261,6 +282,7 @@ pub(super) async fn run_pending_book_writer(
         }
     }
     if !batch.is_empty() {
+        ctx.ensure_lock_held()?;
         commit_pending_book_batch(&ctx, batch).await?;
     }
     Ok(())
@@ -275,44 +297,85 @@ async fn commit_pending_book_batch(
     }
     let inserted_count = pending_books.len();
 
+    // The transaction is rolled back on failure, so a retry starts clean.
+    let pending = &pending_books;
+    let book_ids = ctx
+        .pool
+        .retry(|| async move {
+            if ctx.pool.bulk_insert() {
+                insert_pending_books_bulk(ctx, pending).await
+            } else {
+                insert_pending_books(ctx, pending).await
+            }
+        })
+        .await?;
+
+    for (pending, book_id) in pending_books.into_iter().zip(book_ids) {
+        let Some(book_id) = book_id else {
+            continue;
+        };
+        if let Some(cover_data) = &pending.cover_data {
+            match save_cover(
+                &ctx.covers_path,
+                book_id,
+                cover_data,
+                &pending.cover_type,
+                ctx.cover_image_cfg,
+            ) {
+                Ok(Some(stored)) if stored.width > 0 => {
+                    books::set_cover_size(&ctx.pool, book_id, stored.width, stored.height).await?;
+                    books::set_cover_palette(&ctx.pool, book_id, &stored.palette).await?;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to save cover for book {book_id}: {e}"),
+            }
+        }
+        ctx.observers.book_added(&ScannedBook {
+            id: book_id,
+            path: &pending.path,
+            filename: &pending.filename,
+            format: &pending.format,
+            title: &pending.title,
+        });
+    }
+
+    ctx.stats
+        .books_added
+        .fetch_add(inserted_count as u64, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Insert a batch of books and their links one row at a time. Returns the
+/// new book ids in the order of `pending_books`.
+async fn insert_pending_books(
+    ctx: &ScanContext,
+    pending_books: &[PendingBookInsert],
+) -> Result<Vec<Option<i64>>, sqlx::Error> {
     let mut tx = ctx.pool.inner().begin().await?;
-    let mut covers_to_save = Vec::new();
+    let mut book_ids = Vec::with_capacity(pending_books.len());
 
     let books_insert_sql = ctx.pool.sql(
         "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
-         annotation, docdate, lang, lang_code, size, avail, cat_type, cover, cover_type, author_key) \
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+         translit_title, annotation, docdate, lang, lang_code, size, avail, cat_type, cover, \
+         cover_type, author_key, preview_text, word_count, page_count, file_hash) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     );
     let select_inserted_sql = ctx
         .pool
         .sql("SELECT id FROM books WHERE path = ? AND filename = ? ORDER BY id DESC LIMIT 1");
-    let link_author_sql = match ctx.pool.backend() {
-        DbBackend::Mysql => ctx
-            .pool
-            .sql("INSERT IGNORE INTO book_authors (book_id, author_id) VALUES (?, ?)"),
-        _ => ctx.pool.sql(
-            "INSERT INTO book_authors (book_id, author_id) VALUES (?, ?) \
-             ON CONFLICT (book_id, author_id) DO NOTHING",
-        ),
-    };
-    let link_genre_sql = match ctx.pool.backend() {
-        DbBackend::Mysql => ctx
-            .pool
-            .sql("INSERT IGNORE INTO book_genres (book_id, genre_id) VALUES (?, ?)"),
-        _ => ctx.pool.sql(
-            "INSERT INTO book_genres (book_id, genre_id) VALUES (?, ?) \
-             ON CONFLICT (book_id, genre_id) DO NOTHING",
-        ),
-    };
-    let link_series_sql = match ctx.pool.backend() {
-        DbBackend::Mysql => ctx
-            .pool
-            .sql("INSERT IGNORE INTO book_series (book_id, series_id, ser_no) VALUES (?, ?, ?)"),
-        _ => ctx.pool.sql(
-            "INSERT INTO book_series (book_id, series_id, ser_no) VALUES (?, ?, ?) \
-             ON CONFLICT (book_id, series_id) DO NOTHING",
-        ),
-    };
+    let link_author_sql = ctx
+        .pool
+        .upsert_ignore("book_authors", &["book_id", "author_id"]);
+    let link_genre_sql = ctx
+        .pool
+        .upsert_ignore("book_genres", &["book_id", "genre_id"]);
+    let link_series_sql = ctx
+        .pool
+        .upsert_ignore("book_series", &["book_id", "series_id", "ser_no"]);
+
+    let audio_sql = ctx
+        .pool
+        .upsert_ignore("book_audio", &["book_id", "duration", "narrator"]);
 
     for pending in pending_books {
         let has_cover = if pending.cover_data.is_some() { 1 } else { 0 };
@@ -323,6 +386,7 @@ async fn commit_pending_book_batch(
             .bind(&pending.format)
             .bind(&pending.title)
             .bind(&pending.search_title)
+            .bind(crate::translit::fold(&pending.search_title))
             .bind(&pending.annotation)
             .bind(&pending.docdate)
             .bind(&pending.lang)
@@ -333,6 +397,10 @@ async fn commit_pending_book_batch(
             .bind(has_cover)
             .bind(&pending.cover_type)
             .bind(&pending.author_key)
+            .bind(&pending.preview_text)
+            .bind(pending.word_count)
+            .bind(pending.page_count)
+            .bind(&pending.file_hash)
             .execute(&mut *tx)
             .await?;
 
@@ -347,14 +415,14 @@ async fn commit_pending_book_batch(
             row.0
         };
 
-        for author_id in pending.author_ids {
+        for author_id in &pending.author_ids {
             sqlx::query(&link_author_sql)
                 .bind(book_id)
                 .bind(author_id)
                 .execute(&mut *tx)
                 .await?;
         }
-        for genre_id in pending.genre_ids {
+        for genre_id in &pending.genre_ids {
             sqlx::query(&link_genre_sql)
                 .bind(book_id)
                 .bind(genre_id)
@@ -370,27 +438,150 @@ async fn commit_pending_book_batch(
                 .await?;
         }
 
-        if let Some(cover_data) = pending.cover_data {
-            covers_to_save.push((book_id, cover_data, pending.cover_type));
+        if let Some((duration, narrator)) = &pending.audio {
+            sqlx::query(&audio_sql)
+                .bind(book_id)
+                .bind(duration)
+                .bind(narrator)
+                .execute(&mut *tx)
+                .await?;
         }
+
+        book_ids.push(Some(book_id));
     }
 
     tx.commit().await?;
 
-    for (book_id, cover_data, cover_type) in covers_to_save {
-        if let Err(e) = save_cover(
-            &ctx.covers_path,
-            book_id,
-            &cover_data,
-            &cover_type,
-            ctx.cover_image_cfg,
-        ) {
-            warn!("Failed to save cover for book {book_id}: {e}");
+    Ok(book_ids)
+}
+
+/// Insert a batch of books with one multi-row statement per table (see
+/// [`DbPool::bulk_insert`](crate::db::DbPool::bulk_insert)). Initial imports
+/// of large INPX collections spend most of their time in per-row round
+/// trips, which this path cuts to a handful per batch.
+async fn insert_pending_books_bulk(
+    ctx: &ScanContext,
+    pending_books: &[PendingBookInsert],
+) -> Result<Vec<Option<i64>>, sqlx::Error> {
+    const BOOK_COLUMNS: usize = 21;
+
+    let mut tx = ctx.pool.inner().begin().await?;
+    let mut book_ids: HashMap<(String, String), i64> = HashMap::new();
+    for chunk in pending_books.chunks(MAX_BIND_PARAMS / BOOK_COLUMNS) {
+        let raw = format!(
+            "INSERT INTO books (catalog_id, filename, path, format, title, search_title, \
+             translit_title, annotation, docdate, lang, lang_code, size, avail, cat_type, cover, \
+             cover_type, author_key, preview_text, word_count, page_count, file_hash) VALUES {} \
+             RETURNING id, path, filename",
+            values_placeholders(BOOK_COLUMNS, chunk.len())
+        );
+        let sql = ctx.pool.sql(&raw);
+        let mut query = sqlx::query_as::<_, (i64, String, String)>(&sql);
+        for pending in chunk {
+            let has_cover = if pending.cover_data.is_some() { 1 } else { 0 };
+            query = query
+                .bind(pending.catalog_id)
+                .bind(&pending.filename)
+                .bind(&pending.path)
+                .bind(&pending.format)
+                .bind(&pending.title)
+                .bind(&pending.search_title)
+                .bind(crate::translit::fold(&pending.search_title))
+                .bind(&pending.annotation)
+                .bind(&pending.docdate)
+                .bind(&pending.lang)
+                .bind(pending.lang_code)
+                .bind(pending.size)
+                .bind(AvailStatus::Confirmed as i32)
+                .bind(pending.cat_type as i32)
+                .bind(has_cover)
+                .bind(&pending.cover_type)
+                .bind(&pending.author_key)
+                .bind(&pending.preview_text)
+                .bind(pending.word_count)
+                .bind(pending.page_count)
+                .bind(&pending.file_hash);
+        }
+        for (id, path, filename) in query.fetch_all(&mut *tx).await? {
+            book_ids.insert((path, filename), id);
         }
     }
 
-    ctx.stats
-        .books_added
-        .fetch_add(inserted_count as u64, Ordering::Relaxed);
-    Ok(())
+    let mut author_links = Vec::new();
+    let mut genre_links = Vec::new();
+    let mut series_links = Vec::new();
+    let mut audio_rows = Vec::new();
+    let mut ids = Vec::with_capacity(pending_books.len());
+    for pending in pending_books {
+        let key = (pending.path.clone(), pending.filename.clone());
+        let book_id = book_ids.get(&key).copied();
+        ids.push(book_id);
+        let Some(book_id) = book_id else {
+            continue;
+        };
+        author_links.extend(pending.author_ids.iter().map(|&id| (book_id, id)));
+        genre_links.extend(pending.genre_ids.iter().map(|&id| (book_id, id)));
+        if let Some((series_id, ser_no)) = pending.series_link {
+            series_links.push((book_id, series_id, ser_no));
+        }
+        if let Some((duration, narrator)) = &pending.audio {
+            audio_rows.push((book_id, *duration, narrator));
+        }
+    }
+
+    for chunk in author_links.chunks(MAX_BIND_PARAMS / 2) {
+        let raw = format!(
+            "INSERT INTO book_authors (book_id, author_id) VALUES {} \
+             ON CONFLICT (book_id, author_id) DO NOTHING",
+            values_placeholders(2, chunk.len())
+        );
+        let sql = ctx.pool.sql(&raw);
+        let mut query = sqlx::query(&sql);
+        for (book_id, author_id) in chunk {
+            query = query.bind(*book_id).bind(*author_id);
+        }
+        query.execute(&mut *tx).await?;
+    }
+    for chunk in genre_links.chunks(MAX_BIND_PARAMS / 2) {
+        let raw = format!(
+            "INSERT INTO book_genres (book_id, genre_id) VALUES {} \
+             ON CONFLICT (book_id, genre_id) DO NOTHING",
+            values_placeholders(2, chunk.len())
+        );
+        let sql = ctx.pool.sql(&raw);
+        let mut query = sqlx::query(&sql);
+        for (book_id, genre_id) in chunk {
+            query = query.bind(*book_id).bind(*genre_id);
+        }
+        query.execute(&mut *tx).await?;
+    }
+    for chunk in series_links.chunks(MAX_BIND_PARAMS / 3) {
+        let raw = format!(
+            "INSERT INTO book_series (book_id, series_id, ser_no) VALUES {} \
+             ON CONFLICT (book_id, series_id) DO NOTHING",
+            values_placeholders(3, chunk.len())
+        );
+        let sql = ctx.pool.sql(&raw);
+        let mut query = sqlx::query(&sql);
+        for (book_id, series_id, ser_no) in chunk {
+            query = query.bind(*book_id).bind(*series_id).bind(*ser_no);
+        }
+        query.execute(&mut *tx).await?;
+    }
+    for chunk in audio_rows.chunks(MAX_BIND_PARAMS / 3) {
+        let raw = format!(
+            "INSERT INTO book_audio (book_id, duration, narrator) VALUES {} \
+             ON CONFLICT (book_id) DO NOTHING",
+            values_placeholders(3, chunk.len())
+        );
+        let sql = ctx.pool.sql(&raw);
+        let mut query = sqlx::query(&sql);
+        for (book_id, duration, narrator) in chunk {
+            query = query.bind(*book_id).bind(*duration).bind(narrator);
+        }
+        query.execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+    Ok(ids)
 }
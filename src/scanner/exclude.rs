@@ -0,0 +1,86 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::db::queries::catalogs;
+
+use super::ScanError;
+
+/// Library paths the scanner skips: `scanner.exclude` globs plus the
+/// folders of catalogs an admin marked hidden.
+#[derive(Default)]
+pub(super) struct Exclusions {
+    globs: GlobSet,
+    hidden: Vec<String>,
+}
+
+impl Exclusions {
+    pub(super) fn new(patterns: &[String], hidden: Vec<String>) -> Result<Self, globset::Error> {
+        Ok(Self {
+            globs: compile(patterns)?,
+            hidden,
+        })
+    }
+
+    /// Exclusions of the configured globs and the hidden catalogs in `pool`.
+    pub(super) async fn load(pool: &DbPool, config: &Config) -> Result<Self, ScanError> {
+        let hidden = catalogs::hidden_paths(pool).await?;
+        Self::new(&config.scanner.exclude, hidden).map_err(|e| ScanError::Internal(e.to_string()))
+    }
+
+    /// Whether a library-relative file or folder path is skipped. A folder
+    /// also matches patterns meant for its contents (`**/samples/**`).
+    pub(super) fn is_excluded(&self, rel_path: &str) -> bool {
+        if rel_path.is_empty() {
+            return false;
+        }
+        self.hidden.iter().any(|hidden| {
+            rel_path == hidden
+                || rel_path
+                    .strip_prefix(hidden.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        }) || self.globs.is_match(rel_path)
+            || self.globs.is_match(format!("{rel_path}/"))
+    }
+}
+
+/// Compile `scanner.exclude`; also used to validate the config.
+pub(crate) fn compile(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_globs_and_hidden_paths() {
+        let exclusions = Exclusions::new(
+            &["**/samples/**".to_string(), "**/*.tmp.fb2".to_string()],
+            vec!["junk".to_string()],
+        )
+        .unwrap();
+
+        assert!(exclusions.is_excluded("samples"));
+        assert!(exclusions.is_excluded("fiction/samples"));
+        assert!(exclusions.is_excluded("fiction/samples/a.fb2"));
+        assert!(exclusions.is_excluded("fiction/draft.tmp.fb2"));
+        assert!(exclusions.is_excluded("junk"));
+        assert!(exclusions.is_excluded("junk/deep/a.fb2"));
+
+        assert!(!exclusions.is_excluded(""));
+        assert!(!exclusions.is_excluded("fiction"));
+        assert!(!exclusions.is_excluded("fiction/samples.fb2"));
+        assert!(!exclusions.is_excluded("junkyard/a.fb2"));
+        assert!(!Exclusions::default().is_excluded("fiction/a.fb2"));
+    }
+
+    #[test]
+    fn test_compile_rejects_bad_pattern() {
+        assert!(compile(&["a/[b".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use super::*;
+
+use std::io::Read;
+
+use sha2::{Digest, Sha256};
+
+use crate::db::queries::book_files;
+
+/// A new file identical to another new file of the same scan, linked once
+/// that book is written.
+pub(super) struct DeferredCopy {
+    size: i64,
+    hash: String,
+    path: String,
+    filename: String,
+    format: String,
+}
+
+/// Hex SHA-256 of a file's content.
+pub(super) fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+pub(super) async fn hash_file_blocking(path: PathBuf) -> Result<String, ScanError> {
+    tokio::task::spawn_blocking(move || hash_file(&path))
+        .await
+        .map_err(|e| ScanError::Internal(e.to_string()))?
+        .map_err(ScanError::from)
+}
+
+/// An available book with the same content. Books stored before hashing
+/// was enabled are hashed on demand when their size matches.
+async fn find_identical(
+    ctx: &ScanContext,
+    size: i64,
+    hash: &str,
+) -> Result<Option<i64>, ScanError> {
+    if let Some(id) = books::find_by_file_hash(&ctx.pool, size, hash).await? {
+        return Ok(Some(id));
+    }
+    for (id, path, filename) in books::unhashed_with_size(&ctx.pool, size).await? {
+        let file = crate::vfs::local_file(&ctx.root, &path, &filename);
+        let Ok(candidate) = hash_file_blocking(file).await else {
+            continue;
+        };
+        books::set_file_hash(&ctx.pool, id, &candidate).await?;
+        if candidate == hash {
+            return Ok(Some(id));
+        }
+    }
+    Ok(None)
+}
+
+/// With `scanner.link_identical`, hash a new plain file and register it as
+/// a copy of an identical book. Returns `None` when the file was linked
+/// (now or once an identical new book is written), otherwise the hash to
+/// store with the new book.
+pub(super) async fn link_new_file(
+    ctx: &ScanContext,
+    path: &Path,
+    rel_path: &str,
+    filename: &str,
+    extension: &str,
+    size: i64,
+) -> Result<Option<String>, ScanError> {
+    let hash = {
+        let _permit = acquire_scan_permit(ctx).await?;
+        hash_file_blocking(path.to_path_buf()).await?
+    };
+    if let Some(book_id) = find_identical(ctx, size, &hash).await? {
+        book_files::insert(&ctx.pool, book_id, rel_path, filename, extension, size).await?;
+        ctx.files_linked.fetch_add(1, Ordering::Relaxed);
+        debug!("Linked {rel_path}/{filename} to identical book {book_id}");
+        return Ok(None);
+    }
+    if !ctx.pending_hashes.insert(format!("{size}:{hash}")) {
+        ctx.deferred_copies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(DeferredCopy {
+                size,
+                hash,
+                path: rel_path.to_string(),
+                filename: filename.to_string(),
+                format: extension.to_string(),
+            });
+        return Ok(None);
+    }
+    Ok(Some(hash))
+}
+
+/// Known copy of a book at this location: confirm it when the size still
+/// matches, forget it when the file changed (it is then scanned as a new
+/// file). Returns true when the file needs no further work.
+pub(super) async fn confirm_copy(
+    ctx: &ScanContext,
+    rel_path: &str,
+    filename: &str,
+    size: i64,
+) -> Result<bool, ScanError> {
+    let Some(&(copy_id, known_size)) = ctx
+        .existing_copies
+        .get(rel_path)
+        .and_then(|by_name| by_name.get(filename))
+    else {
+        return Ok(false);
+    };
+    if known_size == size {
+        ctx.confirmed_copy_ids.insert(copy_id);
+        return Ok(true);
+    }
+    book_files::delete_ids(&ctx.pool, &[copy_id]).await?;
+    Ok(false)
+}
+
+/// Link new files that were identical to a book written in the same scan.
+pub(super) async fn link_deferred_copies(ctx: &ScanContext) -> Result<(), ScanError> {
+    let deferred = std::mem::take(
+        &mut *ctx
+            .deferred_copies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()),
+    );
+    for copy in deferred {
+        match books::find_by_file_hash(&ctx.pool, copy.size, &copy.hash).await? {
+            Some(book_id) => {
+                book_files::insert(
+                    &ctx.pool,
+                    book_id,
+                    &copy.path,
+                    &copy.filename,
+                    &copy.format,
+                    copy.size,
+                )
+                .await?;
+                ctx.files_linked.fetch_add(1, Ordering::Relaxed);
+            }
+            None => warn!(
+                "Identical book of {}/{} was not written; it is scanned again next time",
+                copy.path, copy.filename
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Forget copies not found under the scanned path and move books whose own
+/// file is gone to one of their remaining copies, so they are not deleted.
+pub(super) async fn reconcile_copies(
+    ctx: &ScanContext,
+    scope: Option<&str>,
+) -> Result<(), ScanError> {
+    let missing: Vec<i64> = ctx
+        .existing_copies
+        .iter()
+        .filter(|(path, _)| in_scope(path, scope))
+        .flat_map(|(_, by_name)| by_name.values().map(|(id, _)| *id))
+        .filter(|id| !ctx.confirmed_copy_ids.contains(id))
+        .collect();
+    let forgotten = book_files::delete_ids(&ctx.pool, &missing).await?;
+
+    let linked = ctx.files_linked.load(Ordering::Relaxed);
+    if ctx.existing_copies.is_empty() && linked == 0 {
+        return Ok(());
+    }
+    let mut relocated = 0;
+    for book_id in books::get_unavailable_ids(&ctx.pool, scope).await? {
+        let copies = book_files::get_for_book(&ctx.pool, book_id).await?;
+        let Some(copy) = copies
+            .iter()
+            .find(|c| crate::vfs::local_file(&ctx.root, &c.path, &c.filename).is_file())
+        else {
+            continue;
+        };
+        let catalog_id = cached_ensure_catalog(ctx, &copy.path, CatType::Normal).await?;
+        books::move_to_file(&ctx.pool, book_id, catalog_id, &copy.path, &copy.filename).await?;
+        book_files::delete_ids(&ctx.pool, &[copy.id]).await?;
+        relocated += 1;
+    }
+    if linked + forgotten + relocated > 0 {
+        info!(
+            "Identical files: {linked} linked, {forgotten} copies gone, {relocated} books moved to a copy"
+        );
+    }
+    Ok(())
+}
+
+/// Whether a library path lies at or below the scanned path.
+fn in_scope(path: &str, scope: Option<&str>) -> bool {
+    match scope.map(|s| s.trim_end_matches('/')) {
+        None | Some("") => true,
+        Some(scope) => path == scope || path.starts_with(&format!("{scope}/")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_file_and_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.fb2");
+        let b = dir.path().join("b.fb2");
+        fs::write(&a, b"same").unwrap();
+        fs::write(&b, b"same").unwrap();
+        assert_eq!(hash_file(&a).unwrap(), hash_file(&b).unwrap());
+        assert_eq!(hash_file(&a).unwrap().len(), 64);
+        assert!(hash_file(&dir.path().join("missing")).is_err());
+
+        assert!(in_scope("mirror/a", None));
+        assert!(in_scope("mirror", Some("mirror/")));
+        assert!(in_scope("mirror/a", Some("mirror")));
+        assert!(!in_scope("mirrors", Some("mirror")));
+    }
+}
@@ -15,6 +15,14 @@ pub(super) async fn process_inpx(
         .unwrap_or(Path::new(""))
         .to_string_lossy()
         .to_string();
+    let info = {
+        let inpx_path = inpx_path.to_path_buf();
+        let encoding = ctx.zip_encoding;
+        tokio::task::spawn_blocking(move || read_inpx_info(&inpx_path, encoding))
+            .await
+            .map_err(|e| ScanError::Internal(e.to_string()))??
+    };
+    let version = info.version.unwrap_or_default();
 
     if try_skip_inpx_archive(
         &ctx.pool,
@@ -23,6 +31,7 @@ pub(super) async fn process_inpx(
         inpx_size,
         ctx.skip_unchanged,
         mtime,
+        &version,
     )
     .await?
     {
@@ -30,7 +39,16 @@ pub(super) async fn process_inpx(
         return Ok(());
     }
 
-    ensure_archive_catalog(&ctx.pool, rel_path, CatType::Inpx, inpx_size, mtime).await?;
+    let cat_id =
+        ensure_archive_catalog(&ctx.pool, rel_path, CatType::Inpx, inpx_size, mtime).await?;
+    let cat_name = info.collection_name.unwrap_or_else(|| {
+        inpx_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    });
+    catalogs::update_collection_info(&ctx.pool, cat_id, &cat_name, &version).await?;
 
     // Keep a moderate buffer so parser throughput is less sensitive to
     // temporary DB-side stalls in worker tasks.
@@ -39,10 +57,11 @@ pub(super) async fn process_inpx(
 
     let inpx_path_buf = inpx_path.to_path_buf();
     let inpx_dir_for_parse = inpx_dir.clone();
+    let encoding = ctx.zip_encoding;
     let parser_task = tokio::task::spawn_blocking(move || -> Result<u64, ScanError> {
         let file = fs::File::open(&inpx_path_buf)?;
         let reader = BufReader::new(file);
-        parsers::inpx::parse_grouped_streaming(reader, move |folder, records| {
+        parsers::inpx::parse_grouped_streaming(reader, encoding, move |folder, records| {
             let book_path = if inpx_dir_for_parse.is_empty() {
                 folder
             } else {
@@ -75,7 +94,8 @@ pub(super) async fn process_inpx(
                     break;
                 };
 
-                if let Err(e) = process_inpx_zip_group(&ctx, &book_path, zip_records).await {
+                if let Err(e) = process_inpx_zip_group(&ctx, cat_id, &book_path, zip_records).await
+                {
                     warn!("INPX group processing failed for '{}': {}", book_path, e);
                     ctx.stats.errors.fetch_add(1, Ordering::Relaxed);
                 }
@@ -101,9 +121,15 @@ pub(super) async fn process_inpx(
 
 async fn process_inpx_zip_group(
     ctx: &ScanContext,
+    inpx_cat_id: i64,
     book_path: &str,
     zip_records: Vec<parsers::inpx::InpxRecord>,
 ) -> Result<(), ScanError> {
+    // Archives listed by the index are browsed under the collection catalog,
+    // which also keeps that catalog (and its version) from being pruned.
+    let group_cat_id = cached_ensure_catalog(ctx, book_path, CatType::Inpx).await?;
+    catalogs::set_parent(&ctx.pool, group_cat_id, Some(inpx_cat_id)).await?;
+
     let mut pending = Vec::new();
     for record in zip_records {
         if let Some(existing_id) = ctx.existing_book_id(book_path, &record.filename) {
@@ -156,7 +182,9 @@ async fn process_inpx_zip_group(
         let zip_abs_path_for_parse = zip_abs_path.clone();
         let exts = ctx.extensions.clone();
         let test_files = ctx.test_files;
+        let encoding = ctx.zip_encoding;
         let cover_cfg = ctx.cover_image_cfg;
+        let patterns = ctx.filename_patterns.clone();
 
         let parsed_meta = {
             let _permit = acquire_scan_permit(ctx).await?;
@@ -166,7 +194,9 @@ async fn process_inpx_zip_group(
                     &exts,
                     &needed_filenames,
                     test_files,
+                    encoding,
                     cover_cfg,
+                    &patterns,
                 )
             })
             .await
@@ -192,6 +222,8 @@ async fn process_inpx_zip_group(
             if !parsed.annotation.trim().is_empty() {
                 meta.annotation = parsed.annotation;
             }
+            meta.preview_text = parsed.preview_text;
+            meta.word_count = parsed.word_count;
             if let Some(cover_data) = parsed.cover_data {
                 meta.cover_data = Some(cover_data);
                 meta.cover_type = parsed.cover_type;
@@ -232,6 +264,39 @@ async fn process_inpx_zip_group(
     Ok(())
 }
 
+/// Read `collection.info` / `version.info` from an INPX file.
+pub(super) fn read_inpx_info(
+    inpx_path: &Path,
+    encoding: &'static encoding_rs::Encoding,
+) -> Result<parsers::inpx::InpxInfo, ScanError> {
+    let file = fs::File::open(inpx_path)?;
+    parsers::inpx::read_info(BufReader::new(file), encoding)
+        .map_err(|e| ScanError::Internal(e.to_string()))
+}
+
+/// Whether a catalogued INPX archive is unchanged. When both the file and
+/// the catalog carry a `version.info` release, the versions decide;
+/// otherwise size (and mtime with `skip_unchanged`) are compared.
+pub(super) fn inpx_catalog_unchanged(
+    cat: &crate::db::models::Catalog,
+    inpx_size: i64,
+    mtime: &str,
+    version: &str,
+    skip_unchanged: bool,
+) -> bool {
+    if CatType::try_from(cat.cat_type).ok() != Some(CatType::Inpx) {
+        return false;
+    }
+    if !version.is_empty() && !cat.cat_version.is_empty() {
+        return cat.cat_version == version;
+    }
+    if cat.cat_size != inpx_size {
+        return false;
+    }
+    // Like ZIP archives, mtime only counts when both sides have one.
+    !skip_unchanged || mtime.is_empty() || cat.cat_mtime.is_empty() || cat.cat_mtime == mtime
+}
+
 /// Try to skip scanning an unchanged INPX archive.
 async fn try_skip_inpx_archive(
     pool: &DbPool,
@@ -240,14 +305,12 @@ async fn try_skip_inpx_archive(
     inpx_size: i64,
     skip_unchanged: bool,
     mtime: &str,
+    version: &str,
 ) -> Result<bool, ScanError> {
     let Some(cat) = catalogs::find_by_path(pool, rel_inpx).await? else {
         return Ok(false);
     };
-    if CatType::try_from(cat.cat_type).ok() != Some(CatType::Inpx) || cat.cat_size != inpx_size {
-        return Ok(false);
-    }
-    if skip_unchanged && !mtime.is_empty() && !cat.cat_mtime.is_empty() && cat.cat_mtime != mtime {
+    if !inpx_catalog_unchanged(&cat, inpx_size, mtime, version, skip_unchanged) {
         return Ok(false);
     }
     let updated = books::set_avail_for_inpx_dir(pool, inpx_dir, AvailStatus::Confirmed).await?;
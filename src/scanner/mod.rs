@@ -1,42 +1,61 @@
 mod book;
 mod cover;
 mod db;
+mod exclude;
+mod identical;
 mod inpx;
+mod mosaic;
+mod observer;
 pub mod parsers;
+mod preview;
+mod remote;
+mod walk;
 mod zip;
 
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 
 use chrono::Utc;
 use dashmap::{DashMap, DashSet};
 use tokio::sync::{Semaphore, mpsc};
 use tracing::{debug, info, warn};
-use walkdir::WalkDir;
 
 use crate::config::{Config, CoverImageConfig};
 use crate::db::DbPool;
 use crate::db::models::{AvailStatus, CatType};
-use crate::db::queries::{authors, books, catalogs, counters, genres, series};
+use crate::db::queries::{
+    authors, book_audio, book_files, books, catalogs, counters, genres, scan_lock, series,
+};
 
 use book::process_file;
-pub use book::{insert_book_with_meta, parse_book_bytes, parse_book_file};
-use cover::delete_cover;
-pub(crate) use cover::normalize_cover_for_storage_with_options;
+pub use book::{
+    insert_book_with_meta, parse_book_bytes, parse_book_file, replace_book_with_meta, resolve_lang,
+};
 pub use cover::{
-    cover_storage_path, legacy_cover_storage_path, save_cover, two_level_cover_storage_path,
+    StoredCover, catalog_mosaic_path, cover_storage_path, legacy_cover_storage_path, replace_cover,
+    save_cover, two_level_cover_storage_path,
 };
+pub(crate) use cover::{delete_cover, read_cover};
 use db::{
-    build_pending_book_insert, enqueue_pending_book, ensure_archive_catalog,
+    build_pending_book_insert, cached_ensure_catalog, enqueue_pending_book, ensure_archive_catalog,
     run_pending_book_writer,
 };
 pub use db::{ensure_author, ensure_catalog, ensure_series};
+use exclude::Exclusions;
+pub(crate) use exclude::compile as compile_exclude;
 use inpx::process_inpx;
+pub use observer::{ChangeLogObserver, ScanObserver, ScanObservers, ScannedBook};
 use parsers::{BookMeta, detect_lang_code, normalise_author_name};
+pub use preview::{ScanPreview, preview_scan, preview_scan_path};
+use remote::{collect_remote_entries, process_remote_file, process_remote_zip};
+use walk::walk_library;
+pub use walk::{SkipReason, SkippedPath};
 use zip::process_zip;
+pub use zip::{LOW_MEMORY_ENTRY_BUFFER_BYTES, find_zip_entry, zip_entry_name};
 
 // ---------------------------------------------------------------------------
 // Global state
@@ -45,6 +64,12 @@ use zip::process_zip;
 /// Global scan lock — prevents overlapping scans.
 static SCAN_LOCK: AtomicBool = AtomicBool::new(false);
 
+/// Lifetime of the database scan lock without a heartbeat. A lock left by a
+/// crashed instance is taken over once it expires.
+const DB_LOCK_TTL_SECS: i64 = 300;
+/// Interval at which a scanning instance extends the database lock.
+const DB_LOCK_HEARTBEAT: Duration = Duration::from_secs(60);
+
 /// Last completed scan result (taken once by the status endpoint).
 static LAST_SCAN_RESULT: Mutex<Option<ScanResult>> = Mutex::new(None);
 
@@ -82,22 +107,55 @@ pub struct ScanResult {
 #[derive(Debug, Default)]
 pub struct ScanStats {
     pub books_added: AtomicU64,
+    /// Known books whose file changed size and was read again.
+    pub books_updated: AtomicU64,
     pub books_skipped: AtomicU64,
     pub books_deleted: AtomicU64,
     pub archives_scanned: AtomicU64,
     pub archives_skipped: AtomicU64,
     pub errors: AtomicU64,
+    /// Paths the filesystem walk left out.
+    pub paths_skipped: AtomicU64,
+    /// The first [`MAX_REPORTED_SKIPS`] of those, for the scan report.
+    pub skipped_paths: Mutex<Vec<SkippedPath>>,
 }
 
+/// Skipped paths listed in a scan report; the rest are only counted.
+const MAX_REPORTED_SKIPS: usize = 100;
+
 impl ScanStats {
     pub fn snapshot(&self) -> ScanStatsSnapshot {
         ScanStatsSnapshot {
             books_added: self.books_added.load(Ordering::Relaxed),
+            books_updated: self.books_updated.load(Ordering::Relaxed),
             books_skipped: self.books_skipped.load(Ordering::Relaxed),
             books_deleted: self.books_deleted.load(Ordering::Relaxed),
             archives_scanned: self.archives_scanned.load(Ordering::Relaxed),
             archives_skipped: self.archives_skipped.load(Ordering::Relaxed),
             errors: self.errors.load(Ordering::Relaxed),
+            paths_skipped: self.paths_skipped.load(Ordering::Relaxed),
+            skipped_paths: self
+                .skipped_paths
+                .lock()
+                .map(|list| list.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Count a path the walk left out. An unreadable path also counts as an
+    /// error, so books that may still be there are not deleted.
+    fn record_skip(&self, skipped: SkippedPath) {
+        if skipped.reason == SkipReason::Unreadable {
+            warn!("Cannot read {}: {}", skipped.path, skipped.detail);
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        } else {
+            debug!("Skipping {} ({:?})", skipped.path, skipped.reason);
+        }
+        self.paths_skipped.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut list) = self.skipped_paths.lock()
+            && list.len() < MAX_REPORTED_SKIPS
+        {
+            list.push(skipped);
         }
     }
 }
@@ -106,11 +164,14 @@ impl ScanStats {
 #[derive(Debug, Default, Clone, serde::Serialize)]
 pub struct ScanStatsSnapshot {
     pub books_added: u64,
+    pub books_updated: u64,
     pub books_skipped: u64,
     pub books_deleted: u64,
     pub archives_scanned: u64,
     pub archives_skipped: u64,
     pub errors: u64,
+    pub paths_skipped: u64,
+    pub skipped_paths: Vec<SkippedPath>,
 }
 
 // ---------------------------------------------------------------------------
@@ -119,6 +180,74 @@ pub struct ScanStatsSnapshot {
 
 /// Run a full scan of the library directory.
 pub async fn run_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, ScanError> {
+    run_scoped_scan(pool, config, None, &ScanObservers::default()).await
+}
+
+/// Scan only `path` (relative to the library root) and its subfolders.
+/// Only books stored under that path are marked unverified and cleaned up
+/// afterwards. An empty path scans the whole library.
+pub async fn run_scan_path(
+    pool: &DbPool,
+    config: &Config,
+    path: &str,
+) -> Result<ScanStatsSnapshot, ScanError> {
+    run_scan_path_observed(pool, config, path, &ScanObservers::default()).await
+}
+
+/// [`run_scan_path`] reporting each book change to `observers`.
+pub async fn run_scan_path_observed(
+    pool: &DbPool,
+    config: &Config,
+    path: &str,
+    observers: &ScanObservers,
+) -> Result<ScanStatsSnapshot, ScanError> {
+    let scope = resolve_scan_path(&config.library.root_path, path)?;
+    run_scoped_scan(pool, config, scope.as_deref(), observers).await
+}
+
+/// Normalise a scan path relative to `root`: surrounding slashes are
+/// dropped, and the result must name an existing directory inside the
+/// library. Returns `None` for an empty path (whole library).
+pub fn resolve_scan_path(root: &Path, path: &str) -> Result<Option<String>, ScanError> {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    let relative = Path::new(trimmed);
+    let plain = relative
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)));
+    if !plain || (!crate::vfs::is_remote(root) && !crate::vfs::local_path(root, trimmed).is_dir()) {
+        return Err(ScanError::InvalidPath(trimmed.to_string()));
+    }
+    Ok(Some(relative.to_string_lossy().to_string()))
+}
+
+/// Whether a scan runs in this process or in another instance sharing the
+/// database.
+pub async fn scan_in_progress(pool: &DbPool) -> bool {
+    is_scanning()
+        || scan_lock::current_holder(pool, Utc::now().timestamp())
+            .await
+            .is_ok_and(|holder| holder.is_some())
+}
+
+/// Name of this process in the database scan lock.
+fn lock_holder() -> &'static str {
+    static HOLDER: OnceLock<String> = OnceLock::new();
+    HOLDER.get_or_init(|| {
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let started = Utc::now().timestamp_micros();
+        format!("{host}:{}:{started}", std::process::id())
+    })
+}
+
+async fn run_scoped_scan(
+    pool: &DbPool,
+    config: &Config,
+    scope: Option<&str>,
+    observers: &ScanObservers,
+) -> Result<ScanStatsSnapshot, ScanError> {
     // Acquire scan lock
     if SCAN_LOCK
         .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
@@ -127,7 +256,7 @@ pub async fn run_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapsho
         return Err(ScanError::AlreadyRunning);
     }
 
-    let result = do_scan(pool, config).await;
+    let result = run_with_db_lock(pool, config, scope, observers).await;
 
     // Release lock
     SCAN_LOCK.store(false, Ordering::SeqCst);
@@ -135,10 +264,69 @@ pub async fn run_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapsho
     result
 }
 
+/// Run the scan while holding the database scan lock, so that instances
+/// sharing the database never scan at the same time.
+async fn run_with_db_lock(
+    pool: &DbPool,
+    config: &Config,
+    scope: Option<&str>,
+    observers: &ScanObservers,
+) -> Result<ScanStatsSnapshot, ScanError> {
+    let holder = lock_holder();
+    let now = Utc::now().timestamp();
+    if !scan_lock::try_acquire(pool, holder, now, now + DB_LOCK_TTL_SECS).await? {
+        if let Ok(Some(other)) = scan_lock::current_holder(pool, now).await {
+            info!("Scan skipped: {other} is scanning the library");
+        }
+        return Err(ScanError::AlreadyRunning);
+    }
+
+    // Aborted on drop, so that a panicking scan stops extending the lock.
+    struct Heartbeat(tokio::task::JoinHandle<()>);
+    impl Drop for Heartbeat {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+    let lock_lost = Arc::new(AtomicBool::new(false));
+    let heartbeat_lost = Arc::clone(&lock_lost);
+    let heartbeat_pool = pool.clone();
+    let heartbeat = Heartbeat(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DB_LOCK_HEARTBEAT);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let expires_at = Utc::now().timestamp() + DB_LOCK_TTL_SECS;
+            match scan_lock::refresh(&heartbeat_pool, holder, expires_at).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!("Scan lock was taken over by another instance, stopping the scan");
+                    heartbeat_lost.store(true, Ordering::SeqCst);
+                    break;
+                }
+                Err(e) => warn!("Failed to extend scan lock: {e}"),
+            }
+        }
+    }));
+
+    let result = do_scan(pool, config, scope, observers, lock_lost).await;
+
+    drop(heartbeat);
+    if let Err(e) = scan_lock::release(pool, holder).await {
+        warn!("Failed to release scan lock: {e}");
+    }
+    result
+}
+
 // ---------------------------------------------------------------------------
 // ScanContext — shared state for (parallel) scan workers
 // ---------------------------------------------------------------------------
 
+/// Parsed books queued for the DB writer, per scan worker.
+const PENDING_QUEUE_PER_WORKER: usize = 128;
+/// Pending-book queue length of the low-memory profile.
+const LOW_MEMORY_PENDING_QUEUE: usize = 8;
+
 struct ScanContext {
     pool: DbPool,
     root: PathBuf,
@@ -147,28 +335,66 @@ struct ScanContext {
     workers_num: usize,
     concurrency_semaphore: Arc<Semaphore>,
     extensions: HashSet<String>,
+    /// Decoding for ZIP entry names and INPX text that are not UTF-8
+    /// (`library.zip_codepage`).
+    zip_encoding: &'static encoding_rs::Encoding,
+    /// Templates for books without embedded metadata (`library.filename_patterns`).
+    filename_patterns: Arc<[parsers::filename::FilenamePattern]>,
+    /// Minimum confidence for guessing missing languages; `None` when off.
+    lang_detect: Option<f32>,
     stats: Arc<ScanStats>,
+    observers: ScanObservers,
     // Config flags
     skip_unchanged: bool,
     test_zip: bool,
     test_files: bool,
+    /// `server.profile = "low-memory"`: parse ZIP entries one at a time and
+    /// keep the insert queue short.
+    low_memory: bool,
+    /// Client of a remote (WebDAV or SFTP) library; `None` for a local directory.
+    remote: Option<Arc<crate::vfs::RemoteFs>>,
     // Caches (reduces DB round-trips under parallelism)
     catalog_cache: DashMap<String, i64>,
     author_cache: DashMap<String, i64>,
     genre_cache: DashMap<String, Option<i64>>,
     series_cache: DashMap<String, i64>,
-    existing_books_by_path: HashMap<String, HashMap<String, i64>>,
+    /// Known books by path and filename, with their id and recorded file size.
+    existing_books_by_path: HashMap<String, HashMap<String, KnownBook>>,
     confirmed_existing_ids: DashSet<i64>,
     pending_new_books: DashSet<String>,
+    /// `scanner.link_identical`: register identical files as copies.
+    link_identical: bool,
+    /// Known copies of books by path and filename, with their id and size.
+    existing_copies: HashMap<String, HashMap<String, (i64, i64)>>,
+    confirmed_copy_ids: DashSet<i64>,
+    /// `size:hash` of new files queued in this scan.
+    pending_hashes: DashSet<String>,
+    deferred_copies: Mutex<Vec<identical::DeferredCopy>>,
+    files_linked: AtomicU64,
     pending_book_tx: mpsc::Sender<PendingBookMsg>,
+    /// Set once another instance took the database scan lock over; workers
+    /// skip their remaining entries and nothing more is written.
+    lock_lost: Arc<AtomicBool>,
 }
 
 impl ScanContext {
+    /// Fails with [`ScanError::LockLost`] once the heartbeat lost the lock.
+    fn ensure_lock_held(&self) -> Result<(), ScanError> {
+        if self.lock_lost.load(Ordering::SeqCst) {
+            return Err(ScanError::LockLost);
+        }
+        Ok(())
+    }
+
     fn existing_book_id(&self, path: &str, filename: &str) -> Option<i64> {
+        self.existing_book(path, filename).map(|known| known.id)
+    }
+
+    /// A book already in the database.
+    fn existing_book(&self, path: &str, filename: &str) -> Option<&KnownBook> {
         self.existing_books_by_path
             .get(path)
             .and_then(|by_name| by_name.get(filename))
-            .copied()
     }
 
     fn mark_existing_book_confirmed(&self, book_id: i64) {
@@ -187,6 +413,16 @@ impl ScanContext {
     }
 }
 
+/// A book already in the database when the scan started.
+struct KnownBook {
+    id: i64,
+    size: i64,
+    /// When the file was recorded: the later of `reg_date` and `last_modified`.
+    recorded: String,
+    /// Content hash, empty unless `scanner.link_identical` recorded one.
+    file_hash: String,
+}
+
 struct PendingBookInsert {
     catalog_id: i64,
     filename: String,
@@ -197,6 +433,11 @@ struct PendingBookInsert {
     title: String,
     search_title: String,
     annotation: String,
+    preview_text: String,
+    word_count: i32,
+    page_count: i32,
+    /// Content hash of a plain file (`scanner.link_identical`), else empty.
+    file_hash: String,
     docdate: String,
     lang: String,
     lang_code: i32,
@@ -206,6 +447,8 @@ struct PendingBookInsert {
     genre_ids: Vec<i64>,
     series_link: Option<(i64, i32)>,
     author_key: String,
+    /// Audiobook running time and narrators.
+    audio: Option<(i64, String)>,
 }
 
 enum PendingBookMsg {
@@ -217,7 +460,13 @@ enum PendingBookMsg {
 // do_scan — internal scan logic
 // ---------------------------------------------------------------------------
 
-async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, ScanError> {
+async fn do_scan(
+    pool: &DbPool,
+    config: &Config,
+    scope: Option<&str>,
+    observers: &ScanObservers,
+    lock_lost: Arc<AtomicBool>,
+) -> Result<ScanStatsSnapshot, ScanError> {
     let root = &config.library.root_path;
     let covers_path = &config.covers.covers_path;
     let extensions: HashSet<String> = config
@@ -228,38 +477,107 @@ async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, Sc
         .collect();
     let scan_zip = config.library.scan_zip;
     let inpx_enable = config.library.inpx_enable;
-    let workers_num = config.scanner.workers_num;
+    let audiobooks = config.library.audiobooks;
+    let low_memory = config.low_memory();
+    let workers_num = if low_memory {
+        1
+    } else {
+        config.scanner.workers_num
+    };
 
-    info!("Starting library scan: {}", root.display());
+    let remote = crate::vfs::remote(root)?;
+
+    let walk_from = match scope {
+        Some(path) => crate::vfs::local_path(root, path),
+        None => root.clone(),
+    };
+    info!(
+        "Starting library scan: {}",
+        crate::vfs::display_root(&walk_from)
+    );
 
     let stats = Arc::new(ScanStats::default());
     let existing_books = books::list_existing_for_scan(pool).await?;
-    let mut existing_books_by_path: HashMap<String, HashMap<String, i64>> = HashMap::new();
+    let mut existing_books_by_path: HashMap<String, HashMap<String, KnownBook>> = HashMap::new();
     for row in existing_books {
-        existing_books_by_path
-            .entry(row.path)
+        existing_books_by_path.entry(row.path).or_default().insert(
+            row.filename,
+            KnownBook {
+                id: row.id,
+                size: row.size,
+                recorded: row.recorded,
+                file_hash: row.file_hash,
+            },
+        );
+    }
+    let mut existing_copies: HashMap<String, HashMap<String, (i64, i64)>> = HashMap::new();
+    for copy in book_files::list_for_scan(pool).await? {
+        existing_copies
+            .entry(copy.path)
             .or_default()
-            .insert(row.filename, row.id);
+            .insert(copy.filename, (copy.id, copy.size));
     }
 
-    // Step 1: Mark all available books as unverified (avail=1)
-    let marked = books::set_avail_all(pool, AvailStatus::Unverified).await?;
+    // Step 1: Mark all available books (under the scanned path) as unverified (avail=1)
+    let marked = match scope {
+        Some(path) => {
+            pool.retry(|| books::set_avail_under_path(pool, path, AvailStatus::Unverified))
+                .await?
+        }
+        None => {
+            pool.retry(|| books::set_avail_all(pool, AvailStatus::Unverified))
+                .await?
+        }
+    };
     info!("Marked {marked} books as unverified");
 
-    // Step 2: Walk filesystem
-    let root_path = root.clone();
-    let extensions_clone = extensions.clone();
-    let walk_result = tokio::task::spawn_blocking(move || {
-        collect_entries(&root_path, &extensions_clone, scan_zip, inpx_enable)
-    })
-    .await
-    .map_err(|e| ScanError::Internal(e.to_string()))?;
-
-    let entries = walk_result?;
+    // Step 2: Walk filesystem (or list the remote library)
+    let exclusions = Exclusions::load(pool, config).await?;
+    let entries = match &remote {
+        Some(fs) => {
+            collect_remote_entries(
+                fs,
+                scope.unwrap_or_default(),
+                &extensions,
+                &exclusions,
+                scan_zip,
+                inpx_enable,
+                audiobooks,
+            )
+            .await?
+        }
+        None => {
+            let root_path = root.clone();
+            let extensions_clone = extensions.clone();
+            let walk_stats = Arc::clone(&stats);
+            let max_depth = config.scanner.max_depth;
+            tokio::task::spawn_blocking(move || {
+                collect_entries(
+                    &root_path,
+                    &walk_from,
+                    &extensions_clone,
+                    &exclusions,
+                    max_depth,
+                    &walk_stats,
+                    scan_zip,
+                    inpx_enable,
+                    audiobooks,
+                )
+            })
+            .await
+            .map_err(|e| ScanError::Internal(e.to_string()))??
+        }
+    };
     info!("Found {} entries to process", entries.len());
-    let (pending_book_tx, pending_book_rx) =
-        mpsc::channel::<PendingBookMsg>(workers_num.max(1) * 128);
+    let queue_len = if low_memory {
+        LOW_MEMORY_PENDING_QUEUE
+    } else {
+        workers_num.max(1) * PENDING_QUEUE_PER_WORKER
+    };
+    let (pending_book_tx, pending_book_rx) = mpsc::channel::<PendingBookMsg>(queue_len);
 
+    // Only local plain files are hashed; see `identical`.
+    let link_identical = config.scanner.link_identical && remote.is_none();
     let ctx = ScanContext {
         pool: pool.clone(),
         root: root.clone(),
@@ -268,10 +586,17 @@ async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, Sc
         workers_num,
         concurrency_semaphore: Arc::new(Semaphore::new(workers_num.max(1))),
         extensions,
+        zip_encoding: codepage_encoding(&config.library.zip_codepage),
+        filename_patterns: parsers::filename::compile_patterns(&config.library.filename_patterns)
+            .into(),
+        lang_detect: config.library.lang_detect_threshold(),
         stats: Arc::clone(&stats),
+        observers: observers.clone(),
         skip_unchanged: config.scanner.skip_unchanged,
         test_zip: config.scanner.test_zip,
         test_files: config.scanner.test_files,
+        low_memory,
+        remote,
         catalog_cache: DashMap::new(),
         author_cache: DashMap::new(),
         genre_cache: DashMap::new(),
@@ -279,8 +604,16 @@ async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, Sc
         existing_books_by_path,
         confirmed_existing_ids: DashSet::new(),
         pending_new_books: DashSet::new(),
+        link_identical,
+        existing_copies,
+        confirmed_copy_ids: DashSet::new(),
+        pending_hashes: DashSet::new(),
+        deferred_copies: Mutex::new(Vec::new()),
+        files_linked: AtomicU64::new(0),
         pending_book_tx,
+        lock_lost,
     };
+    ctx.ensure_lock_held()?;
 
     let ctx = Arc::new(ctx);
     let writer_ctx = Arc::clone(&ctx);
@@ -290,6 +623,9 @@ async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, Sc
     if workers_num <= 1 {
         // Sequential processing (default)
         for entry in entries {
+            if ctx.ensure_lock_held().is_err() {
+                break;
+            }
             process_entry(Arc::clone(&ctx), entry).await;
         }
     } else {
@@ -314,6 +650,9 @@ async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, Sc
                 warn!("Top-level scan worker join failure: {e}");
                 ctx.stats.errors.fetch_add(1, Ordering::Relaxed);
             }
+            if ctx.ensure_lock_held().is_err() {
+                continue;
+            }
             if let Some(entry) = iter.next() {
                 let ctx = Arc::clone(&ctx);
                 tasks.spawn(async move {
@@ -339,36 +678,58 @@ async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, Sc
         }
     }
 
+    // Everything below rewrites availability and deletes books, which is
+    // only safe while this instance still owns the scan.
+    ctx.ensure_lock_held()?;
+
     let mut confirmed_existing_ids: Vec<i64> =
         ctx.confirmed_existing_ids.iter().map(|id| *id).collect();
     confirmed_existing_ids.sort_unstable();
-    let confirmed_updated =
-        books::set_avail_confirmed_for_ids(pool, &confirmed_existing_ids).await?;
+    let confirmed_updated = pool
+        .retry(|| books::set_avail_confirmed_for_ids(pool, &confirmed_existing_ids))
+        .await?;
     debug!(
         "Confirmed existing books by id: requested={}, updated={}",
         confirmed_existing_ids.len(),
         confirmed_updated
     );
 
+    identical::link_deferred_copies(&ctx).await?;
+    ctx.ensure_lock_held()?;
+
     // Step 3: Handle books not found during scan (avail <= 1)
     let scan_errors = stats.errors.load(Ordering::Relaxed);
+    if scan_errors == 0 {
+        identical::reconcile_copies(&ctx, scope).await?;
+    }
     if scan_errors > 0 {
         warn!(
             "Skipping deletion step: {scan_errors} error(s) occurred during scan, \
              some books may have been left unverified due to worker failures"
         );
     } else if config.scanner.delete_logical {
-        let deleted = books::logical_delete_unavailable(pool).await?;
+        let ids = if observers.is_empty() {
+            Vec::new()
+        } else {
+            books::get_unavailable_ids(pool, scope).await?
+        };
+        let deleted = pool
+            .retry(|| books::logical_delete_unavailable(pool, scope))
+            .await?;
         stats.books_deleted.store(deleted, Ordering::Relaxed);
+        ids.iter().for_each(|id| observers.book_deleted(*id));
         info!("Logically deleted {deleted} unavailable books");
     } else {
         // Get IDs before deletion so we can remove cover files
-        let ids = books::get_unavailable_ids(pool).await?;
-        let deleted = books::physical_delete_unavailable(pool).await?;
+        let ids = books::get_unavailable_ids(pool, scope).await?;
+        let deleted = pool
+            .retry(|| books::physical_delete_unavailable(pool, scope))
+            .await?;
         stats.books_deleted.store(deleted, Ordering::Relaxed);
         // Remove cover files from disk
         for id in &ids {
             delete_cover(covers_path, *id);
+            observers.book_deleted(*id);
         }
         info!(
             "Physically deleted {deleted} unavailable books, removed {} covers",
@@ -377,24 +738,53 @@ async fn do_scan(pool: &DbPool, config: &Config) -> Result<ScanStatsSnapshot, Sc
     }
 
     // Step 4: Remove empty catalogs (left after book deletion)
-    let cats_deleted = catalogs::delete_empty(pool).await?;
+    ctx.ensure_lock_held()?;
+    let cats_deleted = pool.retry(|| catalogs::delete_empty(pool)).await?;
     if cats_deleted > 0 {
         info!("Removed {cats_deleted} empty catalogs");
     }
 
     // Step 5: Update counters
-    counters::update_all(pool).await?;
+    pool.retry(|| counters::update_all(pool)).await?;
+
+    let library_changed = stats.books_added.load(Ordering::Relaxed) > 0
+        || stats.books_updated.load(Ordering::Relaxed) > 0
+        || stats.books_deleted.load(Ordering::Relaxed) > 0;
+    if library_changed {
+        counters::bump_library_version(pool).await?;
+    }
+
+    // Step 6: Rebuild catalog cover mosaics when the library changed
+    let changed = library_changed || !covers_path.join(cover::CATALOG_MOSAIC_DIR).exists();
+    if config.covers.show_covers && changed {
+        match mosaic::rebuild_catalog_mosaics(pool, config).await {
+            Ok(written) => info!("Rebuilt {written} catalog cover mosaics"),
+            Err(e) => warn!("Failed to rebuild catalog cover mosaics: {e}"),
+        }
+    }
+
+    // Step 7: Index the text of new and changed books
+    if config.search.fulltext {
+        match crate::fulltext::index_pending(pool, config).await {
+            Ok(0) => {}
+            Ok(indexed) => info!("Indexed the text of {indexed} books"),
+            Err(e) => warn!("Failed to index book text: {e}"),
+        }
+    }
 
     let snap = stats.snapshot();
     info!(
-        "Scan complete: added={}, skipped={}, deleted={}, archives_scanned={}, archives_skipped={}, errors={}",
+        "Scan complete: added={}, updated={}, skipped={}, deleted={}, archives_scanned={}, archives_skipped={}, errors={}, paths_skipped={}",
         snap.books_added,
+        snap.books_updated,
         snap.books_skipped,
         snap.books_deleted,
         snap.archives_scanned,
         snap.archives_skipped,
-        snap.errors
+        snap.errors,
+        snap.paths_skipped
     );
+    observers.scan_finished(&snap);
 
     Ok(snap)
 }
@@ -421,6 +811,20 @@ enum ScanEntry {
         rel_path: String,
         mtime: String,
     },
+    /// A book file of a remote library, fetched only when new.
+    RemoteFile {
+        rel_path: String,
+        filename: String,
+        extension: String,
+        size: i64,
+    },
+    /// A ZIP archive of a remote library, fetched only when changed.
+    RemoteZip {
+        rel_path: String,
+        filename: String,
+        size: i64,
+        mtime: String,
+    },
 }
 
 /// Get file modification time as RFC 3339 string.
@@ -439,74 +843,133 @@ fn file_mtime(path: &Path) -> String {
 // Filesystem walk
 // ---------------------------------------------------------------------------
 
-/// Walk the filesystem and collect all entries to process.
+/// Walk the filesystem below `walk_from` and collect all entries to process.
+/// Relative paths are computed against the library `root`; excluded folders
+/// are not descended into and skipped paths are recorded in `stats`.
+#[allow(clippy::too_many_arguments)]
 fn collect_entries(
     root: &Path,
+    walk_from: &Path,
     extensions: &HashSet<String>,
+    exclusions: &Exclusions,
+    max_depth: usize,
+    stats: &ScanStats,
     scan_zip: bool,
     inpx_enable: bool,
+    audiobooks: bool,
 ) -> Result<Vec<ScanEntry>, ScanError> {
     let mut entries = Vec::new();
     let mut inpx_dirs: HashSet<PathBuf> = HashSet::new();
+    let mut audio_dirs: HashSet<PathBuf> = HashSet::new();
+
+    // First pass: find directories containing INPX files and audiobook
+    // folders (MP3 tracks next to a metadata file, scanned as one book)
+    if inpx_enable || audiobooks {
+        // Skipped paths are reported by the second pass.
+        let mut ignore_skip = |_: SkippedPath| {};
+        walk_library(
+            root,
+            walk_from,
+            exclusions,
+            max_depth,
+            &mut ignore_skip,
+            |entry| {
+                if audiobooks
+                    && entry.file_type().is_file()
+                    && entry
+                        .file_name()
+                        .to_string_lossy()
+                        .eq_ignore_ascii_case(crate::audio::FOLDER_METADATA_FILE)
+                    && let Some(dir) = entry.path().parent()
+                    && dir != root
+                    && let Ok(tracks) = crate::audio::list_tracks(dir)
+                    && !tracks.is_empty()
+                {
+                    let size = tracks
+                        .iter()
+                        .filter_map(|t| t.metadata().ok())
+                        .map(|m| m.len() as i64)
+                        .sum();
+                    audio_dirs.insert(dir.to_path_buf());
+                    entries.push(ScanEntry::File {
+                        path: dir.to_path_buf(),
+                        rel_path: rel_path(root, dir.parent().unwrap_or(root)),
+                        filename: crate::vfs::decode_name(dir.file_name().unwrap_or_default()),
+                        extension: "mp3".to_string(),
+                        size,
+                    });
+                    return;
+                }
+                if inpx_enable
+                    && entry.file_type().is_file()
+                    && let Some(ext) = entry.path().extension()
+                    && ext.to_string_lossy().eq_ignore_ascii_case("inpx")
+                {
+                    if let Some(parent) = entry.path().parent() {
+                        inpx_dirs.insert(parent.to_path_buf());
+                    }
+                    let rel = rel_path(root, entry.path());
+                    let mtime = file_mtime(entry.path());
+                    entries.push(ScanEntry::Inpx {
+                        path: entry.path().to_path_buf(),
+                        rel_path: rel,
+                        mtime,
+                    });
+                }
+            },
+        );
+    }
 
-    // First pass: find directories containing INPX files
-    if inpx_enable {
-        for entry in WalkDir::new(root).follow_links(true).into_iter().flatten() {
-            if entry.file_type().is_file()
-                && let Some(ext) = entry.path().extension()
-                && ext.to_string_lossy().eq_ignore_ascii_case("inpx")
+    // Second pass: collect regular files and ZIPs (skip INPX directories)
+    let mut record_skip = |skipped: SkippedPath| stats.record_skip(skipped);
+    walk_library(
+        root,
+        walk_from,
+        exclusions,
+        max_depth,
+        &mut record_skip,
+        |entry| {
+            if !entry.file_type().is_file() {
+                return;
+            }
+            if let Some(parent) = entry.path().parent()
+                && inpx_dirs.contains(parent)
             {
-                if let Some(parent) = entry.path().parent() {
-                    inpx_dirs.insert(parent.to_path_buf());
-                }
-                let rel = rel_path(root, entry.path());
+                return; // Skip files in INPX directories
+            }
+            if let Some(parent) = entry.path().parent()
+                && audio_dirs.contains(parent)
+            {
+                return; // Tracks of an audiobook folder
+            }
+
+            let ext = match entry.path().extension() {
+                Some(e) => e.to_string_lossy().to_lowercase(),
+                None => return,
+            };
+
+            if ext == "zip" && scan_zip {
+                let rel = rel_path(root, entry.path().parent().unwrap_or(entry.path()));
                 let mtime = file_mtime(entry.path());
-                entries.push(ScanEntry::Inpx {
+                entries.push(ScanEntry::Zip {
                     path: entry.path().to_path_buf(),
                     rel_path: rel,
                     mtime,
                 });
+            } else if extensions.contains(&ext) || (audiobooks && ext == "m4b") {
+                let filename = crate::vfs::decode_name(entry.file_name());
+                let rel = rel_path(root, entry.path().parent().unwrap_or(entry.path()));
+                let size = entry.metadata().map(|m| m.len() as i64).unwrap_or(0);
+                entries.push(ScanEntry::File {
+                    path: entry.path().to_path_buf(),
+                    rel_path: rel,
+                    filename,
+                    extension: ext,
+                    size,
+                });
             }
-        }
-    }
-
-    // Second pass: collect regular files and ZIPs (skip INPX directories)
-    for entry in WalkDir::new(root).follow_links(true).into_iter().flatten() {
-        if !entry.file_type().is_file() {
-            continue;
-        }
-        if let Some(parent) = entry.path().parent()
-            && inpx_dirs.contains(parent)
-        {
-            continue; // Skip files in INPX directories
-        }
-
-        let ext = match entry.path().extension() {
-            Some(e) => e.to_string_lossy().to_lowercase(),
-            None => continue,
-        };
-
-        if ext == "zip" && scan_zip {
-            let rel = rel_path(root, entry.path().parent().unwrap_or(entry.path()));
-            let mtime = file_mtime(entry.path());
-            entries.push(ScanEntry::Zip {
-                path: entry.path().to_path_buf(),
-                rel_path: rel,
-                mtime,
-            });
-        } else if extensions.contains(&ext) {
-            let filename = entry.file_name().to_string_lossy().to_string();
-            let rel = rel_path(root, entry.path().parent().unwrap_or(entry.path()));
-            let size = entry.metadata().map(|m| m.len() as i64).unwrap_or(0);
-            entries.push(ScanEntry::File {
-                path: entry.path().to_path_buf(),
-                rel_path: rel,
-                filename,
-                extension: ext,
-                size,
-            });
-        }
-    }
+        },
+    );
 
     Ok(entries)
 }
@@ -517,6 +980,9 @@ fn collect_entries(
 
 /// Dispatch a single scan entry to the appropriate handler.
 async fn process_entry(ctx: Arc<ScanContext>, entry: ScanEntry) {
+    if ctx.ensure_lock_held().is_err() {
+        return;
+    }
     match entry {
         ScanEntry::File {
             path,
@@ -551,6 +1017,37 @@ async fn process_entry(ctx: Arc<ScanContext>, entry: ScanEntry) {
                 ctx.stats.errors.fetch_add(1, Ordering::Relaxed);
             }
         }
+        ScanEntry::RemoteFile {
+            rel_path,
+            filename,
+            extension,
+            size,
+        } => {
+            let Some(fs) = ctx.remote.clone() else {
+                return;
+            };
+            if let Err(e) =
+                process_remote_file(&ctx, &fs, &rel_path, &filename, &extension, size).await
+            {
+                debug!("Error processing remote {rel_path}/{filename}: {e}");
+                ctx.stats.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        ScanEntry::RemoteZip {
+            rel_path,
+            filename,
+            size,
+            mtime,
+        } => {
+            let Some(fs) = ctx.remote.clone() else {
+                return;
+            };
+            if let Err(e) = process_remote_zip(&ctx, &fs, &rel_path, &filename, size, &mtime).await
+            {
+                debug!("Error processing remote ZIP {rel_path}/{filename}: {e}");
+                ctx.stats.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
     }
 }
 
@@ -568,11 +1065,17 @@ async fn acquire_scan_permit(
 // Covers & utilities
 // ---------------------------------------------------------------------------
 
+/// Encoding for a `library.zip_codepage` label (`cp866`, `cp1251`, ...),
+/// falling back to UTF-8 for unknown labels.
+pub fn codepage_encoding(label: &str) -> &'static encoding_rs::Encoding {
+    encoding_rs::Encoding::for_label(label.trim().as_bytes()).unwrap_or_else(|| {
+        warn!("Unknown zip_codepage '{label}', decoding as UTF-8");
+        encoding_rs::UTF_8
+    })
+}
+
 fn rel_path(root: &Path, path: &Path) -> String {
-    path.strip_prefix(root)
-        .unwrap_or(path)
-        .to_string_lossy()
-        .to_string()
+    crate::vfs::decode_path(path.strip_prefix(root).unwrap_or(path))
 }
 
 // ---------------------------------------------------------------------------
@@ -583,6 +1086,10 @@ fn rel_path(root: &Path, path: &Path) -> String {
 pub enum ScanError {
     #[error("scan already running")]
     AlreadyRunning,
+    #[error("scan lock taken over by another instance")]
+    LockLost,
+    #[error("invalid scan path: {0}")]
+    InvalidPath(String),
     #[error("database error: {0}")]
     Db(#[from] sqlx::Error),
     #[error("I/O error: {0}")]
@@ -591,6 +1098,8 @@ pub enum ScanError {
     Zip(#[from] ::zip::result::ZipError),
     #[error("parse error: {0}")]
     Parse(String),
+    #[error("remote library: {0}")]
+    Remote(#[from] crate::vfs::VfsError),
     #[error("internal error: {0}")]
     Internal(String),
 }
@@ -599,8 +1108,7 @@ pub enum ScanError {
 mod tests {
     use super::*;
     use crate::db::create_test_pool;
-    use image::{DynamicImage, GenericImageView};
-    use std::io::{Cursor, Write};
+    use std::io::Write;
     use tempfile::tempdir;
 
     fn test_cover_cfg() -> CoverImageConfig {
@@ -633,7 +1141,8 @@ mod tests {
 
     #[test]
     fn test_parse_book_bytes_fallback_for_unknown_ext() {
-        let meta = parse_book_bytes(b"ignored", "txt", "my-file.txt", test_cover_cfg()).unwrap();
+        let meta =
+            parse_book_bytes(b"ignored", "doc", "my-file.doc", test_cover_cfg(), &[]).unwrap();
         assert_eq!(meta.title, "my-file");
     }
 
@@ -642,10 +1151,81 @@ mod tests {
         let dir = tempdir().unwrap();
         let path = dir.path().join("book.unknown");
         fs::write(&path, b"data").unwrap();
-        let meta = parse_book_file(&path, "unknown", test_cover_cfg()).unwrap();
+        let meta = parse_book_file(&path, "unknown", test_cover_cfg(), &[]).unwrap();
         assert_eq!(meta.title, "book");
     }
 
+    #[test]
+    fn test_parse_book_fallback_uses_filename_patterns() {
+        let patterns = parsers::filename::compile_patterns(&[
+            "{author} - {series} #{ser_no} - {title}".to_string(),
+        ]);
+        let meta = parse_book_bytes(
+            b"ignored",
+            "txt",
+            "Frank Herbert - Dune #1 - Dune.txt",
+            test_cover_cfg(),
+            &patterns,
+        )
+        .unwrap();
+        assert_eq!(meta.title, "Dune");
+        assert_eq!(meta.authors, vec!["Frank Herbert"]);
+        assert_eq!(meta.series_title.as_deref(), Some("Dune"));
+        assert_eq!(meta.series_index, 1);
+
+        let meta =
+            parse_book_bytes(b"ignored", "doc", "notes.doc", test_cover_cfg(), &patterns).unwrap();
+        assert_eq!(meta.title, "notes");
+        assert!(meta.authors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_text_books() {
+        let meta = parse_book_bytes(
+            "\n  Solaris\n\nThe ship arrived at the station.\n".as_bytes(),
+            "txt",
+            "book.txt",
+            test_cover_cfg(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(meta.title, "Solaris");
+        assert_eq!(meta.annotation, "The ship arrived at the station.");
+
+        let meta = parse_book_bytes(b"", "txt", "empty.txt", test_cover_cfg(), &[]).unwrap();
+        assert_eq!(meta.title, "empty");
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("story.rtf");
+        fs::write(
+            &path,
+            br"{\rtf1\ansi{\fonttbl{\f0 Arial;}}Eden\par Six men landed.\par}",
+        )
+        .unwrap();
+        let meta = parse_book_file(&path, "rtf", test_cover_cfg(), &[]).unwrap();
+        assert_eq!(meta.title, "Eden");
+        assert_eq!(meta.annotation, "Six men landed.");
+    }
+
+    #[test]
+    fn test_resolve_lang_guesses_from_preview_only_when_missing() {
+        let mut meta = parsers::BookMeta {
+            preview_text: "Он сказал, что она не придёт. Это было всё, что он знал о ней."
+                .to_string(),
+            ..Default::default()
+        };
+        assert_eq!(resolve_lang(&meta, Some(0.3)), "ru");
+        assert_eq!(resolve_lang(&meta, Some(1.0)), "");
+        assert_eq!(resolve_lang(&meta, None), "");
+
+        meta.lang = "uk".to_string();
+        assert_eq!(resolve_lang(&meta, Some(0.3)), "uk");
+
+        meta.lang.clear();
+        meta.preview_text = "Foundation".to_string();
+        assert_eq!(resolve_lang(&meta, Some(0.0)), "");
+    }
+
     #[test]
     fn test_read_zip_entries_and_validate_integrity() {
         let dir = tempdir().unwrap();
@@ -663,13 +1243,55 @@ mod tests {
         exts.insert("fb2".to_string());
         exts.insert("epub".to_string());
 
-        let entries = zip::read_zip_entries(&zip_path, &exts, false).unwrap();
+        let entries = zip::read_zip_entries(&zip_path, &exts, false, encoding_rs::UTF_8).unwrap();
         assert_eq!(entries.len(), 2);
         assert!(entries.iter().any(|e| e.filename == "a.fb2"));
         assert!(entries.iter().any(|e| e.filename == "c.epub"));
         assert!(zip::validate_zip_integrity(&zip_path).unwrap());
     }
 
+    #[test]
+    fn test_zip_entry_names_use_codepage() {
+        let dir = tempdir().unwrap();
+        let zip_path = dir.path().join("old.zip");
+        make_zip(
+            &zip_path,
+            &[
+                ("XXXXXX.fb2", b"cp866"),
+                ("Утро.fb2", b"utf8"),
+                ("plain.fb2", b"ascii"),
+            ],
+        );
+        // Swap in "Привет" in cp866 without the UTF-8 flag, as DOS-era
+        // archivers wrote it.
+        let mut bytes = fs::read(&zip_path).unwrap();
+        let (from, to) = (b"XXXXXX", b"\x8f\xe0\xa8\xa2\xa5\xe2");
+        for i in 0..bytes.len() - from.len() {
+            if &bytes[i..i + from.len()] == from {
+                bytes[i..i + from.len()].copy_from_slice(to);
+            }
+        }
+        fs::write(&zip_path, bytes).unwrap();
+
+        let exts: HashSet<String> = ["fb2".to_string()].into();
+        let cp866 = codepage_encoding("cp866");
+        let mut names: Vec<String> = zip::read_zip_entries(&zip_path, &exts, false, cp866)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.filename)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["plain.fb2", "Привет.fb2", "Утро.fb2"]);
+
+        let mut archive = ::zip::ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+        let index = find_zip_entry(&mut archive, "Привет.fb2", cp866).unwrap();
+        let mut data = String::new();
+        std::io::Read::read_to_string(&mut archive.by_index(index).unwrap(), &mut data).unwrap();
+        assert_eq!(data, "cp866");
+        assert!(find_zip_entry(&mut archive, "plain.fb2", cp866).is_some());
+        assert!(find_zip_entry(&mut archive, "missing.fb2", cp866).is_none());
+    }
+
     #[test]
     fn test_zip_helpers_invalid_archive_errors() {
         let dir = tempdir().unwrap();
@@ -678,7 +1300,7 @@ mod tests {
 
         let exts = HashSet::from(["fb2".to_string()]);
         assert!(matches!(
-            zip::read_zip_entries(&bad, &exts, false),
+            zip::read_zip_entries(&bad, &exts, false, encoding_rs::UTF_8),
             Err(ScanError::Zip(_))
         ));
         assert!(matches!(
@@ -730,6 +1352,51 @@ root_path = "/tmp"
         let res = run_scan(&pool, &cfg).await;
         SCAN_LOCK.store(false, Ordering::SeqCst);
         assert!(matches!(res, Err(ScanError::AlreadyRunning)));
+
+        // Another instance holds the database lock
+        let now = Utc::now().timestamp();
+        assert!(
+            scan_lock::try_acquire(&pool, "other:1:1", now, now + 60)
+                .await
+                .unwrap()
+        );
+        assert!(scan_in_progress(&pool).await);
+        let res = run_scan(&pool, &cfg).await;
+        assert!(matches!(res, Err(ScanError::AlreadyRunning)));
+    }
+
+    #[tokio::test]
+    async fn test_scan_stops_when_lock_is_lost() {
+        let pool = create_test_pool().await;
+        let dir = tempdir().unwrap();
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/no_cover.fb2"),
+            dir.path().join("no_cover.fb2"),
+        )
+        .unwrap();
+        let mut cfg: crate::config::Config = toml::from_str(
+            r#"
+[server]
+base_url = "http://127.0.0.1:8081"
+[library]
+root_path = "/tmp"
+[database]
+[opds]
+[scanner]
+"#,
+        )
+        .unwrap();
+        cfg.library.root_path = dir.path().to_path_buf();
+        cfg.covers.covers_path = dir.path().join("covers");
+
+        let lost = Arc::new(AtomicBool::new(true));
+        let res = do_scan(&pool, &cfg, None, &ScanObservers::default(), lost).await;
+        assert!(matches!(res, Err(ScanError::LockLost)));
+        let (books,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM books")
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        assert_eq!(books, 0);
     }
 
     #[test]
@@ -781,56 +1448,10 @@ root_path = "/tmp"
         assert_eq!(rel_path(root, file), "sub/book.fb2");
     }
 
-    #[test]
-    fn test_normalize_cover_for_storage_converts_non_jpeg_and_resizes_when_needed() {
-        let small = DynamicImage::new_rgb8(320, 480);
-        let mut small_png = Cursor::new(Vec::new());
-        small
-            .write_to(&mut small_png, image::ImageFormat::Png)
-            .unwrap();
-        let small_bytes = small_png.into_inner();
-        let cfg = test_cover_cfg();
-        let (converted_data, converted_mime) =
-            normalize_cover_for_storage_with_options(&small_bytes, "image/png", cfg);
-        assert_eq!(converted_mime, "image/jpeg");
-        assert_ne!(converted_data, small_bytes);
-        assert!(matches!(
-            image::guess_format(&converted_data),
-            Ok(image::ImageFormat::Jpeg)
-        ));
-
-        let large = DynamicImage::new_rgb8(1800, 1200);
-        let mut large_png = Cursor::new(Vec::new());
-        large
-            .write_to(&mut large_png, image::ImageFormat::Png)
-            .unwrap();
-        let (resized_data, resized_mime) =
-            normalize_cover_for_storage_with_options(&large_png.into_inner(), "image/png", cfg);
-        assert_eq!(resized_mime, "image/jpeg");
-        let resized = image::load_from_memory(&resized_data).unwrap();
-        let (w, h) = resized.dimensions();
-        assert_eq!(w.max(h), cfg.scale_to());
-    }
-
-    #[test]
-    fn test_normalize_cover_for_storage_converts_gif_to_jpeg() {
-        let gif_1x1 = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\
-\x00\x00\x00\xff\xff\xff!\xf9\x04\x01\x00\x00\x00\x00,\
-\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02D\x01\x00;";
-        let cfg = test_cover_cfg();
-        let (converted_data, converted_mime) =
-            normalize_cover_for_storage_with_options(gif_1x1, "image/gif", cfg);
-        assert_eq!(converted_mime, "image/jpeg");
-        assert!(matches!(
-            image::guess_format(&converted_data),
-            Ok(image::ImageFormat::Jpeg)
-        ));
-    }
-
     #[test]
     fn test_parse_book_bytes_invalid_epub_returns_parse_error() {
-        let err =
-            parse_book_bytes(b"not-an-epub", "epub", "bad.epub", test_cover_cfg()).unwrap_err();
+        let err = parse_book_bytes(b"not-an-epub", "epub", "bad.epub", test_cover_cfg(), &[])
+            .unwrap_err();
         assert!(matches!(err, ScanError::Parse(_)));
     }
 }
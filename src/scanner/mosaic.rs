@@ -0,0 +1,61 @@
+use super::*;
+
+use cover::{CATALOG_MOSAIC_DIR, catalog_mosaic_path, read_cover};
+
+/// Covers sampled for a catalog mosaic (2×2 grid).
+const MOSAIC_TILES: i32 = 4;
+/// Side of a catalog mosaic in pixels.
+const MOSAIC_SIZE_PX: u32 = 200;
+
+/// Rebuild the cover mosaic of every catalog from the first covers found in
+/// its subtree, and remove mosaics of catalogs that are gone or have no
+/// covers left. Returns the number of mosaics written.
+pub async fn rebuild_catalog_mosaics(pool: &DbPool, config: &Config) -> Result<u64, ScanError> {
+    let covers_path = config.covers.covers_path.clone();
+    let quality = CoverImageConfig::from(&config.covers).jpeg_quality();
+    let mut keep: HashSet<i64> = HashSet::new();
+    let mut written = 0;
+
+    for cat in catalogs::get_all(pool).await? {
+        let ids = books::cover_ids_in_catalog_tree(pool, cat.id, MOSAIC_TILES).await?;
+        if ids.is_empty() {
+            continue;
+        }
+        let covers_path = covers_path.clone();
+        let built = tokio::task::spawn_blocking(move || {
+            let covers: Vec<Vec<u8>> = ids
+                .iter()
+                .filter_map(|id| read_cover(&covers_path, *id))
+                .collect();
+            let data = crate::covers::mosaic(&covers, MOSAIC_SIZE_PX, quality).ok()?;
+            let path = catalog_mosaic_path(&covers_path, cat.id);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).ok()?;
+            }
+            fs::write(&path, data).ok()
+        })
+        .await
+        .unwrap_or(None);
+        if built.is_some() {
+            keep.insert(cat.id);
+            written += 1;
+        }
+    }
+
+    // Drop stale mosaics.
+    if let Ok(dir) = fs::read_dir(covers_path.join(CATALOG_MOSAIC_DIR)) {
+        for entry in dir.flatten() {
+            let path = entry.path();
+            let id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<i64>().ok());
+            if id.is_none_or(|id| !keep.contains(&id))
+                && let Err(e) = fs::remove_file(&path)
+            {
+                warn!("Failed to remove mosaic {}: {e}", path.display());
+            }
+        }
+    }
+    Ok(written)
+}
@@ -0,0 +1,176 @@
+//! Scan observers: hooks called for every book a scan adds, updates or
+//! deletes, and once when the scan completes.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use tracing::warn;
+
+use super::ScanStatsSnapshot;
+use crate::config::Config;
+
+/// A book the scanner wrote to the database.
+#[derive(Debug, Clone, Copy)]
+pub struct ScannedBook<'a> {
+    pub id: i64,
+    /// Folder of the book relative to the library root (for books in a ZIP
+    /// archive, the archive path).
+    pub path: &'a str,
+    pub filename: &'a str,
+    pub format: &'a str,
+    pub title: &'a str,
+}
+
+/// Receives book changes of library scans (scheduled, manual and one-shot).
+///
+/// Methods are called from scan tasks, possibly from several workers at once,
+/// after the change is committed. They should return quickly: hand slow work
+/// (network calls, indexing) to a channel or a spawned task. Every method has
+/// an empty default, so observers only implement what they need.
+pub trait ScanObserver: Send + Sync {
+    /// A new book was inserted.
+    fn book_added(&self, _book: &ScannedBook<'_>) {}
+    /// A known book was read again because its file changed.
+    fn book_updated(&self, _book: &ScannedBook<'_>) {}
+    /// A book missing from the library was deleted (or, with
+    /// `scanner.delete_logical`, marked deleted).
+    fn book_deleted(&self, _book_id: i64) {}
+    /// The scan completed; not called when it failed.
+    fn scan_finished(&self, _stats: &ScanStatsSnapshot) {}
+}
+
+/// The observers registered for scans, cheap to clone.
+#[derive(Clone, Default)]
+pub struct ScanObservers(Arc<Vec<Arc<dyn ScanObserver>>>);
+
+impl ScanObservers {
+    /// The built-in observers enabled in `config` (`scanner.changes_log`).
+    pub fn from_config(config: &Config) -> Self {
+        let mut observers = Self::default();
+        if let Some(path) = &config.scanner.changes_log {
+            observers.register(Arc::new(ChangeLogObserver::new(path.clone())));
+        }
+        observers
+    }
+
+    pub fn register(&mut self, observer: Arc<dyn ScanObserver>) {
+        Arc::make_mut(&mut self.0).push(observer);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(super) fn book_added(&self, book: &ScannedBook<'_>) {
+        self.0.iter().for_each(|o| o.book_added(book));
+    }
+
+    pub(super) fn book_updated(&self, book: &ScannedBook<'_>) {
+        self.0.iter().for_each(|o| o.book_updated(book));
+    }
+
+    pub(super) fn book_deleted(&self, book_id: i64) {
+        self.0.iter().for_each(|o| o.book_deleted(book_id));
+    }
+
+    pub(super) fn scan_finished(&self, stats: &ScanStatsSnapshot) {
+        self.0.iter().for_each(|o| o.scan_finished(stats));
+    }
+}
+
+impl std::fmt::Debug for ScanObservers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ScanObservers({})", self.0.len())
+    }
+}
+
+/// Example observer behind `scanner.changes_log`: appends one JSON object
+/// per change to a file, e.g. for an external search index to pick up.
+///
+/// ```text
+/// {"event":"added","id":42,"path":"sf/asimov","filename":"foundation.fb2","format":"fb2","title":"Foundation"}
+/// {"event":"deleted","id":17}
+/// {"event":"finished","added":1,"updated":0,"deleted":1,"errors":0}
+/// ```
+pub struct ChangeLogObserver {
+    path: PathBuf,
+    /// Opened on the first change and closed when the scan finishes.
+    file: Mutex<Option<BufWriter<File>>>,
+}
+
+impl ChangeLogObserver {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: Mutex::new(None),
+        }
+    }
+
+    fn write(&self, line: serde_json::Value) {
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        if file.is_none() {
+            match OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+            {
+                Ok(f) => *file = Some(BufWriter::new(f)),
+                Err(e) => {
+                    warn!("Cannot open scan changes log {}: {e}", self.path.display());
+                    return;
+                }
+            }
+        }
+        if let Some(out) = file.as_mut()
+            && let Err(e) = writeln!(out, "{line}")
+        {
+            warn!("Cannot write scan changes log {}: {e}", self.path.display());
+        }
+    }
+
+    fn book_line(event: &str, book: &ScannedBook<'_>) -> serde_json::Value {
+        json!({
+            "event": event,
+            "id": book.id,
+            "path": book.path,
+            "filename": book.filename,
+            "format": book.format,
+            "title": book.title,
+        })
+    }
+}
+
+impl ScanObserver for ChangeLogObserver {
+    fn book_added(&self, book: &ScannedBook<'_>) {
+        self.write(Self::book_line("added", book));
+    }
+
+    fn book_updated(&self, book: &ScannedBook<'_>) {
+        self.write(Self::book_line("updated", book));
+    }
+
+    fn book_deleted(&self, book_id: i64) {
+        self.write(json!({ "event": "deleted", "id": book_id }));
+    }
+
+    fn scan_finished(&self, stats: &ScanStatsSnapshot) {
+        self.write(json!({
+            "event": "finished",
+            "added": stats.books_added,
+            "updated": stats.books_updated,
+            "deleted": stats.books_deleted,
+            "errors": stats.errors,
+        }));
+        if let Ok(mut file) = self.file.lock()
+            && let Some(mut out) = file.take()
+            && let Err(e) = out.flush()
+        {
+            warn!("Cannot write scan changes log {}: {e}", self.path.display());
+        }
+    }
+}
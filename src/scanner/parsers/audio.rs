@@ -0,0 +1,524 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use id3::TagLike;
+
+use super::BookMeta;
+use crate::audio::{FOLDER_METADATA_FILE, list_tracks};
+
+/// Largest `moov` box read into memory; real ones are a few MB at most.
+const MAX_MOOV_SIZE: u64 = 64 << 20;
+/// Cover image files looked up in an MP3 audiobook folder.
+const FOLDER_COVERS: &[&str] = &["cover.jpg", "cover.jpeg", "cover.png", "folder.jpg"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum AudioError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid {FOLDER_METADATA_FILE}: {0}")]
+    Metadata(#[from] serde_json::Error),
+    #[error("no moov box found in MP4 file")]
+    NoMoov,
+    #[error("no MP3 tracks found in folder")]
+    NoTracks,
+}
+
+// ---------------------------------------------------------------------------
+// M4B (MP4 container)
+// ---------------------------------------------------------------------------
+
+/// Parse an M4B audiobook: iTunes-style tags from `moov/udta/meta/ilst` and
+/// the running time from `moov/mvhd`.
+pub fn parse_m4b<R: Read + Seek>(mut reader: R) -> Result<BookMeta, AudioError> {
+    let moov = read_moov(&mut reader)?.ok_or(AudioError::NoMoov)?;
+    let mut meta = BookMeta::default();
+    let mut composer = None;
+
+    for (kind, body) in boxes(&moov) {
+        match kind {
+            b"mvhd" => meta.duration_secs = mvhd_duration(body).unwrap_or(0),
+            b"udta" => {
+                let Some(meta_box) = child(body, b"meta") else {
+                    continue;
+                };
+                // `meta` is a full box: skip version and flags.
+                let Some(ilst) = meta_box.get(4..).and_then(|b| child(b, b"ilst")) else {
+                    continue;
+                };
+                for (item, item_body) in boxes(ilst) {
+                    let Some((data_type, value)) = item_data(item_body) else {
+                        continue;
+                    };
+                    let text = || String::from_utf8_lossy(value).trim().to_string();
+                    match item {
+                        b"\xa9nam" => meta.title = text(),
+                        b"\xa9alb" if meta.title.is_empty() => meta.title = text(),
+                        b"\xa9ART" => meta.authors = split_people(&text()),
+                        b"aART" if meta.authors.is_empty() => meta.authors = split_people(&text()),
+                        b"\xa9nrt" => meta.narrators = split_people(&text()),
+                        b"\xa9wrt" => composer = Some(text()),
+                        b"desc" | b"ldes" | b"\xa9cmt" if meta.annotation.is_empty() => {
+                            meta.annotation = text()
+                        }
+                        b"\xa9day" => meta.docdate = text(),
+                        b"covr" if meta.cover_data.is_none() => {
+                            meta.cover_type = match data_type {
+                                14 => "image/png",
+                                _ => "image/jpeg",
+                            }
+                            .to_string();
+                            meta.cover_data = Some(value.to_vec());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    // Audiobook tools commonly put the narrator in the composer field.
+    if meta.narrators.is_empty()
+        && let Some(composer) = composer
+    {
+        meta.narrators = split_people(&composer);
+    }
+    Ok(meta)
+}
+
+/// Find the top-level `moov` box and read its body.
+fn read_moov<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, AudioError> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let mut pos = 0u64;
+    while pos.checked_add(8).is_some_and(|end| end <= file_len) {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let mut size = u32::from_be_bytes(header[..4].try_into().unwrap()) as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = file_len - pos;
+        }
+        if size < header_len {
+            break;
+        }
+        // A box must end past its start and within the file.
+        let Some(end) = pos
+            .checked_add(size)
+            .filter(|end| *end > pos && *end <= file_len)
+        else {
+            break;
+        };
+        if &header[4..] == b"moov" {
+            let body_len = size - header_len;
+            if body_len > MAX_MOOV_SIZE {
+                return Ok(None);
+            }
+            let mut body = vec![0u8; body_len as usize];
+            reader.read_exact(&mut body)?;
+            return Ok(Some(body));
+        }
+        pos = end;
+    }
+    Ok(None)
+}
+
+/// Iterate the child boxes of a box body as `(type, body)` pairs.
+fn boxes(mut data: &[u8]) -> impl Iterator<Item = (&[u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < 8 {
+            return None;
+        }
+        let size = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        let (size, header_len) = match size {
+            0 => (data.len(), 8),
+            1 if data.len() >= 16 => {
+                let size = u64::from_be_bytes(data[8..16].try_into().unwrap());
+                (usize::try_from(size).unwrap_or(usize::MAX), 16)
+            }
+            _ => (size, 8),
+        };
+        if size < header_len || size > data.len() {
+            return None;
+        }
+        let kind: &[u8; 4] = data[4..8].try_into().unwrap();
+        let body = &data[header_len..size];
+        data = &data[size..];
+        Some((kind, body))
+    })
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data).find(|(k, _)| *k == kind).map(|(_, body)| body)
+}
+
+/// Running time in whole seconds from an `mvhd` body.
+fn mvhd_duration(body: &[u8]) -> Option<i64> {
+    let (timescale, duration) = if body.first() == Some(&1) {
+        (
+            u32::from_be_bytes(body.get(20..24)?.try_into().ok()?) as u64,
+            u64::from_be_bytes(body.get(24..32)?.try_into().ok()?),
+        )
+    } else {
+        (
+            u32::from_be_bytes(body.get(12..16)?.try_into().ok()?) as u64,
+            u32::from_be_bytes(body.get(16..20)?.try_into().ok()?) as u64,
+        )
+    };
+    (timescale > 0).then(|| (duration / timescale) as i64)
+}
+
+/// The type code and payload of an `ilst` item's `data` box.
+fn item_data(item: &[u8]) -> Option<(u32, &[u8])> {
+    let data = child(item, b"data")?;
+    let data_type = u32::from_be_bytes(data.get(..4)?.try_into().ok()?) & 0x00ff_ffff;
+    Some((data_type, data.get(8..)?))
+}
+
+// ---------------------------------------------------------------------------
+// MP3 folders
+// ---------------------------------------------------------------------------
+
+/// `metadata.json` of an audiobook folder, as written by Audiobookshelf.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct FolderMetadata {
+    title: Option<String>,
+    authors: Vec<String>,
+    narrators: Vec<String>,
+    series: Vec<String>,
+    description: Option<String>,
+    language: Option<String>,
+    published_year: Option<String>,
+}
+
+/// Parse a folder of MP3 tracks. `metadata.json` comes first; the ID3 tags
+/// of the first track fill in what it lacks, and the running time is the
+/// sum of all tracks.
+pub fn parse_mp3_folder(dir: &Path) -> Result<BookMeta, AudioError> {
+    let tracks = list_tracks(dir)?;
+    if tracks.is_empty() {
+        return Err(AudioError::NoTracks);
+    }
+    let folder: FolderMetadata = match fs::read(dir.join(FOLDER_METADATA_FILE)) {
+        Ok(data) => serde_json::from_slice(&data)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => FolderMetadata::default(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut meta = BookMeta {
+        title: folder.title.unwrap_or_default().trim().to_string(),
+        authors: folder.authors,
+        narrators: folder.narrators,
+        annotation: folder.description.unwrap_or_default(),
+        lang: folder.language.unwrap_or_default(),
+        docdate: folder.published_year.unwrap_or_default(),
+        ..Default::default()
+    };
+    if let Some((title, index)) = folder.series.first().map(|s| split_series(s)) {
+        meta.series_title = Some(title);
+        meta.series_index = index;
+    }
+
+    let tag = id3::Tag::read_from_path(&tracks[0]).ok();
+    if let Some(tag) = &tag {
+        if meta.title.is_empty() {
+            meta.title = tag.album().or(tag.title()).unwrap_or_default().to_string();
+        }
+        if meta.authors.is_empty() {
+            meta.authors = split_people(tag.album_artist().or(tag.artist()).unwrap_or_default());
+        }
+        if meta.narrators.is_empty()
+            && let Some(composer) = tag.get("TCOM").and_then(|f| f.content().text())
+        {
+            meta.narrators = split_people(composer);
+        }
+    }
+
+    if let Some(cover) = FOLDER_COVERS
+        .iter()
+        .map(|n| dir.join(n))
+        .find(|p| p.is_file())
+    {
+        meta.cover_data = Some(fs::read(&cover)?);
+        meta.cover_type = mime_guess::from_path(&cover)
+            .first_or_octet_stream()
+            .to_string();
+    } else if let Some(picture) = tag.as_ref().and_then(|t| t.pictures().next()) {
+        meta.cover_data = Some(picture.data.clone());
+        meta.cover_type = picture.mime_type.clone();
+    }
+
+    meta.duration_secs = tracks
+        .iter()
+        .map(|track| mp3_duration_secs(track).unwrap_or(0.0))
+        .sum::<f64>()
+        .round() as i64;
+    Ok(meta)
+}
+
+/// Split "Series Name #3" into the name and number.
+fn split_series(s: &str) -> (String, i32) {
+    match s.rsplit_once('#') {
+        Some((name, no)) if no.trim().parse::<f32>().is_ok() => (
+            name.trim().to_string(),
+            no.trim().parse::<f32>().unwrap_or(0.0) as i32,
+        ),
+        _ => (s.trim().to_string(), 0),
+    }
+}
+
+/// Split a tag value holding several people ("A; B", "A & B", "A, B").
+/// Commas only separate when every part looks like a full name, so
+/// "Asimov, Isaac" stays one person.
+fn split_people(s: &str) -> Vec<String> {
+    let parts = |s: &str| -> Vec<String> {
+        s.split([';', '&', '/'])
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    let people = parts(s);
+    if people.len() == 1 && s.contains(',') {
+        let by_comma: Vec<&str> = s.split(',').map(str::trim).collect();
+        if by_comma.iter().all(|p| p.contains(' ')) {
+            return by_comma.into_iter().map(String::from).collect();
+        }
+    }
+    people
+}
+
+/// Running time of an MP3 file: the ID3 `TLEN` frame when present, else
+/// the Xing/Info frame count, else file size over the bitrate of the first
+/// frame (exact for constant bitrate).
+fn mp3_duration_secs(path: &Path) -> Option<f64> {
+    if let Ok(tag) = id3::Tag::read_from_path(path)
+        && let Some(ms) = tag.duration()
+    {
+        return Some(ms as f64 / 1000.0);
+    }
+
+    let mut file = fs::File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let mut head = [0u8; 10];
+    file.read_exact(&mut head).ok()?;
+    let mut audio_start = 0u64;
+    if &head[..3] == b"ID3" {
+        let size = head[6..10]
+            .iter()
+            .fold(0u64, |acc, b| (acc << 7) | (*b & 0x7f) as u64);
+        let footer = if head[5] & 0x10 != 0 { 10 } else { 0 };
+        audio_start = 10 + size + footer;
+    }
+    file.seek(SeekFrom::Start(audio_start)).ok()?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let n = file.read(&mut buf).ok()?;
+    let buf = &buf[..n];
+
+    let offset = (0..buf.len().saturating_sub(4)).find(|&i| {
+        buf[i] == 0xff && buf[i + 1] & 0xe0 == 0xe0 && frame_header(&buf[i..]).is_some()
+    })?;
+    let frame = frame_header(&buf[offset..])?;
+
+    // Xing/Info header sits after the side information of the first frame.
+    let side_info = match (frame.mpeg1, frame.mono) {
+        (true, false) => 32,
+        (true, true) => 17,
+        (false, false) => 17,
+        (false, true) => 9,
+    };
+    let xing = offset + 4 + side_info;
+    if let Some(tag) = buf.get(xing..xing + 12)
+        && (&tag[..4] == b"Xing" || &tag[..4] == b"Info")
+        && tag[7] & 0x01 != 0
+    {
+        let frames = u32::from_be_bytes(tag[8..12].try_into().ok()?) as f64;
+        return Some(frames * frame.samples as f64 / frame.sample_rate as f64);
+    }
+
+    let audio_len = file_len.saturating_sub(audio_start + offset as u64);
+    Some(audio_len as f64 * 8.0 / (frame.bitrate_kbps as f64 * 1000.0))
+}
+
+struct FrameHeader {
+    mpeg1: bool,
+    mono: bool,
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    samples: u32,
+}
+
+/// Decode an MPEG audio Layer III frame header.
+fn frame_header(b: &[u8]) -> Option<FrameHeader> {
+    const BITRATES_V1: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+    if b.len() < 4 || b[0] != 0xff || b[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+    let version = (b[1] >> 3) & 0x03; // 0 = 2.5, 2 = 2, 3 = 1
+    let layer = (b[1] >> 1) & 0x03; // 1 = Layer III
+    if version == 1 || layer != 1 {
+        return None;
+    }
+    let mpeg1 = version == 3;
+    let bitrate_idx = (b[2] >> 4) as usize;
+    let rate_idx = ((b[2] >> 2) & 0x03) as usize;
+    if bitrate_idx == 0 || bitrate_idx == 15 || rate_idx == 3 {
+        return None;
+    }
+    let base_rate = [44100, 48000, 32000][rate_idx];
+    let sample_rate = match version {
+        3 => base_rate,
+        2 => base_rate / 2,
+        _ => base_rate / 4,
+    };
+    Some(FrameHeader {
+        mpeg1,
+        mono: b[3] >> 6 == 3,
+        bitrate_kbps: if mpeg1 {
+            BITRATES_V1[bitrate_idx]
+        } else {
+            BITRATES_V2[bitrate_idx]
+        },
+        sample_rate,
+        samples: if mpeg1 { 1152 } else { 576 },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn ilst_item(kind: &[u8; 4], data_type: u32, value: &[u8]) -> Vec<u8> {
+        let mut data = data_type.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(value);
+        mp4_box(kind, &mp4_box(b"data", &data))
+    }
+
+    #[test]
+    fn test_parse_m4b_tags_and_duration() {
+        let mut mvhd = vec![0u8; 12];
+        mvhd.extend_from_slice(&1000u32.to_be_bytes());
+        mvhd.extend_from_slice(&5_400_000u32.to_be_bytes());
+        let ilst: Vec<u8> = [
+            ilst_item(b"\xa9nam", 1, b"Dune"),
+            ilst_item(b"\xa9ART", 1, b"Frank Herbert"),
+            ilst_item(b"\xa9wrt", 1, b"Scott Brick; Orlagh Cassidy"),
+            ilst_item(b"desc", 1, b"Desert planet."),
+            ilst_item(b"covr", 13, b"\xff\xd8jpeg"),
+        ]
+        .concat();
+        let mut meta_body = vec![0u8; 4];
+        meta_body.extend(mp4_box(b"ilst", &ilst));
+        let moov = [
+            mp4_box(b"mvhd", &mvhd),
+            mp4_box(b"udta", &mp4_box(b"meta", &meta_body)),
+        ]
+        .concat();
+        let file = [
+            mp4_box(b"ftyp", b"M4B \0\0\0\0"),
+            mp4_box(b"mdat", &[0; 32]),
+            mp4_box(b"moov", &moov),
+        ]
+        .concat();
+
+        let meta = parse_m4b(Cursor::new(file)).unwrap();
+        assert_eq!(meta.title, "Dune");
+        assert_eq!(meta.authors, vec!["Frank Herbert"]);
+        assert_eq!(meta.narrators, vec!["Scott Brick", "Orlagh Cassidy"]);
+        assert_eq!(meta.annotation, "Desert planet.");
+        assert_eq!(meta.duration_secs, 5400);
+        assert_eq!(meta.cover_type, "image/jpeg");
+        assert!(meta.cover_data.is_some());
+
+        assert!(matches!(
+            parse_m4b(Cursor::new(mp4_box(b"ftyp", b"M4B "))),
+            Err(AudioError::NoMoov)
+        ));
+    }
+
+    #[test]
+    fn test_parse_m4b_bad_box_size() {
+        // A 64-bit box size that would overflow the file offset.
+        let mut huge = 1u32.to_be_bytes().to_vec();
+        huge.extend_from_slice(b"free");
+        huge.extend_from_slice(&u64::MAX.to_be_bytes());
+        let file = [mp4_box(b"ftyp", b"M4B "), huge].concat();
+        assert!(matches!(
+            parse_m4b(Cursor::new(file)),
+            Err(AudioError::NoMoov)
+        ));
+
+        // A box claiming to run past the end of the file.
+        let mut long = 1000u32.to_be_bytes().to_vec();
+        long.extend_from_slice(b"mdat");
+        long.extend_from_slice(&[0; 16]);
+        let file = [long, mp4_box(b"moov", &[])].concat();
+        assert!(matches!(
+            parse_m4b(Cursor::new(file)),
+            Err(AudioError::NoMoov)
+        ));
+    }
+
+    /// A constant bitrate MPEG-1 Layer III stream: 128 kbps, 44.1 kHz, stereo.
+    fn cbr_mp3(seconds: usize) -> Vec<u8> {
+        let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+        frame.resize(417, 0);
+        let frames = seconds * 128_000 / 8 / 417;
+        frame.repeat(frames)
+    }
+
+    #[test]
+    fn test_parse_mp3_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(FOLDER_METADATA_FILE),
+            r#"{"title": "The Hobbit", "authors": ["J. R. R. Tolkien"],
+                "narrators": ["Andy Serkis"], "series": ["Middle-earth #1"],
+                "description": "There and back again.", "language": "en",
+                "publishedYear": "2020"}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("01.mp3"), cbr_mp3(10)).unwrap();
+        std::fs::write(dir.path().join("02.mp3"), cbr_mp3(20)).unwrap();
+        std::fs::write(dir.path().join("cover.jpg"), b"\xff\xd8jpeg").unwrap();
+
+        let meta = parse_mp3_folder(dir.path()).unwrap();
+        assert_eq!(meta.title, "The Hobbit");
+        assert_eq!(meta.authors, vec!["J. R. R. Tolkien"]);
+        assert_eq!(meta.narrators, vec!["Andy Serkis"]);
+        assert_eq!(meta.series_title.as_deref(), Some("Middle-earth"));
+        assert_eq!(meta.series_index, 1);
+        assert_eq!(meta.lang, "en");
+        assert_eq!(meta.docdate, "2020");
+        assert_eq!(meta.cover_type, "image/jpeg");
+        assert_eq!(meta.duration_secs, 30);
+    }
+
+    #[test]
+    fn test_split_people() {
+        assert_eq!(split_people("Asimov, Isaac"), vec!["Asimov, Isaac"]);
+        assert_eq!(
+            split_people("Terry Pratchett, Neil Gaiman"),
+            vec!["Terry Pratchett", "Neil Gaiman"]
+        );
+        assert_eq!(split_people("A B & C D"), vec!["A B", "C D"]);
+    }
+}
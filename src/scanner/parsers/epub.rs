@@ -5,7 +5,7 @@ use quick_xml::XmlVersion;
 use quick_xml::events::Event;
 use quick_xml::reader::Reader;
 
-use super::{BookMeta, strip_meta};
+use super::{BookMeta, PreviewBuilder, strip_meta};
 
 /// Parse EPUB metadata from a ZIP archive.
 /// The reader must implement Read + Seek (for the zip crate).
@@ -22,10 +22,114 @@ pub fn parse<R: Read + Seek>(reader: R) -> Result<BookMeta, EpubError> {
         meta.cover_data = Some(cover_data);
         meta.cover_type = cover_type;
     }
+    (meta.preview_text, meta.word_count) = read_spine_text(
+        &opf_data,
+        &opf_path,
+        &mut archive,
+        PreviewBuilder::counting(),
+    );
 
     Ok(meta)
 }
 
+/// The text of all spine documents in reading order, one paragraph per
+/// line, cut after `max_chars` characters.
+pub fn body_text<R: Read + Seek>(reader: R, max_chars: usize) -> Result<String, EpubError> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let opf_path = find_opf_path(&mut archive)?;
+    let opf_data = read_zip_entry(&mut archive, &opf_path)?;
+    let (text, _) = read_spine_text(
+        &opf_data,
+        &opf_path,
+        &mut archive,
+        PreviewBuilder::with_limit(max_chars),
+    );
+    Ok(text)
+}
+
+/// Feed the book text to `preview` by walking the spine in reading order;
+/// returns the preview and the word count.
+fn read_spine_text<R: Read + Seek>(
+    opf_data: &[u8],
+    opf_path: &str,
+    archive: &mut zip::ZipArchive<R>,
+    mut preview: PreviewBuilder,
+) -> (String, i32) {
+    let opf_dir = parent_dir(opf_path);
+    let opf = parse_opf_manifest(opf_data);
+    let documents = opf
+        .spine
+        .iter()
+        .filter_map(|idref| opf.items.iter().find(|m| m.id == *idref))
+        .filter(|item| is_document(item) && item.media_type != "image/svg+xml");
+    for item in documents {
+        if preview.is_done() {
+            break;
+        }
+        if let Some(data) = read_zip_entry_opt(archive, &resolve_path(opf_dir, &item.href)) {
+            collect_document_text(&data, &mut preview);
+        }
+    }
+    preview.finish_counted()
+}
+
+/// Append the `<body>` text of an XHTML document, one paragraph per block element.
+fn collect_document_text(data: &[u8], preview: &mut PreviewBuilder) {
+    let mut xml = Reader::from_reader(data);
+    xml.config_mut().check_end_names = false;
+    let mut buf = Vec::new();
+    let mut in_body = false;
+    let mut skip_depth = 0usize;
+
+    loop {
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(Event::Start(ref e)) => match local_name(e.name().as_ref()).as_str() {
+                "body" => in_body = true,
+                "script" | "style" => skip_depth += 1,
+                name if is_block(name) => preview.end_paragraph(),
+                _ => {}
+            },
+            Ok(Event::Empty(ref e)) if local_name(e.name().as_ref()) == "br" => {
+                preview.end_paragraph()
+            }
+            Ok(Event::End(ref e)) => match local_name(e.name().as_ref()).as_str() {
+                "body" => in_body = false,
+                "script" | "style" => skip_depth = skip_depth.saturating_sub(1),
+                name if is_block(name) => preview.end_paragraph(),
+                _ => {}
+            },
+            Ok(Event::Text(ref e)) if in_body && skip_depth == 0 => {
+                preview.push(&e.decode().unwrap_or_default());
+            }
+            Ok(Event::GeneralRef(ref r)) if in_body && skip_depth == 0 => preview.push_ref(r),
+            _ => {}
+        }
+        if preview.is_done() {
+            break;
+        }
+        buf.clear();
+    }
+    preview.end_paragraph();
+}
+
+fn is_block(name: &str) -> bool {
+    matches!(
+        name,
+        "p" | "div"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "li"
+            | "blockquote"
+            | "section"
+            | "tr"
+    )
+}
+
 /// Locate the OPF root file inside the EPUB ZIP.
 fn find_opf_path<R: Read + Seek>(archive: &mut zip::ZipArchive<R>) -> Result<String, EpubError> {
     // Try META-INF/container.xml first
@@ -194,47 +298,109 @@ fn parse_opf(data: &[u8]) -> Result<BookMeta, EpubError> {
     Ok(meta)
 }
 
+/// Upper bound for an embedded cover image; anything larger is not worth storing.
+const MAX_COVER_BYTES: usize = 16 * 1024 * 1024;
+/// Minimum short side (px) for covers found by heuristics rather than explicit markup.
+/// Filters out ornaments, dividers and publisher logos on title pages.
+const MIN_HEURISTIC_COVER_SIDE: u32 = 150;
+/// Maximum long/short side ratio for covers found by heuristics (rejects banners).
+const MAX_HEURISTIC_COVER_ASPECT: u32 = 3;
+
 /// Try to extract cover image from the EPUB.
-/// Tries multiple strategies matching the Python implementation.
-fn extract_cover_from_opf<R: Read + Seek>(
+/// Explicit cover markup is tried first (EPUB3 `cover-image`, EPUB2 `<meta name="cover">`,
+/// `id="cover"`), then cover pages (guide/landmarks, `cover.xhtml`), the first spine document
+/// and finally image file names. Heuristic matches must look like a real cover image.
+pub fn extract_cover_from_opf<R: Read + Seek>(
     opf_data: &[u8],
     opf_path: &str,
     archive: &mut zip::ZipArchive<R>,
 ) -> Option<(Vec<u8>, String)> {
-    let opf_dir = match opf_path.rfind('/') {
-        Some(i) => &opf_path[..=i],
-        None => "",
-    };
+    let opf_dir = parent_dir(opf_path);
 
     // Parse OPF to find manifest items and cover reference
-    let (manifest, cover_id) = parse_opf_manifest(opf_data);
+    let opf = parse_opf_manifest(opf_data);
+    let manifest = &opf.items;
 
     // Strategy 1: item with properties="cover-image"
-    for item in &manifest {
-        if item.properties.contains("cover-image") && item.media_type.starts_with("image/") {
+    for item in manifest {
+        if has_property(&item.properties, "cover-image") && item.media_type.starts_with("image/") {
             let path = resolve_path(opf_dir, &item.href);
-            if let Some(data) = read_zip_entry_opt(archive, &path) {
+            if let Some(data) = read_cover_entry(archive, &path) {
                 return Some((data, item.media_type.clone()));
             }
         }
     }
 
     // Strategy 2: <meta name="cover" content="id"/> → lookup in manifest
-    if let Some(ref id) = cover_id
+    if let Some(ref id) = opf.cover_id
         && let Some(item) = manifest.iter().find(|m| m.id == *id)
         && item.media_type.starts_with("image/")
     {
         let path = resolve_path(opf_dir, &item.href);
-        if let Some(data) = read_zip_entry_opt(archive, &path) {
+        if let Some(data) = read_cover_entry(archive, &path) {
             return Some((data, item.media_type.clone()));
         }
     }
 
     // Strategy 3: manifest item with id="cover" (case-insensitive)
-    for item in &manifest {
+    for item in manifest {
         if item.id.eq_ignore_ascii_case("cover") && item.media_type.starts_with("image/") {
             let path = resolve_path(opf_dir, &item.href);
-            if let Some(data) = read_zip_entry_opt(archive, &path) {
+            if let Some(data) = read_cover_entry(archive, &path) {
+                return Some((data, item.media_type.clone()));
+            }
+        }
+    }
+
+    // Strategy 4: cover page documents — EPUB2 <guide> reference, EPUB3 nav landmarks,
+    // then XHTML items named like "cover.xhtml".
+    let mut cover_pages: Vec<String> = Vec::new();
+    if let Some(ref href) = opf.guide_cover {
+        cover_pages.push(resolve_path(opf_dir, href));
+    }
+    if let Some(nav) = manifest.iter().find(|m| has_property(&m.properties, "nav")) {
+        let nav_path = resolve_path(opf_dir, &nav.href);
+        if let Some(href) =
+            read_zip_entry_opt(archive, &nav_path).and_then(|data| find_landmark_cover(&data))
+        {
+            cover_pages.push(resolve_path(parent_dir(&nav_path), &href));
+        }
+    }
+    for item in manifest {
+        if is_document(item)
+            && (item.id.to_lowercase().contains("cover") || is_cover_name(&item.href))
+        {
+            cover_pages.push(resolve_path(opf_dir, &item.href));
+        }
+    }
+    for page in &cover_pages {
+        if let Some(cover) = first_document_image(archive, manifest, opf_dir, page) {
+            return Some(cover);
+        }
+    }
+
+    // Strategy 5: first image of the first spine document
+    if let Some(item) = opf
+        .spine
+        .first()
+        .and_then(|idref| manifest.iter().find(|m| m.id == *idref))
+        .filter(|item| is_document(item))
+    {
+        let page = resolve_path(opf_dir, &item.href);
+        if !cover_pages.contains(&page)
+            && let Some(cover) = first_document_image(archive, manifest, opf_dir, &page)
+        {
+            return Some(cover);
+        }
+    }
+
+    // Strategy 6: image item whose file name mentions "cover"
+    for item in manifest {
+        if item.media_type.starts_with("image/") && is_cover_name(&item.href) {
+            let path = resolve_path(opf_dir, &item.href);
+            if let Some(data) = read_cover_entry(archive, &path)
+                && is_plausible_cover(&data)
+            {
                 return Some((data, item.media_type.clone()));
             }
         }
@@ -243,6 +409,158 @@ fn extract_cover_from_opf<R: Read + Seek>(
     None
 }
 
+/// Read the first image referenced by an XHTML document (`<img src>` or SVG `<image href>`)
+/// and return it if it passes the heuristic size checks.
+fn first_document_image<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    manifest: &[ManifestItem],
+    opf_dir: &str,
+    page_path: &str,
+) -> Option<(Vec<u8>, String)> {
+    let page = read_zip_entry_opt(archive, page_path)?;
+    let href = find_first_image_href(&page)?;
+    let path = resolve_path(parent_dir(page_path), &href);
+    let data = read_cover_entry(archive, &path)?;
+    if !is_plausible_cover(&data) {
+        return None;
+    }
+    let media_type = manifest
+        .iter()
+        .find(|m| m.media_type.starts_with("image/") && resolve_path(opf_dir, &m.href) == path)
+        .map(|m| m.media_type.clone())
+        .or_else(|| {
+            image::guess_format(&data)
+                .ok()
+                .map(|f| f.to_mime_type().to_string())
+        })?;
+    Some((data, media_type))
+}
+
+/// Find the `src`/`href` of the first image element in an XHTML document.
+fn find_first_image_href(data: &[u8]) -> Option<String> {
+    let mut xml = Reader::from_reader(data);
+    xml.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+
+    loop {
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Eof) | Err(_) => return None,
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                let attr_name = match local_name(e.name().as_ref()).as_str() {
+                    "img" => "src",
+                    "image" => "href",
+                    _ => {
+                        buf.clear();
+                        continue;
+                    }
+                };
+                for attr in e.attributes().flatten() {
+                    if local_name(attr.key.as_ref()) == attr_name {
+                        let val = attr
+                            .decoded_and_normalized_value(XmlVersion::Implicit1_0, xml.decoder())
+                            .unwrap_or_default();
+                        if !val.is_empty() {
+                            return Some(val.to_string());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Find the cover page href in an EPUB3 navigation document
+/// (`<nav epub:type="landmarks">` → `<a epub:type="cover" href="...">`).
+fn find_landmark_cover(data: &[u8]) -> Option<String> {
+    let mut xml = Reader::from_reader(data);
+    xml.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+    let mut in_landmarks = false;
+
+    loop {
+        match xml.read_event_into(&mut buf) {
+            Ok(Event::Eof) | Err(_) => return None,
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                let local = local_name(e.name().as_ref());
+                if local != "nav" && !(in_landmarks && local == "a") {
+                    buf.clear();
+                    continue;
+                }
+                let mut epub_type = String::new();
+                let mut href = String::new();
+                for attr in e.attributes().flatten() {
+                    let val = attr
+                        .decoded_and_normalized_value(XmlVersion::Implicit1_0, xml.decoder())
+                        .unwrap_or_default();
+                    match local_name(attr.key.as_ref()).as_str() {
+                        "type" => epub_type = val.to_string(),
+                        "href" => href = val.to_string(),
+                        _ => {}
+                    }
+                }
+                if local == "nav" {
+                    in_landmarks = has_property(&epub_type, "landmarks");
+                } else if has_property(&epub_type, "cover") && !href.is_empty() {
+                    return Some(href);
+                }
+            }
+            Ok(Event::End(ref e)) if local_name(e.name().as_ref()) == "nav" => {
+                in_landmarks = false;
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Check decoded dimensions of a heuristically found image: it must be a format we can
+/// decode, not tiny and not a narrow banner.
+fn is_plausible_cover(data: &[u8]) -> bool {
+    let dims = image::ImageReader::new(std::io::Cursor::new(data))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+    let Some((width, height)) = dims else {
+        return false;
+    };
+    let short = width.min(height);
+    let long = width.max(height);
+    short >= MIN_HEURISTIC_COVER_SIDE && long <= short.saturating_mul(MAX_HEURISTIC_COVER_ASPECT)
+}
+
+fn is_document(item: &ManifestItem) -> bool {
+    matches!(
+        item.media_type.as_str(),
+        "application/xhtml+xml" | "text/html" | "image/svg+xml"
+    )
+}
+
+/// True when the file name (without directories) mentions "cover".
+fn is_cover_name(href: &str) -> bool {
+    let name = href.rsplit('/').next().unwrap_or(href);
+    name.to_lowercase().contains("cover")
+}
+
+/// Match a token in a space-separated attribute such as `properties` or `epub:type`.
+fn has_property(value: &str, token: &str) -> bool {
+    value.split_ascii_whitespace().any(|p| p == token)
+}
+
+/// Read a cover image entry, rejecting empty and oversized files.
+fn read_cover_entry<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+) -> Option<Vec<u8>> {
+    let entry = archive.by_name(name).ok()?;
+    if entry.size() == 0 || entry.size() > MAX_COVER_BYTES as u64 {
+        return None;
+    }
+    let data = read_to_vec(entry.take(MAX_COVER_BYTES as u64)).ok()?;
+    (!data.is_empty()).then_some(data)
+}
+
 struct ManifestItem {
     id: String,
     href: String,
@@ -250,10 +568,21 @@ struct ManifestItem {
     properties: String,
 }
 
-/// Parse the OPF manifest and any cover meta reference.
-fn parse_opf_manifest(data: &[u8]) -> (Vec<ManifestItem>, Option<String>) {
-    let mut items = Vec::new();
-    let mut cover_id = None;
+/// Manifest, spine and cover references of an OPF package.
+#[derive(Default)]
+struct OpfManifest {
+    items: Vec<ManifestItem>,
+    /// Manifest id from `<meta name="cover" content="..."/>`.
+    cover_id: Option<String>,
+    /// Spine `idref`s in reading order.
+    spine: Vec<String>,
+    /// Href of the EPUB2 `<guide><reference type="cover"/>` page.
+    guide_cover: Option<String>,
+}
+
+/// Parse the OPF manifest, spine, guide and any cover meta reference.
+fn parse_opf_manifest(data: &[u8]) -> OpfManifest {
+    let mut opf = OpfManifest::default();
 
     let mut xml = Reader::from_reader(data);
     xml.config_mut().trim_text(true);
@@ -282,7 +611,7 @@ fn parse_opf_manifest(data: &[u8]) -> (Vec<ManifestItem>, Option<String>) {
                             _ => {}
                         }
                     }
-                    items.push(ManifestItem {
+                    opf.items.push(ManifestItem {
                         id,
                         href,
                         media_type,
@@ -304,7 +633,38 @@ fn parse_opf_manifest(data: &[u8]) -> (Vec<ManifestItem>, Option<String>) {
                         }
                     }
                     if name_attr == "cover" && !content_attr.is_empty() {
-                        cover_id = Some(content_attr);
+                        opf.cover_id = Some(content_attr);
+                    }
+                }
+                if local == "itemref" {
+                    for attr in e.attributes().flatten() {
+                        if attr.key.as_ref() == b"idref" {
+                            let val = attr
+                                .decoded_and_normalized_value(
+                                    XmlVersion::Implicit1_0,
+                                    xml.decoder(),
+                                )
+                                .unwrap_or_default();
+                            opf.spine.push(val.to_string());
+                        }
+                    }
+                }
+                if local == "reference" && opf.guide_cover.is_none() {
+                    let mut ref_type = String::new();
+                    let mut href = String::new();
+                    for attr in e.attributes().flatten() {
+                        let key = std::str::from_utf8(attr.key.as_ref()).unwrap_or("");
+                        let val = attr
+                            .decoded_and_normalized_value(XmlVersion::Implicit1_0, xml.decoder())
+                            .unwrap_or_default();
+                        match key {
+                            "type" => ref_type = val.to_string(),
+                            "href" => href = val.to_string(),
+                            _ => {}
+                        }
+                    }
+                    if ref_type.eq_ignore_ascii_case("cover") && !href.is_empty() {
+                        opf.guide_cover = Some(href);
                     }
                 }
             }
@@ -313,7 +673,7 @@ fn parse_opf_manifest(data: &[u8]) -> (Vec<ManifestItem>, Option<String>) {
         buf.clear();
     }
 
-    (items, cover_id)
+    opf
 }
 
 /// Handle attributes on a Start or Empty OPF element.
@@ -363,11 +723,33 @@ fn handle_opf_open(
     }
 }
 
+/// Resolve an href against a directory inside the archive.
+/// Drops any fragment and collapses `.`/`..` segments (`Text/../Images/c.jpg`).
 fn resolve_path(base_dir: &str, href: &str) -> String {
-    if href.starts_with('/') {
-        href.trim_start_matches('/').to_string()
+    let href = href.split('#').next().unwrap_or("");
+    let joined = if href.starts_with('/') {
+        href.to_string()
     } else {
         format!("{}{}", base_dir, href)
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            s => parts.push(s),
+        }
+    }
+    parts.join("/")
+}
+
+/// Directory part of an archive path, including the trailing slash.
+fn parent_dir(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(i) => &path[..=i],
+        None => "",
     }
 }
 
@@ -499,6 +881,196 @@ mod tests {
         assert_eq!(meta.series_index, 2);
         assert_eq!(meta.cover_type, "image/jpeg");
         assert_eq!(meta.cover_data.unwrap(), cover);
+        assert!(meta.preview_text.is_empty());
+    }
+
+    #[test]
+    fn test_parse_epub_preview_follows_spine() {
+        let opf = br#"
+            <package>
+              <metadata><dc:title>T</dc:title></metadata>
+              <manifest>
+                <item id="c1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c2" href="Text/ch2.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine><itemref idref="c2"/><itemref idref="c1"/></spine>
+            </package>
+        "#;
+        let ch1 = br#"<html><body><p>Second  <i>chap</i>ter</p></body></html>"#;
+        let ch2 = br#"<html><head><title>Skip</title><style>p{}</style></head>
+            <body><h1>One</h1><p>Fish &amp; chips<br/>tonight&#8230;</p></body></html>"#;
+        let epub = make_epub(&[
+            (
+                "META-INF/container.xml",
+                br#"<container><rootfiles><rootfile full-path="OPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#,
+            ),
+            ("OPS/content.opf", opf),
+            ("OPS/Text/ch1.xhtml", ch1),
+            ("OPS/Text/ch2.xhtml", ch2),
+        ]);
+
+        let meta = parse(Cursor::new(epub)).unwrap();
+        assert_eq!(
+            meta.preview_text,
+            "One\nFish & chips\ntonight…\nSecond chapter"
+        );
+        assert_eq!(meta.word_count, 6);
+    }
+
+    fn make_png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::DynamicImage::new_rgb8(width, height);
+        let mut png = Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    fn extract_cover(opf: &[u8], entries: &[(&str, &[u8])]) -> Option<(Vec<u8>, String)> {
+        let zip_data = make_epub(entries);
+        let mut archive = zip::ZipArchive::new(Cursor::new(zip_data)).unwrap();
+        extract_cover_from_opf(opf, "OEBPS/content.opf", &mut archive)
+    }
+
+    #[test]
+    fn test_cover_from_first_spine_document() {
+        let opf = br#"
+            <package>
+              <manifest>
+                <item id="title" href="Text/title.xhtml" media-type="application/xhtml+xml"/>
+                <item id="img" href="Images/front.png" media-type="image/png"/>
+              </manifest>
+              <spine><itemref idref="title"/></spine>
+            </package>
+        "#;
+        let xhtml = br#"<html xmlns="http://www.w3.org/1999/xhtml"><body>
+            <div><img src="../Images/front.png" alt=""/></div></body></html>"#;
+        let png = make_png(300, 450);
+        let (data, media_type) = extract_cover(
+            opf,
+            &[
+                ("OEBPS/Text/title.xhtml", xhtml),
+                ("OEBPS/Images/front.png", &png),
+            ],
+        )
+        .unwrap();
+        assert_eq!(data, png);
+        assert_eq!(media_type, "image/png");
+    }
+
+    #[test]
+    fn test_cover_from_svg_cover_page_in_guide() {
+        let opf = br#"
+            <package>
+              <manifest>
+                <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="front" href="front.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine><itemref idref="ch1"/><itemref idref="front"/></spine>
+              <guide><reference type="cover" href="front.xhtml#top"/></guide>
+            </package>
+        "#;
+        let xhtml = br#"<html><body><svg xmlns:xlink="http://www.w3.org/1999/xlink">
+            <image width="600" height="900" xlink:href="art/f.img"/></svg></body></html>"#;
+        let png = make_png(200, 300);
+        let (data, media_type) = extract_cover(
+            opf,
+            &[
+                ("OEBPS/ch1.xhtml", b"<html><body><p>Text</p></body></html>"),
+                ("OEBPS/front.xhtml", xhtml),
+                ("OEBPS/art/f.img", &png),
+            ],
+        )
+        .unwrap();
+        assert_eq!(data, png);
+        // Not in the manifest: media type is sniffed from the data.
+        assert_eq!(media_type, "image/png");
+    }
+
+    #[test]
+    fn test_cover_from_nav_landmarks() {
+        let opf = br#"
+            <package>
+              <manifest>
+                <item id="nav" href="nav/toc.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+                <item id="p1" href="p1.xhtml" media-type="application/xhtml+xml"/>
+                <item id="img" href="i.png" media-type="image/png"/>
+              </manifest>
+            </package>
+        "#;
+        let nav = br#"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+            <nav epub:type="toc"><ol><li><a epub:type="cover" href="wrong.xhtml">X</a></li></ol></nav>
+            <nav epub:type="landmarks"><ol>
+              <li><a epub:type="cover" href="../p1.xhtml">Cover</a></li>
+            </ol></nav></body></html>"#;
+        let png = make_png(400, 600);
+        let (data, _) = extract_cover(
+            opf,
+            &[
+                ("OEBPS/nav/toc.xhtml", nav),
+                (
+                    "OEBPS/p1.xhtml",
+                    br#"<html><body><img src="i.png"/></body></html>"#,
+                ),
+                ("OEBPS/i.png", &png),
+            ],
+        )
+        .unwrap();
+        assert_eq!(data, png);
+    }
+
+    #[test]
+    fn test_cover_heuristics_reject_small_and_banner_images() {
+        let opf = br#"
+            <package>
+              <manifest>
+                <item id="c1" href="cover.xhtml" media-type="application/xhtml+xml"/>
+                <item id="orn" href="ornament.png" media-type="image/png"/>
+                <item id="logo" href="cover-logo.png" media-type="image/png"/>
+              </manifest>
+              <spine><itemref idref="c1"/></spine>
+            </package>
+        "#;
+        let ornament = make_png(40, 40);
+        let banner = make_png(900, 200);
+        let result = extract_cover(
+            opf,
+            &[
+                (
+                    "OEBPS/cover.xhtml",
+                    br#"<html><body><img src="ornament.png"/></body></html>"#,
+                ),
+                ("OEBPS/ornament.png", &ornament),
+                ("OEBPS/cover-logo.png", &banner),
+            ],
+        );
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_cover_from_image_file_name() {
+        let opf = br#"
+            <package>
+              <manifest>
+                <item id="i1" href="img/Cover_Front.png" media-type="image/png"/>
+              </manifest>
+            </package>
+        "#;
+        let png = make_png(300, 400);
+        let (data, media_type) =
+            extract_cover(opf, &[("OEBPS/img/Cover_Front.png", &png)]).unwrap();
+        assert_eq!(data, png);
+        assert_eq!(media_type, "image/png");
+    }
+
+    #[test]
+    fn test_explicit_cover_skips_empty_entry() {
+        let opf = br#"
+            <package>
+              <manifest>
+                <item id="cover" href="empty.jpg" media-type="image/jpeg" properties="cover-image"/>
+              </manifest>
+            </package>
+        "#;
+        assert!(extract_cover(opf, &[("OEBPS/empty.jpg", b"")]).is_none());
     }
 
     #[test]
@@ -519,6 +1091,14 @@ mod tests {
     fn test_helper_functions() {
         assert_eq!(resolve_path("OPS/", "img/c.jpg"), "OPS/img/c.jpg");
         assert_eq!(resolve_path("OPS/", "/img/c.jpg"), "img/c.jpg");
+        assert_eq!(
+            resolve_path("OPS/Text/", "../Images/c.jpg#x"),
+            "OPS/Images/c.jpg"
+        );
+        assert_eq!(parent_dir("OPS/content.opf"), "OPS/");
+        assert_eq!(parent_dir("content.opf"), "");
+        assert!(has_property("nav cover-image", "cover-image"));
+        assert!(!has_property("cover-images", "cover-image"));
         assert_eq!(local_name(b"dc:title"), "title");
         assert_eq!(local_name(b"title"), "title");
         assert!(path_in_metadata(&[
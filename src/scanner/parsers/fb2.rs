@@ -6,13 +6,26 @@ use quick_xml::XmlVersion;
 use quick_xml::events::Event;
 use quick_xml::reader::Reader;
 
-use super::{BookMeta, strip_meta};
+use super::{BookMeta, PreviewBuilder, strip_meta};
 
 /// Parse FB2 XML from any `BufRead` source and return extracted metadata.
 /// Tolerant of malformed XML: returns partial metadata on parse errors.
 /// Reads all data into memory first, then extracts cover from raw bytes
 /// if the XML parser fails before reaching <binary> elements.
-pub fn parse(mut reader: impl BufRead) -> Result<BookMeta, quick_xml::Error> {
+pub fn parse(reader: impl BufRead) -> Result<BookMeta, quick_xml::Error> {
+    parse_with(reader, PreviewBuilder::counting())
+}
+
+/// The text of the main `<body>`, one paragraph per line, cut after
+/// `max_chars` characters.
+pub fn body_text(reader: impl BufRead, max_chars: usize) -> Result<String, quick_xml::Error> {
+    parse_with(reader, PreviewBuilder::with_limit(max_chars)).map(|meta| meta.preview_text)
+}
+
+fn parse_with(
+    mut reader: impl BufRead,
+    mut preview: PreviewBuilder,
+) -> Result<BookMeta, quick_xml::Error> {
     // Read all data upfront so we can fall back to raw byte search for covers
     let mut raw_data = Vec::new();
     if reader.read_to_end(&mut raw_data).is_err() {
@@ -37,6 +50,9 @@ pub fn parse(mut reader: impl BufRead) -> Result<BookMeta, quick_xml::Error> {
     let mut in_annotation = false;
     let mut annotation_parts: Vec<String> = Vec::new();
     let mut description_done = false;
+    // Only the first <body> is the main text; later ones hold notes.
+    let mut bodies_seen = 0;
+    let mut in_body = false;
 
     loop {
         match xml.read_event_into(&mut buf) {
@@ -46,6 +62,9 @@ pub fn parse(mut reader: impl BufRead) -> Result<BookMeta, quick_xml::Error> {
             Ok(Event::Start(ref e)) => {
                 let local = local_name(e.name().as_ref());
                 handle_open_tag(&local, e, &path, &mut cover_ref, &mut meta, xml.decoder());
+                if local == "body" && bodies_seen == 0 {
+                    in_body = true;
+                }
                 path.push(local);
 
                 if matches_path(&path, &["description", "title-info", "annotation"]) {
@@ -88,6 +107,15 @@ pub fn parse(mut reader: impl BufRead) -> Result<BookMeta, quick_xml::Error> {
                     description_done = true;
                 }
 
+                if local == "body" {
+                    bodies_seen += 1;
+                    in_body = false;
+                } else if in_body
+                    && matches!(local.as_str(), "p" | "v" | "subtitle" | "text-author")
+                {
+                    preview.end_paragraph();
+                }
+
                 if !path.is_empty() {
                     path.pop();
                 }
@@ -149,9 +177,14 @@ pub fn parse(mut reader: impl BufRead) -> Result<BookMeta, quick_xml::Error> {
                             annotation_parts.push(t);
                         }
                     }
+                } else if in_body {
+                    preview.push(" ");
+                    preview.push(&text);
                 }
             }
 
+            Ok(Event::GeneralRef(ref r)) if in_body => preview.push_ref(r),
+
             _ => {}
         }
         buf.clear();
@@ -166,6 +199,7 @@ pub fn parse(mut reader: impl BufRead) -> Result<BookMeta, quick_xml::Error> {
         meta.cover_type = mime;
     }
 
+    (meta.preview_text, meta.word_count) = preview.finish_counted();
     Ok(meta)
 }
 
@@ -367,6 +401,25 @@ mod tests {
         assert_eq!(meta.cover_data.unwrap(), cover_bytes);
     }
 
+    #[test]
+    fn test_parse_fb2_preview_skips_notes_body() {
+        let fb2 = r#"<FictionBook>
+  <description><title-info><book-title>T</book-title></title-info></description>
+  <body>
+    <title><p>Chapter  1</p></title>
+    <section><p>Tom &amp; Jerry <emphasis>ran</emphasis> home&#33;</p>
+    <poem><stanza><v>A verse</v></stanza></poem></section>
+  </body>
+  <body name="notes"><section><p>Footnote</p></section></body>
+</FictionBook>"#;
+        let meta = parse(Cursor::new(fb2.as_bytes())).unwrap();
+        assert_eq!(
+            meta.preview_text,
+            "Chapter 1\nTom & Jerry ran home!\nA verse"
+        );
+        assert_eq!(meta.word_count, 8);
+    }
+
     #[test]
     fn test_parse_fb2_windows_1251_encoding() {
         // FB2 with windows-1251 declared encoding, Cyrillic title/author.
@@ -0,0 +1,214 @@
+use super::{BookMeta, strip_meta};
+
+/// A placeholder in a filename template.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Author,
+    Title,
+    Series,
+    SerNo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Field(Field),
+}
+
+/// A compiled filename template such as `"{author} - {series} #{ser_no} - {title}"`.
+///
+/// Supported placeholders are `{author}`, `{title}`, `{series}` and `{ser_no}`.
+/// Text between placeholders must appear literally in the file stem;
+/// `{ser_no}` only matches digits. Several authors in `{author}` are
+/// separated with `;`.
+#[derive(Debug, Clone)]
+pub struct FilenamePattern {
+    tokens: Vec<Token>,
+}
+
+impl FilenamePattern {
+    /// Parse a template. Fails on unknown or adjacent placeholders, which
+    /// would make the match ambiguous.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            literal.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                return Err(format!("unclosed placeholder in '{template}'"));
+            };
+            let field = match &rest[start + 1..start + len] {
+                "author" => Field::Author,
+                "title" => Field::Title,
+                "series" => Field::Series,
+                "ser_no" => Field::SerNo,
+                other => return Err(format!("unknown placeholder {{{other}}} in '{template}'")),
+            };
+            if literal.is_empty() && matches!(tokens.last(), Some(Token::Field(_))) {
+                return Err(format!("adjacent placeholders in '{template}'"));
+            }
+            if !literal.is_empty() {
+                tokens.push(Token::Literal(std::mem::take(&mut literal)));
+            }
+            tokens.push(Token::Field(field));
+            rest = &rest[start + len + 1..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+        if !tokens.iter().any(|t| matches!(t, Token::Field(_))) {
+            return Err(format!("no placeholders in '{template}'"));
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Match the whole `stem` against the template and return the captured
+    /// placeholder values in template order.
+    fn captures<'a>(&self, stem: &'a str) -> Option<Vec<(Field, &'a str)>> {
+        let mut out = Vec::new();
+        match_tokens(&self.tokens, stem, &mut out).then_some(out)
+    }
+}
+
+fn match_tokens<'a>(tokens: &[Token], s: &'a str, out: &mut Vec<(Field, &'a str)>) -> bool {
+    let Some((first, rest)) = tokens.split_first() else {
+        return s.is_empty();
+    };
+    match first {
+        Token::Literal(lit) => s
+            .strip_prefix(lit.as_str())
+            .is_some_and(|s| match_tokens(rest, s, out)),
+        Token::Field(field) => {
+            // Placeholders are never adjacent, so a field either runs to the
+            // end of the stem or stops at an occurrence of the next literal.
+            let ends: Vec<usize> = match rest.first() {
+                None => vec![s.len()],
+                Some(Token::Literal(lit)) => {
+                    s.match_indices(lit.as_str()).map(|(i, _)| i).collect()
+                }
+                Some(Token::Field(_)) => return false,
+            };
+            for end in ends {
+                let value = &s[..end];
+                if value.trim().is_empty() {
+                    continue;
+                }
+                if *field == Field::SerNo && !value.trim().chars().all(|c| c.is_ascii_digit()) {
+                    continue;
+                }
+                out.push((*field, value));
+                if match_tokens(rest, &s[end..], out) {
+                    return true;
+                }
+                out.pop();
+            }
+            false
+        }
+    }
+}
+
+/// Compile the configured templates, skipping invalid ones with a warning.
+pub fn compile_patterns(templates: &[String]) -> Vec<FilenamePattern> {
+    templates
+        .iter()
+        .filter_map(|t| match FilenamePattern::parse(t) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                tracing::warn!("Ignoring filename pattern: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Fill `meta` from the first pattern matching the file `stem`.
+/// Returns `true` when a pattern matched.
+pub fn apply_patterns(patterns: &[FilenamePattern], stem: &str, meta: &mut BookMeta) -> bool {
+    let Some(captures) = patterns.iter().find_map(|p| p.captures(stem)) else {
+        return false;
+    };
+    for (field, value) in captures {
+        match field {
+            Field::Author => {
+                meta.authors = value
+                    .split(';')
+                    .map(strip_meta)
+                    .filter(|a| !a.is_empty())
+                    .collect();
+            }
+            Field::Title => {
+                let title = value.trim();
+                if !title.is_empty() {
+                    meta.title = title.to_string();
+                }
+            }
+            Field::Series => {
+                let series = strip_meta(value);
+                meta.series_title = (!series.is_empty()).then_some(series);
+            }
+            Field::SerNo => meta.series_index = value.trim().parse().unwrap_or(0),
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(templates: &[&str], stem: &str) -> Option<BookMeta> {
+        let templates: Vec<String> = templates.iter().map(|t| t.to_string()).collect();
+        let patterns = compile_patterns(&templates);
+        let mut meta = BookMeta {
+            title: stem.to_string(),
+            ..Default::default()
+        };
+        apply_patterns(&patterns, stem, &mut meta).then_some(meta)
+    }
+
+    #[test]
+    fn test_series_pattern() {
+        let meta = apply(
+            &["{author} - {series} #{ser_no} - {title}"],
+            "Isaac Asimov - Foundation #2 - Foundation and Empire",
+        )
+        .unwrap();
+        assert_eq!(meta.authors, vec!["Isaac Asimov"]);
+        assert_eq!(meta.series_title.as_deref(), Some("Foundation"));
+        assert_eq!(meta.series_index, 2);
+        assert_eq!(meta.title, "Foundation and Empire");
+    }
+
+    #[test]
+    fn test_first_matching_pattern_wins() {
+        let templates = [
+            "{author} - {series} #{ser_no} - {title}",
+            "{author} - {title}",
+        ];
+        let meta = apply(&templates, "Strugatsky A.; Strugatsky B. - Roadside Picnic").unwrap();
+        assert_eq!(meta.authors, vec!["Strugatsky A", "Strugatsky B"]);
+        assert_eq!(meta.title, "Roadside Picnic");
+        assert!(meta.series_title.is_none());
+
+        // A title containing the separator falls through to the second template.
+        let meta = apply(&templates, "Author - Title - With Dash").unwrap();
+        assert_eq!(meta.authors, vec!["Author"]);
+        assert_eq!(meta.title, "Title - With Dash");
+    }
+
+    #[test]
+    fn test_ser_no_requires_digits() {
+        assert!(apply(&["{series} #{ser_no} - {title}"], "Dune #one - Dune").is_none());
+        assert!(apply(&["{author} - {title}"], "no separator").is_none());
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        assert!(FilenamePattern::parse("{author}{title}").is_err());
+        assert!(FilenamePattern::parse("{author} - {name}").is_err());
+        assert!(FilenamePattern::parse("{author").is_err());
+        assert!(FilenamePattern::parse("plain text").is_err());
+    }
+}
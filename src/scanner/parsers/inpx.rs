@@ -1,6 +1,9 @@
+use std::borrow::Cow;
 use std::io::{BufRead, BufReader, Read, Seek};
 use std::path::Path;
 
+use encoding_rs::Encoding;
+
 use super::{BookMeta, strip_meta};
 
 const INPX_SEPARATOR: u8 = 0x04;
@@ -50,11 +53,37 @@ pub struct InpxRecord {
     pub meta: BookMeta,
 }
 
+/// Collection metadata stored next to the `.inp` files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InpxInfo {
+    /// First line of `collection.info` (the collection display name).
+    pub collection_name: Option<String>,
+    /// First line of `version.info` (collection release, e.g. `20240101`).
+    pub version: Option<String>,
+}
+
+/// Read `collection.info` and `version.info` from an INPX archive.
+/// Text that is not valid UTF-8 is decoded with `encoding`.
+pub fn read_info<R: Read + Seek>(
+    inpx_reader: R,
+    encoding: &'static Encoding,
+) -> Result<InpxInfo, InpxError> {
+    let mut archive = zip::ZipArchive::new(inpx_reader)?;
+    Ok(InpxInfo {
+        collection_name: read_info_line(&mut archive, "collection.info", encoding),
+        version: read_info_line(&mut archive, "version.info", encoding),
+    })
+}
+
 /// Parse all book records from an INPX archive.
-/// `inpx_reader` should be a seekable reader over the .inpx ZIP file.
-pub fn parse<R: Read + Seek>(inpx_reader: R) -> Result<Vec<InpxRecord>, InpxError> {
+/// `inpx_reader` should be a seekable reader over the .inpx ZIP file;
+/// records that are not valid UTF-8 are decoded with `encoding`.
+pub fn parse<R: Read + Seek>(
+    inpx_reader: R,
+    encoding: &'static Encoding,
+) -> Result<Vec<InpxRecord>, InpxError> {
     let mut records = Vec::new();
-    parse_grouped_streaming(inpx_reader, |_folder, mut batch| {
+    parse_grouped_streaming(inpx_reader, encoding, |_folder, mut batch| {
         records.append(&mut batch);
         Ok(())
     })?;
@@ -68,6 +97,7 @@ pub fn parse<R: Read + Seek>(inpx_reader: R) -> Result<Vec<InpxRecord>, InpxErro
 /// Returns total number of parsed records delivered to `on_batch`.
 pub fn parse_grouped_streaming<R: Read + Seek, F>(
     inpx_reader: R,
+    encoding: &'static Encoding,
     mut on_batch: F,
 ) -> Result<u64, InpxError>
 where
@@ -95,7 +125,7 @@ where
         let folder = default_folder(inp_name);
         let entry = archive.by_name(inp_name)?;
         let reader = BufReader::new(entry);
-        let records = parse_inp_records(reader, &folder, &field_index);
+        let records = parse_inp_records(reader, &folder, &field_index, encoding);
         if !records.is_empty() {
             total_parsed += records.len() as u64;
             on_batch(folder, records)?;
@@ -171,9 +201,7 @@ fn parse_inp_line(line: &str, folder: &str, idx: &InpxFieldIndex) -> Option<Inpx
         docdate,
         series_title,
         series_index,
-        annotation: String::new(),
-        cover_data: None,
-        cover_type: String::new(),
+        ..Default::default()
     };
 
     Some(InpxRecord {
@@ -185,20 +213,58 @@ fn parse_inp_line(line: &str, folder: &str, idx: &InpxFieldIndex) -> Option<Inpx
     })
 }
 
-fn parse_inp_records(reader: impl BufRead, folder: &str, idx: &InpxFieldIndex) -> Vec<InpxRecord> {
+fn parse_inp_records(
+    reader: impl BufRead,
+    folder: &str,
+    idx: &InpxFieldIndex,
+    encoding: &'static Encoding,
+) -> Vec<InpxRecord> {
     let mut records = Vec::new();
-    for line in reader.lines() {
+    for line in reader.split(b'\n') {
         let line = match line {
             Ok(l) => l,
             Err(_) => continue,
         };
-        if let Some(record) = parse_inp_line(&line, folder, idx) {
+        if let Some(record) = parse_inp_line(&decode_text(&line, encoding), folder, idx) {
             records.push(record);
         }
     }
     records
 }
 
+/// Decode INPX text: valid UTF-8 (with or without BOM) is taken as is, so
+/// UTF-8 collections keep working when a legacy codepage is configured.
+fn decode_text<'a>(bytes: &'a [u8], encoding: &'static Encoding) -> Cow<'a, str> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => encoding.decode_without_bom_handling(bytes).0,
+    }
+}
+
+/// First non-empty line of a top-level info entry (`collection.info`, ...).
+fn read_info_line<R: Read + Seek>(
+    archive: &mut zip::ZipArchive<R>,
+    name: &str,
+    encoding: &'static Encoding,
+) -> Option<String> {
+    let entry_name = archive
+        .file_names()
+        .find(|n| n.eq_ignore_ascii_case(name))?;
+    let entry_name = entry_name.to_string();
+    let mut data = Vec::new();
+    archive
+        .by_name(&entry_name)
+        .ok()?
+        .read_to_end(&mut data)
+        .ok()?;
+    decode_text(&data, encoding)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
 /// Default folder for an .inp entry: strip the .inp extension, append .zip.
 /// e.g. "fb2-000001-000500.inp" → "fb2-000001-000500.zip"
 fn default_folder(inp_name: &str) -> String {
@@ -347,7 +413,7 @@ mod tests {
             ),
         )]);
 
-        let records = parse(std::io::Cursor::new(zip_data)).unwrap();
+        let records = parse(std::io::Cursor::new(zip_data), encoding_rs::UTF_8).unwrap();
         assert_eq!(records.len(), 1);
 
         let r = &records[0];
@@ -382,7 +448,7 @@ mod tests {
             ("pack-0001.inp", &format!("{line}\n")),
         ]);
 
-        let records = parse(std::io::Cursor::new(zip_data)).unwrap();
+        let records = parse(std::io::Cursor::new(zip_data), encoding_rs::UTF_8).unwrap();
         assert_eq!(records.len(), 1);
         let r = &records[0];
         assert_eq!(r.filename, "book.fb2");
@@ -429,10 +495,14 @@ mod tests {
         ]);
 
         let mut groups: Vec<(String, Vec<InpxRecord>)> = Vec::new();
-        let parsed = parse_grouped_streaming(std::io::Cursor::new(zip_data), |folder, records| {
-            groups.push((folder, records));
-            Ok(())
-        })
+        let parsed = parse_grouped_streaming(
+            std::io::Cursor::new(zip_data),
+            encoding_rs::UTF_8,
+            |folder, records| {
+                groups.push((folder, records));
+                Ok(())
+            },
+        )
         .unwrap();
 
         assert_eq!(parsed, 2);
@@ -445,6 +515,60 @@ mod tests {
         assert_eq!(groups[1].1[0].filename, "second.fb2");
     }
 
+    #[test]
+    fn test_info_files_and_codepage_decoding() {
+        let line = inpx_line([
+            "Пушкин,Александр",
+            "poetry",
+            "Стихи",
+            "",
+            "0",
+            "verses",
+            "10",
+            "0",
+            "fb2",
+            "1830",
+            "ru",
+        ]);
+        let (cp866, _, _) = encoding_rs::IBM866.encode(&line);
+        let (name, _, _) = encoding_rs::IBM866.encode("Либрусек\r\nlibrusec.db\r\n");
+
+        let cursor = std::io::Cursor::new(Vec::new());
+        let mut zip = zip::ZipWriter::new(cursor);
+        let opts = zip::write::SimpleFileOptions::default();
+        zip.start_file("collection.info", opts).unwrap();
+        zip.write_all(&name).unwrap();
+        zip.start_file("version.info", opts).unwrap();
+        zip.write_all(b"\n20240101\n").unwrap();
+        zip.start_file("pack.inp", opts).unwrap();
+        zip.write_all(&cp866).unwrap();
+        zip.write_all(b"\n").unwrap();
+        let zip_data = zip.finish().unwrap().into_inner();
+
+        let info = read_info(std::io::Cursor::new(zip_data.clone()), encoding_rs::IBM866).unwrap();
+        assert_eq!(info.collection_name.as_deref(), Some("Либрусек"));
+        assert_eq!(info.version.as_deref(), Some("20240101"));
+
+        let records = parse(std::io::Cursor::new(zip_data), encoding_rs::IBM866).unwrap();
+        assert_eq!(records[0].meta.title, "Стихи");
+        assert_eq!(
+            records[0].meta.authors,
+            vec!["Пушкин Александр".to_string()]
+        );
+
+        // UTF-8 text is not re-decoded with the legacy codepage.
+        assert_eq!(
+            decode_text("Стихи".as_bytes(), encoding_rs::IBM866),
+            "Стихи"
+        );
+        let info = read_info(
+            std::io::Cursor::new(make_inpx_zip(&[("pack.inp", "")])),
+            encoding_rs::UTF_8,
+        )
+        .unwrap();
+        assert_eq!(info, InpxInfo::default());
+    }
+
     #[test]
     fn test_default_folder() {
         assert_eq!(default_folder("fb2-000001.inp"), "fb2-000001.zip");
@@ -453,7 +577,11 @@ mod tests {
 
     #[test]
     fn test_parse_invalid_zip_error() {
-        let err = parse(std::io::Cursor::new(b"not-a-zip".to_vec())).unwrap_err();
+        let err = parse(
+            std::io::Cursor::new(b"not-a-zip".to_vec()),
+            encoding_rs::UTF_8,
+        )
+        .unwrap_err();
         assert!(matches!(err, InpxError::Zip(_)));
     }
 }
@@ -1,7 +1,11 @@
+pub mod audio;
 pub mod epub;
 pub mod fb2;
+pub mod filename;
 pub mod inpx;
 pub mod mobi;
+pub mod rtf;
+pub mod txt;
 
 /// Metadata extracted from a single book file.
 #[derive(Debug, Clone, Default)]
@@ -18,6 +22,184 @@ pub struct BookMeta {
     pub cover_data: Option<Vec<u8>>,
     /// MIME type of the cover image (e.g. "image/jpeg").
     pub cover_type: String,
+    /// Audiobook narrators.
+    pub narrators: Vec<String>,
+    /// Audiobook running time in seconds (0 for text books).
+    pub duration_secs: i64,
+    /// First [`PREVIEW_WORDS`] words of the body text, one paragraph per line.
+    pub preview_text: String,
+    /// Words of the body text (FB2, EPUB, TXT); 0 when not counted.
+    pub word_count: i32,
+    /// Pages of PDF and DjVu books; 0 when unknown.
+    pub page_count: i32,
+}
+
+/// Number of body-text words kept as a book's "first lines" preview.
+pub const PREVIEW_WORDS: usize = 500;
+/// Upper bound on the preview length in characters, so it fits the MySQL
+/// `VARCHAR(3000)` column regardless of word length.
+pub const PREVIEW_MAX_CHARS: usize = 3000;
+
+/// Collects the first [`PREVIEW_WORDS`] words of a book's body text,
+/// paragraph by paragraph.
+#[derive(Debug)]
+pub(crate) struct PreviewBuilder {
+    paragraphs: Vec<String>,
+    current: String,
+    words: usize,
+    chars: usize,
+    truncated: bool,
+    max_words: usize,
+    max_chars: usize,
+    /// Keep reading after the preview is full, to count every word.
+    count_all: bool,
+    total_words: usize,
+}
+
+impl Default for PreviewBuilder {
+    fn default() -> Self {
+        Self {
+            paragraphs: Vec::new(),
+            current: String::new(),
+            words: 0,
+            chars: 0,
+            truncated: false,
+            max_words: PREVIEW_WORDS,
+            max_chars: PREVIEW_MAX_CHARS,
+            count_all: false,
+            total_words: 0,
+        }
+    }
+}
+
+impl PreviewBuilder {
+    /// Collect up to `max_chars` characters of text, with no word limit
+    /// (the body text of the full-text index).
+    pub fn with_limit(max_chars: usize) -> Self {
+        Self {
+            max_words: usize::MAX,
+            max_chars,
+            ..Self::default()
+        }
+    }
+
+    /// Collect the usual preview and count the words of the whole text
+    /// (see [`finish_counted`](Self::finish_counted)).
+    pub fn counting() -> Self {
+        Self {
+            count_all: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.truncated || self.words >= self.max_words
+    }
+
+    /// Nothing more is needed from the text: the preview is full and words
+    /// are not being counted.
+    pub fn is_done(&self) -> bool {
+        self.is_full() && !self.count_all
+    }
+
+    /// Append raw text to the current paragraph. Callers whose reader trims
+    /// text events must push their own separating space.
+    pub fn push(&mut self, text: &str) {
+        if !self.is_full() || self.count_all {
+            self.current.push_str(text);
+        }
+        if self.is_full() && !text.trim().is_empty() {
+            self.truncated = true;
+        }
+    }
+
+    /// Append a resolved entity or character reference.
+    pub fn push_ref(&mut self, r: &quick_xml::events::BytesRef<'_>) {
+        if let Ok(Some(c)) = r.resolve_char_ref() {
+            self.push(c.encode_utf8(&mut [0; 4]));
+            return;
+        }
+        let text = match r.decode().as_deref() {
+            Ok("amp") => " & ",
+            Ok("lt") => "<",
+            Ok("gt") => ">",
+            Ok("quot") => "\"",
+            Ok("apos") => "'",
+            _ => " ",
+        };
+        self.push(text);
+    }
+
+    pub fn end_paragraph(&mut self) {
+        if self.count_all {
+            self.total_words += count_words(&self.current);
+        }
+        let words = self
+            .current
+            .split_whitespace()
+            .map(|w| {
+                w.chars()
+                    .filter(|&c| (c as u32) < 0x10000)
+                    .collect::<String>()
+            })
+            .filter(|w| !w.is_empty());
+        let mut line: Vec<String> = Vec::new();
+        for word in words {
+            // One char for the separating space or newline, one for the ellipsis.
+            let len = word.chars().count() + 1;
+            if self.is_full() || self.chars + len + 1 > self.max_chars {
+                self.truncated = true;
+                break;
+            }
+            self.words += 1;
+            self.chars += len;
+            line.push(word);
+        }
+        if !line.is_empty() {
+            self.paragraphs.push(line.join(" "));
+        }
+        self.current.clear();
+    }
+
+    pub fn finish(self) -> String {
+        self.finish_counted().0
+    }
+
+    /// The preview and the number of words in the whole text (0 unless
+    /// built with [`counting`](Self::counting)).
+    pub fn finish_counted(mut self) -> (String, i32) {
+        self.end_paragraph();
+        let mut text = self.paragraphs.join("\n");
+        if self.truncated {
+            text.push('…');
+        }
+        (text, i32::try_from(self.total_words).unwrap_or(i32::MAX))
+    }
+}
+
+/// Build a preview from plain text, treating blank lines as paragraph breaks.
+pub fn preview_from_text(text: &str) -> String {
+    let mut preview = PreviewBuilder::default();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            preview.end_paragraph();
+        } else {
+            preview.push(line);
+            preview.push(" ");
+        }
+        if preview.is_full() {
+            break;
+        }
+    }
+    preview.finish()
+}
+
+/// Number of words in a text; dashes and other tokens without a letter or
+/// digit are not words.
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphanumeric))
+        .count()
 }
 
 /// Strip leading/trailing whitespace and common punctuation from metadata strings.
@@ -127,4 +309,33 @@ mod tests {
         assert_eq!(normalise_author_name("  Single  "), "Single");
         assert_eq!(normalise_author_name(""), "");
     }
+
+    #[test]
+    fn test_preview_from_text_keeps_paragraphs_and_caps_words() {
+        assert_eq!(
+            preview_from_text("First  line\nwrapped.\n\n\nSecond 🙂 para."),
+            "First line wrapped.\nSecond para."
+        );
+        let long = "word ".repeat(PREVIEW_WORDS + 10);
+        let preview = preview_from_text(&long);
+        assert!(preview.ends_with('…'));
+        assert_eq!(preview.split_whitespace().count(), PREVIEW_WORDS);
+        assert!(!preview_from_text(&"word ".repeat(PREVIEW_WORDS)).ends_with('…'));
+        let wide = "abcdefghijklmnopqrst ".repeat(PREVIEW_WORDS);
+        assert!(preview_from_text(&wide).chars().count() <= PREVIEW_MAX_CHARS);
+    }
+
+    #[test]
+    fn test_counting_preview_counts_past_the_cap() {
+        assert_eq!(count_words("Tom & Jerry — 42 times!"), 4);
+        let mut preview = PreviewBuilder::counting();
+        for _ in 0..PREVIEW_WORDS {
+            preview.push("word word ");
+            preview.end_paragraph();
+        }
+        assert!(!preview.is_done());
+        let (text, words) = preview.finish_counted();
+        assert_eq!(words, (PREVIEW_WORDS * 2) as i32);
+        assert!(text.split_whitespace().count() <= PREVIEW_WORDS);
+    }
 }
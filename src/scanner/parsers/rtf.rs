@@ -0,0 +1,243 @@
+use encoding_rs::{Encoding, WINDOWS_1252};
+
+use super::BookMeta;
+use super::txt::text_meta;
+
+/// Destinations whose text is not part of the document body.
+const SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "object",
+    "header",
+    "footer",
+    "headerl",
+    "headerr",
+    "footerl",
+    "footerr",
+    "footnote",
+    "listtable",
+    "listoverridetable",
+    "revtbl",
+    "rsidtbl",
+    "themedata",
+    "colorschememapping",
+    "datastore",
+    "latentstyles",
+    "xmlnstbl",
+];
+
+/// Parse an RTF book: title and annotation come from the body text, as for
+/// plain text files.
+pub fn parse(data: &[u8]) -> BookMeta {
+    text_meta(&to_text(data))
+}
+
+/// Group state that RTF scopes with braces.
+#[derive(Clone, Copy)]
+struct Group {
+    skip: bool,
+    /// Characters to drop after a `\uN` escape (`\ucN`).
+    uc: usize,
+}
+
+/// Extract the plain body text of an RTF document.
+///
+/// Handles `\par`/`\line` breaks, `\'hh` bytes in the `\ansicpg` codepage
+/// and `\uN` Unicode escapes; ignorable (`{\*...}`) and non-body destinations
+/// such as the font table or pictures are skipped.
+pub fn to_text(data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut encoding: &'static Encoding = WINDOWS_1252;
+    let mut group = Group { skip: false, uc: 1 };
+    let mut stack: Vec<Group> = Vec::new();
+    // Characters still to drop after a `\uN` escape.
+    let mut drop_chars = 0usize;
+    let mut i = 0;
+
+    // Flush bytes collected from `\'hh` escapes and plain text.
+    let flush = |pending: &mut Vec<u8>, out: &mut String, encoding: &'static Encoding| {
+        if !pending.is_empty() {
+            let (text, _) = encoding.decode_without_bom_handling(pending);
+            out.push_str(&text);
+            pending.clear();
+        }
+    };
+
+    while i < data.len() {
+        let b = data[i];
+        match b {
+            b'{' => {
+                flush(&mut pending, &mut out, encoding);
+                stack.push(group);
+                drop_chars = 0;
+                i += 1;
+                if data.get(i..i + 2) == Some(b"\\*") {
+                    group.skip = true;
+                }
+            }
+            b'}' => {
+                flush(&mut pending, &mut out, encoding);
+                group = stack.pop().unwrap_or(group);
+                drop_chars = 0;
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&next) = data.get(i) else {
+                    break;
+                };
+                if next == b'\'' {
+                    let hex = data.get(i + 1..i + 3).and_then(|h| {
+                        std::str::from_utf8(h)
+                            .ok()
+                            .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    });
+                    i += 3;
+                    if let Some(byte) = hex {
+                        if drop_chars > 0 {
+                            drop_chars -= 1;
+                        } else if !group.skip {
+                            pending.push(byte);
+                        }
+                    }
+                    continue;
+                }
+                if !next.is_ascii_alphabetic() {
+                    // Control symbol: escaped literal or special character.
+                    i += 1;
+                    if group.skip {
+                        continue;
+                    }
+                    match next {
+                        b'\\' | b'{' | b'}' => pending.push(next),
+                        b'~' => pending.push(b' '),
+                        b'\n' | b'\r' => {
+                            flush(&mut pending, &mut out, encoding);
+                            out.push('\n');
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                let start = i;
+                while i < data.len() && data[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+                let word = std::str::from_utf8(&data[start..i]).unwrap_or_default();
+                let num_start = i;
+                if i < data.len() && data[i] == b'-' {
+                    i += 1;
+                }
+                while i < data.len() && data[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let param: Option<i32> = std::str::from_utf8(&data[num_start..i])
+                    .ok()
+                    .and_then(|n| n.parse().ok());
+                if i < data.len() && data[i] == b' ' {
+                    i += 1;
+                }
+
+                if SKIPPED_DESTINATIONS.contains(&word) {
+                    group.skip = true;
+                    continue;
+                }
+                if group.skip {
+                    continue;
+                }
+                match word {
+                    "ansicpg" => {
+                        flush(&mut pending, &mut out, encoding);
+                        encoding = param
+                            .and_then(|cp| codepage_label(cp as u32))
+                            .and_then(|label| Encoding::for_label(label.as_bytes()))
+                            .unwrap_or(WINDOWS_1252);
+                    }
+                    "uc" => group.uc = param.unwrap_or(1).max(0) as usize,
+                    "u" => {
+                        flush(&mut pending, &mut out, encoding);
+                        if let Some(code) = param {
+                            // Values above 32767 are written as negative numbers.
+                            let code = if code < 0 { code + 65536 } else { code };
+                            if let Some(c) = char::from_u32(code as u32) {
+                                out.push(c);
+                            }
+                        }
+                        drop_chars = group.uc;
+                    }
+                    "par" | "line" | "sect" | "page" => {
+                        flush(&mut pending, &mut out, encoding);
+                        out.push('\n');
+                    }
+                    "tab" => pending.push(b'\t'),
+                    "emdash" => {
+                        flush(&mut pending, &mut out, encoding);
+                        out.push('—');
+                    }
+                    "endash" => {
+                        flush(&mut pending, &mut out, encoding);
+                        out.push('–');
+                    }
+                    _ => {}
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            _ => {
+                i += 1;
+                if drop_chars > 0 {
+                    drop_chars -= 1;
+                } else if !group.skip {
+                    pending.push(b);
+                }
+            }
+        }
+    }
+    flush(&mut pending, &mut out, encoding);
+    out
+}
+
+/// Map an `\ansicpg` number to an encoding label.
+fn codepage_label(cp: u32) -> Option<String> {
+    match cp {
+        866 => Some("ibm866".to_string()),
+        874 => Some("windows-874".to_string()),
+        932 => Some("shift_jis".to_string()),
+        936 => Some("gbk".to_string()),
+        949 => Some("euc-kr".to_string()),
+        950 => Some("big5".to_string()),
+        1250..=1258 => Some(format!("windows-{cp}")),
+        10000 => Some("macintosh".to_string()),
+        20866 => Some("koi8-r".to_string()),
+        21866 => Some("koi8-u".to_string()),
+        28591..=28599 => Some(format!("iso-8859-{}", cp - 28590)),
+        65001 => Some("utf-8".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_text_skips_destinations_and_decodes() {
+        let rtf = br"{\rtf1\ansi\ansicpg1251\deff0{\fonttbl{\f0 Times New Roman;}}{\info{\title Ignored}}{\*\generator Writer;}
+\pard \'cf\'e8\'ea\'ed\'e8\'ea\par
+Line \{one\}\line caf\u233?\par}";
+        let text = to_text(rtf);
+        assert_eq!(text, "Пикник\nLine {one}\ncafé\n");
+    }
+
+    #[test]
+    fn test_parse_rtf() {
+        let rtf = br"{\rtf1\ansi{\fonttbl{\f0 Arial;}}\pard\qc\b Roadside Picnic\b0\par\par\pard Red Schuhart went into the Zone.\par}";
+        let meta = parse(rtf);
+        assert_eq!(meta.title, "Roadside Picnic");
+        assert_eq!(meta.annotation, "Red Schuhart went into the Zone.");
+    }
+}
@@ -0,0 +1,150 @@
+use encoding_rs::{Encoding, IBM866, KOI8_R, UTF_8, WINDOWS_1251, WINDOWS_1252};
+
+use super::{BookMeta, count_words, preview_from_text};
+
+/// Longest first line still taken as a title; longer ones are prose.
+const MAX_TITLE_CHARS: usize = 120;
+/// Annotation snippet length in characters.
+const ANNOTATION_CHARS: usize = 500;
+
+/// Frequent Cyrillic letters, used to tell single-byte codepages apart.
+const COMMON_CYRILLIC: &str = "оеаинтсрвлкмдпуяы";
+
+/// Guess the encoding of a plain text file.
+///
+/// A BOM wins, then UTF-8 (a multi-byte sequence cut off at the end of the
+/// buffer is allowed, since callers may pass a prefix). Otherwise the Russian
+/// single-byte codepages are scored by how many frequent Cyrillic letters
+/// they decode to, with windows-1252 as the fallback for Western text.
+pub fn detect_encoding(data: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(data) {
+        return encoding;
+    }
+    match std::str::from_utf8(data) {
+        Ok(_) => return UTF_8,
+        Err(e) if e.error_len().is_none() => return UTF_8,
+        Err(_) => {}
+    }
+
+    let high_bytes = data.iter().filter(|b| **b >= 0x80).count();
+    let (best, score) = [WINDOWS_1251, KOI8_R, IBM866]
+        .into_iter()
+        .map(|enc| {
+            let (text, _) = enc.decode_without_bom_handling(data);
+            let score = text
+                .chars()
+                .filter(|c| COMMON_CYRILLIC.contains(*c))
+                .count();
+            (enc, score)
+        })
+        .max_by_key(|(_, score)| *score)
+        .unwrap_or((WINDOWS_1252, 0));
+    if score * 2 >= high_bytes {
+        best
+    } else {
+        WINDOWS_1252
+    }
+}
+
+/// Decode text with the detected encoding, dropping any BOM.
+pub fn decode(data: &[u8]) -> String {
+    let (text, _, _) = detect_encoding(data).decode(data);
+    text.into_owned()
+}
+
+/// Parse a plain text book: the first non-empty line becomes the title when
+/// it is short enough, and the text after it the annotation. An empty title
+/// means the caller should fall back to the filename.
+pub fn parse(data: &[u8]) -> BookMeta {
+    text_meta(&decode(data))
+}
+
+/// Build title and annotation from already decoded text.
+pub(super) fn text_meta(text: &str) -> BookMeta {
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    let mut meta = BookMeta::default();
+    let mut rest: Vec<&str> = Vec::new();
+    if let Some(first) = lines.next() {
+        if first.chars().count() <= MAX_TITLE_CHARS {
+            meta.title = first.to_string();
+        } else {
+            rest.push(first);
+        }
+    }
+
+    let mut len = rest.iter().map(|l| l.chars().count()).sum::<usize>();
+    for line in lines {
+        if len > ANNOTATION_CHARS {
+            break;
+        }
+        len += line.chars().count() + 1;
+        rest.push(line);
+    }
+    meta.annotation = snippet(&rest.join(" "), ANNOTATION_CHARS);
+    meta.preview_text = if meta.title.is_empty() {
+        preview_from_text(text)
+    } else {
+        let start = text.find(meta.title.as_str()).unwrap_or(0) + meta.title.len();
+        preview_from_text(&text[start..])
+    };
+    meta.word_count = i32::try_from(count_words(text)).unwrap_or(i32::MAX);
+    meta
+}
+
+/// Collapse whitespace and cut `text` at a word boundary.
+fn snippet(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    let cut = match cut.rfind(' ') {
+        Some(pos) if pos > 0 => &cut[..pos],
+        _ => cut.as_str(),
+    };
+    format!(
+        "{}…",
+        cut.trim_end_matches(|c: char| c.is_ascii_punctuation())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_encoding() {
+        let text = "Война и мир\nЛев Толстой написал этот роман";
+        assert_eq!(detect_encoding(text.as_bytes()), UTF_8);
+        for enc in [WINDOWS_1251, KOI8_R, IBM866] {
+            let (bytes, _, _) = enc.encode(text);
+            assert_eq!(detect_encoding(&bytes), enc, "{}", enc.name());
+        }
+        let (bytes, _, _) = WINDOWS_1252.encode("Café déjà vu, naïve façade");
+        assert_eq!(detect_encoding(&bytes), WINDOWS_1252);
+
+        // A UTF-8 prefix cut in the middle of a character is still UTF-8.
+        let bytes = "Привет".as_bytes();
+        assert_eq!(detect_encoding(&bytes[..bytes.len() - 1]), UTF_8);
+    }
+
+    #[test]
+    fn test_parse_title_and_annotation() {
+        let (bytes, _, _) = WINDOWS_1251
+            .encode("\r\n\r\n  Пикник на обочине  \r\n\r\nПервая   строка.\r\nВторая строка.\r\n");
+        let meta = parse(&bytes);
+        assert_eq!(meta.title, "Пикник на обочине");
+        assert_eq!(meta.annotation, "Первая строка. Вторая строка.");
+        assert_eq!(meta.preview_text, "Первая строка. Вторая строка.");
+        assert_eq!(meta.word_count, 7);
+    }
+
+    #[test]
+    fn test_long_first_line_is_not_a_title() {
+        let text = "word ".repeat(200);
+        let meta = parse(text.as_bytes());
+        assert!(meta.title.is_empty());
+        assert!(meta.annotation.ends_with('…'));
+        assert!(meta.annotation.chars().count() <= ANNOTATION_CHARS + 1);
+    }
+}
@@ -0,0 +1,400 @@
+use super::inpx::{inpx_catalog_unchanged, read_inpx_info};
+use super::zip::list_zip_entry_names;
+use super::*;
+use crate::db::queries::suppressed;
+
+/// Changes a scan would apply, computed without touching the database.
+///
+/// Book entries are `path/filename` as stored in the `books` table; archive
+/// entries are paths relative to the library root.
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct ScanPreview {
+    /// Book files (including ZIP members) not yet in the library.
+    pub new_books: Vec<String>,
+    /// Available books whose file is gone from disk.
+    pub missing_books: Vec<String>,
+    /// ZIP and INPX archives not catalogued yet.
+    pub new_archives: Vec<String>,
+    /// Catalogued archives whose size (or mtime) no longer matches.
+    pub changed_archives: Vec<String>,
+    /// Archives and folders that could not be read.
+    pub errors: u64,
+    /// Paths the walk left out (symlink loops, depth limit, unreadable).
+    pub skipped_paths: Vec<String>,
+}
+
+impl ScanPreview {
+    pub fn is_empty(&self) -> bool {
+        self.new_books.is_empty()
+            && self.missing_books.is_empty()
+            && self.new_archives.is_empty()
+            && self.changed_archives.is_empty()
+    }
+}
+
+/// Walk the library like [`run_scan`] and report what it would add, remove
+/// or re-read. Holds the scan lock so the report is not raced by a real scan.
+pub async fn preview_scan(pool: &DbPool, config: &Config) -> Result<ScanPreview, ScanError> {
+    preview_scoped_scan(pool, config, None).await
+}
+
+/// Preview a partial scan of `path`, as run by [`run_scan_path`].
+pub async fn preview_scan_path(
+    pool: &DbPool,
+    config: &Config,
+    path: &str,
+) -> Result<ScanPreview, ScanError> {
+    let scope = resolve_scan_path(&config.library.root_path, path)?;
+    preview_scoped_scan(pool, config, scope.as_deref()).await
+}
+
+async fn preview_scoped_scan(
+    pool: &DbPool,
+    config: &Config,
+    scope: Option<&str>,
+) -> Result<ScanPreview, ScanError> {
+    if SCAN_LOCK
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err(ScanError::AlreadyRunning);
+    }
+
+    let result = do_preview(pool, config, scope).await;
+
+    SCAN_LOCK.store(false, Ordering::SeqCst);
+
+    result
+}
+
+#[derive(Debug, PartialEq)]
+enum ArchiveState {
+    New,
+    Changed,
+    Unchanged,
+}
+
+async fn archive_state(
+    pool: &DbPool,
+    rel_path: &str,
+    cat_type: CatType,
+    size: i64,
+    mtime: &str,
+    skip_unchanged: bool,
+) -> Result<ArchiveState, ScanError> {
+    let Some(cat) = catalogs::find_by_path(pool, rel_path).await? else {
+        return Ok(ArchiveState::New);
+    };
+    if CatType::try_from(cat.cat_type).ok() != Some(cat_type) {
+        return Ok(ArchiveState::New);
+    }
+    if cat.cat_size != size
+        || (skip_unchanged
+            && !mtime.is_empty()
+            && !cat.cat_mtime.is_empty()
+            && cat.cat_mtime != mtime)
+    {
+        return Ok(ArchiveState::Changed);
+    }
+    Ok(ArchiveState::Unchanged)
+}
+
+fn book_key(path: &str, filename: &str) -> String {
+    if path.is_empty() {
+        filename.to_string()
+    } else {
+        format!("{path}/{filename}")
+    }
+}
+
+async fn do_preview(
+    pool: &DbPool,
+    config: &Config,
+    scope: Option<&str>,
+) -> Result<ScanPreview, ScanError> {
+    let root = config.library.root_path.clone();
+    if crate::vfs::is_remote(&root) {
+        return Err(ScanError::Internal(
+            "dry-run preview is not available for remote libraries".to_string(),
+        ));
+    }
+    let extensions: HashSet<String> = config
+        .library
+        .book_extensions
+        .iter()
+        .map(|e| e.to_lowercase())
+        .collect();
+    let scan_zip = config.library.scan_zip;
+    let inpx_enable = config.library.inpx_enable;
+    let audiobooks = config.library.audiobooks;
+    let skip_unchanged = config.scanner.skip_unchanged;
+    let encoding = codepage_encoding(&config.library.zip_codepage);
+
+    let walk_from = match scope {
+        Some(path) => crate::vfs::local_path(&root, path),
+        None => root.clone(),
+    };
+    info!("Previewing library scan: {}", walk_from.display());
+
+    let in_scope = |path: &str| {
+        scope.is_none_or(|scope| {
+            path == scope || path.strip_prefix(scope).is_some_and(|r| r.starts_with('/'))
+        })
+    };
+    let mut remaining: HashMap<String, HashSet<String>> = HashMap::new();
+    for row in books::list_available_for_scan(pool).await? {
+        if in_scope(&row.path) {
+            remaining.entry(row.path).or_default().insert(row.filename);
+        }
+    }
+
+    let walk_root = root.clone();
+    let walk_extensions = extensions.clone();
+    let exclusions = Exclusions::load(pool, config).await?;
+    let max_depth = config.scanner.max_depth;
+    let walk_stats = Arc::new(ScanStats::default());
+    let collect_stats = Arc::clone(&walk_stats);
+    let entries = tokio::task::spawn_blocking(move || {
+        collect_entries(
+            &walk_root,
+            &walk_from,
+            &walk_extensions,
+            &exclusions,
+            max_depth,
+            &collect_stats,
+            scan_zip,
+            inpx_enable,
+            audiobooks,
+        )
+    })
+    .await
+    .map_err(|e| ScanError::Internal(e.to_string()))??;
+
+    let walked = walk_stats.snapshot();
+    let mut preview = ScanPreview {
+        errors: walked.errors,
+        skipped_paths: walked
+            .skipped_paths
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        ..ScanPreview::default()
+    };
+    let mut inpx_entries = Vec::new();
+
+    for entry in entries {
+        match entry {
+            ScanEntry::File {
+                rel_path, filename, ..
+            } => {
+                let known = remaining
+                    .get_mut(&rel_path)
+                    .is_some_and(|names| names.remove(&filename));
+                if !known && !suppressed::is_suppressed(pool, &rel_path, &filename).await? {
+                    preview.new_books.push(book_key(&rel_path, &filename));
+                }
+            }
+            ScanEntry::Zip {
+                path,
+                rel_path,
+                mtime,
+            } => {
+                let zip_filename = crate::vfs::decode_name(path.file_name().unwrap_or_default());
+                let rel_zip = book_key(&rel_path, &zip_filename);
+                let size = fs::metadata(&path)?.len() as i64;
+                let state =
+                    archive_state(pool, &rel_zip, CatType::Zip, size, &mtime, skip_unchanged)
+                        .await?;
+                if state == ArchiveState::Unchanged {
+                    remaining.remove(&rel_zip);
+                    continue;
+                }
+                if state == ArchiveState::New {
+                    preview.new_archives.push(rel_zip.clone());
+                } else {
+                    preview.changed_archives.push(rel_zip.clone());
+                }
+
+                let zip_path = path.clone();
+                let zip_extensions = extensions.clone();
+                let names = tokio::task::spawn_blocking(move || {
+                    list_zip_entry_names(&zip_path, &zip_extensions, encoding)
+                })
+                .await
+                .map_err(|e| ScanError::Internal(e.to_string()))?;
+                let names = match names {
+                    Ok(names) => names,
+                    Err(e) => {
+                        warn!("Failed to read ZIP {}: {e}", path.display());
+                        preview.errors += 1;
+                        remaining.remove(&rel_zip);
+                        continue;
+                    }
+                };
+                for filename in names {
+                    let known = remaining
+                        .get_mut(&rel_zip)
+                        .is_some_and(|known| known.remove(&filename));
+                    if !known && !suppressed::is_suppressed(pool, &rel_zip, &filename).await? {
+                        preview.new_books.push(book_key(&rel_zip, &filename));
+                    }
+                }
+            }
+            ScanEntry::Inpx {
+                path,
+                rel_path,
+                mtime,
+            } => inpx_entries.push((path, rel_path, mtime)),
+            // Remote libraries are rejected above.
+            ScanEntry::RemoteFile { .. } | ScanEntry::RemoteZip { .. } => {}
+        }
+    }
+
+    // INPX contents are not diffed: books under an index directory are kept
+    // as long as the index exists. Handled last so that plain files in
+    // subdirectories have already been matched.
+    for (path, rel_path, mtime) in inpx_entries {
+        let size = fs::metadata(&path)?.len() as i64;
+        let info_path = path.clone();
+        let version = tokio::task::spawn_blocking(move || read_inpx_info(&info_path, encoding))
+            .await
+            .map_err(|e| ScanError::Internal(e.to_string()))?
+            .map(|info| info.version.unwrap_or_default());
+        let version = match version {
+            Ok(version) => version,
+            Err(e) => {
+                warn!("Failed to read INPX {}: {e}", path.display());
+                preview.errors += 1;
+                String::new()
+            }
+        };
+        match catalogs::find_by_path(pool, &rel_path).await? {
+            Some(cat) if CatType::try_from(cat.cat_type).ok() == Some(CatType::Inpx) => {
+                if !inpx_catalog_unchanged(&cat, size, &mtime, &version, skip_unchanged) {
+                    preview.changed_archives.push(rel_path.clone());
+                }
+            }
+            _ => preview.new_archives.push(rel_path.clone()),
+        }
+        let inpx_dir = Path::new(&rel_path)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_string_lossy()
+            .to_string();
+        let prefix = format!("{inpx_dir}/");
+        remaining.retain(|p, _| !(inpx_dir.is_empty() || *p == inpx_dir || p.starts_with(&prefix)));
+    }
+
+    for (path, names) in remaining {
+        preview
+            .missing_books
+            .extend(names.iter().map(|name| book_key(&path, name)));
+    }
+
+    preview.new_books.sort();
+    preview.missing_books.sort();
+    preview.new_archives.sort();
+    preview.changed_archives.sort();
+
+    info!(
+        "Scan preview: new={}, missing={}, new_archives={}, changed_archives={}, errors={}",
+        preview.new_books.len(),
+        preview.missing_books.len(),
+        preview.new_archives.len(),
+        preview.changed_archives.len(),
+        preview.errors
+    );
+
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn preview_config(root: &Path) -> Config {
+        let mut cfg: Config = toml::from_str(
+            r#"
+[server]
+base_url = "http://127.0.0.1:8081"
+[library]
+root_path = "/tmp"
+[database]
+[opds]
+[scanner]
+"#,
+        )
+        .unwrap();
+        cfg.library.root_path = root.to_path_buf();
+        cfg
+    }
+
+    fn write_zip(path: &Path, names: &[&str]) {
+        let mut zip = ::zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for name in names {
+            zip.start_file(*name, ::zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(b"data").unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    async fn insert_book(pool: &DbPool, catalog_id: i64, path: &str, filename: &str) {
+        books::insert(
+            pool,
+            catalog_id,
+            filename,
+            path,
+            "fb2",
+            filename,
+            &filename.to_uppercase(),
+            "",
+            "",
+            "en",
+            2,
+            1,
+            CatType::Normal,
+            0,
+            "",
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_preview_scan_reports_changes_without_writing() {
+        let pool = create_test_pool().await;
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("sub/kept.fb2"), b"x").unwrap();
+        fs::write(root.join("sub/new.fb2"), b"x").unwrap();
+        write_zip(&root.join("pack.zip"), &["a.fb2", "b.fb2"]);
+
+        let cat = ensure_catalog(&pool, "sub", CatType::Normal).await.unwrap();
+        let zip_cat = ensure_catalog(&pool, "pack.zip", CatType::Zip)
+            .await
+            .unwrap();
+        insert_book(&pool, cat, "sub", "kept.fb2").await;
+        insert_book(&pool, cat, "sub", "gone.fb2").await;
+        insert_book(&pool, zip_cat, "pack.zip", "a.fb2").await;
+
+        let preview = do_preview(&pool, &preview_config(root), None)
+            .await
+            .unwrap();
+        assert_eq!(preview.new_books, vec!["pack.zip/b.fb2", "sub/new.fb2"]);
+        assert_eq!(preview.missing_books, vec!["sub/gone.fb2"]);
+        assert_eq!(preview.changed_archives, vec!["pack.zip"]);
+        assert!(preview.new_archives.is_empty());
+        assert!(!preview.is_empty());
+
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM books")
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        assert_eq!(count.0, 3, "preview must not touch the database");
+    }
+}
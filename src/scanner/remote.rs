@@ -0,0 +1,97 @@
+use super::*;
+
+use super::zip::try_skip_zip_archive;
+use crate::vfs::{LibraryFs, RemoteFs, StagedFile};
+
+/// List books and ZIP archives of a remote library below `walk_from`
+/// (relative to the library root, `""` for the whole library).
+pub(super) async fn collect_remote_entries(
+    fs: &RemoteFs,
+    walk_from: &str,
+    extensions: &HashSet<String>,
+    exclusions: &Exclusions,
+    scan_zip: bool,
+    inpx_enable: bool,
+    audiobooks: bool,
+) -> Result<Vec<ScanEntry>, ScanError> {
+    if inpx_enable || audiobooks {
+        warn!("INPX indexes and audiobook folders are not scanned in remote libraries");
+    }
+    let mut entries = Vec::new();
+    for file in fs.list_files(walk_from).await? {
+        if exclusions.is_excluded(&file.rel_path) {
+            continue;
+        }
+        let (dir, filename) = match file.rel_path.rsplit_once('/') {
+            Some((dir, name)) => (dir.to_string(), name.to_string()),
+            None => (String::new(), file.rel_path.clone()),
+        };
+        let ext = match Path::new(&filename).extension() {
+            Some(e) => e.to_string_lossy().to_lowercase(),
+            None => continue,
+        };
+        if ext == "zip" && scan_zip {
+            entries.push(ScanEntry::RemoteZip {
+                rel_path: dir,
+                filename,
+                size: file.size as i64,
+                mtime: file.mtime,
+            });
+        } else if extensions.contains(&ext) {
+            entries.push(ScanEntry::RemoteFile {
+                rel_path: dir,
+                filename,
+                extension: ext,
+                size: file.size as i64,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+fn join_rel(dir: &str, filename: &str) -> String {
+    if dir.is_empty() {
+        filename.to_string()
+    } else {
+        format!("{dir}/{filename}")
+    }
+}
+
+/// Download a new or changed remote book to a temporary file and parse it
+/// like a local one. Unchanged books are confirmed without a download.
+pub(super) async fn process_remote_file(
+    ctx: &ScanContext,
+    fs: &RemoteFs,
+    rel_path: &str,
+    filename: &str,
+    extension: &str,
+    size: i64,
+) -> Result<(), ScanError> {
+    if let Some(known) = ctx.existing_book(rel_path, filename)
+        && known.size == size
+    {
+        ctx.mark_existing_book_confirmed(known.id);
+        ctx.stats.books_skipped.fetch_add(1, Ordering::Relaxed);
+        return Ok(());
+    }
+    let staged = StagedFile::fetch(fs, &join_rel(rel_path, filename)).await?;
+    process_file(ctx, staged.path(), rel_path, filename, extension, size).await
+}
+
+/// Download a changed remote ZIP archive to a temporary file and scan it.
+pub(super) async fn process_remote_zip(
+    ctx: &ScanContext,
+    fs: &RemoteFs,
+    rel_path: &str,
+    filename: &str,
+    size: i64,
+    mtime: &str,
+) -> Result<(), ScanError> {
+    let rel_zip = join_rel(rel_path, filename);
+    if try_skip_zip_archive(&ctx.pool, &rel_zip, size, ctx.skip_unchanged, mtime).await? {
+        ctx.stats.archives_skipped.fetch_add(1, Ordering::Relaxed);
+        return Ok(());
+    }
+    let staged = StagedFile::fetch(fs, &rel_zip).await?;
+    process_zip(ctx, staged.path(), rel_path, mtime).await
+}
@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use walkdir::{DirEntry, WalkDir};
+
+use super::exclude::Exclusions;
+use super::rel_path;
+
+/// Why the walk left a path out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// A symlink pointing back at one of its own parent folders.
+    SymlinkLoop,
+    /// A symlink to a folder that another symlink already led to.
+    DuplicateLink,
+    /// A folder at `scanner.max_depth`; its contents were not walked.
+    MaxDepth,
+    /// A symlink whose target does not exist.
+    BrokenLink,
+    /// A folder or file that could not be read (permissions, I/O error).
+    Unreadable,
+}
+
+impl SkipReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::SymlinkLoop => "symlink_loop",
+            Self::DuplicateLink => "duplicate_link",
+            Self::MaxDepth => "max_depth",
+            Self::BrokenLink => "broken_link",
+            Self::Unreadable => "unreadable",
+        }
+    }
+}
+
+/// A path the walk left out, reported in the scan summary.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SkippedPath {
+    /// Path relative to the library root.
+    pub path: String,
+    pub reason: SkipReason,
+    /// Error message for unreadable paths.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+}
+
+/// Walk the library below `walk_from`, following symlinks, and hand every
+/// entry that is not excluded to `visit`. Symlink cycles, repeated links to
+/// the same folder, folders at `max_depth` (counted from `root`) and
+/// unreadable entries are passed to `on_skip` instead of being dropped
+/// silently.
+pub(super) fn walk_library(
+    root: &Path,
+    walk_from: &Path,
+    exclusions: &Exclusions,
+    max_depth: usize,
+    on_skip: &mut dyn FnMut(SkippedPath),
+    mut visit: impl FnMut(&DirEntry),
+) {
+    // A scoped scan starts below the root; keep the limit relative to it.
+    let scope_depth = walk_from
+        .strip_prefix(root)
+        .map(|p| p.components().count())
+        .unwrap_or(0);
+    let max_depth = max_depth.saturating_sub(scope_depth);
+    let mut linked_dirs: HashSet<PathBuf> = HashSet::new();
+
+    let mut walker = WalkDir::new(walk_from)
+        .follow_links(true)
+        .max_depth(max_depth)
+        .into_iter();
+    while let Some(next) = walker.next() {
+        let entry = match next {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = e.path().map(|p| rel_path(root, p)).unwrap_or_default();
+                let skipped = if e.loop_ancestor().is_some() {
+                    SkippedPath {
+                        path,
+                        reason: SkipReason::SymlinkLoop,
+                        detail: String::new(),
+                    }
+                } else if e
+                    .io_error()
+                    .is_some_and(|io| io.kind() == std::io::ErrorKind::NotFound)
+                {
+                    SkippedPath {
+                        path,
+                        reason: SkipReason::BrokenLink,
+                        detail: String::new(),
+                    }
+                } else {
+                    SkippedPath {
+                        path,
+                        reason: SkipReason::Unreadable,
+                        detail: e
+                            .io_error()
+                            .map(ToString::to_string)
+                            .unwrap_or_else(|| e.to_string()),
+                    }
+                };
+                on_skip(skipped);
+                continue;
+            }
+        };
+
+        let is_dir = entry.file_type().is_dir();
+        let rel = rel_path(root, entry.path());
+        if exclusions.is_excluded(&rel) {
+            if is_dir {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        if is_dir && entry.path_is_symlink() {
+            let first_link = fs::canonicalize(entry.path())
+                .map(|target| linked_dirs.insert(target))
+                .unwrap_or(true);
+            if !first_link {
+                on_skip(SkippedPath {
+                    path: rel,
+                    reason: SkipReason::DuplicateLink,
+                    detail: String::new(),
+                });
+                walker.skip_current_dir();
+                continue;
+            }
+        }
+        if is_dir && entry.depth() > 0 && entry.depth() == max_depth {
+            on_skip(SkippedPath {
+                path: rel,
+                reason: SkipReason::MaxDepth,
+                detail: String::new(),
+            });
+        }
+        visit(&entry);
+    }
+}
+
+impl std::fmt::Display for SkippedPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.path, self.reason.as_str())?;
+        if !self.detail.is_empty() {
+            write!(f, ": {}", self.detail)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn walk(root: &Path, max_depth: usize) -> (Vec<String>, Vec<(String, SkipReason)>) {
+        let mut files = Vec::new();
+        let mut skipped = Vec::new();
+        walk_library(
+            root,
+            root,
+            &Exclusions::default(),
+            max_depth,
+            &mut |s| skipped.push((s.path, s.reason)),
+            |e| {
+                if e.file_type().is_file() {
+                    files.push(rel_path(root, e.path()));
+                }
+            },
+        );
+        files.sort();
+        skipped.sort_by(|a, b| a.0.cmp(&b.0));
+        (files, skipped)
+    }
+
+    #[test]
+    fn test_symlink_loops_and_duplicate_links_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/book.fb2"), b"x").unwrap();
+        symlink(root.join("a"), root.join("a/b/back")).unwrap();
+        fs::create_dir(root.join("shared")).unwrap();
+        fs::write(root.join("shared/s.fb2"), b"x").unwrap();
+        symlink(root.join("shared"), root.join("link1")).unwrap();
+        symlink(root.join("shared"), root.join("link2")).unwrap();
+
+        let (files, skipped) = walk(root, 64);
+        assert!(files.contains(&"a/b/book.fb2".to_string()));
+        assert!(files.contains(&"shared/s.fb2".to_string()));
+        let links = files.iter().filter(|f| f.starts_with("link")).count();
+        assert_eq!(links, 1, "only the first link to a folder is walked");
+        assert!(skipped.contains(&("a/b/back".to_string(), SkipReason::SymlinkLoop)));
+        assert_eq!(
+            skipped
+                .iter()
+                .filter(|(_, r)| *r == SkipReason::DuplicateLink)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_max_depth_and_broken_links_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("one/two/three")).unwrap();
+        fs::write(root.join("one/top.fb2"), b"x").unwrap();
+        fs::write(root.join("one/two/three/deep.fb2"), b"x").unwrap();
+        symlink(root.join("missing"), root.join("dangling")).unwrap();
+
+        let (files, skipped) = walk(root, 2);
+        assert_eq!(files, vec!["one/top.fb2"]);
+        assert_eq!(
+            skipped,
+            vec![
+                ("dangling".to_string(), SkipReason::BrokenLink),
+                ("one/two".to_string(), SkipReason::MaxDepth),
+            ]
+        );
+    }
+}
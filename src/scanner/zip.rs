@@ -1,7 +1,17 @@
 use super::*;
-use std::io::BufReader;
+use parsers::filename::FilenamePattern;
+use std::io::{BufReader, Read, Seek};
+
+use encoding_rs::Encoding;
+
+/// Largest ZIP entry the low-memory profile buffers for parsing. FB2 and
+/// plain text are read incrementally whatever their size; bigger books of
+/// other formats get filename metadata.
+pub const LOW_MEMORY_ENTRY_BUFFER_BYTES: u64 = 16 * 1024 * 1024;
 
 pub(super) struct ZipBookEntry {
+    /// Full path of the entry inside the archive.
+    pub(super) entry_name: String,
     pub(super) filename: String,
     pub(super) extension: String,
     pub(super) size: i64,
@@ -15,11 +25,7 @@ pub(super) async fn process_zip(
     rel_dir: &str,
     mtime: &str,
 ) -> Result<(), ScanError> {
-    let zip_filename = zip_path
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
+    let zip_filename = crate::vfs::decode_name(zip_path.file_name().unwrap_or_default());
     let rel_zip = if rel_dir.is_empty() {
         zip_filename.clone()
     } else {
@@ -54,11 +60,19 @@ pub(super) async fn process_zip(
     let zip_path_buf = zip_path.to_path_buf();
     let extensions_clone = ctx.extensions.clone();
     let test_files = ctx.test_files;
+    let encoding = ctx.zip_encoding;
+
+    let low_memory = ctx.low_memory;
 
     let zip_entries = {
         let _permit = acquire_scan_permit(ctx).await?;
         tokio::task::spawn_blocking(move || {
-            read_zip_entries(&zip_path_buf, &extensions_clone, test_files)
+            if low_memory {
+                // Entries are read one by one when parsed below.
+                list_zip_book_entries(&zip_path_buf, &extensions_clone, encoding)
+            } else {
+                read_zip_entries(&zip_path_buf, &extensions_clone, test_files, encoding)
+            }
         })
         .await
         .map_err(|e| ScanError::Internal(e.to_string()))??
@@ -93,18 +107,44 @@ pub(super) async fn process_zip(
             continue;
         }
 
-        // Parse metadata from in-memory data
-        let meta = {
+        // Parse metadata from in-memory data, or straight from the archive
+        // under the low-memory profile.
+        let meta = if low_memory {
+            let zip_path = zip_path.to_path_buf();
+            let entry_name = ze.entry_name.clone();
+            let ext = ze.extension.clone();
+            let filename = ze.filename.clone();
+            let cover_cfg = ctx.cover_image_cfg;
+            let patterns = ctx.filename_patterns.clone();
+            let _permit = acquire_scan_permit(ctx).await?;
+            tokio::task::spawn_blocking(move || {
+                parse_zip_entry_streaming(
+                    &zip_path,
+                    &entry_name,
+                    encoding,
+                    &ext,
+                    &filename,
+                    test_files,
+                    cover_cfg,
+                    &patterns,
+                )
+            })
+            .await
+            .map_err(|e| ScanError::Internal(e.to_string()))?
+        } else {
             let data = ze.data.clone();
             let ext = ze.extension.clone();
             let filename = ze.filename.clone();
             let cover_cfg = ctx.cover_image_cfg;
+            let patterns = ctx.filename_patterns.clone();
             // Keep per-entry parse under the shared budget so ZIP parsing and
             // INPX enrichment parsing draw from the same global limit.
             let _permit = acquire_scan_permit(ctx).await?;
-            tokio::task::spawn_blocking(move || parse_book_bytes(&data, &ext, &filename, cover_cfg))
-                .await
-                .map_err(|e| ScanError::Internal(e.to_string()))?
+            tokio::task::spawn_blocking(move || {
+                parse_book_bytes(&data, &ext, &filename, cover_cfg, &patterns)
+            })
+            .await
+            .map_err(|e| ScanError::Internal(e.to_string()))?
         };
 
         let meta = match meta {
@@ -139,12 +179,13 @@ fn for_each_matching_zip_entry<S, H>(
     path: &Path,
     extensions: &HashSet<String>,
     test_files: bool,
+    encoding: &'static Encoding,
     mut should_take: S,
     mut handle: H,
 ) -> Result<(), ScanError>
 where
     S: FnMut(&str, &str, u64) -> bool,
-    H: FnMut(String, String, u64, Vec<u8>),
+    H: FnMut(String, String, String, u64, Vec<u8>),
 {
     let file = fs::File::open(path)?;
     let reader = BufReader::new(file);
@@ -162,20 +203,10 @@ where
             continue;
         }
 
-        let entry_name = entry.name().to_string();
-        let filename = Path::new(&entry_name)
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let ext = Path::new(&filename)
-            .extension()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_lowercase();
-        if !extensions.contains(&ext) {
+        let entry_name = zip_entry_name(&entry, encoding);
+        let Some((filename, ext)) = book_entry_name(&entry_name, extensions) else {
             continue;
-        }
+        };
 
         let declared_size = entry.size();
         if !should_take(&entry_name, &filename, declared_size) {
@@ -195,12 +226,157 @@ where
             continue;
         }
 
-        handle(filename, ext, declared_size, data);
+        handle(entry_name, filename, ext, declared_size, data);
     }
 
     Ok(())
 }
 
+/// Name of a ZIP entry. Names flagged as UTF-8 (or given in a Unicode path
+/// field) are used as the zip crate reads them. Other non-ASCII names are
+/// taken as UTF-8 when valid and otherwise decoded with `encoding`
+/// (`library.zip_codepage`) instead of CP437, which old Russian archivers
+/// never used.
+pub fn zip_entry_name<R: Read>(
+    entry: &::zip::read::ZipFile<'_, R>,
+    encoding: &'static Encoding,
+) -> String {
+    let raw = entry.name_raw();
+    if entry.name().as_bytes() == raw {
+        return entry.name().to_string();
+    }
+    match std::str::from_utf8(raw) {
+        Ok(name) => name.to_string(),
+        Err(_) => encoding.decode_without_bom_handling(raw).0.into_owned(),
+    }
+}
+
+/// Index of the entry a book was stored under: the entry whose
+/// [`zip_entry_name`] is `name`. Names stored before that decoding (as the
+/// zip crate reads them) are found too.
+pub fn find_zip_entry<R: Read + Seek>(
+    archive: &mut ::zip::ZipArchive<R>,
+    name: &str,
+    encoding: &'static Encoding,
+) -> Option<usize> {
+    if let Some(index) = archive.index_for_name(name) {
+        return Some(index);
+    }
+    (0..archive.len()).find(|&i| {
+        archive
+            .by_index_raw(i)
+            .is_ok_and(|entry| zip_entry_name(&entry, encoding) == name)
+    })
+}
+
+/// Basename and lowercased extension of a ZIP entry with a book extension.
+fn book_entry_name(entry_name: &str, extensions: &HashSet<String>) -> Option<(String, String)> {
+    let filename = Path::new(entry_name)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let ext = Path::new(&filename)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    extensions.contains(&ext).then_some((filename, ext))
+}
+
+/// List the book files of a ZIP archive without reading their data.
+pub(super) fn list_zip_book_entries(
+    path: &Path,
+    extensions: &HashSet<String>,
+    encoding: &'static Encoding,
+) -> Result<Vec<ZipBookEntry>, ScanError> {
+    let file = fs::File::open(path)?;
+    let mut archive = ::zip::ZipArchive::new(BufReader::new(file))?;
+    let mut entries = Vec::new();
+
+    for i in 0..archive.len() {
+        let Ok(entry) = archive.by_index_raw(i) else {
+            continue;
+        };
+        if !entry.is_file() {
+            continue;
+        }
+        let entry_name = zip_entry_name(&entry, encoding);
+        if let Some((filename, extension)) = book_entry_name(&entry_name, extensions) {
+            entries.push(ZipBookEntry {
+                entry_name,
+                filename,
+                extension,
+                size: entry.size() as i64,
+                data: Vec::new(),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Parse one book straight from its ZIP entry (low-memory profile). FB2 is
+/// parsed through the decompressor and text books read only their head;
+/// other formats are buffered up to [`LOW_MEMORY_ENTRY_BUFFER_BYTES`].
+/// With `test_files`, the entry is first decompressed into a sink to check
+/// its declared size.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn parse_zip_entry_streaming(
+    zip_path: &Path,
+    entry_name: &str,
+    encoding: &'static Encoding,
+    ext: &str,
+    filename: &str,
+    test_files: bool,
+    cover_cfg: CoverImageConfig,
+    filename_patterns: &[FilenamePattern],
+) -> Result<BookMeta, ScanError> {
+    let file = fs::File::open(zip_path)?;
+    let mut archive = ::zip::ZipArchive::new(BufReader::new(file))?;
+    let index = find_zip_entry(&mut archive, entry_name, encoding)
+        .ok_or(::zip::result::ZipError::FileNotFound)?;
+
+    if test_files {
+        let mut entry = archive.by_index(index)?;
+        let declared_size = entry.size();
+        let read = std::io::copy(&mut entry, &mut std::io::sink())?;
+        if declared_size > 0 && declared_size != read {
+            return Err(ScanError::Parse(format!(
+                "ZIP entry size mismatch: {entry_name} declared={declared_size}, read={read}"
+            )));
+        }
+    }
+
+    let entry = archive.by_index(index)?;
+    let size = entry.size();
+    match ext {
+        "fb2" => book::parse_fb2_stream(BufReader::new(entry), cover_cfg),
+        "txt" | "rtf" => {
+            let mut data = Vec::new();
+            entry
+                .take(book::TEXT_READ_LIMIT as u64)
+                .read_to_end(&mut data)?;
+            parse_book_bytes(&data, ext, filename, cover_cfg, filename_patterns)
+        }
+        _ if size <= LOW_MEMORY_ENTRY_BUFFER_BYTES => {
+            let mut data = Vec::with_capacity(size as usize);
+            entry
+                .take(LOW_MEMORY_ENTRY_BUFFER_BYTES)
+                .read_to_end(&mut data)?;
+            parse_book_bytes(&data, ext, filename, cover_cfg, filename_patterns)
+        }
+        _ => {
+            debug!("Not buffering {entry_name} ({size} bytes) for parsing");
+            let stem = Path::new(filename).file_stem().unwrap_or_default();
+            Ok(book::filename_meta(
+                &stem.to_string_lossy(),
+                filename_patterns,
+            ))
+        }
+    }
+}
+
 /// Read all matching book files from a ZIP archive.
 /// When `test_files` is enabled, entries whose extracted size does not match
 /// the declared size are skipped.
@@ -208,6 +384,7 @@ pub(super) fn read_zip_entries(
     path: &Path,
     extensions: &HashSet<String>,
     test_files: bool,
+    encoding: &'static Encoding,
 ) -> Result<Vec<ZipBookEntry>, ScanError> {
     let mut entries = Vec::new();
 
@@ -215,9 +392,11 @@ pub(super) fn read_zip_entries(
         path,
         extensions,
         test_files,
+        encoding,
         |_, _, _| true,
-        |filename, ext, declared_size, data| {
+        |entry_name, filename, ext, declared_size, data| {
             entries.push(ZipBookEntry {
+                entry_name,
                 filename,
                 extension: ext,
                 size: declared_size as i64,
@@ -236,7 +415,9 @@ pub(super) fn read_selected_zip_entries_meta(
     extensions: &HashSet<String>,
     needed_filenames: &HashSet<String>,
     test_files: bool,
+    encoding: &'static Encoding,
     cover_cfg: CoverImageConfig,
+    filename_patterns: &[FilenamePattern],
 ) -> Result<HashMap<String, BookMeta>, ScanError> {
     let mut out = HashMap::new();
 
@@ -244,8 +425,9 @@ pub(super) fn read_selected_zip_entries_meta(
         path,
         extensions,
         test_files,
+        encoding,
         |_, filename, _| needed_filenames.contains(filename),
-        |filename, ext, _, data| {
+        |_, filename, ext, _, data| {
             if out.contains_key(&filename) {
                 warn!(
                     "Duplicate basename '{}' in ZIP {}; keeping first matched entry",
@@ -255,7 +437,8 @@ pub(super) fn read_selected_zip_entries_meta(
                 return;
             }
 
-            if let Ok(meta) = parse_book_bytes(&data, &ext, &filename, cover_cfg) {
+            if let Ok(meta) = parse_book_bytes(&data, &ext, &filename, cover_cfg, filename_patterns)
+            {
                 out.insert(filename, meta);
             }
         },
@@ -264,6 +447,32 @@ pub(super) fn read_selected_zip_entries_meta(
     Ok(out)
 }
 
+/// List basenames of matching book files in a ZIP archive without
+/// decompressing them.
+pub(super) fn list_zip_entry_names(
+    path: &Path,
+    extensions: &HashSet<String>,
+    encoding: &'static Encoding,
+) -> Result<Vec<String>, ScanError> {
+    let file = fs::File::open(path)?;
+    let reader = BufReader::new(file);
+    let mut archive = ::zip::ZipArchive::new(reader)?;
+    let mut names = Vec::new();
+    for i in 0..archive.len() {
+        let Ok(entry) = archive.by_index_raw(i) else {
+            continue;
+        };
+        if !entry.is_file() {
+            continue;
+        }
+        if let Some((filename, _)) = book_entry_name(&zip_entry_name(&entry, encoding), extensions)
+        {
+            names.push(filename);
+        }
+    }
+    Ok(names)
+}
+
 /// Validate ZIP archive integrity by reading every entry (triggers CRC check).
 /// Returns `false` if any entry is corrupt.
 pub(super) fn validate_zip_integrity(path: &Path) -> Result<bool, ScanError> {
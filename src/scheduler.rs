@@ -4,6 +4,7 @@ use tracing::{info, warn};
 
 use crate::config::{Config, ScannerConfig};
 use crate::db::DbPool;
+use crate::db::queries::{counters, recommendations};
 use crate::scanner;
 
 /// Validate scanner schedule config values at startup.
@@ -83,9 +84,56 @@ pub fn format_schedule(config: &ScannerConfig) -> String {
     format!("minutes=[{minutes}] hours=[{hours}] days=[{dow}]")
 }
 
+/// Recompute the aggregate and per-genre counts, so genre menus have counts
+/// before the first scan after an upgrade.
+async fn refresh_counters(pool: &DbPool) {
+    if let Err(e) = counters::update_all(pool).await {
+        warn!("Counters refresh failed: {e}");
+    }
+}
+
+/// Recompute the cached popular-book download counts.
+async fn refresh_popular(pool: &DbPool) {
+    if let Err(e) = counters::update_popular(pool).await {
+        warn!("Popular books refresh failed: {e}");
+    }
+}
+
+/// Hour (local time) of the nightly recommendations rebuild.
+const RECOMMENDATIONS_HOUR: u32 = 3;
+
+/// Rebuild the book-to-book recommendation scores.
+async fn refresh_recommendations(pool: &DbPool) {
+    match recommendations::rebuild(pool).await {
+        Ok(stored) => info!("Recommendations rebuilt: {stored} entries"),
+        Err(e) => warn!("Recommendations rebuild failed: {e}"),
+    }
+}
+
 /// Run the scheduler loop. Checks every minute, spawns a scan task if schedule matches.
-pub async fn run(pool: DbPool, config: Config) {
+/// Counters are refreshed at startup (and after every scan); popular-book
+/// counts at startup and then at the top of every hour; recommendations are
+/// rebuilt at startup and then nightly. Covers stored without a colour
+/// palette get one in the background at startup.
+pub async fn run(pool: DbPool, config: Config, observers: scanner::ScanObservers) {
     info!("Scheduler started: {}", format_schedule(&config.scanner));
+    refresh_counters(&pool).await;
+    refresh_popular(&pool).await;
+    {
+        let pool = pool.clone();
+        tokio::spawn(async move { refresh_recommendations(&pool).await });
+    }
+    {
+        let pool = pool.clone();
+        let covers_path = config.covers.covers_path.clone();
+        tokio::spawn(async move {
+            match crate::covers::backfill_palettes(&pool, &covers_path).await {
+                Ok(0) => {}
+                Ok(n) => info!("Cover palettes computed for {n} books"),
+                Err(e) => warn!("Failed to compute cover palettes: {e}"),
+            }
+        });
+    }
 
     loop {
         // Sleep until the start of the next minute
@@ -96,21 +144,35 @@ pub async fn run(pool: DbPool, config: Config) {
             - Duration::from_nanos(nanos_into_second as u64);
         sleep(wait).await;
 
+        let now = Local::now();
+        if now.minute() == 0 {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                refresh_popular(&pool).await;
+                if now.hour() == RECOMMENDATIONS_HOUR {
+                    refresh_recommendations(&pool).await;
+                }
+            });
+        }
+
         if matches_schedule(&config.scanner) {
             info!("Scheduled scan triggered");
             let pool = pool.clone();
             let config = config.clone();
+            let observers = observers.clone();
             tokio::spawn(async move {
-                match scanner::run_scan(&pool, &config).await {
+                match scanner::run_scan_path_observed(&pool, &config, "", &observers).await {
                     Ok(stats) => {
                         info!(
-                            "Scheduled scan finished: added={}, skipped={}, deleted={}, archives_scanned={}, archives_skipped={}, errors={}",
+                            "Scheduled scan finished: added={}, updated={}, skipped={}, deleted={}, archives_scanned={}, archives_skipped={}, errors={}, paths_skipped={}",
                             stats.books_added,
+                            stats.books_updated,
                             stats.books_skipped,
                             stats.books_deleted,
                             stats.archives_scanned,
                             stats.archives_skipped,
                             stats.errors,
+                            stats.paths_skipped,
                         );
                     }
                     Err(scanner::ScanError::AlreadyRunning) => {
@@ -140,6 +202,10 @@ mod tests {
             test_zip: false,
             test_files: false,
             workers_num: 1,
+            exclude: Vec::new(),
+            max_depth: 64,
+            changes_log: None,
+            link_identical: false,
         }
     }
 
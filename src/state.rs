@@ -1,19 +1,49 @@
+use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::time::{Duration, Instant};
 
 use crate::config::Config;
 use crate::db::DbPool;
+use crate::db::queries::counters;
+use crate::opds::filename::FilenameTemplate;
+use crate::scanner::{ScanObserver, ScanObservers};
 use crate::web::i18n::Translations;
 use dashmap::DashMap;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
+const LIBRARY_VERSION_CACHE_KEY: &str = "library_version";
+/// How long an instance trusts its cached library change stamp; bounds how
+/// late changes made by a scan or by another instance show up.
+const LIBRARY_VERSION_TTL: Duration = Duration::from_secs(5);
+/// Key prefix of library query results; the stamp follows the prefix.
+const LIBRARY_QUERY_PREFIX: &str = "lib:";
+const LIBRARY_QUERY_TTL: Duration = Duration::from_secs(600);
+/// Cached library query results are dropped wholesale past this many entries.
+const LIBRARY_QUERY_MAX_ENTRIES: usize = 2000;
+
 #[derive(Debug, Clone)]
 struct CachedValue {
     value: serde_json::Value,
     expires_at: Instant,
 }
 
+/// External tools and database extensions found at startup.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capabilities {
+    /// `pdftoppm` renders PDF covers.
+    pub pdf_preview_tool: bool,
+    /// `pdfinfo` reads PDF metadata.
+    pub pdf_metadata_tool: bool,
+    /// `pdftotext` extracts the PDF text layer.
+    pub pdf_text_tool: bool,
+    /// `ddjvu` renders DJVU covers.
+    pub djvu_preview_tool: bool,
+    /// PostgreSQL `pg_trgm` is enabled for fuzzy search.
+    pub pg_trgm: bool,
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<Config>,
@@ -22,8 +52,18 @@ pub struct AppState {
     pub translations: Arc<Translations>,
     pub started_at: Instant,
     pub pdf_preview_tool_available: bool,
+    pub pdf_metadata_tool_available: bool,
+    pub pdf_text_tool_available: bool,
     pub djvu_preview_tool_available: bool,
+    /// PostgreSQL `pg_trgm` is enabled for fuzzy search.
+    pub pg_trgm_available: bool,
+    /// Observers of the scans started by the server (scheduled and manual).
+    pub scan_observers: ScanObservers,
+    /// `download.filename_template`, parsed once.
+    pub filename_template: Arc<FilenameTemplate>,
     query_cache: Arc<DashMap<String, CachedValue>>,
+    /// Last library change stamp seen, to drop results of older versions.
+    library_version_seen: Arc<AtomicI64>,
 }
 
 impl AppState {
@@ -32,21 +72,43 @@ impl AppState {
         db: DbPool,
         tera: tera::Tera,
         translations: Translations,
-        pdf_preview_tool_available: bool,
-        djvu_preview_tool_available: bool,
+        capabilities: Capabilities,
     ) -> Self {
+        let scan_observers = ScanObservers::from_config(&config);
+        // Config validation refuses invalid templates.
+        let filename_template = FilenameTemplate::parse(&config.download.filename_template)
+            .unwrap_or_else(|e| {
+                tracing::error!("download.filename_template: {e}; using the default");
+                FilenameTemplate::default()
+            });
+        let mut tera = tera;
+        tera.register_function("cover_url", crate::opds::covers::CoverUrls::new(&config));
         Self {
             config: Arc::new(config),
             db,
             tera: Arc::new(tera),
             translations: Arc::new(translations),
             started_at: Instant::now(),
-            pdf_preview_tool_available,
-            djvu_preview_tool_available,
+            pdf_preview_tool_available: capabilities.pdf_preview_tool,
+            pdf_metadata_tool_available: capabilities.pdf_metadata_tool,
+            pdf_text_tool_available: capabilities.pdf_text_tool,
+            djvu_preview_tool_available: capabilities.djvu_preview_tool,
+            pg_trgm_available: capabilities.pg_trgm,
+            scan_observers,
+            filename_template: Arc::new(filename_template),
             query_cache: Arc::new(DashMap::new()),
+            library_version_seen: Arc::new(AtomicI64::new(-1)),
         }
     }
 
+    /// Add an observer to the scans started by this server. Register
+    /// observers before the router is built, since the state is cloned into
+    /// it.
+    pub fn with_scan_observer(mut self, observer: Arc<dyn ScanObserver>) -> Self {
+        self.scan_observers.register(observer);
+        self
+    }
+
     pub fn get_cached<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
         let cached_value = {
             let entry = self.query_cache.get(key)?;
@@ -66,6 +128,10 @@ impl AppState {
         }
     }
 
+    pub fn invalidate_cached(&self, key: &str) {
+        self.query_cache.remove(key);
+    }
+
     pub fn set_cached<T: Serialize>(&self, key: impl Into<String>, ttl: Duration, value: &T) {
         let Ok(serialized) = serde_json::to_value(value) else {
             return;
@@ -79,4 +145,63 @@ impl AppState {
             },
         );
     }
+
+    /// Current library change stamp, cached for [`LIBRARY_VERSION_TTL`].
+    /// `None` when it cannot be read.
+    pub async fn library_version(&self) -> Option<i64> {
+        if let Some(version) = self.get_cached::<i64>(LIBRARY_VERSION_CACHE_KEY) {
+            return Some(version);
+        }
+        match counters::library_version(&self.db).await {
+            Ok(version) => {
+                self.set_cached(LIBRARY_VERSION_CACHE_KEY, LIBRARY_VERSION_TTL, &version);
+                if self.library_version_seen.swap(version, Ordering::Relaxed) != version {
+                    self.clear_library_queries();
+                }
+                Some(version)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to read library version: {e}");
+                None
+            }
+        }
+    }
+
+    /// Record a library change: bump the stamp and drop this instance's cached
+    /// copy so feeds and cached queries are refreshed at once.
+    pub async fn library_changed(&self) {
+        if let Err(e) = counters::bump_library_version(&self.db).await {
+            tracing::warn!("Failed to bump library version: {e}");
+        }
+        self.invalidate_cached(LIBRARY_VERSION_CACHE_KEY);
+    }
+
+    /// Result of an expensive library query, reused until the library changes.
+    /// `key` must identify the query and all of its parameters.
+    pub async fn library_cached<T, F, Fut>(&self, key: &str, load: F) -> Result<T, sqlx::Error>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        let Some(version) = self.library_version().await else {
+            return load().await;
+        };
+        let key = format!("{LIBRARY_QUERY_PREFIX}{version}:{key}");
+        if let Some(value) = self.get_cached(&key) {
+            return Ok(value);
+        }
+
+        let value = load().await?;
+        if self.query_cache.len() >= LIBRARY_QUERY_MAX_ENTRIES {
+            self.clear_library_queries();
+        }
+        self.set_cached(key, LIBRARY_QUERY_TTL, &value);
+        Ok(value)
+    }
+
+    fn clear_library_queries(&self) {
+        self.query_cache
+            .retain(|key, _| !key.starts_with(LIBRARY_QUERY_PREFIX));
+    }
 }
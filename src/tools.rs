@@ -0,0 +1,138 @@
+//! External helper programs (poppler, djvulibre): configured paths and bounded execution.
+
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::config::ToolsConfig;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+static TOOLS: OnceLock<ToolsConfig> = OnceLock::new();
+
+/// Install the `[tools]` settings for this process. Must be called before the
+/// first tool invocation; later calls are ignored.
+pub fn configure(cfg: &ToolsConfig) {
+    if TOOLS.set(cfg.clone()).is_err() {
+        tracing::debug!("[tools] settings already initialized; ignoring reconfiguration");
+    }
+}
+
+/// Current tool settings (defaults when `configure` was never called).
+pub fn settings() -> &'static ToolsConfig {
+    TOOLS.get_or_init(ToolsConfig::default)
+}
+
+/// Check whether `program` can be started (`<program> -h`).
+pub fn is_available(program: &Path) -> bool {
+    run_with_timeout(Command::new(program).arg("-h"), Duration::from_secs(5)).is_ok()
+}
+
+/// Run a tool with the configured timeout, capturing stdout and stderr.
+pub fn run(cmd: &mut Command) -> Result<Output, ToolError> {
+    run_with_timeout(cmd, Duration::from_secs(settings().timeout_secs))
+}
+
+/// Run a command, killing it if it does not exit within `timeout`.
+pub fn run_with_timeout(cmd: &mut Command, timeout: Duration) -> Result<Output, ToolError> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(ToolError::Spawn)?;
+
+    // Drain pipes on separate threads so a chatty child never blocks on a full pipe.
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(ToolError::TimedOut(timeout));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(ToolError::Wait(e));
+            }
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut data = Vec::new();
+        let _ = pipe.read_to_end(&mut data);
+        data
+    })
+}
+
+fn collect(handle: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    handle.and_then(|h| h.join().ok()).unwrap_or_default()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ToolError {
+    #[error("failed to start: {0}")]
+    Spawn(std::io::Error),
+    #[error("failed to wait for exit: {0}")]
+    Wait(std::io::Error),
+    #[error("timed out after {}s", .0.as_secs())]
+    TimedOut(Duration),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_captures_output() {
+        let output = run_with_timeout(
+            Command::new("sh")
+                .arg("-c")
+                .arg("printf out; printf err >&2"),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out");
+        assert_eq!(output.stderr, b"err");
+    }
+
+    #[test]
+    fn test_run_kills_on_timeout() {
+        let started = Instant::now();
+        let err = run_with_timeout(
+            Command::new("sh").arg("-c").arg("sleep 30"),
+            Duration::from_millis(200),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ToolError::TimedOut(_)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_missing_program() {
+        let missing = Path::new("/definitely/missing/tool");
+        assert!(!is_available(missing));
+        assert!(matches!(
+            run_with_timeout(&mut Command::new(missing), Duration::from_secs(1)),
+            Err(ToolError::Spawn(_))
+        ));
+    }
+}
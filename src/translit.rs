@@ -0,0 +1,245 @@
+//! Transliteration folding for script-independent search.
+//!
+//! [`fold`] maps Cyrillic and Latin spellings of a name onto one Latin key, so
+//! "Dostoevsky" and "Достоевский" both become `DOSTOEVSKI`. Keys are stored
+//! next to the regular search columns (`books.translit_title`,
+//! `authors.translit_full_name`) and matched when `search.translit` is on.
+
+use crate::db::DbPool;
+use crate::db::queries::MatchMode;
+
+/// Rows updated per backfill batch.
+const BACKFILL_BATCH: i32 = 500;
+
+/// Fold a string into its transliteration search key.
+///
+/// Cyrillic letters are romanized, then spellings that differ between
+/// romanization schemes are unified (`Y`/`J` → `I`, `W` → `V`, `Q` → `K`,
+/// `X` → `KS`) and runs of the same letter are collapsed.
+pub fn fold(s: &str) -> String {
+    let mut latin = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_uppercase) {
+        match romanize(c) {
+            Some(r) => latin.push_str(r),
+            None => latin.push(c),
+        }
+    }
+
+    let mut key = String::with_capacity(latin.len());
+    let mut prev = None;
+    for c in latin.chars() {
+        let unified = match c {
+            'Y' | 'J' => "I",
+            'W' => "V",
+            'Q' => "K",
+            'X' => "KS",
+            _ => "",
+        };
+        let mut push = |c: char| {
+            if !(c.is_alphabetic() && prev == Some(c)) {
+                key.push(c);
+            }
+            prev = Some(c);
+        };
+        if unified.is_empty() {
+            push(c);
+        } else {
+            unified.chars().for_each(&mut push);
+        }
+    }
+    key
+}
+
+/// Romanize the Cyrillic letters of `s`, keeping case and every other
+/// character ("Щедрин" becomes "Shchedrin").
+pub fn latinize(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        let upper = c.to_uppercase().next().unwrap_or(c);
+        match romanize(upper) {
+            Some(r) if c == upper => {
+                let mut letters = r.chars();
+                out.extend(letters.next());
+                out.extend(letters.flat_map(char::to_lowercase));
+            }
+            Some(r) => out.push_str(&r.to_lowercase()),
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+/// `LIKE` pattern matching transliteration keys that contain `term`, or
+/// `None` when transliteration search is off (`LIKE NULL` matches no row).
+pub fn contains_pattern(term: &str, enabled: bool) -> Option<String> {
+    pattern(term, enabled, MatchMode::Contains)
+}
+
+/// Like [`contains_pattern`], for any match mode.
+pub fn pattern(term: &str, enabled: bool, mode: MatchMode) -> Option<String> {
+    let key = fold(term);
+    (enabled && !key.is_empty()).then(|| mode.like_pattern(&key))
+}
+
+/// Latin spelling of an uppercase Cyrillic letter (Russian, Ukrainian and
+/// Belarusian alphabets).
+fn romanize(c: char) -> Option<&'static str> {
+    let r = match c {
+        'А' => "A",
+        'Б' => "B",
+        'В' => "V",
+        'Г' | 'Ґ' => "G",
+        'Д' => "D",
+        'Е' | 'Ё' | 'Є' | 'Э' => "E",
+        'Ж' => "ZH",
+        'З' => "Z",
+        'И' | 'Й' | 'І' | 'Ї' | 'Ы' => "I",
+        'К' => "K",
+        'Л' => "L",
+        'М' => "M",
+        'Н' => "N",
+        'О' => "O",
+        'П' => "P",
+        'Р' => "R",
+        'С' => "S",
+        'Т' => "T",
+        'У' | 'Ў' => "U",
+        'Ф' => "F",
+        'Х' => "KH",
+        'Ц' => "TS",
+        'Ч' => "CH",
+        'Ш' => "SH",
+        'Щ' => "SHCH",
+        'Ъ' | 'Ь' => "",
+        'Ю' => "IU",
+        'Я' => "IA",
+        _ => return None,
+    };
+    Some(r)
+}
+
+/// Fill missing transliteration keys (rows stored before the columns existed).
+/// Rows are paged by id, so values that fold to nothing are passed over
+/// rather than selected again. Returns the number of updated rows.
+pub async fn backfill(pool: &DbPool) -> Result<u64, sqlx::Error> {
+    let mut updated = 0;
+    for (table, source, target) in [
+        ("books", "search_title", "translit_title"),
+        ("authors", "search_full_name", "translit_full_name"),
+    ] {
+        let raw = format!(
+            "SELECT id, {source} FROM {table} \
+             WHERE {target} = '' AND {source} <> '' AND id > ? ORDER BY id LIMIT ?"
+        );
+        let select_sql = pool.sql(&raw);
+        let raw = format!("UPDATE {table} SET {target} = ? WHERE id = ?");
+        let update_sql = pool.sql(&raw);
+        let mut last_id = 0i64;
+        loop {
+            let rows: Vec<(i64, String)> = sqlx::query_as(&select_sql)
+                .bind(last_id)
+                .bind(BACKFILL_BATCH)
+                .fetch_all(pool.inner())
+                .await?;
+            let Some((max_id, _)) = rows.last() else {
+                break;
+            };
+            last_id = *max_id;
+            let mut tx = pool.inner().begin().await?;
+            for (id, value) in &rows {
+                let key = fold(value);
+                if key.is_empty() {
+                    continue;
+                }
+                sqlx::query(&update_sql)
+                    .bind(key)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                updated += 1;
+            }
+            tx.commit().await?;
+        }
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_test_pool;
+    use crate::db::queries::authors;
+
+    #[test]
+    fn test_fold_matches_across_scripts() {
+        assert_eq!(fold("Достоевский"), "DOSTOEVSKI");
+        assert_eq!(fold("Dostoevsky"), "DOSTOEVSKI");
+        assert_eq!(fold("Толстой Лев"), fold("Tolstoy Lev"));
+        assert_eq!(fold("Чехов"), fold("Chekhov"));
+        assert_eq!(fold("Гоголь"), fold("Gogol"));
+        assert_eq!(fold("Щедрин"), "SHCHEDRIN");
+    }
+
+    #[test]
+    fn test_fold_keeps_other_characters() {
+        assert_eq!(fold("451 Degree"), "451 DEGRE");
+        assert_eq!(fold("Anna  Karenina"), "ANA  KARENINA");
+        assert_eq!(fold(""), "");
+    }
+
+    #[test]
+    fn test_latinize_keeps_case() {
+        assert_eq!(latinize("Щедрин"), "Shchedrin");
+        assert_eq!(latinize("Война и мир, 1869"), "Voina i mir, 1869");
+        assert_eq!(latinize("Dune"), "Dune");
+    }
+
+    #[test]
+    fn test_contains_pattern() {
+        assert_eq!(
+            contains_pattern("dostoevsky", true).as_deref(),
+            Some("%DOSTOEVSKI%")
+        );
+        assert_eq!(contains_pattern("dostoevsky", false), None);
+        assert_eq!(contains_pattern("Ъ", true), None);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_fills_missing_keys() {
+        let pool = create_test_pool().await;
+        let id = authors::insert(&pool, "Чехов", "ЧЕХОВ", 1).await.unwrap();
+        let sql = pool.sql("UPDATE authors SET translit_full_name = '' WHERE id = ?");
+        sqlx::query(&sql)
+            .bind(id)
+            .execute(pool.inner())
+            .await
+            .unwrap();
+
+        assert_eq!(backfill(&pool).await.unwrap(), 1);
+        let found = authors::search_by_name(&pool, "CHEKHOV", MatchMode::Contains, 10, 0, true)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(backfill(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_backfill_passes_over_empty_keys() {
+        let pool = create_test_pool().await;
+        // "Ъ" folds to nothing, so its key stays empty
+        authors::insert(&pool, "Ъ", "Ъ", 1).await.unwrap();
+        let id = authors::insert(&pool, "Чехов", "ЧЕХОВ", 1).await.unwrap();
+        let sql = pool.sql("UPDATE authors SET translit_full_name = ''");
+        sqlx::query(&sql).execute(pool.inner()).await.unwrap();
+
+        assert_eq!(backfill(&pool).await.unwrap(), 1);
+        let sql = pool.sql("SELECT translit_full_name FROM authors WHERE id = ?");
+        let key: String = sqlx::query_scalar(&sql)
+            .bind(id)
+            .fetch_one(pool.inner())
+            .await
+            .unwrap();
+        assert_eq!(key, "CHEKHOV");
+        assert_eq!(backfill(&pool).await.unwrap(), 0);
+    }
+}
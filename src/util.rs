@@ -17,6 +17,32 @@ pub fn slugify_username(name: &str) -> String {
     }
 }
 
+/// Render rows as CSV (RFC 4180 quoting, CRLF line endings).
+pub fn to_csv(rows: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Quote a CSV field when needed. Fields that a spreadsheet would evaluate as
+/// a formula get a leading apostrophe.
+fn csv_field(field: &str) -> String {
+    let field = if field.starts_with(['=', '+', '-', '@']) {
+        format!("'{field}")
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -27,4 +53,18 @@ mod tests {
         assert_eq!(slugify_username(""), "user");
         assert_eq!(slugify_username("Иван Петров"), "user");
     }
+
+    #[test]
+    fn test_to_csv_quoting() {
+        let rows = vec![
+            vec!["id".to_string(), "name".to_string()],
+            vec!["1".to_string(), "Tolstoy, Leo".to_string()],
+            vec!["2".to_string(), "Say \"hi\"".to_string()],
+            vec!["3".to_string(), "=SUM(A1)".to_string()],
+        ];
+        assert_eq!(
+            to_csv(&rows),
+            "id,name\r\n1,\"Tolstoy, Leo\"\r\n2,\"Say \"\"hi\"\"\"\r\n3,'=SUM(A1)\r\n"
+        );
+    }
 }
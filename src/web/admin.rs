@@ -5,24 +5,38 @@ use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum_extra::extract::cookie::CookieJar;
 use serde::Deserialize;
 
-use crate::db::queries::users;
+use crate::db::queries::{upload_quota, users};
 use crate::state::AppState;
 use crate::web::auth::verify_session;
 use crate::web::context::{build_context, validate_csrf};
 
+mod book_cover;
 mod book_delete;
 mod book_edit;
+mod book_replace;
+mod catalogs;
 mod duplicates;
 mod genres;
+pub mod impersonate;
+mod languages;
 pub mod oauth_requests;
 mod scan;
+mod shares;
+mod stats;
 mod user_pages;
 
+pub use book_cover::*;
 pub use book_delete::*;
 pub use book_edit::*;
+pub use book_replace::*;
+pub use catalogs::*;
 pub use duplicates::*;
 pub use genres::*;
+pub use impersonate::{impersonate, stop_impersonating};
+pub use languages::*;
 pub use scan::*;
+pub use shares::*;
+pub use stats::*;
 pub use user_pages::*;
 
 /// Middleware: require superuser for admin routes.
@@ -51,16 +65,45 @@ pub async fn require_superuser(
     next.run(request).await
 }
 
+/// Middleware: an admin action that went through (catalog edits, deletions,
+/// genre changes) bumps the library change stamp so OPDS feed ETags change.
+pub async fn bump_library_version_on_change(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let safe = matches!(
+        *request.method(),
+        axum::http::Method::GET | axum::http::Method::HEAD
+    );
+    let response = next.run(request).await;
+    if !safe && (response.status().is_success() || response.status().is_redirection()) {
+        state.library_changed().await;
+    }
+    response
+}
+
+/// Helper: `{"ok": false, "error": code}` reply of the JSON admin endpoints.
+fn json_error(status: StatusCode, error: &str) -> Response {
+    (
+        status,
+        axum::Json(serde_json::json!({ "ok": false, "error": error })),
+    )
+        .into_response()
+}
+
 /// Helper: extract current user_id from session cookie.
 fn get_session_user_id(jar: &CookieJar, secret: &[u8]) -> Option<i64> {
     jar.get("session")
         .and_then(|c| verify_session(c.value(), secret))
 }
 
-/// Validate password length (8-32 characters).
-fn is_valid_password(password: &str) -> bool {
-    let len = password.chars().count();
-    (8..=32).contains(&len)
+/// Check a new password against the `[password]` policy; the error code
+/// of the violation when it is refused.
+fn password_error(state: &AppState, password: &str) -> Option<&'static str> {
+    crate::password::check_policy(&state.config.password, password)
+        .err()
+        .map(|v| v.code())
 }
 
 /// Validate username characters: ASCII alnum plus dot, dash and underscore.
@@ -87,6 +130,64 @@ pub(crate) fn validate_book_title(title: &str) -> Result<String, &'static str> {
     Ok(trimmed)
 }
 
+/// Validate a catalog display name: same rules as book titles.
+pub(crate) fn validate_catalog_name(name: &str) -> Result<String, &'static str> {
+    validate_book_title(name).map_err(|err| match err {
+        "title_empty" => "name_empty",
+        "title_too_long" => "name_too_long",
+        _ => "name_invalid",
+    })
+}
+
+/// Validate a book annotation: max 8000 chars (the MySQL column size), no
+/// control characters besides line breaks and tabs. Line endings are
+/// normalized, characters outside the BMP are dropped (MySQL 3-byte UTF8
+/// compat) and an empty result clears the annotation.
+pub(crate) fn validate_book_annotation(annotation: &str) -> Result<String, &'static str> {
+    let normalized: String = annotation
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|c| (*c as u32) < 0x10000)
+        .collect();
+    let trimmed = normalized.trim().to_string();
+    if trimmed.chars().count() > 8000 {
+        return Err("annotation_too_long");
+    }
+    if trimmed
+        .chars()
+        .any(|c| c.is_control() && c != '\n' && c != '\t')
+    {
+        return Err("annotation_invalid");
+    }
+    Ok(trimmed)
+}
+
+/// Validate a book language: empty, or a lowercase ISO 639 code with an
+/// optional region or script subtag (`en`, `pt-br`, `sr-latn`).
+pub(crate) fn validate_book_lang(lang: &str) -> Result<String, &'static str> {
+    let lang = lang.trim().to_lowercase().replace('_', "-");
+    if lang.is_empty() {
+        return Ok(lang);
+    }
+    let mut parts = lang.split('-');
+    let primary = parts.next().unwrap_or_default();
+    let primary_ok =
+        (2..=3).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_lowercase());
+    let subtag_ok = match (parts.next(), parts.next()) {
+        (None, _) => true,
+        (Some(sub), None) => {
+            (2..=8).contains(&sub.len()) && sub.chars().all(|c| c.is_ascii_alphanumeric())
+        }
+        (Some(_), Some(_)) => false,
+    };
+    if primary_ok && subtag_ok {
+        Ok(lang)
+    } else {
+        Err("lang_invalid")
+    }
+}
+
 /// Format elapsed seconds as human-readable uptime using translations from context.
 fn format_uptime(total_secs: u64, ctx: &tera::Context) -> String {
     let days = total_secs / 86400;
@@ -0,0 +1,168 @@
+use super::*;
+
+use crate::config::CoverImageConfig;
+use crate::db::models::Book;
+use crate::db::queries::books;
+
+async fn find_book(state: &AppState, book_id: i64) -> Result<Book, Response> {
+    match books::get_by_id(&state.db, book_id).await {
+        Ok(Some(b)) => Ok(b),
+        Ok(None) => Err(json_error(StatusCode::NOT_FOUND, "error_book_not_found")),
+        Err(e) => {
+            tracing::error!("Failed to fetch book {book_id}: {e}");
+            Err(json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db"))
+        }
+    }
+}
+
+/// Cover found in the book file itself: the embedded image, or the rendered
+/// first page of a PDF/DjVu.
+async fn cover_from_file(state: &AppState, book: &Book) -> Option<(Vec<u8>, String)> {
+    let root = state.config.library.root_path.clone();
+    let (path, filename, format) = (
+        book.path.clone(),
+        book.filename.clone(),
+        book.format.clone(),
+    );
+    let cat_type = book.cat_type;
+    let cover_cfg = CoverImageConfig::from(&state.config.covers);
+    let zip_encoding = crate::scanner::codepage_encoding(&state.config.library.zip_codepage);
+    tokio::task::spawn_blocking(move || {
+        crate::opds::covers::extract_book_cover(
+            &root,
+            &path,
+            &filename,
+            &format,
+            cat_type,
+            zip_encoding,
+            cover_cfg,
+        )
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// GET /web/admin/book/:id/cover/candidate -- preview of the cover the book
+/// file provides, so it can be compared with the current one.
+pub async fn book_cover_candidate(
+    State(state): State<AppState>,
+    Path(book_id): Path<i64>,
+) -> Response {
+    let book = match find_book(&state, book_id).await {
+        Ok(b) => b,
+        Err(r) => return r,
+    };
+    match cover_from_file(&state, &book).await {
+        Some((data, mime)) => ([(axum::http::header::CONTENT_TYPE, mime)], data).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// MIME type of an uploaded image, judged by its content.
+fn sniff_image_mime(data: &[u8]) -> Option<&'static str> {
+    match image::guess_format(data).ok()? {
+        image::ImageFormat::Jpeg => Some("image/jpeg"),
+        image::ImageFormat::Png => Some("image/png"),
+        image::ImageFormat::Gif => Some("image/gif"),
+        _ => None,
+    }
+}
+
+/// POST /web/admin/book/:id/cover -- set a book's cover (multipart:
+/// `csrf_token`, `source` and, for `source=upload`, `file`). `source=book`
+/// takes the cover from the book file. The stored image replaces the old
+/// one in every format, so thumbnails are rendered from it right away.
+pub async fn update_book_cover(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(book_id): Path<i64>,
+    mut multipart: axum::extract::Multipart,
+) -> Response {
+    let mut csrf_token = String::new();
+    let mut source = String::new();
+    let mut file: Option<Vec<u8>> = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name().unwrap_or("") {
+            "csrf_token" => csrf_token = field.text().await.unwrap_or_default(),
+            "source" => source = field.text().await.unwrap_or_default(),
+            "file" => file = Some(field.bytes().await.unwrap_or_default().to_vec()),
+            _ => {}
+        }
+    }
+
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+    let book = match find_book(&state, book_id).await {
+        Ok(b) => b,
+        Err(r) => return r,
+    };
+
+    let (data, mime) = match source.as_str() {
+        "upload" => {
+            let Some(data) = file.filter(|d| !d.is_empty()) else {
+                return json_error(StatusCode::BAD_REQUEST, "error_no_file");
+            };
+            // Undecodable data would be stored as is, so check it up front.
+            match sniff_image_mime(&data) {
+                Some(mime) if crate::covers::decode(&data).is_ok() => (data, mime.to_string()),
+                _ => return json_error(StatusCode::BAD_REQUEST, "error_cover_invalid"),
+            }
+        }
+        "book" => match cover_from_file(&state, &book).await {
+            Some(found) => found,
+            None => return json_error(StatusCode::NOT_FOUND, "error_cover_not_found"),
+        },
+        _ => return json_error(StatusCode::BAD_REQUEST, "error_cover_source"),
+    };
+
+    if !crate::disk::has_room(
+        &state.config.covers.covers_path,
+        data.len() as u64,
+        state.config.server.min_free_space_mb,
+    ) {
+        return json_error(StatusCode::INSUFFICIENT_STORAGE, "error_disk_full");
+    }
+
+    let cover_cfg = CoverImageConfig::from(&state.config.covers);
+    let stored = match crate::scanner::replace_cover(
+        &state.config.covers.covers_path,
+        book_id,
+        &data,
+        &mime,
+        cover_cfg,
+    ) {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return json_error(StatusCode::BAD_REQUEST, "error_cover_invalid"),
+        Err(e) => {
+            tracing::error!("Failed to store cover for book {book_id}: {e}");
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db");
+        }
+    };
+    let updated = async {
+        books::set_cover(&state.db, book_id, 1, &stored.mime).await?;
+        books::set_cover_size(&state.db, book_id, stored.width, stored.height).await?;
+        books::set_cover_palette(&state.db, book_id, &stored.palette).await
+    }
+    .await;
+    if let Err(e) = updated {
+        tracing::error!("Failed to update cover of book {book_id}: {e}");
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db");
+    }
+
+    // Cache-busting URLs for the page that made the change.
+    let version = chrono::Utc::now().timestamp_millis();
+    let urls = crate::opds::covers::CoverUrls::new(&state.config);
+    let versioned = |url: String| {
+        let sep = if url.contains('?') { '&' } else { '?' };
+        format!("{url}{sep}v={version}")
+    };
+    axum::Json(serde_json::json!({
+        "ok": true,
+        "cover_url": versioned(urls.cover(book_id)),
+        "thumbnail_url": versioned(urls.thumb(book_id)),
+    }))
+    .into_response()
+}
@@ -29,14 +29,12 @@ pub async fn delete_book(
         }
     };
 
-    // For plain files: delete from disk
-    if let Ok(CatType::Normal) = CatType::try_from(book.cat_type) {
-        let full_path = state
-            .config
-            .library
-            .root_path
-            .join(&book.path)
-            .join(&book.filename);
+    // For plain files: delete from disk (remote libraries are read-only, so
+    // their files are suppressed like archive entries)
+    let remote = crate::vfs::is_remote(&state.config.library.root_path);
+    if let (Ok(CatType::Normal), false) = (CatType::try_from(book.cat_type), remote) {
+        let full_path =
+            crate::vfs::local_file(&state.config.library.root_path, &book.path, &book.filename);
         if let Err(e) = std::fs::remove_file(&full_path)
             && e.kind() != std::io::ErrorKind::NotFound
         {
@@ -44,7 +42,7 @@ pub async fn delete_book(
             return Redirect::to(&redirect_url(&params, "error=file_delete_error")).into_response();
         }
     } else {
-        // For ZIP/INPX: add to suppression table
+        // For ZIP/INPX and remote files: add to suppression table
         if let Err(e) = suppressed::suppress(&state.db, &book.path, &book.filename).await {
             tracing::error!("Failed to suppress book {book_id}: {e}");
             return Redirect::to(&redirect_url(&params, "error=db_error")).into_response();
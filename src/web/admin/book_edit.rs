@@ -1,5 +1,60 @@
 use super::*;
 
+/// Render the editable card fields (`web/_book_edit_fields.html`) for a book,
+/// so the edit modal can swap the fragment in place after a save.
+pub(super) async fn render_book_fields(
+    state: &AppState,
+    jar: &CookieJar,
+    book_id: i64,
+) -> Option<String> {
+    let book = crate::db::queries::books::get_by_id(&state.db, book_id)
+        .await
+        .ok()
+        .flatten()?;
+    let mut ctx = build_context(state, jar, "books").await;
+    let locale = ctx
+        .get("locale")
+        .and_then(|v| v.as_str())
+        .unwrap_or(&state.config.web.language)
+        .to_string();
+    let authors = crate::db::queries::authors::get_for_book(&state.db, book_id)
+        .await
+        .unwrap_or_default();
+    let genres = crate::db::queries::genres::get_for_book(&state.db, book_id, &locale)
+        .await
+        .unwrap_or_default();
+    let series_list: Vec<serde_json::Value> =
+        crate::db::queries::series::get_for_book(&state.db, book_id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(s, ser_no)| {
+                serde_json::json!({
+                    "id": s.id,
+                    "ser_name": s.ser_name,
+                    "ser_no": ser_no,
+                })
+            })
+            .collect();
+    ctx.insert(
+        "item",
+        &serde_json::json!({
+            "id": book.id,
+            "title": book.title,
+            "authors": authors,
+            "genres": genres,
+            "series_list": series_list,
+        }),
+    );
+    match state.tera.render("web/_book_edit_fields.html", &ctx) {
+        Ok(html) => Some(html),
+        Err(e) => {
+            tracing::error!("Template render error (book fields {book_id}): {e}");
+            None
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct UpdateBookGenresPayload {
     pub book_id: i64,
@@ -48,9 +103,11 @@ pub async fn update_book_genres(
                 crate::db::queries::genres::get_for_book(&state.db, payload.book_id, &locale)
                     .await
                     .unwrap_or_default();
+            let html = render_book_fields(&state, &jar, payload.book_id).await;
             axum::Json(serde_json::json!({
                 "ok": true,
                 "genres": updated,
+                "html": html,
             }))
             .into_response()
         }
@@ -149,9 +206,11 @@ pub async fn update_book_authors(
             let updated = crate::db::queries::authors::get_for_book(&state.db, payload.book_id)
                 .await
                 .unwrap_or_default();
+            let html = render_book_fields(&state, &jar, payload.book_id).await;
             axum::Json(serde_json::json!({
                 "ok": true,
                 "authors": updated,
+                "html": html,
             }))
             .into_response()
         }
@@ -226,9 +285,11 @@ pub async fn update_book_series(
                     })
                 })
                 .collect();
+            let html = render_book_fields(&state, &jar, payload.book_id).await;
             axum::Json(serde_json::json!({
                 "ok": true,
                 "series": series_json,
+                "html": html,
             }))
             .into_response()
         }
@@ -325,11 +386,15 @@ pub async fn update_book_title(
     )
     .await
     {
-        Ok(()) => axum::Json(serde_json::json!({
-            "ok": true,
-            "title": title,
-        }))
-        .into_response(),
+        Ok(()) => {
+            let html = render_book_fields(&state, &jar, payload.book_id).await;
+            axum::Json(serde_json::json!({
+                "ok": true,
+                "title": title,
+                "html": html,
+            }))
+            .into_response()
+        }
         Err(e) => {
             tracing::error!("Failed to update title for book {}: {e}", payload.book_id);
             (
@@ -340,3 +405,102 @@ pub async fn update_book_title(
         }
     }
 }
+
+// ── Book language management (admin-only) ───────────────────────────
+
+#[derive(Deserialize)]
+pub struct UpdateBookLangPayload {
+    pub book_id: i64,
+    #[serde(default)]
+    pub lang: String,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/book-lang -- set a book's language code; empty clears it.
+pub async fn update_book_lang(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(payload): axum::Json<UpdateBookLangPayload>,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &payload.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "csrf");
+    }
+
+    let lang = match validate_book_lang(&payload.lang) {
+        Ok(l) => l,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err),
+    };
+
+    if let Ok(None) | Err(_) =
+        crate::db::queries::books::get_by_id(&state.db, payload.book_id).await
+    {
+        return json_error(StatusCode::NOT_FOUND, "error_book_not_found");
+    }
+
+    match crate::db::queries::books::update_lang(&state.db, payload.book_id, &lang).await {
+        Ok(()) => axum::Json(serde_json::json!({"ok": true, "lang": lang})).into_response(),
+        Err(e) => {
+            tracing::error!(
+                "Failed to update language for book {}: {e}",
+                payload.book_id
+            );
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db")
+        }
+    }
+}
+
+// ── Book annotation management (admin-only) ─────────────────────────
+
+#[derive(Deserialize)]
+pub struct UpdateBookAnnotationPayload {
+    pub book_id: i64,
+    #[serde(default)]
+    pub annotation: String,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/book-annotation -- replace a book's annotation. The text
+/// is stored as markdown; the response carries the rendered HTML so the page
+/// can update the card without a reload.
+pub async fn update_book_annotation(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(payload): axum::Json<UpdateBookAnnotationPayload>,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &payload.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "csrf");
+    }
+
+    let annotation = match validate_book_annotation(&payload.annotation) {
+        Ok(a) => a,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err),
+    };
+
+    if let Ok(None) | Err(_) =
+        crate::db::queries::books::get_by_id(&state.db, payload.book_id).await
+    {
+        return json_error(StatusCode::NOT_FOUND, "error_book_not_found");
+    }
+
+    match crate::db::queries::books::update_annotation(&state.db, payload.book_id, &annotation)
+        .await
+    {
+        Ok(()) => axum::Json(serde_json::json!({
+            "ok": true,
+            "annotation": annotation,
+            "annotation_html": crate::markdown::to_html(&annotation),
+        }))
+        .into_response(),
+        Err(e) => {
+            tracing::error!(
+                "Failed to update annotation for book {}: {e}",
+                payload.book_id
+            );
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db")
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use super::*;
+
+use crate::db::models::CatType;
+use crate::db::queries::books;
+use crate::web::upload::{extract_book_from_zip, validate_extension};
+
+/// POST /web/admin/book/:id/replace -- swap a book's file for a better copy
+/// (multipart: `csrf_token`, `file`). The book keeps its id, so bookshelf
+/// entries, reading positions and download history stay attached; see
+/// [`crate::scanner::replace_book_with_meta`] for how metadata is merged.
+pub async fn replace_book_file(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(book_id): Path<i64>,
+    mut multipart: axum::extract::Multipart,
+) -> Response {
+    let max_bytes = state.config.upload.max_upload_size_mb * 1024 * 1024;
+    let mut csrf_token = String::new();
+    let mut file_data: Option<(String, Vec<u8>)> = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name().unwrap_or("") {
+            "csrf_token" => csrf_token = field.text().await.unwrap_or_default(),
+            "file" => {
+                let filename = field.file_name().unwrap_or("").to_string();
+                let bytes = field.bytes().await.unwrap_or_default();
+                if bytes.len() as u64 > max_bytes {
+                    return json_error(StatusCode::BAD_REQUEST, "error_too_large");
+                }
+                file_data = Some((filename, bytes.to_vec()));
+            }
+            _ => {}
+        }
+    }
+
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+
+    let book = match books::get_by_id(&state.db, book_id).await {
+        Ok(Some(b)) => b,
+        Ok(None) => return json_error(StatusCode::NOT_FOUND, "error_book_not_found"),
+        Err(e) => {
+            tracing::error!("Failed to fetch book {book_id}: {e}");
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db");
+        }
+    };
+
+    // Archive members and remote libraries cannot be rewritten in place.
+    let remote = crate::vfs::is_remote(&state.config.library.root_path);
+    if !matches!(CatType::try_from(book.cat_type), Ok(CatType::Normal)) || remote {
+        return json_error(StatusCode::BAD_REQUEST, "error_not_replaceable");
+    }
+
+    let (upload_name, data) = match file_data {
+        Some(d) if !d.1.is_empty() => d,
+        _ => return json_error(StatusCode::BAD_REQUEST, "error_no_file"),
+    };
+    let allowed_exts = &state.config.library.book_extensions;
+    let Some(extension) = validate_extension(&upload_name, allowed_exts) else {
+        return json_error(StatusCode::BAD_REQUEST, "error_unsupported");
+    };
+    let (data, extension) = if extension == "zip" {
+        if !state.config.library.scan_zip {
+            return json_error(StatusCode::BAD_REQUEST, "error_unsupported");
+        }
+        match extract_book_from_zip(std::io::Cursor::new(&data), allowed_exts, max_bytes) {
+            Ok((data, ext, _)) => (data, ext),
+            Err(error_code) => return json_error(StatusCode::BAD_REQUEST, error_code),
+        }
+    } else {
+        (data, extension)
+    };
+
+    // The new file keeps the old name; only the extension follows the format.
+    let stem = std::path::Path::new(&book.filename)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let new_filename = format!("{stem}.{extension}");
+    let root = &state.config.library.root_path;
+    let dir = crate::vfs::local_path(root, &book.path);
+    let old_path = crate::vfs::local_file(root, &book.path, &book.filename);
+    let new_path = if new_filename == book.filename {
+        old_path.clone()
+    } else {
+        dir.join(&new_filename)
+    };
+    if new_filename != book.filename {
+        let taken = matches!(
+            books::find_by_path_and_filename(&state.db, &book.path, &new_filename).await,
+            Ok(Some(ref other)) if other.id != book.id
+        );
+        if taken || tokio::fs::try_exists(&new_path).await.unwrap_or(false) {
+            return json_error(StatusCode::CONFLICT, "error_duplicate");
+        }
+    }
+
+    if !crate::disk::has_room(
+        &dir,
+        data.len() as u64,
+        state.config.server.min_free_space_mb,
+    ) {
+        return json_error(StatusCode::INSUFFICIENT_STORAGE, "error_disk_full");
+    }
+
+    // Parse a hidden staging copy next to the target, then rename it into
+    // place so readers never see a half-written book.
+    let staging = dir.join(format!(".{new_filename}.replace"));
+    if let Err(e) = tokio::fs::write(&staging, &data).await {
+        tracing::error!("Failed to write replacement for book {book_id}: {e}");
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+    }
+    let cover_cfg = crate::config::CoverImageConfig::from(&state.config.covers);
+    let patterns = crate::scanner::parsers::filename::compile_patterns(
+        &state.config.library.filename_patterns,
+    );
+    let (parse_path, parse_ext) = (staging.clone(), extension.clone());
+    let mut meta = match tokio::task::spawn_blocking(move || {
+        crate::scanner::parse_book_file(&parse_path, &parse_ext, cover_cfg, &patterns)
+    })
+    .await
+    {
+        Ok(Ok(m)) => m,
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to parse replacement for book {book_id}: {e}");
+            let _ = tokio::fs::remove_file(&staging).await;
+            return json_error(StatusCode::BAD_REQUEST, "error_parse");
+        }
+        Err(e) => {
+            tracing::error!("spawn_blocking error: {e}");
+            let _ = tokio::fs::remove_file(&staging).await;
+            return json_error(StatusCode::BAD_REQUEST, "error_parse");
+        }
+    };
+    meta.lang = crate::scanner::resolve_lang(&meta, state.config.library.lang_detect_threshold());
+
+    // Keep the old file aside until the database points at the new one.
+    let backup = dir.join(format!(".{}.bak", book.filename));
+    let backed_up = match tokio::fs::rename(&old_path, &backup).await {
+        Ok(()) => true,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+        Err(e) => {
+            tracing::error!("Failed to back up {old_path:?}: {e}");
+            let _ = tokio::fs::remove_file(&staging).await;
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+        }
+    };
+    let restore = || async {
+        if backed_up && let Err(e) = tokio::fs::rename(&backup, &old_path).await {
+            tracing::error!("Failed to restore {old_path:?} from {backup:?}: {e}");
+        }
+    };
+    if let Err(e) = tokio::fs::rename(&staging, &new_path).await {
+        tracing::error!("Failed to move replacement into {new_path:?}: {e}");
+        let _ = tokio::fs::remove_file(&staging).await;
+        restore().await;
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+    }
+
+    if let Err(e) = crate::scanner::replace_book_with_meta(
+        &state.db,
+        &book,
+        &new_filename,
+        &extension,
+        data.len() as i64,
+        &meta,
+        &state.config.covers.covers_path,
+        cover_cfg,
+    )
+    .await
+    {
+        tracing::error!("Failed to update book {book_id} after replacement: {e}");
+        let _ = tokio::fs::remove_file(&new_path).await;
+        restore().await;
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db");
+    }
+    if backed_up && let Err(e) = tokio::fs::remove_file(&backup).await {
+        tracing::warn!("Failed to remove replaced file {backup:?}: {e}");
+    }
+
+    let html = render_book_fields(&state, &jar, book_id).await;
+    axum::Json(serde_json::json!({
+        "ok": true,
+        "format": extension,
+        "size": data.len(),
+        "html": html,
+    }))
+    .into_response()
+}
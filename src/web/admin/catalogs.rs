@@ -0,0 +1,165 @@
+use super::*;
+
+use crate::db::queries::catalogs;
+
+#[derive(Deserialize)]
+pub struct RenameCatalogPayload {
+    pub catalog_id: i64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/catalog-rename -- change a catalog's display name. The
+/// directory or archive on disk keeps its path.
+pub async fn rename_catalog(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(payload): axum::Json<RenameCatalogPayload>,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &payload.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "csrf");
+    }
+
+    let name = match validate_catalog_name(&payload.name) {
+        Ok(n) => n,
+        Err(err) => return json_error(StatusCode::BAD_REQUEST, err),
+    };
+
+    if let Ok(None) | Err(_) = catalogs::get_by_id(&state.db, payload.catalog_id).await {
+        return json_error(StatusCode::NOT_FOUND, "error_catalog_not_found");
+    }
+
+    match catalogs::rename(&state.db, payload.catalog_id, &name).await {
+        Ok(()) => axum::Json(serde_json::json!({"ok": true, "cat_name": name})).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to rename catalog {}: {e}", payload.catalog_id);
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MoveCatalogPayload {
+    pub catalog_id: i64,
+    /// New parent; `None` moves the catalog to the top level.
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/catalog-move -- attach a catalog to another parent in
+/// the browse tree. Moving a catalog under itself or one of its descendants
+/// is refused.
+pub async fn move_catalog(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(payload): axum::Json<MoveCatalogPayload>,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &payload.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "csrf");
+    }
+
+    if let Ok(None) | Err(_) = catalogs::get_by_id(&state.db, payload.catalog_id).await {
+        return json_error(StatusCode::NOT_FOUND, "error_catalog_not_found");
+    }
+    if let Some(parent_id) = payload.parent_id {
+        if let Ok(None) | Err(_) = catalogs::get_by_id(&state.db, parent_id).await {
+            return json_error(StatusCode::NOT_FOUND, "error_catalog_not_found");
+        }
+        match catalogs::is_in_subtree(&state.db, payload.catalog_id, parent_id).await {
+            Ok(false) => {}
+            Ok(true) => return json_error(StatusCode::BAD_REQUEST, "error_catalog_cycle"),
+            Err(e) => {
+                tracing::error!("Failed to check catalog {parent_id} ancestry: {e}");
+                return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db");
+            }
+        }
+    }
+
+    match catalogs::move_to(&state.db, payload.catalog_id, payload.parent_id).await {
+        Ok(()) => axum::Json(serde_json::json!({"ok": true, "parent_id": payload.parent_id}))
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to move catalog {}: {e}", payload.catalog_id);
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CatalogSearchQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+/// GET /web/admin/catalog-search -- catalogs by display name, for picking a
+/// new parent.
+pub async fn catalog_search(
+    State(state): State<AppState>,
+    Query(params): Query<CatalogSearchQuery>,
+) -> Response {
+    let term = params.q.trim();
+    if term.chars().count() < 2 {
+        return axum::Json(serde_json::json!({"ok": true, "catalogs": []})).into_response();
+    }
+    let results = catalogs::search_by_name(&state.db, term, 20)
+        .await
+        .unwrap_or_default();
+    let catalogs_json: Vec<serde_json::Value> = results
+        .into_iter()
+        .map(|c| {
+            serde_json::json!({
+                "id": c.id,
+                "cat_name": c.cat_name,
+                "path": c.path,
+                "hidden": c.cat_hidden != 0,
+            })
+        })
+        .collect();
+    axum::Json(serde_json::json!({"ok": true, "catalogs": catalogs_json})).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct HideCatalogPayload {
+    pub catalog_id: i64,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/catalog-hidden -- hide a catalog from browsing and have
+/// the scanner skip its folder, or show it again. Its books leave (or
+/// return to) the library with the next scan.
+pub async fn set_catalog_hidden(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(payload): axum::Json<HideCatalogPayload>,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &payload.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "csrf");
+    }
+
+    if let Ok(None) | Err(_) = catalogs::get_by_id(&state.db, payload.catalog_id).await {
+        return json_error(StatusCode::NOT_FOUND, "error_catalog_not_found");
+    }
+
+    match catalogs::set_hidden(&state.db, payload.catalog_id, payload.hidden).await {
+        Ok(()) => {
+            axum::Json(serde_json::json!({"ok": true, "hidden": payload.hidden})).into_response()
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to update hidden flag of catalog {}: {e}",
+                payload.catalog_id
+            );
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_db")
+        }
+    }
+}
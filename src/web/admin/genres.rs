@@ -339,3 +339,49 @@ pub async fn delete_section(
         }
     }
 }
+
+#[derive(Deserialize)]
+pub struct ReseedGenresPayload {
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/genres/reseed — restore missing default sections, genres
+/// and translations.
+pub async fn reseed_genres(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(payload): axum::Json<ReseedGenresPayload>,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &payload.csrf_token) {
+        return (
+            StatusCode::FORBIDDEN,
+            axum::Json(serde_json::json!({"ok": false})),
+        )
+            .into_response();
+    }
+
+    match crate::db::queries::genres::seed_defaults(&state.db).await {
+        Ok(report) => {
+            if report != Default::default() {
+                state.library_changed().await;
+            }
+            axum::Json(serde_json::json!({
+                "ok": true,
+                "sections": report.sections,
+                "genres": report.genres,
+                "translations": report.translations,
+            }))
+            .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to restore default genres: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(serde_json::json!({"ok": false})),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use super::*;
+
+use axum::extract::ConnectInfo;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use std::net::SocketAddr;
+
+use crate::db::queries::audit;
+use crate::web::auth::{parse_session, session_cookie, sign_session};
+
+/// Cookie keeping the superuser's own session while they browse as someone else.
+pub const IMPERSONATOR_COOKIE: &str = "impersonator";
+
+/// Lifetime of an impersonated session, independent of `session_ttl_hours`.
+const IMPERSONATION_TTL_HOURS: u64 = 1;
+
+/// A cookie that ends with the browser.
+fn browser_cookie(name: &'static str, value: String) -> Cookie<'static> {
+    Cookie::build((name, value))
+        .path("/web")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+/// Keep an impersonation event for the admin panel's audit log. A read-only
+/// mirror writes nothing; the `audit` log line remains.
+async fn record_audit(
+    state: &AppState,
+    admin_name: &str,
+    action: &str,
+    user_name: &str,
+    addr: SocketAddr,
+) {
+    if state.config.server.read_only {
+        return;
+    }
+    let ip = addr.ip().to_string();
+    if let Err(e) = audit::record(&state.db, admin_name, action, user_name, &ip).await {
+        tracing::error!("Failed to record {action} of {admin_name}: {e}");
+    }
+}
+
+/// The superuser behind the current impersonated session, if any.
+pub async fn impersonator_id(state: &AppState, jar: &CookieJar) -> Option<i64> {
+    let secret = state.config.server.session_secret.as_bytes();
+    let admin_id = jar
+        .get(IMPERSONATOR_COOKIE)
+        .and_then(|c| verify_session(c.value(), secret))?;
+    users::is_superuser(&state.db, admin_id)
+        .await
+        .unwrap_or(false)
+        .then_some(admin_id)
+}
+
+/// POST /web/admin/impersonate/{id} — browse the library as another user.
+pub async fn impersonate(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    Path(user_id): Path<i64>,
+    axum::Form(form): axum::Form<CsrfForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+    let (Some(session), Some(admin_id)) = (
+        jar.get("session").map(|c| c.value().to_string()),
+        get_session_user_id(&jar, secret),
+    ) else {
+        return (StatusCode::FORBIDDEN, "Forbidden").into_response();
+    };
+
+    // Superusers are not impersonated: the session would carry admin rights.
+    let target = match users::get_by_id(&state.db, user_id).await {
+        Ok(Some(user)) if user.id != admin_id && user.is_superuser == 0 => user,
+        Ok(_) => return Redirect::to("/web/admin?error=cannot_impersonate").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to load user {user_id}: {e}");
+            return Redirect::to("/web/admin?error=db_error").into_response();
+        }
+    };
+
+    let admin_name = users::get_username(&state.db, admin_id)
+        .await
+        .unwrap_or_else(|_| format!("uid={admin_id}"));
+    tracing::info!(
+        target: "audit",
+        "{} Impersonation started: admin={admin_name} user={}",
+        addr.ip(),
+        target.username
+    );
+    record_audit(
+        &state,
+        &admin_name,
+        audit::IMPERSONATION_STARTED,
+        &target.username,
+        addr,
+    )
+    .await;
+
+    let token = sign_session(user_id, secret, IMPERSONATION_TTL_HOURS);
+    let jar = jar
+        .add(browser_cookie(IMPERSONATOR_COOKIE, session))
+        .add(browser_cookie("session", token));
+    (jar, Redirect::to("/web")).into_response()
+}
+
+/// POST /web/impersonate/exit — return to the superuser's own session.
+pub async fn stop_impersonating(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    jar: CookieJar,
+    axum::Form(form): axum::Form<CsrfForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    // The admin session is restored as it was issued, so a "Remember me"
+    // session outlives the browser again.
+    let admin_cookie = jar.get(IMPERSONATOR_COOKIE).and_then(|c| {
+        let session = parse_session(c.value(), secret)?;
+        Some(session_cookie(
+            c.value().to_string(),
+            session.ttl_hours,
+            session.remember,
+        ))
+    });
+    let (Some(admin_id), Some(admin_cookie)) = (impersonator_id(&state, &jar).await, admin_cookie)
+    else {
+        let jar = jar.remove(Cookie::build(IMPERSONATOR_COOKIE).path("/web"));
+        return (jar, Redirect::to("/web")).into_response();
+    };
+
+    let admin_name = users::get_username(&state.db, admin_id)
+        .await
+        .unwrap_or_else(|_| format!("uid={admin_id}"));
+    let user_name = match get_session_user_id(&jar, secret) {
+        Some(uid) => users::get_username(&state.db, uid)
+            .await
+            .unwrap_or_else(|_| format!("uid={uid}")),
+        None => "(expired)".to_string(),
+    };
+    tracing::info!(
+        target: "audit",
+        "{} Impersonation ended: admin={admin_name} user={user_name}",
+        addr.ip()
+    );
+    record_audit(
+        &state,
+        &admin_name,
+        audit::IMPERSONATION_ENDED,
+        &user_name,
+        addr,
+    )
+    .await;
+
+    let jar = jar
+        .remove(Cookie::build(IMPERSONATOR_COOKIE).path("/web"))
+        .add(admin_cookie);
+    (jar, Redirect::to("/web/admin")).into_response()
+}
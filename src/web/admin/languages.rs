@@ -0,0 +1,96 @@
+use super::*;
+
+use crate::db::queries::books;
+
+/// Cap on books listed on the language preview page.
+const PREVIEW_LIST_LIMIT: usize = 200;
+
+#[derive(Deserialize)]
+pub struct LanguagesParams {
+    /// Books updated by the last apply, shown as a confirmation.
+    pub applied: Option<usize>,
+}
+
+/// GET /web/admin/languages — list the languages guessed for books stored
+/// without one, so they can be checked before [`apply_languages`] saves
+/// them.
+pub async fn languages_page(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<LanguagesParams>,
+) -> Response {
+    let (guesses, total) = match crate::langdetect::guess_missing(&state.db).await {
+        Ok(found) => found,
+        Err(e) => {
+            tracing::error!("Language guessing failed: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let mut per_lang: Vec<(&str, usize)> = Vec::new();
+    for guess in &guesses {
+        match per_lang.iter_mut().find(|(lang, _)| *lang == guess.lang) {
+            Some((_, n)) => *n += 1,
+            None => per_lang.push((guess.lang, 1)),
+        }
+    }
+    per_lang.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let per_lang: Vec<serde_json::Value> = per_lang
+        .into_iter()
+        .map(|(lang, count)| serde_json::json!({"lang": lang, "count": count}))
+        .collect();
+
+    let mut ctx = build_context(&state, &jar, "admin").await;
+    ctx.insert("missing_total", &total);
+    ctx.insert("guessed_total", &guesses.len());
+    ctx.insert("per_lang", &per_lang);
+    ctx.insert("guesses", &guesses[..guesses.len().min(PREVIEW_LIST_LIMIT)]);
+    ctx.insert("hidden", &guesses.len().saturating_sub(PREVIEW_LIST_LIMIT));
+    ctx.insert("applied", &params.applied);
+    ctx.insert("cfg_read_only", &state.config.server.read_only);
+
+    match state.tera.render("web/languages.html", &ctx) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Template error: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ApplyLanguagesForm {
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/languages/apply — store the guessed languages. Guesses are made
+/// afresh, so books tagged since the preview keep their language.
+pub async fn apply_languages(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Form(form): axum::Form<ApplyLanguagesForm>,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    let guesses = match crate::langdetect::guess_missing(&state.db).await {
+        Ok((guesses, _)) => guesses,
+        Err(e) => {
+            tracing::error!("Language guessing failed: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let mut applied = 0;
+    for guess in &guesses {
+        match books::update_lang(&state.db, guess.book_id, guess.lang).await {
+            Ok(()) => applied += 1,
+            Err(e) => tracing::warn!("Failed to set language of book {}: {e}", guess.book_id),
+        }
+    }
+    tracing::info!("Language fixing: {applied} book(s) updated");
+
+    Redirect::to(&format!("/web/admin/languages?applied={applied}")).into_response()
+}
@@ -4,6 +4,9 @@ use super::*;
 pub struct ScanForm {
     #[serde(default)]
     pub csrf_token: String,
+    /// Folder to scan, relative to the library root; empty scans everything.
+    #[serde(default)]
+    pub path: String,
 }
 
 /// POST /web/admin/scan — trigger a manual scan.
@@ -17,18 +20,23 @@ pub async fn scan_now(
         return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
     }
 
-    if crate::scanner::is_scanning() {
+    if crate::scanner::scan_in_progress(&state.db).await {
         return Redirect::to("/web/admin?error=scan_already_running").into_response();
     }
+    if crate::scanner::resolve_scan_path(&state.config.library.root_path, &form.path).is_err() {
+        return Redirect::to("/web/admin?error=invalid_scan_path").into_response();
+    }
 
     let pool = state.db.clone();
     let config = (*state.config).clone();
+    let observers = state.scan_observers.clone();
     tokio::spawn(async move {
-        match crate::scanner::run_scan(&pool, &config).await {
+        match crate::scanner::run_scan_path_observed(&pool, &config, &form.path, &observers).await {
             Ok(ref stats) => {
                 tracing::info!(
-                    "Manual scan finished: {} added, {} skipped, {} deleted, {} errors",
+                    "Manual scan finished: {} added, {} updated, {} skipped, {} deleted, {} errors",
                     stats.books_added,
+                    stats.books_updated,
                     stats.books_skipped,
                     stats.books_deleted,
                     stats.errors,
@@ -53,9 +61,72 @@ pub async fn scan_now(
     Redirect::to("/web/admin?msg=scan_started").into_response()
 }
 
+/// Cap on paths listed per section of the scan preview page.
+const PREVIEW_LIST_LIMIT: usize = 200;
+
+#[derive(Deserialize)]
+pub struct ScanPreviewParams {
+    #[serde(default)]
+    pub path: String,
+}
+
+/// GET /web/admin/scan-preview — show what a scan (of `?path=`, if given)
+/// would change, without touching the database.
+pub async fn scan_preview(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<ScanPreviewParams>,
+) -> Response {
+    let preview =
+        match crate::scanner::preview_scan_path(&state.db, &state.config, &params.path).await {
+            Ok(preview) => preview,
+            Err(crate::scanner::ScanError::AlreadyRunning) => {
+                return Redirect::to("/web/admin?error=scan_already_running").into_response();
+            }
+            Err(crate::scanner::ScanError::InvalidPath(_)) => {
+                return Redirect::to("/web/admin?error=invalid_scan_path").into_response();
+            }
+            Err(e) => {
+                tracing::error!("Scan preview failed: {e}");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+    let section = |key: &str, items: &[String]| {
+        serde_json::json!({
+            "key": key,
+            "total": items.len(),
+            "hidden": items.len().saturating_sub(PREVIEW_LIST_LIMIT),
+            "items": &items[..items.len().min(PREVIEW_LIST_LIMIT)],
+        })
+    };
+    let sections = vec![
+        section("new_archives", &preview.new_archives),
+        section("changed_archives", &preview.changed_archives),
+        section("new_books", &preview.new_books),
+        section("missing_books", &preview.missing_books),
+        section("skipped_paths", &preview.skipped_paths),
+    ];
+
+    let mut ctx = build_context(&state, &jar, "admin").await;
+    ctx.insert("sections", &sections);
+    ctx.insert("scan_path", params.path.trim().trim_matches('/'));
+    ctx.insert("is_empty", &preview.is_empty());
+    ctx.insert("preview_errors", &preview.errors);
+    ctx.insert("cfg_delete_logical", &state.config.scanner.delete_logical);
+
+    match state.tera.render("web/scan_preview.html", &ctx) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Template error: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 /// GET /web/admin/scan-status — returns JSON scan status for polling.
-pub async fn scan_status() -> impl IntoResponse {
-    let scanning = crate::scanner::is_scanning();
+pub async fn scan_status(State(state): State<AppState>) -> impl IntoResponse {
+    let scanning = crate::scanner::scan_in_progress(&state.db).await;
     let mut resp = serde_json::json!({ "scanning": scanning });
     if !scanning && let Some(result) = crate::scanner::take_last_scan_result() {
         resp["result"] = serde_json::to_value(result).unwrap_or_default();
@@ -0,0 +1,104 @@
+use super::*;
+
+use crate::db::queries::shares;
+
+fn format_expiry(expires_at: i64) -> String {
+    chrono::DateTime::from_timestamp(expires_at, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// GET /web/admin/shares — active book share links and bookshelf guest links.
+pub async fn shares_page(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<Html<String>, StatusCode> {
+    let mut ctx = build_context(&state, &jar, "admin").await;
+    let now = chrono::Utc::now().timestamp();
+    let links: Vec<serde_json::Value> = shares::list_active(&state.db, now)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| {
+            serde_json::json!({
+                "id": s.id,
+                "book_id": s.book_id,
+                "title": s.title,
+                "username": s.username,
+                "created_at": s.created_at,
+                "expires_at": format_expiry(s.expires_at),
+            })
+        })
+        .collect();
+    let shelf_links: Vec<serde_json::Value> = shares::list_active_shelves(&state.db, now)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| {
+            serde_json::json!({
+                "id": s.id,
+                "username": s.username,
+                "created_at": s.created_at,
+                "expires_at": format_expiry(s.expires_at),
+            })
+        })
+        .collect();
+
+    ctx.insert("shares", &links);
+    ctx.insert("shelf_shares", &shelf_links);
+    ctx.insert("share_ttl_hours", &state.config.sharing.book_link_ttl_hours);
+    ctx.insert(
+        "shelf_share_ttl_hours",
+        &state.config.sharing.shelf_link_ttl_hours,
+    );
+
+    match state.tera.render("web/shares.html", &ctx) {
+        Ok(html) => Ok(Html(html)),
+        Err(e) => {
+            tracing::error!("Template error: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// POST /web/admin/shares/:id/revoke
+pub async fn revoke_share(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(share_id): Path<i64>,
+    axum::Form(form): axum::Form<CsrfForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    match shares::revoke(&state.db, share_id).await {
+        Ok(()) => Redirect::to("/web/admin/shares?msg=share_revoked").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to revoke share link {share_id}: {e}");
+            Redirect::to("/web/admin/shares?error=db_error").into_response()
+        }
+    }
+}
+
+/// POST /web/admin/shares/shelf/:id/revoke
+pub async fn revoke_shelf_share(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(share_id): Path<i64>,
+    axum::Form(form): axum::Form<CsrfForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    match shares::revoke_shelf(&state.db, share_id).await {
+        Ok(()) => Redirect::to("/web/admin/shares?msg=shelf_revoked").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to revoke shelf link {share_id}: {e}");
+            Redirect::to("/web/admin/shares?error=db_error").into_response()
+        }
+    }
+}
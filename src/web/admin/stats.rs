@@ -0,0 +1,220 @@
+use super::*;
+
+use std::collections::HashMap;
+
+use axum::http::header;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+
+use crate::db::models::DownloadCount;
+use crate::db::queries::downloads;
+use crate::util::to_csv;
+
+/// Periods (in days) offered on the stats page; 0 means all time.
+const STATS_PERIODS: [u32; 5] = [7, 30, 90, 365, 0];
+const DEFAULT_PERIOD: u32 = 30;
+/// Rows shown in each leaderboard on the page.
+const LEADERBOARD_SIZE: i32 = 20;
+/// Rows written to a CSV leaderboard export.
+const CSV_ROWS_MAX: i32 = 10_000;
+
+#[derive(Deserialize)]
+pub struct DownloadStatsParams {
+    pub period: Option<u32>,
+    /// `csv` exports `table` instead of rendering the page.
+    pub format: Option<String>,
+    /// `books`, `authors`, `users`, `daily` or `weekly`.
+    pub table: Option<String>,
+}
+
+/// GET /web/admin/stats/downloads — download leaderboards and charts,
+/// or a CSV export of one of them with `?format=csv&table=...`.
+pub async fn download_stats_page(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<DownloadStatsParams>,
+) -> Response {
+    let period = params
+        .period
+        .filter(|p| STATS_PERIODS.contains(p))
+        .unwrap_or(DEFAULT_PERIOD);
+    let today = Utc::now().date_naive();
+    let since_day = (period > 0).then(|| today - Duration::days(i64::from(period) - 1));
+    let since = since_day
+        .map(|d| format!("{d} 00:00:00"))
+        .unwrap_or_default();
+
+    if params.format.as_deref() == Some("csv") {
+        let table = params.table.as_deref().unwrap_or("books");
+        return csv_export(&state, table, &since, since_day, today, period).await;
+    }
+
+    let daily = downloads::daily_counts(&state.db, &since)
+        .await
+        .unwrap_or_default();
+    let total: i64 = daily.iter().map(|(_, n)| n).sum();
+    let daily = fill_days(&daily, since_day, today);
+    let weekly = weekly_counts(&daily);
+    let books = downloads::book_leaderboard(&state.db, &since, LEADERBOARD_SIZE)
+        .await
+        .unwrap_or_default();
+    let authors = downloads::author_leaderboard(&state.db, &since, LEADERBOARD_SIZE)
+        .await
+        .unwrap_or_default();
+    let users = downloads::user_leaderboard(&state.db, &since, LEADERBOARD_SIZE)
+        .await
+        .unwrap_or_default();
+
+    let mut ctx = build_context(&state, &jar, "admin").await;
+    ctx.insert("period", &period);
+    ctx.insert("periods", &STATS_PERIODS);
+    ctx.insert("total_downloads", &total);
+    ctx.insert(
+        "charts",
+        &[chart("daily", &daily), chart("weekly", &weekly)],
+    );
+    ctx.insert(
+        "boards",
+        &serde_json::json!([
+            { "key": "books", "rows": books },
+            { "key": "authors", "rows": authors },
+            { "key": "users", "rows": users },
+        ]),
+    );
+
+    match state.tera.render("web/download_stats.html", &ctx) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Template error: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn csv_export(
+    state: &AppState,
+    table: &str,
+    since: &str,
+    since_day: Option<NaiveDate>,
+    today: NaiveDate,
+    period: u32,
+) -> Response {
+    let rows: Vec<Vec<String>> = match table {
+        "books" | "authors" | "users" => {
+            let leaderboard = match table {
+                "books" => downloads::book_leaderboard(&state.db, since, CSV_ROWS_MAX).await,
+                "authors" => downloads::author_leaderboard(&state.db, since, CSV_ROWS_MAX).await,
+                _ => downloads::user_leaderboard(&state.db, since, CSV_ROWS_MAX).await,
+            };
+            let Ok(leaderboard) = leaderboard else {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            };
+            let mut rows = vec![vec!["id".into(), "name".into(), "downloads".into()]];
+            rows.extend(leaderboard.iter().map(leaderboard_row));
+            rows
+        }
+        "daily" | "weekly" => {
+            let Ok(daily) = downloads::daily_counts(&state.db, since).await else {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            };
+            let mut counts = fill_days(&daily, since_day, today);
+            if table == "weekly" {
+                counts = weekly_counts(&counts);
+            }
+            let header = if table == "weekly" { "week" } else { "day" };
+            let mut rows = vec![vec![header.into(), "downloads".into()]];
+            rows.extend(counts.into_iter().map(|(day, n)| vec![day, n.to_string()]));
+            rows
+        }
+        _ => return (StatusCode::BAD_REQUEST, "Unknown table").into_response(),
+    };
+
+    let period_label = if period == 0 {
+        "all".to_string()
+    } else {
+        format!("{period}d")
+    };
+    let filename = format!("downloads-{table}-{period_label}.csv");
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        to_csv(&rows),
+    )
+        .into_response()
+}
+
+fn leaderboard_row(count: &DownloadCount) -> Vec<String> {
+    vec![
+        count.id.to_string(),
+        count.name.clone(),
+        count.downloads.to_string(),
+    ]
+}
+
+/// Daily counts for every day from `from` (or the first day with downloads)
+/// to `to`, with zeros for days without downloads.
+pub(super) fn fill_days(
+    daily: &[(String, i64)],
+    from: Option<NaiveDate>,
+    to: NaiveDate,
+) -> Vec<(String, i64)> {
+    let counts: HashMap<&str, i64> = daily.iter().map(|(day, n)| (day.as_str(), *n)).collect();
+    let first = daily
+        .first()
+        .and_then(|(day, _)| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok());
+    let Some(start) = from.or(first) else {
+        return Vec::new();
+    };
+    start
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| {
+            let label = day.to_string();
+            let n = counts.get(label.as_str()).copied().unwrap_or(0);
+            (label, n)
+        })
+        .collect()
+}
+
+/// Sum daily counts into weeks, labelled by their Monday.
+pub(super) fn weekly_counts(daily: &[(String, i64)]) -> Vec<(String, i64)> {
+    let mut weeks: Vec<(String, i64)> = Vec::new();
+    for (day, n) in daily {
+        let Ok(date) = NaiveDate::parse_from_str(day, "%Y-%m-%d") else {
+            continue;
+        };
+        let monday = date - Duration::days(i64::from(date.weekday().num_days_from_monday()));
+        let label = monday.to_string();
+        match weeks.last_mut() {
+            Some((last, total)) if *last == label => *total += n,
+            _ => weeks.push((label, *n)),
+        }
+    }
+    weeks
+}
+
+/// A bar chart: bar heights are relative to the largest count.
+fn chart(key: &str, counts: &[(String, i64)]) -> serde_json::Value {
+    let max = counts.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+    let bars: Vec<serde_json::Value> = counts
+        .iter()
+        .map(|(label, n)| {
+            serde_json::json!({
+                "label": label,
+                "downloads": n,
+                "pct": n * 100 / max,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "key": key,
+        "bars": bars,
+        "first": counts.first().map(|(label, _)| label.as_str()).unwrap_or_default(),
+        "last": counts.last().map(|(label, _)| label.as_str()).unwrap_or_default(),
+    })
+}
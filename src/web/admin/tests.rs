@@ -3,7 +3,7 @@ mod tests {
     use super::*;
     use crate::config::{
         Config, CoversConfig, DatabaseConfig, LibraryConfig, OpdsConfig, ReaderConfig,
-        ScannerConfig, ServerConfig, UploadConfig, WebConfig,
+        ScannerConfig, ServerConfig, ServerProfile, UploadConfig, WebConfig,
     };
     use crate::db::{DbPool, create_test_pool};
     use crate::web::auth::sign_session;
@@ -20,7 +20,18 @@ mod tests {
                 log_level: "info".to_string(),
                 session_secret: "test-secret".to_string(),
                 session_ttl_hours: 24,
+                session_sliding: true,
+                remember_me_ttl_hours: 720,
                 base_url: String::new(),
+                trust_proxy_headers: false,
+                profile: ServerProfile::Default,
+                read_only: false,
+                request_timeout_secs: 60,
+                opds_body_limit_kb: 64,
+                web_body_limit_kb: 256,
+                admin_body_limit_kb: 2048,
+                min_free_space_mb: 0,
+                access_log_path: None,
             },
             library: LibraryConfig {
                 root_path: PathBuf::from("/tmp/books"),
@@ -31,16 +42,25 @@ mod tests {
                 scan_zip: true,
                 zip_codepage: "cp866".to_string(),
                 inpx_enable: false,
+                filename_patterns: Vec::new(),
+                audiobooks: false,
+                detect_lang: true,
+                detect_lang_confidence: 0.3,
             },
             covers: CoversConfig {
                 covers_path: PathBuf::from("/tmp/covers"),
                 cover_max_dimension_px: 600,
                 cover_jpeg_quality: 85,
                 show_covers: true,
+                signed_urls: false,
+                signed_url_ttl_mins: 60,
             },
             database: DatabaseConfig {
                 url: "sqlite::memory:".to_string(),
                 max_connections: 5,
+                retry: 3,
+                retry_backoff_ms: 100,
+                auto_migrate: true,
             },
             opds: OpdsConfig {
                 title: "ROPDS".to_string(),
@@ -51,6 +71,15 @@ mod tests {
                 show_covers: None,
                 alphabet_menu: true,
                 hide_doubles: false,
+                group_formats: false,
+                hide_empty: false,
+                deep_catalogs: false,
+                catalog_thumbnails: false,
+                auto_bookshelf: true,
+                icon_path: None,
+                logo_url: None,
+                absolute_urls: false,
+                dump_page_size: 500,
             },
             scanner: ScannerConfig {
                 schedule_minutes: vec![0],
@@ -61,25 +90,49 @@ mod tests {
                 test_zip: false,
                 test_files: false,
                 workers_num: 1,
+                exclude: Vec::new(),
+                max_depth: 64,
+                changes_log: None,
+                link_identical: false,
             },
             web: WebConfig {
                 language: "en".to_string(),
                 theme: "light".to_string(),
+                accent_color: None,
+                site_name: None,
+                seo_enabled: false,
+                robots_txt: None,
             },
             upload: UploadConfig {
                 allow_upload: true,
                 upload_path: PathBuf::from("/tmp/uploads"),
                 max_upload_size_mb: 10,
+                ..Default::default()
             },
             reader: ReaderConfig::default(),
             oauth: Default::default(),
             smtp: Default::default(),
+            tools: Default::default(),
+            search: Default::default(),
+            sharing: Default::default(),
+            api: Default::default(),
+            password: Default::default(),
+            download: Default::default(),
+            formats: Default::default(),
+            clients: Default::default(),
+            stats: Default::default(),
         };
 
         let tera = tera::Tera::default();
         let mut translations = crate::web::i18n::Translations::new();
         translations.insert("en".to_string(), serde_json::json!({}));
-        AppState::new(config, pool, tera, translations, false, false)
+        AppState::new(
+            config,
+            pool,
+            tera,
+            translations,
+            crate::state::Capabilities::default(),
+        )
     }
 
     async fn insert_test_book(pool: &DbPool, title: &str) -> i64 {
@@ -128,12 +181,16 @@ mod tests {
         serde_json::from_slice(&body).unwrap()
     }
 
-    #[test]
-    fn test_is_valid_password_boundaries() {
-        assert!(!is_valid_password("1234567"));
-        assert!(is_valid_password("12345678"));
-        assert!(is_valid_password(&"x".repeat(32)));
-        assert!(!is_valid_password(&"x".repeat(33)));
+    #[tokio::test]
+    async fn test_password_error_boundaries() {
+        let state = test_state(create_test_pool().await);
+        assert_eq!(password_error(&state, "1234567"), Some("password_short"));
+        assert_eq!(password_error(&state, "12345678"), None);
+        assert_eq!(password_error(&state, &"x".repeat(32)), None);
+        assert_eq!(
+            password_error(&state, &"x".repeat(33)),
+            Some("password_short")
+        );
     }
 
     #[test]
@@ -166,6 +223,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_book_annotation_rules() {
+        assert_eq!(
+            validate_book_annotation("  First\r\n\r\n- item\t1  ").unwrap(),
+            "First\n\n- item\t1".to_string()
+        );
+        assert_eq!(validate_book_annotation("   ").unwrap(), "");
+        assert_eq!(validate_book_annotation("a\u{1F600}b").unwrap(), "ab");
+        assert_eq!(
+            validate_book_annotation(&"a".repeat(8001)).unwrap_err(),
+            "annotation_too_long"
+        );
+        assert_eq!(
+            validate_book_annotation("abc\u{0007}def").unwrap_err(),
+            "annotation_invalid"
+        );
+    }
+
+    #[test]
+    fn test_validate_book_lang_rules() {
+        assert_eq!(validate_book_lang(" EN ").unwrap(), "en");
+        assert_eq!(validate_book_lang("pt_BR").unwrap(), "pt-br");
+        assert_eq!(validate_book_lang("sr-Latn").unwrap(), "sr-latn");
+        assert_eq!(validate_book_lang("").unwrap(), "");
+        for bad in ["e", "english", "en-", "en-us-x", "ру", "e1"] {
+            assert_eq!(validate_book_lang(bad).unwrap_err(), "lang_invalid", "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_validate_catalog_name_rules() {
+        assert_eq!(validate_catalog_name(" Fiction ").unwrap(), "Fiction");
+        assert_eq!(validate_catalog_name(" ").unwrap_err(), "name_empty");
+        assert_eq!(
+            validate_catalog_name(&"a".repeat(257)).unwrap_err(),
+            "name_too_long"
+        );
+        assert_eq!(validate_catalog_name("a\tb").unwrap_err(), "name_invalid");
+    }
+
     #[test]
     fn test_get_session_user_id_valid_and_invalid() {
         let secret = b"session-secret-for-tests";
@@ -289,4 +386,113 @@ mod tests {
         assert!(!results.is_empty());
         assert_eq!(results[0]["ser_name"], "Foundations");
     }
+
+    #[tokio::test]
+    async fn test_move_catalog_handler_rejects_cycles() {
+        use crate::db::models::CatType;
+        use crate::db::queries::catalogs;
+
+        let pool = create_test_pool().await;
+        let state = test_state(pool.clone());
+        let top = catalogs::insert(&pool, None, "/top", "top", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+        let child = catalogs::insert(&pool, Some(top), "/top/child", "child", CatType::Normal, 0, "")
+            .await
+            .unwrap();
+
+        let secret = state.config.server.session_secret.as_bytes();
+        let session = sign_session(1, secret, 24);
+        let csrf_token = generate_csrf_token(&session, secret);
+        let jar = CookieJar::new().add(Cookie::new("session", session.clone()));
+        let move_to = |catalog_id, parent_id| {
+            move_catalog(
+                State(state.clone()),
+                jar.clone(),
+                axum::Json(MoveCatalogPayload {
+                    catalog_id,
+                    parent_id,
+                    csrf_token: csrf_token.clone(),
+                }),
+            )
+        };
+
+        let json = response_json(move_to(top, Some(child)).await).await;
+        assert_eq!(json["error"], "error_catalog_cycle");
+        let json = response_json(move_to(top, Some(top)).await).await;
+        assert_eq!(json["error"], "error_catalog_cycle");
+        let resp = move_to(child, Some(9999)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = move_to(child, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = response_json(move_to(top, Some(child)).await).await;
+        assert_eq!(json["ok"], true);
+        let cat = catalogs::get_by_id(&pool, top).await.unwrap().unwrap();
+        assert_eq!(cat.parent_id, Some(child));
+    }
+
+    #[tokio::test]
+    async fn test_reseed_genres_handler_restores_deleted_genre() {
+        use crate::db::queries::genres;
+
+        let pool = create_test_pool().await;
+        let state = test_state(pool.clone());
+        let genre = genres::get_by_code(&pool, "sf_fantasy_city")
+            .await
+            .unwrap()
+            .unwrap();
+        genres::delete_genre(&pool, genre.id).await.unwrap();
+
+        let secret = state.config.server.session_secret.as_bytes();
+        let session = sign_session(1, secret, 24);
+        let csrf_token = generate_csrf_token(&session, secret);
+        let jar = CookieJar::new().add(Cookie::new("session", session.clone()));
+
+        let resp = reseed_genres(
+            State(state.clone()),
+            jar.clone(),
+            axum::Json(ReseedGenresPayload {
+                csrf_token: String::new(),
+            }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = reseed_genres(
+            State(state.clone()),
+            jar,
+            axum::Json(ReseedGenresPayload { csrf_token }),
+        )
+        .await;
+        let json = response_json(resp).await;
+        assert_eq!(json["ok"], true);
+        assert_eq!(json["genres"], 1);
+        assert_eq!(json["sections"], 0);
+        assert!(
+            genres::get_by_code(&pool, "sf_fantasy_city")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_download_stats_fill_days_and_weeks() {
+        let day = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let daily = vec![("2024-03-02".to_string(), 2), ("2024-03-05".to_string(), 3)];
+
+        let filled = fill_days(&daily, Some(day("2024-03-01")), day("2024-03-05"));
+        let counts: Vec<i64> = filled.iter().map(|(_, n)| *n).collect();
+        assert_eq!(counts, [0, 2, 0, 0, 3]);
+        // All time starts at the first download.
+        assert_eq!(fill_days(&daily, None, day("2024-03-05")).len(), 4);
+        assert!(fill_days(&[], None, day("2024-03-05")).is_empty());
+
+        // 2024-03-04 is a Monday.
+        assert_eq!(
+            weekly_counts(&filled),
+            vec![("2024-02-26".to_string(), 2), ("2024-03-04".to_string(), 3)]
+        );
+    }
 }
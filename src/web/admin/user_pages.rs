@@ -1,5 +1,8 @@
 use super::*;
 
+/// Audit log entries shown on the admin page.
+const AUDIT_LOG_LIMIT: i64 = 50;
+
 pub async fn admin_page(
     State(state): State<AppState>,
     jar: CookieJar,
@@ -23,26 +26,72 @@ pub async fn admin_page(
     };
     ctx.insert("current_user_id", &current_user_id);
 
+    // OPDS clients seen per user (stats.user_agents)
+    let devices = crate::db::queries::devices::list_all(&state.db)
+        .await
+        .unwrap_or_default();
+    ctx.insert("devices", &devices);
+    ctx.insert("cfg_stats_user_agents", &state.config.stats.user_agents);
+
+    // Recent administrator actions (impersonation)
+    let audit_entries = crate::db::queries::audit::list_recent(&state.db, AUDIT_LOG_LIMIT)
+        .await
+        .unwrap_or_default();
+    ctx.insert("audit_entries", &audit_entries);
+
     // Server config sections (read-only display)
     ctx.insert(
         "cfg_uptime",
         &format_uptime(state.started_at.elapsed().as_secs(), &ctx),
     );
+    let volumes = crate::disk::report(&state.config);
+    ctx.insert("disk_low", &volumes.iter().any(|v| v.low));
+    ctx.insert("disk_volumes", &volumes);
+    ctx.insert(
+        "cfg_min_free_space_mb",
+        &state.config.server.min_free_space_mb,
+    );
     ctx.insert("cfg_host", &state.config.server.host);
     ctx.insert("cfg_port", &state.config.server.port);
     ctx.insert("cfg_log_level", &state.config.server.log_level);
+    ctx.insert("cfg_read_only", &state.config.server.read_only);
     ctx.insert(
         "cfg_pdf_preview_tool_available",
         &state.pdf_preview_tool_available,
     );
+    ctx.insert(
+        "cfg_pdf_metadata_tool_available",
+        &state.pdf_metadata_tool_available,
+    );
+    ctx.insert(
+        "cfg_pdf_text_tool_available",
+        &state.pdf_text_tool_available,
+    );
     ctx.insert(
         "cfg_djvu_preview_tool_available",
         &state.djvu_preview_tool_available,
     );
+    ctx.insert(
+        "cfg_pdftoppm_path",
+        &state.config.tools.pdftoppm_path.display().to_string(),
+    );
+    ctx.insert(
+        "cfg_pdfinfo_path",
+        &state.config.tools.pdfinfo_path.display().to_string(),
+    );
+    ctx.insert(
+        "cfg_pdftotext_path",
+        &state.config.tools.pdftotext_path.display().to_string(),
+    );
+    ctx.insert(
+        "cfg_ddjvu_path",
+        &state.config.tools.ddjvu_path.display().to_string(),
+    );
+    ctx.insert("cfg_tools_timeout_secs", &state.config.tools.timeout_secs);
 
     ctx.insert(
         "cfg_root_path",
-        &state.config.library.root_path.display().to_string(),
+        &crate::vfs::display_root(&state.config.library.root_path),
     );
     ctx.insert(
         "cfg_book_extensions",
@@ -95,7 +144,10 @@ pub async fn admin_page(
         &state.config.scanner.schedule_day_of_week,
     );
     ctx.insert("cfg_delete_logical", &state.config.scanner.delete_logical);
-    ctx.insert("is_scanning", &crate::scanner::is_scanning());
+    ctx.insert(
+        "is_scanning",
+        &crate::scanner::scan_in_progress(&state.db).await,
+    );
 
     // OAuth access requests (for Access Requests accordion)
     let pending_identities = crate::db::queries::oauth::list_by_status(&state.db, "pending")
@@ -169,8 +221,8 @@ pub async fn create_user(
     if !is_valid_username(username) {
         return Redirect::to("/web/admin?error=username_invalid").into_response();
     }
-    if !is_valid_password(&form.password) {
-        return Redirect::to("/web/admin?error=password_short").into_response();
+    if let Some(code) = password_error(&state, &form.password) {
+        return Redirect::to(&format!("/web/admin?error={code}")).into_response();
     }
 
     let is_super = if form.is_superuser.is_some() { 1 } else { 0 };
@@ -195,8 +247,8 @@ pub async fn change_password(
         return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
     }
 
-    if !is_valid_password(&form.password) {
-        return Redirect::to("/web/admin?error=password_short").into_response();
+    if let Some(code) = password_error(&state, &form.password) {
+        return Redirect::to(&format!("/web/admin?error={code}")).into_response();
     }
 
     let hash = crate::password::hash(&form.password);
@@ -287,6 +339,122 @@ pub async fn toggle_upload(
     }
 }
 
+#[derive(Deserialize)]
+pub struct ToggleActiveForm {
+    #[serde(default)]
+    pub active: Option<String>, // checkbox: present = "on", absent = None
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/users/:id/active — enable or disable an account.
+pub async fn toggle_active(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(user_id): Path<i64>,
+    axum::Form(form): axum::Form<ToggleActiveForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    if get_session_user_id(&jar, secret) == Some(user_id) {
+        return Redirect::to("/web/admin?error=cannot_disable_self").into_response();
+    }
+
+    match users::set_active(&state.db, user_id, form.active.is_some()).await {
+        Ok(_) => Redirect::to("/web/admin?msg=active_toggled").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to toggle account state of user {user_id}: {e}");
+            Redirect::to("/web/admin?error=db_error").into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UploadQuotaForm {
+    #[serde(default)]
+    pub upload_quota_files: i32,
+    #[serde(default)]
+    pub upload_quota_mb: i32,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/admin/users/:id/quota — set the monthly upload limits
+/// (`0` = unlimited).
+pub async fn update_upload_quota(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(user_id): Path<i64>,
+    axum::Form(form): axum::Form<UploadQuotaForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+    if form.upload_quota_files < 0 || form.upload_quota_mb < 0 {
+        return Redirect::to("/web/admin?error=invalid_quota").into_response();
+    }
+
+    match upload_quota::set_limits(
+        &state.db,
+        user_id,
+        form.upload_quota_files,
+        form.upload_quota_mb,
+    )
+    .await
+    {
+        Ok(_) => Redirect::to("/web/admin?msg=quota_updated").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to update upload quota for user {user_id}: {e}");
+            Redirect::to("/web/admin?error=db_error").into_response()
+        }
+    }
+}
+
+/// POST /web/admin/users/:id/quota/reset — reset the upload usage counters.
+pub async fn reset_upload_quota(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(user_id): Path<i64>,
+    axum::Form(form): axum::Form<CsrfForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    match upload_quota::reset(&state.db, user_id).await {
+        Ok(_) => Redirect::to("/web/admin?msg=quota_reset").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to reset upload usage for user {user_id}: {e}");
+            Redirect::to("/web/admin?error=db_error").into_response()
+        }
+    }
+}
+
+/// POST /web/admin/devices/clear — forget the recorded OPDS clients.
+pub async fn clear_devices(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Form(form): axum::Form<CsrfForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    match crate::db::queries::devices::clear_all(&state.db).await {
+        Ok(_) => Redirect::to("/web/admin?msg=devices_cleared").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to clear recorded devices: {e}");
+            Redirect::to("/web/admin?error=db_error").into_response()
+        }
+    }
+}
+
 /// GET /web/profile — render profile page for authenticated users.
 pub async fn profile_page(State(state): State<AppState>, jar: CookieJar) -> Response {
     let secret = state.config.server.session_secret.as_bytes();
@@ -306,6 +474,22 @@ pub async fn profile_page(State(state): State<AppState>, jar: CookieJar) -> Resp
     let base = &state.config.server.base_url;
     ctx.insert("opds_url", &format!("{base}/opds"));
     ctx.insert("opds_v2_url", &format!("{base}/opds/v2"));
+    ctx.insert(
+        "auto_bookshelf_available",
+        &state.config.opds.auto_bookshelf,
+    );
+    let auto_bookshelf = users::auto_bookshelf(&state.db, user_id)
+        .await
+        .unwrap_or(true);
+    ctx.insert("auto_bookshelf", &auto_bookshelf);
+    let opds_client = users::opds_client(&state.db, user_id)
+        .await
+        .unwrap_or_default();
+    ctx.insert("opds_client", &opds_client);
+    ctx.insert(
+        "opds_client_profiles",
+        &crate::opds::compat::profile_names(&state.config.clients),
+    );
 
     match state.tera.render("web/profile.html", &ctx) {
         Ok(html) => Html(html).into_response(),
@@ -348,6 +532,80 @@ pub async fn profile_update_display_name(
     Redirect::to("/web/profile?msg=display_name_changed").into_response()
 }
 
+#[derive(Deserialize)]
+pub struct AutoBookshelfForm {
+    /// Present (`1`) when the switch is on; browsers omit unchecked boxes.
+    #[serde(default)]
+    pub enabled: Option<String>,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/profile/auto-bookshelf — opt in or out of `opds.auto_bookshelf`.
+pub async fn profile_update_auto_bookshelf(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Form(form): axum::Form<AutoBookshelfForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    let user_id = match get_session_user_id(&jar, secret) {
+        Some(id) => id,
+        None => return Redirect::to("/web/login").into_response(),
+    };
+
+    let enabled = form.enabled.is_some();
+    if let Err(e) = users::update_auto_bookshelf(&state.db, user_id, enabled).await {
+        tracing::error!("Failed to update bookshelf setting for user {user_id}: {e}");
+        return Redirect::to("/web/profile?error=db_error").into_response();
+    }
+
+    Redirect::to("/web/profile?msg=auto_bookshelf_changed").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct OpdsClientForm {
+    /// Profile name, `none`, or empty to detect it from the User-Agent.
+    #[serde(default)]
+    pub profile: String,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/profile/opds-client — choose the OPDS client profile.
+pub async fn profile_update_opds_client(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Form(form): axum::Form<OpdsClientForm>,
+) -> impl IntoResponse {
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "CSRF validation failed").into_response();
+    }
+
+    let user_id = match get_session_user_id(&jar, secret) {
+        Some(id) => id,
+        None => return Redirect::to("/web/login").into_response(),
+    };
+
+    let profile = form.profile.trim().to_lowercase();
+    let known = profile.is_empty()
+        || profile == crate::opds::compat::PROFILE_NONE
+        || crate::opds::compat::profile_names(&state.config.clients).contains(&profile);
+    if !known {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    if let Err(e) = users::update_opds_client(&state.db, user_id, &profile).await {
+        tracing::error!("Failed to update OPDS client profile for user {user_id}: {e}");
+        return Redirect::to("/web/profile?error=db_error").into_response();
+    }
+
+    Redirect::to("/web/profile?msg=opds_client_changed").into_response()
+}
+
 /// POST /web/profile/password — change own password.
 pub async fn profile_change_password(
     State(state): State<AppState>,
@@ -364,8 +622,8 @@ pub async fn profile_change_password(
         None => return Redirect::to("/web/login").into_response(),
     };
 
-    if !is_valid_password(&form.password) {
-        return Redirect::to("/web/profile?error=password_short").into_response();
+    if let Some(code) = password_error(&state, &form.password) {
+        return Redirect::to(&format!("/web/profile?error={code}")).into_response();
     }
 
     let hash = crate::password::hash(&form.password);
@@ -443,11 +701,9 @@ pub async fn change_password_submit(
         .map(|n| format!("&next={}", urlencoding::encode(n)))
         .unwrap_or_default();
 
-    if !is_valid_password(&form.password) {
-        return Redirect::to(&format!(
-            "/web/change-password?error=password_short{next_param}"
-        ))
-        .into_response();
+    if let Some(code) = password_error(&state, &form.password) {
+        return Redirect::to(&format!("/web/change-password?error={code}{next_param}"))
+            .into_response();
     }
 
     let hash = crate::password::hash(&form.password);
@@ -504,7 +760,7 @@ pub async fn opds_password_reset(
         return (StatusCode::FORBIDDEN, "Not an OAuth user").into_response();
     }
 
-    let new_password = crate::password::generate_opds_password();
+    let new_password = crate::password::generate_opds_password(&state.config.password);
     let new_hash = crate::password::hash(&new_password);
 
     if let Err(e) = users::update_password(&state.db, user_id, &new_hash).await {
@@ -2,36 +2,78 @@ use axum::extract::{ConnectInfo, Query, Request, State};
 use axum::http::StatusCode;
 use axum::middleware::Next;
 use axum::response::{Html, IntoResponse, Redirect, Response};
-use axum_extra::extract::cookie::{Cookie, CookieJar};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use hmac::{Hmac, KeyInit, Mac};
 use serde::Deserialize;
 use sha2::Sha256;
 use std::net::SocketAddr;
 
 use crate::state::AppState;
+use crate::web::admin::impersonate::IMPERSONATOR_COOKIE;
+use crate::web::context::{insert_branding, insert_theme, theme_mode};
 use crate::web::i18n;
 
 type HmacSha256 = Hmac<Sha256>;
 
-/// Create a signed session cookie value: `{user_id}:{expiry}:{hex_signature}`.
+/// A verified session cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub user_id: i64,
+    /// Unix time the session expires at.
+    pub expiry: i64,
+    /// Lifetime the session was issued with; 0 for cookies from before it
+    /// was recorded, which are not renewed.
+    pub ttl_hours: u64,
+    /// Issued with "Remember me": the cookie outlives the browser.
+    pub remember: bool,
+}
+
+impl Session {
+    /// Whether less than half of the session's lifetime is left at `now`.
+    pub fn past_half_life(&self, now: i64) -> bool {
+        self.ttl_hours > 0 && self.expiry - now <= (self.ttl_hours * 3600 / 2) as i64
+    }
+}
+
+/// Create a signed session cookie value for a session that ends with the
+/// browser.
 pub fn sign_session(user_id: i64, secret: &[u8], ttl_hours: u64) -> String {
+    sign_session_with(user_id, secret, ttl_hours, false)
+}
+
+/// Create a signed session cookie value:
+/// `{user_id}:{expiry}:{ttl_hours}:{remember}:{hex_signature}`, `remember`
+/// being `1` for "Remember me" sessions and `0` otherwise.
+pub fn sign_session_with(user_id: i64, secret: &[u8], ttl_hours: u64, remember: bool) -> String {
     let expiry = chrono::Utc::now().timestamp() + (ttl_hours * 3600) as i64;
-    let payload = format!("{user_id}:{expiry}");
+    let remember = u8::from(remember);
+    let payload = format!("{user_id}:{expiry}:{ttl_hours}:{remember}");
     let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC can take key of any size");
     mac.update(payload.as_bytes());
     let sig = hex::encode(mac.finalize().into_bytes());
     format!("{payload}:{sig}")
 }
 
-/// Verify a signed session cookie value. Returns user_id if valid and not expired.
-pub fn verify_session(cookie_value: &str, secret: &[u8]) -> Option<i64> {
-    let parts: Vec<&str> = cookie_value.splitn(3, ':').collect();
-    if parts.len() != 3 {
+/// Verify a signed session cookie value. Returns the session if valid and
+/// not expired; cookies without the `remember` or `ttl_hours` fields are
+/// accepted too.
+pub fn parse_session(cookie_value: &str, secret: &[u8]) -> Option<Session> {
+    let (payload, sig_hex) = cookie_value.rsplit_once(':')?;
+    let mut fields = payload.split(':');
+    let user_id: i64 = fields.next()?.parse().ok()?;
+    let expiry: i64 = fields.next()?.parse().ok()?;
+    let ttl_hours: u64 = match fields.next() {
+        Some(ttl) => ttl.parse().ok()?,
+        None => 0,
+    };
+    let remember = match fields.next() {
+        Some("1") => true,
+        Some("0") | None => false,
+        Some(_) => return None,
+    };
+    if fields.next().is_some() {
         return None;
     }
-    let user_id: i64 = parts[0].parse().ok()?;
-    let expiry: i64 = parts[1].parse().ok()?;
-    let sig_hex = parts[2];
 
     // Check expiry
     if chrono::Utc::now().timestamp() > expiry {
@@ -39,14 +81,98 @@ pub fn verify_session(cookie_value: &str, secret: &[u8]) -> Option<i64> {
     }
 
     // Verify HMAC
-    let payload = format!("{}:{}", parts[0], parts[1]);
     let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC can take key of any size");
     mac.update(payload.as_bytes());
 
     let expected = hex::decode(sig_hex).ok()?;
     mac.verify_slice(&expected).ok()?;
 
-    Some(user_id)
+    Some(Session {
+        user_id,
+        expiry,
+        ttl_hours,
+        remember,
+    })
+}
+
+/// Verify a signed session cookie value. Returns user_id if valid and not expired.
+pub fn verify_session(cookie_value: &str, secret: &[u8]) -> Option<i64> {
+    parse_session(cookie_value, secret).map(|s| s.user_id)
+}
+
+/// User of the request's `session` cookie, if it is valid and the account
+/// is still active. Fails closed when the active check cannot be made.
+pub async fn active_session_user(state: &AppState, jar: &CookieJar) -> Option<i64> {
+    let secret = state.config.server.session_secret.as_bytes();
+    let uid = verify_session(jar.get("session")?.value(), secret)?;
+    crate::db::queries::users::is_active(&state.db, uid)
+        .await
+        .unwrap_or(false)
+        .then_some(uid)
+}
+
+/// The `session` cookie for a token issued with `ttl_hours`. "Remember me"
+/// sessions are kept across browser restarts; others end with the browser.
+pub fn session_cookie(token: String, ttl_hours: u64, remember: bool) -> Cookie<'static> {
+    let mut cookie = Cookie::build(("session", token))
+        .path("/web")
+        .http_only(true)
+        .same_site(SameSite::Lax);
+    if remember {
+        cookie = cookie.max_age(time::Duration::hours(ttl_hours as i64));
+    }
+    cookie.build()
+}
+
+/// Re-issue a session cookie past half of its lifetime (`session_sliding`).
+/// Impersonated sessions keep their fixed lifetime; an impersonator cookie
+/// only counts when its signature is valid.
+fn renew_session(state: &AppState, jar: &CookieJar) -> Option<Cookie<'static>> {
+    let server = &state.config.server;
+    if !server.session_sliding {
+        return None;
+    }
+    let impersonated = jar
+        .get(IMPERSONATOR_COOKIE)
+        .and_then(|c| parse_session(c.value(), server.session_secret.as_bytes()))
+        .is_some();
+    if impersonated {
+        return None;
+    }
+    let session = parse_session(
+        jar.get("session")?.value(),
+        server.session_secret.as_bytes(),
+    )?;
+    if !session.past_half_life(chrono::Utc::now().timestamp()) {
+        return None;
+    }
+    let token = sign_session_with(
+        session.user_id,
+        server.session_secret.as_bytes(),
+        session.ttl_hours,
+        session.remember,
+    );
+    Some(session_cookie(token, session.ttl_hours, session.remember))
+}
+
+/// Run the request and renew its session cookie on the way out when due.
+async fn run_renewing(state: &AppState, jar: CookieJar, request: Request, next: Next) -> Response {
+    let renewed = renew_session(state, &jar);
+    let response = next.run(request).await;
+    match renewed {
+        Some(cookie) => (jar.add(cookie), response).into_response(),
+        None => response,
+    }
+}
+
+/// Drop the session of a disabled account and send it to the login page.
+fn end_disabled_session(jar: CookieJar) -> Response {
+    let cookie = Cookie::build(("session", "")).path("/web");
+    (
+        jar.remove(cookie),
+        Redirect::to("/web/login?error=disabled"),
+    )
+        .into_response()
 }
 
 /// Middleware: require a valid session cookie for web routes.
@@ -58,14 +184,28 @@ pub async fn session_auth_layer(
     next: Next,
 ) -> Response {
     if !state.config.opds.auth_required {
-        return next.run(request).await;
+        // Anonymous access is allowed, but a disabled account's session
+        // must not keep acting as that user.
+        let secret = state.config.server.session_secret.as_bytes();
+        let has_session = jar
+            .get("session")
+            .and_then(|c| verify_session(c.value(), secret))
+            .is_some();
+        if has_session && active_session_user(&state, &jar).await.is_none() {
+            return end_disabled_session(jar);
+        }
+        return run_renewing(&state, jar, request, next).await;
     }
 
     let path = request.uri().path().to_string();
 
-    // Allow login page and set-language without auth
+    // Allow login page, set-language and guest shelf links without auth
     // Note: paths are relative to the nested /web router (prefix already stripped)
-    if path == "/login" || path.starts_with("/set-language") || path.starts_with("/oauth/") {
+    if path == "/login"
+        || path.starts_with("/set-language")
+        || path.starts_with("/oauth/")
+        || path.starts_with("/shared/")
+    {
         return next.run(request).await;
     }
 
@@ -78,10 +218,23 @@ pub async fn session_auth_layer(
     match user_id {
         Some(uid) => {
             // Allow these paths even when password change is required
-            if path == "/change-password" || path == "/profile/password" || path == "/logout" {
+            if path == "/change-password"
+                || path == "/profile/password"
+                || path == "/logout"
+                || path == "/impersonate/exit"
+            {
                 return next.run(request).await;
             }
 
+            // Disabled accounts lose their sessions at the next request.
+            // Fail closed like the password-change check below.
+            if !crate::db::queries::users::is_active(&state.db, uid)
+                .await
+                .unwrap_or(false)
+            {
+                return end_disabled_session(jar);
+            }
+
             // Check if user must change password before accessing the app.
             // Fail closed: DB errors are treated as "change required" to avoid
             // bypassing enforcement when the check cannot be trusted.
@@ -96,7 +249,7 @@ pub async fn session_auth_layer(
                     .into_response();
             }
 
-            next.run(request).await
+            run_renewing(&state, jar, request, next).await
         }
         None => {
             // No valid session — redirect to login
@@ -138,11 +291,15 @@ pub async fn login_page(
     let mut ctx = tera::Context::new();
     ctx.insert("t", t);
     ctx.insert("locale", &locale);
-    ctx.insert("app_title", &state.config.opds.title);
-    ctx.insert("default_theme", &state.config.web.theme);
+    insert_branding(&mut ctx, &state.config);
+    insert_theme(&mut ctx, &theme_mode(&state, &jar, ""));
     ctx.insert("version", env!("CARGO_PKG_VERSION"));
     ctx.insert("next", &query.next.unwrap_or_default());
     ctx.insert("error", &query.error.unwrap_or_default());
+    ctx.insert(
+        "remember_me",
+        &(state.config.server.remember_me_ttl_hours > 0),
+    );
 
     ctx.insert(
         "oauth_google",
@@ -176,6 +333,8 @@ pub struct LoginForm {
     pub username: String,
     pub password: String,
     pub next: Option<String>,
+    /// "Remember me" checkbox: present = "on", absent = None
+    pub remember: Option<String>,
 }
 
 /// POST /web/login — validate credentials and set session cookie.
@@ -186,21 +345,26 @@ pub async fn login_submit(
     axum::Form(form): axum::Form<LoginForm>,
 ) -> impl IntoResponse {
     let remote = addr.ip().to_string();
-    let valid = verify_credentials(&state.db, &form.username, &form.password).await;
-
-    if !valid {
-        tracing::info!("{remote} Login failed: user={}", form.username);
-        let next_val = form.next.as_deref().unwrap_or_default().to_string();
-        let next = urlencoding::encode(&next_val);
-        return (
-            jar,
-            Redirect::to(&format!("/web/login?error=1&next={next}")),
-        )
-            .into_response();
-    }
-
-    // Get user_id for the session
-    let user_id = get_user_id(&state.db, &form.username).await.unwrap_or(0);
+    let user_id = match verify_credentials(&state.db, &form.username, &form.password).await {
+        Some((user_id, true)) => user_id,
+        Some((_, false)) => {
+            tracing::info!(
+                "{remote} Login refused, account disabled: user={}",
+                form.username
+            );
+            return (jar, Redirect::to("/web/login?error=disabled")).into_response();
+        }
+        None => {
+            tracing::info!("{remote} Login failed: user={}", form.username);
+            let next_val = form.next.as_deref().unwrap_or_default().to_string();
+            let next = urlencoding::encode(&next_val);
+            return (
+                jar,
+                Redirect::to(&format!("/web/login?error=1&next={next}")),
+            )
+                .into_response();
+        }
+    };
 
     tracing::info!("{remote} Login: user={}", form.username);
 
@@ -208,20 +372,22 @@ pub async fn login_submit(
     let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     let _ = crate::db::queries::users::update_last_login(&state.db, user_id, &now).await;
 
-    let secret = state.config.server.session_secret.as_bytes();
-    let ttl = state.config.server.session_ttl_hours;
-    let token = sign_session(user_id, secret, ttl);
-
-    let cookie = Cookie::build(("session", token))
-        .path("/web")
-        .http_only(true)
-        .same_site(axum_extra::extract::cookie::SameSite::Lax);
+    let server = &state.config.server;
+    let remember = form.remember.is_some() && server.remember_me_ttl_hours > 0;
+    let ttl = if remember {
+        server.remember_me_ttl_hours
+    } else {
+        server.session_ttl_hours
+    };
+    let token = sign_session_with(user_id, server.session_secret.as_bytes(), ttl, remember);
+    let cookie = session_cookie(token, ttl, remember);
 
     let redirect_to = form
         .next
         .filter(|n| !n.is_empty() && n.starts_with('/'))
         .unwrap_or_else(|| "/web/bookshelf".to_string());
 
+    let jar = jar.remove(Cookie::build(IMPERSONATOR_COOKIE).path("/web"));
     (jar.add(cookie), Redirect::to(&redirect_to)).into_response()
 }
 
@@ -243,33 +409,36 @@ pub async fn logout(
         tracing::info!("{remote} Logout: user={name}");
     }
     let cookie = Cookie::build(("session", "")).path("/web").http_only(true);
+    let jar = jar.remove(Cookie::build(IMPERSONATOR_COOKIE).path("/web"));
     (jar.remove(cookie), Redirect::to("/web/login"))
 }
 
-/// Verify username/password against the users table using argon2.
-async fn verify_credentials(pool: &crate::db::DbPool, username: &str, password: &str) -> bool {
-    let result: Result<Option<(String,)>, _> =
-        sqlx::query_as(&pool.sql("SELECT password_hash FROM users WHERE username = ?"))
-            .bind(username)
-            .fetch_optional(pool.inner())
-            .await;
+/// Verify username/password against the users table, upgrading an outdated
+/// password hash on success. Returns the user's id and whether the account
+/// is active.
+async fn verify_credentials(
+    pool: &crate::db::DbPool,
+    username: &str,
+    password: &str,
+) -> Option<(i64, bool)> {
+    let result: Result<Option<(i64, String, i32)>, _> = sqlx::query_as(
+        &pool.sql("SELECT id, password_hash, is_active FROM users WHERE username = ?"),
+    )
+    .bind(username)
+    .fetch_optional(pool.inner())
+    .await;
 
     match result {
-        Ok(Some((stored_hash,))) => crate::password::verify(password, &stored_hash),
-        _ => false,
+        Ok(Some((user_id, stored_hash, is_active)))
+            if crate::password::verify(password, &stored_hash) =>
+        {
+            crate::password::rehash_if_needed(pool, user_id, password, &stored_hash).await;
+            Some((user_id, is_active != 0))
+        }
+        _ => None,
     }
 }
 
-/// Get user ID by username.
-async fn get_user_id(pool: &crate::db::DbPool, username: &str) -> Option<i64> {
-    let result: Result<Option<(i64,)>, _> =
-        sqlx::query_as(&pool.sql("SELECT id FROM users WHERE username = ?"))
-            .bind(username)
-            .fetch_optional(pool.inner())
-            .await;
-    result.ok().flatten().map(|(id,)| id)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,6 +452,40 @@ mod tests {
         assert_eq!(user_id, Some(42));
     }
 
+    #[test]
+    fn test_session_records_ttl() {
+        let secret = b"test-secret-key";
+        let session = parse_session(&sign_session(42, secret, 5), secret).unwrap();
+        assert_eq!(session.ttl_hours, 5);
+        let now = chrono::Utc::now().timestamp();
+        assert!(!session.past_half_life(now));
+        assert!(session.past_half_life(now + 3 * 3600));
+
+        // Cookies without a TTL still verify but are never renewed
+        let payload = format!("42:{}", now + 3600);
+        let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+        mac.update(payload.as_bytes());
+        let token = format!("{payload}:{}", hex::encode(mac.finalize().into_bytes()));
+        let legacy = parse_session(&token, secret).unwrap();
+        assert_eq!((legacy.user_id, legacy.ttl_hours), (42, 0));
+        assert!(!legacy.past_half_life(now + 3599));
+    }
+
+    #[test]
+    fn test_session_records_remember_me() {
+        let secret = b"test-secret-key";
+        let session = parse_session(&sign_session_with(42, secret, 24, true), secret).unwrap();
+        assert!(session.remember);
+        let session = parse_session(&sign_session(42, secret, 24), secret).unwrap();
+        assert!(!session.remember);
+
+        // The flag is signed.
+        let token = sign_session_with(42, secret, 24, false);
+        let (payload, sig) = token.rsplit_once(':').unwrap();
+        let forged = format!("{}1:{sig}", payload.strip_suffix('0').unwrap());
+        assert_eq!(parse_session(&forged, secret), None);
+    }
+
     #[test]
     fn test_verify_wrong_secret() {
         let token = sign_session(42, b"secret-a", 1);
@@ -319,7 +522,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_verify_credentials_and_get_user_id() {
+    async fn test_verify_credentials() {
         let pool = create_test_pool().await;
         let hash = crate::password::hash("password123");
         sqlx::query(
@@ -331,13 +534,28 @@ mod tests {
         .await
         .unwrap();
 
-        assert!(verify_credentials(&pool, "alice", "password123").await);
-        assert!(!verify_credentials(&pool, "alice", "wrong-password").await);
-        assert!(!verify_credentials(&pool, "missing-user", "password123").await);
+        let (uid, active) = verify_credentials(&pool, "alice", "password123")
+            .await
+            .unwrap();
+        assert!(active);
+        assert!(
+            verify_credentials(&pool, "alice", "wrong-password")
+                .await
+                .is_none()
+        );
+        assert!(
+            verify_credentials(&pool, "missing-user", "password123")
+                .await
+                .is_none()
+        );
 
-        let uid = get_user_id(&pool, "alice").await;
-        assert!(uid.is_some());
-        assert_eq!(get_user_id(&pool, "missing-user").await, None);
+        crate::db::queries::users::set_active(&pool, uid, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            verify_credentials(&pool, "alice", "password123").await,
+            Some((uid, false))
+        );
     }
 
     #[test]
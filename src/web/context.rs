@@ -66,21 +66,27 @@ pub async fn build_context(state: &AppState, jar: &CookieJar, active_page: &str)
     ctx.insert("available_locales", &["en", "ru"]);
     ctx.insert("reader_read_badge", reader_read_badge);
 
-    // Theme (server only knows the default; JS handles runtime switching)
-    let theme = &state.config.web.theme;
-    ctx.insert("default_theme", theme);
-
     // Active page for navbar highlighting
     ctx.insert("active_page", active_page);
     // Navbar search target: title | author | series
     ctx.insert("search_target", "title");
 
     // App config
-    ctx.insert("app_title", &state.config.opds.title);
+    insert_branding(&mut ctx, &state.config);
     ctx.insert("show_covers", &state.config.covers.show_covers);
     ctx.insert("alphabet_menu", &state.config.opds.alphabet_menu);
     ctx.insert("split_items", &state.config.opds.split_items);
     ctx.insert("auth_required", &state.config.opds.auth_required);
+    ctx.insert("fulltext_search", &state.config.search.fulltext);
+    // Strings, so templates can also substitute them into messages
+    ctx.insert(
+        "password_min_length",
+        &state.config.password.min_length.to_string(),
+    );
+    ctx.insert(
+        "password_max_length",
+        &state.config.password.max_length.to_string(),
+    );
 
     // Auth state for navbar (admin link / profile link) + CSRF token
     let secret = state.config.server.session_secret.as_bytes();
@@ -89,6 +95,7 @@ pub async fn build_context(state: &AppState, jar: &CookieJar, active_page: &str)
     let mut display_name = String::new();
     let mut username = String::new();
     let mut user_allow_upload: i32 = 0;
+    let mut user_theme = String::new();
     let mut last_read_book_id: i64 = 0;
     if let Some(cookie) = jar.get("session")
         && let Some(user_id) = crate::web::auth::verify_session(cookie.value(), secret)
@@ -101,6 +108,7 @@ pub async fn build_context(state: &AppState, jar: &CookieJar, active_page: &str)
             display_name = user.display_name;
             username = user.username;
             user_allow_upload = user.allow_upload;
+            user_theme = user.theme;
         }
         // Last read book for Reader navbar button
         if state.config.reader.enable
@@ -113,9 +121,27 @@ pub async fn build_context(state: &AppState, jar: &CookieJar, active_page: &str)
     }
     ctx.insert("is_superuser", &is_superuser);
     ctx.insert("is_authenticated", &is_authenticated);
+    ctx.insert(
+        "can_share",
+        &(is_authenticated == 1 && state.config.sharing.book_link_ttl_hours > 0),
+    );
+
+    // Theme: user setting, then cookie, then config default; JS resolves `auto`.
+    insert_theme(&mut ctx, &theme_mode(state, jar, &user_theme));
     ctx.insert("display_name", &display_name);
     ctx.insert("username", &username);
 
+    // Impersonation banner: the superuser browsing as this account
+    let mut impersonator = String::new();
+    if is_authenticated == 1
+        && let Some(admin_id) = crate::web::admin::impersonate::impersonator_id(state, jar).await
+    {
+        impersonator = crate::db::queries::users::get_username(&state.db, admin_id)
+            .await
+            .unwrap_or_default();
+    }
+    ctx.insert("impersonator", &impersonator);
+
     // Pending OAuth access requests (badge count for admin navbar)
     if is_superuser == 1 {
         let pending_count = crate::db::queries::oauth::count_pending(&state.db)
@@ -188,9 +214,62 @@ pub async fn build_context(state: &AppState, jar: &CookieJar, active_page: &str)
     ctx
 }
 
-/// Register custom Tera filters.
+/// Cookie holding the theme chosen by the visitor (`light`, `dark` or `auto`).
+pub const THEME_COOKIE: &str = "theme";
+
+/// Theme preference for a request: the user's saved setting, then the theme
+/// cookie, then `web.theme` from the config.
+pub fn theme_mode(state: &AppState, jar: &CookieJar, user_theme: &str) -> String {
+    if is_theme_mode(user_theme) {
+        return user_theme.to_string();
+    }
+    match jar.get(THEME_COOKIE) {
+        Some(cookie) if is_theme_mode(cookie.value()) => cookie.value().to_string(),
+        _ => state.config.web.theme.clone(),
+    }
+}
+
+/// Theme preference of the signed-in user (if any), falling back like [`theme_mode`].
+pub async fn request_theme_mode(state: &AppState, jar: &CookieJar) -> String {
+    let secret = state.config.server.session_secret.as_bytes();
+    let mut user_theme = String::new();
+    if let Some(user_id) = jar
+        .get("session")
+        .and_then(|c| crate::web::auth::verify_session(c.value(), secret))
+        && let Ok(Some(user)) = crate::db::queries::users::get_by_id(&state.db, user_id).await
+    {
+        user_theme = user.theme;
+    }
+    theme_mode(state, jar, &user_theme)
+}
+
+/// Insert `theme_mode` and `default_theme` (the initial `data-bs-theme`).
+/// For `auto` the page starts light and `ropds.js` switches it before first paint.
+pub fn insert_theme(ctx: &mut Context, mode: &str) {
+    let initial = if mode == "dark" { "dark" } else { "light" };
+    ctx.insert("theme_mode", mode);
+    ctx.insert("default_theme", initial);
+}
+
+/// Instance branding shared by all page heads: `app_title` and the optional
+/// `accent_color` / `accent_rgb` CSS overrides.
+pub fn insert_branding(ctx: &mut Context, config: &crate::config::Config) {
+    ctx.insert("app_title", config.site_name());
+    if let (Some(color), Some([r, g, b])) = (&config.web.accent_color, config.web.accent_rgb()) {
+        ctx.insert("accent_color", color.trim());
+        ctx.insert("accent_rgb", &format!("{r}, {g}, {b}"));
+    }
+}
+
+pub fn is_theme_mode(value: &str) -> bool {
+    crate::config::THEME_MODES.contains(&value)
+}
+
+/// Register custom Tera filters and functions.
 pub fn register_filters(tera: &mut tera::Tera) {
     tera.register_filter("filesizeformat", filesizeformat);
+    tera.register_filter("format_name", format_name);
+    tera.register_function("asset_url", crate::assets::AssetUrl);
 }
 
 /// Tera filter: format bytes as human-readable file size.
@@ -209,6 +288,15 @@ fn filesizeformat(
     Ok(tera::Value::String(result))
 }
 
+/// Tera filter: display name of a book format (see [`crate::formats`]).
+fn format_name(
+    value: &tera::Value,
+    _args: &std::collections::HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
+    let format = value.as_str().unwrap_or_default();
+    Ok(tera::Value::String(crate::formats::name(format)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,6 +4,8 @@ pub mod context;
 pub mod i18n;
 pub mod oauth;
 pub mod pagination;
+pub mod seo;
+pub mod share;
 pub mod upload;
 pub mod views;
 
@@ -18,6 +20,7 @@ pub fn router(state: AppState) -> Router<AppState> {
     // Body limit for upload: configured max + 1 MB overhead for multipart framing
     let upload_body_limit =
         (state.config.upload.max_upload_size_mb as usize * 1024 * 1024) + 1_048_576;
+    let server = &state.config.server;
 
     let admin_router = Router::new()
         .route("/", get(admin::admin_page))
@@ -25,13 +28,25 @@ pub fn router(state: AppState) -> Router<AppState> {
         .route("/users/{id}/password", post(admin::change_password))
         .route("/users/{id}/delete", post(admin::delete_user))
         .route("/users/{id}/upload", post(admin::toggle_upload))
+        .route("/users/{id}/active", post(admin::toggle_active))
+        .route("/users/{id}/quota", post(admin::update_upload_quota))
+        .route("/users/{id}/quota/reset", post(admin::reset_upload_quota))
+        .route("/impersonate/{id}", post(admin::impersonate))
+        .route("/devices/clear", post(admin::clear_devices))
         .route("/book-genres", post(admin::update_book_genres))
         .route("/book-authors", post(admin::update_book_authors))
         .route("/book-series", post(admin::update_book_series))
         .route("/series-search", get(admin::series_search))
         .route("/book-title", post(admin::update_book_title))
+        .route("/book-annotation", post(admin::update_book_annotation))
+        .route("/book-lang", post(admin::update_book_lang))
+        .route("/catalog-rename", post(admin::rename_catalog))
+        .route("/catalog-move", post(admin::move_catalog))
+        .route("/catalog-search", get(admin::catalog_search))
+        .route("/catalog-hidden", post(admin::set_catalog_hidden))
         .route("/scan", post(admin::scan_now))
         .route("/scan-status", get(admin::scan_status))
+        .route("/scan-preview", get(admin::scan_preview))
         .route("/genres", get(admin::genres_admin_json))
         .route("/genre-translation", post(admin::upsert_genre_translation))
         .route(
@@ -42,8 +57,19 @@ pub fn router(state: AppState) -> Router<AppState> {
         .route("/genre/delete", post(admin::delete_genre))
         .route("/section", post(admin::create_section))
         .route("/section/delete", post(admin::delete_section))
+        .route("/genres/reseed", post(admin::reseed_genres))
         .route("/books/{id}/delete", post(admin::delete_book))
+        .route(
+            "/book/{id}/cover/candidate",
+            get(admin::book_cover_candidate),
+        )
         .route("/duplicates", get(admin::duplicates_page))
+        .route("/languages", get(admin::languages_page))
+        .route("/languages/apply", post(admin::apply_languages))
+        .route("/stats/downloads", get(admin::download_stats_page))
+        .route("/shares", get(admin::shares_page))
+        .route("/shares/{id}/revoke", post(admin::revoke_share))
+        .route("/shares/shelf/{id}/revoke", post(admin::revoke_shelf_share))
         .route("/oauth-requests", get(admin::oauth_requests::page))
         .route(
             "/oauth-requests/{id}/approve",
@@ -57,13 +83,34 @@ pub fn router(state: AppState) -> Router<AppState> {
         .route(
             "/oauth-requests/{id}/reinstate",
             post(admin::oauth_requests::reinstate),
+        );
+    // File uploads are added after the limits so they keep their own
+    let admin_router = crate::limit_requests(admin_router, server, server.admin_body_limit_kb)
+        .route(
+            "/book/{id}/replace",
+            post(admin::replace_book_file).layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
+        .route(
+            "/book/{id}/cover",
+            post(admin::update_book_cover).layer(DefaultBodyLimit::max(upload_body_limit)),
         )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            admin::bump_library_version_on_change,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             admin::require_superuser,
         ));
 
-    Router::new()
+    // Signing in and out only touch the session cookie, so they keep working
+    // on a read-only mirror
+    let session_pages = Router::new()
+        .route("/login", get(auth::login_page).post(auth::login_submit))
+        .route("/logout", get(auth::logout))
+        .route("/impersonate/exit", post(admin::stop_impersonating));
+
+    let pages = Router::new()
         .route("/", get(views::home))
         .route("/catalogs", get(views::catalogs))
         .route("/books", get(views::books_browse))
@@ -77,8 +124,7 @@ pub fn router(state: AppState) -> Router<AppState> {
         .route("/search/authors", get(views::search_authors))
         .route("/search/series", get(views::search_series))
         .route("/set-language", get(views::set_language))
-        .route("/login", get(auth::login_page).post(auth::login_submit))
-        .route("/logout", get(auth::logout))
+        .route("/api/theme", post(views::set_theme))
         .route("/oauth/login/{provider}", get(oauth::login))
         .route("/oauth/callback/{provider}", get(oauth::callback))
         .route(
@@ -91,15 +137,35 @@ pub fn router(state: AppState) -> Router<AppState> {
             "/profile/display-name",
             post(admin::profile_update_display_name),
         )
+        .route(
+            "/profile/auto-bookshelf",
+            post(admin::profile_update_auto_bookshelf),
+        )
+        .route(
+            "/profile/opds-client",
+            post(admin::profile_update_opds_client),
+        )
         .route("/profile/opds-reset", post(admin::opds_password_reset))
         .route("/download/{book_id}/{zip_flag}", get(views::web_download))
         .route("/bookshelf", get(views::bookshelf_page))
         .route("/bookshelf/cards", get(views::bookshelf_cards))
+        .route("/bookshelf/export", get(views::bookshelf_export))
         .route("/bookshelf/toggle", post(views::bookshelf_toggle))
         .route("/bookshelf/clear", post(views::bookshelf_clear))
+        .route("/bookshelf/share", post(share::create_shelf_share))
+        .route("/share", post(share::create_share))
+        .route("/shared/{token}", get(share::shared_shelf_page))
+        .route(
+            "/shared/{token}/download/{book_id}",
+            get(share::shared_shelf_download),
+        )
         .route("/api/genres", get(views::genres_json))
+        .route("/api/books", get(views::book_cards))
+        .route("/api/suggest", get(views::suggest_json))
         .route("/reader/{book_id}", get(views::web_reader))
         .route("/read/{book_id}", get(views::web_read_inline))
+        .route("/listen/{book_id}", get(views::web_listen))
+        .route("/audio/{book_id}/{track}", get(views::web_audio_track))
         .route("/api/reading-position", post(views::save_reading_position))
         .route(
             "/api/reading-position/{book_id}",
@@ -107,13 +173,39 @@ pub fn router(state: AppState) -> Router<AppState> {
         )
         .route("/api/reading-history", get(views::get_reading_history))
         .route("/upload", get(upload::upload_page))
+        .route("/upload/cover/{token}", get(upload::upload_cover))
+        .route("/upload/publish", post(upload::publish))
+        .route("/upload/publish-all", post(upload::publish_all))
+        .route("/upload/chunked", post(upload::chunked_start));
+
+    crate::limit_requests(pages, server, server.web_body_limit_kb)
         .route(
             "/upload/file",
             post(upload::upload_file).layer(DefaultBodyLimit::max(upload_body_limit)),
         )
-        .route("/upload/cover/{token}", get(upload::upload_cover))
-        .route("/upload/publish", post(upload::publish))
+        .route(
+            "/upload/files",
+            post(upload::upload_files).layer(DefaultBodyLimit::max(upload_body_limit)),
+        )
+        // Chunks and assembly run outside the request timeout: a slow link
+        // may need longer than it for one chunk, and finishing parses the book.
+        .route(
+            "/upload/chunked/{id}",
+            get(upload::chunked_status)
+                .put(upload::chunked_append)
+                .layer(DefaultBodyLimit::max(upload::UPLOAD_CHUNK_BYTES)),
+        )
+        .route("/upload/chunked/{id}/finish", post(upload::chunked_finish))
         .nest("/admin", admin_router)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::reject_writes_when_read_only,
+        ))
+        .merge(crate::limit_requests(
+            session_pages,
+            server,
+            server.web_body_limit_kb,
+        ))
         .layer(middleware::from_fn_with_state(
             state,
             auth::session_auth_layer,
@@ -125,7 +217,7 @@ mod tests {
     use super::*;
     use crate::config::{
         Config, CoversConfig, DatabaseConfig, LibraryConfig, OpdsConfig, ReaderConfig,
-        ScannerConfig, ServerConfig, UploadConfig, WebConfig,
+        ScannerConfig, ServerConfig, ServerProfile, UploadConfig, WebConfig,
     };
     use crate::db::create_test_pool;
     use crate::web::i18n::Translations;
@@ -140,7 +232,18 @@ mod tests {
                 log_level: "info".to_string(),
                 session_secret: "test-secret".to_string(),
                 session_ttl_hours: 24,
+                session_sliding: true,
+                remember_me_ttl_hours: 720,
                 base_url: String::new(),
+                trust_proxy_headers: false,
+                profile: ServerProfile::Default,
+                read_only: false,
+                request_timeout_secs: 60,
+                opds_body_limit_kb: 64,
+                web_body_limit_kb: 256,
+                admin_body_limit_kb: 2048,
+                min_free_space_mb: 0,
+                access_log_path: None,
             },
             library: LibraryConfig {
                 root_path: PathBuf::from("/tmp/books"),
@@ -151,16 +254,25 @@ mod tests {
                 scan_zip: true,
                 zip_codepage: "cp866".to_string(),
                 inpx_enable: false,
+                filename_patterns: Vec::new(),
+                audiobooks: false,
+                detect_lang: true,
+                detect_lang_confidence: 0.3,
             },
             covers: CoversConfig {
                 covers_path: PathBuf::from("/tmp/covers"),
                 cover_max_dimension_px: 600,
                 cover_jpeg_quality: 85,
                 show_covers: true,
+                signed_urls: false,
+                signed_url_ttl_mins: 60,
             },
             database: DatabaseConfig {
                 url: "sqlite::memory:".to_string(),
                 max_connections: 5,
+                retry: 3,
+                retry_backoff_ms: 100,
+                auto_migrate: true,
             },
             opds: OpdsConfig {
                 title: "ROPDS".to_string(),
@@ -171,6 +283,15 @@ mod tests {
                 show_covers: None,
                 alphabet_menu: true,
                 hide_doubles: false,
+                group_formats: false,
+                hide_empty: false,
+                deep_catalogs: false,
+                catalog_thumbnails: false,
+                auto_bookshelf: true,
+                icon_path: None,
+                logo_url: None,
+                absolute_urls: false,
+                dump_page_size: 500,
             },
             scanner: ScannerConfig {
                 schedule_minutes: vec![0],
@@ -181,15 +302,24 @@ mod tests {
                 test_zip: false,
                 test_files: false,
                 workers_num: 1,
+                exclude: Vec::new(),
+                max_depth: 64,
+                changes_log: None,
+                link_identical: false,
             },
             web: WebConfig {
                 language: "en".to_string(),
                 theme: "light".to_string(),
+                accent_color: None,
+                site_name: None,
+                seo_enabled: false,
+                robots_txt: None,
             },
             upload: UploadConfig {
                 allow_upload: true,
                 upload_path: PathBuf::from("/tmp/uploads"),
                 max_upload_size_mb: 10,
+                ..Default::default()
             },
             reader: ReaderConfig {
                 enable: true,
@@ -198,6 +328,15 @@ mod tests {
             },
             oauth: Default::default(),
             smtp: Default::default(),
+            tools: Default::default(),
+            search: Default::default(),
+            sharing: Default::default(),
+            api: Default::default(),
+            password: Default::default(),
+            download: Default::default(),
+            formats: Default::default(),
+            clients: Default::default(),
+            stats: Default::default(),
         };
 
         let pool = create_test_pool().await;
@@ -205,7 +344,13 @@ mod tests {
         let mut translations = Translations::new();
         translations.insert("en".to_string(), serde_json::json!({"admin": {}}));
 
-        let state = AppState::new(config, pool, tera, translations, false, false);
+        let state = AppState::new(
+            config,
+            pool,
+            tera,
+            translations,
+            crate::state::Capabilities::default(),
+        );
         let _router = router(state);
     }
 }
@@ -431,7 +431,7 @@ async fn handle_new_identity(userinfo: UserInfo, state: &AppState, jar: CookieJa
         .map(crate::util::slugify_username)
         .unwrap_or_else(|| "user".to_string());
 
-    let opds_password = crate::password::generate_opds_password();
+    let opds_password = crate::password::generate_opds_password(&state.config.password);
     let opds_hash = crate::password::hash(&opds_password);
 
     // Insert-first retry loop to avoid race between "username available" check and insert.
@@ -643,26 +643,30 @@ async fn notify_admin_pending(state: &AppState, userinfo: &UserInfo, is_reapply:
 }
 
 async fn make_session(user_id: i64, state: &AppState, jar: CookieJar) -> Response {
+    if !crate::db::queries::users::is_active(&state.db, user_id)
+        .await
+        .unwrap_or(false)
+    {
+        return (jar, Redirect::to("/web/login?error=disabled")).into_response();
+    }
+
     // Keep OAuth login behavior consistent with password login: record last_login.
     let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     if let Err(e) = crate::db::queries::users::update_last_login(&state.db, user_id, &now).await {
         tracing::warn!("Failed to update last_login for OAuth user {user_id}: {e}");
     }
 
-    let secret = state.config.server.session_secret.as_bytes();
-    let ttl = state.config.server.session_ttl_hours;
-    let token = sign_session(user_id, secret, ttl);
-    let cookie = Cookie::build(("session", token))
-        .path("/web")
-        .http_only(true)
-        .same_site(axum_extra::extract::cookie::SameSite::Lax);
+    let server = &state.config.server;
+    let ttl = server.session_ttl_hours;
+    let token = sign_session(user_id, server.session_secret.as_bytes(), ttl);
+    let cookie = crate::web::auth::session_cookie(token, ttl, false);
     (jar.add(cookie), Redirect::to("/web/bookshelf")).into_response()
 }
 
 fn render_status(state: &AppState, template: &str, mut ctx: tera::Context) -> Response {
     ctx.insert("locale", &state.config.web.language);
-    ctx.insert("default_theme", &state.config.web.theme);
-    ctx.insert("app_title", &state.config.opds.title);
+    crate::web::context::insert_theme(&mut ctx, &state.config.web.theme);
+    crate::web::context::insert_branding(&mut ctx, &state.config);
     ctx.insert("version", env!("CARGO_PKG_VERSION"));
     match state.tera.render(template, &ctx) {
         Ok(html) => Html(html).into_response(),
@@ -0,0 +1,229 @@
+//! Search engine support for public libraries (`web.seo_enabled`): the
+//! `/sitemap.xml` index of catalog and book pages, schema.org `Book`
+//! metadata embedded in book pages, and `/robots.txt`. Other libraries are
+//! kept out of search results with `X-Robots-Tag: noindex`.
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderName, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use quick_xml::escape::escape;
+use serde_json::json;
+
+use crate::db::models::Book;
+use crate::db::queries::{authors, books, catalogs, genres, series};
+use crate::state::AppState;
+
+/// URLs per sitemap file, the limit of the sitemaps protocol.
+const SITEMAP_URLS: i64 = 50_000;
+const SITEMAP_NS: &str = "http://www.sitemaps.org/schemas/sitemap/0.9";
+
+fn base_url(state: &AppState) -> &str {
+    state.config.server.base_url.trim_end_matches('/')
+}
+
+fn xml_response(body: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+        body,
+    )
+        .into_response()
+}
+
+/// `/robots.txt` of libraries that are not open to search engines.
+const ROBOTS_DENY_ALL: &str = "User-agent: *\nDisallow: /\n";
+
+/// GET /robots.txt — `web.robots_txt`, else a default that turns crawlers
+/// away unless search engine support is on.
+pub async fn robots_txt(State(state): State<AppState>) -> Response {
+    let body = match &state.config.web.robots_txt {
+        Some(custom) => custom.clone(),
+        None if state.config.seo_enabled() => format!(
+            "User-agent: *\n\
+             Disallow: /web/admin\n\
+             Disallow: /web/download/\n\
+             Disallow: /web/reader/\n\
+             Disallow: /opds/\n\
+             Disallow: /api/\n\
+             Sitemap: {}/sitemap.xml\n",
+            base_url(&state)
+        ),
+        None => ROBOTS_DENY_ALL.to_string(),
+    };
+    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response()
+}
+
+/// Middleware response mapper: ask search engines not to index or follow
+/// the response.
+pub async fn noindex(mut response: Response) -> Response {
+    response.headers_mut().insert(
+        HeaderName::from_static("x-robots-tag"),
+        HeaderValue::from_static("noindex, nofollow"),
+    );
+    response
+}
+
+/// Web page of a book.
+fn book_path(book_id: i64) -> String {
+    format!("/web/search/books?type=i&q={book_id}")
+}
+
+/// GET /sitemap.xml — index of the catalog and book sitemaps.
+pub async fn sitemap_index(State(state): State<AppState>) -> Response {
+    if !state.config.seo_enabled() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let (Ok(catalog_count), Ok(book_count)) = (
+        catalogs::count_visible(&state.db).await,
+        books::count_recent_added(&state.db, false).await,
+    ) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    let base = escape(base_url(&state));
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<sitemapindex xmlns=\"{SITEMAP_NS}\">\n"
+    );
+    for (kind, count) in [("catalogs", catalog_count), ("books", book_count)] {
+        for part in 1..=(count + SITEMAP_URLS - 1) / SITEMAP_URLS {
+            xml.push_str(&format!(
+                "  <sitemap><loc>{base}/sitemap/{kind}/{part}.xml</loc></sitemap>\n"
+            ));
+        }
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml_response(xml)
+}
+
+/// GET /sitemap/{kind}/{n}.xml — the `n`th sitemap of catalog or book pages.
+pub async fn sitemap_part(
+    State(state): State<AppState>,
+    Path((kind, file)): Path<(String, String)>,
+) -> Response {
+    let part = file
+        .strip_suffix(".xml")
+        .and_then(|n| n.parse::<i64>().ok())
+        .filter(|n| *n >= 1);
+    let Some(part) = part.filter(|_| state.config.seo_enabled()) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let offset = (part - 1) * SITEMAP_URLS;
+
+    let urls: Result<Vec<(String, Option<String>)>, sqlx::Error> = match kind.as_str() {
+        "catalogs" => catalogs::visible_ids(&state.db, SITEMAP_URLS, offset)
+            .await
+            .map(|ids| {
+                ids.into_iter()
+                    .map(|id| (format!("/web/catalogs?cat_id={id}"), None))
+                    .collect()
+            }),
+        "books" => books::available_ids(&state.db, SITEMAP_URLS, offset)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|(id, reg_date)| (book_path(id), lastmod(&reg_date)))
+                    .collect()
+            }),
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let urls = match urls {
+        Ok(urls) if urls.is_empty() => return StatusCode::NOT_FOUND.into_response(),
+        Ok(urls) => urls,
+        Err(e) => {
+            tracing::error!("Failed to list {kind} for the sitemap: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let base = base_url(&state);
+    let mut xml =
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"{SITEMAP_NS}\">\n");
+    for (path, lastmod) in urls {
+        xml.push_str(&format!(
+            "  <url><loc>{}</loc>",
+            escape(format!("{base}{path}").as_str())
+        ));
+        if let Some(date) = lastmod {
+            xml.push_str(&format!("<lastmod>{date}</lastmod>"));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml_response(xml)
+}
+
+/// The date of a `reg_date` timestamp, as a sitemap `<lastmod>`.
+fn lastmod(reg_date: &str) -> Option<String> {
+    reg_date
+        .get(..10)
+        .filter(|day| chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").is_ok())
+        .map(str::to_string)
+}
+
+/// schema.org `Book` JSON-LD of a book page, safe to embed in a `<script>`.
+pub async fn book_json_ld(state: &AppState, book: &Book, locale: &str) -> String {
+    let base = base_url(state);
+    let book_authors = authors::get_for_book(&state.db, book.id)
+        .await
+        .unwrap_or_default();
+    let book_genres = genres::get_for_book(&state.db, book.id, locale)
+        .await
+        .unwrap_or_default();
+    let book_series = series::get_for_book(&state.db, book.id)
+        .await
+        .unwrap_or_default();
+
+    let mut doc = json!({
+        "@context": "https://schema.org",
+        "@type": "Book",
+        "name": book.title,
+        "url": format!("{base}{}", book_path(book.id)),
+        "bookFormat": "https://schema.org/EBook",
+        "encodingFormat": crate::formats::mime(&book.format),
+    });
+    if !book_authors.is_empty() {
+        doc["author"] = book_authors
+            .iter()
+            .map(|a| json!({"@type": "Person", "name": a.full_name}))
+            .collect();
+    }
+    if !book_genres.is_empty() {
+        doc["genre"] = book_genres.iter().map(|g| json!(g.subsection)).collect();
+    }
+    if let Some((ser, number)) = book_series.first() {
+        doc["isPartOf"] = json!({"@type": "BookSeries", "name": ser.ser_name});
+        if *number > 0 {
+            doc["position"] = json!(number);
+        }
+    }
+    if !book.lang.is_empty() {
+        doc["inLanguage"] = json!(book.lang);
+    }
+    if let Some(date) = crate::opds::v1::helpers::issued_date(&book.docdate) {
+        doc["datePublished"] = json!(date);
+    }
+    if !book.annotation.trim().is_empty() {
+        doc["description"] = json!(book.annotation.trim());
+    }
+    if book.cover != 0 && state.config.covers.show_covers {
+        let cover = crate::opds::covers::CoverUrls::new(&state.config).cover(book.id);
+        doc["image"] = json!(format!("{base}{cover}"));
+    }
+    // `</script>` in a title or annotation must not end the script element.
+    doc.to_string().replace("</", "<\\/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lastmod() {
+        assert_eq!(
+            lastmod("2024-03-05 10:11:12").as_deref(),
+            Some("2024-03-05")
+        );
+        assert_eq!(lastmod("2024-03-05").as_deref(), Some("2024-03-05"));
+        assert_eq!(lastmod(""), None);
+        assert_eq!(lastmod("not a date at all"), None);
+    }
+}
@@ -0,0 +1,382 @@
+//! Share links: signed, expiring URLs that work without an account.
+//!
+//! - `/share/{token}` downloads one book (`book_shares`).
+//! - `/web/shared/{token}` and `/opds/shared/{token}/` open a read-only view
+//!   of a user's bookshelf (`shelf_shares`).
+//!
+//! A token is `{id}.{expiry}.{nonce}.{hex_signature}`, where `id` is the book
+//! or the shelf owner; the signature covers the scope, the id, the expiry and
+//! the nonce. The nonce is also stored in the database, where an admin can
+//! revoke the link.
+
+use axum::extract::{Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum_extra::extract::cookie::CookieJar;
+use hmac::{Hmac, KeyInit, Mac};
+use rand::RngExt;
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::db::queries::{authors, books, bookshelf, shares, users};
+use crate::state::AppState;
+use crate::web::auth::verify_session;
+use crate::web::context::{insert_branding, insert_theme, theme_mode, validate_csrf};
+use crate::web::i18n;
+use crate::web::pagination::Pagination;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a share token grants access to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareScope {
+    /// One book, by book id.
+    Book,
+    /// A user's bookshelf, by user id.
+    Shelf,
+}
+
+impl ShareScope {
+    fn domain(self) -> &'static [u8] {
+        match self {
+            ShareScope::Book => b"book-share:",
+            ShareScope::Shelf => b"shelf-share:",
+        }
+    }
+}
+
+fn token_signature(secret: &[u8], scope: ShareScope, payload: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC can take key of any size");
+    mac.update(scope.domain());
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// Create a signed share token for `id` valid until `expiry` (Unix seconds).
+pub fn sign_share_token(
+    secret: &[u8],
+    scope: ShareScope,
+    id: i64,
+    expiry: i64,
+    nonce: &str,
+) -> String {
+    let payload = format!("{id}.{expiry}.{nonce}");
+    let sig = hex::encode(
+        token_signature(secret, scope, &payload)
+            .finalize()
+            .into_bytes(),
+    );
+    format!("{payload}.{sig}")
+}
+
+/// Verify a share token of `scope`. Returns the id and nonce if the signature
+/// is valid and the token has not expired at `now`.
+pub fn verify_share_token(
+    secret: &[u8],
+    scope: ShareScope,
+    token: &str,
+    now: i64,
+) -> Option<(i64, String)> {
+    let (payload, sig_hex) = token.rsplit_once('.')?;
+    let mut parts = payload.splitn(3, '.');
+    let id: i64 = parts.next()?.parse().ok()?;
+    let expiry: i64 = parts.next()?.parse().ok()?;
+    let nonce = parts.next()?;
+    if now > expiry {
+        return None;
+    }
+
+    let expected = hex::decode(sig_hex).ok()?;
+    token_signature(secret, scope, payload)
+        .verify_slice(&expected)
+        .ok()?;
+    Some((id, nonce.to_string()))
+}
+
+/// Resolve a shelf token to the shelf owner's user id. `None` when shelf
+/// sharing is off or the link is invalid, expired or revoked.
+pub async fn resolve_shelf_token(
+    state: &AppState,
+    token: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    if state.config.sharing.shelf_link_ttl_hours == 0 {
+        return Ok(None);
+    }
+    let secret = state.config.server.session_secret.as_bytes();
+    let now = chrono::Utc::now().timestamp();
+    let Some((user_id, nonce)) = verify_share_token(secret, ShareScope::Shelf, token, now) else {
+        return Ok(None);
+    };
+    let active = shares::is_shelf_active(&state.db, &nonce, user_id, now).await?;
+    Ok(active.then_some(user_id))
+}
+
+fn new_nonce() -> String {
+    let bytes: [u8; 12] = rand::rng().random();
+    hex::encode(bytes)
+}
+
+#[derive(Deserialize)]
+pub struct ShareForm {
+    pub book_id: i64,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/share — create a share link for a book. Answers JSON with the
+/// absolute `url` and its `expires_at` (Unix seconds).
+pub async fn create_share(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Form(form): axum::Form<ShareForm>,
+) -> Response {
+    let ttl_hours = state.config.sharing.book_link_ttl_hours;
+    if ttl_hours == 0 {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "Invalid CSRF token").into_response();
+    }
+    let Some(user_id) = jar
+        .get("session")
+        .and_then(|c| verify_session(c.value(), secret))
+    else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    match books::get_by_id(&state.db, form.book_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, "Book not found").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let expires_at = now + (ttl_hours * 3600) as i64;
+    let nonce = new_nonce();
+    if let Err(e) = shares::delete_expired(&state.db, now).await {
+        tracing::warn!("Failed to delete expired share links: {e}");
+    }
+    if let Err(e) = shares::create(&state.db, &nonce, form.book_id, user_id, expires_at).await {
+        tracing::error!("Failed to create share link: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response();
+    }
+
+    let token = sign_share_token(secret, ShareScope::Book, form.book_id, expires_at, &nonce);
+    let base = state.config.server.base_url.trim_end_matches('/');
+    axum::Json(serde_json::json!({
+        "ok": true,
+        "url": format!("{base}/share/{token}"),
+        "expires_at": expires_at,
+    }))
+    .into_response()
+}
+
+/// GET /share/:token — download a shared book without an account.
+pub async fn shared_download(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    request: Request,
+) -> Response {
+    let secret = state.config.server.session_secret.as_bytes();
+    let now = chrono::Utc::now().timestamp();
+    let gone = || (StatusCode::NOT_FOUND, "Link expired or revoked").into_response();
+    if state.config.sharing.book_link_ttl_hours == 0 {
+        return gone();
+    }
+    let Some((book_id, nonce)) = verify_share_token(secret, ShareScope::Book, &token, now) else {
+        return gone();
+    };
+    match shares::is_active(&state.db, &nonce, book_id, now).await {
+        Ok(true) => {}
+        Ok(false) => return gone(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
+    }
+
+    let book = match books::get_by_id(&state.db, book_id).await {
+        Ok(Some(b)) => b,
+        Ok(None) => return gone(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
+    };
+    crate::opds::download::book_file_response(&state, &book, None, request).await
+}
+
+#[derive(Deserialize)]
+pub struct ShelfShareForm {
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// POST /web/bookshelf/share — create a guest link to the caller's bookshelf.
+/// Answers JSON with the web `url`, the `opds_url` and `expires_at`.
+pub async fn create_shelf_share(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Form(form): axum::Form<ShelfShareForm>,
+) -> Response {
+    let ttl_hours = state.config.sharing.shelf_link_ttl_hours;
+    if ttl_hours == 0 {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return (StatusCode::FORBIDDEN, "Invalid CSRF token").into_response();
+    }
+    let Some(user_id) = jar
+        .get("session")
+        .and_then(|c| verify_session(c.value(), secret))
+    else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let expires_at = now + (ttl_hours * 3600) as i64;
+    let nonce = new_nonce();
+    if let Err(e) = shares::delete_expired_shelves(&state.db, now).await {
+        tracing::warn!("Failed to delete expired shelf links: {e}");
+    }
+    if let Err(e) = shares::create_shelf(&state.db, &nonce, user_id, expires_at).await {
+        tracing::error!("Failed to create shelf link: {e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response();
+    }
+
+    let token = sign_share_token(secret, ShareScope::Shelf, user_id, expires_at, &nonce);
+    let base = state.config.server.base_url.trim_end_matches('/');
+    axum::Json(serde_json::json!({
+        "ok": true,
+        "url": format!("{base}/web/shared/{token}"),
+        "opds_url": format!("{base}/opds/shared/{token}/"),
+        "expires_at": expires_at,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct SharedShelfParams {
+    #[serde(default)]
+    pub page: i32,
+}
+
+/// GET /web/shared/:token — read-only view of a shared bookshelf.
+pub async fn shared_shelf_page(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(token): Path<String>,
+    Query(params): Query<SharedShelfParams>,
+) -> Response {
+    let user_id = match resolve_shelf_token(&state, &token).await {
+        Ok(Some(uid)) => uid,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Link expired or revoked").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
+    };
+
+    let locale = jar
+        .get("lang")
+        .map(|c| c.value().to_string())
+        .unwrap_or_else(|| state.config.web.language.clone());
+    let mut ctx = tera::Context::new();
+    ctx.insert("t", i18n::get_locale(&state.translations, &locale));
+    ctx.insert("locale", &locale);
+    insert_branding(&mut ctx, &state.config);
+    insert_theme(&mut ctx, &theme_mode(&state, &jar, ""));
+    ctx.insert("version", env!("CARGO_PKG_VERSION"));
+
+    let max_items = state.config.opds.max_items as i32;
+    let total = bookshelf::count_by_user(&state.db, user_id)
+        .await
+        .unwrap_or(0);
+    let pagination = Pagination::new(params.page.max(0), max_items, total);
+    let raw_books = bookshelf::get_by_user(
+        &state.db,
+        user_id,
+        &bookshelf::SortColumn::Date,
+        false,
+        max_items,
+        pagination.current_page * max_items,
+    )
+    .await
+    .unwrap_or_default();
+
+    let mut items = Vec::with_capacity(raw_books.len());
+    for book in raw_books {
+        let names: Vec<String> = authors::get_for_book(&state.db, book.id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|a| a.full_name)
+            .collect();
+        items.push(serde_json::json!({
+            "id": book.id,
+            "title": book.title,
+            "authors": names.join(", "),
+            "format": book.format,
+            "size": book.size,
+            "cover": book.cover != 0,
+        }));
+    }
+
+    ctx.insert(
+        "owner",
+        &users::get_username(&state.db, user_id)
+            .await
+            .unwrap_or_default(),
+    );
+    ctx.insert("token", &token);
+    ctx.insert("books", &items);
+    ctx.insert("pagination", &pagination);
+    ctx.insert("show_covers", &state.config.covers.show_covers);
+
+    match state.tera.render("web/shared_shelf.html", &ctx) {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => {
+            tracing::error!("Template error: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// GET /web/shared/:token/download/:book_id — download a book from a shared
+/// shelf.
+pub async fn shared_shelf_download(
+    State(state): State<AppState>,
+    Path((token, book_id)): Path<(String, i64)>,
+    request: Request,
+) -> Response {
+    crate::opds::shared::shared_book_response(&state, &token, book_id, request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_token_roundtrip_and_tampering() {
+        let secret = b"secret";
+        let token = sign_share_token(secret, ShareScope::Book, 42, 1000, "abc");
+        assert_eq!(
+            verify_share_token(secret, ShareScope::Book, &token, 900),
+            Some((42, "abc".to_string()))
+        );
+        // Expired, wrong key, other book, other scope.
+        assert_eq!(
+            verify_share_token(secret, ShareScope::Book, &token, 1001),
+            None
+        );
+        assert_eq!(
+            verify_share_token(b"other", ShareScope::Book, &token, 900),
+            None
+        );
+        let forged = token.replacen("42.", "43.", 1);
+        assert_eq!(
+            verify_share_token(secret, ShareScope::Book, &forged, 900),
+            None
+        );
+        assert_eq!(
+            verify_share_token(secret, ShareScope::Shelf, &token, 900),
+            None
+        );
+        assert_eq!(
+            verify_share_token(secret, ShareScope::Book, "garbage", 900),
+            None
+        );
+    }
+}
@@ -5,8 +5,9 @@ use axum_extra::extract::cookie::CookieJar;
 use hmac::KeyInit;
 use serde::{Deserialize, Serialize};
 
+use crate::config::PublishStrategy;
 use crate::db::models::CatType;
-use crate::db::queries::users;
+use crate::db::queries::{upload_quota, users};
 use crate::state::AppState;
 use crate::web::auth::verify_session;
 use crate::web::context::{build_context, validate_csrf};
@@ -27,6 +28,17 @@ fn json_success(data: serde_json::Value) -> Response {
     (StatusCode::OK, axum::Json(data)).into_response()
 }
 
+/// Outcome of an upload step: a value, or the status and error code to
+/// report.
+type UploadResult<T> = Result<T, (StatusCode, &'static str)>;
+
+fn reply(result: UploadResult<serde_json::Value>) -> Response {
+    match result {
+        Ok(data) => json_success(data),
+        Err((status, error)) => json_error(status, error),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Permission check
 // ---------------------------------------------------------------------------
@@ -59,6 +71,45 @@ async fn check_upload_permission(state: &AppState, jar: &CookieJar) -> Result<i6
         .map_err(|()| json_error(StatusCode::FORBIDDEN, "forbidden"))
 }
 
+// ---------------------------------------------------------------------------
+// Monthly upload quota
+// ---------------------------------------------------------------------------
+
+/// Refuse `files` more books of `bytes` bytes beyond the user's monthly
+/// quota. Only published books count against it.
+async fn check_quota(state: &AppState, user_id: i64, files: i64, bytes: i64) -> UploadResult<()> {
+    match upload_quota::get(&state.db, user_id).await {
+        Ok(quota) if quota.allows(files, bytes) => Ok(()),
+        Ok(_) => Err((StatusCode::FORBIDDEN, "error_quota")),
+        Err(e) => {
+            tracing::error!("Failed to read upload quota of user {user_id}: {e}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "error_upload"))
+        }
+    }
+}
+
+/// Count published books against the user's quota (logged on failure: the
+/// books are already in the library).
+async fn record_quota_usage(state: &AppState, user_id: i64, books: &[PublishedBook]) {
+    let bytes = books.iter().map(|b| b.upload_state.size).sum();
+    if let Err(e) = upload_quota::record(&state.db, user_id, books.len() as i64, bytes).await {
+        tracing::warn!("Failed to record upload usage of user {user_id}: {e}");
+    }
+}
+
+/// Refuse writing `bytes` under `dir` when that would leave less than
+/// `server.min_free_space_mb` free.
+fn check_disk_space(state: &AppState, dir: &std::path::Path, bytes: u64) -> UploadResult<()> {
+    if crate::disk::has_room(dir, bytes, state.config.server.min_free_space_mb) {
+        return Ok(());
+    }
+    tracing::warn!(
+        "Refusing upload of {bytes} bytes: low disk space under '{}'",
+        dir.display()
+    );
+    Err((StatusCode::INSUFFICIENT_STORAGE, "error_disk_full"))
+}
+
 // ---------------------------------------------------------------------------
 // Token generation (HMAC-SHA256 over timestamp + counter + pid)
 // ---------------------------------------------------------------------------
@@ -92,7 +143,7 @@ fn generate_token(secret: &[u8]) -> String {
 
 /// Validate the extension of `filename` against a list of allowed extensions.
 /// Returns `Some(lowercase_ext)` if valid, `None` otherwise.
-fn validate_extension(filename: &str, allowed: &[String]) -> Option<String> {
+pub(crate) fn validate_extension(filename: &str, allowed: &[String]) -> Option<String> {
     let ext = std::path::Path::new(filename)
         .extension()?
         .to_string_lossy()
@@ -171,6 +222,12 @@ struct UploadState {
     annotation: String,
     docdate: String,
     lang: String,
+    #[serde(default)]
+    preview_text: String,
+    #[serde(default)]
+    word_count: i32,
+    #[serde(default)]
+    page_count: i32,
     series_title: Option<String>,
     series_index: i32,
     has_cover: bool,
@@ -180,6 +237,36 @@ struct UploadState {
     created_at: String,
 }
 
+// ---------------------------------------------------------------------------
+// Resumable (chunked) upload sessions persisted as JSON on disk
+// ---------------------------------------------------------------------------
+
+/// Largest chunk accepted by `PUT /web/upload/chunked/{id}`; the upload page
+/// sends chunks of exactly this size.
+pub const UPLOAD_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Sessions that received no chunk for this long are treated as abandoned.
+const CHUNKED_SESSION_MAX_IDLE_SECS: u64 = 24 * 3600;
+
+/// A resumable upload in progress. Received bytes are appended to
+/// `chunked_{id}.part`; its length is the authoritative upload offset.
+#[derive(Serialize, Deserialize)]
+struct ChunkedSession {
+    original_filename: String,
+    size: u64,
+    user_id: i64,
+    created_at: String,
+    updated_at: String,
+}
+
+fn chunked_state_path(temp_dir: &std::path::Path, id: &str) -> std::path::PathBuf {
+    temp_dir.join(format!("chunked_{id}.json"))
+}
+
+fn chunked_part_path(temp_dir: &std::path::Path, id: &str) -> std::path::PathBuf {
+    temp_dir.join(format!("chunked_{id}.part"))
+}
+
 // ---------------------------------------------------------------------------
 // Stale upload cleanup
 // ---------------------------------------------------------------------------
@@ -232,20 +319,71 @@ fn cleanup_stale_uploads(temp_dir: &std::path::Path, max_age_secs: u64) {
     }
 }
 
+/// Remove resumable upload sessions that received no chunk for
+/// `max_idle_secs`, together with their partial data.
+fn cleanup_abandoned_sessions(temp_dir: &std::path::Path, max_idle_secs: u64) {
+    let cutoff = chrono::Utc::now() - chrono::Duration::seconds(max_idle_secs as i64);
+
+    let entries = match std::fs::read_dir(temp_dir) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(id) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("chunked_"))
+            .and_then(|n| n.strip_suffix(".json"))
+        else {
+            continue;
+        };
+
+        let Some(session) = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<ChunkedSession>(&json).ok())
+        else {
+            continue;
+        };
+        let updated = match chrono::DateTime::parse_from_rfc3339(&session.updated_at) {
+            Ok(dt) => dt.with_timezone(&chrono::Utc),
+            Err(_) => continue,
+        };
+
+        if updated < cutoff {
+            tracing::info!("Cleaning up abandoned upload session: {id}");
+            let _ = std::fs::remove_file(chunked_part_path(temp_dir, id));
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
+
+/// Run both temp-file cleanups in the background.
+fn spawn_upload_cleanup(state: &AppState) {
+    let upload_path = state.config.upload.upload_path.clone();
+    tokio::task::spawn(async move {
+        let _ = tokio::task::spawn_blocking(move || {
+            cleanup_stale_uploads(&upload_path, 3600);
+            cleanup_abandoned_sessions(&upload_path, CHUNKED_SESSION_MAX_IDLE_SECS);
+        })
+        .await;
+    });
+}
+
 // ---------------------------------------------------------------------------
 // ZIP extraction helper
 // ---------------------------------------------------------------------------
 
-/// Extract a single book file from a ZIP archive.
+/// Extract a single book file from a ZIP archive, read from memory or a file.
 /// Returns `(data, extension, filename)` or an error-code string.
-fn extract_book_from_zip(
-    zip_data: &[u8],
+pub(crate) fn extract_book_from_zip(
+    reader: impl std::io::Read + std::io::Seek,
     allowed_exts: &[String],
     max_bytes: u64,
 ) -> Result<(Vec<u8>, String, String), &'static str> {
-    use std::io::{Cursor, Read};
+    use std::io::Read;
 
-    let reader = Cursor::new(zip_data);
     let mut archive = zip::ZipArchive::new(reader).map_err(|_| "error_unsupported")?;
 
     // Hard limit on number of entries
@@ -315,9 +453,9 @@ fn extract_book_from_zip(
 // ---------------------------------------------------------------------------
 
 pub async fn upload_page(State(state): State<AppState>, jar: CookieJar) -> Response {
-    if verify_upload_permission(&state, &jar).await.is_err() {
+    let Ok(user_id) = verify_upload_permission(&state, &jar).await else {
         return StatusCode::FORBIDDEN.into_response();
-    }
+    };
 
     let mut ctx = build_context(&state, &jar, "upload").await;
 
@@ -343,6 +481,11 @@ pub async fn upload_page(State(state): State<AppState>, jar: CookieJar) -> Respo
         "max_upload_size_mb",
         &state.config.upload.max_upload_size_mb,
     );
+    if let Ok(quota) = upload_quota::get(&state.db, user_id).await
+        && quota.is_limited()
+    {
+        ctx.insert("upload_quota", &quota);
+    }
 
     match state.tera.render("web/upload.html", &ctx) {
         Ok(html) => Html(html).into_response(),
@@ -363,13 +506,7 @@ pub async fn upload_file(
     mut multipart: axum::extract::Multipart,
 ) -> Response {
     // 0. Clean up stale uploads (older than 1 hour) in a blocking task
-    let upload_path = state.config.upload.upload_path.clone();
-    tokio::task::spawn(async move {
-        let _ = tokio::task::spawn_blocking(move || {
-            cleanup_stale_uploads(&upload_path, 3600);
-        })
-        .await;
-    });
+    spawn_upload_cleanup(&state);
 
     // 1. Permission check
     let user_id = match check_upload_permission(&state, &jar).await {
@@ -412,42 +549,163 @@ pub async fn upload_file(
         _ => return json_error(StatusCode::BAD_REQUEST, "error_no_file"),
     };
 
+    reply(stage_upload(&state, user_id, original_filename, data).await)
+}
+
+/// Validate one uploaded file, unpack it from a ZIP if needed, save it to
+/// the upload directory and parse it (steps 5–11 of an upload).
+async fn stage_upload(
+    state: &AppState,
+    user_id: i64,
+    original_filename: String,
+    data: Vec<u8>,
+) -> UploadResult<serde_json::Value> {
+    let max_bytes = state.config.upload.max_upload_size_mb * 1024 * 1024;
+
     // 5. Validate and extract extension
     let allowed_exts = &state.config.library.book_extensions;
-    let extension = match validate_extension(&original_filename, allowed_exts) {
-        Some(ext) => ext,
-        None => return json_error(StatusCode::BAD_REQUEST, "error_unsupported"),
-    };
+    let extension = validate_extension(&original_filename, allowed_exts)
+        .ok_or((StatusCode::BAD_REQUEST, "error_unsupported"))?;
 
     // 6. Handle ZIP: extract the single book file inside
     let (book_data, book_ext, book_filename) = if extension == "zip" {
         if !state.config.library.scan_zip {
-            return json_error(StatusCode::BAD_REQUEST, "error_unsupported");
-        }
-        match extract_book_from_zip(&data, allowed_exts, max_bytes) {
-            Ok(result) => result,
-            Err(error_code) => return json_error(StatusCode::BAD_REQUEST, error_code),
+            return Err((StatusCode::BAD_REQUEST, "error_unsupported"));
         }
+        extract_book_from_zip(std::io::Cursor::new(&data), allowed_exts, max_bytes)
+            .map_err(|error_code| (StatusCode::BAD_REQUEST, error_code))?
     } else {
-        (data, extension.clone(), original_filename.clone())
+        (data, extension, original_filename)
     };
+    check_quota(state, user_id, 1, book_data.len() as i64).await?;
+    check_disk_space(
+        state,
+        &state.config.upload.upload_path,
+        book_data.len() as u64,
+    )?;
 
     // 7. Generate token and save to temp dir
-    let token = generate_token(secret);
-    let temp_dir = &state.config.upload.upload_path;
-    let temp_file = temp_dir.join(format!("upload_{token}.{book_ext}"));
+    let token = generate_token(state.config.server.session_secret.as_bytes());
+    let temp_file = state
+        .config
+        .upload
+        .upload_path
+        .join(format!("upload_{token}.{book_ext}"));
 
     if let Err(e) = std::fs::write(&temp_file, &book_data) {
         tracing::error!("Failed to write temp file: {e}");
-        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "error_upload"));
+    }
+
+    register_upload(
+        state,
+        user_id,
+        token,
+        temp_file,
+        book_filename,
+        book_ext,
+        book_data.len() as u64,
+    )
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// POST /web/upload/files — several files in one multipart request
+// ---------------------------------------------------------------------------
+
+/// Stage every `file` field like `POST /web/upload/file` and report each
+/// file separately: `{"files": [{"filename", "success", "token", "meta"} |
+/// {"filename", "success": false, "error"}]}`. The request body limit
+/// applies to all files together.
+pub async fn upload_files(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    mut multipart: axum::extract::Multipart,
+) -> Response {
+    spawn_upload_cleanup(&state);
+
+    let user_id = match check_upload_permission(&state, &jar).await {
+        Ok(id) => id,
+        Err(r) => return r,
+    };
+
+    let max_bytes = state.config.upload.max_upload_size_mb * 1024 * 1024;
+    let mut csrf_token_value = String::new();
+    let mut files: Vec<(String, UploadResult<Vec<u8>>)> = Vec::new();
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name().unwrap_or("") {
+            "csrf_token" => {
+                csrf_token_value = field.text().await.unwrap_or_default();
+            }
+            "file" => {
+                if files.len() == MAX_FILES_PER_REQUEST {
+                    return json_error(StatusCode::BAD_REQUEST, "error_too_many");
+                }
+                let filename = field.file_name().unwrap_or("").to_string();
+                let data = match field.bytes().await {
+                    Ok(bytes) if bytes.is_empty() => {
+                        Err((StatusCode::BAD_REQUEST, "error_no_file"))
+                    }
+                    Ok(bytes) if bytes.len() as u64 > max_bytes => {
+                        Err((StatusCode::BAD_REQUEST, "error_too_large"))
+                    }
+                    Ok(bytes) => Ok(bytes.to_vec()),
+                    Err(_) => Err((StatusCode::BAD_REQUEST, "error_upload")),
+                };
+                files.push((filename, data));
+            }
+            _ => {}
+        }
     }
 
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &csrf_token_value) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+    if files.is_empty() {
+        return json_error(StatusCode::BAD_REQUEST, "error_no_file");
+    }
+
+    let mut results = Vec::with_capacity(files.len());
+    for (filename, data) in files {
+        let staged = match data {
+            Ok(data) => stage_upload(&state, user_id, filename.clone(), data).await,
+            Err(e) => Err(e),
+        };
+        let mut result = match staged {
+            Ok(value) => value,
+            Err((_, error)) => serde_json::json!({ "success": false, "error": error }),
+        };
+        result["filename"] = serde_json::Value::String(filename);
+        results.push(result);
+    }
+
+    json_success(serde_json::json!({
+        "success": true,
+        "files": results,
+    }))
+}
+
+/// Parse a book saved as `upload_{token}.{ext}` in the upload directory,
+/// store its cover and upload state, and return the parsed metadata
+/// (steps 8–11 of an upload).
+async fn register_upload(
+    state: &AppState,
+    user_id: i64,
+    token: String,
+    temp_file: std::path::PathBuf,
+    book_filename: String,
+    book_ext: String,
+    size: u64,
+) -> UploadResult<serde_json::Value> {
+    let temp_dir = &state.config.upload.upload_path;
+
     // 8. Parse metadata (in blocking task to avoid blocking the async runtime)
     let book_ext_clone = book_ext.clone();
     let temp_file_clone = temp_file.clone();
     let cover_cfg = crate::config::CoverImageConfig::from(&state.config.covers);
     let meta_result = tokio::task::spawn_blocking(move || {
-        crate::scanner::parse_book_file(&temp_file_clone, &book_ext_clone, cover_cfg)
+        crate::scanner::parse_book_file(&temp_file_clone, &book_ext_clone, cover_cfg, &[])
     })
     .await;
 
@@ -456,23 +714,30 @@ pub async fn upload_file(
         Ok(Err(e)) => {
             tracing::warn!("Failed to parse uploaded book: {e}");
             let _ = std::fs::remove_file(&temp_file);
-            return json_error(StatusCode::BAD_REQUEST, "error_parse");
+            return Err((StatusCode::BAD_REQUEST, "error_parse"));
         }
         Err(e) => {
             tracing::error!("spawn_blocking error: {e}");
             let _ = std::fs::remove_file(&temp_file);
-            return json_error(StatusCode::BAD_REQUEST, "error_parse");
+            return Err((StatusCode::BAD_REQUEST, "error_parse"));
         }
     };
 
-    // If the parser used the temp filename as fallback title, replace with original name
+    // If the parser used the temp filename as fallback title, replace with
+    // original name (or what the configured filename patterns make of it)
     if meta.title.starts_with("upload_") {
-        meta.title = std::path::Path::new(&book_filename)
+        let stem = std::path::Path::new(&book_filename)
             .file_stem()
             .unwrap_or_default()
             .to_string_lossy()
             .to_string();
+        let patterns = crate::scanner::parsers::filename::compile_patterns(
+            &state.config.library.filename_patterns,
+        );
+        meta.title = stem.clone();
+        crate::scanner::parsers::filename::apply_patterns(&patterns, &stem, &mut meta);
     }
+    meta.lang = crate::scanner::resolve_lang(&meta, state.config.library.lang_detect_threshold());
 
     // 9. Save cover to temp if present
     let cover_path = if let Some(ref cover_data) = meta.cover_data {
@@ -496,13 +761,16 @@ pub async fn upload_file(
         temp_path: temp_file.to_string_lossy().to_string(),
         original_filename: book_filename,
         extension: book_ext.clone(),
-        size: book_data.len() as i64,
+        size: size as i64,
         title: meta.title.clone(),
         authors: meta.authors.clone(),
         genres: meta.genres.clone(),
         annotation: meta.annotation.clone(),
         docdate: meta.docdate.clone(),
         lang: meta.lang.clone(),
+        preview_text: meta.preview_text.clone(),
+        word_count: meta.word_count,
+        page_count: meta.page_count,
         series_title: meta.series_title.clone(),
         series_index: meta.series_index,
         has_cover: meta.cover_data.is_some(),
@@ -520,11 +788,11 @@ pub async fn upload_file(
         if let Some(ref cp) = upload_state.cover_path {
             let _ = std::fs::remove_file(cp);
         }
-        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "error_upload"));
     }
 
     // 11. Return success with parsed metadata
-    json_success(serde_json::json!({
+    Ok(serde_json::json!({
         "success": true,
         "token": token,
         "meta": {
@@ -532,15 +800,362 @@ pub async fn upload_file(
             "authors": meta.authors,
             "genres": meta.genres,
             "format": book_ext,
-            "size": book_data.len(),
+            "size": size,
             "lang": meta.lang,
             "has_cover": meta.cover_data.is_some(),
+            "cover_url": meta.cover_data.is_some().then(|| format!("/web/upload/cover/{token}")),
             "series_title": meta.series_title,
             "series_index": meta.series_index,
         }
     }))
 }
 
+// ---------------------------------------------------------------------------
+// Resumable uploads: POST /web/upload/chunked, GET|PUT /web/upload/chunked/{id},
+// POST /web/upload/chunked/{id}/finish
+// ---------------------------------------------------------------------------
+
+/// Most files accepted by one `POST /web/upload/files` or
+/// `POST /web/upload/publish-all` request.
+pub const MAX_FILES_PER_REQUEST: usize = 20;
+
+/// Sessions with a chunk or finish request in flight; a second concurrent
+/// request for the same session is rejected instead of interleaving writes.
+static BUSY_SESSIONS: std::sync::LazyLock<std::sync::Mutex<std::collections::HashSet<String>>> =
+    std::sync::LazyLock::new(Default::default);
+
+/// Marks a session busy until dropped.
+struct SessionGuard(String);
+
+impl SessionGuard {
+    fn acquire(id: &str) -> Option<Self> {
+        let mut busy = BUSY_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        busy.insert(id.to_string()).then(|| Self(id.to_string()))
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let mut busy = BUSY_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        busy.remove(&self.0);
+    }
+}
+
+fn json_offset_error(error: &str, received: u64) -> Response {
+    (
+        StatusCode::CONFLICT,
+        axum::Json(serde_json::json!({
+            "success": false,
+            "error": error,
+            "received": received,
+        })),
+    )
+        .into_response()
+}
+
+/// Load the session `id` and check that `user_id` owns it.
+fn load_chunked_session(
+    temp_dir: &std::path::Path,
+    id: &str,
+    user_id: i64,
+) -> UploadResult<ChunkedSession> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) || id.len() > 64 {
+        return Err((StatusCode::NOT_FOUND, "error_session"));
+    }
+    let session: ChunkedSession = std::fs::read_to_string(chunked_state_path(temp_dir, id))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or((StatusCode::NOT_FOUND, "error_session"))?;
+    if session.user_id != user_id {
+        return Err((StatusCode::FORBIDDEN, "forbidden"));
+    }
+    Ok(session)
+}
+
+/// Bytes received so far for session `id`.
+fn chunked_received(temp_dir: &std::path::Path, id: &str) -> u64 {
+    std::fs::metadata(chunked_part_path(temp_dir, id))
+        .map(|m| m.len())
+        .unwrap_or(0)
+}
+
+#[derive(Deserialize)]
+pub struct ChunkedStartForm {
+    pub filename: String,
+    pub size: u64,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// Open a resumable upload session for a file of `size` bytes.
+pub async fn chunked_start(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(form): axum::Json<ChunkedStartForm>,
+) -> Response {
+    spawn_upload_cleanup(&state);
+
+    let user_id = match check_upload_permission(&state, &jar).await {
+        Ok(id) => id,
+        Err(r) => return r,
+    };
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+
+    if form.size == 0 {
+        return json_error(StatusCode::BAD_REQUEST, "error_no_file");
+    }
+    if form.size > state.config.upload.max_upload_size_mb * 1024 * 1024 {
+        return json_error(StatusCode::BAD_REQUEST, "error_too_large");
+    }
+    match validate_extension(&form.filename, &state.config.library.book_extensions) {
+        Some(ext) if ext != "zip" || state.config.library.scan_zip => {}
+        _ => return json_error(StatusCode::BAD_REQUEST, "error_unsupported"),
+    }
+    if let Err((status, error)) = check_quota(&state, user_id, 1, form.size as i64).await {
+        return json_error(status, error);
+    }
+    if let Err((status, error)) =
+        check_disk_space(&state, &state.config.upload.upload_path, form.size)
+    {
+        return json_error(status, error);
+    }
+
+    let id = generate_token(secret);
+    let temp_dir = &state.config.upload.upload_path;
+    let now = chrono::Utc::now().to_rfc3339();
+    let session = ChunkedSession {
+        original_filename: form.filename,
+        size: form.size,
+        user_id,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    let state_json = serde_json::to_string(&session).unwrap_or_default();
+    if let Err(e) = std::fs::File::create(chunked_part_path(temp_dir, &id))
+        .and_then(|_| std::fs::write(chunked_state_path(temp_dir, &id), state_json))
+    {
+        tracing::error!("Failed to create upload session: {e}");
+        let _ = std::fs::remove_file(chunked_part_path(temp_dir, &id));
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+    }
+
+    json_success(serde_json::json!({
+        "success": true,
+        "session": id,
+        "chunk_size": UPLOAD_CHUNK_BYTES,
+        "received": 0,
+    }))
+}
+
+/// Progress of a resumable upload; clients resume from `received`.
+pub async fn chunked_status(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Response {
+    let user_id = match check_upload_permission(&state, &jar).await {
+        Ok(id) => id,
+        Err(r) => return r,
+    };
+    let temp_dir = &state.config.upload.upload_path;
+    let session = match load_chunked_session(temp_dir, &id, user_id) {
+        Ok(s) => s,
+        Err((status, error)) => return json_error(status, error),
+    };
+
+    json_success(serde_json::json!({
+        "success": true,
+        "session": id,
+        "filename": session.original_filename,
+        "size": session.size,
+        "received": chunked_received(temp_dir, &id),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ChunkQuery {
+    pub offset: u64,
+}
+
+/// Append one chunk at `?offset=`, which must equal the bytes received so
+/// far; a mismatch answers 409 with the offset to resume from. The CSRF
+/// token travels in the `X-CSRF-Token` header since the body is raw data.
+pub async fn chunked_append(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<ChunkQuery>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    let user_id = match check_upload_permission(&state, &jar).await {
+        Ok(id) => id,
+        Err(r) => return r,
+    };
+    let secret = state.config.server.session_secret.as_bytes();
+    let csrf = headers
+        .get("x-csrf-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if !validate_csrf(&jar, secret, csrf) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+
+    let temp_dir = &state.config.upload.upload_path;
+    let mut session = match load_chunked_session(temp_dir, &id, user_id) {
+        Ok(s) => s,
+        Err((status, error)) => return json_error(status, error),
+    };
+    let Some(_guard) = SessionGuard::acquire(&id) else {
+        return json_offset_error("error_busy", chunked_received(temp_dir, &id));
+    };
+
+    let received = chunked_received(temp_dir, &id);
+    if query.offset != received {
+        return json_offset_error("error_offset", received);
+    }
+    if received + body.len() as u64 > session.size {
+        return json_error(StatusCode::BAD_REQUEST, "error_too_large");
+    }
+
+    let part_path = chunked_part_path(temp_dir, &id);
+    if let Err(e) = append_chunk(&part_path, &body).await {
+        tracing::error!("Failed to append upload chunk: {e}");
+        // Drop a partially written chunk so the client can resend it.
+        if let Ok(part) = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&part_path)
+            .await
+        {
+            let _ = part.set_len(received).await;
+        }
+        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+    }
+
+    session.updated_at = chrono::Utc::now().to_rfc3339();
+    if let Ok(json) = serde_json::to_string(&session) {
+        let _ = tokio::fs::write(chunked_state_path(temp_dir, &id), json).await;
+    }
+
+    json_success(serde_json::json!({
+        "success": true,
+        "received": received + body.len() as u64,
+        "size": session.size,
+    }))
+}
+
+async fn append_chunk(part_path: &std::path::Path, chunk: &[u8]) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let mut part = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(part_path)
+        .await?;
+    part.write_all(chunk).await?;
+    part.flush().await
+}
+
+#[derive(Deserialize)]
+pub struct ChunkedFinishForm {
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// Assemble a fully received session into a regular upload and reply like
+/// `POST /web/upload/file`.
+pub async fn chunked_finish(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    axum::Json(form): axum::Json<ChunkedFinishForm>,
+) -> Response {
+    let user_id = match check_upload_permission(&state, &jar).await {
+        Ok(id) => id,
+        Err(r) => return r,
+    };
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+
+    let temp_dir = &state.config.upload.upload_path;
+    let session = match load_chunked_session(temp_dir, &id, user_id) {
+        Ok(s) => s,
+        Err((status, error)) => return json_error(status, error),
+    };
+    let Some(_guard) = SessionGuard::acquire(&id) else {
+        return json_offset_error("error_busy", chunked_received(temp_dir, &id));
+    };
+    let received = chunked_received(temp_dir, &id);
+    if received != session.size {
+        return json_offset_error("error_incomplete", received);
+    }
+
+    let allowed_exts = &state.config.library.book_extensions;
+    let Some(extension) = validate_extension(&session.original_filename, allowed_exts) else {
+        return json_error(StatusCode::BAD_REQUEST, "error_unsupported");
+    };
+    let part_path = chunked_part_path(temp_dir, &id);
+    let state_path = chunked_state_path(temp_dir, &id);
+    let discard_session = || async {
+        let _ = tokio::fs::remove_file(&part_path).await;
+        let _ = tokio::fs::remove_file(&state_path).await;
+    };
+
+    // Same ZIP handling as a single-request upload, reading the archive from
+    // disk; other books are moved into place without reading them at all.
+    let (temp_file, book_filename, book_ext, size) = if extension == "zip" {
+        let max_bytes = state.config.upload.max_upload_size_mb * 1024 * 1024;
+        let exts = allowed_exts.clone();
+        let zip_path = part_path.clone();
+        let extracted = tokio::task::spawn_blocking(move || {
+            std::fs::File::open(&zip_path)
+                .map_err(|_| "error_upload")
+                .and_then(|file| {
+                    extract_book_from_zip(std::io::BufReader::new(file), &exts, max_bytes)
+                })
+        })
+        .await
+        .unwrap_or(Err("error_upload"));
+        let (data, book_ext, book_filename) = match extracted {
+            Ok(result) => result,
+            Err(error_code) => {
+                discard_session().await;
+                return json_error(StatusCode::BAD_REQUEST, error_code);
+            }
+        };
+        let temp_file = temp_dir.join(format!("upload_{id}.{book_ext}"));
+        if let Err(e) = tokio::fs::write(&temp_file, &data).await {
+            tracing::error!("Failed to write temp file: {e}");
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+        }
+        (temp_file, book_filename, book_ext, data.len() as u64)
+    } else {
+        let temp_file = temp_dir.join(format!("upload_{id}.{extension}"));
+        if let Err(e) = tokio::fs::rename(&part_path, &temp_file).await {
+            tracing::error!("Failed to move assembled upload: {e}");
+            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_upload");
+        }
+        (temp_file, session.original_filename, extension, received)
+    };
+    discard_session().await;
+
+    reply(
+        register_upload(
+            &state,
+            user_id,
+            id,
+            temp_file,
+            book_filename,
+            book_ext,
+            size,
+        )
+        .await,
+    )
+}
+
 // ---------------------------------------------------------------------------
 // GET /web/upload/cover/{token} — serve temp cover image
 // ---------------------------------------------------------------------------
@@ -602,6 +1217,15 @@ pub async fn upload_cover(
 
 #[derive(Deserialize)]
 pub struct PublishForm {
+    #[serde(flatten)]
+    pub item: PublishItem,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// One staged upload to publish, with the metadata edits made on the page.
+#[derive(Deserialize)]
+pub struct PublishItem {
     pub token: String,
     #[serde(default)]
     pub title: String,
@@ -613,163 +1237,187 @@ pub struct PublishForm {
     pub series_title: Option<String>,
     #[serde(default)]
     pub series_index: Option<i32>,
-    #[serde(default)]
-    pub csrf_token: String,
 }
 
-pub async fn publish(
-    State(state): State<AppState>,
-    jar: CookieJar,
-    axum::Json(form): axum::Json<PublishForm>,
-) -> Response {
-    // 1. Permission check
-    let user_id = match check_upload_permission(&state, &jar).await {
-        Ok(id) => id,
-        Err(r) => return r,
-    };
+/// A book inserted by [`publish_item`], with everything needed to undo the
+/// insert or to drop its staging files.
+struct PublishedBook {
+    book_id: i64,
+    dest_path: std::path::PathBuf,
+    placement: Placement,
+    state_file: std::path::PathBuf,
+    upload_state: UploadState,
+}
 
-    // 2. CSRF check
-    let secret = state.config.server.session_secret.as_bytes();
-    if !validate_csrf(&jar, secret, &form.csrf_token) {
-        return json_error(StatusCode::FORBIDDEN, "forbidden");
+impl PublishedBook {
+    fn remove_staging_files(&self) {
+        let _ = std::fs::remove_file(&self.upload_state.temp_path);
+        if let Some(ref cover) = self.upload_state.cover_path {
+            let _ = std::fs::remove_file(cover);
+        }
+        let _ = std::fs::remove_file(&self.state_file);
     }
 
-    // 3. Validate token format
-    if !form.token.chars().all(|c| c.is_ascii_hexdigit()) || form.token.len() > 64 {
-        return json_error(StatusCode::BAD_REQUEST, "error_publish");
+    /// Undo the publish: drop the book row, its cover and the library copy.
+    /// The staging files stay so the upload can be published again.
+    async fn roll_back(&self, state: &AppState) {
+        if let Err(e) =
+            crate::db::queries::books::delete_book_and_relations(&state.db, self.book_id).await
+        {
+            tracing::error!("Failed to roll back published book {}: {e}", self.book_id);
+        }
+        crate::scanner::delete_cover(&state.config.covers.covers_path, self.book_id);
+        unplace_upload(
+            std::path::Path::new(&self.upload_state.temp_path),
+            &self.dest_path,
+            self.placement,
+        );
     }
+}
 
-    // 4. Read upload state
-    let temp_dir = &state.config.upload.upload_path;
-    let state_file = temp_dir.join(format!("upload_{}.json", form.token));
-    let state_json = match std::fs::read_to_string(&state_file) {
-        Ok(s) => s,
-        Err(_) => return json_error(StatusCode::BAD_REQUEST, "error_publish"),
-    };
-    let upload_state: UploadState = match serde_json::from_str(&state_json) {
-        Ok(s) => s,
-        Err(_) => return json_error(StatusCode::BAD_REQUEST, "error_publish"),
-    };
-
-    // 5. Verify user owns this upload
-    if upload_state.user_id != user_id {
-        return json_error(StatusCode::FORBIDDEN, "forbidden");
-    }
+fn valid_token(token: &str) -> bool {
+    !token.is_empty() && token.len() <= 64 && token.chars().all(|c| c.is_ascii_hexdigit())
+}
 
-    // 6. Build destination path under per-user upload directory
-    let username = match users::get_username(&state.db, user_id).await {
-        Ok(name) if !name.is_empty() => name,
-        Ok(_) => return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish"),
+/// Per-user upload directory under `root_path` (a catalog path).
+async fn user_upload_dir(state: &AppState, user_id: i64) -> UploadResult<String> {
+    match users::get_username(&state.db, user_id).await {
+        Ok(name) if !name.is_empty() => Ok(sanitize_upload_dir_name(&name)),
+        Ok(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "error_publish")),
         Err(e) => {
             tracing::error!("Failed to load username for publish: {e}");
-            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "error_publish"))
         }
-    };
-    let user_dir = sanitize_upload_dir_name(&username);
+    }
+}
 
-    // 7. Build a safe destination filename
-    let safe_filename = format!(
+/// Read the upload state of `token` and check that `user_id` owns it.
+fn load_upload_state(
+    temp_dir: &std::path::Path,
+    token: &str,
+    user_id: i64,
+) -> UploadResult<(UploadState, std::path::PathBuf)> {
+    if !valid_token(token) {
+        return Err((StatusCode::BAD_REQUEST, "error_publish"));
+    }
+    let state_file = temp_dir.join(format!("upload_{token}.json"));
+    let upload_state: UploadState = std::fs::read_to_string(&state_file)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .ok_or((StatusCode::BAD_REQUEST, "error_publish"))?;
+    if upload_state.user_id != user_id {
+        return Err((StatusCode::FORBIDDEN, "forbidden"));
+    }
+    Ok((upload_state, state_file))
+}
+
+/// Library filename a staged upload is published under.
+fn publish_filename(upload_state: &UploadState) -> String {
+    format!(
         "{}.{}",
         sanitize_filename(&upload_state.original_filename),
         upload_state.extension
-    );
-    let root_path = &state.config.library.root_path;
-    let dest_dir = root_path.join(&user_dir);
+    )
+}
+
+/// Place one staged upload in the user's directory (see [`place_upload`])
+/// and insert it into the DB. Staging files are left in place; the caller
+/// removes them once the whole request has succeeded.
+async fn publish_item(
+    state: &AppState,
+    user_id: i64,
+    user_dir: &str,
+    item: PublishItem,
+) -> UploadResult<PublishedBook> {
+    // 1. Read upload state and verify the user owns it
+    let temp_dir = &state.config.upload.upload_path;
+    let (upload_state, state_file) = load_upload_state(temp_dir, &item.token, user_id)?;
+
+    // 2. Build a safe destination under the per-user upload directory
+    let safe_filename = publish_filename(&upload_state);
+    let dest_dir = state.config.library.root_path.join(user_dir);
     let dest_path = dest_dir.join(&safe_filename);
 
-    // 8. Check for DB duplicate in the same user directory
+    // 3. Check for DB duplicate in the same user directory
     if let Ok(Some(_)) =
-        crate::db::queries::books::find_by_path_and_filename(&state.db, &user_dir, &safe_filename)
+        crate::db::queries::books::find_by_path_and_filename(&state.db, user_dir, &safe_filename)
             .await
     {
-        return json_error(StatusCode::CONFLICT, "error_duplicate");
+        return Err((StatusCode::CONFLICT, "error_duplicate"));
     }
 
-    // 9. Ensure destination directory exists (first upload for user).
+    // 4. Ensure destination directory exists (first upload for user).
     if let Err(e) = std::fs::create_dir_all(&dest_dir) {
         tracing::error!(
             "Failed to create destination upload directory '{}': {e}",
             dest_dir.display()
         );
-        return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish");
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "error_publish"));
     }
 
-    // 10. Atomically create destination file (prevents TOCTOU race on disk)
-    let source_data = match std::fs::read(&upload_state.temp_path) {
-        Ok(d) => d,
+    // 5. Place the file; the reserved name prevents TOCTOU races on disk
+    let temp_path = std::path::Path::new(&upload_state.temp_path);
+    let strategy = state.config.upload.publish_strategy;
+    let placement = match place_upload(temp_path, &dest_path, strategy) {
+        Ok(placement) => placement,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err((StatusCode::CONFLICT, "error_duplicate"));
+        }
         Err(e) => {
-            tracing::error!("Failed to read temp file: {e}");
-            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish");
+            tracing::error!("Failed to place '{}': {e}", dest_path.display());
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "error_publish"));
         }
     };
-    {
-        use std::io::Write;
-        let mut dest_file = match std::fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(&dest_path)
-        {
-            Ok(f) => f,
-            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
-                return json_error(StatusCode::CONFLICT, "error_duplicate");
-            }
-            Err(e) => {
-                tracing::error!("Failed to create destination file: {e}");
-                return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish");
-            }
-        };
-        if let Err(e) = dest_file.write_all(&source_data) {
-            tracing::error!("Failed to write to destination: {e}");
-            let _ = std::fs::remove_file(&dest_path);
-            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish");
-        }
-    }
 
-    // 11. Build BookMeta and insert into DB
+    // 6. Build BookMeta and insert into DB
     let cover_data = upload_state
         .cover_path
         .as_ref()
         .and_then(|p| std::fs::read(p).ok());
 
     // Use user-submitted title if provided and valid, otherwise fall back to parsed title
-    let publish_title = match crate::web::admin::validate_book_title(&form.title) {
+    let publish_title = match crate::web::admin::validate_book_title(&item.title) {
         Ok(t) => t,
         Err(_) => upload_state.title.clone(),
     };
 
     let meta = crate::scanner::parsers::BookMeta {
         title: publish_title,
-        authors: if form.authors.is_empty() {
+        authors: if item.authors.is_empty() {
             upload_state.authors.clone()
         } else {
-            form.authors
+            item.authors
         },
-        genres: if form.genres.is_empty() {
+        genres: if item.genres.is_empty() {
             upload_state.genres.clone()
         } else {
-            form.genres
+            item.genres
         },
         annotation: upload_state.annotation.clone(),
         docdate: upload_state.docdate.clone(),
         lang: upload_state.lang.clone(),
-        series_title: if form.series_title.is_some() {
-            form.series_title
+        preview_text: upload_state.preview_text.clone(),
+        word_count: upload_state.word_count,
+        page_count: upload_state.page_count,
+        series_title: if item.series_title.is_some() {
+            item.series_title
         } else {
             upload_state.series_title.clone()
         },
-        series_index: form.series_index.unwrap_or(upload_state.series_index),
+        series_index: item.series_index.unwrap_or(upload_state.series_index),
         cover_data,
         cover_type: upload_state.cover_type.clone(),
+        ..Default::default()
     };
 
     // Ensure user upload catalog exists.
     let catalog_id =
-        match crate::scanner::ensure_catalog(&state.db, &user_dir, CatType::Normal).await {
+        match crate::scanner::ensure_catalog(&state.db, user_dir, CatType::Normal).await {
             Ok(id) => id,
             Err(e) => {
                 tracing::error!("Failed to ensure catalog: {e}");
-                let _ = std::fs::remove_file(&dest_path);
-                return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish");
+                unplace_upload(temp_path, &dest_path, placement);
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, "error_publish"));
             }
         };
 
@@ -778,7 +1426,7 @@ pub async fn publish(
         &state.db,
         catalog_id,
         &safe_filename,
-        &user_dir, // path relative to root
+        user_dir, // path relative to root
         &upload_state.extension,
         upload_state.size,
         CatType::Normal,
@@ -791,28 +1439,242 @@ pub async fn publish(
         Ok(id) => id,
         Err(e) => {
             tracing::error!("Failed to insert book into DB: {e}");
-            // Rollback: delete the copied file
-            let _ = std::fs::remove_file(&dest_path);
-            return json_error(StatusCode::INTERNAL_SERVER_ERROR, "error_publish");
+            unplace_upload(temp_path, &dest_path, placement);
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "error_publish"));
         }
     };
 
-    // 12. Update counters (non-critical, log on failure)
+    Ok(PublishedBook {
+        book_id,
+        dest_path,
+        placement,
+        state_file,
+        upload_state,
+    })
+}
+
+/// How [`place_upload`] put a staged file into the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Moved,
+    Linked,
+    Copied,
+}
+
+/// Put the staged upload `source` at `dest` following `strategy`. `dest` is
+/// reserved first (`AlreadyExists` if taken); a link or copy is made next to
+/// it under a hidden name and renamed over the reservation, so the library
+/// never shows a partial file. Moving and linking fall back to copying when
+/// they fail, e.g. across filesystems. Nothing is left at `dest` on error.
+fn place_upload(
+    source: &std::path::Path,
+    dest: &std::path::Path,
+    strategy: PublishStrategy,
+) -> std::io::Result<Placement> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)?;
+    if strategy == PublishStrategy::Move && std::fs::rename(source, dest).is_ok() {
+        return Ok(Placement::Moved);
+    }
+    let partial = partial_publish_path(dest);
+    let _ = std::fs::remove_file(&partial);
+    let placed =
+        if strategy == PublishStrategy::Hardlink && std::fs::hard_link(source, &partial).is_ok() {
+            Ok(Placement::Linked)
+        } else {
+            std::fs::copy(source, &partial).map(|_| Placement::Copied)
+        };
+    match placed.and_then(|placement| std::fs::rename(&partial, dest).map(|()| placement)) {
+        Ok(placement) => Ok(placement),
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            let _ = std::fs::remove_file(dest);
+            Err(e)
+        }
+    }
+}
+
+/// Undo [`place_upload`]: a moved file goes back to staging so the upload
+/// can be published again; a link or copy is removed.
+fn unplace_upload(source: &std::path::Path, dest: &std::path::Path, placement: Placement) {
+    if placement == Placement::Moved {
+        if let Err(e) = std::fs::rename(dest, source) {
+            // Keep the only copy; the next scan adds it to the library.
+            tracing::error!("Failed to move '{}' back to staging: {e}", dest.display());
+        }
+        return;
+    }
+    let _ = std::fs::remove_file(dest);
+}
+
+/// Hidden sibling of `dest` a book is written to before the final rename.
+fn partial_publish_path(dest: &std::path::Path) -> std::path::PathBuf {
+    let name = dest.file_name().unwrap_or_default().to_string_lossy();
+    dest.with_file_name(format!(".{name}.publishing"))
+}
+
+/// Room for `bytes` of published books in the library and for their covers.
+fn library_has_room(state: &AppState, bytes: i64) -> UploadResult<()> {
+    check_disk_space(state, &state.config.library.root_path, bytes.max(0) as u64)?;
+    check_disk_space(state, &state.config.covers.covers_path, 0)
+}
+
+/// Refresh counters and caches after books were published.
+async fn finish_publish(state: &AppState) {
     if let Err(e) = crate::db::queries::counters::update_all(&state.db).await {
         tracing::warn!("Failed to update counters after publish: {e}");
     }
+    state.library_changed().await;
+}
+
+pub async fn publish(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(form): axum::Json<PublishForm>,
+) -> Response {
+    // 1. Permission check
+    let user_id = match check_upload_permission(&state, &jar).await {
+        Ok(id) => id,
+        Err(r) => return r,
+    };
+
+    // 2. CSRF check
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+
+    // 3. Check the monthly quota and the free space in the library
+    let temp_dir = &state.config.upload.upload_path;
+    let quota_check = match load_upload_state(temp_dir, &form.item.token, user_id) {
+        Ok((upload_state, _)) => match check_quota(&state, user_id, 1, upload_state.size).await {
+            Ok(()) => library_has_room(&state, upload_state.size),
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    if let Err((status, error)) = quota_check {
+        return json_error(status, error);
+    }
+
+    // 4. Copy into the library and insert into the DB
+    let published = match user_upload_dir(&state, user_id).await {
+        Ok(user_dir) => publish_item(&state, user_id, &user_dir, form.item).await,
+        Err(e) => Err(e),
+    };
+    let published = match published {
+        Ok(p) => p,
+        Err((status, error)) => return json_error(status, error),
+    };
+
+    // 5. Update counters (non-critical, log on failure) and clean up temp files
+    record_quota_usage(&state, user_id, std::slice::from_ref(&published)).await;
+    finish_publish(&state).await;
+    published.remove_staging_files();
+
+    json_success(serde_json::json!({
+        "success": true,
+        "book_id": published.book_id,
+    }))
+}
+
+// ---------------------------------------------------------------------------
+// POST /web/upload/publish-all — publish several staged uploads at once
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+pub struct PublishAllForm {
+    pub items: Vec<PublishItem>,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// Publish every item or none: items are checked up front, and when one
+/// fails the books already inserted by this request are removed again. The
+/// error names the failing item by its position in `items`.
+pub async fn publish_all(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(form): axum::Json<PublishAllForm>,
+) -> Response {
+    let user_id = match check_upload_permission(&state, &jar).await {
+        Ok(id) => id,
+        Err(r) => return r,
+    };
+    let secret = state.config.server.session_secret.as_bytes();
+    if !validate_csrf(&jar, secret, &form.csrf_token) {
+        return json_error(StatusCode::FORBIDDEN, "forbidden");
+    }
+    if form.items.is_empty() || form.items.len() > MAX_FILES_PER_REQUEST {
+        return json_error(StatusCode::BAD_REQUEST, "error_publish");
+    }
+
+    let item_error = |index: usize, status: StatusCode, error: &str| {
+        (
+            status,
+            axum::Json(serde_json::json!({
+                "success": false,
+                "error": error,
+                "index": index,
+            })),
+        )
+            .into_response()
+    };
 
-    // 13. Clean up temp files
-    let _ = std::fs::remove_file(&upload_state.temp_path);
-    if let Some(ref cover) = upload_state.cover_path {
-        let _ = std::fs::remove_file(cover);
+    // Two items may not land on the same library file.
+    let temp_dir = &state.config.upload.upload_path;
+    let mut filenames = std::collections::HashSet::new();
+    let mut total_bytes = 0;
+    for (index, item) in form.items.iter().enumerate() {
+        match load_upload_state(temp_dir, &item.token, user_id) {
+            Ok((upload_state, _)) if filenames.insert(publish_filename(&upload_state)) => {
+                total_bytes += upload_state.size;
+            }
+            Ok(_) => return item_error(index, StatusCode::CONFLICT, "error_duplicate"),
+            Err((status, error)) => return item_error(index, status, error),
+        }
     }
-    let _ = std::fs::remove_file(&state_file);
+    let checked = match check_quota(&state, user_id, form.items.len() as i64, total_bytes).await {
+        Ok(()) => library_has_room(&state, total_bytes),
+        Err(e) => Err(e),
+    };
+    if let Err((status, error)) = checked {
+        return json_error(status, error);
+    }
+
+    let user_dir = match user_upload_dir(&state, user_id).await {
+        Ok(dir) => dir,
+        Err((status, error)) => return json_error(status, error),
+    };
 
-    // 14. Return success
+    let mut published: Vec<PublishedBook> = Vec::with_capacity(form.items.len());
+    for (index, item) in form.items.into_iter().enumerate() {
+        match publish_item(&state, user_id, &user_dir, item).await {
+            Ok(book) => published.push(book),
+            Err((status, error)) => {
+                for book in &published {
+                    book.roll_back(&state).await;
+                }
+                if !published.is_empty() {
+                    finish_publish(&state).await;
+                }
+                return item_error(index, status, error);
+            }
+        }
+    }
+
+    record_quota_usage(&state, user_id, &published).await;
+    finish_publish(&state).await;
+    for book in &published {
+        book.remove_staging_files();
+    }
+
+    let book_ids: Vec<i64> = published.iter().map(|b| b.book_id).collect();
     json_success(serde_json::json!({
         "success": true,
-        "book_id": book_id,
+        "book_ids": book_ids,
     }))
 }
 
@@ -925,7 +1787,8 @@ mod tests {
             ("books/test-book.fb2", b"book-bytes"),
         ]);
 
-        let (data, ext, filename) = extract_book_from_zip(&zip_data, &allowed, 10_000).unwrap();
+        let (data, ext, filename) =
+            extract_book_from_zip(std::io::Cursor::new(&zip_data), &allowed, 10_000).unwrap();
         assert_eq!(data, b"book-bytes");
         assert_eq!(ext, "fb2");
         assert_eq!(filename, "test-book.fb2");
@@ -935,7 +1798,8 @@ mod tests {
     fn test_extract_book_from_zip_multiple_books_rejected() {
         let allowed = vec!["fb2".to_string(), "epub".to_string()];
         let zip_data = make_zip(&[("a.fb2", b"one"), ("b.epub", b"two")]);
-        let err = extract_book_from_zip(&zip_data, &allowed, 10_000).unwrap_err();
+        let err =
+            extract_book_from_zip(std::io::Cursor::new(&zip_data), &allowed, 10_000).unwrap_err();
         assert_eq!(err, "error_unsupported");
     }
 
@@ -944,7 +1808,7 @@ mod tests {
         let allowed = vec!["fb2".to_string()];
         let data = vec![b'x'; 32];
         let zip_data = make_zip(&[("large.fb2", &data)]);
-        let err = extract_book_from_zip(&zip_data, &allowed, 16).unwrap_err();
+        let err = extract_book_from_zip(std::io::Cursor::new(&zip_data), &allowed, 16).unwrap_err();
         assert_eq!(err, "error_too_large");
     }
 
@@ -952,7 +1816,8 @@ mod tests {
     fn test_extract_book_from_zip_no_supported_file() {
         let allowed = vec!["fb2".to_string()];
         let zip_data = make_zip(&[("notes.txt", b"text only")]);
-        let err = extract_book_from_zip(&zip_data, &allowed, 10_000).unwrap_err();
+        let err =
+            extract_book_from_zip(std::io::Cursor::new(&zip_data), &allowed, 10_000).unwrap_err();
         assert_eq!(err, "error_unsupported");
     }
 
@@ -977,6 +1842,9 @@ mod tests {
             annotation: String::new(),
             docdate: String::new(),
             lang: "en".to_string(),
+            preview_text: String::new(),
+            word_count: 0,
+            page_count: 0,
             series_title: None,
             series_index: 0,
             has_cover: true,
@@ -1013,6 +1881,9 @@ mod tests {
             annotation: String::new(),
             docdate: String::new(),
             lang: "en".to_string(),
+            preview_text: String::new(),
+            word_count: 0,
+            page_count: 0,
             series_title: None,
             series_index: 0,
             has_cover: false,
@@ -1028,4 +1899,78 @@ mod tests {
         assert!(temp_book.exists());
         assert!(state_path.exists());
     }
+
+    #[test]
+    fn test_cleanup_abandoned_sessions_uses_last_activity() {
+        let dir = tempdir().unwrap();
+        let write_session = |id: &str, idle: chrono::Duration| {
+            let session = ChunkedSession {
+                original_filename: "big.pdf".to_string(),
+                size: 100,
+                user_id: 1,
+                created_at: (chrono::Utc::now() - chrono::Duration::days(3)).to_rfc3339(),
+                updated_at: (chrono::Utc::now() - idle).to_rfc3339(),
+            };
+            std::fs::write(chunked_part_path(dir.path(), id), b"partial").unwrap();
+            std::fs::write(
+                chunked_state_path(dir.path(), id),
+                serde_json::to_string(&session).unwrap(),
+            )
+            .unwrap();
+        };
+        write_session("aa", chrono::Duration::hours(2));
+        write_session("bb", chrono::Duration::seconds(5));
+
+        cleanup_abandoned_sessions(dir.path(), 3600);
+
+        assert!(!chunked_part_path(dir.path(), "aa").exists());
+        assert!(!chunked_state_path(dir.path(), "aa").exists());
+        assert!(chunked_part_path(dir.path(), "bb").exists());
+        assert!(chunked_state_path(dir.path(), "bb").exists());
+    }
+
+    #[test]
+    fn test_place_upload_strategies() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("upload_1.pdf");
+        for (strategy, placement) in [
+            (PublishStrategy::Copy, Placement::Copied),
+            (PublishStrategy::Hardlink, Placement::Linked),
+            (PublishStrategy::Move, Placement::Moved),
+        ] {
+            std::fs::write(&source, b"book").unwrap();
+            let dest = dir.path().join(format!("{strategy:?}.pdf"));
+            assert_eq!(place_upload(&source, &dest, strategy).unwrap(), placement);
+            assert_eq!(std::fs::read(&dest).unwrap(), b"book");
+            assert_eq!(source.exists(), placement != Placement::Moved);
+            assert!(!partial_publish_path(&dest).exists());
+
+            // Undoing the placement restores the staging file.
+            unplace_upload(&source, &dest, placement);
+            assert!(!dest.exists());
+            assert_eq!(std::fs::read(&source).unwrap(), b"book");
+        }
+    }
+
+    #[test]
+    fn test_place_upload_keeps_existing_and_cleans_up_on_error() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("upload_1.fb2");
+        let dest = dir.path().join("book.fb2");
+        std::fs::write(&source, b"new").unwrap();
+        std::fs::write(&dest, b"old").unwrap();
+        let err = place_upload(&source, &dest, PublishStrategy::Move).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"old");
+        assert!(source.exists());
+
+        // A missing staging file leaves nothing behind in the library.
+        let missing = dir.path().join("upload_2.fb2");
+        let dest = dir.path().join("other.fb2");
+        for strategy in [PublishStrategy::Move, PublishStrategy::Hardlink] {
+            assert!(place_upload(&missing, &dest, strategy).is_err());
+            assert!(!dest.exists());
+            assert!(!partial_publish_path(&dest).exists());
+        }
+    }
 }
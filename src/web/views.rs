@@ -5,7 +5,12 @@ use axum_extra::extract::cookie::{Cookie, CookieJar};
 use serde::{Deserialize, Serialize};
 
 use crate::db::models::{Author, Genre};
-use crate::db::queries::{authors, books, bookshelf, catalogs, genres, reading_positions, series};
+use crate::db::queries::books::BookSort;
+use crate::db::queries::{
+    MatchMode, authors, book_terms, books, bookshelf, cached, catalogs, downloads, fuzzy, genres,
+    reading_positions, recommendations, series, suggest,
+};
+use crate::fulltext;
 use crate::state::AppState;
 use crate::web::context::build_context;
 use crate::web::i18n;
@@ -21,7 +26,10 @@ pub use browse_handlers::*;
 pub use reader_handlers::*;
 pub use shared::*;
 
-use shared::{build_breadcrumbs, enrich_book, render, sanitize_internal_redirect, session_user_id};
+use shared::{
+    BOOKS_VIEW_COOKIE, build_breadcrumbs, enrich_book, enrich_books, fetch_grid_page, grid_listing,
+    render, sanitize_internal_redirect, session_user_id, sort_options,
+};
 
 #[cfg(test)]
 use bookshelf_handlers::parse_bookshelf_sort;
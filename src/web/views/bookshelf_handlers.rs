@@ -101,29 +101,38 @@ pub async fn bookshelf_toggle(
 // ── Bookshelf helpers ───────────────────────────────────────────────
 
 pub(super) fn parse_bookshelf_sort(sort: &str, dir: &str) -> (bookshelf::SortColumn, bool) {
-    let col = match sort {
-        "title" => bookshelf::SortColumn::Title,
-        "author" => bookshelf::SortColumn::Author,
-        _ => bookshelf::SortColumn::Date,
-    };
-    let ascending = dir == "asc";
-    (col, ascending)
+    (bookshelf::SortColumn::parse(sort), dir == "asc")
+}
+
+/// Sort, direction and read-state keys of a bookshelf request, with defaults.
+fn bookshelf_keys<'a>(
+    sort: &'a str,
+    dir: &'a str,
+    state: &str,
+) -> (&'a str, &'a str, bookshelf::ReadState) {
+    let sort = if sort.is_empty() { "date" } else { sort };
+    let dir = if dir.is_empty() { "desc" } else { dir };
+    (sort, dir, bookshelf::ReadState::parse(state))
 }
 
 const BOOKSHELF_BATCH: i32 = 30;
 
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn fetch_bookshelf_views(
     state: &AppState,
     user_id: i64,
     sort: &bookshelf::SortColumn,
     ascending: bool,
+    read_state: bookshelf::ReadState,
     limit: i32,
     offset: i32,
     lang: &str,
 ) -> Vec<BookView> {
-    let raw_books = bookshelf::get_by_user(&state.db, user_id, sort, ascending, limit, offset)
-        .await
-        .unwrap_or_default();
+    let raw_books = bookshelf::get_filtered(
+        &state.db, user_id, sort, ascending, read_state, limit, offset,
+    )
+    .await
+    .unwrap_or_default();
     let read_times = bookshelf::get_read_times(&state.db, user_id)
         .await
         .unwrap_or_default();
@@ -162,6 +171,9 @@ pub struct BookshelfPageParams {
     pub sort: String,
     #[serde(default)]
     pub dir: String,
+    /// Reading-progress filter: `unread`, `reading` or `finished`.
+    #[serde(default)]
+    pub state: String,
 }
 
 pub async fn bookshelf_page(
@@ -180,19 +192,10 @@ pub async fn bookshelf_page(
         None => return Err(StatusCode::UNAUTHORIZED),
     };
 
-    let sort_key = if params.sort.is_empty() {
-        "date"
-    } else {
-        &params.sort
-    };
-    let dir_key = if params.dir.is_empty() {
-        "desc"
-    } else {
-        &params.dir
-    };
+    let (sort_key, dir_key, read_state) = bookshelf_keys(&params.sort, &params.dir, &params.state);
     let (sort_col, ascending) = parse_bookshelf_sort(sort_key, dir_key);
 
-    let total = bookshelf::count_by_user(&state.db, user_id)
+    let total = bookshelf::count_filtered(&state.db, user_id, read_state)
         .await
         .unwrap_or(0);
 
@@ -201,6 +204,7 @@ pub async fn bookshelf_page(
         user_id,
         &sort_col,
         ascending,
+        read_state,
         BOOKSHELF_BATCH,
         0,
         &locale,
@@ -213,8 +217,13 @@ pub async fn bookshelf_page(
     ctx.insert("current_path", "/web/bookshelf");
     ctx.insert("sort", sort_key);
     ctx.insert("dir", dir_key);
+    ctx.insert("read_state", read_state.as_str());
     ctx.insert("has_more", &has_more);
     ctx.insert("batch_size", &BOOKSHELF_BATCH);
+    ctx.insert(
+        "can_share_shelf",
+        &(state.config.sharing.shelf_link_ttl_hours > 0),
+    );
 
     render(&state.tera, "web/bookshelf.html", &ctx)
 }
@@ -229,6 +238,8 @@ pub struct BookshelfCardsParams {
     pub sort: String,
     #[serde(default)]
     pub dir: String,
+    #[serde(default)]
+    pub state: String,
 }
 
 pub async fn bookshelf_cards(
@@ -245,19 +256,10 @@ pub async fn bookshelf_cards(
         None => return Err(StatusCode::UNAUTHORIZED),
     };
 
-    let sort_key = if params.sort.is_empty() {
-        "date"
-    } else {
-        &params.sort
-    };
-    let dir_key = if params.dir.is_empty() {
-        "desc"
-    } else {
-        &params.dir
-    };
+    let (sort_key, dir_key, read_state) = bookshelf_keys(&params.sort, &params.dir, &params.state);
     let (sort_col, ascending) = parse_bookshelf_sort(sort_key, dir_key);
 
-    let total = bookshelf::count_by_user(&state.db, user_id)
+    let total = bookshelf::count_filtered(&state.db, user_id, read_state)
         .await
         .unwrap_or(0);
 
@@ -266,6 +268,7 @@ pub async fn bookshelf_cards(
         user_id,
         &sort_col,
         ascending,
+        read_state,
         BOOKSHELF_BATCH,
         params.offset,
         &locale,
@@ -291,6 +294,129 @@ pub async fn bookshelf_cards(
     })))
 }
 
+// ── Bookshelf export ────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct BookshelfExportParams {
+    /// `csv` (default) or `json`.
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub sort: String,
+    #[serde(default)]
+    pub dir: String,
+    #[serde(default)]
+    pub state: String,
+}
+
+/// One exported bookshelf entry.
+#[derive(Serialize)]
+struct BookshelfExportRow {
+    id: i64,
+    title: String,
+    authors: Vec<String>,
+    series: Vec<String>,
+    format: String,
+    lang: String,
+    added: String,
+    progress_pct: Option<i32>,
+}
+
+/// Download the user's bookshelf as CSV or JSON, honoring the same sort and
+/// read-state parameters as the bookshelf page.
+pub async fn bookshelf_export(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<BookshelfExportParams>,
+) -> Response {
+    let Some(user_id) = session_user_id(&state, &jar) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let locale = jar
+        .get("lang")
+        .map(|c| c.value().to_string())
+        .unwrap_or_else(|| state.config.web.language.clone());
+    let (sort_key, dir_key, read_state) = bookshelf_keys(&params.sort, &params.dir, &params.state);
+    let (sort_col, ascending) = parse_bookshelf_sort(sort_key, dir_key);
+
+    let views = fetch_bookshelf_views(
+        &state,
+        user_id,
+        &sort_col,
+        ascending,
+        read_state,
+        i32::MAX,
+        0,
+        &locale,
+    )
+    .await;
+    let rows: Vec<BookshelfExportRow> = views
+        .into_iter()
+        .map(|v| BookshelfExportRow {
+            id: v.id,
+            title: v.title,
+            authors: v.authors.into_iter().map(|a| a.full_name).collect(),
+            series: v
+                .series_list
+                .into_iter()
+                .map(|s| match s.ser_no {
+                    n if n > 0 => format!("{} #{n}", s.ser_name),
+                    _ => s.ser_name,
+                })
+                .collect(),
+            format: v.format,
+            lang: v.lang,
+            added: v.read_time,
+            progress_pct: v.has_read_progress.then_some(v.read_progress_pct),
+        })
+        .collect();
+
+    let (content_type, ext, body) = if params.format == "json" {
+        (
+            "application/json",
+            "json",
+            serde_json::to_string_pretty(&rows).unwrap_or_default(),
+        )
+    } else {
+        let mut table = vec![
+            [
+                "id", "title", "authors", "series", "format", "lang", "added", "progress",
+            ]
+            .map(String::from)
+            .to_vec(),
+        ];
+        table.extend(rows.into_iter().map(|r| {
+            vec![
+                r.id.to_string(),
+                r.title,
+                r.authors.join("; "),
+                r.series.join("; "),
+                r.format,
+                r.lang,
+                r.added,
+                r.progress_pct.map(|p| p.to_string()).unwrap_or_default(),
+            ]
+        }));
+        (
+            "text/csv; charset=utf-8",
+            "csv",
+            crate::util::to_csv(&table),
+        )
+    };
+    (
+        StatusCode::OK,
+        [
+            (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+            (
+                axum::http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"bookshelf.{ext}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
 // ── Bookshelf clear handler ─────────────────────────────────────────
 
 #[derive(Deserialize)]
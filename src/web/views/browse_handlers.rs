@@ -1,13 +1,36 @@
 use super::*;
 
+/// Books shown in the home page "Popular" section.
+const HOME_POPULAR_ITEMS: i32 = 10;
+/// Cover cards per home dashboard shelf (favorite genres, random picks).
+const HOME_SHELF_ITEMS: i32 = 6;
+/// Favorite genres the "new in your genres" shelf is built from.
+const HOME_FAVORITE_GENRES: i32 = 3;
+/// Cover cards in the "readers also liked" section of a book page.
+const BOOK_RECOMMENDATIONS: i32 = 6;
+/// Type-ahead suggestions per group (titles, authors, series).
+const SUGGEST_LIMIT: i32 = 5;
+/// Shortest query that gets type-ahead suggestions.
+const SUGGEST_MIN_CHARS: usize = 2;
+
+/// GET /web — dashboard. Personal shelves (continue reading, new books in
+/// favorite genres) are shown to signed-in users only; popular and random
+/// picks are shown to everyone.
 pub async fn home(
     State(state): State<AppState>,
     jar: CookieJar,
+    Query(params): Query<HomeParams>,
 ) -> Result<Html<String>, StatusCode> {
     let mut ctx = build_context(&state, &jar, "home").await;
+    let locale = jar
+        .get("lang")
+        .map(|c| c.value().to_string())
+        .unwrap_or_else(|| state.config.web.language.clone());
+    let hide_doubles = state.config.opds.collapse_doubles();
+    let user_id = session_user_id(&state, &jar);
 
     if state.config.reader.enable
-        && let Some(user_id) = session_user_id(&state, &jar)
+        && let Some(user_id) = user_id
     {
         let recent = reading_positions::get_recent(&state.db, user_id, 8)
             .await
@@ -26,6 +49,57 @@ pub async fn home(
         ctx.insert("continue_reading", &continue_reading);
     }
 
+    if let Some(user_id) = user_id {
+        let favorite_ids = genres::get_favorite_ids(&state.db, user_id, HOME_FAVORITE_GENRES)
+            .await
+            .unwrap_or_default();
+        let raw_books =
+            books::get_recent_in_genres(&state.db, &favorite_ids, user_id, HOME_SHELF_ITEMS)
+                .await
+                .unwrap_or_default();
+        if !raw_books.is_empty() {
+            let mut favorite_genres = Vec::with_capacity(favorite_ids.len());
+            for genre_id in favorite_ids {
+                if let Ok(Some(genre)) = genres::get_by_id(&state.db, genre_id, &locale).await {
+                    favorite_genres.push(genre);
+                }
+            }
+            let genre_books = enrich_books(&state, &jar, raw_books, hide_doubles, &locale).await;
+            ctx.insert("favorite_genres", &favorite_genres);
+            ctx.insert("genre_books", &genre_books);
+        }
+
+        let next_books = series::next_unread_for_user(&state.db, user_id, HOME_SHELF_ITEMS, 0)
+            .await
+            .unwrap_or_default();
+        if !next_books.is_empty() {
+            let next_books = enrich_books(&state, &jar, next_books, hide_doubles, &locale).await;
+            ctx.insert("next_in_series", &next_books);
+        }
+    }
+
+    let period = downloads::popular_period(params.period.as_deref());
+    let popular: Vec<PopularItem> = books::get_popular(&state.db, period, HOME_POPULAR_ITEMS, 0)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(book, downloads)| PopularItem {
+            book_id: book.id,
+            title: book.title,
+            format: book.format,
+            downloads,
+        })
+        .collect();
+    ctx.insert("popular", &popular);
+    ctx.insert("popular_period", &period);
+    ctx.insert("popular_periods", &downloads::POPULAR_PERIODS);
+
+    let raw_books = books::get_random_list(&state.db, HOME_SHELF_ITEMS)
+        .await
+        .unwrap_or_default();
+    let random_books = enrich_books(&state, &jar, raw_books, hide_doubles, &locale).await;
+    ctx.insert("random_books", &random_books);
+
     render(&state.tera, "web/home.html", &ctx)
 }
 
@@ -38,16 +112,21 @@ pub async fn recent_books(
     let page = params.page.max(0);
     let max_items = state.config.opds.max_items as i32;
     let offset = page * max_items;
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
     let locale = jar
         .get("lang")
         .map(|c| c.value().to_string())
         .unwrap_or_else(|| state.config.web.language.clone());
 
-    let raw_books = books::get_recent_added(&state.db, max_items, offset, hide_doubles)
+    let kind = match (params.genre, params.show.as_deref()) {
+        (Some(genre_id), _) => books::RecentKind::Genre(genre_id),
+        (None, Some("updated")) => books::RecentKind::Updated,
+        _ => books::RecentKind::Added,
+    };
+    let raw_books = books::get_recent(&state.db, kind, max_items, offset, hide_doubles)
         .await
         .unwrap_or_default();
-    let total = books::count_recent_added(&state.db, hide_doubles)
+    let total = books::count_recent(&state.db, kind, hide_doubles)
         .await
         .unwrap_or(0);
 
@@ -89,11 +168,53 @@ pub async fn recent_books(
         .and_then(|value| value.as_str())
         .unwrap_or("Recently added");
 
+    let mut filters: Vec<SortOption> = [
+        ("added", books::RecentKind::Added, "/web/recent"),
+        (
+            "updated",
+            books::RecentKind::Updated,
+            "/web/recent?show=updated",
+        ),
+    ]
+    .into_iter()
+    .map(|(value, option, url)| SortOption {
+        value,
+        label: t["browse"][format!("recent_{value}")]
+            .as_str()
+            .unwrap_or(value)
+            .to_string(),
+        url: url.to_string(),
+        active: option == kind,
+    })
+    .collect();
+    let pagination_qs = match kind {
+        books::RecentKind::Added => String::new(),
+        books::RecentKind::Updated => "show=updated&".to_string(),
+        books::RecentKind::Genre(genre_id) => format!("genre={genre_id}&"),
+    };
+    let mut search_label = recent_label.to_string();
+    if let books::RecentKind::Genre(genre_id) = kind {
+        // New arrivals of one genre lead back to the genre's book list.
+        filters.clear();
+        if let Ok(Some(genre)) = genres::get_by_id(&state.db, genre_id, &locale).await {
+            search_label = format!("{recent_label}: {}", genre.subsection);
+            ctx.insert("back_label", &genre.subsection);
+            ctx.insert(
+                "back_url",
+                &format!("/web/search/books?type=g&q={genre_id}"),
+            );
+        }
+    }
+
     ctx.insert("books", &book_views);
-    ctx.insert("search_label", recent_label);
+    ctx.insert("search_label", &search_label);
+    ctx.insert("recent_filters", &filters);
     ctx.insert("pagination", &Pagination::new(page, max_items, total));
-    ctx.insert("pagination_qs", "");
-    ctx.insert("current_path", &format!("/web/recent?page={page}"));
+    ctx.insert("pagination_qs", &pagination_qs);
+    ctx.insert(
+        "current_path",
+        &format!("/web/recent?{pagination_qs}page={page}"),
+    );
 
     render(&state.tera, "web/books.html", &ctx)
 }
@@ -109,28 +230,48 @@ pub async fn catalogs(
     let offset = params.page * max_items;
 
     let subcatalogs = if cat_id == 0 {
-        catalogs::get_root_catalogs(&state.db)
-            .await
-            .unwrap_or_default()
+        cached::root_catalogs(&state).await.unwrap_or_default()
     } else {
         catalogs::get_children(&state.db, cat_id)
             .await
             .unwrap_or_default()
     };
 
-    let hide_doubles = state.config.opds.hide_doubles;
-    let (catalog_books, book_total) = if cat_id > 0 {
-        let bks = books::get_by_catalog(&state.db, cat_id, max_items, offset, hide_doubles)
+    let hide_doubles = state.config.opds.collapse_doubles();
+    let sort = BookSort::resolve(&BookSort::LISTING, params.sort.as_deref());
+    let deep = state.config.opds.catalog_deep(params.deep.as_deref());
+    let (catalog_books, book_total) = match (cat_id > 0, deep) {
+        (true, true) => {
+            let bks = books::get_by_catalog_tree(
+                &state.db,
+                cat_id,
+                max_items,
+                offset,
+                hide_doubles,
+                sort,
+            )
             .await
             .unwrap_or_default();
-        let cnt = books::count_by_catalog(&state.db, cat_id, hide_doubles)
-            .await
-            .unwrap_or(0);
-        (bks, cnt)
-    } else {
-        (vec![], 0)
+            let cnt = books::count_by_catalog_tree(&state.db, cat_id, hide_doubles)
+                .await
+                .unwrap_or(0);
+            (bks, cnt)
+        }
+        (true, false) => {
+            let bks =
+                books::get_by_catalog(&state.db, cat_id, max_items, offset, hide_doubles, sort)
+                    .await
+                    .unwrap_or_default();
+            let cnt = books::count_by_catalog(&state.db, cat_id, hide_doubles)
+                .await
+                .unwrap_or(0);
+            (bks, cnt)
+        }
+        (false, _) => (vec![], 0),
     };
+    let has_subcatalogs = !subcatalogs.is_empty();
 
+    let covers = &state.config.covers;
     let mut entries: Vec<CatalogEntry> = subcatalogs
         .iter()
         .map(|c| CatalogEntry {
@@ -141,6 +282,8 @@ pub async fn catalogs(
             title: None,
             format: None,
             authors_str: None,
+            has_mosaic: covers.show_covers
+                && crate::scanner::catalog_mosaic_path(&covers.covers_path, c.id).is_file(),
         })
         .collect();
 
@@ -161,27 +304,65 @@ pub async fn catalogs(
             title: Some(book.title.clone()),
             format: Some(book.format.clone()),
             authors_str: Some(authors_str),
+            has_mosaic: false,
         });
     }
 
     ctx.insert("entries", &entries);
+    // Keep an explicit ?deep= choice on pagination and drill-down links.
+    let deep_qs = match params.deep {
+        Some(_) => format!("deep={}&", u8::from(deep)),
+        None => String::new(),
+    };
+    let sort_base = format!("/web/catalogs?cat_id={cat_id}&{deep_qs}");
+    let mut pagination_qs = format!("cat_id={cat_id}&{deep_qs}");
+    if params.sort.is_some() {
+        pagination_qs.push_str(&format!("sort={}&", sort.as_str()));
+    }
     ctx.insert("cat_id", &cat_id);
-    ctx.insert("pagination_qs", &format!("cat_id={}&", cat_id));
+    ctx.insert("deep", &deep);
+    ctx.insert("deep_qs", &deep_qs);
+    ctx.insert("has_subcatalogs", &has_subcatalogs);
+    ctx.insert("pagination_qs", &pagination_qs);
+    ctx.insert("cfg_read_only", &state.config.server.read_only);
+    if book_total > 0 {
+        let locale = ctx
+            .get("locale")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&state.config.web.language)
+            .to_string();
+        ctx.insert(
+            "sort_options",
+            &sort_options(&state, &locale, &BookSort::LISTING, sort, &sort_base),
+        );
+    }
+
+    let is_superuser = ctx.get("is_superuser").and_then(|v| v.as_i64()) == Some(1);
+    if is_superuser && cat_id == 0 {
+        let hidden = catalogs::get_hidden(&state.db).await.unwrap_or_default();
+        ctx.insert("hidden_catalogs", &hidden);
+    }
+    if is_superuser && cat_id > 0 {
+        let hidden = catalogs::get_by_id(&state.db, cat_id)
+            .await
+            .ok()
+            .flatten()
+            .is_some_and(|c| c.cat_hidden != 0);
+        ctx.insert("current_cat_hidden", &hidden);
+    }
 
     if cat_id > 0 {
         let crumbs = build_breadcrumbs(&state, cat_id).await;
         if let Some(last) = crumbs.last() {
             ctx.insert("current_cat_name", &last.name);
-        }
-        if crumbs.len() > 1 {
-            let parent = &crumbs[crumbs.len() - 2];
             ctx.insert(
-                "parent_url",
-                &format!("/web/catalogs?cat_id={}", parent.cat_id.unwrap_or(0)),
+                "search_scope",
+                &books::SearchScope::Catalog(cat_id).as_param(),
             );
-            ctx.insert("parent_name", &parent.name);
-        } else {
-            ctx.insert("parent_url", "/web/catalogs");
+            ctx.insert("search_scope_name", &last.name);
+        }
+        if let [.., parent, _] = crumbs.as_slice() {
+            ctx.insert("current_parent_name", &parent.name);
         }
         ctx.insert("breadcrumbs", &crumbs);
     }
@@ -192,6 +373,27 @@ pub async fn catalogs(
     render(&state.tera, "web/catalogs.html", &ctx)
 }
 
+/// The next volume of a book's series once the signed-in user has finished
+/// the book.
+async fn next_after_finished(
+    state: &AppState,
+    jar: &CookieJar,
+    book_id: i64,
+) -> Option<crate::db::models::Book> {
+    let user_id = session_user_id(state, jar)?;
+    let position = reading_positions::get_position(&state.db, user_id, book_id)
+        .await
+        .ok()
+        .flatten()?;
+    if position.progress < crate::db::queries::bookshelf::FINISHED_PROGRESS {
+        return None;
+    }
+    series::next_in_series(&state.db, book_id)
+        .await
+        .ok()
+        .flatten()
+}
+
 pub async fn search_books(
     State(state): State<AppState>,
     jar: CookieJar,
@@ -205,17 +407,48 @@ pub async fn search_books(
     let search_target = match params.search_type.as_str() {
         "a" => "author",
         "s" => "series",
+        "t" => "text",
         _ => "title",
     };
     ctx.insert("search_target", search_target);
-    let max_items = state.config.opds.max_items as i32;
+
+    let scope = match params.search_type.as_str() {
+        "a" | "s" | "d" | "g" | "i" | "b" | "t" => None,
+        _ => params.scope.as_deref().and_then(books::SearchScope::parse),
+    };
+    // Scoped searches are plain paged lists; the cover grid has no scope filter.
+    let listing = grid_listing(&params.search_type, &params.q, state.config.search.translit)
+        .filter(|_| scope.is_none());
+    let view = params
+        .view
+        .clone()
+        .or_else(|| jar.get(BOOKS_VIEW_COOKIE).map(|c| c.value().to_string()))
+        .unwrap_or_default();
+    let mut grid_view = view == "grid" && listing.is_some();
+
+    // In grid view cards are loaded by cursor below; the queries in the match
+    // only provide labels and counts, so skip their rows.
+    let max_items = if grid_view {
+        0
+    } else {
+        state.config.opds.max_items as i32
+    };
     let offset = params.page * max_items;
 
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
+    let sort_choices: &[BookSort] = if params.search_type == "s" {
+        &BookSort::SERIES_LISTING
+    } else {
+        &BookSort::LISTING
+    };
+    let sort = BookSort::resolve(sort_choices, params.sort.as_deref());
+    let mut recommended = Vec::new();
+    let mut next_in_series = None;
+    let mut fuzzy_results = false;
     let (raw_books, total) = match params.search_type.as_str() {
         "a" => {
             let id: i64 = params.q.parse().unwrap_or(0);
-            let bks = books::get_by_author(&state.db, id, max_items, offset, hide_doubles)
+            let bks = books::get_by_author(&state.db, id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default();
             let cnt = books::count_by_author(&state.db, id, hide_doubles)
@@ -223,6 +456,8 @@ pub async fn search_books(
                 .unwrap_or(0);
             if let Ok(Some(author)) = authors::get_by_id(&state.db, id).await {
                 ctx.insert("search_label", &author.full_name);
+                ctx.insert("search_scope", &books::SearchScope::Author(id).as_param());
+                ctx.insert("search_scope_name", &author.full_name);
             }
             let t = i18n::get_locale(&state.translations, &locale);
             let label = t["nav"]["authors"].as_str().unwrap_or("Authors");
@@ -242,7 +477,7 @@ pub async fn search_books(
         }
         "s" => {
             let id: i64 = params.q.parse().unwrap_or(0);
-            let bks = books::get_by_series(&state.db, id, max_items, offset, hide_doubles)
+            let bks = books::get_by_series(&state.db, id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default();
             let cnt = books::count_by_series(&state.db, id, hide_doubles)
@@ -250,6 +485,8 @@ pub async fn search_books(
                 .unwrap_or(0);
             if let Ok(Some(ser)) = series::get_by_id(&state.db, id).await {
                 ctx.insert("search_label", &ser.ser_name);
+                ctx.insert("search_scope", &books::SearchScope::Series(id).as_param());
+                ctx.insert("search_scope_name", &ser.ser_name);
             }
             let t = i18n::get_locale(&state.translations, &locale);
             let label = t["nav"]["series"].as_str().unwrap_or("Series");
@@ -266,7 +503,7 @@ pub async fn search_books(
         }
         "g" => {
             let id: i64 = params.q.parse().unwrap_or(0);
-            let bks = books::get_by_genre(&state.db, id, max_items, offset, hide_doubles)
+            let bks = books::get_by_genre(&state.db, id, max_items, offset, hide_doubles, sort)
                 .await
                 .unwrap_or_default();
             let cnt = books::count_by_genre(&state.db, id, hide_doubles)
@@ -274,6 +511,7 @@ pub async fn search_books(
                 .unwrap_or(0);
             if let Ok(Some(genre)) = genres::get_by_id(&state.db, id, &locale).await {
                 ctx.insert("search_label", &genre.subsection);
+                ctx.insert("recent_url", &format!("/web/recent?genre={id}"));
                 // Back navigation to the genre's section
                 if let Some(section_id) = genre.section_id
                     && let Ok(Some(code)) = genres::get_section_code(&state.db, section_id).await
@@ -320,10 +558,16 @@ pub async fn search_books(
         }
         "b" => {
             let term = params.q.to_uppercase();
-            let bks =
-                books::search_by_title_prefix(&state.db, &term, max_items, offset, hide_doubles)
-                    .await
-                    .unwrap_or_default();
+            let bks = books::search_by_title_prefix(
+                &state.db,
+                &term,
+                max_items,
+                offset,
+                hide_doubles,
+                sort,
+            )
+            .await
+            .unwrap_or_default();
             let cnt = books::count_by_title_prefix(&state.db, &term, hide_doubles)
                 .await
                 .unwrap_or(0);
@@ -343,55 +587,114 @@ pub async fn search_books(
                 .map(|b| vec![b])
                 .unwrap_or_default();
             let cnt = bks.len() as i64;
+            if let Some(book) = bks.first()
+                && state.config.seo_enabled()
+            {
+                ctx.insert(
+                    "json_ld",
+                    &crate::web::seo::book_json_ld(&state, book, &locale).await,
+                );
+            }
+            if cnt > 0 {
+                recommended = recommendations::get_for_book(&state.db, id, BOOK_RECOMMENDATIONS)
+                    .await
+                    .unwrap_or_default();
+                next_in_series = next_after_finished(&state, &jar, id).await;
+            }
             (bks, cnt)
         }
-        _ => {
-            let term = params.q.to_uppercase();
-            let bks = books::search_by_title(&state.db, &term, max_items, offset, hide_doubles)
+        "t" if state.config.search.fulltext => {
+            let terms = fulltext::query_terms(&params.q);
+            let bks = book_terms::search(&state.db, &terms, max_items, offset)
                 .await
                 .unwrap_or_default();
-            let cnt = books::count_by_title_search(&state.db, &term, hide_doubles)
-                .await
-                .unwrap_or(0);
+            let cnt = book_terms::count(&state.db, &terms).await.unwrap_or(0);
             ctx.insert("search_label", &params.q);
             (bks, cnt)
         }
-    };
-
-    let user_id = session_user_id(&state, &jar);
-    let shelf_ids = if let Some(user_id) = user_id {
-        crate::db::queries::bookshelf::get_book_ids_for_user(&state.db, user_id)
+        _ => {
+            let term = params.q.to_uppercase();
+            let filter = books::SearchFilter {
+                scope,
+                ..Default::default()
+            };
+            let bks = books::search_by_title(
+                &state.db,
+                &term,
+                MatchMode::Contains,
+                max_items,
+                offset,
+                hide_doubles,
+                sort,
+                state.config.search.translit,
+                &filter,
+            )
             .await
-            .ok()
-    } else {
-        None
-    };
-    let raw_book_ids: Vec<i64> = raw_books.iter().map(|book| book.id).collect();
-    let read_progress = if let Some(user_id) = user_id {
-        reading_positions::get_progress_map(&state.db, user_id, &raw_book_ids)
+            .unwrap_or_default();
+            let cnt = books::count_by_title_search(
+                &state.db,
+                &term,
+                MatchMode::Contains,
+                hide_doubles,
+                state.config.search.translit,
+                &filter,
+            )
             .await
-            .unwrap_or_default()
-    } else {
-        std::collections::HashMap::new()
+            .unwrap_or(0);
+            ctx.insert("search_label", &params.q);
+            if let Some(scope) = scope
+                && let Some((name, url)) = scope_target(&state, scope).await
+            {
+                ctx.insert("search_scope", &scope.as_param());
+                ctx.insert("search_scope_name", &name);
+                ctx.insert("scope_terms", &params.q);
+                ctx.insert("back_label", &name);
+                ctx.insert("back_url", &url);
+            }
+            if cnt == 0 && params.page == 0 && state.config.search.fuzzy && scope.is_none() {
+                let similar = fuzzy::search_books(
+                    &state.db,
+                    &term,
+                    state.config.opds.max_items as i32,
+                    hide_doubles,
+                    state.config.search.fuzzy_threshold,
+                    state.pg_trgm_available,
+                )
+                .await
+                .unwrap_or_default();
+                fuzzy_results = !similar.is_empty();
+                let cnt = similar.len() as i64;
+                (similar, cnt)
+            } else {
+                (bks, cnt)
+            }
+        }
     };
 
-    let mut book_views = Vec::with_capacity(raw_books.len());
-    for book in raw_books {
-        let progress = read_progress.get(&book.id).copied();
-        book_views.push(
-            enrich_book(
-                &state,
-                book,
-                hide_doubles,
-                shelf_ids.as_ref(),
-                progress,
-                &locale,
-            )
-            .await,
-        );
+    // Similar matches come as a single ranked page, not a keyset listing.
+    if fuzzy_results {
+        grid_view = false;
+        ctx.insert("fuzzy_results", &true);
+    }
+    let (raw_books, next_cursor) = match listing.as_ref().filter(|_| grid_view) {
+        Some(listing) => fetch_grid_page(&state, listing, None).await,
+        None => (raw_books, None),
+    };
+    let book_views = enrich_books(&state, &jar, raw_books, hide_doubles, &locale).await;
+    if let Some(next) = next_in_series {
+        let next = enrich_books(&state, &jar, vec![next], hide_doubles, &locale).await;
+        ctx.insert("next_in_series", &next);
+    }
+    if !recommended.is_empty() {
+        let recommended = enrich_books(&state, &jar, recommended, hide_doubles, &locale).await;
+        ctx.insert("recommended_books", &recommended);
     }
 
-    let pagination = Pagination::new(params.page, max_items, total);
+    let pagination = Pagination::new(
+        params.page,
+        state.config.opds.max_items as i32,
+        if grid_view { 0 } else { total },
+    );
 
     let display_query = match params.search_type.as_str() {
         // Preserve original typed query for grouped author/series flows.
@@ -414,10 +717,43 @@ pub async fn search_books(
     if let Some(src_q) = params.src_q.as_deref().filter(|s| !s.trim().is_empty()) {
         pagination_qs.push_str(&format!("src_q={}&", urlencoding::encode(src_q)));
     }
+    if let Some(scope) = scope {
+        pagination_qs.push_str(&format!("scope={}&", scope.as_param()));
+    }
 
     let current_url = format!("/web/search/books?{}", pagination_qs);
+    if params.view.is_some() {
+        pagination_qs.push_str(&format!("view={}&", urlencoding::encode(&view)));
+    }
+    // Sorting applies to the paged list; the cover grid always runs in title order.
+    if !grid_view
+        && !fuzzy_results
+        && total > 0
+        && !matches!(params.search_type.as_str(), "d" | "i" | "t")
+    {
+        let sort_base = format!("/web/search/books?{pagination_qs}");
+        ctx.insert(
+            "sort_options",
+            &sort_options(&state, &locale, sort_choices, sort, &sort_base),
+        );
+    }
+    if params.sort.is_some() {
+        pagination_qs.push_str(&format!("sort={}&", sort.as_str()));
+    }
     ctx.insert("current_path", &current_url);
     ctx.insert("books", &book_views);
+    ctx.insert("total_books", &total);
+    ctx.insert("grid_available", &(listing.is_some() && !fuzzy_results));
+    ctx.insert("grid_view", &grid_view);
+    ctx.insert(
+        "grid_api_url",
+        &format!(
+            "/web/api/books?view=grid&type={}&q={}",
+            urlencoding::encode(&params.search_type),
+            urlencoding::encode(&params.q)
+        ),
+    );
+    ctx.insert("next_cursor", &next_cursor.unwrap_or_default());
     ctx.insert("pagination", &pagination);
     ctx.insert("search_type", &params.search_type);
     ctx.insert("search_terms", &display_query);
@@ -426,6 +762,91 @@ pub async fn search_books(
     render(&state.tera, "web/books.html", &ctx)
 }
 
+/// GET /web/api/books?view=grid&type=..&q=..&cursor=.. — next page of
+/// cover-grid cards for infinite scroll (AJAX JSON).
+pub async fn book_cards(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<BookCardsParams>,
+) -> Result<axum::Json<serde_json::Value>, StatusCode> {
+    if params.view != "grid" {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let Some(listing) = grid_listing(&params.search_type, &params.q, state.config.search.translit)
+    else {
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    let after = match params.cursor.as_deref().filter(|c| !c.is_empty()) {
+        Some(token) => Some(books::TitleCursor::decode(token).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+
+    let locale = jar
+        .get("lang")
+        .map(|c| c.value().to_string())
+        .unwrap_or_else(|| state.config.web.language.clone());
+    let (raw_books, next_cursor) = fetch_grid_page(&state, &listing, after.as_ref()).await;
+    let book_views = enrich_books(
+        &state,
+        &jar,
+        raw_books,
+        state.config.opds.collapse_doubles(),
+        &locale,
+    )
+    .await;
+
+    let mut ctx = build_context(&state, &jar, "books").await;
+    ctx.insert("books", &book_views);
+    let html = render(&state.tera, "web/_book_grid_cards.html", &ctx)?.0;
+
+    Ok(axum::Json(serde_json::json!({
+        "html": html,
+        "next_cursor": next_cursor,
+        "has_more": next_cursor.is_some()
+    })))
+}
+
+/// GET /web/api/suggest?q=.. — type-ahead suggestions for the header search
+/// box: titles, authors and series starting with the query (AJAX JSON).
+pub async fn suggest_json(
+    State(state): State<AppState>,
+    Query(params): Query<SuggestParams>,
+) -> axum::Json<serde_json::Value> {
+    let q = params.q.trim();
+    if q.chars().count() < SUGGEST_MIN_CHARS {
+        return axum::Json(serde_json::json!({
+            "query": q,
+            "titles": [],
+            "authors": [],
+            "series": []
+        }));
+    }
+
+    let group = |items: Result<Vec<suggest::Suggestion>, sqlx::Error>, search_type: &str| {
+        items
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| {
+                serde_json::json!({
+                    "id": s.id,
+                    "name": s.name,
+                    "url": format!("/web/search/books?type={search_type}&q={}", s.id),
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let titles = group(suggest::titles(&state.db, q, SUGGEST_LIMIT).await, "i");
+    let authors = group(suggest::authors(&state.db, q, SUGGEST_LIMIT).await, "a");
+    let series = group(suggest::series(&state.db, q, SUGGEST_LIMIT).await, "s");
+
+    axum::Json(serde_json::json!({
+        "query": q,
+        "titles": titles,
+        "authors": authors,
+        "series": series
+    }))
+}
+
 pub async fn books_browse(
     State(state): State<AppState>,
     jar: CookieJar,
@@ -434,8 +855,14 @@ pub async fn books_browse(
     let mut ctx = build_context(&state, &jar, "books").await;
     let split_items = state.config.opds.split_items as i64;
 
-    let prefix = params.chars.to_uppercase();
-    let groups = books::get_title_prefix_groups(&state.db, params.lang, &prefix)
+    // Without the alphabet menu only the first letters are offered, never nested prefixes.
+    let alphabet_menu = state.config.opds.alphabet_menu;
+    let prefix = if alphabet_menu {
+        params.chars.to_uppercase()
+    } else {
+        String::new()
+    };
+    let groups = cached::title_prefix_groups(&state, params.lang, &prefix)
         .await
         .unwrap_or_default();
 
@@ -444,7 +871,7 @@ pub async fn books_browse(
         .map(|(p, cnt)| PrefixGroup {
             prefix: p,
             count: cnt,
-            drill_deeper: cnt >= split_items,
+            drill_deeper: alphabet_menu && cnt >= split_items,
         })
         .collect();
 
@@ -467,8 +894,13 @@ pub async fn authors_browse(
     let mut ctx = build_context(&state, &jar, "authors").await;
     let split_items = state.config.opds.split_items as i64;
 
-    let prefix = params.chars.to_uppercase();
-    let groups = authors::get_name_prefix_groups(&state.db, params.lang, &prefix)
+    let alphabet_menu = state.config.opds.alphabet_menu;
+    let prefix = if alphabet_menu {
+        params.chars.to_uppercase()
+    } else {
+        String::new()
+    };
+    let groups = cached::author_prefix_groups(&state, params.lang, &prefix)
         .await
         .unwrap_or_default();
 
@@ -477,7 +909,7 @@ pub async fn authors_browse(
         .map(|(p, cnt)| PrefixGroup {
             prefix: p,
             count: cnt,
-            drill_deeper: cnt >= split_items,
+            drill_deeper: alphabet_menu && cnt >= split_items,
         })
         .collect();
 
@@ -501,8 +933,13 @@ pub async fn series_browse(
     let mut ctx = build_context(&state, &jar, "series").await;
     let split_items = state.config.opds.split_items as i64;
 
-    let prefix = params.chars.to_uppercase();
-    let groups = series::get_name_prefix_groups(&state.db, params.lang, &prefix)
+    let alphabet_menu = state.config.opds.alphabet_menu;
+    let prefix = if alphabet_menu {
+        params.chars.to_uppercase()
+    } else {
+        String::new()
+    };
+    let groups = cached::series_prefix_groups(&state, params.lang, &prefix)
         .await
         .unwrap_or_default();
 
@@ -511,7 +948,7 @@ pub async fn series_browse(
         .map(|(p, cnt)| PrefixGroup {
             prefix: p,
             count: cnt,
-            drill_deeper: cnt >= split_items,
+            drill_deeper: alphabet_menu && cnt >= split_items,
         })
         .collect();
 
@@ -538,26 +975,38 @@ pub async fn genres(
         .map(|c| c.value().to_string())
         .unwrap_or_else(|| state.config.web.language.clone());
 
+    // Book counts come from the counters table, refreshed after each scan.
+    let counts = cached::item_counts(&state).await.unwrap_or_default();
     match params.section {
         None => {
-            let sections = genres::get_sections_with_counts(&state.db, &locale)
+            let sections: Vec<(String, String, i64)> = cached::genre_sections(&state, &locale)
                 .await
-                .unwrap_or_default();
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(code, name)| {
+                    let count = counts.section(&code);
+                    (code, name, count)
+                })
+                .filter(|(_, _, count)| *count > 0)
+                .collect();
             ctx.insert("sections", &sections);
             ctx.insert("is_top_level", &true);
         }
         Some(ref section_code) => {
-            let subsections = genres::get_by_section_with_counts(&state.db, section_code, &locale)
+            let subsections = cached::genres_in_section(&state, section_code, &locale)
                 .await
                 .unwrap_or_default();
             // Extract translated section name from the first genre
             let section_name = subsections
                 .first()
-                .map(|(g, _)| g.section.clone())
+                .map(|g| g.section.clone())
                 .unwrap_or_else(|| section_code.clone());
+            let hide_empty = state.config.opds.hide_empty;
             let items: Vec<serde_json::Value> = subsections
                 .into_iter()
-                .map(|(g, cnt)| {
+                .map(|g| (counts.genre(g.id), g))
+                .filter(|(cnt, _)| !hide_empty || *cnt > 0)
+                .map(|(cnt, g)| {
                     serde_json::json!({
                         "id": g.id,
                         "subsection": g.subsection,
@@ -587,14 +1036,31 @@ pub async fn search_authors(
     let offset = params.page * max_items;
 
     let term = params.q.to_uppercase();
-    let items = authors::search_by_name(&state.db, &term, max_items, offset)
+    let mode = MatchMode::from_search_type(&params.search_type);
+    let translit = state.config.search.translit;
+    let mut items = authors::search_by_name(&state.db, &term, mode, max_items, offset, translit)
         .await
         .unwrap_or_default();
-    let total = authors::count_by_name_search(&state.db, &term)
+    let mut total = authors::count_by_name_search(&state.db, &term, mode, translit)
         .await
         .unwrap_or(0);
+    if total == 0 && params.page == 0 && state.config.search.fuzzy {
+        items = fuzzy::search_authors(
+            &state.db,
+            &term,
+            max_items,
+            state.config.search.fuzzy_threshold,
+            state.pg_trgm_available,
+        )
+        .await
+        .unwrap_or_default();
+        total = items.len() as i64;
+        if total > 0 {
+            ctx.insert("fuzzy_results", &true);
+        }
+    }
 
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
     let mut enriched: Vec<serde_json::Value> = Vec::new();
     for author in &items {
         let book_count = books::count_by_author(&state.db, author.id, hide_doubles)
@@ -645,7 +1111,7 @@ pub async fn search_series(
         .await
         .unwrap_or(0);
 
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
     let mut enriched: Vec<serde_json::Value> = Vec::new();
     for ser in &items {
         let book_count = books::count_by_series(&state.db, ser.id, hide_doubles)
@@ -695,11 +1161,11 @@ pub async fn authors_list_by_prefix(
         authors::get_by_lang_code_prefix(&state.db, params.lang, &prefix, max_items, offset)
             .await
             .unwrap_or_default();
-    let total = authors::count_by_lang_code_prefix(&state.db, params.lang, &prefix)
+    let total = cached::author_count(&state, params.lang, &prefix)
         .await
         .unwrap_or(0);
 
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
     let mut enriched: Vec<serde_json::Value> = Vec::new();
     for author in &items {
         let book_count = books::count_by_author(&state.db, author.id, hide_doubles)
@@ -744,11 +1210,11 @@ pub async fn series_list_by_prefix(
     let items = series::get_by_lang_code_prefix(&state.db, params.lang, &prefix, max_items, offset)
         .await
         .unwrap_or_default();
-    let total = series::count_by_lang_code_prefix(&state.db, params.lang, &prefix)
+    let total = cached::series_count(&state, params.lang, &prefix)
         .await
         .unwrap_or(0);
 
-    let hide_doubles = state.config.opds.hide_doubles;
+    let hide_doubles = state.config.opds.collapse_doubles();
     let mut enriched: Vec<serde_json::Value> = Vec::new();
     for ser in &items {
         let book_count = books::count_by_series(&state.db, ser.id, hide_doubles)
@@ -789,3 +1255,34 @@ pub async fn set_language(
     let redirect = sanitize_internal_redirect(params.redirect.as_deref());
     (jar, Redirect::to(redirect))
 }
+
+/// POST /web/api/theme — remember the theme in a cookie and, for signed-in
+/// users, in their account (AJAX JSON).
+pub async fn set_theme(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    axum::Json(body): axum::Json<SetThemeRequest>,
+) -> Response {
+    if !crate::web::context::is_theme_mode(&body.theme) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    if let Some(user_id) = session_user_id(&state, &jar) {
+        let secret = state.config.server.session_secret.as_bytes();
+        if !crate::web::context::validate_csrf(&jar, secret, &body.csrf_token) {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        if let Err(e) =
+            crate::db::queries::users::update_theme(&state.db, user_id, &body.theme).await
+        {
+            tracing::error!("Failed to save theme for user {user_id}: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    let cookie = Cookie::build((crate::web::context::THEME_COOKIE, body.theme))
+        .path("/")
+        .max_age(time::Duration::days(365))
+        .build();
+    (jar.add(cookie), axum::Json(serde_json::json!({"ok": true}))).into_response()
+}
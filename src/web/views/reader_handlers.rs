@@ -8,21 +8,65 @@ pub async fn web_download(
     State(state): State<AppState>,
     jar: CookieJar,
     Path((book_id, zip_flag)): Path<(i64, i32)>,
+    request: axum::extract::Request,
 ) -> Response {
     let book = match books::get_by_id(&state.db, book_id).await {
         Ok(Some(b)) => b,
         Ok(None) => return (StatusCode::NOT_FOUND, "Book not found").into_response(),
         Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
     };
+    let book = crate::opds::download::with_available_copy(&state, book).await;
 
     let root = &state.config.library.root_path;
+    let wants_zip = zip_flag == 1 && !crate::opds::v1::xml::is_nozip_format(&book.format);
+    let format = crate::opds::v1::xml::download_format(&book.format);
+    let download_name = crate::opds::download::download_filename(&state, &book, format).await;
+    let user_agent = crate::opds::download::client_user_agent(&state, request.headers());
+    let secret = state.config.server.session_secret.as_bytes();
+    let user_id = jar
+        .get("session")
+        .and_then(|c| crate::web::auth::verify_session(c.value(), secret));
+
+    if state.config.low_memory()
+        && !wants_zip
+        && let Some(response) =
+            crate::opds::download::stream_book_file(root, &book, &download_name, request).await
+    {
+        crate::opds::download::record_download(&state, user_id, book_id, user_agent.as_deref())
+            .await;
+        return response;
+    }
+
+    if wants_zip {
+        return match crate::opds::download::zipped_book_response(&state, &book, &download_name)
+            .await
+        {
+            Ok(response) => {
+                crate::opds::download::record_download(
+                    &state,
+                    user_id,
+                    book_id,
+                    user_agent.as_deref(),
+                )
+                .await;
+                response
+            }
+            Err(e) => {
+                tracing::warn!("Failed to read book {}: {e}", book_id);
+                (StatusCode::NOT_FOUND, "File not found").into_response()
+            }
+        };
+    }
 
-    let data = match crate::opds::download::read_book_file(
+    let data = match crate::opds::download::load_book_file(
         root,
         &book.path,
         &book.filename,
         book.cat_type,
-    ) {
+        crate::scanner::codepage_encoding(&state.config.library.zip_codepage),
+    )
+    .await
+    {
         Ok(d) => d,
         Err(e) => {
             tracing::warn!("Failed to read book {}: {e}", book_id);
@@ -30,31 +74,11 @@ pub async fn web_download(
         }
     };
 
-    // Fire-and-forget bookshelf tracking via session cookie
-    let secret = state.config.server.session_secret.as_bytes();
-    if let Some(user_id) = jar
-        .get("session")
-        .and_then(|c| crate::web::auth::verify_session(c.value(), secret))
-    {
-        let _ = bookshelf::upsert(&state.db, user_id, book_id).await;
-    }
+    // Fire-and-forget bookshelf and download history tracking via session cookie
+    crate::opds::download::record_download(&state, user_id, book_id, user_agent.as_deref()).await;
 
-    let download_name =
-        crate::opds::download::title_to_filename(&book.title, &book.format, &book.filename);
-    let mime = crate::opds::v1::xml::mime_for_format(&book.format);
-
-    if zip_flag == 1 && !crate::opds::v1::xml::is_nozip_format(&book.format) {
-        match crate::opds::download::wrap_in_zip(&book.filename, &data) {
-            Ok(zipped) => {
-                let zip_name = format!("{download_name}.zip");
-                let zip_mime = crate::opds::v1::xml::mime_for_zip(&book.format);
-                crate::opds::download::file_response(&zipped, &zip_name, &zip_mime)
-            }
-            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "ZIP error").into_response(),
-        }
-    } else {
-        crate::opds::download::file_response(&data, &download_name, mime)
-    }
+    let mime = crate::opds::download::download_mime(format, &data);
+    crate::opds::download::file_response(&data, &download_name, &mime)
 }
 
 // ── Reader ─────────────────────────────────────────────────────────
@@ -148,13 +172,13 @@ pub async fn web_reader(
         .map(|c| c.value().to_string())
         .unwrap_or_else(|| state.config.web.language.clone());
     let t = crate::web::i18n::get_locale(&state.translations, &locale);
-    let theme = &state.config.web.theme;
+    let theme = crate::web::context::request_theme_mode(&state, &jar).await;
 
     let mut ctx = tera::Context::new();
     ctx.insert("t", t);
     ctx.insert("locale", &locale);
-    ctx.insert("default_theme", theme);
-    ctx.insert("app_title", &state.config.opds.title);
+    crate::web::context::insert_theme(&mut ctx, &theme);
+    crate::web::context::insert_branding(&mut ctx, &state.config);
     ctx.insert("version", env!("CARGO_PKG_VERSION"));
     ctx.insert("book_id", &book.id);
     ctx.insert("book_title", &book.title);
@@ -197,14 +221,18 @@ pub async fn web_read_inline(
         Ok(None) => return (StatusCode::NOT_FOUND, "Book not found").into_response(),
         Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
     };
+    let book = crate::opds::download::with_available_copy(&state, book).await;
 
     let root = &state.config.library.root_path;
-    let data = match crate::opds::download::read_book_file(
+    let data = match crate::opds::download::load_book_file(
         root,
         &book.path,
         &book.filename,
         book.cat_type,
-    ) {
+        crate::scanner::codepage_encoding(&state.config.library.zip_codepage),
+    )
+    .await
+    {
         Ok(d) => d,
         Err(e) => {
             tracing::warn!("Failed to read book {}: {e}", book_id);
@@ -218,27 +246,88 @@ pub async fn web_read_inline(
         .get("session")
         .and_then(|c| crate::web::auth::verify_session(c.value(), secret))
     {
-        let _ = bookshelf::upsert(&state.db, user_id, book_id).await;
+        crate::opds::download::auto_shelve(&state, user_id, book_id).await;
     }
 
     let mime = crate::opds::v1::xml::mime_for_format(&book.format);
-    let filename =
-        crate::opds::download::title_to_filename(&book.title, &book.format, &book.filename);
-    let content_disposition = format!("inline; filename=\"{filename}\"");
+    let filename = crate::opds::download::download_filename(&state, &book, &book.format).await;
+    crate::opds::download::file_response_with(&data, &filename, mime, "inline")
+}
 
-    (
-        StatusCode::OK,
-        [
-            (
-                axum::http::header::CONTENT_TYPE,
-                format!("{mime}; name=\"{filename}\""),
-            ),
-            (axum::http::header::CONTENT_DISPOSITION, content_disposition),
-            (axum::http::header::CONTENT_LENGTH, data.len().to_string()),
-        ],
-        data,
-    )
-        .into_response()
+// ── Audiobooks ────────────────────────────────────────────────────
+
+/// GET /web/listen/:book_id — audio player page for an audiobook
+pub async fn web_listen(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(book_id): Path<i64>,
+) -> Response {
+    let book = match books::get_by_id(&state.db, book_id).await {
+        Ok(Some(b)) => b,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Book not found").into_response(),
+        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "DB error").into_response(),
+    };
+    if !crate::audio::is_audio_format(&book.format) {
+        return (StatusCode::BAD_REQUEST, "Not an audiobook").into_response();
+    }
+
+    let tracks: Vec<serde_json::Value> =
+        crate::audio::book_tracks(&state.config.library.root_path, &book)
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                serde_json::json!({
+                    "url": format!("/web/audio/{book_id}/{i}"),
+                    "name": crate::vfs::decode_name(path.file_name().unwrap_or_default()),
+                })
+            })
+            .collect();
+    let audio = crate::db::queries::book_audio::get_for_book(&state.db, book_id)
+        .await
+        .ok()
+        .flatten();
+    let book_authors = authors::get_for_book(&state.db, book.id)
+        .await
+        .unwrap_or_default();
+    let authors_str: String = book_authors
+        .iter()
+        .map(|a| a.full_name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut ctx = build_context(&state, &jar, "books").await;
+    ctx.insert("book", &book);
+    ctx.insert("book_authors", &authors_str);
+    ctx.insert("tracks", &tracks);
+    ctx.insert(
+        "narrator",
+        &audio
+            .as_ref()
+            .map(|a| a.narrator.as_str())
+            .unwrap_or_default(),
+    );
+    ctx.insert(
+        "duration",
+        &audio
+            .as_ref()
+            .filter(|a| a.duration > 0)
+            .map(|a| crate::audio::format_duration(a.duration))
+            .unwrap_or_default(),
+    );
+
+    match render(&state.tera, "web/listen.html", &ctx) {
+        Ok(html) => html.into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+/// GET /web/audio/:book_id/:track — stream an audiobook track
+pub async fn web_audio_track(
+    State(state): State<AppState>,
+    Path((book_id, track)): Path<(i64, usize)>,
+    request: axum::extract::Request,
+) -> Response {
+    crate::opds::download::serve_audio_track(&state, book_id, track, request).await
 }
 
 // ── Reading Position API ──────────────────────────────────────────
@@ -11,11 +11,27 @@ pub struct BookView {
     pub size: i64,
     pub lang: String,
     pub annotation: String,
+    /// `annotation` rendered from markdown; already sanitized.
+    pub annotation_html: String,
+    /// First lines of the book text, one paragraph per line.
+    pub preview_text: String,
     pub docdate: String,
+    /// Counted or estimated pages; 0 when the length is unknown.
+    pub pages: u32,
+    /// Estimated reading time, split for display; both 0 when unknown.
+    pub reading_hours: u32,
+    pub reading_mins: u32,
     pub cover: i32,
+    /// Main and second colour of the cover (`#rrggbb`, the second falling
+    /// back to the main one) for a placeholder shown while it loads; empty
+    /// when unknown.
+    pub cover_tint: String,
+    pub cover_accent: String,
     pub cat_type: i32,
     pub show_zip: bool,
     pub doubles: i64,
+    /// Other formats of the same work (`opds.group_formats`).
+    pub other_formats: Vec<FormatLink>,
     pub authors: Vec<Author>,
     pub genres: Vec<Genre>,
     pub series_list: Vec<SeriesEntry>,
@@ -25,6 +41,14 @@ pub struct BookView {
     pub read_time: String,
 }
 
+/// Download of another format of a work, shown on its card.
+#[derive(Debug, Serialize)]
+pub struct FormatLink {
+    pub id: i64,
+    pub format: String,
+    pub show_zip: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SeriesEntry {
     pub id: i64,
@@ -41,6 +65,8 @@ pub struct CatalogEntry {
     pub title: Option<String>,
     pub format: Option<String>,
     pub authors_str: Option<String>,
+    /// A cover mosaic was generated for this catalog.
+    pub has_mosaic: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -56,6 +82,14 @@ pub struct Breadcrumb {
     pub cat_id: Option<i64>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct SortOption {
+    pub value: &'static str,
+    pub label: String,
+    pub url: String,
+    pub active: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ContinueReadingItem {
     pub book_id: i64,
@@ -65,13 +99,31 @@ pub struct ContinueReadingItem {
     pub updated_at: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct PopularItem {
+    pub book_id: i64,
+    pub title: String,
+    pub format: String,
+    pub downloads: i64,
+}
+
 // ── Query parameter structs ─────────────────────────────────────────
 
+#[derive(Deserialize)]
+pub struct HomeParams {
+    /// Popular books window in days (`30` or `90`).
+    pub period: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct CatalogsParams {
     pub cat_id: Option<i64>,
     #[serde(default)]
     pub page: i32,
+    /// `1` lists books from the whole subtree, `0` only from this catalog.
+    pub deep: Option<String>,
+    /// Book order (`title`, `recent`, `series`, `size`, `year`, `rating`).
+    pub sort: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -107,6 +159,33 @@ pub struct SearchBooksParams {
     pub src_q: Option<String>,
     #[serde(default)]
     pub page: i32,
+    /// `list` or `grid`; falls back to the `books_view` cookie.
+    #[serde(default)]
+    pub view: Option<String>,
+    /// Book order (`title`, `recent`, `series`, `size`, `year`, `rating`).
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// Limits a title search to a catalog, author or series (`c:ID`, `a:ID`, `s:ID`).
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SuggestParams {
+    #[serde(default)]
+    pub q: String,
+}
+
+#[derive(Deserialize)]
+pub struct BookCardsParams {
+    #[serde(default = "default_grid_view")]
+    pub view: String,
+    #[serde(rename = "type", default = "default_m")]
+    pub search_type: String,
+    #[serde(default)]
+    pub q: String,
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -125,10 +204,23 @@ pub struct SetLanguageParams {
     pub redirect: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct SetThemeRequest {
+    pub theme: String,
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
 #[derive(Deserialize)]
 pub struct RecentBooksParams {
     #[serde(default)]
     pub page: i32,
+    /// `updated` lists books whose file changed instead of new arrivals.
+    #[serde(default)]
+    pub show: Option<String>,
+    /// Only new arrivals in this genre.
+    #[serde(default)]
+    pub genre: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -141,6 +233,30 @@ pub(super) fn default_m() -> String {
     "m".to_string()
 }
 
+fn default_grid_view() -> String {
+    "grid".to_string()
+}
+
+/// Cookie remembering the list/grid choice on book result pages.
+pub(super) const BOOKS_VIEW_COOKIE: &str = "books_view";
+
+/// Keyset-paginated listing behind a book search type, if it has one.
+/// Series (ordered by number), duplicates and single-book lookups keep page numbers.
+pub(super) fn grid_listing(
+    search_type: &str,
+    q: &str,
+    translit: bool,
+) -> Option<books::BookListing> {
+    match search_type {
+        "a" => Some(books::BookListing::Author(q.parse().unwrap_or(0))),
+        "g" => Some(books::BookListing::Genre(q.parse().unwrap_or(0))),
+        "b" => Some(books::BookListing::TitlePrefix(q.to_uppercase())),
+        "s" | "d" | "i" | "t" => None,
+        _ if translit => Some(books::BookListing::TranslitTitleSearch(q.to_uppercase())),
+        _ => Some(books::BookListing::TitleSearch(q.to_uppercase())),
+    }
+}
+
 pub(super) fn sanitize_internal_redirect(path: Option<&str>) -> &str {
     path.filter(|value| value.starts_with('/') && !value.starts_with("//") && !value.contains('\\'))
         .unwrap_or("/web")
@@ -179,6 +295,26 @@ pub(super) async fn enrich_book(
     };
 
     let is_nozip = book.format == "epub" || book.format == "mobi";
+    let other_formats = if state.config.opds.group_formats {
+        books::get_other_formats(&state.db, book.id)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, format)| FormatLink {
+                id,
+                show_zip: format != "epub" && format != "mobi",
+                format,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let colors = crate::covers::palette_colors(&book.cover_palette);
+    let cover_tint = colors.first().copied().unwrap_or_default().to_string();
+    let cover_accent = colors.get(1).map_or(cover_tint.clone(), |c| c.to_string());
+    let pages = book.pages();
+    let reading_minutes = book.reading_minutes();
 
     let read_progress_pct = read_progress
         .map(|value| (value * 100.0).round() as i32)
@@ -191,12 +327,20 @@ pub(super) async fn enrich_book(
         format: book.format.clone(),
         size: book.size,
         lang: book.lang,
+        annotation_html: crate::markdown::to_html(&book.annotation),
         annotation: book.annotation,
+        preview_text: book.preview_text,
         docdate: book.docdate,
+        pages,
+        reading_hours: reading_minutes / 60,
+        reading_mins: reading_minutes % 60,
         cover: book.cover,
+        cover_tint,
+        cover_accent,
         cat_type: book.cat_type,
         show_zip: !is_nozip,
         doubles,
+        other_formats,
         authors: book_authors,
         genres: book_genres,
         series_list: book_series
@@ -214,6 +358,77 @@ pub(super) async fn enrich_book(
     }
 }
 
+/// Enrich a page of books with authors, genres, series and the current
+/// user's bookshelf and reading progress.
+pub(super) async fn enrich_books(
+    state: &AppState,
+    jar: &CookieJar,
+    raw_books: Vec<crate::db::models::Book>,
+    hide_doubles: bool,
+    locale: &str,
+) -> Vec<BookView> {
+    let user_id = session_user_id(state, jar);
+    let shelf_ids = if let Some(user_id) = user_id {
+        crate::db::queries::bookshelf::get_book_ids_for_user(&state.db, user_id)
+            .await
+            .ok()
+    } else {
+        None
+    };
+    let raw_book_ids: Vec<i64> = raw_books.iter().map(|book| book.id).collect();
+    let read_progress = if let Some(user_id) = user_id {
+        reading_positions::get_progress_map(&state.db, user_id, &raw_book_ids)
+            .await
+            .unwrap_or_default()
+    } else {
+        std::collections::HashMap::new()
+    };
+
+    let mut book_views = Vec::with_capacity(raw_books.len());
+    for book in raw_books {
+        let progress = read_progress.get(&book.id).copied();
+        book_views.push(
+            enrich_book(
+                state,
+                book,
+                hide_doubles,
+                shelf_ids.as_ref(),
+                progress,
+                locale,
+            )
+            .await,
+        );
+    }
+    book_views
+}
+
+/// One page of grid cards: the books and the cursor of the next page.
+pub(super) async fn fetch_grid_page(
+    state: &AppState,
+    listing: &books::BookListing,
+    after: Option<&books::TitleCursor>,
+) -> (Vec<crate::db::models::Book>, Option<String>) {
+    let limit = state.config.opds.max_items as i32;
+    // Ask for one extra row to know whether another page exists.
+    let mut page = books::list_after(
+        &state.db,
+        listing,
+        after,
+        limit + 1,
+        state.config.opds.collapse_doubles(),
+    )
+    .await
+    .unwrap_or_default();
+    let next_cursor = if page.len() > limit as usize {
+        page.truncate(limit as usize);
+        page.last()
+            .map(|book| books::TitleCursor::from_book(book).encode())
+    } else {
+        None
+    };
+    (page, next_cursor)
+}
+
 // ── Helper: render template or return error ─────────────────────────
 
 pub(super) fn render(
@@ -227,25 +442,70 @@ pub(super) fn render(
     })
 }
 
+// ── Helper: book listing sort ───────────────────────────────────────
+
+/// Entries of the sort dropdown; `base_url` must end with `?` or `&`.
+pub(super) fn sort_options(
+    state: &AppState,
+    locale: &str,
+    options: &[BookSort],
+    active: BookSort,
+    base_url: &str,
+) -> Vec<SortOption> {
+    let t = i18n::get_locale(&state.translations, locale);
+    options
+        .iter()
+        .map(|sort| {
+            let value = sort.as_str();
+            SortOption {
+                value,
+                label: t["book"][format!("sort_{value}")]
+                    .as_str()
+                    .unwrap_or(value)
+                    .to_string(),
+                url: format!("{base_url}sort={value}"),
+                active: *sort == active,
+            }
+        })
+        .collect()
+}
+
 // ── Helper: build breadcrumbs for catalog hierarchy ─────────────────
 
 pub(super) async fn build_breadcrumbs(state: &AppState, cat_id: i64) -> Vec<Breadcrumb> {
-    let mut crumbs = Vec::new();
-    let mut current = Some(cat_id);
-    while let Some(id) = current {
-        match catalogs::get_by_id(&state.db, id).await {
-            Ok(Some(cat)) => {
-                crumbs.push(Breadcrumb {
-                    name: cat.cat_name.clone(),
-                    cat_id: Some(cat.id),
-                });
-                current = cat.parent_id;
-            }
-            _ => break,
-        }
+    catalogs::get_ancestors(&state.db, cat_id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|cat| Breadcrumb {
+            name: cat.cat_name,
+            cat_id: Some(cat.id),
+        })
+        .collect()
+}
+
+/// Name and page URL of the catalog, author or series a search is limited to.
+pub(super) async fn scope_target(
+    state: &AppState,
+    scope: books::SearchScope,
+) -> Option<(String, String)> {
+    match scope {
+        books::SearchScope::Catalog(id) => catalogs::get_by_id(&state.db, id)
+            .await
+            .ok()
+            .flatten()
+            .map(|c| (c.cat_name, format!("/web/catalogs?cat_id={id}"))),
+        books::SearchScope::Author(id) => authors::get_by_id(&state.db, id)
+            .await
+            .ok()
+            .flatten()
+            .map(|a| (a.full_name, format!("/web/search/books?type=a&q={id}"))),
+        books::SearchScope::Series(id) => series::get_by_id(&state.db, id)
+            .await
+            .ok()
+            .flatten()
+            .map(|s| (s.ser_name, format!("/web/search/books?type=s&q={id}"))),
     }
-    crumbs.reverse();
-    crumbs
 }
 
 // ═══════════════════════════════════════════════════════════════════
@@ -3,7 +3,7 @@ mod tests {
     use super::*;
     use crate::config::{
         Config, CoversConfig, DatabaseConfig, LibraryConfig, OpdsConfig, ReaderConfig,
-        ScannerConfig, ServerConfig, UploadConfig, WebConfig,
+        ScannerConfig, ServerConfig, ServerProfile, UploadConfig, WebConfig,
     };
     use crate::db::create_test_pool;
     use crate::db::models::CatType;
@@ -22,7 +22,18 @@ mod tests {
                 log_level: "info".to_string(),
                 session_secret: "test-secret".to_string(),
                 session_ttl_hours: 24,
+                session_sliding: true,
+                remember_me_ttl_hours: 720,
                 base_url: String::new(),
+                trust_proxy_headers: false,
+                profile: ServerProfile::Default,
+                read_only: false,
+                request_timeout_secs: 60,
+                opds_body_limit_kb: 64,
+                web_body_limit_kb: 256,
+                admin_body_limit_kb: 2048,
+                min_free_space_mb: 0,
+                access_log_path: None,
             },
             library: LibraryConfig {
                 root_path,
@@ -33,16 +44,25 @@ mod tests {
                 scan_zip: true,
                 zip_codepage: "cp866".to_string(),
                 inpx_enable: false,
+                filename_patterns: Vec::new(),
+                audiobooks: false,
+                detect_lang: true,
+                detect_lang_confidence: 0.3,
             },
             covers: CoversConfig {
                 covers_path: PathBuf::from("/tmp/covers"),
                 cover_max_dimension_px: 600,
                 cover_jpeg_quality: 85,
                 show_covers: true,
+                signed_urls: false,
+                signed_url_ttl_mins: 60,
             },
             database: DatabaseConfig {
                 url: "sqlite::memory:".to_string(),
                 max_connections: 5,
+                retry: 3,
+                retry_backoff_ms: 100,
+                auto_migrate: true,
             },
             opds: OpdsConfig {
                 title: "ROPDS".to_string(),
@@ -53,6 +73,15 @@ mod tests {
                 show_covers: None,
                 alphabet_menu: true,
                 hide_doubles: false,
+                group_formats: false,
+                hide_empty: false,
+                deep_catalogs: false,
+                catalog_thumbnails: false,
+                auto_bookshelf: true,
+                icon_path: None,
+                logo_url: None,
+                absolute_urls: false,
+                dump_page_size: 500,
             },
             scanner: ScannerConfig {
                 schedule_minutes: vec![0],
@@ -63,19 +92,37 @@ mod tests {
                 test_zip: false,
                 test_files: false,
                 workers_num: 1,
+                exclude: Vec::new(),
+                max_depth: 64,
+                changes_log: None,
+                link_identical: false,
             },
             web: WebConfig {
                 language: "en".to_string(),
                 theme: "light".to_string(),
+                accent_color: None,
+                site_name: None,
+                seo_enabled: false,
+                robots_txt: None,
             },
             upload: UploadConfig {
                 allow_upload: true,
                 upload_path: PathBuf::from("/tmp/uploads"),
                 max_upload_size_mb: 10,
+                ..Default::default()
             },
             reader: ReaderConfig::default(),
             oauth: Default::default(),
             smtp: Default::default(),
+            tools: Default::default(),
+            search: Default::default(),
+            sharing: Default::default(),
+            api: Default::default(),
+            password: Default::default(),
+            download: Default::default(),
+            formats: Default::default(),
+            clients: Default::default(),
+            stats: Default::default(),
         };
 
         let db = create_test_pool().await;
@@ -83,7 +130,13 @@ mod tests {
         let mut translations = Translations::new();
         translations.insert("en".to_string(), serde_json::json!({"web": {}}));
 
-        AppState::new(config, db, tera, translations, false, false)
+        AppState::new(
+            config,
+            db,
+            tera,
+            translations,
+            crate::state::Capabilities::default(),
+        )
     }
 
     async fn ensure_catalog(pool: &crate::db::DbPool) -> i64 {
@@ -183,7 +236,13 @@ mod tests {
     async fn test_web_download_book_not_found() {
         let tmp = tempdir().unwrap();
         let state = build_test_state(tmp.path().to_path_buf()).await;
-        let response = web_download(State(state), CookieJar::new(), Path((999_999, 0))).await;
+        let response = web_download(
+            State(state),
+            CookieJar::new(),
+            Path((999_999, 0)),
+            axum::extract::Request::default(),
+        )
+        .await;
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
@@ -212,7 +271,13 @@ mod tests {
         .await
         .unwrap();
 
-        let response = web_download(State(state), CookieJar::new(), Path((book_id, 0))).await;
+        let response = web_download(
+            State(state),
+            CookieJar::new(),
+            Path((book_id, 0)),
+            axum::extract::Request::default(),
+        )
+        .await;
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 }
@@ -1,43 +1,80 @@
-// Theme toggle (persists in localStorage)
+// Theme toggle: light -> dark -> auto (follows prefers-color-scheme).
+// The server renders the saved preference into data-theme-mode; changes are
+// posted back so they persist per user (or in a cookie for anonymous visitors).
 (function () {
-  const THEME_KEY = "ropds-theme";
+  const MODES = ["light", "dark", "auto"];
+  const LEGACY_THEME_KEY = "ropds-theme";
+  const root = document.documentElement;
   const mediaQuery = window.matchMedia
     ? window.matchMedia("(prefers-color-scheme: dark)")
     : null;
 
-  function getSavedTheme() {
-    const saved = localStorage.getItem(THEME_KEY);
-    if (saved === "light" || saved === "dark") return saved;
-    return null;
+  function currentMode() {
+    const mode = root.getAttribute("data-theme-mode");
+    if (MODES.indexOf(mode) !== -1) return mode;
+    return root.getAttribute("data-bs-theme") === "dark" ? "dark" : "light";
   }
 
-  function getPreferred() {
-    const saved = getSavedTheme();
-    if (saved) return saved;
-    if (mediaQuery) return mediaQuery.matches ? "dark" : "light";
-    return document.documentElement.getAttribute("data-bs-theme") || "light";
+  function resolve(mode) {
+    if (mode !== "auto") return mode;
+    return mediaQuery && mediaQuery.matches ? "dark" : "light";
   }
 
-  function apply(theme, persist) {
-    document.documentElement.setAttribute("data-bs-theme", theme);
-    if (persist) {
-      localStorage.setItem(THEME_KEY, theme);
-    }
-    // Update toggle button icon
+  function updateToggle(mode) {
     const icon = document.getElementById("theme-icon");
     if (icon) {
-      icon.className = theme === "dark" ? "bi bi-sun" : "bi bi-moon";
+      icon.className =
+        mode === "auto" ? "bi bi-circle-half" : mode === "dark" ? "bi bi-sun" : "bi bi-moon";
+    }
+    const button = document.getElementById("theme-toggle");
+    if (button) {
+      const label = button.getAttribute("data-label-" + mode);
+      if (label) button.setAttribute("aria-label", label);
     }
   }
 
-  // Apply theme immediately. Persist only when set explicitly by user.
-  apply(getPreferred(), false);
+  function apply(mode) {
+    root.setAttribute("data-theme-mode", mode);
+    root.setAttribute("data-bs-theme", resolve(mode));
+    updateToggle(mode);
+  }
+
+  function csrfToken() {
+    const meta = document.querySelector('meta[name="csrf-token"]');
+    if (meta && meta.content) return meta.content;
+    return (document.body && document.body.getAttribute("data-csrf-token")) || "";
+  }
+
+  function save(mode) {
+    if (!window.fetch) return;
+    fetch("/web/api/theme", {
+      method: "POST",
+      credentials: "same-origin",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify({ theme: mode, csrf_token: csrfToken() }),
+    }).catch(function () {});
+  }
+
+  // Apply immediately (before first paint) so `auto` never flashes the wrong theme.
+  apply(currentMode());
+  document.addEventListener("DOMContentLoaded", function () {
+    updateToggle(currentMode());
+
+    // One-time migration of the old localStorage-only setting.
+    let legacy = null;
+    try {
+      legacy = localStorage.getItem(LEGACY_THEME_KEY);
+      localStorage.removeItem(LEGACY_THEME_KEY);
+    } catch (_e) { /* storage unavailable */ }
+    if (legacy === "light" || legacy === "dark") {
+      apply(legacy);
+      save(legacy);
+    }
+  });
 
-  // Follow OS preference changes unless user has manually selected a theme.
   if (mediaQuery) {
-    const onSystemThemeChange = function (event) {
-      if (getSavedTheme()) return;
-      apply(event.matches ? "dark" : "light", false);
+    const onSystemThemeChange = function () {
+      if (currentMode() === "auto") apply("auto");
     };
 
     if (typeof mediaQuery.addEventListener === "function") {
@@ -47,10 +84,10 @@
     }
   }
 
-  // Expose toggle function
   window.toggleTheme = function () {
-    const current = document.documentElement.getAttribute("data-bs-theme");
-    apply(current === "dark" ? "light" : "dark", true);
+    const mode = MODES[(MODES.indexOf(currentMode()) + 1) % MODES.length];
+    apply(mode);
+    save(mode);
   };
 })();
 
@@ -241,19 +278,134 @@
     const form = document.getElementById("search-form");
     const radios = document.querySelectorAll('input[name="search-target"]');
     if (!form || !radios.length) return;
+    const typeInput = form.querySelector('input[name="type"]');
+    // Book searches by title and inside texts share an action; the hidden
+    // type field tells them apart.
+    function apply(radio) {
+      form.action = radio.dataset.action;
+      if (typeInput) typeInput.value = radio.dataset.type || "m";
+    }
     // Keep form action in sync with the currently selected radio on initial load.
     const selected = document.querySelector('input[name="search-target"]:checked');
     if (selected && selected.dataset.action) {
-      form.action = selected.dataset.action;
+      apply(selected);
     }
     radios.forEach(function (radio) {
       radio.addEventListener("change", function () {
-        form.action = this.dataset.action;
+        apply(this);
       });
     });
   });
 })();
 
+// Search suggestions: grouped titles/authors/series under the header search box
+(function () {
+  var MIN_CHARS = 2;
+  var DELAY_MS = 200;
+
+  document.addEventListener("DOMContentLoaded", function () {
+    var form = document.getElementById("search-form");
+    var menu = document.getElementById("search-suggest");
+    var input = form ? form.querySelector("input[data-suggest-url]") : null;
+    if (!input || !menu) return;
+
+    var timer = null;
+    var pending = null;
+    var active = -1;
+
+    function items() {
+      return menu.querySelectorAll("a.dropdown-item");
+    }
+
+    function hide() {
+      menu.classList.remove("show");
+      menu.innerHTML = "";
+      active = -1;
+    }
+
+    function highlight(index) {
+      var links = items();
+      if (!links.length) return;
+      active = (index + links.length) % links.length;
+      links.forEach(function (link, i) {
+        link.classList.toggle("active", i === active);
+      });
+    }
+
+    function render(data) {
+      menu.innerHTML = "";
+      active = -1;
+      [
+        ["titles", "bi-book"],
+        ["authors", "bi-person"],
+        ["series", "bi-collection"],
+      ].forEach(function (group) {
+        var entries = data[group[0]] || [];
+        if (!entries.length) return;
+        var header = document.createElement("h6");
+        header.className = "dropdown-header";
+        header.textContent = input.dataset["label" + group[0].charAt(0).toUpperCase() + group[0].slice(1)] || group[0];
+        menu.appendChild(header);
+        entries.forEach(function (entry) {
+          var link = document.createElement("a");
+          link.className = "dropdown-item text-truncate";
+          link.href = entry.url;
+          link.setAttribute("role", "option");
+          var icon = document.createElement("i");
+          icon.className = "bi " + group[1] + " me-2 text-body-secondary";
+          link.appendChild(icon);
+          link.appendChild(document.createTextNode(entry.name));
+          menu.appendChild(link);
+        });
+      });
+      menu.classList.toggle("show", items().length > 0);
+    }
+
+    function fetchSuggestions() {
+      var q = input.value.trim();
+      if (q.length < MIN_CHARS) {
+        hide();
+        return;
+      }
+      if (pending) pending.abort();
+      pending = new AbortController();
+      fetch(input.dataset.suggestUrl + "?q=" + encodeURIComponent(q), {
+        signal: pending.signal,
+        headers: { Accept: "application/json" },
+      })
+        .then(function (resp) {
+          return resp.ok ? resp.json() : null;
+        })
+        .then(function (data) {
+          if (data && data.query === input.value.trim()) render(data);
+        })
+        .catch(function () {});
+    }
+
+    input.addEventListener("input", function () {
+      clearTimeout(timer);
+      timer = setTimeout(fetchSuggestions, DELAY_MS);
+    });
+
+    input.addEventListener("keydown", function (event) {
+      if (!menu.classList.contains("show")) return;
+      if (event.key === "ArrowDown" || event.key === "ArrowUp") {
+        event.preventDefault();
+        highlight(active + (event.key === "ArrowDown" ? 1 : -1));
+      } else if (event.key === "Enter" && active >= 0) {
+        event.preventDefault();
+        window.location.href = items()[active].href;
+      } else if (event.key === "Escape") {
+        hide();
+      }
+    });
+
+    document.addEventListener("click", function (event) {
+      if (!form.contains(event.target)) hide();
+    });
+  });
+})();
+
 // Language selector: redirect to current page instead of /web
 (function () {
   document.addEventListener("DOMContentLoaded", function () {
@@ -340,6 +492,25 @@
   });
 })();
 
+// Admin: populate shared upload-quota modal
+(function () {
+  document.addEventListener("DOMContentLoaded", function () {
+    document.querySelectorAll(".btn-upload-quota").forEach(function (btn) {
+      btn.addEventListener("click", function () {
+        var action = "/web/admin/users/" + this.getAttribute("data-user-id") + "/quota";
+        document.getElementById("quotaModalForm").action = action;
+        document.getElementById("quotaResetBtn").setAttribute("formaction", action + "/reset");
+        document.getElementById("quotaModalTitle").textContent = this.getAttribute("data-username");
+        document.getElementById("quotaModalUsed").textContent = this.getAttribute("data-used");
+        document.getElementById("quota-files").value = this.getAttribute("data-quota-files");
+        document.getElementById("quota-mb").value = this.getAttribute("data-quota-mb");
+        var modal = new bootstrap.Modal(document.getElementById("quotaModal"));
+        modal.show();
+      });
+    });
+  });
+})();
+
 // Admin: populate shared delete-confirmation modal
 (function () {
   document.addEventListener("DOMContentLoaded", function () {
@@ -512,6 +683,46 @@ function convertUtcTimes(root) {
   });
 })();
 
+// Book and bookshelf share links: create them via AJAX and show them for copying
+(function () {
+  document.addEventListener("DOMContentLoaded", function () {
+    document.addEventListener("click", function (e) {
+      var btn = e.target.closest(".share-link-btn");
+      if (!btn) return;
+      e.preventDefault();
+
+      var form = btn.closest("form");
+      if (!form) return;
+
+      btn.disabled = true;
+      fetch(form.action, {
+        method: "POST",
+        headers: { "Content-Type": "application/x-www-form-urlencoded" },
+        body: new URLSearchParams(new FormData(form)).toString(),
+        credentials: "same-origin"
+      })
+        .then(function (res) { return res.ok ? res.json() : null; })
+        .then(function (data) {
+          if (!data || !data.ok) {
+            window.alert(btn.dataset.failed);
+            return;
+          }
+          var url = new URL(data.url, window.location.origin).href;
+          if (navigator.clipboard && window.isSecureContext) {
+            navigator.clipboard.writeText(url).catch(function () {});
+          }
+          window.prompt(btn.dataset.prompt, url);
+          if (data.opds_url && btn.dataset.opdsPrompt) {
+            window.prompt(btn.dataset.opdsPrompt, new URL(data.opds_url, window.location.origin).href);
+          }
+        })
+        .finally(function () {
+          btn.disabled = false;
+        });
+    });
+  });
+})();
+
 // Bookshelf infinite scroll
 (function () {
   document.addEventListener("DOMContentLoaded", function () {
@@ -525,13 +736,14 @@ function convertUtcTimes(root) {
     var offset = parseInt(grid.dataset.offset, 10) || 0;
     var sort = grid.dataset.sort || "date";
     var dir = grid.dataset.dir || "desc";
+    var state = grid.dataset.state || "all";
 
     function loadMore() {
       if (loading || !hasMore) return;
       loading = true;
       if (loader) loader.classList.remove("d-none");
 
-      var url = "/web/bookshelf/cards?offset=" + offset + "&sort=" + sort + "&dir=" + dir;
+      var url = "/web/bookshelf/cards?offset=" + offset + "&sort=" + sort + "&dir=" + dir + "&state=" + state;
       fetch(url, { credentials: "same-origin" })
         .then(function (res) { return res.json(); })
         .then(function (data) {
@@ -567,6 +779,59 @@ function convertUtcTimes(root) {
   });
 })();
 
+// Book results: list/grid switch and cover-grid infinite scroll
+(function () {
+  document.addEventListener("DOMContentLoaded", function () {
+    document.querySelectorAll("[data-books-view]").forEach(function (link) {
+      link.addEventListener("click", function () {
+        document.cookie = "books_view=" + link.dataset.booksView + "; path=/; max-age=31536000; SameSite=Lax";
+      });
+    });
+
+    var grid = document.getElementById("book-grid");
+    var sentinel = document.getElementById("book-grid-sentinel");
+    var loader = document.getElementById("book-grid-loader");
+    if (!grid || !sentinel) return;
+
+    var loading = false;
+    var cursor = grid.dataset.nextCursor || "";
+
+    function loadMore() {
+      if (loading || !cursor) return;
+      loading = true;
+      if (loader) loader.classList.remove("d-none");
+
+      var url = grid.dataset.apiUrl + "&cursor=" + encodeURIComponent(cursor);
+      fetch(url, { credentials: "same-origin" })
+        .then(function (res) { return res.json(); })
+        .then(function (data) {
+          if (data.html) {
+            var tmp = document.createElement("div");
+            tmp.innerHTML = data.html;
+            while (tmp.firstElementChild) {
+              grid.appendChild(tmp.firstElementChild);
+            }
+          }
+          cursor = data.next_cursor || "";
+        })
+        .catch(function () { /* keep cursor; retry on next intersection */ })
+        .finally(function () {
+          loading = false;
+          if (loader) loader.classList.add("d-none");
+        });
+    }
+
+    if ("IntersectionObserver" in window) {
+      var observer = new IntersectionObserver(function (entries) {
+        if (entries[0].isIntersecting && cursor) {
+          loadMore();
+        }
+      }, { rootMargin: "400px" });
+      observer.observe(sentinel);
+    }
+  });
+})();
+
 // Genre selector utility (shared by upload page and book detail editor)
 window.GenreSelector = (function () {
   var cachedSections = null;
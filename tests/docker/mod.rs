@@ -131,7 +131,13 @@ pub fn test_app_state(pool: DbPool, config: Config) -> AppState {
 
     let translations = i18n::load_runtime_translations().expect("translations should load");
 
-    AppState::new(config, pool, tera, translations, false, false)
+    AppState::new(
+        config,
+        pool,
+        tera,
+        translations,
+        ropds::state::Capabilities::default(),
+    )
 }
 
 /// Build a full Router from an AppState.
@@ -1,6 +1,10 @@
 use super::*;
 use ropds::db::models::CatType;
-use ropds::db::queries::{authors, books, bookshelf, catalogs, genres};
+use ropds::db::queries::MatchMode;
+use ropds::db::queries::books::BookSort;
+use ropds::db::queries::{
+    authors, books, bookshelf, catalogs, counters, downloads, genres, recommendations, users,
+};
 use ropds::scanner;
 
 // ---------------------------------------------------------------------------
@@ -65,6 +69,192 @@ async fn mysql_insert_duplicate_catalog_returns_same_id() {
     assert_eq!(id1, id2);
 }
 
+/// The recursive ancestor query and the subtree path range work on this backend.
+#[tokio::test]
+async fn mysql_catalog_ancestors_and_subtree_books() {
+    let (_container, pool) = start_mysql().await;
+    let top = catalogs::insert(&pool, None, "lib", "lib", CatType::Normal, 0, "")
+        .await
+        .unwrap();
+    let child = catalogs::insert(&pool, Some(top), "lib/a", "a", CatType::Normal, 0, "")
+        .await
+        .unwrap();
+    let sibling = catalogs::insert(&pool, None, "lib-x", "lib-x", CatType::Normal, 0, "")
+        .await
+        .unwrap();
+
+    let chain = catalogs::get_ancestors(&pool, child).await.unwrap();
+    assert_eq!(
+        chain.iter().map(|c| c.id).collect::<Vec<_>>(),
+        vec![top, child]
+    );
+
+    for (catalog_id, title) in [(child, "Nested"), (sibling, "Sibling")] {
+        books::insert(
+            &pool,
+            catalog_id,
+            &format!("{title}.fb2"),
+            "lib",
+            "fb2",
+            title,
+            &title.to_uppercase(),
+            "",
+            "",
+            "en",
+            2,
+            100,
+            CatType::Normal,
+            0,
+            "",
+        )
+        .await
+        .unwrap();
+    }
+    let tree = books::get_by_catalog_tree(&pool, top, 10, 0, true, BookSort::Title)
+        .await
+        .unwrap();
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree[0].title, "Nested");
+    assert_eq!(
+        books::count_by_catalog_tree(&pool, top, false)
+            .await
+            .unwrap(),
+        1
+    );
+}
+
+/// Download history feeds the popular cache kept in `counters`; the home
+/// dashboard queries run on this backend.
+#[tokio::test]
+async fn mysql_downloads_popular_and_dashboard_queries() {
+    let (_container, pool) = start_mysql().await;
+    let cat = catalogs::insert(&pool, None, "lib", "lib", CatType::Normal, 0, "")
+        .await
+        .unwrap();
+    let book_id = books::insert(
+        &pool,
+        cat,
+        "Popular.fb2",
+        "lib",
+        "fb2",
+        "Popular",
+        "POPULAR",
+        "",
+        "",
+        "en",
+        2,
+        100,
+        CatType::Normal,
+        0,
+        "",
+    )
+    .await
+    .unwrap();
+    downloads::record(&pool, book_id, None).await.unwrap();
+    downloads::record(&pool, book_id, None).await.unwrap();
+
+    counters::update_popular(&pool).await.unwrap();
+    counters::update_popular(&pool).await.unwrap();
+    let popular = books::get_popular(&pool, 30, 10, 0).await.unwrap();
+    assert_eq!(popular.len(), 1);
+    assert_eq!(popular[0].0.id, book_id);
+    assert_eq!(popular[0].1, 2);
+    assert!(
+        counters::get_all(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .all(|c| c.name.starts_with("all"))
+    );
+
+    // Home dashboard shelves
+    genres::link_book_by_code(&pool, book_id, "sf_fantasy")
+        .await
+        .unwrap();
+    bookshelf::upsert(&pool, 1, book_id).await.unwrap();
+    let favorites = genres::get_favorite_ids(&pool, 1, 3).await.unwrap();
+    assert_eq!(favorites.len(), 1);
+    assert!(
+        books::get_recent_in_genres(&pool, &favorites, 1, 6)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        books::get_recent_in_genres(&pool, &favorites, 2, 6)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(books::get_random_list(&pool, 6).await.unwrap().len(), 1);
+
+    // Recommendations: co-downloads and shared genres
+    let sequel = books::insert(
+        &pool,
+        cat,
+        "Sequel.fb2",
+        "lib",
+        "fb2",
+        "Sequel",
+        "SEQUEL",
+        "",
+        "",
+        "en",
+        2,
+        100,
+        CatType::Normal,
+        0,
+        "",
+    )
+    .await
+    .unwrap();
+    genres::link_book_by_code(&pool, sequel, "sf_fantasy")
+        .await
+        .unwrap();
+    let reader = users::create(&pool, "reader", "", 0, "").await.unwrap();
+    downloads::record(&pool, book_id, Some(reader))
+        .await
+        .unwrap();
+    downloads::record(&pool, sequel, Some(reader))
+        .await
+        .unwrap();
+    assert!(recommendations::rebuild(&pool).await.unwrap() >= 2);
+    let similar = recommendations::get_for_book(&pool, book_id, 6)
+        .await
+        .unwrap();
+    assert_eq!(similar.len(), 1);
+    assert_eq!(similar[0].id, sequel);
+    assert!(
+        recommendations::get_for_user(&pool, reader, 10, 0)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let shelf_owner = users::create(&pool, "shelf-owner", "", 0, "")
+        .await
+        .unwrap();
+    bookshelf::upsert(&pool, shelf_owner, book_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        recommendations::get_for_user(&pool, shelf_owner, 10, 0)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    books::delete_book_and_relations(&pool, sequel)
+        .await
+        .unwrap();
+    assert!(
+        recommendations::get_for_book(&pool, book_id, 6)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
 // ---------------------------------------------------------------------------
 // Unicode / Cyrillic search
 // ---------------------------------------------------------------------------
@@ -76,7 +266,7 @@ async fn mysql_cyrillic_search_works() {
     let id = authors::insert(&pool, "Толстой Лев", "ТОЛСТОЙ ЛЕВ", 1)
         .await
         .unwrap();
-    let found = authors::search_by_name(&pool, "ТОЛСТОЙ", 10, 0)
+    let found = authors::search_by_name(&pool, "ТОЛСТОЙ", MatchMode::Contains, 10, 0, false)
         .await
         .unwrap();
     assert_eq!(found.len(), 1);
@@ -241,6 +431,26 @@ async fn mysql_genre_upsert_translations() {
     assert_eq!(translations[0].name, "Updated Section");
 }
 
+/// Re-seeding restores a deleted default genre under its old id and keeps
+/// the sequence past the seeded ids for genres created afterwards.
+#[tokio::test]
+async fn mysql_genre_seed_defaults_restores_deleted_genre() {
+    let (_container, pool) = start_mysql().await;
+    let genre = genres::get_by_code(&pool, "sf_fantasy_city")
+        .await
+        .unwrap()
+        .unwrap();
+    genres::delete_genre(&pool, genre.id).await.unwrap();
+    let report = genres::seed_defaults(&pool).await.unwrap();
+    assert_eq!(report.genres, 1);
+    assert_eq!(report.translations, 2);
+    assert_eq!(genres::seed_defaults(&pool).await.unwrap().genres, 0);
+    let created = genres::create_genre(&pool, "test_after_seed", genre.section_id.unwrap())
+        .await
+        .unwrap();
+    assert!(created > 228);
+}
+
 // ---------------------------------------------------------------------------
 // Scanner integration (full pipeline)
 // ---------------------------------------------------------------------------
@@ -351,15 +561,35 @@ async fn mysql_book_title_search() {
     .await
     .unwrap();
 
-    let results = books::search_by_title(&pool, "ALPHA", 100, 0, false)
-        .await
-        .unwrap();
+    let results = books::search_by_title(
+        &pool,
+        "ALPHA",
+        MatchMode::Contains,
+        100,
+        0,
+        false,
+        BookSort::Title,
+        false,
+        &books::SearchFilter::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(results.len(), 1);
     assert_eq!(results[0].title, "Alpha Book");
 
-    let all = books::search_by_title(&pool, "BOOK", 100, 0, false)
-        .await
-        .unwrap();
+    let all = books::search_by_title(
+        &pool,
+        "BOOK",
+        MatchMode::Contains,
+        100,
+        0,
+        false,
+        BookSort::Title,
+        false,
+        &books::SearchFilter::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(all.len(), 2);
 }
 
@@ -77,6 +77,11 @@ async fn admin_series_endpoints_update_search_and_remove() {
     assert_eq!(json["ok"], true);
     assert_eq!(json["series"][0]["ser_name"], "Foundation");
     assert_eq!(json["series"][0]["ser_no"], 4);
+    let html = json["html"].as_str().unwrap();
+    assert!(html.contains("book-edit-fields"));
+    assert!(html.contains("Foundation"));
+    assert!(html.contains("#4"));
+    assert!(html.contains("btn-edit-book"));
 
     let linked = series::get_for_book(&pool, book_id).await.unwrap();
     assert_eq!(linked.len(), 1);
@@ -109,6 +114,7 @@ async fn admin_series_endpoints_update_search_and_remove() {
     let json3: serde_json::Value = serde_json::from_str(&body_string(resp3).await).unwrap();
     assert_eq!(json3["ok"], true);
     assert_eq!(json3["series"].as_array().unwrap().len(), 0);
+    assert!(!json3["html"].as_str().unwrap().contains("bi-collection"));
 
     let linked = series::get_for_book(&pool, book_id).await.unwrap();
     assert!(linked.is_empty());
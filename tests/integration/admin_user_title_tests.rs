@@ -143,6 +143,12 @@ async fn admin_book_title_endpoint_updates_and_validates() {
     let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
     assert_eq!(json["ok"], true);
     assert_eq!(json["title"], "Updated Title");
+    assert!(
+        json["html"]
+            .as_str()
+            .unwrap()
+            .contains(r#"<h5 class="card-title mb-1">Updated Title</h5>"#)
+    );
 
     let updated = ropds::db::queries::books::get_by_id(&pool, book_id)
         .await
@@ -168,3 +174,362 @@ async fn admin_book_title_endpoint_updates_and_validates() {
     assert_eq!(json2["ok"], false);
     assert_eq!(json2["error"], "title_empty");
 }
+
+#[tokio::test]
+async fn admin_book_annotation_endpoint_renders_markdown() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    let super_id = create_test_user(&pool, "admin-annotation", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let csrf = csrf_for_session(&session);
+
+    let book_id = insert_test_book(&pool, "Annotated").await;
+    let catalog_id = ropds::db::queries::books::get_by_id(&pool, book_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .catalog_id;
+
+    let state = test_app_state(pool.clone(), config);
+    let resp = post_json(
+        test_router(state.clone()),
+        "/web/admin/book-annotation",
+        serde_json::json!({
+            "book_id": book_id,
+            "annotation": "A **bold** tale.\r\n\r\n- one\n- two\n\n<script>x</script>",
+            "csrf_token": csrf,
+        }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["ok"], true);
+    assert_eq!(
+        json["annotation"],
+        "A **bold** tale.\n\n- one\n- two\n\n<script>x</script>"
+    );
+    assert_eq!(
+        json["annotation_html"],
+        "<p>A <strong>bold</strong> tale.</p><ul><li>one</li><li>two</li></ul>\
+         <p>&lt;script&gt;x&lt;/script&gt;</p>"
+    );
+
+    let updated = ropds::db::queries::books::get_by_id(&pool, book_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(updated.annotation, json["annotation"]);
+
+    // Web cards show the rendered markdown and keep the source for editing.
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/search/books?type=m&q=Annotated",
+        &session,
+    )
+    .await;
+    let html = body_string(resp).await;
+    assert!(html.contains("<p>A <strong>bold</strong> tale.</p><ul><li>one</li>"));
+    assert!(!html.contains("<script>x</script>"));
+    assert!(html.contains(r#"data-annotation="A **bold** tale."#));
+
+    // OPDS content carries the same sanitized HTML.
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/opds/catalogs/{catalog_id}/"),
+    )
+    .await;
+    let xml = body_string(resp).await;
+    assert!(
+        xml.contains("<div class='book'><p>A <strong>bold</strong> tale.</p>"),
+        "OPDS content should render markdown: {xml}"
+    );
+    assert!(!xml.contains("<script>"));
+
+    let resp = post_json(
+        test_router(state),
+        "/web/admin/book-annotation",
+        serde_json::json!({
+            "book_id": book_id,
+            "annotation": "bad\u{0007}",
+            "csrf_token": csrf_for_session(&session),
+        }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["error"], "annotation_invalid");
+}
+
+#[tokio::test]
+async fn admin_book_lang_edit_and_bulk_guessing() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    let super_id = create_test_user(&pool, "admin-lang", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let csrf = csrf_for_session(&session);
+
+    let book_id = insert_test_book(&pool, "Война и мир").await;
+    let state = test_app_state(pool.clone(), config);
+
+    let resp = post_json(
+        test_router(state.clone()),
+        "/web/admin/book-lang",
+        serde_json::json!({"book_id": book_id, "lang": "english", "csrf_token": csrf}),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["error"], "lang_invalid");
+
+    // Clearing the language puts the book up for guessing.
+    let resp = post_json(
+        test_router(state.clone()),
+        "/web/admin/book-lang",
+        serde_json::json!({"book_id": book_id, "lang": "", "csrf_token": csrf}),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["lang"], "");
+
+    let resp = get_with_session(test_router(state.clone()), "/web/admin/languages", &session).await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("Война и мир"));
+    assert!(html.contains(r#"<span class="badge text-bg-secondary">ru</span>"#));
+    let stored = ropds::db::queries::books::get_by_id(&pool, book_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.lang, "", "the preview must not change anything");
+
+    let resp = post_form(
+        test_router(state.clone()),
+        "/web/admin/languages/apply",
+        &format!("csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(
+        resp.headers().get("location").unwrap(),
+        "/web/admin/languages?applied=1"
+    );
+    let stored = ropds::db::queries::books::get_by_id(&pool, book_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.lang, "ru");
+
+    let resp = post_json(
+        test_router(state),
+        "/web/admin/book-lang",
+        serde_json::json!({"book_id": book_id, "lang": "pt_BR", "csrf_token": csrf}),
+        &session,
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["lang"], "pt-br");
+}
+
+#[tokio::test]
+async fn admin_impersonates_user_and_returns() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+
+    let super_id = create_test_user(&pool, "admin-imp", "password123", true).await;
+    let other_super = create_test_user(&pool, "admin-imp2", "password123", true).await;
+    let reader_id = create_test_user(&pool, "reader-imp", "password123", false).await;
+    let session = session_cookie_value(super_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    // Other superusers cannot be impersonated.
+    let resp = post_form_with_cookies(
+        test_router(state.clone()),
+        &format!("/web/admin/impersonate/{other_super}"),
+        &format!("csrf_token={csrf}"),
+        &format!("session={session}"),
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(
+        resp.headers()["location"],
+        "/web/admin?error=cannot_impersonate"
+    );
+    assert!(set_cookie(&resp, "impersonator").is_none());
+
+    let resp = post_form_with_cookies(
+        test_router(state.clone()),
+        &format!("/web/admin/impersonate/{reader_id}"),
+        &format!("csrf_token={csrf}"),
+        &format!("session={session}"),
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(
+        set_cookie(&resp, "impersonator").as_deref(),
+        Some(&*session)
+    );
+    let reader_session = set_cookie(&resp, "session").unwrap();
+    assert_eq!(
+        ropds::web::auth::verify_session(&reader_session, b"test-secret-key-for-integration-tests"),
+        Some(reader_id)
+    );
+
+    // The impersonated session shows the banner and has no admin access.
+    let cookies = format!("session={reader_session}; impersonator={session}");
+    let req = axum::http::Request::builder()
+        .uri("/web/profile")
+        .header("cookie", &cookies)
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let body = body_string(resp).await;
+    assert!(body.contains("impersonation-banner"));
+    assert!(body.contains("admin-imp"));
+    let resp = get_with_session(test_router(state.clone()), "/web/admin", &reader_session).await;
+    assert_eq!(resp.status(), 403);
+
+    // Without the impersonator cookie a plain session shows no banner.
+    let resp = get_with_session(test_router(state.clone()), "/web/profile", &reader_session).await;
+    assert!(!body_string(resp).await.contains("impersonation-banner"));
+
+    let reader_csrf = csrf_for_session(&reader_session);
+    let resp = post_form_with_cookies(
+        test_router(state.clone()),
+        "/web/impersonate/exit",
+        &format!("csrf_token={reader_csrf}"),
+        &cookies,
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(resp.headers()["location"], "/web/admin");
+    assert_eq!(set_cookie(&resp, "session").as_deref(), Some(&*session));
+    assert_eq!(set_cookie(&resp, "impersonator").as_deref(), Some(""));
+
+    // Both ends are kept in the audit log on the admin page.
+    let entries = ropds::db::queries::audit::list_recent(&pool, 10)
+        .await
+        .unwrap();
+    let actions: Vec<(&str, &str, &str)> = entries
+        .iter()
+        .map(|e| (e.admin.as_str(), e.action.as_str(), e.subject.as_str()))
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            ("admin-imp", "impersonation_ended", "reader-imp"),
+            ("admin-imp", "impersonation_started", "reader-imp"),
+        ]
+    );
+    let resp = get_with_session(test_router(state), "/web/admin", &session).await;
+    let html = body_string(resp).await;
+    assert!(html.contains("Logged in as user"));
+    assert!(html.contains("Returned from user"));
+}
+
+#[tokio::test]
+async fn disabled_account_and_password_policy() {
+    use base64::Engine;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    config.password.min_length = 10;
+    config.password.required_classes = vec![ropds::config::CharClass::Digit];
+    config.password.reject_common = true;
+
+    let super_id = create_test_user(&pool, "admin-active", "password123", true).await;
+    let reader_id = create_test_user(&pool, "reader-active", "password123", false).await;
+    let session = session_cookie_value(super_id);
+    let csrf = csrf_for_session(&session);
+    let reader_session = session_cookie_value(reader_id);
+    let state = test_app_state(pool.clone(), config);
+
+    for (password, code) in [
+        ("short1", "password_short"),
+        ("no-digits-here", "password_weak"),
+        ("1q2w3e4r5t", "password_common"),
+    ] {
+        let resp = post_form(
+            test_router(state.clone()),
+            &format!("/web/admin/users/{reader_id}/password"),
+            &format!("password={password}&csrf_token={csrf}"),
+            &session,
+        )
+        .await;
+        assert_eq!(
+            resp.headers()["location"],
+            format!("/web/admin?error={code}").as_str()
+        );
+    }
+
+    let resp = post_form(
+        test_router(state.clone()),
+        &format!("/web/admin/users/{super_id}/active"),
+        &format!("csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(
+        resp.headers()["location"],
+        "/web/admin?error=cannot_disable_self"
+    );
+
+    let basic = format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode("reader-active:password123")
+    );
+    let opds = |state: AppState| {
+        let req = axum::http::Request::builder()
+            .uri("/opds")
+            .header("authorization", &basic)
+            .body(Body::empty())
+            .unwrap();
+        test_router(state).oneshot(req)
+    };
+    assert_eq!(opds(state.clone()).await.unwrap().status(), 200);
+    let resp = get_with_session(test_router(state.clone()), "/web/profile", &reader_session).await;
+    assert_eq!(resp.status(), 200);
+
+    // Unchecked switch: the form carries no `active` field.
+    let resp = post_form(
+        test_router(state.clone()),
+        &format!("/web/admin/users/{reader_id}/active"),
+        &format!("csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.headers()["location"], "/web/admin?msg=active_toggled");
+
+    assert_eq!(opds(state.clone()).await.unwrap().status(), 401);
+    let resp = get_with_session(test_router(state.clone()), "/web/profile", &reader_session).await;
+    assert_eq!(resp.headers()["location"], "/web/login?error=disabled");
+
+    let resp = post_form(
+        test_router(state.clone()),
+        &format!("/web/admin/users/{reader_id}/active"),
+        &format!("active=on&csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(opds(state).await.unwrap().status(), 200);
+}
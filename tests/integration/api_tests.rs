@@ -0,0 +1,153 @@
+use axum::http::StatusCode;
+use ropds::db;
+use ropds::db::queries::books;
+use ropds::scanner;
+
+use super::*;
+
+async fn get_json(app: axum::Router, path: &str) -> (StatusCode, serde_json::Value) {
+    let resp = get(app, path).await;
+    let status = resp.status();
+    let body = body_string(resp).await;
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+/// Lists, details and search share the envelope format; errors are JSON too.
+#[tokio::test]
+async fn api_lists_books_authors_and_search() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let state = test_app_state(pool, config);
+
+    let (status, json) = get_json(test_router(state.clone()), "/api/v1/books?per_page=5").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["pagination"]["page"], 1);
+    assert_eq!(json["pagination"]["per_page"], 5);
+    assert_eq!(json["pagination"]["total"], 1);
+    assert_eq!(json["pagination"]["total_pages"], 1);
+    assert_eq!(json["data"][0]["title"], "Test Book Title");
+    assert_eq!(
+        json["data"][0]["download_url"],
+        format!("/api/v1/books/{}/download", book.id)
+    );
+
+    let (status, json) = get_json(
+        test_router(state.clone()),
+        &format!("/api/v1/books/{}", book.id),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["data"]["id"], book.id);
+    assert_eq!(json["data"]["series"][0]["name"], "Test Series");
+    assert_eq!(json["data"]["series"][0]["number"], 1);
+
+    let (status, json) = get_json(test_router(state.clone()), "/api/v1/search?q=smith").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["data"]["authors_total"], 1);
+    assert_eq!(json["data"]["authors"][0]["name"], "Smith Jane");
+    let author_id = json["data"]["authors"][0]["id"].as_i64().unwrap();
+
+    let (_, json) = get_json(
+        test_router(state.clone()),
+        &format!("/api/v1/authors/{author_id}/books"),
+    )
+    .await;
+    assert_eq!(json["pagination"]["total"], 1);
+    assert_eq!(json["data"][0]["id"], book.id);
+
+    let (status, json) = get_json(test_router(state.clone()), "/api/v1/books/999999").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["error"]["code"], "not_found");
+    assert_eq!(json["error"]["message"], "book not found");
+
+    let (status, json) = get_json(test_router(state.clone()), "/api/v1/books?sort=bogus").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["error"]["code"], "bad_request");
+
+    let (status, _) = get_json(test_router(state.clone()), "/api/v1/search").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let resp = get(
+        test_router(state),
+        &format!("/api/v1/books/{}/download", book.id),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(!body_string(resp).await.is_empty());
+}
+
+/// With `opds.auth_required` anonymous requests get a JSON 401.
+#[tokio::test]
+async fn api_requires_auth_when_configured() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    let user_id = create_test_user(&pool, "api-user", "password123", false).await;
+    let state = test_app_state(pool, config);
+
+    let (status, json) = get_json(test_router(state.clone()), "/api/v1/books").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(json["error"]["code"], "unauthorized");
+
+    let resp = get_with_session(
+        test_router(state),
+        "/api/v1/books",
+        &session_cookie_value(user_id),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+/// A disabled account's session no longer authenticates API or GraphQL
+/// requests.
+#[tokio::test]
+async fn api_rejects_sessions_of_disabled_users() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    let user_id = create_test_user(&pool, "api-disabled", "password123", false).await;
+    let session = session_cookie_value(user_id);
+    ropds::db::queries::users::set_active(&pool, user_id, false)
+        .await
+        .unwrap();
+    let state = test_app_state(pool, config);
+
+    let resp = get_with_session(test_router(state.clone()), "/api/v1/books", &session).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = post_json(
+        test_router(state),
+        "/graphql",
+        serde_json::json!({ "query": "{ bookshelf { total } }" }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+/// `api.rest = false` removes the endpoints.
+#[tokio::test]
+async fn api_disabled_by_config() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.api.rest = false;
+    let state = test_app_state(pool, config);
+
+    let resp = get(test_router(state), "/api/v1/books").await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
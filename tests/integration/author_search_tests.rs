@@ -177,3 +177,134 @@ async fn opds_authors_list() {
         "should contain book-by-author links"
     );
 }
+
+/// With `alphabet_menu = false` the drill-down feeds go straight to flat
+/// paginated lists and the web browse page shows a plain letter bar.
+#[tokio::test]
+async fn alphabet_menu_disabled_serves_flat_lists() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.alphabet_menu = false;
+    config.opds.max_items = 1;
+
+    copy_test_files(
+        lib_dir.path(),
+        &[
+            "test_book.fb2",
+            "no_cover.fb2",
+            "author_no_genre.fb2",
+            "series_no_genre.fb2",
+        ],
+    );
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let state = test_app_state(pool, config);
+
+    let xml = body_string(get(test_router(state.clone()), "/opds/authors/0/").await).await;
+    assert!(
+        xml.contains("/opds/search/books/a/"),
+        "lists authors directly"
+    );
+    assert!(
+        xml.contains("/opds/authors/0/list/2/"),
+        "links the next page"
+    );
+
+    let resp = get(test_router(state.clone()), "/opds/authors/0/list/2/").await;
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(
+        xml.contains("/opds/authors/0/list/1/"),
+        "links the previous page"
+    );
+
+    let xml = body_string(get(test_router(state.clone()), "/opds/series/2/").await).await;
+    assert!(
+        xml.contains("/opds/search/books/s/"),
+        "lists series directly"
+    );
+
+    let xml = body_string(get(test_router(state.clone()), "/opds/books/2/").await).await;
+    assert!(xml.contains("<entry>"), "lists books directly");
+    assert!(xml.contains("/opds/books/2/list/2/"));
+
+    let resp = get(test_router(state.clone()), "/opds/v2/authors/0/").await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert!(
+        json["navigation"][0]["href"]
+            .as_str()
+            .unwrap()
+            .starts_with("/opds/v2/search/books/a/")
+    );
+
+    let html = body_string(get(test_router(state), "/web/authors?lang=2&chars=DO").await).await;
+    assert!(html.contains("alphabet-bar"));
+    assert!(!html.contains("prefix-grid"));
+}
+
+/// Author search types: `b` matches the start of the name (surname first),
+/// `m` any part of it and `e` the whole name, on the web and in OPDS.
+#[tokio::test]
+async fn author_search_types() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "no_cover.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let doe = ropds::db::queries::authors::find_by_name(&pool, "Doe John")
+        .await
+        .unwrap()
+        .expect("author 'Doe John' should exist");
+    // Result rows link to the author's books; the page footer may show a
+    // random book with its authors, so author names alone are ambiguous.
+    let doe_link = format!("type=a&q={}", doe.id);
+    let state = test_app_state(pool, config);
+
+    let html = body_string(
+        get(
+            test_router(state.clone()),
+            "/web/search/authors?type=m&q=John",
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains(&doe_link), "contains matches the first name");
+    let html = body_string(
+        get(
+            test_router(state.clone()),
+            "/web/search/authors?type=b&q=John",
+        )
+        .await,
+    )
+    .await;
+    assert!(!html.contains(&doe_link), "begins matches the surname only");
+    let html = body_string(
+        get(
+            test_router(state.clone()),
+            "/web/search/authors?type=b&q=Do",
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains(&doe_link));
+
+    let xml =
+        body_string(get(test_router(state.clone()), "/opds/search/authors/b/Do/").await).await;
+    assert!(xml.contains("Doe John"));
+    assert!(xml.contains("/opds/search/authors/b/Do/1/"));
+    let xml =
+        body_string(get(test_router(state.clone()), "/opds/search/authors/b/John/").await).await;
+    assert!(!xml.contains("Doe John"));
+    let xml =
+        body_string(get(test_router(state.clone()), "/opds/search/authors/e/Doe/").await).await;
+    assert!(!xml.contains("Doe John"), "exact needs the whole name");
+    let xml =
+        body_string(get(test_router(state), "/opds/search/authors/e/Doe%20John/").await).await;
+    assert!(xml.contains("Doe John"));
+}
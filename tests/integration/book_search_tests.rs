@@ -1,5 +1,5 @@
 use ropds::db;
-use ropds::db::queries::{authors, genres, series};
+use ropds::db::queries::{authors, books, genres, series};
 use ropds::scanner;
 
 use super::*;
@@ -258,6 +258,134 @@ async fn search_cyrillic_book_by_title() {
     );
 }
 
+/// With `search.translit`, Latin queries find Cyrillic titles and authors.
+#[tokio::test]
+async fn search_cyrillic_book_by_transliteration() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["cyrillic_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let state = test_app_state(pool.clone(), config.clone());
+    let resp = get(test_router(state), "/opds/search/books/m/Tayna/").await;
+    let xml = body_string(resp).await;
+    assert!(
+        !xml.contains("Тайна старого дома"),
+        "transliteration is off by default"
+    );
+
+    config.search.translit = true;
+    let state = test_app_state(pool, config);
+
+    let resp = get(
+        test_router(state.clone()),
+        "/web/search/books?type=m&q=Tayna",
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(
+        html.contains("book-card") && html.contains("Тайна старого дома"),
+        "Latin query should match"
+    );
+
+    let resp = get(
+        test_router(state.clone()),
+        "/web/search/books?type=m&q=Tayna&view=grid",
+    )
+    .await;
+    let html = body_string(resp).await;
+    assert!(
+        html.contains("book-grid-card"),
+        "grid view should use the same match"
+    );
+
+    let resp = get(test_router(state.clone()), "/opds/search/books/m/Tayna/").await;
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(xml.contains("Тайна старого дома"));
+
+    let resp = get(test_router(state), "/web/search/authors?type=m&q=Ivanov").await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("Иванов"), "Latin author query should match");
+}
+
+/// With `search.fuzzy`, misspelled title and author queries that match
+/// nothing fall back to similar entries.
+#[tokio::test]
+async fn fuzzy_search_tolerates_typos() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["title_only.fb2", "series_no_genre.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let state = test_app_state(pool.clone(), config.clone());
+    let xml = body_string(get(test_router(state), "/opds/search/books/m/Lonelly/").await).await;
+    assert!(
+        !xml.contains("Lonely Title Book"),
+        "fuzzy search is off by default"
+    );
+
+    config.search.fuzzy = true;
+    let state = test_app_state(pool, config);
+
+    let resp = get(
+        test_router(state.clone()),
+        "/web/search/books?type=m&q=Lonelly",
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("book-card") && html.contains("Lonely Title Book"));
+    assert!(html.contains("No exact matches"));
+
+    let xml =
+        body_string(get(test_router(state.clone()), "/opds/search/books/m/Lonelly/").await).await;
+    assert!(xml.contains("Lonely Title Book"));
+    assert!(!xml.contains("rel=\"next\""));
+
+    let resp = get(
+        test_router(state.clone()),
+        "/opds/v2/search/books/m/Lonelly/",
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(
+        json["publications"][0]["metadata"]["title"],
+        "Lonely Title Book"
+    );
+
+    let html = body_string(
+        get(
+            test_router(state.clone()),
+            "/web/search/authors?type=m&q=Serano",
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains("Serrano"), "misspelled author should match");
+
+    let xml =
+        body_string(get(test_router(state.clone()), "/opds/search/authors/m/Serano/").await).await;
+    assert!(xml.contains("Serrano"));
+
+    // Unrelated queries still find nothing.
+    let html =
+        body_string(get(test_router(state), "/web/search/authors?type=m&q=Zzyzx").await).await;
+    assert!(html.contains("No results found."));
+}
+
 /// Single book lookup by ID (type=i).
 #[tokio::test]
 async fn search_single_book_by_id() {
@@ -278,3 +406,439 @@ async fn search_single_book_by_id() {
     let html = body_string(resp).await;
     assert!(html.contains("Test Book Title"));
 }
+
+/// Cover-grid view: first page is rendered inline, the rest comes from the
+/// cursor API until `next_cursor` runs out.
+#[tokio::test]
+async fn book_grid_infinite_scroll() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let (pool, mut config, _lib, _cov) = setup_library().await;
+    config.opds.max_items = 2;
+    config.opds.hide_doubles = false;
+    let state = test_app_state(pool, config);
+    let app = test_router(state);
+
+    let resp = get(app.clone(), "/web/search/books?type=b&q=&view=grid").await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(
+        html.contains(r#"id="book-grid""#),
+        "grid container expected"
+    );
+    assert_eq!(html.matches("book-grid-card").count(), 2);
+
+    let marker = r#"data-next-cursor=""#;
+    let start = html.find(marker).expect("next cursor attribute") + marker.len();
+    let mut cursor = html[start..html[start..].find('"').unwrap() + start].to_string();
+    assert!(!cursor.is_empty());
+
+    let mut cards = 2;
+    while !cursor.is_empty() {
+        let resp = get(
+            app.clone(),
+            &format!("/web/api/books?view=grid&type=b&q=&cursor={cursor}"),
+        )
+        .await;
+        assert_eq!(resp.status(), 200);
+        let data: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+        cards += data["html"]
+            .as_str()
+            .unwrap()
+            .matches("book-grid-card")
+            .count();
+        cursor = data["next_cursor"].as_str().unwrap_or_default().to_string();
+    }
+    assert_eq!(cards, 5);
+
+    let resp = get(
+        app.clone(),
+        "/web/api/books?view=grid&type=b&q=&cursor=%21%21",
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let resp = get(app, "/web/api/books?view=grid&type=s&q=1").await;
+    assert_eq!(resp.status(), 400);
+}
+
+/// `?sort=` picks the listing order: the web dropdown marks it active and
+/// keeps it on pagination, OPDS feeds expose a "Sort by" facet group.
+#[tokio::test]
+async fn book_listing_sort_options() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let (pool, mut config, _lib, _cov) = setup_library().await;
+    config.opds.max_items = 2;
+    config.opds.hide_doubles = false;
+    let state = test_app_state(pool, config);
+
+    let html = body_string(
+        get(
+            test_router(state.clone()),
+            "/web/search/books?type=b&q=&sort=size",
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains("books-sort"), "sort dropdown expected");
+    assert!(html.contains(r#"q=&amp;sort=size" aria-current="true""#));
+    assert!(
+        html.contains("sort=size&amp;page=2"),
+        "pagination keeps the sort"
+    );
+
+    let xml = body_string(
+        get(
+            test_router(state.clone()),
+            "/opds/search/books/m/BOOK/?sort=recent",
+        )
+        .await,
+    )
+    .await;
+    assert!(xml.contains(r#"opds:facetGroup="Sort by""#));
+    assert!(xml.contains(
+        r#"href="/opds/search/books/m/BOOK/?sort=recent&amp;lang=en" rel="http://opds-spec.org/facet""#
+    ));
+    assert!(xml.contains("/opds/search/books/m/BOOK/2/?sort=recent&amp;lang=en"));
+
+    let resp = get(test_router(state), "/opds/v2/search/books/s/0/").await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert!(
+        json.get("facets").is_none(),
+        "no facets for an empty listing"
+    );
+}
+
+#[tokio::test]
+async fn suggest_groups_titles_authors_and_series_by_prefix() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["title_only.fb2", "series_no_genre.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let state = test_app_state(pool, config);
+
+    let resp = get(test_router(state.clone()), "/web/api/suggest?q=lo").await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["titles"][0]["name"], "Lonely Title Book");
+    let url = json["titles"][0]["url"].as_str().unwrap();
+    assert!(url.starts_with("/web/search/books?type=i&q="));
+    assert!(json["authors"].as_array().unwrap().is_empty());
+
+    let resp = get(test_router(state.clone()), "/web/api/suggest?q=ser").await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["titles"][0]["name"], "Series Without Genre");
+    assert!(
+        json["authors"][0]["name"]
+            .as_str()
+            .unwrap()
+            .starts_with("Serrano")
+    );
+    assert!(json["series"].as_array().unwrap().is_empty());
+
+    let resp = get(test_router(state.clone()), "/web/api/suggest?q=genreless").await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["series"][0]["name"], "Genreless Adventures");
+    let url = json["series"][0]["url"].as_str().unwrap();
+    assert!(url.starts_with("/web/search/books?type=s&q="));
+
+    // Book pages behind the suggestion links resolve.
+    let resp = get(test_router(state.clone()), url).await;
+    assert_eq!(resp.status(), 200);
+    assert!(body_string(resp).await.contains("Series Without Genre"));
+
+    let resp = get(test_router(state), "/web/api/suggest?q=s").await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert!(json["titles"].as_array().unwrap().is_empty());
+}
+
+/// OPDS title searches offer genre, language and format facets and narrow
+/// the results by the selected facet parameters.
+#[tokio::test]
+async fn opds_search_facets_filter_results() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let (pool, config, _lib, _cov) = setup_library().await;
+    let genre = genres::get_by_code(&pool, "sf_fantasy")
+        .await
+        .unwrap()
+        .expect("sf_fantasy genre should exist");
+    let state = test_app_state(pool, config);
+
+    let xml = body_string(
+        get(
+            test_router(state.clone()),
+            "/opds/search/books/m/Test%20Book/",
+        )
+        .await,
+    )
+    .await;
+    assert!(xml.contains("Test Book Title") && xml.contains("EPUB Test Book"));
+    assert!(xml.contains("opds:facetGroup=\"Format\""));
+    assert!(xml.contains("/opds/search/books/m/Test%20Book/?format=epub"));
+
+    let xml = body_string(
+        get(
+            test_router(state.clone()),
+            "/opds/search/books/m/Test%20Book/?format=epub",
+        )
+        .await,
+    )
+    .await;
+    assert!(xml.contains("EPUB Test Book"));
+    assert!(!xml.contains("Test Book Title"));
+
+    let xml = body_string(
+        get(
+            test_router(state.clone()),
+            &format!("/opds/search/books/m/Test%20Book/?genre={}", genre.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(xml.contains("Test Book Title"));
+    assert!(!xml.contains("EPUB Test Book"));
+    assert!(xml.contains("opds:activeFacet=\"true\""));
+
+    let resp = get(
+        test_router(state.clone()),
+        "/opds/v2/search/books/m/Test%20Book/?format=fb2&language=en",
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let titles: Vec<_> = json["publications"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["metadata"]["title"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(titles, ["Test Book Title"]);
+    let format_group = json["facets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|f| f["metadata"]["title"] == "Format")
+        .expect("format facet group");
+    let active: Vec<_> = format_group["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|l| l["rel"] == "self")
+        .collect();
+    assert_eq!(active.len(), 1);
+    assert!(active[0]["title"].as_str().unwrap().starts_with("FB2"));
+}
+
+/// Titles of the book entries in an OPDS 1.2 or 2.0 search feed.
+async fn opds_search_titles(state: &ropds::state::AppState, url: &str) -> Vec<String> {
+    let resp = get(test_router(state.clone()), url).await;
+    assert_eq!(resp.status(), 200, "{url}");
+    let body = body_string(resp).await;
+    let mut titles: Vec<String> = if url.starts_with("/opds/v2/") {
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        json["publications"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["metadata"]["title"].as_str().unwrap().to_string())
+            .collect()
+    } else {
+        body.split("<entry>")
+            .skip(1)
+            .filter_map(|entry| {
+                let start = entry.find("<title>")? + "<title>".len();
+                let end = entry[start..].find("</title>")? + start;
+                Some(entry[start..end].to_string())
+            })
+            .collect()
+    };
+    titles.sort();
+    titles
+}
+
+/// Title search types `m` (contains), `b` (a word begins) and `e` (whole
+/// title), plus the ID listings, in both OPDS versions.
+#[tokio::test]
+async fn opds_search_types() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let (pool, config, _lib, _cov) = setup_library().await;
+    let author = authors::find_by_name(&pool, "Doe John")
+        .await
+        .unwrap()
+        .expect("author 'Doe John' should exist");
+    let state = test_app_state(pool, config);
+
+    for base in ["/opds/search/books", "/opds/v2/search/books"] {
+        let titles = opds_search_titles(&state, &format!("{base}/m/ook/")).await;
+        assert_eq!(titles.len(), 5, "{base}: contains matches inside words");
+        assert!(
+            opds_search_titles(&state, &format!("{base}/b/ook/"))
+                .await
+                .is_empty(),
+            "{base}: begins needs a word start"
+        );
+        assert_eq!(
+            opds_search_titles(&state, &format!("{base}/b/test/")).await,
+            ["EPUB Test Book", "Test Book Title"],
+            "{base}: begins matches any word"
+        );
+        assert!(
+            opds_search_titles(&state, &format!("{base}/e/Lonely/"))
+                .await
+                .is_empty(),
+            "{base}: exact needs the whole title"
+        );
+        assert_eq!(
+            opds_search_titles(&state, &format!("{base}/e/lonely%20title%20book/")).await,
+            ["Lonely Title Book"],
+            "{base}: exact ignores case"
+        );
+        assert_eq!(
+            opds_search_titles(&state, &format!("{base}/a/{}/", author.id)).await,
+            ["Test Book Title"],
+            "{base}: books by author ID"
+        );
+    }
+}
+
+/// Title searches limited to a catalog subtree, an author or a series.
+#[tokio::test]
+async fn scoped_title_search() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "test_book.epub"]);
+    copy_test_files_to_subdir(
+        lib_dir.path(),
+        "shelf/nested",
+        &["no_cover.fb2", "title_only.fb2"],
+    );
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let shelf = ropds::db::queries::catalogs::find_by_path(&pool, "shelf")
+        .await
+        .unwrap()
+        .expect("shelf catalog");
+    let author = authors::find_by_name(&pool, "Doe John")
+        .await
+        .unwrap()
+        .expect("author 'Doe John' should exist");
+    let outside = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let state = test_app_state(pool, config);
+    let in_shelf = ["Lonely Title Book", "No Cover Book"];
+
+    assert_eq!(
+        opds_search_titles(&state, &format!("/opds/catalogs/{}/search/Book/", shelf.id)).await,
+        in_shelf,
+        "catalog search covers subfolders only"
+    );
+    for base in ["/opds/search/books", "/opds/v2/search/books"] {
+        assert_eq!(
+            opds_search_titles(&state, &format!("{base}/m/Book/?scope=c:{}", shelf.id)).await,
+            in_shelf,
+            "{base}: catalog scope"
+        );
+        assert_eq!(
+            opds_search_titles(&state, &format!("{base}/m/Book/?scope=a:{}", author.id)).await,
+            ["Test Book Title"],
+            "{base}: author scope"
+        );
+    }
+
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/opds/catalogs/{}/", shelf.id),
+    )
+    .await;
+    let xml = body_string(resp).await;
+    assert!(
+        xml.contains(&format!(
+            "/opds/catalogs/{}/search/{{searchTerms}}/",
+            shelf.id
+        )),
+        "catalog feed advertises a scoped search template"
+    );
+
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/web/search/books?type=m&q=Book&scope=c:{}", shelf.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("No Cover Book"));
+    assert!(html.contains("Lonely Title Book"));
+    assert!(!html.contains(&format!("/web/download/{}/", outside.id)));
+    assert!(html.contains(&format!("name=\"scope\" value=\"c:{}\"", shelf.id)));
+
+    let resp = get(
+        test_router(state),
+        &format!("/web/catalogs?cat_id={}", shelf.id),
+    )
+    .await;
+    let html = body_string(resp).await;
+    assert!(html.contains(&format!("name=\"scope\" value=\"c:{}\"", shelf.id)));
+}
+
+/// Search inside book texts (type=t) with `search.fulltext` on: the scan
+/// indexes FB2 and EPUB bodies, and both web and OPDS find words of them.
+#[tokio::test]
+async fn search_books_inside_text() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.search.fulltext = true;
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "test_book.epub"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let epub = books::find_by_path_and_filename(&pool, "", "test_book.epub")
+        .await
+        .unwrap()
+        .unwrap();
+    let epub_link = format!("/web/download/{}/", epub.id);
+    let state = test_app_state(pool, config);
+
+    // "testing" is in both bodies, "paragr" starts a word of the FB2 only
+    let resp = get(
+        test_router(state.clone()),
+        "/web/search/books?type=t&q=Testing",
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("Test Book Title"));
+    assert!(html.contains(&epub_link));
+    assert!(html.contains(r#"id="st-text""#));
+
+    let resp = get(
+        test_router(state.clone()),
+        "/web/search/books?type=t&q=paragr+testing",
+    )
+    .await;
+    let html = body_string(resp).await;
+    assert!(html.contains("Test Book Title"));
+    assert!(!html.contains(&epub_link));
+
+    // Only the body is indexed, not the description
+    assert!(
+        opds_search_titles(&state, "/opds/search/books/t/Writer/")
+            .await
+            .is_empty()
+    );
+    assert_eq!(
+        opds_search_titles(&state, "/opds/search/books/t/sample/").await,
+        ["EPUB Test Book"]
+    );
+
+    let xml = body_string(get(test_router(state), "/opds/search/sample/").await).await;
+    assert!(xml.contains("/opds/search/books/t/sample/"));
+}
@@ -1,9 +1,19 @@
+use base64::Engine;
 use ropds::db;
 use ropds::db::queries::{bookshelf, reading_positions};
 use ropds::scanner;
+use tower::ServiceExt;
 
 use super::*;
 
+fn basic_auth(username: &str, password: &str) -> String {
+    let raw = format!("{username}:{password}");
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(raw.as_bytes())
+    )
+}
+
 /// Helper: set up a scanned library with a test user and return (pool, config, user_id, session).
 async fn setup_with_user() -> (
     db::DbPool,
@@ -207,6 +217,155 @@ async fn bookshelf_sorting() {
     assert_eq!(resp2.status(), 200);
 }
 
+/// The read-state filter applies to the web page, the export and the OPDS
+/// feed, which also offers sort and state facets.
+#[tokio::test]
+async fn bookshelf_read_state_filter_and_export() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let (pool, mut config, user_id, session, _lib, _cov) = setup_with_user().await;
+    config.opds.auth_required = true;
+
+    let fb2 = ropds::db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let epub = ropds::db::queries::books::find_by_path_and_filename(&pool, "", "test_book.epub")
+        .await
+        .unwrap()
+        .unwrap();
+    bookshelf::upsert(&pool, user_id, fb2.id).await.unwrap();
+    bookshelf::upsert(&pool, user_id, epub.id).await.unwrap();
+    reading_positions::save_position(&pool, user_id, fb2.id, "end", 1.0, 100)
+        .await
+        .unwrap();
+
+    let state = test_app_state(pool, config);
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/bookshelf?state=finished",
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    // The footer's random book may be either one; look at the shelf grid only
+    let grid = &html[html.find("bookshelf-grid").unwrap()..html.find("bookshelf-loader").unwrap()];
+    assert!(grid.contains("Test Book Title"));
+    assert!(!grid.contains("EPUB Test Book"));
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/bookshelf/export?format=csv&sort=title&dir=asc",
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let csv = body_string(resp).await;
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "id,title,authors,series,format,lang,added,progress"
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].contains("EPUB Test Book"));
+    assert!(lines[2].contains("Test Book Title") && lines[2].ends_with(",100"));
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/bookshelf/export?format=json&state=unread",
+        &session,
+    )
+    .await;
+    let rows: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(rows.as_array().unwrap().len(), 1);
+    assert_eq!(rows[0]["id"], epub.id);
+    assert!(rows[0]["progress_pct"].is_null());
+
+    let resp = get(test_router(state.clone()), "/web/bookshelf/export").await;
+    assert_eq!(
+        resp.status(),
+        303,
+        "anonymous export should redirect to login"
+    );
+
+    let req = axum::http::Request::builder()
+        .uri("/opds/bookshelf/?state=unread")
+        .header("authorization", basic_auth("testuser", "password123"))
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let resp = test_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(xml.contains("EPUB Test Book"));
+    assert!(!xml.contains("Test Book Title"));
+    assert!(xml.contains("/opds/bookshelf/?sort=title&amp;state=unread"));
+    assert!(xml.contains("/opds/bookshelf/?state=finished"));
+}
+
+/// Downloads put books on the shelf unless `opds.auto_bookshelf` is off or
+/// the user switched it off on the profile page.
+#[tokio::test]
+async fn bookshelf_auto_add_on_download_respects_opt_out() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let (pool, mut config, user_id, session, _lib, _cov) = setup_with_user().await;
+    let fb2 = ropds::db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let epub = ropds::db::queries::books::find_by_path_and_filename(&pool, "", "test_book.epub")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let state = test_app_state(pool.clone(), config.clone());
+    let uri = format!("/web/download/{}/0", fb2.id);
+    let resp = get_with_session(test_router(state.clone()), &uri, &session).await;
+    assert_eq!(resp.status(), 200);
+    assert!(
+        bookshelf::is_on_shelf(&pool, user_id, fb2.id)
+            .await
+            .unwrap()
+    );
+
+    // The user opts out on the profile page
+    let body = format!("csrf_token={}", csrf_for_session(&session));
+    let resp = post_form(
+        test_router(state.clone()),
+        "/web/profile/auto-bookshelf",
+        &body,
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    let uri = format!("/web/download/{}/0", epub.id);
+    let resp = get_with_session(test_router(state), &uri, &session).await;
+    assert_eq!(resp.status(), 200);
+    assert!(
+        !bookshelf::is_on_shelf(&pool, user_id, epub.id)
+            .await
+            .unwrap()
+    );
+
+    // Opted back in, but switched off server-wide
+    ropds::db::queries::users::update_auto_bookshelf(&pool, user_id, true)
+        .await
+        .unwrap();
+    config.opds.auto_bookshelf = false;
+    let state = test_app_state(pool.clone(), config);
+    let resp = get_with_session(test_router(state), &uri, &session).await;
+    assert_eq!(resp.status(), 200);
+    assert!(
+        !bookshelf::is_on_shelf(&pool, user_id, epub.id)
+            .await
+            .unwrap()
+    );
+}
+
 /// Bookshelf requires authentication when auth_required is true.
 #[tokio::test]
 async fn bookshelf_requires_auth() {
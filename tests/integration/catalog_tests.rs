@@ -60,3 +60,454 @@ async fn catalog_drill_down_shows_books() {
         "should show the book title in catalog view"
     );
 }
+
+/// Nested catalogs render a breadcrumb trail on the web and `up` links in OPDS.
+#[tokio::test]
+async fn nested_catalog_breadcrumbs_and_up_links() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files_to_subdir(lib_dir.path(), "shelf/nested", &["test_book.fb2"]);
+
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let parent = ropds::db::queries::catalogs::find_by_path(&pool, "shelf")
+        .await
+        .unwrap()
+        .expect("shelf catalog should exist");
+    let nested = ropds::db::queries::catalogs::find_by_path(&pool, "shelf/nested")
+        .await
+        .unwrap()
+        .expect("nested catalog should exist");
+
+    let state = test_app_state(pool, config);
+
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/web/catalogs?cat_id={}", nested.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains(r#"<ol class="breadcrumb mb-0">"#));
+    assert!(html.contains(&format!(r#"href="/web/catalogs?cat_id={}""#, parent.id)));
+    assert!(html.contains(r#"<li class="breadcrumb-item active" aria-current="page">nested</li>"#));
+
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/opds/catalogs/{}/", nested.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(
+        xml.contains(&format!(
+            r#"<link href="/opds/catalogs/{}/?lang=en" rel="up""#,
+            parent.id
+        )),
+        "nested feed should link up to its parent: {xml}"
+    );
+
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/opds/catalogs/{}/", parent.id),
+    )
+    .await;
+    let xml = body_string(resp).await;
+    assert!(xml.contains(r#"<link href="/opds/catalogs/?lang=en" rel="up""#));
+
+    let resp = get(
+        test_router(state),
+        &format!("/opds/v2/catalogs/{}/", nested.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let up = json["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|l| l["rel"] == "up")
+        .expect("OPDS 2.0 feed should have an up link");
+    assert_eq!(
+        up["href"],
+        format!("/opds/v2/catalogs/{}/?lang=en", parent.id)
+    );
+    assert_eq!(up["title"], "shelf");
+}
+
+/// `?deep=1` (or `opds.deep_catalogs`) lists books from the whole subtree.
+#[tokio::test]
+async fn catalog_deep_lists_subtree_books() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files_to_subdir(lib_dir.path(), "shelf/nested", &["test_book.fb2"]);
+
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let shelf = ropds::db::queries::catalogs::find_by_path(&pool, "shelf")
+        .await
+        .unwrap()
+        .expect("shelf catalog should exist");
+
+    let state = test_app_state(pool.clone(), config.clone());
+
+    let html = body_string(
+        get(
+            test_router(state.clone()),
+            &format!("/web/catalogs?cat_id={}", shelf.id),
+        )
+        .await,
+    )
+    .await;
+    // The footer may show the book as a random pick, so look for a catalog entry.
+    assert!(!html.contains("bi-file-text"));
+    assert!(html.contains("deep=1"), "should offer the subfolder toggle");
+
+    let html = body_string(
+        get(
+            test_router(state.clone()),
+            &format!("/web/catalogs?cat_id={}&deep=1", shelf.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains("bi-file-text"));
+    assert!(html.contains("/web/catalogs?deep=1&amp;cat_id="));
+
+    let xml = body_string(
+        get(
+            test_router(state.clone()),
+            &format!("/opds/catalogs/{}/?deep=1", shelf.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(xml.contains("Test Book Title"));
+
+    let resp = get(
+        test_router(state),
+        &format!("/opds/v2/catalogs/{}/?deep=1", shelf.id),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["publications"].as_array().map(Vec::len), Some(1));
+
+    // The config default flattens; an explicit ?deep=0 switches it off again.
+    let mut deep_config = config;
+    deep_config.opds.deep_catalogs = true;
+    let state = test_app_state(pool, deep_config);
+    let xml = body_string(
+        get(
+            test_router(state.clone()),
+            &format!("/opds/catalogs/{}/", shelf.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(xml.contains("Test Book Title"));
+    let xml = body_string(
+        get(
+            test_router(state),
+            &format!("/opds/catalogs/{}/?deep=0", shelf.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(!xml.contains("Test Book Title"));
+}
+
+/// A scan builds a cover mosaic per catalog; it is served publicly, shown in
+/// the web catalog list and, when enabled, attached to OPDS catalog entries.
+#[tokio::test]
+async fn catalog_cover_mosaics_after_scan() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.catalog_thumbnails = true;
+
+    copy_test_files_to_subdir(
+        lib_dir.path(),
+        "covered",
+        &["test_book.fb2", "test_book.epub"],
+    );
+    copy_test_files_to_subdir(lib_dir.path(), "bare", &["no_cover.fb2"]);
+
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let covered = ropds::db::queries::catalogs::find_by_path(&pool, "covered")
+        .await
+        .unwrap()
+        .unwrap();
+    let bare = ropds::db::queries::catalogs::find_by_path(&pool, "bare")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(scanner::catalog_mosaic_path(covers_dir.path(), covered.id).is_file());
+    assert!(!scanner::catalog_mosaic_path(covers_dir.path(), bare.id).exists());
+
+    let state = test_app_state(pool, config);
+    let thumb = format!("/opds/catalog-thumb/{}/", covered.id);
+    let resp = get(test_router(state.clone()), &thumb).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "image/jpeg");
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/opds/catalog-thumb/{}/", bare.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 404);
+
+    let html = body_string(get(test_router(state.clone()), "/web/catalogs").await).await;
+    assert!(html.contains(&thumb));
+    assert!(!html.contains(&format!("/opds/catalog-thumb/{}/", bare.id)));
+
+    let xml = body_string(get(test_router(state), "/opds/catalogs/").await).await;
+    assert!(xml.contains(&format!(
+        r#"href="{thumb}" rel="http://opds-spec.org/image/thumbnail""#
+    )));
+}
+
+/// With `covers.signed_urls` on a private library, mosaics need a signed
+/// link like book covers; the web catalog list signs its links.
+#[tokio::test]
+async fn catalog_mosaics_need_signed_links_in_private_libraries() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    copy_test_files_to_subdir(lib_dir.path(), "covered", &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let covered = ropds::db::queries::catalogs::find_by_path(&pool, "covered")
+        .await
+        .unwrap()
+        .unwrap();
+    let user_id = create_test_user(&pool, "mosaic-user", "password123", false).await;
+    config.opds.auth_required = true;
+    config.covers.signed_urls = true;
+    let state = test_app_state(pool, config);
+
+    let thumb = format!("/opds/catalog-thumb/{}/", covered.id);
+    let resp = get(test_router(state.clone()), &thumb).await;
+    assert_eq!(resp.status(), 403);
+
+    let html = body_string(
+        get_with_session(
+            test_router(state.clone()),
+            "/web/catalogs",
+            &session_cookie_value(user_id),
+        )
+        .await,
+    )
+    .await;
+    let start = html
+        .find(&format!("{thumb}?exp="))
+        .expect("signed mosaic link");
+    let end = start + html[start..].find('"').unwrap();
+    let href = html[start..end].replace("&amp;", "&");
+
+    let resp = get(test_router(state.clone()), &href).await;
+    assert_eq!(resp.status(), 200);
+    let other = href.replace(&thumb, &format!("/opds/catalog-thumb/{}/", covered.id + 1));
+    assert_eq!(get(test_router(state), &other).await.status(), 403);
+}
+
+/// Admins can rename and re-parent catalogs; browsing follows the new
+/// structure and a rescan keeps it.
+#[tokio::test]
+async fn admin_catalog_rename_and_move() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files_to_subdir(lib_dir.path(), "shelf/nested", &["test_book.fb2"]);
+    copy_test_files_to_subdir(lib_dir.path(), "other", &["test_book.epub"]);
+
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let nested = ropds::db::queries::catalogs::find_by_path(&pool, "shelf/nested")
+        .await
+        .unwrap()
+        .unwrap();
+    let other = ropds::db::queries::catalogs::find_by_path(&pool, "other")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let super_id = create_test_user(&pool, "admin-cat", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config.clone());
+
+    let resp = post_json(
+        test_router(state.clone()),
+        "/web/admin/catalog-rename",
+        serde_json::json!({"catalog_id": nested.id, "name": "  Favourites ", "csrf_token": csrf}),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["cat_name"], "Favourites");
+
+    let resp = post_json(
+        test_router(state.clone()),
+        "/web/admin/catalog-move",
+        serde_json::json!({"catalog_id": nested.id, "parent_id": other.id, "csrf_token": csrf}),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = post_json(
+        test_router(state.clone()),
+        "/web/admin/catalog-move",
+        serde_json::json!({"catalog_id": other.id, "parent_id": nested.id, "csrf_token": csrf}),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["error"], "error_catalog_cycle");
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/admin/catalog-search?q=favour",
+        &session,
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["catalogs"][0]["id"], nested.id);
+    assert_eq!(json["catalogs"][0]["path"], "shelf/nested");
+
+    // A rescan leaves the admin's arrangement alone.
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let moved = ropds::db::queries::catalogs::get_by_id(&pool, nested.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(moved.parent_id, Some(other.id));
+    assert_eq!(moved.cat_name, "Favourites");
+
+    let html = body_string(
+        get_with_session(
+            test_router(state.clone()),
+            &format!("/web/catalogs?cat_id={}", other.id),
+            &session,
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains("Favourites"));
+    assert!(html.contains("catalogEditModal"));
+
+    let xml = body_string(
+        get(
+            test_router(state.clone()),
+            &format!("/opds/catalogs/{}/?deep=1", other.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(xml.contains("Test Book Title"));
+    assert!(xml.contains("EPUB Test Book"));
+
+    let xml = body_string(
+        get(
+            test_router(state),
+            &format!("/opds/catalogs/{}/", nested.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(
+        xml.contains(&format!(
+            r#"<link href="/opds/catalogs/{}/?lang=en" rel="up""#,
+            other.id
+        )),
+        "moved feed should link up to its new parent: {xml}"
+    );
+}
+
+/// `scanner.exclude` globs keep folders out of the scan; a catalog an admin
+/// hides is skipped by later scans and left out of browsing.
+#[tokio::test]
+async fn excluded_and_hidden_catalogs() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.scanner.exclude = vec!["**/samples/**".to_string()];
+
+    copy_test_files_to_subdir(lib_dir.path(), "fiction", &["test_book.fb2"]);
+    copy_test_files_to_subdir(lib_dir.path(), "fiction/samples", &["no_cover.fb2"]);
+    copy_test_files_to_subdir(lib_dir.path(), "junk", &["test_book.epub"]);
+
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let find = |path: &'static str| {
+        let pool = pool.clone();
+        async move {
+            ropds::db::queries::catalogs::find_by_path(&pool, path)
+                .await
+                .unwrap()
+        }
+    };
+    assert!(find("fiction/samples").await.is_none());
+    let junk = find("junk").await.expect("junk catalog should exist");
+    assert_eq!(
+        ropds::db::queries::books::count_by_catalog(&pool, junk.id, false)
+            .await
+            .unwrap(),
+        1
+    );
+
+    let super_id = create_test_user(&pool, "admin-hide", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config.clone());
+
+    let resp = post_json(
+        test_router(state.clone()),
+        "/web/admin/catalog-hidden",
+        serde_json::json!({"catalog_id": junk.id, "hidden": true, "csrf_token": csrf}),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let junk = find("junk")
+        .await
+        .expect("a hidden catalog outlives its books");
+    assert_eq!(junk.cat_hidden, 1);
+    assert_eq!(
+        ropds::db::queries::books::count_by_catalog(&pool, junk.id, false)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let xml = body_string(get(test_router(state.clone()), "/opds/catalogs/").await).await;
+    assert!(xml.contains("fiction"));
+    assert!(!xml.contains("junk"));
+
+    let html = body_string(get(test_router(state.clone()), "/web/catalogs").await).await;
+    assert!(!html.contains(&format!("cat_id={}", junk.id)));
+    let html =
+        body_string(get_with_session(test_router(state), "/web/catalogs", &session).await).await;
+    assert!(html.contains(&format!(r#"href="/web/catalogs?cat_id={}""#, junk.id)));
+}
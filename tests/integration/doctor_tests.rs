@@ -0,0 +1,78 @@
+use ropds::doctor::{self, CheckStatus};
+
+fn write_config(dir: &std::path::Path, root: &std::path::Path) -> std::path::PathBuf {
+    let path = dir.join("config.toml");
+    let covers = dir.join("covers");
+    std::fs::write(
+        &path,
+        format!(
+            r#"
+[server]
+session_secret = "doctor-test-secret"
+base_url = "http://localhost:8081"
+
+[library]
+root_path = {root:?}
+
+[covers]
+covers_path = {covers:?}
+
+[database]
+url = "sqlite::memory:"
+
+[opds]
+
+[scanner]
+"#
+        ),
+    )
+    .unwrap();
+    path
+}
+
+/// A healthy setup passes; external tools and a fresh database only warn.
+#[tokio::test]
+async fn doctor_reports_healthy_setup() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("books");
+    std::fs::create_dir(&root).unwrap();
+
+    let report = doctor::run(&write_config(dir.path(), &root)).await;
+    assert!(report.passed(), "{}", report.render());
+    let status = |name: &str| {
+        report
+            .checks
+            .iter()
+            .find(|c| c.name == name)
+            .unwrap_or_else(|| panic!("missing check {name}"))
+            .status
+    };
+    for name in [
+        "config",
+        "library",
+        "covers path",
+        "database",
+        "templates",
+        "locales",
+    ] {
+        assert_eq!(status(name), CheckStatus::Pass, "{name}");
+    }
+    assert_eq!(status("migrations"), CheckStatus::Warn);
+    assert!(dir.path().join("covers").is_dir());
+}
+
+/// Problems are all reported instead of stopping at the first one.
+#[tokio::test]
+async fn doctor_reports_unreadable_library() {
+    let dir = tempfile::tempdir().unwrap();
+    let report = doctor::run(&write_config(dir.path(), &dir.path().join("missing"))).await;
+    assert!(!report.passed());
+    let library = report.checks.iter().find(|c| c.name == "library").unwrap();
+    assert_eq!(library.status, CheckStatus::Fail);
+    assert!(report.checks.iter().any(|c| c.name == "locales"));
+    assert!(report.render().contains("[FAIL] library"));
+
+    let report = doctor::run(&dir.path().join("nope.toml")).await;
+    assert_eq!(report.checks.len(), 1);
+    assert!(!report.passed());
+}
@@ -0,0 +1,158 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use ropds::db;
+use ropds::db::queries::{books, bookshelf};
+use ropds::scanner;
+use tower::ServiceExt;
+
+use super::*;
+
+async fn graphql(app: axum::Router, query: &str, session: Option<&str>) -> (StatusCode, String) {
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json");
+    if let Some(session) = session {
+        req = req.header("cookie", format!("session={session}"));
+    }
+    let body = serde_json::json!({ "query": query }).to_string();
+    let resp = app
+        .oneshot(req.body(Body::from(body)).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    (status, body_string(resp).await)
+}
+
+/// Catalog queries resolve nested authors and series; per-user fields need
+/// a session.
+#[tokio::test]
+async fn graphql_queries_catalog_and_bookshelf() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let user_id = create_test_user(&pool, "graphql-user", "password123", false).await;
+    bookshelf::upsert(&pool, user_id, book.id).await.unwrap();
+    let session = session_cookie_value(user_id);
+    let state = test_app_state(pool, config);
+
+    let (status, body) = graphql(
+        test_router(state.clone()),
+        r#"{ searchBooks(query: "test book") { total items { title authors { name } series { number series { name } } } } }"#,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["data"]["searchBooks"]["total"], 1);
+    let found = &json["data"]["searchBooks"]["items"][0];
+    assert_eq!(found["title"], "Test Book Title");
+    let mut names: Vec<&str> = found["authors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["name"].as_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Doe John", "Smith Jane"]);
+    assert_eq!(found["series"][0]["series"]["name"], "Test Series");
+    assert_eq!(found["series"][0]["number"], 1);
+
+    // Anonymous callers get an error for their bookshelf.
+    let (_, body) = graphql(test_router(state.clone()), "{ bookshelf { total } }", None).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["errors"][0]["message"], "Authentication required");
+
+    let (_, body) = graphql(
+        test_router(state),
+        "{ me { username } bookshelf { total items { id onBookshelf } } }",
+        Some(&session),
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["data"]["me"]["username"], "graphql-user");
+    assert_eq!(json["data"]["bookshelf"]["total"], 1);
+    assert_eq!(json["data"]["bookshelf"]["items"][0]["id"], book.id);
+    assert_eq!(json["data"]["bookshelf"]["items"][0]["onBookshelf"], true);
+}
+
+/// With `opds.auth_required` the endpoint refuses anonymous callers and
+/// accepts OPDS Basic credentials.
+#[tokio::test]
+async fn graphql_requires_auth_when_configured() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    create_test_user(&pool, "graphql-basic", "password123", false).await;
+    let state = test_app_state(pool, config);
+
+    let (status, _) = graphql(test_router(state.clone()), "{ me { id } }", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/graphql")
+        .header("content-type", "application/json")
+        .header(
+            "authorization",
+            "Basic Z3JhcGhxbC1iYXNpYzpwYXNzd29yZDEyMw==",
+        )
+        .body(Body::from(r#"{"query":"{ me { username } }"}"#))
+        .unwrap();
+    let resp = test_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["data"]["me"]["username"], "graphql-basic");
+}
+
+/// `api.graphql = false` removes the endpoint.
+#[tokio::test]
+async fn graphql_disabled_by_config() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.api.graphql = false;
+    let state = test_app_state(pool, config);
+
+    let (status, _) = graphql(test_router(state), "{ me { id } }", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+/// Bodies above `server.web_body_limit_kb` are refused before the query runs.
+#[tokio::test]
+async fn graphql_rejects_body_over_web_limit() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.server.web_body_limit_kb = 1;
+    let state = test_app_state(pool, config);
+
+    let padding = " ".repeat(2048);
+    let (status, _) = graphql(
+        test_router(state.clone()),
+        &format!("{{ searchBooks(query: \"x\") {{ total }} }}{padding}"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    let (status, _) = graphql(
+        test_router(state),
+        r#"{ searchBooks(query: "x") { total } }"#,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,145 @@
+use std::io::Write;
+
+use http_body_util::BodyExt;
+use ropds::config::ServerProfile;
+use ropds::db;
+use ropds::db::queries::books;
+use ropds::opds::download::DOWNLOAD_CHUNK_BYTES;
+use ropds::scanner;
+
+use super::*;
+
+fn low_memory_config(lib_dir: &Path, covers_dir: &Path) -> Config {
+    let mut config = test_config(lib_dir, covers_dir);
+    config.server.profile = ServerProfile::LowMemory;
+    config.apply_profile();
+    config
+}
+
+/// An FB2 book padded past `size` bytes with whitespace.
+fn padded_fb2(size: usize) -> Vec<u8> {
+    let fb2 = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    let fb2 = String::from_utf8(fb2).unwrap();
+    let padding = " ".repeat(size);
+    fb2.replace("</FictionBook>", &format!("{padding}</FictionBook>"))
+        .into_bytes()
+}
+
+/// The low-memory scan parses ZIP entries one by one: FB2 is streamed from
+/// the archive whatever its size, and other books over the buffer limit get
+/// filename metadata instead of being read into memory.
+#[tokio::test]
+async fn low_memory_scan_bounds_zip_entry_buffers() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = low_memory_config(lib_dir.path(), covers_dir.path());
+    assert_eq!(config.scanner.workers_num, 1);
+
+    let over_limit = scanner::LOW_MEMORY_ENTRY_BUFFER_BYTES as usize + 1;
+    let file = std::fs::File::create(lib_dir.path().join("archive.zip")).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    let opts = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file("big.fb2", opts).unwrap();
+    zip.write_all(&padded_fb2(over_limit)).unwrap();
+    zip.start_file("huge.mobi", opts).unwrap();
+    zip.write_all(&vec![0; over_limit]).unwrap();
+    zip.start_file("small.epub", opts).unwrap();
+    zip.write_all(&std::fs::read(test_data_dir().join("test_book.epub")).unwrap())
+        .unwrap();
+    zip.finish().unwrap();
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 3);
+
+    let big = books::find_by_path_and_filename(&pool, "archive.zip", "big.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(big.title, "Test Book Title", "FB2 is parsed as a stream");
+    let huge = books::find_by_path_and_filename(&pool, "archive.zip", "huge.mobi")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(huge.title, "huge", "oversized entries are not buffered");
+    let small = books::find_by_path_and_filename(&pool, "archive.zip", "small.epub")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_ne!(small.title, "small", "small entries are still parsed");
+}
+
+/// Low-memory downloads of plain files are streamed in bounded chunks and
+/// honor Range requests.
+#[tokio::test]
+async fn low_memory_download_streams_in_chunks() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = low_memory_config(lib_dir.path(), covers_dir.path());
+
+    let data = padded_fb2(1024 * 1024);
+    std::fs::write(lib_dir.path().join("big.fb2"), &data).unwrap();
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "big.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let state = test_app_state(pool, config);
+    for path in [
+        format!("/opds/download/{}/0/", book.id),
+        format!("/web/download/{}/0", book.id),
+    ] {
+        let resp = get(test_router(state.clone()), &path).await;
+        assert_eq!(resp.status(), 200, "{path}");
+        assert_eq!(resp.headers()["content-length"], data.len().to_string());
+        let disposition = resp.headers()["content-disposition"].to_str().unwrap();
+        assert!(disposition.starts_with("attachment; filename=\"Test Book Title.fb2\""));
+
+        let mut body = resp.into_body();
+        let mut total = 0;
+        let mut frames = 0;
+        while let Some(frame) = body.frame().await {
+            let chunk = frame.unwrap().into_data().unwrap();
+            assert!(
+                chunk.len() <= DOWNLOAD_CHUNK_BYTES,
+                "{path}: {}",
+                chunk.len()
+            );
+            total += chunk.len();
+            frames += 1;
+        }
+        assert_eq!(total, data.len());
+        assert!(frames > 1);
+    }
+
+    let req = axum::http::Request::builder()
+        .uri(format!("/opds/download/{}/0/", book.id))
+        .header("range", "bytes=0-9")
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 206);
+    assert_eq!(
+        body_string(resp).await,
+        String::from_utf8_lossy(&data[..10])
+    );
+
+    // ZIP-wrapped downloads are streamed as well.
+    let resp = get(
+        test_router(state),
+        &format!("/opds/download/{}/1/", book.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert!(
+        resp.headers()["content-disposition"]
+            .to_str()
+            .unwrap()
+            .contains(".zip")
+    );
+}
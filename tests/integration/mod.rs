@@ -1,20 +1,33 @@
 mod admin_series_tests;
 mod admin_user_title_tests;
+mod api_tests;
 mod author_search_tests;
 mod book_search_tests;
 mod bookshelf_tests;
 mod catalog_tests;
+mod doctor_tests;
 mod duplicates_tests;
+mod graphql_tests;
+mod low_memory_tests;
 mod opds2_tests;
 mod opds_core_tests;
 mod opds_language_facets_tests;
 mod opds_recent_tests;
+mod opds_validation_tests;
+mod popular_tests;
+mod read_only_tests;
 mod reader_tests;
 mod recent_tests;
+mod recommendations_tests;
 mod scanner_tests;
+mod seo_tests;
 mod series_search_tests;
+mod session_tests;
+mod share_tests;
 mod static_tests;
+mod theme_tests;
 mod upload_tests;
+mod webdav_tests;
 
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
@@ -104,7 +117,13 @@ pub fn test_app_state(pool: DbPool, config: Config) -> AppState {
 
     let translations = i18n::load_runtime_translations().expect("translations should load");
 
-    AppState::new(config, pool, tera, translations, false, false)
+    AppState::new(
+        config,
+        pool,
+        tera,
+        translations,
+        ropds::state::Capabilities::default(),
+    )
 }
 
 /// Build a full Router from an AppState.
@@ -152,6 +171,40 @@ pub fn csrf_for_session(session_value: &str) -> String {
     generate_csrf_token(session_value, b"test-secret-key-for-integration-tests")
 }
 
+/// POST a form with the given cookies and a client address, as the server
+/// supplies it to handlers that log the remote peer.
+pub async fn post_form_with_cookies(
+    app: Router,
+    path: &str,
+    body: &str,
+    cookies: &str,
+) -> axum::response::Response {
+    let mut req = axum::http::Request::builder()
+        .method("POST")
+        .uri(path)
+        .header("content-type", "application/x-www-form-urlencoded")
+        .header("cookie", cookies)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    req.extensions_mut()
+        .insert(axum::extract::ConnectInfo(std::net::SocketAddr::from((
+            [127, 0, 0, 1],
+            0,
+        ))));
+    app.oneshot(req).await.unwrap()
+}
+
+/// Decoded value of the cookie `name` set by the response, if any.
+pub fn set_cookie(resp: &axum::response::Response, name: &str) -> Option<String> {
+    resp.headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|v| v.strip_prefix(&format!("{name}=")))
+        .map(|v| v.split(';').next().unwrap_or_default())
+        .map(|v| urlencoding::decode(v).unwrap().into_owned())
+}
+
 /// Send a GET request and return the response.
 pub async fn get(app: Router, path: &str) -> axum::response::Response {
     let req = axum::http::Request::builder()
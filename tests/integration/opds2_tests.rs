@@ -204,3 +204,342 @@ async fn opds_v2_bookshelf_requires_auth_when_enabled() {
         Some("application/opds+json; charset=utf-8")
     );
 }
+
+#[tokio::test]
+async fn opds_v2_publication_images_carry_sizes() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = ropds::db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(book.cover_width > 0 && book.cover_height > 0);
+
+    let state = test_app_state(pool, config);
+    let app = test_router(state);
+
+    let resp = get(app, "/opds/v2/search/books/m/Test/").await;
+    assert_eq!(resp.status(), 200);
+    let doc: Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let images = doc["publications"][0]["images"].as_array().unwrap();
+    assert_eq!(images.len(), 2);
+
+    let cover = &images[0];
+    assert_eq!(cover["href"], format!("/opds/cover/{}/", book.id));
+    assert_eq!(cover["type"], "image/jpeg");
+    assert_eq!(cover["width"], book.cover_width);
+    assert_eq!(cover["height"], book.cover_height);
+
+    let thumb = &images[1];
+    assert_eq!(thumb["href"], format!("/opds/thumb/{}/", book.id));
+    let (width, height) = (
+        thumb["width"].as_u64().unwrap(),
+        thumb["height"].as_u64().unwrap(),
+    );
+    assert_eq!(width.max(height), 200);
+}
+
+#[tokio::test]
+async fn cover_palette_is_stored_backfilled_and_shown() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let find = || db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2");
+    let book = find().await.unwrap().unwrap();
+    let palette = book.cover_palette.clone();
+    let colors = ropds::covers::palette_colors(&palette);
+    assert!(!colors.is_empty(), "scan should store the cover palette");
+
+    // Covers stored before palettes existed are filled by the backfill.
+    db::queries::books::set_cover_palette(&pool, book.id, "")
+        .await
+        .unwrap();
+    let backfilled = ropds::covers::backfill_palettes(&pool, covers_dir.path())
+        .await
+        .unwrap();
+    assert_eq!(backfilled, 1);
+    assert_eq!(find().await.unwrap().unwrap().cover_palette, palette);
+
+    let state = test_app_state(pool, config);
+    let doc: Value = serde_json::from_str(
+        &body_string(get(test_router(state.clone()), "/opds/v2/search/books/m/Test/").await).await,
+    )
+    .unwrap();
+    for image in doc["publications"][0]["images"].as_array().unwrap() {
+        assert_eq!(image["properties"]["tint"], colors[0]);
+    }
+
+    let html = body_string(
+        get(
+            test_router(state),
+            &format!("/web/search/books?type=i&q={}", book.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains(&format!("--cover-tint: {}", colors[0])));
+}
+
+#[tokio::test]
+async fn opds_v2_and_web_show_book_length() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "test_book.pdf"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let fb2 = db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(fb2.word_count > 0, "FB2 words should be counted");
+    assert_eq!(fb2.page_count, 0);
+    let pdf = db::queries::books::find_by_path_and_filename(&pool, "", "test_book.pdf")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(pdf.page_count > 0, "PDF pages should be counted");
+
+    let state = test_app_state(pool, config);
+
+    let body = body_string(get(test_router(state.clone()), "/opds/v2/recent/?lang=en").await).await;
+    let doc: Value = serde_json::from_str(&body).unwrap();
+    let pubs = doc["publications"].as_array().unwrap();
+    for book in [&fb2, &pdf] {
+        let publication = pubs
+            .iter()
+            .find(|p| p["metadata"]["identifier"] == format!("b:{}", book.id))
+            .expect("book in recent feed");
+        assert_eq!(publication["metadata"]["numberOfPages"], book.pages());
+    }
+
+    let html = body_string(
+        get(
+            test_router(state),
+            &format!("/web/search/books?type=i&q={}", fb2.id),
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains("book-reading-time"));
+}
+
+/// With `opds.group_formats`, the formats of a work are one OPDS 2
+/// publication and one web card, with a download link per format.
+#[tokio::test]
+async fn opds_v2_and_web_group_formats_of_a_work() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.group_formats = true;
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "test_book.epub"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let fb2 = db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let epub = db::queries::books::find_by_path_and_filename(&pool, "", "test_book.epub")
+        .await
+        .unwrap()
+        .unwrap();
+    // The sample books are different works; make the EPUB another edition.
+    let sql = pool.sql("UPDATE books SET search_title = ?, author_key = ? WHERE id = ?");
+    sqlx::query(&sql)
+        .bind(&fb2.search_title)
+        .bind(&fb2.author_key)
+        .bind(epub.id)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    let state = test_app_state(pool, config);
+    let body = body_string(get(test_router(state.clone()), "/opds/v2/recent/?lang=en").await).await;
+    let doc: Value = serde_json::from_str(&body).unwrap();
+    let pubs = doc["publications"].as_array().unwrap();
+    assert_eq!(pubs.len(), 1);
+    let (shown, other) = if pubs[0]["metadata"]["identifier"] == format!("b:{}", fb2.id) {
+        (&fb2, &epub)
+    } else {
+        (&epub, &fb2)
+    };
+    let hrefs: Vec<&str> = pubs[0]["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|l| l["href"].as_str())
+        .collect();
+    assert!(hrefs.contains(&format!("/opds/download/{}/0/", other.id).as_str()));
+
+    let html = body_string(get(test_router(state), "/web/recent").await).await;
+    assert!(html.contains(&format!("/web/download/{}/0", shown.id)));
+    assert!(html.contains(&format!("/web/download/{}/0", other.id)));
+    assert_eq!(html.matches("book-actions").count(), 1, "one card");
+}
+
+/// Publications name their series and position and link to the series.
+#[tokio::test]
+async fn opds_v2_publication_belongs_to_series() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let (ser, _) = db::queries::series::get_for_book(&pool, book.id)
+        .await
+        .unwrap()
+        .remove(0);
+    let series_href = format!("/opds/v2/search/books/s/{}/?lang=en", ser.id);
+
+    let state = test_app_state(pool, config);
+    let body = body_string(get(test_router(state), "/opds/v2/recent/?lang=en").await).await;
+    let doc: Value = serde_json::from_str(&body).unwrap();
+    let publication = &doc["publications"][0];
+    let series = &publication["metadata"]["belongsTo"]["series"][0];
+    assert_eq!(series["name"], "Test Series");
+    assert_eq!(series["position"], 1);
+    assert_eq!(series["links"][0]["href"], series_href);
+    let related = publication["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|l| l["rel"] == "related")
+        .expect("related series link");
+    assert_eq!(related["href"], series_href);
+    assert_eq!(related["title"], "All books in Test Series");
+}
+
+#[tokio::test]
+async fn private_library_serves_covers_through_signed_links() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = ropds::db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let user_id = create_test_user(&pool, "covers-auth", "password123", false).await;
+    config.opds.auth_required = true;
+    config.covers.signed_urls = true;
+    let state = test_app_state(pool, config);
+
+    let thumb_path = format!("/opds/thumb/{}/", book.id);
+    let resp = get(test_router(state.clone()), &thumb_path).await;
+    assert_eq!(resp.status(), 403);
+
+    let req = axum::http::Request::builder()
+        .uri(&thumb_path)
+        .header("authorization", basic_auth("covers-auth", "password123"))
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let req = axum::http::Request::builder()
+        .uri("/opds/v2/search/books/m/Test/")
+        .header("authorization", basic_auth("covers-auth", "password123"))
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    let doc: Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let href = doc["publications"][0]["images"][1]["href"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(href.starts_with(&format!("{thumb_path}?exp=")));
+
+    // The signed link works without credentials, for this book only.
+    let resp = get(test_router(state.clone()), &href).await;
+    assert_eq!(resp.status(), 200);
+    let other = href.replace(&thumb_path, &format!("/opds/thumb/{}/", book.id + 1));
+    assert_eq!(get(test_router(state.clone()), &other).await.status(), 403);
+
+    // Web pages sign the links of their <img> tags too.
+    let session = session_cookie_value(user_id);
+    let resp = get_with_session(
+        test_router(state),
+        &format!("/web/search/books?type=i&q={}", book.id),
+        &session,
+    )
+    .await;
+    let html = body_string(resp).await;
+    assert!(html.contains(&format!("{thumb_path}?exp=")));
+}
+
+#[tokio::test]
+async fn books_without_covers_get_a_placeholder() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["no_cover.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = ropds::db::queries::books::find_by_path_and_filename(&pool, "", "no_cover.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(book.cover, 0);
+    let state = test_app_state(pool, config);
+
+    let thumb_path = format!("/opds/thumb/{}/", book.id);
+    let resp = get(test_router(state.clone()), &thumb_path).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "image/svg+xml");
+    assert_eq!(resp.headers()["cache-control"], "no-cache");
+    let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    let svg = body_string(resp).await;
+    assert!(svg.contains(">NC</text>"), "{svg}");
+
+    let req = axum::http::Request::builder()
+        .uri(&thumb_path)
+        .header("if-none-match", &etag)
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 304);
+
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/opds/cover/{}/?placeholder=none", book.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 404);
+
+    // The web UI loads the placeholder lazily instead of a static image.
+    let resp = get(
+        test_router(state),
+        &format!("/web/search/books?type=i&q={}", book.id),
+    )
+    .await;
+    let html = body_string(resp).await;
+    assert!(html.contains(&format!("src=\"{thumb_path}\"")));
+}
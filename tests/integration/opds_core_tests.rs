@@ -46,6 +46,46 @@ async fn opds_requires_basic_auth_when_enabled() {
     assert_eq!(resp2.status(), 200);
 }
 
+/// A user imported with a legacy PBKDF2-SHA256 hash can sign in, and the
+/// hash is replaced by an Argon2id one on that first login.
+#[tokio::test]
+async fn opds_basic_auth_upgrades_legacy_password_hash() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+
+    let legacy = "pbkdf2_sha256$1000$Qx7bN2kLpZ4a$ADEvf+Iq23VhDwUZwc3P8GzPxlye0lQhdWrA9wlH7q8=";
+    let id = db::queries::users::create(&pool, "sopds-user", legacy, 0, "")
+        .await
+        .unwrap();
+
+    let state = test_app_state(pool.clone(), config);
+    let req = axum::http::Request::builder()
+        .uri("/opds/books/")
+        .header("authorization", basic_auth("sopds-user", "sopds-secret"))
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let user = db::queries::users::get_by_id(&pool, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(user.password_hash.starts_with("$argon2id$"));
+    assert!(!ropds::password::needs_rehash(&user.password_hash));
+
+    let req = axum::http::Request::builder()
+        .uri("/opds/books/")
+        .header("authorization", basic_auth("sopds-user", "sopds-secret"))
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+}
+
 #[tokio::test]
 async fn opds_recent_feed_has_prev_next_navigation() {
     let _lock = SCAN_MUTEX.lock().await;
@@ -122,3 +162,547 @@ async fn opds_book_search_returns_matching_entries() {
         "search results should include acquisition link"
     );
 }
+
+#[tokio::test]
+async fn opds_book_entries_carry_dublin_core_metadata_and_series() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let state = test_app_state(pool, config);
+    let app = test_router(state);
+    let resp = get(app, "/opds/search/books/m/Test%20Book/").await;
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(xml.contains("xmlns:dc=\"http://purl.org/dc/elements/1.1/\""));
+    assert!(xml.contains("xmlns:dcterms=\"http://purl.org/dc/terms/\""));
+    assert!(xml.contains("<dc:language>en</dc:language>"), "{xml}");
+    assert!(xml.contains("<dcterms:issued>2025-01-01</dcterms:issued>"));
+    assert!(xml.contains("<dc:format>application/fb2+xml</dc:format>"));
+    assert!(xml.contains(
+        "<category scheme=\"http://schema.org/CreativeWorkSeries\" term=\"Test Series\" \
+         label=\"Test Series #1\"/>"
+    ));
+    assert!(xml.contains("title=\"All books in Test Series\""));
+}
+
+#[tokio::test]
+async fn opds_book_entries_carry_text_preview() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        book.preview_text,
+        "Chapter 1\nThis is the first paragraph of the test book.\n\
+         It contains minimal content for testing purposes."
+    );
+
+    let state = test_app_state(pool, config);
+    let app = test_router(state);
+    let resp = get(app, "/opds/search/books/m/Test%20Book/").await;
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(
+        xml.contains("<div class='book-preview'><p>Chapter 1</p><p>This is the first"),
+        "entry content should include the first lines: {xml}"
+    );
+}
+
+#[tokio::test]
+async fn downloads_name_files_from_template_with_rfc5987_encoding() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.download.filename_template = "{author} - {title}.{ext}".to_string();
+
+    copy_test_files(lib_dir.path(), &["cyrillic_book.fb2", "quoted_title.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let cyrillic = books::find_by_path_and_filename(&pool, "", "cyrillic_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let quoted = books::find_by_path_and_filename(&pool, "", "quoted_title.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let state = test_app_state(pool, config);
+    for path in [
+        format!("/opds/download/{}/0/", cyrillic.id),
+        format!("/web/download/{}/0", cyrillic.id),
+    ] {
+        let resp = get(test_router(state.clone()), &path).await;
+        assert_eq!(resp.status(), 200, "{path}");
+        let disposition = resp.headers()["content-disposition"].to_str().unwrap();
+        assert_eq!(
+            disposition,
+            format!(
+                "attachment; filename=\"Ivanov Petr - Taina starogo doma.fb2\"; filename*=UTF-8''{}",
+                urlencoding::encode("Иванов Пётр - Тайна старого дома.fb2")
+            ),
+            "{path}"
+        );
+    }
+
+    let resp = get(
+        test_router(state.clone()),
+        &format!("/opds/download/{}/1/", quoted.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let disposition = resp.headers()["content-disposition"].to_str().unwrap();
+    assert!(
+        disposition.contains(&format!(
+            "filename*=UTF-8''{}",
+            urlencoding::encode("Смирнова Анна - 'Цитата' и другие рассказы.fb2.zip")
+        )),
+        "{disposition}"
+    );
+    assert!(
+        disposition.starts_with(
+            "attachment; filename=\"Smirnova Anna - 'Tsitata' i drugie rasskazi.fb2.zip\""
+        ),
+        "{disposition}"
+    );
+}
+
+/// `zip_flag = 1` streams a ZIP with the book under its download name and
+/// the metadata in the archive comment, from the web and OPDS alike.
+#[tokio::test]
+async fn zipped_download_names_member_and_carries_metadata_comment() {
+    use http_body_util::BodyExt;
+    use std::io::Read;
+
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2.zip"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "test_book.fb2.zip", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let expected = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+
+    let state = test_app_state(pool, config);
+    for path in [
+        format!("/opds/download/{}/1/", book.id),
+        format!("/web/download/{}/1", book.id),
+    ] {
+        let resp = get(test_router(state.clone()), &path).await;
+        assert_eq!(resp.status(), 200, "{path}");
+        assert!(
+            resp.headers()["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("application/fb2+zip"),
+            "{path}"
+        );
+        assert_eq!(
+            resp.headers()["content-disposition"],
+            "attachment; filename=\"Test Book Title.fb2.zip\"",
+            "{path}"
+        );
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(body)).unwrap();
+        let comment = String::from_utf8(archive.comment().to_vec()).unwrap();
+        assert!(comment.starts_with("Test Book Title\n"), "{comment}");
+        assert!(comment.contains("Doe John"), "{comment}");
+        assert_eq!(archive.len(), 1);
+        let mut entry = archive.by_name("Test Book Title.fb2").unwrap();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        assert_eq!(data, expected, "{path}");
+    }
+
+    // A missing file is reported before any ZIP bytes are sent
+    std::fs::remove_file(lib_dir.path().join("test_book.fb2.zip")).unwrap();
+    let resp = get(
+        test_router(state),
+        &format!("/opds/download/{}/1/", book.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn opds_feeds_answer_conditional_get_until_library_changes() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let conditional_get = |state: AppState, etag: Option<String>| async move {
+        let mut req = axum::http::Request::builder().uri("/opds/recent/");
+        if let Some(etag) = etag {
+            req = req.header("if-none-match", etag);
+        }
+        test_router(state)
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    };
+
+    let state = test_app_state(pool.clone(), config.clone());
+    let resp = conditional_get(state.clone(), None).await;
+    assert_eq!(resp.status(), 200);
+    let etag = resp.headers()["etag"].to_str().unwrap().to_string();
+    assert!(etag.starts_with("W/\""));
+
+    let resp = conditional_get(state.clone(), Some(etag.clone())).await;
+    assert_eq!(resp.status(), 304);
+    assert_eq!(resp.headers()["etag"].to_str().unwrap(), etag);
+
+    // Per-user feeds are never answered from the stamp.
+    let resp = get(test_router(state), "/opds/").await;
+    assert!(resp.headers().get("etag").is_none());
+
+    copy_test_files(lib_dir.path(), &["title_only.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    let state = test_app_state(pool, config);
+    let resp = conditional_get(state, Some(etag.clone())).await;
+    assert_eq!(resp.status(), 200);
+    assert_ne!(resp.headers()["etag"].to_str().unwrap(), etag);
+}
+
+/// With `stats.user_agents` OPDS clients are recorded per signed-in user and
+/// on download rows; `stats.anonymous_downloads = false` stops counting
+/// downloads of clients that are not signed in.
+#[tokio::test]
+async fn opds_records_user_agents_and_skips_anonymous_downloads() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    config.stats.user_agents = true;
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let user_id = create_test_user(&pool, "reader", "password123", false).await;
+
+    let state = test_app_state(pool.clone(), config.clone());
+    for uri in [
+        "/opds/books/".to_string(),
+        format!("/opds/download/{}/0/", book.id),
+    ] {
+        let req = axum::http::Request::builder()
+            .uri(&uri)
+            .header("authorization", basic_auth("reader", "password123"))
+            .header("user-agent", "KOReader/2024.04 (Kobo)")
+            .body(Body::empty())
+            .unwrap();
+        let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), 200, "{uri}");
+    }
+
+    let devices = db::queries::devices::list_all(&pool).await.unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].user_id, user_id);
+    assert_eq!(devices[0].user_agent, "KOReader/2024.04 (Kobo)");
+    assert_eq!(devices[0].requests, 2);
+    let sql = pool.sql("SELECT user_id, user_agent FROM downloads");
+    let rows: Vec<(Option<i64>, Option<String>)> =
+        sqlx::query_as(&sql).fetch_all(pool.inner()).await.unwrap();
+    assert_eq!(
+        rows,
+        vec![(Some(user_id), Some("KOReader/2024.04 (Kobo)".to_string()))]
+    );
+
+    let admin = create_test_user(&pool, "admin", "password123", true).await;
+    let resp = get_with_session(
+        test_router(state),
+        "/web/admin",
+        &session_cookie_value(admin),
+    )
+    .await;
+    let html = body_string(resp).await;
+    assert!(html.contains("KOReader") && html.contains("(Kobo)"));
+
+    // Anonymous downloads are not counted once opted out
+    config.opds.auth_required = false;
+    config.stats.anonymous_downloads = false;
+    let state = test_app_state(pool.clone(), config);
+    let resp = get(
+        test_router(state),
+        &format!("/opds/download/{}/0/", book.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let sql = pool.sql("SELECT COUNT(*) FROM downloads");
+    let (count,): (i64,) = sqlx::query_as(&sql).fetch_one(pool.inner()).await.unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn hide_empty_leaves_out_genres_and_scripts_without_books() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "digit_title.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    // `sf_fantasy_city` (id 198) shares the `sf` section but has no books.
+    let empty_genre = "/search/books/g/198/";
+
+    let state = test_app_state(pool.clone(), config.clone());
+    let xml = body_string(get(test_router(state.clone()), "/opds/genres/").await).await;
+    assert!(xml.contains("/opds/genres/romance/"));
+    let xml = body_string(get(test_router(state.clone()), "/opds/genres/sf/").await).await;
+    assert!(xml.contains(empty_genre));
+    let xml = body_string(get(test_router(state), "/opds/books/").await).await;
+    assert!(xml.contains("/opds/books/1/"));
+
+    config.opds.hide_empty = true;
+    let state = test_app_state(pool, config);
+    let xml = body_string(get(test_router(state.clone()), "/opds/genres/").await).await;
+    assert!(xml.contains("/opds/genres/sf/"));
+    assert!(!xml.contains("/opds/genres/romance/"));
+    let xml = body_string(get(test_router(state.clone()), "/opds/genres/sf/").await).await;
+    assert!(xml.contains("/opds/search/books/g/213/"));
+    assert!(!xml.contains(empty_genre));
+    let json = body_string(get(test_router(state.clone()), "/opds/v2/genres/sf/").await).await;
+    assert!(json.contains("/opds/v2/search/books/g/213/"));
+    assert!(!json.contains(empty_genre));
+
+    // "All" and the scripts in use stay; Cyrillic has no books here.
+    let xml = body_string(get(test_router(state.clone()), "/opds/books/").await).await;
+    assert!(xml.contains("/opds/books/0/"));
+    assert!(xml.contains("/opds/books/2/"));
+    assert!(!xml.contains("/opds/books/1/"));
+    let json = body_string(get(test_router(state.clone()), "/opds/v2/authors/").await).await;
+    assert!(json.contains("/opds/v2/authors/0/"));
+    assert!(!json.contains("/opds/v2/authors/1/"));
+
+    let html = body_string(get(test_router(state), "/web/genres?section=sf").await).await;
+    assert!(html.contains("type=g&q=213"));
+    assert!(!html.contains("type=g&q=198\""));
+}
+
+#[tokio::test]
+async fn genre_menus_show_book_counts() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(
+        lib_dir.path(),
+        &["test_book.fb2", "digit_title.fb2", "no_cover.fb2"],
+    );
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let state = test_app_state(pool, config);
+
+    // Two sf_fantasy books (genre 213) in the `sf` section.
+    let xml = body_string(get(test_router(state.clone()), "/opds/genres/?lang=en").await).await;
+    assert!(xml.contains("Books: 2"));
+    let xml = body_string(get(test_router(state.clone()), "/opds/genres/sf/?lang=en").await).await;
+    assert!(xml.contains("Books: 2"));
+    assert!(xml.contains("Books: 0"));
+
+    let json = body_string(get(test_router(state.clone()), "/opds/v2/genres/sf/").await).await;
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let fantasy = json["navigation"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|link| link["href"].as_str().unwrap().contains("/g/213/"))
+        .expect("sf_fantasy link");
+    assert_eq!(fantasy["properties"]["numberOfItems"], 2);
+
+    let html = body_string(get(test_router(state), "/web/genres").await).await;
+    assert!(html.contains("/web/genres?section=sf"));
+    assert!(!html.contains("/web/genres?section=romance"));
+    assert!(html.contains(">2</span>"));
+}
+
+#[tokio::test]
+async fn opds_feeds_carry_instance_icon_and_logo() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.logo_url = Some("https://books.example.org/logo.png".to_string());
+
+    let app = test_router(test_app_state(pool, config));
+    let resp = get(app.clone(), "/opds").await;
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(xml.contains("<icon>/favicon.ico</icon>"), "{xml}");
+    assert!(xml.contains("<logo>https://books.example.org/logo.png</logo>"));
+
+    let resp = get(app, "/opds/v2/").await;
+    assert_eq!(resp.status(), 200);
+    let feed: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let links = feed["links"].as_array().unwrap();
+    assert!(
+        links
+            .iter()
+            .any(|l| l["rel"] == "icon" && l["href"] == "/favicon.ico")
+    );
+    assert!(
+        links
+            .iter()
+            .any(|l| l["rel"] == "logo" && l["href"] == "https://books.example.org/logo.png")
+    );
+}
+
+/// Readers with a compatibility profile get feeds without facets and with
+/// absolute links, the zipped download first and their page size; a user
+/// who turned profiles off gets the standard feeds.
+#[tokio::test]
+async fn opds_client_profiles_adjust_feeds() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let user_id = create_test_user(&pool, "reader", "password123", false).await;
+    db::queries::bookshelf::upsert(&pool, user_id, book.id)
+        .await
+        .unwrap();
+
+    let state = test_app_state(pool.clone(), config);
+    let fetch = |uri: &'static str| {
+        let state = state.clone();
+        async move {
+            let req = axum::http::Request::builder()
+                .uri(uri)
+                .header("authorization", basic_auth("reader", "password123"))
+                .header("user-agent", "Mozilla/5.0 PocketBook/740 (Linux)")
+                .body(Body::empty())
+                .unwrap();
+            let resp = test_router(state).oneshot(req).await.unwrap();
+            assert_eq!(resp.status(), 200, "{uri}");
+            body_string(resp).await
+        }
+    };
+
+    let xml = fetch("/opds/bookshelf/").await;
+    assert!(!xml.contains("http://opds-spec.org/facet"), "{xml}");
+    assert!(
+        xml.contains("<link href=\"http://localhost:8081/opds/bookshelf/1/?lang=en\" rel=\"self\"")
+    );
+    // The acquisition links follow the entry's `alternate` download link
+    let zip = xml.find(&format!("/opds/download/{}/1/", book.id)).unwrap();
+    let original = xml
+        .rfind(&format!("/opds/download/{}/0/", book.id))
+        .unwrap();
+    assert!(zip < original);
+
+    let feed: serde_json::Value =
+        serde_json::from_str(&fetch("/opds/v2/bookshelf/").await).unwrap();
+    assert!(feed.get("facets").is_none());
+    let links = feed["publications"][0]["links"].as_array().unwrap();
+    assert_eq!(
+        links[0]["href"],
+        format!("http://localhost:8081/opds/download/{}/1/", book.id)
+    );
+
+    db::queries::users::update_opds_client(&pool, user_id, "none")
+        .await
+        .unwrap();
+    let xml = fetch("/opds/bookshelf/").await;
+    assert!(xml.contains("http://opds-spec.org/facet"));
+    assert!(xml.contains("<link href=\"/opds/bookshelf/1/?lang=en\" rel=\"self\""));
+}
+
+/// `opds.absolute_urls` makes every feed link absolute, with the host a
+/// trusted reverse proxy forwarded when there is one.
+#[tokio::test]
+async fn opds_absolute_urls_use_forwarded_host() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.absolute_urls = true;
+    config.server.trust_proxy_headers = true;
+    let app = test_router(test_app_state(pool, config.clone()));
+
+    let xml = body_string(get(app.clone(), "/opds").await).await;
+    assert!(
+        xml.contains("<icon>http://localhost:8081/favicon.ico</icon>"),
+        "{xml}"
+    );
+    assert!(xml.contains("<link href=\"http://localhost:8081/opds/\" rel=\"self\""));
+    assert!(!xml.contains("href=\"/"), "{xml}");
+
+    let req = axum::http::Request::builder()
+        .uri("/opds/v2/")
+        .header(
+            "forwarded",
+            "for=192.0.2.7;proto=https;host=books.example.org",
+        )
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let feed: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let links = feed["links"].as_array().unwrap();
+    assert!(links.iter().all(|l| {
+        l["href"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://books.example.org/")
+    }));
+
+    let xml = body_string(get(app, "/opds/search/").await).await;
+    assert!(xml.contains("template=\"http://localhost:8081/opds/search/{searchTerms}/\""));
+
+    // Without `server.trust_proxy_headers` the client's headers are ignored
+    config.server.trust_proxy_headers = false;
+    let app = test_router(test_app_state(db::create_test_pool().await, config));
+    let req = axum::http::Request::builder()
+        .uri("/opds/v2/")
+        .header("forwarded", "proto=https;host=evil.example")
+        .body(Body::empty())
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    let feed: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let links = feed["links"].as_array().unwrap();
+    assert!(links.iter().all(|l| {
+        l["href"]
+            .as_str()
+            .unwrap()
+            .starts_with("http://localhost:8081/")
+    }));
+}
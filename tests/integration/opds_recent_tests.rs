@@ -1,4 +1,5 @@
 use ropds::db;
+use ropds::db::queries::{books, genres};
 use ropds::scanner;
 
 use super::*;
@@ -31,3 +32,220 @@ async fn opds_recent_feed_returns_recent_books() {
         "recent feed should include newly scanned books"
     );
 }
+
+#[tokio::test]
+async fn changed_files_are_updated_in_place_and_listed_as_updated() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "no_cover.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let before = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(before.last_modified.is_empty());
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_updated, 0, "unchanged files are skipped");
+
+    // A fixed version of the file: same name, different size.
+    let path = lib_dir.path().join("test_book.fb2");
+    let fixed = std::fs::read_to_string(&path)
+        .unwrap()
+        .replace("Chapter 1", "Chapter One");
+    std::fs::write(&path, fixed).unwrap();
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 0);
+    assert_eq!(stats.books_updated, 1);
+
+    let after = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.id, before.id, "the book keeps its id");
+    assert_ne!(after.size, before.size);
+    assert!(!after.last_modified.is_empty());
+    assert_eq!(after.reg_date, before.reg_date);
+    let untouched = books::find_by_path_and_filename(&pool, "", "no_cover.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let state = test_app_state(pool, config);
+
+    let xml = body_string(get(test_router(state.clone()), "/opds").await).await;
+    assert!(
+        xml.contains("/opds/updated/"),
+        "root links the updated feed"
+    );
+
+    let xml = body_string(get(test_router(state.clone()), "/opds/updated/").await).await;
+    assert!(xml.contains("/opds/updated/1/"));
+    assert!(xml.contains("Test Book Title"));
+    assert!(!xml.contains("No Cover Book"));
+
+    let json = body_string(get(test_router(state.clone()), "/opds/v2/updated/").await).await;
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let publications = json["publications"].as_array().unwrap();
+    assert_eq!(publications.len(), 1);
+    assert_eq!(publications[0]["metadata"]["title"], "Test Book Title");
+
+    let resp = get(test_router(state), "/web/recent?show=updated").await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains(&format!("/web/download/{}/", after.id)));
+    assert!(!html.contains(&format!("/web/download/{}/", untouched.id)));
+}
+
+#[tokio::test]
+async fn same_size_rewrites_are_updated() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    // Recorded well before the edit below.
+    let sql = pool.sql("UPDATE books SET reg_date = ?");
+    sqlx::query(&sql)
+        .bind("2000-01-01 00:00:00")
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    let before = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let path = lib_dir.path().join("test_book.fb2");
+    let fixed = std::fs::read_to_string(&path)
+        .unwrap()
+        .replace("Chapter 1", "Chapter 9");
+    std::fs::write(&path, fixed).unwrap();
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_updated, 1);
+
+    let after = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.id, before.id);
+    assert_eq!(after.size, before.size);
+    assert!(!after.last_modified.is_empty());
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_updated, 0, "the rewrite is recorded once");
+}
+
+#[tokio::test]
+async fn genre_new_arrivals_feeds() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(
+        lib_dir.path(),
+        &["test_book.fb2", "digit_title.fb2", "no_cover.fb2"],
+    );
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let genre = genres::get_by_code(&pool, "sf_fantasy")
+        .await
+        .unwrap()
+        .expect("sf_fantasy genre");
+    let other = books::find_by_path_and_filename(&pool, "", "no_cover.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let state = test_app_state(pool, config);
+
+    let url = format!("/opds/recent/genre/{}/", genre.id);
+    let resp = get(test_router(state.clone()), &url).await;
+    assert_eq!(resp.status(), 200);
+    let xml = body_string(resp).await;
+    assert!(xml.contains(&format!("/opds/recent/genre/{}/1/", genre.id)));
+    assert!(xml.contains("Test Book Title"));
+    assert!(xml.contains("451 Degree"));
+    assert!(!xml.contains("No Cover Book"));
+
+    let url = format!("/opds/v2/recent/genre/{}/", genre.id);
+    let json = body_string(get(test_router(state.clone()), &url).await).await;
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json["publications"].as_array().unwrap().len(), 2);
+
+    let resp = get(test_router(state.clone()), "/opds/recent/genre/999999/").await;
+    assert_eq!(resp.status(), 404);
+
+    // The genre's book list advertises its new arrivals.
+    let url = format!("/opds/search/books/g/{}/", genre.id);
+    let xml = body_string(get(test_router(state.clone()), &url).await).await;
+    assert!(xml.contains("http://opds-spec.org/sort/new"));
+    assert!(xml.contains(&format!("/opds/recent/genre/{}/", genre.id)));
+
+    let url = format!("/web/recent?genre={}", genre.id);
+    let resp = get(test_router(state.clone()), &url).await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("451 Degree"));
+    assert!(!html.contains(&format!("/web/download/{}/", other.id)));
+
+    let url = format!("/web/search/books?type=g&q={}", genre.id);
+    let html = body_string(get(test_router(state), &url).await).await;
+    assert!(html.contains(&format!("recent?genre={}", genre.id)));
+}
+
+#[tokio::test]
+async fn opds_full_dump_pages_through_every_book_by_id() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.dump_page_size = 2;
+
+    copy_test_files(
+        lib_dir.path(),
+        &["test_book.fb2", "no_cover.fb2", "title_only.fb2"],
+    );
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let mut ids = Vec::new();
+    for name in ["test_book.fb2", "no_cover.fb2", "title_only.fb2"] {
+        let book = books::find_by_path_and_filename(&pool, "", name)
+            .await
+            .unwrap()
+            .unwrap();
+        ids.push(book.id);
+    }
+    ids.sort();
+    let state = test_app_state(pool, config);
+
+    let root = body_string(get(test_router(state.clone()), "/opds").await).await;
+    assert!(root.contains(r#"href="/opds/all/1/" rel="http://opds-spec.org/crawlable""#));
+
+    let first = body_string(get(test_router(state.clone()), "/opds/all/1/").await).await;
+    assert!(first.contains(&format!("<id>b:{}</id>", ids[0])), "{first}");
+    assert!(first.contains(&format!("<id>b:{}</id>", ids[1])));
+    assert!(!first.contains(&format!("<id>b:{}</id>", ids[2])));
+    let next = format!("/opds/all/2/?lang=en&amp;after={}", ids[1]);
+    assert!(
+        first.contains(&format!(r#"href="{next}" rel="next""#)),
+        "{first}"
+    );
+    assert!(first.contains(r#"href="/opds/all/2/?lang=en" rel="last""#));
+
+    // The keyset link and the plain page number list the same books.
+    for path in [next.replace("&amp;", "&"), "/opds/all/2/".to_string()] {
+        let last = body_string(get(test_router(state.clone()), &path).await).await;
+        assert!(last.contains(&format!("<id>b:{}</id>", ids[2])));
+        assert!(!last.contains(&format!("<id>b:{}</id>", ids[1])));
+        assert!(!last.contains(r#"rel="next""#));
+        assert!(last.contains(r#"href="/opds/all/1/?lang=en" rel="prev""#));
+    }
+}
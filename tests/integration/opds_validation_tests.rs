@@ -0,0 +1,349 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use base64::Engine;
+use quick_xml::XmlVersion;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use ropds::db;
+use ropds::db::queries::{books, bookshelf};
+use ropds::scanner;
+use tower::ServiceExt;
+
+use super::*;
+
+const ATOM_NS: &str = "http://www.w3.org/2005/Atom";
+const NAV_KIND: &str = "kind=navigation";
+const ACQ_KIND: &str = "kind=acquisition";
+const REL_ACQUISITION_PREFIX: &str = "http://opds-spec.org/acquisition";
+
+fn basic_auth(username: &str, password: &str) -> String {
+    let raw = format!("{username}:{password}");
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(raw.as_bytes())
+    )
+}
+
+#[derive(Default)]
+struct FeedLink {
+    rel: String,
+    href: String,
+    link_type: String,
+}
+
+#[derive(Default)]
+struct FeedEntry {
+    children: HashMap<String, usize>,
+    links: Vec<FeedLink>,
+}
+
+/// Feed-level structure collected by [`validate_feed`].
+#[derive(Default)]
+struct ParsedFeed {
+    children: HashMap<String, usize>,
+    links: Vec<FeedLink>,
+    entries: Vec<FeedEntry>,
+    entry_ids: Vec<String>,
+}
+
+impl ParsedFeed {
+    fn links(&self, rel: &str) -> Vec<&FeedLink> {
+        self.links.iter().filter(|l| l.rel == rel).collect()
+    }
+}
+
+fn parse_link(el: &BytesStart) -> Result<FeedLink, String> {
+    let mut link = FeedLink::default();
+    for attr in el.attributes() {
+        let attr = attr.map_err(|e| e.to_string())?;
+        let value = attr
+            .normalized_value(XmlVersion::Implicit1_0)
+            .map_err(|e| e.to_string())?
+            .to_string();
+        match attr.key.as_ref() {
+            b"rel" => link.rel = value,
+            b"href" => link.href = value,
+            b"type" => link.link_type = value,
+            _ => {}
+        }
+    }
+    if link.href.is_empty() || link.rel.is_empty() || link.link_type.is_empty() {
+        return Err(format!(
+            "link needs href, rel and type: rel={:?} href={:?} type={:?}",
+            link.rel, link.href, link.link_type
+        ));
+    }
+    Ok(link)
+}
+
+/// Check an OPDS 1.2 Atom feed: required feed and entry metadata, typed
+/// links, and consistent `self` / `start` / `up` navigation links.
+fn validate_feed(xml: &str) -> Result<ParsedFeed, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut feed = ParsedFeed::default();
+    let mut stack: Vec<String> = Vec::new();
+    let mut entry: Option<FeedEntry> = None;
+
+    loop {
+        let event = reader.read_event().map_err(|e| e.to_string())?;
+        let (el, empty) = match &event {
+            Event::Start(el) => (el, false),
+            Event::Empty(el) => (el, true),
+            Event::End(_) => {
+                let name = stack.pop().unwrap_or_default();
+                if name == "entry" && stack.len() == 1 {
+                    feed.entries.extend(entry.take());
+                }
+                continue;
+            }
+            Event::Text(text) => {
+                if stack.len() == 3 && stack[1] == "entry" && stack[2] == "id" {
+                    feed.entry_ids
+                        .push(text.decode().map_err(|e| e.to_string())?.to_string());
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let name = String::from_utf8_lossy(el.name().as_ref()).to_string();
+        match stack.len() {
+            0 => {
+                if name != "feed" {
+                    return Err(format!("root element is <{name}>, not <feed>"));
+                }
+                let ns = el
+                    .try_get_attribute("xmlns")
+                    .map_err(|e| e.to_string())?
+                    .map(|a| {
+                        a.normalized_value(XmlVersion::Implicit1_0)
+                            .unwrap_or_default()
+                            .to_string()
+                    });
+                if ns.as_deref() != Some(ATOM_NS) {
+                    return Err(format!("feed is not in the Atom namespace: {ns:?}"));
+                }
+            }
+            1 => {
+                *feed.children.entry(name.clone()).or_default() += 1;
+                if name == "link" {
+                    feed.links.push(parse_link(el)?);
+                } else if name == "entry" {
+                    entry = Some(FeedEntry::default());
+                }
+            }
+            2 if stack[1] == "entry" => {
+                let current = entry.as_mut().ok_or("entry child outside <entry>")?;
+                *current.children.entry(name.clone()).or_default() += 1;
+                if name == "link" {
+                    current.links.push(parse_link(el)?);
+                }
+            }
+            _ => {}
+        }
+        if !empty {
+            stack.push(name);
+        }
+    }
+
+    for required in ["id", "title", "updated"] {
+        let count = feed.children.get(required).copied().unwrap_or(0);
+        if count != 1 {
+            return Err(format!("feed has {count} <{required}> elements"));
+        }
+    }
+    for rel in ["self", "start"] {
+        let count = feed.links(rel).len();
+        if count != 1 {
+            return Err(format!("feed has {count} rel=\"{rel}\" links"));
+        }
+    }
+    let self_link = feed.links("self")[0];
+    let start_link = feed.links("start")[0];
+    if !start_link.link_type.contains(NAV_KIND) {
+        return Err(format!("start link type {:?}", start_link.link_type));
+    }
+    let up = feed.links("up");
+    if self_link.href == start_link.href {
+        if !up.is_empty() {
+            return Err("root feed must not have an up link".to_string());
+        }
+    } else if up.len() != 1 {
+        return Err(format!(
+            "feed {} has {} rel=\"up\" links",
+            self_link.href,
+            up.len()
+        ));
+    } else if !up[0].link_type.contains(NAV_KIND) {
+        return Err(format!("up link type {:?}", up[0].link_type));
+    } else if up[0].href == self_link.href {
+        return Err(format!("feed {} is its own parent", self_link.href));
+    }
+
+    for (i, entry) in feed.entries.iter().enumerate() {
+        for required in ["id", "title", "updated"] {
+            let count = entry.children.get(required).copied().unwrap_or(0);
+            if count != 1 {
+                return Err(format!("entry {i} has {count} <{required}> elements"));
+            }
+        }
+        if entry.links.is_empty() {
+            return Err(format!("entry {i} has no links"));
+        }
+    }
+    let unique: BTreeSet<&String> = feed.entry_ids.iter().collect();
+    if unique.len() != feed.entry_ids.len() {
+        return Err("entry ids are not unique".to_string());
+    }
+
+    let acquisition = feed.entries.iter().any(|e| {
+        e.links
+            .iter()
+            .any(|l| l.rel.starts_with(REL_ACQUISITION_PREFIX))
+    });
+    let expected_kind = if acquisition { ACQ_KIND } else { NAV_KIND };
+    if !feed.entries.is_empty() && !self_link.link_type.contains(expected_kind) {
+        return Err(format!(
+            "feed {} self type {:?}, expected {expected_kind}",
+            self_link.href, self_link.link_type
+        ));
+    }
+    for rel in ["prev", "next"] {
+        for link in feed.links(rel) {
+            if link.link_type != self_link.link_type {
+                return Err(format!(
+                    "{rel} link type {:?} differs from self {:?}",
+                    link.link_type, self_link.link_type
+                ));
+            }
+        }
+    }
+
+    Ok(feed)
+}
+
+/// Feed links worth following while crawling the catalog.
+fn is_followed(link: &FeedLink) -> bool {
+    link.href.starts_with("/opds/")
+        && link.link_type.starts_with("application/atom+xml")
+        && matches!(
+            link.rel.as_str(),
+            "subsection" | "up" | "start" | "next" | "prev"
+        )
+}
+
+async fn fetch_feed(app: &axum::Router, path: &str, auth: Option<&str>) -> Option<String> {
+    let mut req = axum::http::Request::builder().uri(path);
+    if let Some(auth) = auth {
+        req = req.header("authorization", auth);
+    }
+    let resp = app
+        .clone()
+        .oneshot(req.body(axum::body::Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let is_atom = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/atom+xml"));
+    if resp.status() != 200 || !is_atom {
+        return None;
+    }
+    Some(body_string(resp).await)
+}
+
+#[test]
+fn validator_rejects_feeds_without_up_links() {
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>tag:x</id>
+  <title>X</title>
+  <updated>2024-01-01T00:00:00Z</updated>
+  <link href="/opds/x/" rel="self" type="application/atom+xml;profile=opds-catalog;kind=navigation"/>
+  <link href="/opds/" rel="start" type="application/atom+xml;profile=opds-catalog;kind=navigation"/>
+</feed>"#;
+    let err = validate_feed(xml).err().expect("missing up link");
+    assert!(err.contains("rel=\"up\""), "{err}");
+}
+
+#[tokio::test]
+async fn opds_feeds_pass_validation_and_link_up() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(
+        lib_dir.path(),
+        &["test_book.fb2", "cyrillic_book.fb2", "title_only.epub"],
+    );
+    copy_test_files_to_subdir(lib_dir.path(), "nested/deeper", &["test_book.epub"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let user_id = create_test_user(&pool, "reader", "secret123", false).await;
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    bookshelf::upsert(&pool, user_id, book.id).await.unwrap();
+
+    let state = test_app_state(pool, config);
+    let app = test_router(state);
+    let auth = basic_auth("reader", "secret123");
+
+    let mut queue: VecDeque<String> = [
+        "/opds/",
+        "/opds/search/Test/",
+        "/opds/search/books/m/Test/",
+        "/opds/search/authors/m/Test/",
+        "/opds/search/series/m/Test/",
+        "/opds/bookshelf/",
+        "/opds/recommended/",
+        "/opds/facets/languages/",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect();
+    let mut seen: BTreeSet<String> = queue.iter().cloned().collect();
+    let mut validated = 0;
+
+    while let Some(path) = queue.pop_front() {
+        let Some(xml) = fetch_feed(&app, &path, Some(&auth)).await else {
+            continue;
+        };
+        let feed = validate_feed(&xml).unwrap_or_else(|e| panic!("{path}: {e}\n{xml}"));
+        validated += 1;
+        let followed = feed
+            .links
+            .iter()
+            .chain(feed.entries.iter().flat_map(|e| e.links.iter()))
+            .filter(|l| is_followed(l));
+        for link in followed {
+            if seen.len() < 300 && seen.insert(link.href.clone()) {
+                queue.push_back(link.href.clone());
+            }
+        }
+    }
+    assert!(validated > 20, "only {validated} feeds were crawled");
+
+    // Nested catalogs point up at their parent catalog.
+    let xml = fetch_feed(&app, "/opds/catalogs/", None).await.unwrap();
+    let feed = validate_feed(&xml).unwrap();
+    let nested = feed
+        .entries
+        .iter()
+        .flat_map(|e| e.links.iter())
+        .find(|l| l.rel == "subsection" && l.href.starts_with("/opds/catalogs/"))
+        .expect("top-level catalog entry")
+        .href
+        .clone();
+    let xml = fetch_feed(&app, &nested, None).await.unwrap();
+    let feed = validate_feed(&xml).unwrap();
+    assert!(feed.links("up")[0].href.starts_with("/opds/catalogs/"));
+
+    // Alphabet drill-downs go up one letter at a time.
+    let xml = fetch_feed(&app, "/opds/authors/0/", None).await.unwrap();
+    let feed = validate_feed(&xml).unwrap();
+    assert_eq!(feed.links("up")[0].href, "/opds/authors/");
+}
@@ -0,0 +1,136 @@
+use ropds::db;
+use ropds::db::queries::{books, counters, downloads};
+use ropds::scanner;
+
+use super::*;
+
+/// Downloads are recorded and, after a cache refresh, listed in the OPDS
+/// popular feeds and the web home "Popular" section.
+#[tokio::test]
+async fn popular_feeds_list_downloaded_books() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["title_only.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book_id = books::get_recent_added(&pool, 1, 0, false).await.unwrap()[0].id;
+
+    let state = test_app_state(pool.clone(), config);
+
+    // Nothing is popular before the first download.
+    let xml = body_string(get(test_router(state.clone()), "/opds/popular/").await).await;
+    assert!(!xml.contains("Lonely Title Book"));
+
+    for path in [
+        format!("/opds/download/{book_id}/0/"),
+        format!("/opds/download/{book_id}/1/"),
+        format!("/web/download/{book_id}/0"),
+    ] {
+        let resp = get(test_router(state.clone()), &path).await;
+        assert_eq!(resp.status(), 200, "{path}");
+    }
+    counters::update_popular(&pool).await.unwrap();
+
+    let xml = body_string(get(test_router(state.clone()), "/opds/popular/?lang=en").await).await;
+    assert!(xml.contains("Lonely Title Book"));
+    assert!(xml.contains("/opds/popular/1/?period=30&amp;lang=en"));
+    assert!(xml.contains(r#"opds:facetGroup="Period""#));
+
+    let resp = get(test_router(state.clone()), "/opds/v2/popular/?period=90").await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["publications"].as_array().map(Vec::len), Some(1));
+    assert_eq!(json["facets"][0]["links"][1]["rel"], "self");
+
+    let html = body_string(get(test_router(state.clone()), "/web").await).await;
+    assert!(html.contains("home-popular"));
+    assert!(html.contains(&format!("type=i&q={book_id}")));
+
+    let xml = body_string(get(test_router(state), "/opds").await).await;
+    assert!(
+        xml.contains("/opds/popular/"),
+        "root feed links the popular feed"
+    );
+}
+
+/// The admin download statistics page shows leaderboards and exports CSV.
+#[tokio::test]
+async fn admin_download_stats_page_and_csv_export() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["title_only.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book_id = books::get_recent_added(&pool, 1, 0, false).await.unwrap()[0].id;
+
+    let super_id = create_test_user(&pool, "stats-admin", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let user_id = create_test_user(&pool, "stats-user", "password123", false).await;
+    let state = test_app_state(pool.clone(), config);
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/admin/stats/downloads",
+        &session_cookie_value(user_id),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+
+    let html = body_string(
+        get_with_session(
+            test_router(state.clone()),
+            "/web/admin/stats/downloads",
+            &session,
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains("No downloads in this period."));
+
+    downloads::record(&pool, book_id, Some(user_id))
+        .await
+        .unwrap();
+    downloads::record(&pool, book_id, None).await.unwrap();
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/admin/stats/downloads?period=7",
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("stats-daily"));
+    assert!(html.contains("Lonely Title Book"));
+    assert!(html.contains("stats-user"));
+    assert!(html.contains("Anonymous"));
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        "/web/admin/stats/downloads?period=0&format=csv&table=users",
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-disposition"],
+        "attachment; filename=\"downloads-users-all.csv\""
+    );
+    let csv = body_string(resp).await;
+    assert!(csv.starts_with("id,name,downloads\r\n"));
+    assert!(csv.contains(&format!("{user_id},stats-user,1\r\n")));
+    assert!(csv.contains("0,,1\r\n"));
+
+    let resp = get_with_session(
+        test_router(state),
+        "/web/admin/stats/downloads?format=csv&table=nope",
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+}
@@ -0,0 +1,205 @@
+use axum::body::Body;
+use axum::http::StatusCode;
+use base64::Engine;
+use ropds::{db, scanner};
+use tower::ServiceExt;
+
+use super::*;
+
+fn read_only_config(lib_dir: &Path, covers_dir: &Path, upload_dir: &Path) -> Config {
+    let mut config = test_config_with_upload(lib_dir, covers_dir, upload_dir);
+    config.server.read_only = true;
+    config.apply_read_only();
+    config
+}
+
+/// A read-only mirror refuses admin changes and scans, disables uploads,
+/// and keeps serving pages.
+#[tokio::test]
+async fn read_only_mirror_rejects_mutations() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = read_only_config(lib_dir.path(), covers_dir.path(), upload_dir.path());
+    assert!(!config.upload.allow_upload);
+
+    let super_id = create_test_user(&pool, "mirror-admin", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    let resp = post_form(
+        test_router(state.clone()),
+        "/web/admin/scan",
+        &format!("csrf_token={csrf}&path="),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    assert!(!ropds::scanner::is_scanning());
+
+    let resp = post_form(
+        test_router(state.clone()),
+        "/web/admin/users/create",
+        &format!("csrf_token={csrf}&username=newbie&password=password123"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let users: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE username = 'newbie'")
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    assert_eq!(users.0, 0);
+
+    let resp = get_with_session(test_router(state.clone()), "/web/admin", &session).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let html = body_string(resp).await;
+    assert!(html.contains("read-only-notice"));
+    assert!(!html.contains(r#"action="/web/admin/scan""#));
+
+    let resp = get_with_session(test_router(state.clone()), "/web/upload", &session).await;
+    assert_ne!(resp.status(), StatusCode::OK);
+
+    let resp = get(test_router(state), "/health").await;
+    let health: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(health["read_only"], true);
+}
+
+/// Every web form and JSON endpoint that writes is refused on a mirror;
+/// signing in still works.
+#[tokio::test]
+async fn read_only_mirror_rejects_web_writes() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = read_only_config(lib_dir.path(), covers_dir.path(), upload_dir.path());
+    let user_id = create_test_user(&pool, "mirror-reader", "password123", false).await;
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    for path in [
+        "/web/bookshelf/toggle",
+        "/web/bookshelf/clear",
+        "/web/bookshelf/share",
+        "/web/share",
+        "/web/api/reading-position",
+        "/web/profile/password",
+        "/web/profile/display-name",
+        "/web/profile/auto-bookshelf",
+        "/web/profile/opds-client",
+        "/web/profile/opds-reset",
+        "/web/upload/chunked",
+    ] {
+        let resp = post_form(
+            test_router(state.clone()),
+            path,
+            &format!("csrf_token={csrf}&book_id=1&display_name=Changed"),
+            &session,
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{path}");
+    }
+    let name: (String,) = sqlx::query_as("SELECT display_name FROM users WHERE id = ?")
+        .bind(user_id)
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    assert_ne!(name.0, "Changed");
+
+    let resp = post_form_with_cookies(
+        test_router(state),
+        "/web/login",
+        "username=mirror-reader&password=password123",
+        "",
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+    assert!(set_cookie(&resp, "session").is_some());
+}
+
+/// The REST API refuses anything but reads on a mirror.
+#[tokio::test]
+async fn read_only_mirror_rejects_api_writes() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = read_only_config(lib_dir.path(), covers_dir.path(), upload_dir.path());
+    let state = test_app_state(pool, config);
+
+    let resp = get(test_router(state.clone()), "/api/v1/books").await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    for method in ["POST", "PUT", "DELETE"] {
+        let req = axum::http::Request::builder()
+            .method(method)
+            .uri("/api/v1/books")
+            .body(Body::empty())
+            .unwrap();
+        let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{method}");
+    }
+}
+
+/// OPDS feeds and downloads are served on a mirror without recording the
+/// download or the client, and writes are refused.
+#[tokio::test]
+async fn read_only_mirror_records_no_opds_activity() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let mut config = read_only_config(lib_dir.path(), covers_dir.path(), upload_dir.path());
+    config.stats.user_agents = true;
+    config.stats.anonymous_downloads = true;
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = db::queries::books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let user_id = create_test_user(&pool, "mirror-opds", "password123", false).await;
+    let auth = format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode("mirror-opds:password123")
+    );
+    let state = test_app_state(pool.clone(), config);
+
+    let req = axum::http::Request::builder()
+        .uri(format!("/opds/download/{}/0/", book.id))
+        .header("authorization", &auth)
+        .header("user-agent", "KOReader/2024.04 (Kobo)")
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(
+        db::queries::devices::list_all(&pool)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    let downloads: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM downloads")
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    assert_eq!(downloads.0, 0);
+    assert!(
+        !db::queries::bookshelf::is_on_shelf(&pool, user_id, book.id)
+            .await
+            .unwrap()
+    );
+
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri("/opds/books/")
+        .header("authorization", &auth)
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
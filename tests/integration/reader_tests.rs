@@ -363,6 +363,8 @@ base_url = "http://localhost:8081"
 
 [library]
 root_path = {:?}
+
+[covers]
 covers_path = {:?}
 
 [database]
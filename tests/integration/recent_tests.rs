@@ -124,3 +124,54 @@ async fn home_shows_continue_reading_for_authenticated_user() {
     assert!(html.contains(&format!("/web/reader/{}", second_book.id)));
     assert!(html.contains("77%"));
 }
+
+/// The home dashboard adds a "new in your genres" shelf for signed-in users
+/// with download history; anonymous visitors still get random picks.
+#[tokio::test]
+async fn home_dashboard_shelves_degrade_for_anonymous() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    // Both FB2 files are tagged sf_fantasy.
+    copy_test_files(
+        lib_dir.path(),
+        &["test_book.fb2", "digit_title.fb2", "quoted_title.fb2"],
+    );
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let all = books::get_recent_added(&pool, 10, 0, false).await.unwrap();
+    let id_of = |filename: &str| all.iter().find(|b| b.filename == filename).unwrap().id;
+    let (read_id, fresh_id) = (id_of("test_book.fb2"), id_of("digit_title.fb2"));
+
+    let user_id = create_test_user(&pool, "dashboard", "secret", false).await;
+    let session = session_cookie_value(user_id);
+    let state = test_app_state(pool, config);
+
+    let resp = get_with_session(
+        test_router(state.clone()),
+        &format!("/web/download/{read_id}/0"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    let html =
+        body_string(get_with_session(test_router(state.clone()), "/web", &session).await).await;
+    let shelf_start = html
+        .find("home-genre-picks")
+        .expect("signed-in user should get a genre shelf");
+    let shelf =
+        &html[shelf_start..html[shelf_start..].find("home-random-picks").unwrap() + shelf_start];
+    assert!(shelf.contains(&format!("type=i&q={fresh_id}\"")));
+    assert!(
+        !shelf.contains(&format!("type=i&q={read_id}\"")),
+        "books already on the shelf are skipped"
+    );
+
+    let html = body_string(get(test_router(state), "/web").await).await;
+    assert!(!html.contains("home-genre-picks"));
+    assert!(html.contains("home-random-picks"));
+    assert!(html.matches("book-grid-card").count() >= 3);
+}
@@ -0,0 +1,161 @@
+use axum::body::Body;
+use base64::Engine;
+use ropds::db;
+use ropds::db::queries::{books, downloads, reading_positions, recommendations};
+use ropds::scanner;
+use tower::ServiceExt;
+
+use super::*;
+
+fn basic_auth(username: &str, password: &str) -> String {
+    let raw = format!("{username}:{password}");
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(raw.as_bytes())
+    )
+}
+
+async fn get_with_auth(app: Router, path: &str, auth: &str) -> axum::response::Response {
+    let req = axum::http::Request::builder()
+        .uri(path)
+        .header("authorization", auth)
+        .body(Body::empty())
+        .unwrap();
+    app.oneshot(req).await.unwrap()
+}
+
+/// Co-downloads become recommendations after a rebuild: shown on the book
+/// page and in the personal OPDS feeds of readers who share the taste.
+#[tokio::test]
+async fn recommendations_surface_on_book_page_and_opds() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "title_only.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let seed = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    let other = books::find_by_path_and_filename(&pool, "", "title_only.fb2")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+
+    let alice = create_test_user(&pool, "alice", "password123", false).await;
+    let bob = create_test_user(&pool, "bob", "password123", false).await;
+    for (book_id, user_id) in [(seed, alice), (other, alice), (seed, bob)] {
+        downloads::record(&pool, book_id, Some(user_id))
+            .await
+            .unwrap();
+    }
+    recommendations::rebuild(&pool).await.unwrap();
+
+    let state = test_app_state(pool.clone(), config);
+    let session = session_cookie_value(bob);
+
+    let html = body_string(
+        get_with_session(
+            test_router(state.clone()),
+            &format!("/web/search/books?type=i&q={seed}"),
+            &session,
+        )
+        .await,
+    )
+    .await;
+    assert!(html.contains("book-recommendations"));
+    assert!(html.contains("Lonely Title Book"));
+
+    let resp = get(test_router(state.clone()), "/opds/recommended/").await;
+    assert_eq!(resp.status(), 401);
+
+    let auth = basic_auth("bob", "password123");
+    let xml = body_string(get_with_auth(test_router(state.clone()), "/opds", &auth).await).await;
+    assert!(xml.contains("/opds/recommended/"), "root links the feed");
+
+    let xml =
+        body_string(get_with_auth(test_router(state.clone()), "/opds/recommended/", &auth).await)
+            .await;
+    assert!(xml.contains("Lonely Title Book"));
+    assert!(
+        !xml.contains("Test Book Title"),
+        "books the reader already downloaded are not recommended"
+    );
+
+    let resp = get_with_auth(test_router(state), "/opds/v2/recommended/", &auth).await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let publications = json["publications"].as_array().unwrap();
+    assert_eq!(publications.len(), 1);
+    assert_eq!(publications[0]["metadata"]["title"], "Lonely Title Book");
+}
+
+/// Finishing a volume suggests the next one of its series on the book page,
+/// the dashboard and the personal "next in series" feeds.
+#[tokio::test]
+async fn next_in_series_follows_finished_volumes() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    let second = std::fs::read_to_string(test_data_dir().join("test_book.fb2"))
+        .unwrap()
+        .replace("Test Book Title", "Second Volume")
+        .replace(r#"number="1""#, r#"number="2""#);
+    std::fs::write(lib_dir.path().join("second.fb2"), second).unwrap();
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let first = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+
+    let reader = create_test_user(&pool, "reader", "password123", false).await;
+    let state = test_app_state(pool.clone(), config);
+    let session = session_cookie_value(reader);
+    let auth = basic_auth("reader", "password123");
+    let book_page = format!("/web/search/books?type=i&q={first}");
+
+    reading_positions::save_position(&pool, reader, first, "", 0.5, 100)
+        .await
+        .unwrap();
+    let html =
+        body_string(get_with_session(test_router(state.clone()), &book_page, &session).await).await;
+    assert!(!html.contains("book-next-in-series"), "not finished yet");
+
+    reading_positions::save_position(&pool, reader, first, "", 1.0, 100)
+        .await
+        .unwrap();
+    let html =
+        body_string(get_with_session(test_router(state.clone()), &book_page, &session).await).await;
+    assert!(html.contains("book-next-in-series"));
+    assert!(html.contains("Second Volume"));
+    let html =
+        body_string(get_with_session(test_router(state.clone()), "/web", &session).await).await;
+    assert!(html.contains("home-next-in-series"));
+
+    let resp = get(test_router(state.clone()), "/opds/next/").await;
+    assert_eq!(resp.status(), 401);
+    let xml = body_string(get_with_auth(test_router(state.clone()), "/opds", &auth).await).await;
+    assert!(xml.contains("/opds/next/"), "root links the feed");
+    let xml =
+        body_string(get_with_auth(test_router(state.clone()), "/opds/next/", &auth).await).await;
+    assert!(xml.contains("Second Volume"));
+    assert!(!xml.contains("Test Book Title"));
+
+    let resp = get_with_auth(test_router(state), "/opds/v2/next/", &auth).await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let publications = json["publications"].as_array().unwrap();
+    assert_eq!(publications.len(), 1);
+    assert_eq!(publications[0]["metadata"]["title"], "Second Volume");
+}
@@ -1,6 +1,8 @@
 use ropds::db;
 use ropds::db::models::AvailStatus;
-use ropds::db::queries::{authors, books, counters, genres, series};
+use ropds::db::queries::{
+    authors, book_audio, book_files, books, counters, genres, scan_lock, series,
+};
 use ropds::scanner;
 use std::io::Write;
 
@@ -38,6 +40,35 @@ async fn scan_adds_books_from_files() {
     assert_eq!(allbooks, 5);
 }
 
+/// Books without a language tag get one guessed from their first lines,
+/// unless `library.detect_lang` is off.
+#[tokio::test]
+async fn scan_detects_missing_language_from_text() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    for (detect_lang, expected) in [(true, "en"), (false, "")] {
+        let pool = db::create_test_pool().await;
+        let lib_dir = tempfile::tempdir().unwrap();
+        let covers_dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(lib_dir.path(), covers_dir.path());
+        config.library.book_extensions.push("txt".to_string());
+        config.library.detect_lang = detect_lang;
+        std::fs::write(
+            lib_dir.path().join("voyage.txt"),
+            "The Voyage\n\nThe ship arrived at the station in the morning, and the \
+             captain was waiting for her with his crew on the pier.\n",
+        )
+        .unwrap();
+
+        scanner::run_scan(&pool, &config).await.unwrap();
+        let book = books::find_by_path_and_filename(&pool, "", "voyage.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(book.lang, expected, "detect_lang = {detect_lang}");
+    }
+}
+
 /// Books inside ZIP archives are scanned.
 #[tokio::test]
 async fn scan_adds_books_from_zip() {
@@ -171,6 +202,77 @@ async fn scan_inpx_enriches_annotation_and_cover_from_zip() {
     );
 }
 
+/// INPX `collection.info` names the catalog, `version.info` decides whether
+/// the index is rescanned, and records use `library.zip_codepage`.
+#[tokio::test]
+async fn scan_inpx_collection_and_version_info() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.library.inpx_enable = true;
+    config.library.zip_codepage = "cp1251".to_string();
+
+    let sep = '\u{0004}';
+    let line = format!(
+        "Толстой,Лев{sep}prose{sep}Война и мир{sep}{sep}0{sep}war{sep}100{sep}lib{sep}0{sep}fb2{sep}1869{sep}ru"
+    );
+    let (record, _, _) = encoding_rs::WINDOWS_1251.encode(&line);
+    let write_inpx = |version: &str, extra: &[u8]| {
+        let file = std::fs::File::create(lib_dir.path().join("library.inpx")).unwrap();
+        let mut zip = zip::ZipWriter::new(file);
+        let opts = zip::write::SimpleFileOptions::default();
+        zip.start_file("collection.info", opts).unwrap();
+        zip.write_all("Flibusta Local\n".as_bytes()).unwrap();
+        zip.start_file("version.info", opts).unwrap();
+        zip.write_all(version.as_bytes()).unwrap();
+        zip.start_file("pack-0001.inp", opts).unwrap();
+        zip.write_all(&record).unwrap();
+        zip.write_all(b"\n").unwrap();
+        zip.write_all(extra).unwrap();
+        zip.finish().unwrap();
+    };
+
+    write_inpx("20240101", b"");
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 1);
+
+    let book = books::find_by_path_and_filename(&pool, "pack-0001.zip", "war.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(book.title, "Война и мир");
+    let cat = ropds::db::queries::catalogs::find_by_path(&pool, "library.inpx")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cat.cat_name, "Flibusta Local");
+    assert_eq!(cat.cat_version, "20240101");
+    let group = ropds::db::queries::catalogs::find_by_path(&pool, "pack-0001.zip")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        group.parent_id,
+        Some(cat.id),
+        "archives nest under the collection"
+    );
+
+    // A different size with the same version is still unchanged.
+    write_inpx("20240101", b"\n\n");
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.archives_skipped, 1);
+    assert_eq!(stats.archives_scanned, 0);
+
+    write_inpx("20240201", b"\n\n");
+    let preview = scanner::preview_scan(&pool, &config).await.unwrap();
+    assert_eq!(preview.changed_archives, vec!["library.inpx"]);
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.archives_scanned, 1);
+}
+
 /// Missing referenced ZIP during INPX scan should not fail the book insert.
 #[tokio::test]
 async fn scan_inpx_missing_referenced_zip_keeps_inpx_metadata_only() {
@@ -619,3 +721,529 @@ async fn scan_books_have_confirmed_status() {
         .unwrap();
     assert_eq!(book.avail, AvailStatus::Confirmed as i32);
 }
+
+/// Scan preview reports the difference to the library without applying it,
+/// and the admin page renders the same report.
+#[tokio::test]
+async fn scan_preview_reports_without_applying() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "test_book.epub"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    std::fs::remove_file(lib_dir.path().join("test_book.fb2")).unwrap();
+    copy_test_files(lib_dir.path(), &["no_cover.fb2"]);
+
+    let preview = scanner::preview_scan(&pool, &config).await.unwrap();
+    assert_eq!(preview.new_books, vec!["no_cover.fb2"]);
+    assert_eq!(preview.missing_books, vec!["test_book.fb2"]);
+    assert!(preview.new_archives.is_empty() && preview.changed_archives.is_empty());
+
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(book.avail, AvailStatus::Confirmed as i32);
+    assert!(
+        books::find_by_path_and_filename(&pool, "", "no_cover.fb2")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let super_id = create_test_user(&pool, "preview-admin", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let state = test_app_state(pool, config);
+    let resp = get_with_session(test_router(state), "/web/admin/scan-preview", &session).await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("preview-new_books") && html.contains("no_cover.fb2"));
+    assert!(html.contains("preview-missing_books"));
+}
+
+/// A scan limited to one folder adds and removes books only under it.
+#[tokio::test]
+async fn scan_path_limits_walk_and_cleanup() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files_to_subdir(lib_dir.path(), "fiction/new", &["test_book.fb2"]);
+    copy_test_files_to_subdir(lib_dir.path(), "poetry", &["test_book.epub"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+
+    std::fs::remove_file(lib_dir.path().join("fiction/new/test_book.fb2")).unwrap();
+    std::fs::remove_file(lib_dir.path().join("poetry/test_book.epub")).unwrap();
+    copy_test_files_to_subdir(lib_dir.path(), "fiction/new", &["no_cover.fb2"]);
+    copy_test_files_to_subdir(lib_dir.path(), "poetry", &["title_only.fb2"]);
+
+    let preview = scanner::preview_scan_path(&pool, &config, "/fiction/new/")
+        .await
+        .unwrap();
+    assert_eq!(preview.new_books, vec!["fiction/new/no_cover.fb2"]);
+    assert_eq!(preview.missing_books, vec!["fiction/new/test_book.fb2"]);
+
+    let stats = scanner::run_scan_path(&pool, &config, "fiction/new")
+        .await
+        .unwrap();
+    assert_eq!(stats.books_added, 1);
+    assert_eq!(stats.books_deleted, 1);
+
+    let avail = |path: &'static str, filename: &'static str| {
+        let pool = pool.clone();
+        async move {
+            books::find_by_path_and_filename(&pool, path, filename)
+                .await
+                .unwrap()
+                .map(|b| b.avail)
+        }
+    };
+    assert_eq!(
+        avail("fiction/new", "test_book.fb2").await,
+        Some(AvailStatus::Deleted as i32)
+    );
+    assert_eq!(
+        avail("fiction/new", "no_cover.fb2").await,
+        Some(AvailStatus::Confirmed as i32)
+    );
+    assert_eq!(
+        avail("poetry", "test_book.epub").await,
+        Some(AvailStatus::Confirmed as i32),
+        "books outside the scanned folder are untouched"
+    );
+    assert_eq!(avail("poetry", "title_only.fb2").await, None);
+
+    for bad in ["../etc", "missing", "fiction/new/no_cover.fb2"] {
+        assert!(matches!(
+            scanner::run_scan_path(&pool, &config, bad).await,
+            Err(scanner::ScanError::InvalidPath(_))
+        ));
+    }
+
+    let super_id = create_test_user(&pool, "scan-path-admin", "password123", true).await;
+    let session = session_cookie_value(super_id);
+    let body = format!("csrf_token={}&path=..%2Fetc", csrf_for_session(&session));
+    let state = test_app_state(pool, config);
+    let resp = post_form(test_router(state), "/web/admin/scan", &body, &session).await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(
+        resp.headers()["location"],
+        "/web/admin?error=invalid_scan_path"
+    );
+}
+
+/// Audiobook folders are scanned as one book, typed as audiobooks in OPDS and
+/// streamed track by track with Range support.
+#[tokio::test]
+async fn scan_audiobook_folder_and_stream_tracks() {
+    use tower::ServiceExt;
+
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.library.audiobooks = true;
+
+    // Two seconds of 128 kbps CBR frames per track.
+    let mut frame = vec![0xff, 0xfb, 0x90, 0x00];
+    frame.resize(417, 0);
+    let track = frame.repeat(2 * 128_000 / 8 / 417);
+    let book_dir = lib_dir.path().join("audio").join("Hobbit");
+    std::fs::create_dir_all(&book_dir).unwrap();
+    std::fs::write(
+        book_dir.join("metadata.json"),
+        r#"{"title": "The Hobbit", "authors": ["J. R. R. Tolkien"], "narrators": ["Andy Serkis"]}"#,
+    )
+    .unwrap();
+    std::fs::write(book_dir.join("01.mp3"), &track).unwrap();
+    std::fs::write(book_dir.join("02.mp3"), &track).unwrap();
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 1, "the folder is one book");
+    let book = books::find_by_path_and_filename(&pool, "audio", "Hobbit")
+        .await
+        .unwrap()
+        .expect("audiobook should be stored");
+    assert_eq!(book.title, "The Hobbit");
+    assert_eq!(book.format, "mp3");
+    let audio = book_audio::get_for_book(&pool, book.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(audio.narrator, "Andy Serkis");
+    assert_eq!(audio.duration, 4);
+
+    let state = test_app_state(pool, config);
+    let resp = get(test_router(state.clone()), "/opds/search/books/m/Hobbit/").await;
+    let body = body_string(resp).await;
+    assert!(body.contains("<dcterms:type>http://bib.schema.org/Audiobook</dcterms:type>"));
+    assert!(body.contains(&format!("/opds/audio/{}/1/", book.id)));
+    assert!(body.contains("<b>Narrator: </b>Andy Serkis"));
+
+    let req = axum::http::Request::builder()
+        .uri(format!("/opds/audio/{}/1/", book.id))
+        .header("range", "bytes=0-99")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 206);
+    assert_eq!(resp.headers()["content-type"], "audio/mpeg");
+    assert_eq!(resp.headers()["content-length"], "100");
+
+    let resp = get(test_router(state), &format!("/opds/audio/{}/2/", book.id)).await;
+    assert_eq!(resp.status(), 404);
+}
+
+/// Instances sharing a database never scan at the same time: a live scan lock
+/// held by another instance blocks the scan, an expired one is taken over
+/// and released when the scan ends.
+#[tokio::test]
+async fn scan_respects_database_lock_of_other_instance() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+
+    let now = chrono::Utc::now().timestamp();
+    assert!(
+        scan_lock::try_acquire(&pool, "other-host:42:1", now, now + 300)
+            .await
+            .unwrap()
+    );
+    assert!(scanner::scan_in_progress(&pool).await);
+    let res = scanner::run_scan(&pool, &config).await;
+    assert!(matches!(res, Err(scanner::ScanError::AlreadyRunning)));
+
+    // The other instance died an hour ago: its lock has expired.
+    sqlx::query("UPDATE scan_lock SET expires_at = ? WHERE id = 1")
+        .bind(now - 3600)
+        .execute(pool.inner())
+        .await
+        .unwrap();
+    assert!(!scanner::scan_in_progress(&pool).await);
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 1);
+    assert_eq!(scan_lock::current_holder(&pool, now).await.unwrap(), None);
+}
+
+/// A symlink cycle and folders past `scanner.max_depth` do not hang or fail
+/// the scan; they are listed as skipped paths in the report.
+#[cfg(unix)]
+#[tokio::test]
+async fn scan_reports_symlink_loops_and_depth_limit() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.scanner.max_depth = 2;
+
+    copy_test_files_to_subdir(lib_dir.path(), "shelf", &["test_book.fb2"]);
+    copy_test_files_to_subdir(lib_dir.path(), "shelf/a/b", &["test_book.epub"]);
+    std::os::unix::fs::symlink(
+        lib_dir.path().join("shelf"),
+        lib_dir.path().join("shelf/loop"),
+    )
+    .unwrap();
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 1, "only the book within the depth limit");
+    assert_eq!(stats.errors, 0);
+    assert_eq!(stats.paths_skipped, 2);
+    let reasons: Vec<(&str, scanner::SkipReason)> = stats
+        .skipped_paths
+        .iter()
+        .map(|s| (s.path.as_str(), s.reason))
+        .collect();
+    assert!(reasons.contains(&("shelf/loop", scanner::SkipReason::SymlinkLoop)));
+    assert!(reasons.contains(&("shelf/a", scanner::SkipReason::MaxDepth)));
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert!(json["skipped_paths"][0]["reason"].is_string());
+}
+
+/// File and folder names that are not UTF-8 (here cp1251) are stored
+/// decoded, and the books can still be downloaded, from a plain file as
+/// well as from an archive.
+#[tokio::test]
+async fn scan_and_download_non_utf8_file_names() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    // "Книги/Привет.fb2" and "Книги/Архив.zip" in cp1251.
+    let data_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let shelf = lib_dir
+        .path()
+        .join(OsStr::from_bytes(b"\xca\xed\xe8\xe3\xe8"));
+    std::fs::create_dir(&shelf).unwrap();
+    std::fs::copy(
+        data_dir.join("test_book.fb2"),
+        shelf.join(OsStr::from_bytes(b"\xcf\xf0\xe8\xe2\xe5\xf2.fb2")),
+    )
+    .unwrap();
+    std::fs::copy(
+        data_dir.join("test_book.fb2.zip"),
+        shelf.join(OsStr::from_bytes(b"\xc0\xf0\xf5\xe8\xe2.zip")),
+    )
+    .unwrap();
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 2);
+    assert_eq!(stats.errors, 0);
+    let plain = books::find_by_path_and_filename(&pool, "Книги", "Привет.fb2")
+        .await
+        .unwrap()
+        .expect("plain file stored under its decoded name");
+    let archived = books::find_by_path_and_filename(&pool, "Книги/Архив.zip", "test_book.fb2")
+        .await
+        .unwrap()
+        .expect("archive stored under its decoded name");
+
+    let state = test_app_state(pool.clone(), config.clone());
+    for book in [&plain, &archived] {
+        let resp = get(
+            test_router(state.clone()),
+            &format!("/opds/download/{}/0/", book.id),
+        )
+        .await;
+        assert_eq!(resp.status(), 200, "download of {}", book.path);
+        assert!(body_string(resp).await.contains("Test Book Title"));
+    }
+
+    // A rescan matches the stored names and keeps the books.
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_deleted, 0);
+    assert!(books::get_by_id(&pool, plain.id).await.unwrap().is_some());
+}
+
+/// ZIP entry names without the UTF-8 flag are decoded with
+/// `library.zip_codepage` (cp866 by default), and the book is downloaded
+/// from the archive by that name.
+#[tokio::test]
+async fn scan_and_download_zip_entry_in_codepage() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    let fb2 = std::fs::read(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/test_book.fb2"),
+    )
+    .unwrap();
+    let zip_path = lib_dir.path().join("old.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    zip.start_file("XXXXXX.fb2", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(&fb2).unwrap();
+    zip.finish().unwrap();
+    // "Привет.fb2" in cp866, without the UTF-8 flag.
+    let mut bytes = std::fs::read(&zip_path).unwrap();
+    for i in 0..bytes.len() - 6 {
+        if &bytes[i..i + 6] == b"XXXXXX" {
+            bytes[i..i + 6].copy_from_slice(b"\x8f\xe0\xa8\xa2\xa5\xe2");
+        }
+    }
+    std::fs::write(&zip_path, bytes).unwrap();
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 1);
+    let book = books::find_by_path_and_filename(&pool, "old.zip", "Привет.fb2")
+        .await
+        .unwrap()
+        .expect("entry stored under its cp866 name");
+
+    let state = test_app_state(pool, config);
+    let resp = get(
+        test_router(state),
+        &format!("/opds/download/{}/0/", book.id),
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    assert!(body_string(resp).await.contains("Test Book Title"));
+}
+
+/// Scan observers see every added, updated and deleted book and the end of
+/// the scan; `scanner.changes_log` writes the same changes as JSON lines.
+#[tokio::test]
+async fn scan_observers_receive_book_changes() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl scanner::ScanObserver for Recorder {
+        fn book_added(&self, book: &scanner::ScannedBook<'_>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("added {}", book.filename));
+        }
+        fn book_updated(&self, book: &scanner::ScannedBook<'_>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("updated {}", book.filename));
+        }
+        fn book_deleted(&self, book_id: i64) {
+            self.0.lock().unwrap().push(format!("deleted {book_id}"));
+        }
+        fn scan_finished(&self, stats: &scanner::ScanStatsSnapshot) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("finished {}", stats.books_added));
+        }
+    }
+
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let log_dir = tempfile::tempdir().unwrap();
+    let log_path = log_dir.path().join("changes.jsonl");
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.scanner.changes_log = Some(log_path.clone());
+    config.scanner.delete_logical = false;
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "title_only.fb2"]);
+
+    let recorder = Arc::new(Recorder::default());
+    let state = test_app_state(pool.clone(), config.clone()).with_scan_observer(recorder.clone());
+    let observers = state.scan_observers.clone();
+
+    scanner::run_scan_path_observed(&pool, &config, "", &observers)
+        .await
+        .unwrap();
+    let mut events = std::mem::take(&mut *recorder.0.lock().unwrap());
+    events.sort();
+    assert_eq!(
+        events,
+        ["added test_book.fb2", "added title_only.fb2", "finished 2"]
+    );
+
+    let removed = books::find_by_path_and_filename(&pool, "", "title_only.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    std::fs::remove_file(lib_dir.path().join("title_only.fb2")).unwrap();
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(lib_dir.path().join("test_book.fb2"))
+        .unwrap();
+    file.write_all(b"\n").unwrap();
+    drop(file);
+
+    scanner::run_scan_path_observed(&pool, &config, "", &observers)
+        .await
+        .unwrap();
+    let events = std::mem::take(&mut *recorder.0.lock().unwrap());
+    assert_eq!(
+        events,
+        [
+            "updated test_book.fb2".to_string(),
+            format!("deleted {}", removed.id),
+            "finished 0".to_string(),
+        ]
+    );
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    let lines: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let kinds: Vec<&str> = lines.iter().map(|l| l["event"].as_str().unwrap()).collect();
+    assert_eq!(
+        kinds,
+        [
+            "added", "added", "finished", "updated", "deleted", "finished"
+        ]
+    );
+    assert_eq!(lines[4]["id"], removed.id);
+    assert_eq!(lines[3]["title"], "Test Book Title");
+}
+
+/// With `scanner.link_identical`, identical files become copies of one book;
+/// a book whose own file is removed is served from and then moved to a copy.
+#[tokio::test]
+async fn scan_links_identical_files_as_copies() {
+    let _lock = SCAN_MUTEX.lock().await;
+
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    copy_test_files_to_subdir(lib_dir.path(), "a", &["test_book.fb2"]);
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 1);
+
+    // Books stored before the option was on are hashed when a file of the
+    // same size turns up; identical new files of one scan are linked too.
+    config.scanner.link_identical = true;
+    copy_test_files_to_subdir(lib_dir.path(), "b", &["test_book.fb2", "test_book.epub"]);
+    copy_test_files_to_subdir(lib_dir.path(), "c", &["test_book.epub"]);
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 1, "only the epub is a new book");
+
+    let fb2 = books::find_by_path_and_filename(&pool, "a", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fb2.file_hash.len(), 64);
+    let copies = book_files::get_for_book(&pool, fb2.id).await.unwrap();
+    assert_eq!(copies.len(), 1);
+    assert_eq!(
+        (copies[0].path.as_str(), copies[0].filename.as_str()),
+        ("b", "test_book.fb2")
+    );
+    let epub = books::find_by_path_and_filename(&pool, "b", "test_book.epub")
+        .await
+        .unwrap()
+        .unwrap();
+    let copies = book_files::get_for_book(&pool, epub.id).await.unwrap();
+    assert_eq!(copies.len(), 1);
+    assert_eq!(copies[0].path, "c");
+
+    // Rescanning keeps the copies without adding books.
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!((stats.books_added, stats.books_deleted), (0, 0));
+    assert_eq!(book_files::list_for_scan(&pool).await.unwrap().len(), 2);
+
+    std::fs::remove_file(lib_dir.path().join("a/test_book.fb2")).unwrap();
+    std::fs::remove_file(lib_dir.path().join("c/test_book.epub")).unwrap();
+    let state = test_app_state(pool.clone(), config.clone());
+    let resp = get(test_router(state), &format!("/opds/download/{}/0/", fb2.id)).await;
+    assert_eq!(resp.status(), 200, "served from the copy");
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_deleted, 0);
+    let moved = books::get_by_id(&pool, fb2.id).await.unwrap().unwrap();
+    assert_eq!(
+        (moved.path.as_str(), moved.avail),
+        ("b", AvailStatus::Confirmed as i32)
+    );
+    assert!(book_files::list_for_scan(&pool).await.unwrap().is_empty());
+}
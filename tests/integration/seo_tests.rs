@@ -0,0 +1,118 @@
+use ropds::db;
+use ropds::db::queries::books;
+use ropds::scanner;
+
+use super::*;
+
+#[tokio::test]
+async fn public_library_serves_sitemap_and_book_metadata() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let book_page = format!("/web/search/books?type=i&q={}", book.id);
+
+    // Off by default: no sitemap, no metadata.
+    let state = test_app_state(pool.clone(), config.clone());
+    let resp = get(test_router(state.clone()), "/sitemap.xml").await;
+    assert_eq!(resp.status(), 404);
+    let html = body_string(get(test_router(state), &book_page).await).await;
+    assert!(!html.contains("application/ld+json"));
+
+    config.web.seo_enabled = true;
+    let state = test_app_state(pool.clone(), config.clone());
+    let resp = get(test_router(state.clone()), "/sitemap.xml").await;
+    assert_eq!(resp.status(), 200);
+    assert!(
+        resp.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("application/xml")
+    );
+    let index = body_string(resp).await;
+    assert!(index.contains("<loc>http://localhost:8081/sitemap/catalogs/1.xml</loc>"));
+    assert!(index.contains("<loc>http://localhost:8081/sitemap/books/1.xml</loc>"));
+
+    let books_map =
+        body_string(get(test_router(state.clone()), "/sitemap/books/1.xml").await).await;
+    assert!(books_map.contains(&format!(
+        "<loc>http://localhost:8081/web/search/books?type=i&amp;q={}</loc>",
+        book.id
+    )));
+    assert!(books_map.contains("<lastmod>"));
+    for missing in [
+        "/sitemap/books/2.xml",
+        "/sitemap/users/1.xml",
+        "/sitemap/books/x",
+    ] {
+        let resp = get(test_router(state.clone()), missing).await;
+        assert_eq!(resp.status(), 404, "{missing}");
+    }
+
+    let html = body_string(get(test_router(state), &book_page).await).await;
+    let start = html.find(r#"<script type="application/ld+json">"#).unwrap();
+    let json = &html[start..];
+    let json = &json[json.find('>').unwrap() + 1..json.find("</script>").unwrap()];
+    let doc: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(doc["@type"], "Book");
+    assert_eq!(doc["name"], book.title);
+    assert_eq!(
+        doc["url"],
+        format!(
+            "http://localhost:8081/web/search/books?type=i&q={}",
+            book.id
+        )
+    );
+    assert!(doc["author"][0]["name"].is_string());
+
+    // Libraries behind a login are never advertised.
+    config.opds.auth_required = true;
+    let state = test_app_state(pool, config);
+    let resp = get(test_router(state), "/sitemap.xml").await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn robots_txt_and_noindex_follow_seo_setting() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+
+    let state = test_app_state(pool.clone(), config.clone());
+    let resp = get(test_router(state.clone()), "/robots.txt").await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["x-robots-tag"], "noindex, nofollow");
+    assert_eq!(body_string(resp).await, "User-agent: *\nDisallow: /\n");
+    for path in ["/web", "/opds", "/health"] {
+        let resp = get(test_router(state.clone()), path).await;
+        assert_eq!(
+            resp.headers()["x-robots-tag"],
+            "noindex, nofollow",
+            "{path}"
+        );
+    }
+
+    config.web.seo_enabled = true;
+    let state = test_app_state(pool.clone(), config.clone());
+    let resp = get(test_router(state.clone()), "/robots.txt").await;
+    assert!(resp.headers().get("x-robots-tag").is_none());
+    let robots = body_string(resp).await;
+    assert!(robots.contains("Disallow: /web/admin\n"));
+    assert!(robots.contains("Sitemap: http://localhost:8081/sitemap.xml\n"));
+    let resp = get(test_router(state), "/web").await;
+    assert!(resp.headers().get("x-robots-tag").is_none());
+
+    config.web.robots_txt = Some("User-agent: *\nAllow: /\n".to_string());
+    let state = test_app_state(pool, config);
+    let resp = get(test_router(state), "/robots.txt").await;
+    assert_eq!(body_string(resp).await, "User-agent: *\nAllow: /\n");
+}
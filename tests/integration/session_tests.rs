@@ -0,0 +1,205 @@
+use hmac::{Hmac, KeyInit, Mac};
+use ropds::db;
+use ropds::web::auth::parse_session;
+
+use super::*;
+
+const SECRET: &[u8] = b"test-secret-key-for-integration-tests";
+
+/// Session cookie value of `user_id` expiring in `expires_in` seconds,
+/// issued with a `ttl_hours` lifetime and optionally "Remember me".
+fn session_expiring_in(user_id: i64, expires_in: i64, ttl_hours: u64, remember: bool) -> String {
+    let expiry = chrono::Utc::now().timestamp() + expires_in;
+    let remember = u8::from(remember);
+    let payload = format!("{user_id}:{expiry}:{ttl_hours}:{remember}");
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(SECRET).unwrap();
+    mac.update(payload.as_bytes());
+    format!("{payload}:{}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Attributes of the `session` cookie set by the response.
+fn session_set_cookie(resp: &axum::response::Response) -> Option<String> {
+    resp.headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("session="))
+        .map(str::to_string)
+}
+
+#[tokio::test]
+async fn remember_me_login_issues_a_persistent_session() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    config.server.remember_me_ttl_hours = 240;
+    let user_id = create_test_user(&pool, "remember", "password123", false).await;
+    let state = test_app_state(pool, config);
+
+    let resp = get(test_router(state.clone()), "/web/login").await;
+    assert!(body_string(resp).await.contains("name=\"remember\""));
+
+    let resp = post_form_with_cookies(
+        test_router(state.clone()),
+        "/web/login",
+        "username=remember&password=password123",
+        "",
+    )
+    .await;
+    let cookie = session_set_cookie(&resp).unwrap();
+    assert!(!cookie.contains("Max-Age"));
+    let session = parse_session(&set_cookie(&resp, "session").unwrap(), SECRET).unwrap();
+    assert_eq!((session.user_id, session.ttl_hours), (user_id, 24));
+    assert!(!session.remember);
+
+    let resp = post_form_with_cookies(
+        test_router(state),
+        "/web/login",
+        "username=remember&password=password123&remember=on",
+        "",
+    )
+    .await;
+    let cookie = session_set_cookie(&resp).unwrap();
+    assert!(cookie.contains("Max-Age=864000"));
+    let session = parse_session(&set_cookie(&resp, "session").unwrap(), SECRET).unwrap();
+    assert_eq!(session.ttl_hours, 240);
+    assert!(session.remember);
+}
+
+/// "Remember me" is carried in the session itself, so it holds when both
+/// lifetimes are equal and survives renewal.
+#[tokio::test]
+async fn remember_me_does_not_depend_on_the_ttl() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    config.server.remember_me_ttl_hours = config.server.session_ttl_hours;
+    let user_id = create_test_user(&pool, "same-ttl", "password123", false).await;
+    let state = test_app_state(pool, config);
+
+    let resp = post_form_with_cookies(
+        test_router(state.clone()),
+        "/web/login",
+        "username=same-ttl&password=password123",
+        "",
+    )
+    .await;
+    assert!(!session_set_cookie(&resp).unwrap().contains("Max-Age"));
+
+    let aging = session_expiring_in(user_id, 3600, 24, true);
+    let resp = get_with_session(test_router(state), "/web/profile", &aging).await;
+    assert_eq!(resp.status(), 200);
+    assert!(session_set_cookie(&resp).unwrap().contains("Max-Age=86400"));
+    let renewed = parse_session(&set_cookie(&resp, "session").unwrap(), SECRET).unwrap();
+    assert!(renewed.remember);
+}
+
+#[tokio::test]
+async fn sessions_past_half_their_ttl_are_renewed() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    let user_id = create_test_user(&pool, "sliding", "password123", false).await;
+    let state = test_app_state(pool.clone(), config.clone());
+
+    // Fresh sessions are left alone.
+    let fresh = session_expiring_in(user_id, 20 * 3600, 24, false);
+    let resp = get_with_session(test_router(state.clone()), "/web/profile", &fresh).await;
+    assert_eq!(resp.status(), 200);
+    assert!(session_set_cookie(&resp).is_none());
+
+    let aging = session_expiring_in(user_id, 3600, 24, false);
+    let resp = get_with_session(test_router(state), "/web/profile", &aging).await;
+    assert_eq!(resp.status(), 200);
+    let renewed = parse_session(&set_cookie(&resp, "session").unwrap(), SECRET).unwrap();
+    assert_eq!((renewed.user_id, renewed.ttl_hours), (user_id, 24));
+    assert!(renewed.expiry > chrono::Utc::now().timestamp() + 23 * 3600);
+
+    config.server.session_sliding = false;
+    let state = test_app_state(pool, config);
+    let resp = get_with_session(test_router(state), "/web/profile", &aging).await;
+    assert!(session_set_cookie(&resp).is_none());
+}
+
+/// Only a validly signed impersonator cookie holds back renewal.
+#[tokio::test]
+async fn forged_impersonator_cookie_does_not_stop_renewal() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    let user_id = create_test_user(&pool, "forged-imp", "password123", false).await;
+    let admin_id = create_test_user(&pool, "real-imp", "password123", true).await;
+    let state = test_app_state(pool, config);
+
+    let aging = session_expiring_in(user_id, 3600, 24, false);
+    let forged = format!("session={aging}; impersonator=1:2:3:0:bogus");
+    let req = axum::http::Request::builder()
+        .uri("/web/profile")
+        .header("cookie", &forged)
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(session_set_cookie(&resp).is_some());
+
+    let impersonator = session_expiring_in(admin_id, 3600, 24, false);
+    let genuine = format!("session={aging}; impersonator={impersonator}");
+    let req = axum::http::Request::builder()
+        .uri("/web/profile")
+        .header("cookie", &genuine)
+        .body(Body::empty())
+        .unwrap();
+    let resp = test_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert!(session_set_cookie(&resp).is_none());
+}
+
+/// Ending an impersonation gives back the admin's "Remember me" cookie.
+#[tokio::test]
+async fn ending_impersonation_restores_a_persistent_session() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    let admin_id = create_test_user(&pool, "remember-imp", "password123", true).await;
+    let reader_id = create_test_user(&pool, "reader-remember", "password123", false).await;
+    let state = test_app_state(pool, config);
+
+    let admin_session = ropds::web::auth::sign_session_with(admin_id, SECRET, 240, true);
+    let resp = post_form_with_cookies(
+        test_router(state.clone()),
+        &format!("/web/admin/impersonate/{reader_id}"),
+        &format!("csrf_token={}", csrf_for_session(&admin_session)),
+        &format!("session={admin_session}"),
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    let reader_session = set_cookie(&resp, "session").unwrap();
+
+    let resp = post_form_with_cookies(
+        test_router(state),
+        "/web/impersonate/exit",
+        &format!("csrf_token={}", csrf_for_session(&reader_session)),
+        &format!("session={reader_session}; impersonator={admin_session}"),
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(
+        set_cookie(&resp, "session").as_deref(),
+        Some(&*admin_session)
+    );
+    assert!(
+        session_set_cookie(&resp)
+            .unwrap()
+            .contains("Max-Age=864000")
+    );
+}
@@ -0,0 +1,199 @@
+use axum::http::StatusCode;
+use http_body_util::BodyExt;
+use ropds::db;
+use ropds::db::queries::books;
+use ropds::scanner;
+
+use super::*;
+
+/// A share link downloads its book without a session until an admin
+/// revokes it; forged links are refused.
+#[tokio::test]
+async fn share_link_downloads_book_until_revoked() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let admin_id = create_test_user(&pool, "share-admin", "password123", true).await;
+    let session = session_cookie_value(admin_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    let resp = post_form(
+        test_router(state.clone()),
+        "/web/share",
+        &format!("book_id={}&csrf_token={csrf}", book.id),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let created: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let url = created["url"].as_str().unwrap();
+    let path = url
+        .strip_prefix("http://localhost:8081")
+        .unwrap()
+        .to_string();
+    assert!(path.starts_with("/share/"));
+
+    let resp = get(test_router(state.clone()), &path).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let expected = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    assert_eq!(body.as_ref(), expected.as_slice());
+
+    let forged = path.replacen(&format!("/share/{}.", book.id), "/share/999999.", 1);
+    let resp = get(test_router(state.clone()), &forged).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = get_with_session(test_router(state.clone()), "/web/admin/shares", &session).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let html = body_string(resp).await;
+    assert!(html.contains("Test Book Title"));
+
+    let (share_id,): (i64,) = sqlx::query_as("SELECT id FROM book_shares")
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    let resp = post_form(
+        test_router(state.clone()),
+        &format!("/web/admin/shares/{share_id}/revoke"),
+        &format!("csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+
+    let resp = get(test_router(state), &path).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+/// With `book_link_ttl_hours = 0` no links can be created.
+#[tokio::test]
+async fn share_links_disabled_by_config() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.sharing.book_link_ttl_hours = 0;
+
+    let user_id = create_test_user(&pool, "share-user", "password123", false).await;
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool, config);
+
+    let resp = post_form(
+        test_router(state),
+        "/web/share",
+        &format!("book_id=1&csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+/// A bookshelf guest link opens the shelf on the web and over OPDS without
+/// credentials, downloads only books on that shelf and stops working once
+/// revoked.
+#[tokio::test]
+async fn shelf_link_grants_read_only_access_until_revoked() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files(lib_dir.path(), &["test_book.fb2", "test_book.epub"]);
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.opds.auth_required = true;
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let shelved = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let other = books::find_by_path_and_filename(&pool, "", "test_book.epub")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let user_id = create_test_user(&pool, "shelf-owner", "password123", true).await;
+    ropds::db::queries::bookshelf::upsert(&pool, user_id, shelved.id)
+        .await
+        .unwrap();
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    let resp = post_form(
+        test_router(state.clone()),
+        "/web/bookshelf/share",
+        &format!("csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let created: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let web_path = created["url"]
+        .as_str()
+        .unwrap()
+        .strip_prefix("http://localhost:8081")
+        .unwrap()
+        .to_string();
+    let opds_path = created["opds_url"]
+        .as_str()
+        .unwrap()
+        .strip_prefix("http://localhost:8081")
+        .unwrap()
+        .to_string();
+    assert!(web_path.starts_with("/web/shared/"));
+    assert!(opds_path.starts_with("/opds/shared/"));
+
+    let resp = get(test_router(state.clone()), &web_path).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let html = body_string(resp).await;
+    assert!(html.contains("Test Book Title"));
+    assert!(html.contains("shelf-owner"));
+
+    let resp = get(test_router(state.clone()), &opds_path).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let xml = body_string(resp).await;
+    let download = format!("{opds_path}download/{}/", shelved.id);
+    assert!(xml.contains(&download));
+
+    let resp = get(test_router(state.clone()), &download).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let expected = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    assert_eq!(body.as_ref(), expected.as_slice());
+
+    // Books that are not on the shelf stay private.
+    let resp = get(
+        test_router(state.clone()),
+        &format!("{web_path}/download/{}", other.id),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let (share_id,): (i64,) = sqlx::query_as("SELECT id FROM shelf_shares")
+        .fetch_one(pool.inner())
+        .await
+        .unwrap();
+    let resp = post_form(
+        test_router(state.clone()),
+        &format!("/web/admin/shares/shelf/{share_id}/revoke"),
+        &format!("csrf_token={csrf}"),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+
+    let resp = get(test_router(state.clone()), &web_path).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = get(test_router(state), &opds_path).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
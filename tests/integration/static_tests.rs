@@ -136,3 +136,70 @@ async fn serves_service_worker_with_scope_header() {
         "unexpected cache-control: {cache_control}"
     );
 }
+
+#[tokio::test]
+async fn fingerprinted_static_url_is_cached_immutably() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    let state = test_app_state(pool, config);
+    let app = test_router(state);
+
+    let url = ropds::assets::asset_url("js/ropds.js");
+    assert!(
+        url.starts_with("/static/js/ropds.js?h="),
+        "unexpected url: {url}"
+    );
+
+    let response = get(app.clone(), &url).await;
+    assert_eq!(response.status(), 200);
+    let cache_control = response
+        .headers()
+        .get("cache-control")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    assert!(
+        cache_control.contains("immutable"),
+        "unexpected cache-control: {cache_control}"
+    );
+
+    // A stale fingerprint still serves the current file, but without long caching.
+    let response = get(app, "/static/js/ropds.js?h=0000000000000000").await;
+    assert_eq!(response.status(), 200);
+    let cache_control = response
+        .headers()
+        .get("cache-control")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    assert_eq!(cache_control, "public, max-age=3600");
+}
+
+#[tokio::test]
+async fn favicon_serves_configured_icon_or_bundled_default() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    let app = test_router(test_app_state(pool.clone(), config.clone()));
+    let response = get(app, "/favicon.ico").await;
+    assert_eq!(response.status(), 200);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        body.as_ref(),
+        std::fs::read("static/images/favicon.ico").unwrap()
+    );
+
+    let icon = lib_dir.path().join("kids.png");
+    std::fs::write(&icon, b"\x89PNG\r\n\x1a\nicon").unwrap();
+    let mut config = config;
+    config.opds.icon_path = Some(icon);
+    let app = test_router(test_app_state(pool, config));
+    let response = get(app, "/favicon.ico").await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "image/png");
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body.as_ref(), b"\x89PNG\r\n\x1a\nicon");
+}
@@ -0,0 +1,111 @@
+use ropds::db;
+use tower::ServiceExt;
+
+use super::*;
+
+#[tokio::test]
+async fn theme_is_saved_for_signed_in_user() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    let user_id = create_test_user(&pool, "theme-user", "password123", false).await;
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+
+    let state = test_app_state(pool.clone(), config);
+    let app = test_router(state);
+
+    let resp = post_json(
+        app.clone(),
+        "/web/api/theme",
+        serde_json::json!({ "theme": "dark", "csrf_token": csrf }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    let user = ropds::db::queries::users::get_by_id(&pool, user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.theme, "dark");
+
+    // The saved theme is rendered without relying on the cookie.
+    let resp = get_with_session(app.clone(), "/web", &session).await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains(r#"data-bs-theme="dark" data-theme-mode="dark""#));
+
+    let resp = post_json(
+        app,
+        "/web/api/theme",
+        serde_json::json!({ "theme": "auto", "csrf_token": "bad-token" }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+}
+
+#[tokio::test]
+async fn theme_cookie_for_anonymous_visitor() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let config = test_config(lib_dir.path(), covers_dir.path());
+
+    let state = test_app_state(pool, config);
+    let app = test_router(state);
+
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri("/web/api/theme")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"theme":"auto"}"#))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let set_cookie = resp
+        .headers()
+        .get("set-cookie")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+    assert!(set_cookie.starts_with("theme=auto"), "{set_cookie}");
+
+    let req = axum::http::Request::builder()
+        .uri("/web")
+        .header("cookie", "theme=auto")
+        .body(Body::empty())
+        .unwrap();
+    let html = body_string(app.clone().oneshot(req).await.unwrap()).await;
+    assert!(html.contains(r#"data-theme-mode="auto""#));
+
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri("/web/api/theme")
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"theme":"sepia"}"#))
+        .unwrap();
+    let resp = app.oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 400);
+}
+
+#[tokio::test]
+async fn site_name_and_accent_color_brand_the_pages() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let mut config = test_config(lib_dir.path(), covers_dir.path());
+    config.web.site_name = Some("Kids Shelf".to_string());
+    config.web.accent_color = Some("#1a73e8".to_string());
+
+    let app = test_router(test_app_state(pool, config));
+    let resp = get(app, "/web/login").await;
+    assert_eq!(resp.status(), 200);
+    let html = body_string(resp).await;
+    assert!(html.contains("— Kids Shelf</title>"), "{html}");
+    assert!(html.contains(r#"<link rel="icon" href="/favicon.ico">"#));
+    assert!(html.contains("--ropds-accent: #1a73e8;"));
+    assert!(html.contains("--bs-primary-rgb: 26, 115, 232;"));
+}
@@ -4,7 +4,7 @@ use tower::ServiceExt;
 
 use ropds::db;
 use ropds::db::models::CatType;
-use ropds::db::queries::{authors, books, series};
+use ropds::db::queries::{authors, books, bookshelf, series, upload_quota, users};
 use ropds::scanner;
 
 use super::*;
@@ -96,6 +96,11 @@ async fn upload_file_and_publish() {
     // Step 3: Verify in DB
     let book = books::get_by_id(&pool, book_id).await.unwrap().unwrap();
     assert_eq!(book.title, "My Custom Title");
+    assert!(
+        book.preview_text
+            .contains("This is the first paragraph of the test book."),
+        "preview text should survive the upload state round trip"
+    );
 
     let book_authors = authors::get_for_book(&pool, book_id).await.unwrap();
     assert_eq!(book_authors.len(), 1);
@@ -120,6 +125,483 @@ async fn upload_file_and_publish() {
     );
 }
 
+/// Upload a file through `POST /web/upload/file` and return the response.
+async fn upload_one(app: axum::Router, session: &str, data: &[u8]) -> axum::response::Response {
+    let (content_type, body) =
+        build_multipart_body(&csrf_for_session(session), "test_book.fb2", data);
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri("/web/upload/file")
+        .header("content-type", &content_type)
+        .header("cookie", format!("session={session}"))
+        .body(Body::from(body))
+        .unwrap();
+    app.oneshot(req).await.unwrap()
+}
+
+/// Admins set monthly quotas; published books count against them until the
+/// counters are reset.
+#[tokio::test]
+async fn upload_quota_is_enforced_and_resettable() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = test_config_with_upload(lib_dir.path(), covers_dir.path(), upload_dir.path());
+
+    let admin_id = create_test_user(&pool, "admin", "password123", true).await;
+    let admin_session = session_cookie_value(admin_id);
+    let admin_csrf = csrf_for_session(&admin_session);
+    let user_id = create_test_user(&pool, "uploader", "password123", false).await;
+    users::update_allow_upload(&pool, user_id, 1).await.unwrap();
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let app = test_router(test_app_state(pool.clone(), config));
+
+    let resp = post_form(
+        app.clone(),
+        &format!("/web/admin/users/{user_id}/quota"),
+        &format!("upload_quota_files=1&upload_quota_mb=5&csrf_token={admin_csrf}"),
+        &admin_session,
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert!(
+        resp.headers()["location"]
+            .to_str()
+            .unwrap()
+            .contains("quota_updated")
+    );
+
+    let file_data = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    let resp = upload_one(app.clone(), &session, &file_data).await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let token = json["token"].as_str().unwrap().to_string();
+    let resp = post_json(
+        app.clone(),
+        "/web/upload/publish",
+        serde_json::json!({ "token": token, "csrf_token": csrf }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+
+    let quota = upload_quota::get(&pool, user_id).await.unwrap();
+    assert_eq!(quota.used_files, 1);
+    assert_eq!(quota.used_bytes, file_data.len() as i64);
+
+    let page = body_string(get_with_session(app.clone(), "/web/upload", &session).await).await;
+    assert!(page.contains("id=\"upload-quota\""));
+
+    // The monthly book allowance is used up.
+    let resp = upload_one(app.clone(), &session, &file_data).await;
+    assert_eq!(resp.status(), 403);
+    assert!(body_string(resp).await.contains("error_quota"));
+    let resp = post_json(
+        app.clone(),
+        "/web/upload/chunked",
+        serde_json::json!({ "filename": "b.fb2", "size": 100, "csrf_token": csrf }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+
+    let resp = post_form(
+        app.clone(),
+        &format!("/web/admin/users/{user_id}/quota/reset"),
+        &format!("csrf_token={admin_csrf}"),
+        &admin_session,
+    )
+    .await;
+    assert_eq!(resp.status(), 303);
+    assert_eq!(
+        upload_quota::get(&pool, user_id).await.unwrap().used_files,
+        0
+    );
+
+    let resp = upload_one(app, &session, &file_data).await;
+    assert_eq!(resp.status(), 200);
+}
+
+/// Below `server.min_free_space_mb` uploads are refused up front, and the
+/// readiness probe and the admin dashboard report the low volume.
+#[tokio::test]
+async fn low_disk_space_refuses_uploads_and_fails_readiness() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = test_config_with_upload(lib_dir.path(), covers_dir.path(), upload_dir.path());
+
+    let resp = get(
+        test_router(test_app_state(pool.clone(), config.clone())),
+        "/health/ready",
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["status"], "ready");
+    assert_eq!(json["disk"].as_array().unwrap().len(), 3);
+
+    let mut config = config;
+    config.server.min_free_space_mb = u64::MAX / (1024 * 1024);
+    let user_id = create_test_user(&pool, "uploader", "password123", true).await;
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let app = test_router(test_app_state(pool.clone(), config));
+
+    let resp = get(app.clone(), "/health/ready").await;
+    assert_eq!(resp.status(), 503);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["status"], "not_ready");
+    assert!(
+        json["disk"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|v| v["low"] == true)
+    );
+
+    let file_data = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    let resp = upload_one(app.clone(), &session, &file_data).await;
+    assert_eq!(resp.status(), 507);
+    assert!(body_string(resp).await.contains("error_disk_full"));
+    assert_eq!(std::fs::read_dir(upload_dir.path()).unwrap().count(), 0);
+
+    let resp = post_json(
+        app.clone(),
+        "/web/upload/chunked",
+        serde_json::json!({ "filename": "b.fb2", "size": 100, "csrf_token": csrf }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 507);
+
+    let page = body_string(get_with_session(app, "/web/admin", &session).await).await;
+    assert!(page.contains("id=\"disk-low-notice\""));
+}
+
+/// Send one chunk of a resumable upload.
+async fn put_chunk(
+    app: axum::Router,
+    session_id: &str,
+    offset: usize,
+    chunk: &[u8],
+    session: &str,
+    csrf: &str,
+) -> axum::response::Response {
+    let req = axum::http::Request::builder()
+        .method("PUT")
+        .uri(format!("/web/upload/chunked/{session_id}?offset={offset}"))
+        .header("content-type", "application/octet-stream")
+        .header("cookie", format!("session={session}"))
+        .header("x-csrf-token", csrf)
+        .body(Body::from(chunk.to_vec()))
+        .unwrap();
+    app.oneshot(req).await.unwrap()
+}
+
+/// A chunked upload survives a resent chunk, reports its progress, and is
+/// assembled into a regular upload that can be published.
+#[tokio::test]
+async fn chunked_upload_resumes_and_publishes() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = test_config_with_upload(lib_dir.path(), covers_dir.path(), upload_dir.path());
+
+    let user_id = create_test_user(&pool, "uploader", "password123", true).await;
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let app = test_router(test_app_state(pool.clone(), config));
+
+    let file_data = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    let resp = post_json(
+        app.clone(),
+        "/web/upload/chunked",
+        serde_json::json!({
+            "filename": "test_book.fb2",
+            "size": file_data.len(),
+            "csrf_token": csrf,
+        }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let id = json["session"].as_str().unwrap().to_string();
+    assert_eq!(json["received"], 0);
+
+    let (head, tail) = file_data.split_at(500);
+    let resp = put_chunk(app.clone(), &id, 0, head, &session, &csrf).await;
+    assert_eq!(resp.status(), 200);
+
+    // The response to the first chunk was "lost": resending it is refused
+    // with the offset to continue from.
+    let resp = put_chunk(app.clone(), &id, 0, head, &session, &csrf).await;
+    assert_eq!(resp.status(), 409);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["received"], 500);
+
+    let resp = get_with_session(app.clone(), &format!("/web/upload/chunked/{id}"), &session).await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["received"], 500);
+    assert_eq!(json["size"], file_data.len());
+
+    let finish = serde_json::json!({ "csrf_token": csrf });
+    let finish_uri = format!("/web/upload/chunked/{id}/finish");
+    let resp = post_json(app.clone(), &finish_uri, finish.clone(), &session).await;
+    assert_eq!(resp.status(), 409, "incomplete upload must not be finished");
+
+    let resp = put_chunk(app.clone(), &id, 500, tail, &session, &csrf).await;
+    assert_eq!(resp.status(), 200);
+    let resp = post_json(app.clone(), &finish_uri, finish, &session).await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["meta"]["title"], "Test Book Title");
+    assert_eq!(json["meta"]["size"], file_data.len());
+    let token = json["token"].as_str().unwrap();
+    assert!(
+        !upload_dir
+            .path()
+            .join(format!("chunked_{id}.part"))
+            .exists()
+    );
+    assert!(
+        !upload_dir
+            .path()
+            .join(format!("chunked_{id}.json"))
+            .exists()
+    );
+
+    let resp = post_json(
+        app,
+        "/web/upload/publish",
+        serde_json::json!({ "token": token, "csrf_token": csrf }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let book_id = json["book_id"].as_i64().unwrap();
+    let book = books::get_by_id(&pool, book_id).await.unwrap().unwrap();
+    assert_eq!(book.size, file_data.len() as i64);
+}
+
+/// Chunks are checked against the declared size and the session owner.
+#[tokio::test]
+async fn chunked_upload_rejects_overflow_and_foreign_sessions() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = test_config_with_upload(lib_dir.path(), covers_dir.path(), upload_dir.path());
+
+    let owner = session_cookie_value(create_test_user(&pool, "owner", "password123", true).await);
+    let other = session_cookie_value(create_test_user(&pool, "other", "password123", true).await);
+    let app = test_router(test_app_state(pool, config));
+
+    let resp = post_json(
+        app.clone(),
+        "/web/upload/chunked",
+        serde_json::json!({
+            "filename": "book.fb2",
+            "size": 10,
+            "csrf_token": csrf_for_session(&owner),
+        }),
+        &owner,
+    )
+    .await;
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let id = json["session"].as_str().unwrap().to_string();
+
+    let resp = put_chunk(
+        app.clone(),
+        &id,
+        0,
+        &[0; 11],
+        &owner,
+        &csrf_for_session(&owner),
+    )
+    .await;
+    assert_eq!(resp.status(), 400);
+    let resp = put_chunk(
+        app.clone(),
+        &id,
+        0,
+        &[0; 5],
+        &other,
+        &csrf_for_session(&other),
+    )
+    .await;
+    assert_eq!(resp.status(), 403);
+    let resp = put_chunk(app, &id, 0, &[0; 5], &owner, "bad-token").await;
+    assert_eq!(resp.status(), 403);
+}
+
+/// Build a multipart body with a csrf_token field and several file fields.
+fn build_multi_file_body(csrf_token: &str, files: &[(&str, &[u8])]) -> (String, Vec<u8>) {
+    let boundary = "----TestBoundary12345";
+    let mut body = Vec::new();
+    body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+    body.extend_from_slice(b"Content-Disposition: form-data; name=\"csrf_token\"\r\n\r\n");
+    body.extend_from_slice(csrf_token.as_bytes());
+    body.extend_from_slice(b"\r\n");
+    for (filename, data) in files {
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        body.extend_from_slice(
+            format!(
+                "Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+/// Upload several files in one request and publish them all at once.
+#[tokio::test]
+async fn upload_several_files_and_publish_all() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = test_config_with_upload(lib_dir.path(), covers_dir.path(), upload_dir.path());
+
+    let user_id = create_test_user(&pool, "uploader", "password123", true).await;
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let app = test_router(test_app_state(pool.clone(), config));
+
+    let fb2 = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    let epub = std::fs::read(test_data_dir().join("test_book.epub")).unwrap();
+    let (content_type, body) = build_multi_file_body(
+        &csrf,
+        &[
+            ("test_book.fb2", &fb2),
+            ("notes.xyz", b"not a book"),
+            ("test_book.epub", &epub),
+        ],
+    );
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri("/web/upload/files")
+        .header("content-type", &content_type)
+        .header("cookie", format!("session={session}"))
+        .body(Body::from(body))
+        .unwrap();
+    let resp = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let files = json["files"].as_array().unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(files[0]["filename"], "test_book.fb2");
+    assert_eq!(files[0]["success"], true);
+    assert_eq!(files[1]["success"], false);
+    assert_eq!(files[1]["error"], "error_unsupported");
+    assert_eq!(files[2]["meta"]["format"], "epub");
+    let tokens: Vec<&str> = [&files[0], &files[2]]
+        .iter()
+        .map(|f| f["token"].as_str().unwrap())
+        .collect();
+
+    let resp = post_json(
+        app,
+        "/web/upload/publish-all",
+        serde_json::json!({
+            "items": [{ "token": tokens[0] }, { "token": tokens[1], "title": "Renamed Epub" }],
+            "csrf_token": csrf,
+        }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    let ids: Vec<i64> = json["book_ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| id.as_i64().unwrap())
+        .collect();
+    assert_eq!(ids.len(), 2);
+    let epub_book = books::get_by_id(&pool, ids[1]).await.unwrap().unwrap();
+    assert_eq!(epub_book.title, "Renamed Epub");
+    assert_eq!(epub_book.path, "uploader");
+}
+
+/// When one item cannot be published, the books published before it in the
+/// same request are removed again.
+#[tokio::test]
+async fn publish_all_rolls_back_on_failure() {
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    let upload_dir = tempfile::tempdir().unwrap();
+    let config = test_config_with_upload(lib_dir.path(), covers_dir.path(), upload_dir.path());
+
+    let user_id = create_test_user(&pool, "uploader", "password123", true).await;
+    let session = session_cookie_value(user_id);
+    let csrf = csrf_for_session(&session);
+    let app = test_router(test_app_state(pool.clone(), config));
+
+    let mut tokens = Vec::new();
+    for name in ["test_book.fb2", "no_cover.fb2"] {
+        let data = std::fs::read(test_data_dir().join(name)).unwrap();
+        let (content_type, body) = build_multipart_body(&csrf, name, &data);
+        let req = axum::http::Request::builder()
+            .method("POST")
+            .uri("/web/upload/file")
+            .header("content-type", &content_type)
+            .header("cookie", format!("session={session}"))
+            .body(Body::from(body))
+            .unwrap();
+        let resp = app.clone().oneshot(req).await.unwrap();
+        let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+        tokens.push(json["token"].as_str().unwrap().to_string());
+    }
+
+    // The second file is already in the library.
+    let user_dir = lib_dir.path().join("uploader");
+    std::fs::create_dir_all(&user_dir).unwrap();
+    std::fs::write(user_dir.join("no_cover.fb2"), b"taken").unwrap();
+
+    let resp = post_json(
+        app,
+        "/web/upload/publish-all",
+        serde_json::json!({
+            "items": [{ "token": tokens[0] }, { "token": tokens[1] }],
+            "csrf_token": csrf,
+        }),
+        &session,
+    )
+    .await;
+    assert_eq!(resp.status(), 409);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["error"], "error_duplicate");
+    assert_eq!(json["index"], 1);
+
+    assert!(
+        books::find_by_path_and_filename(&pool, "uploader", "test_book.fb2")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(!user_dir.join("test_book.fb2").exists());
+    // Both uploads stay staged and can be published later.
+    assert!(
+        upload_dir
+            .path()
+            .join(format!("upload_{}.json", tokens[0]))
+            .exists()
+    );
+}
+
 /// Upload with metadata override: verify edited values are stored, not parsed ones.
 #[tokio::test]
 async fn upload_edit_metadata_on_publish() {
@@ -435,3 +917,222 @@ async fn upload_duplicate_filename_rejected() {
         "duplicate publish should fail: status={status}, json={json4}"
     );
 }
+
+/// Replacing a book's file keeps its id, bookshelf entry and edited title,
+/// swaps the file on disk and picks up the new format.
+#[tokio::test]
+async fn admin_replaces_book_file_keeping_id_and_edits() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    books::update_title(
+        &pool,
+        book.id,
+        "Edited Title",
+        "EDITED TITLE",
+        book.lang_code,
+    )
+    .await
+    .unwrap();
+
+    let admin_id = create_test_user(&pool, "replacer", "password123", true).await;
+    bookshelf::upsert(&pool, admin_id, book.id).await.unwrap();
+    let session = session_cookie_value(admin_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    let file_data = std::fs::read(test_data_dir().join("test_book.epub")).unwrap();
+    let (content_type, body) = build_multipart_body(&csrf, "better.epub", &file_data);
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri(format!("/web/admin/book/{}/replace", book.id))
+        .header("content-type", &content_type)
+        .header("cookie", format!("session={session}"))
+        .body(Body::from(body))
+        .unwrap();
+    let resp = test_router(state.clone()).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["ok"], true);
+    assert_eq!(json["format"], "epub");
+
+    let replaced = books::get_by_id(&pool, book.id).await.unwrap().unwrap();
+    assert_eq!(replaced.filename, "test_book.epub");
+    assert_eq!(replaced.format, "epub");
+    assert_eq!(replaced.size, file_data.len() as i64);
+    assert_eq!(replaced.title, "Edited Title");
+    assert!(!lib_dir.path().join("test_book.fb2").exists());
+    assert!(lib_dir.path().join("test_book.epub").exists());
+    assert!(!lib_dir.path().join(".test_book.fb2.bak").exists());
+    assert!(
+        bookshelf::is_on_shelf(&pool, admin_id, book.id)
+            .await
+            .unwrap()
+    );
+
+    // A rescan recognises the new file as the same book.
+    scanner::run_scan(&pool, &state.config).await.unwrap();
+    assert_eq!(books::count_recent_added(&pool, false).await.unwrap(), 1);
+    let rescanned = books::get_by_id(&pool, book.id).await.unwrap().unwrap();
+    assert!(rescanned.avail > 0);
+}
+
+/// A replacement the database refuses leaves the original file in place.
+#[tokio::test]
+async fn admin_replace_restores_file_when_update_fails() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let original = std::fs::read(lib_dir.path().join("test_book.fb2")).unwrap();
+    sqlx::query(
+        "CREATE TRIGGER refuse_book_update BEFORE UPDATE ON books \
+         BEGIN SELECT RAISE(ABORT, 'refused'); END",
+    )
+    .execute(pool.inner())
+    .await
+    .unwrap();
+
+    let admin_id = create_test_user(&pool, "replacer", "password123", true).await;
+    let session = session_cookie_value(admin_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    let file_data = std::fs::read(test_data_dir().join("test_book.epub")).unwrap();
+    let (content_type, body) = build_multipart_body(&csrf, "better.epub", &file_data);
+    let req = axum::http::Request::builder()
+        .method("POST")
+        .uri(format!("/web/admin/book/{}/replace", book.id))
+        .header("content-type", &content_type)
+        .header("cookie", format!("session={session}"))
+        .body(Body::from(body))
+        .unwrap();
+    let resp = test_router(state).oneshot(req).await.unwrap();
+    assert_eq!(resp.status(), 500);
+
+    assert_eq!(
+        std::fs::read(lib_dir.path().join("test_book.fb2")).unwrap(),
+        original
+    );
+    assert!(!lib_dir.path().join("test_book.epub").exists());
+    assert!(!lib_dir.path().join(".test_book.fb2.bak").exists());
+    let unchanged = books::get_by_id(&pool, book.id).await.unwrap().unwrap();
+    assert_eq!(unchanged.filename, "test_book.fb2");
+}
+
+/// Multipart body for the admin cover endpoint.
+fn build_cover_body(csrf_token: &str, source: &str, file_data: Option<&[u8]>) -> (String, Vec<u8>) {
+    let boundary = "----TestBoundaryCover";
+    let mut body = Vec::new();
+    for (name, value) in [("csrf_token", csrf_token), ("source", source)] {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    if let Some(data) = file_data {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; \
+                 filename=\"cover.png\"\r\nContent-Type: image/png\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+/// Admins set a book cover from an uploaded image or from the book file;
+/// junk uploads are refused and leave the cover alone.
+#[tokio::test]
+async fn admin_sets_book_cover_from_upload_or_file() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let lib_dir = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files(lib_dir.path(), &["test_book.fb2"]);
+    let config = test_config(lib_dir.path(), covers_dir.path());
+    scanner::run_scan(&pool, &config).await.unwrap();
+    let book = books::find_by_path_and_filename(&pool, "", "test_book.fb2")
+        .await
+        .unwrap()
+        .unwrap();
+    let admin_id = create_test_user(&pool, "coveradmin", "password123", true).await;
+    let session = session_cookie_value(admin_id);
+    let csrf = csrf_for_session(&session);
+    let state = test_app_state(pool.clone(), config);
+
+    let post_cover = |body: (String, Vec<u8>)| {
+        let req = axum::http::Request::builder()
+            .method("POST")
+            .uri(format!("/web/admin/book/{}/cover", book.id))
+            .header("content-type", body.0)
+            .header("cookie", format!("session={session}"))
+            .body(Body::from(body.1))
+            .unwrap();
+        test_router(state.clone()).oneshot(req)
+    };
+
+    let mut png = Vec::new();
+    image::RgbImage::from_pixel(40, 60, image::Rgb([200, 30, 30]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let resp = post_cover(build_cover_body(&csrf, "upload", Some(&png)))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["ok"], true);
+    let updated = books::get_by_id(&pool, book.id).await.unwrap().unwrap();
+    assert_eq!(updated.cover, 1);
+    assert_eq!(updated.cover_type, "image/jpeg");
+    let stored = scanner::cover_storage_path(covers_dir.path(), book.id, "jpg");
+    let uploaded = std::fs::read(&stored).unwrap();
+    let thumb = get(
+        test_router(state.clone()),
+        &format!("/opds/thumb/{}/", book.id),
+    )
+    .await;
+    assert_eq!(thumb.status(), 200);
+
+    let resp = post_cover(build_cover_body(&csrf, "upload", Some(b"not an image")))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
+    let json: serde_json::Value = serde_json::from_str(&body_string(resp).await).unwrap();
+    assert_eq!(json["error"], "error_cover_invalid");
+    assert_eq!(std::fs::read(&stored).unwrap(), uploaded);
+
+    let candidate = get_with_session(
+        test_router(state.clone()),
+        &format!("/web/admin/book/{}/cover/candidate", book.id),
+        &session,
+    )
+    .await;
+    assert_eq!(candidate.status(), 200);
+    let resp = post_cover(build_cover_body(&csrf, "book", None))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_ne!(std::fs::read(&stored).unwrap(), uploaded);
+}
@@ -0,0 +1,117 @@
+use axum::extract::{Request, State};
+use axum::http::{Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use http_body_util::BodyExt;
+use ropds::db;
+use ropds::db::queries::books;
+use ropds::scanner;
+
+use super::*;
+
+/// Minimal WebDAV server over `root`: PROPFIND (depth 1) and GET under `/dav/`.
+async fn dav_handler(State(root): State<PathBuf>, request: Request) -> Response {
+    let path = request.uri().path().to_string();
+    let Some(rel) = path.strip_prefix("/dav/") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let rel = urlencoding::decode(rel).unwrap().into_owned();
+    let local = root.join(rel.trim_end_matches('/'));
+
+    if request.method() == Method::GET {
+        return match std::fs::read(&local) {
+            Ok(data) => data.into_response(),
+            Err(_) => StatusCode::NOT_FOUND.into_response(),
+        };
+    }
+    if request.method().as_str() != "PROPFIND" || !local.is_dir() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let response_xml = |href: &str, meta: &std::fs::Metadata| {
+        let props = if meta.is_dir() {
+            "<D:resourcetype><D:collection/></D:resourcetype>".to_string()
+        } else {
+            format!(
+                "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>\
+                 <D:getlastmodified>Mon, 12 Jan 1998 09:25:56 GMT</D:getlastmodified>",
+                meta.len()
+            )
+        };
+        format!(
+            "<D:response><D:href>{href}</D:href><D:propstat><D:prop>{props}</D:prop></D:propstat></D:response>"
+        )
+    };
+    let mut body = String::from(r#"<?xml version="1.0"?><D:multistatus xmlns:D="DAV:">"#);
+    body.push_str(&response_xml(&path, &std::fs::metadata(&local).unwrap()));
+    for entry in std::fs::read_dir(&local).unwrap().flatten() {
+        let meta = entry.metadata().unwrap();
+        let name = urlencoding::encode(&entry.file_name().to_string_lossy()).into_owned();
+        let slash = if meta.is_dir() { "/" } else { "" };
+        let href = format!("{}/{name}{slash}", path.trim_end_matches('/'));
+        body.push_str(&response_xml(&href, &meta));
+    }
+    body.push_str("</D:multistatus>");
+    (StatusCode::MULTI_STATUS, body).into_response()
+}
+
+/// Serve `root` over WebDAV on a local port; returns the library URL.
+async fn serve_dav(root: &Path) -> String {
+    let app = axum::Router::new()
+        .fallback(dav_handler)
+        .with_state(root.to_path_buf());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("webdav://reader:secret@{addr}/dav/")
+}
+
+/// A WebDAV library is indexed without a local mount, unchanged books are
+/// not fetched again, and downloads are read from the share.
+#[tokio::test]
+async fn webdav_library_scan_and_download() {
+    let _lock = SCAN_MUTEX.lock().await;
+    let pool = db::create_test_pool().await;
+    let share = tempfile::tempdir().unwrap();
+    let covers_dir = tempfile::tempdir().unwrap();
+    copy_test_files_to_subdir(share.path(), "sci fi", &["test_book.fb2"]);
+    copy_test_files(share.path(), &["test_book.epub", "test_book.fb2.zip"]);
+
+    let url = serve_dav(share.path()).await;
+    let config = test_config(Path::new(&url), covers_dir.path());
+    assert_eq!(
+        ropds::vfs::display_root(&config.library.root_path),
+        url.replace("reader:secret@", "")
+    );
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 3);
+    assert_eq!(stats.errors, 0);
+
+    let fb2 = books::find_by_path_and_filename(&pool, "sci fi", "test_book.fb2")
+        .await
+        .unwrap()
+        .expect("book in a remote subfolder");
+    assert_eq!(fb2.title, "Test Book Title");
+    let zipped = books::find_by_path_and_filename(&pool, "test_book.fb2.zip", "test_book.fb2")
+        .await
+        .unwrap()
+        .expect("entry of a remote ZIP archive");
+
+    let stats = scanner::run_scan(&pool, &config).await.unwrap();
+    assert_eq!(stats.books_added, 0);
+    assert_eq!(stats.books_deleted, 0);
+    assert_eq!(stats.archives_skipped, 1);
+
+    let state = test_app_state(pool.clone(), config);
+    let expected = std::fs::read(test_data_dir().join("test_book.fb2")).unwrap();
+    for id in [fb2.id, zipped.id] {
+        let resp = get(
+            test_router(state.clone()),
+            &format!("/opds/download/{id}/0/"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), expected.as_slice());
+    }
+}